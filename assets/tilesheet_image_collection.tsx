@@ -0,0 +1,10 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="image_collection" tilewidth="448" tileheight="192" tilecount="2" columns="0">
+ <grid orientation="orthogonal" width="1" height="1"/>
+ <tile id="0">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tile>
+ <tile id="1" x="64" y="32" width="96">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tile>
+</tileset>
@@ -0,0 +1,6 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="tilesheet_render_options" tilewidth="32" tileheight="32" tilecount="84" columns="14" objectalignment="topright" tilerendersize="grid" fillmode="preserve-aspect-fit">
+ <grid orientation="isometric" width="64" height="32"/>
+ <transformations hflip="1" vflip="0" rotate="1" preferuntransformed="1"/>
+ <image source="tilesheet.png" width="448" height="192"/>
+</tileset>
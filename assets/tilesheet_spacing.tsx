@@ -0,0 +1,4 @@
+<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" tiledversion="1.10.2" name="spacing" tilewidth="30" tileheight="28" spacing="2" margin="1" tilecount="84" columns="14">
+ <image source="tilesheet.png" width="448" height="192"/>
+</tileset>
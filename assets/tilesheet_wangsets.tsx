@@ -17,9 +17,9 @@
    <wangtile tileid="70" wangid="0,0,0,0,0,1,1,1"/>
    <wangtile tileid="71" wangid="0,1,1,1,0,0,0,0"/>
   </wangset>
-  <wangset name="Wall" type="mixed" tile="-1">
+  <wangset name="Wall" class="Walls" type="mixed" tile="-1">
    <wangcolor name="Light" color="#00ff00" tile="-1" probability="1"/>
-   <wangcolor name="Dark" color="#006f00" tile="-1" probability="1"/>
+   <wangcolor name="Dark" class="Shadow" color="#006f00" tile="-1" probability="1"/>
    <wangtile tileid="4" wangid="2,2,2,2,2,2,2,2"/>
    <wangtile tileid="5" wangid="2,2,0,0,0,2,2,2"/>
    <wangtile tileid="6" wangid="2,2,0,0,0,0,0,2"/>
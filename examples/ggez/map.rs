@@ -4,12 +4,12 @@ use ggez::{
     graphics::{self, Canvas, DrawParam, InstanceArray},
     Context, GameResult,
 };
-use tiled::TileLayer;
+use tiled::{LayerId, TileLayer};
 
 pub struct MapHandler {
     map: tiled::Map,
     tileset_image_cache: HashMap<String, graphics::Image>,
-    batch_cache: Option<HashMap<u32, Vec<InstanceArray>>>,
+    batch_cache: Option<HashMap<LayerId, Vec<InstanceArray>>>,
     pub example_animate: bool,
 }
 
@@ -18,8 +18,8 @@ impl MapHandler {
         // Load the map's tileset images
         let mut tileset_image_cache = HashMap::new();
         for ts in map.tilesets().iter() {
-            if let Some(image) = &ts.image {
-                let img = graphics::Image::from_path(ctx, &image.source)?;
+            if let Some(path) = ts.image.as_ref().and_then(|image| image.source.as_path()) {
+                let img = graphics::Image::from_path(ctx, path)?;
 
                 tileset_image_cache.insert(ts.name.clone(), img);
             }
@@ -33,29 +33,9 @@ impl MapHandler {
         })
     }
 
-    pub fn width(&self) -> u32 {
-        self.map.width
-    }
-
-    pub fn height(&self) -> u32 {
-        self.map.height
-    }
-
-    pub fn tile_width(&self) -> u32 {
-        self.map.tile_width
-    }
-
-    pub fn tile_height(&self) -> u32 {
-        self.map.tile_height
-    }
-
     pub fn bounds(&self) -> graphics::Rect {
-        graphics::Rect::new(
-            0.0,
-            0.0,
-            (self.width() * self.tile_width()) as f32,
-            (self.height() * self.tile_height()) as f32,
-        )
+        let bounds = self.map.bounds();
+        graphics::Rect::new(bounds.x, bounds.y, bounds.width, bounds.height)
     }
 
     pub fn background_color(&self) -> Option<graphics::Color> {
@@ -89,7 +69,8 @@ impl MapHandler {
             self.batch_cache = Some(self.generate_map_render(ctx, parallax_pan));
         }
 
-        let layer_batches: &HashMap<u32, Vec<InstanceArray>> = self.batch_cache.as_ref().unwrap();
+        let layer_batches: &HashMap<LayerId, Vec<InstanceArray>> =
+            self.batch_cache.as_ref().unwrap();
 
         // Draw layers
 
@@ -120,8 +101,8 @@ impl MapHandler {
         &self,
         ctx: &Context,
         parallax_pan: (f32, f32),
-    ) -> HashMap<u32, Vec<InstanceArray>> {
-        let mut layer_batches: HashMap<u32, Vec<InstanceArray>> = HashMap::new();
+    ) -> HashMap<LayerId, Vec<InstanceArray>> {
+        let mut layer_batches: HashMap<LayerId, Vec<InstanceArray>> = HashMap::new();
 
         let tile_layers = self
             .map
@@ -237,7 +218,7 @@ impl MapHandler {
             tiled::ObjectShape::Polyline { points } => {
                 let points: Vec<_> = points
                     .iter()
-                    .map(|p| [p.0 + object.x, p.1 + object.y])
+                    .map(|p| [p.x + object.x, p.y + object.y])
                     .collect();
                 let shape = graphics::Mesh::new_polyline(
                     ctx,
@@ -250,7 +231,7 @@ impl MapHandler {
             tiled::ObjectShape::Polygon { points } => {
                 let points: Vec<_> = points
                     .iter()
-                    .map(|p| [p.0 + object.x, p.1 + object.y])
+                    .map(|p| [p.x + object.x, p.y + object.y])
                     .collect();
                 let shape = graphics::Mesh::new_polyline(
                     ctx,
@@ -260,7 +241,7 @@ impl MapHandler {
                 )?;
                 canvas.draw(&shape, draw_param);
             }
-            tiled::ObjectShape::Point(_, _) | tiled::ObjectShape::Text { .. } => {
+            tiled::ObjectShape::Point(_, _) | tiled::ObjectShape::Text(_) => {
                 // Left as an exercise for the reader
             }
         }
@@ -285,19 +266,17 @@ fn get_tile_rect(
     ts_img_width: u32,
     ts_img_height: u32,
 ) -> graphics::Rect {
-    let ts_x = id % tileset.columns;
-    let ts_y = id / tileset.columns;
-
-    let x = (tileset.margin + (tileset.tile_width + tileset.spacing) * ts_x) as f32;
-    let y = (tileset.margin + (tileset.tile_height + tileset.spacing) * ts_y) as f32;
+    let rect = tileset
+        .tile_rect(id)
+        .expect("tile should be part of the tileset");
 
     let ts_img_width = ts_img_width as f32;
     let ts_img_height = ts_img_height as f32;
 
     graphics::Rect {
-        x: x / ts_img_width,
-        y: y / ts_img_height,
-        w: tileset.tile_width as f32 / ts_img_width,
-        h: tileset.tile_height as f32 / ts_img_height,
+        x: rect.x as f32 / ts_img_width,
+        y: rect.y as f32 / ts_img_height,
+        w: rect.width as f32 / ts_img_width,
+        h: rect.height as f32 / ts_img_height,
     }
 }
@@ -33,9 +33,10 @@ fn main() {
             tiled::LayerType::Image(layer) => {
                 println!(
                     "Image layer with {}",
-                    match &layer.image {
-                        Some(img) =>
-                            format!("an image with source = {}", img.source.to_string_lossy()),
+                    match layer.image.as_ref().map(|img| img.source.as_path()) {
+                        Some(Some(path)) =>
+                            format!("an image with source = {}", path.to_string_lossy()),
+                        Some(None) => "an embedded image".to_owned(),
                         None => "no image".to_owned(),
                     }
                 )
@@ -1,10 +1,8 @@
 //! Structures related to tile animations.
 
-use xml::attribute::OwnedAttribute;
-
 use crate::{
-    error::{Error, Result},
-    util::{get_attrs, parse_tag, XmlEventResult},
+    error::{Error, Result, XmlPosition},
+    util::{get_attrs, parse_tag, OwnedAttribute, XmlParser},
 };
 
 /// A structure describing a [frame] of a [TMX tile animation].
@@ -12,6 +10,8 @@ use crate::{
 /// [frame]: https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-frame
 /// [TMX tile animation]: https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#animation
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 pub struct Frame {
     /// The local ID of a tile within the parent tileset.
     pub tile_id: u32,
@@ -20,9 +20,9 @@ pub struct Frame {
 }
 
 impl Frame {
-    pub(crate) fn new(attrs: Vec<OwnedAttribute>) -> Result<Frame> {
+    pub(crate) fn new(attrs: Vec<OwnedAttribute>, position: XmlPosition) -> Result<Frame> {
         let (tile_id, duration) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("frame", position) {
                 "tileid" => tile_id ?= v.parse::<u32>(),
                 "duration" => duration ?= v.parse::<u32>(),
             }
@@ -32,13 +32,120 @@ impl Frame {
     }
 }
 
-pub(crate) fn parse_animation(
-    parser: &mut impl Iterator<Item = XmlEventResult>,
-) -> Result<Vec<Frame>> {
+/// Tracks the playback of a tile animation over time.
+///
+/// ## Example
+/// ```
+/// use tiled::{AnimationState, Frame};
+///
+/// let frames = [
+///     Frame { tile_id: 1, duration: 100 },
+///     Frame { tile_id: 2, duration: 50 },
+/// ];
+///
+/// let mut state = AnimationState::new();
+/// assert_eq!(state.current_tile(&frames), Some(1));
+/// state.advance(120);
+/// assert_eq!(state.current_tile(&frames), Some(2));
+/// state.advance(40);
+/// // The animation loops by default.
+/// assert_eq!(state.current_tile(&frames), Some(1));
+///
+/// state.looping = false;
+/// state.advance(1000);
+/// assert_eq!(state.current_tile(&frames), Some(2));
+/// assert!(state.is_finished(&frames));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AnimationState {
+    elapsed: u64,
+    /// Whether the animation starts over after its last frame. If `false`, the last frame is
+    /// displayed indefinitely once reached. Defaults to `true`, like in Tiled.
+    pub looping: bool,
+}
+
+impl Default for AnimationState {
+    fn default() -> Self {
+        Self {
+            elapsed: 0,
+            looping: true,
+        }
+    }
+}
+
+impl AnimationState {
+    /// Creates a new looping animation state, positioned at the start of the animation.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The time elapsed since the start of the animation, in milliseconds.
+    #[inline]
+    pub fn elapsed(&self) -> u64 {
+        self.elapsed
+    }
+
+    /// Advances the animation by the given amount of milliseconds.
+    #[inline]
+    pub fn advance(&mut self, delta_ms: u64) {
+        self.elapsed = self.elapsed.saturating_add(delta_ms);
+    }
+
+    /// Moves the animation back to its start.
+    #[inline]
+    pub fn reset(&mut self) {
+        self.elapsed = 0;
+    }
+
+    /// Returns the frame currently displayed, or [`None`] if there are no frames.
+    pub fn current_frame<'a>(&self, frames: &'a [Frame]) -> Option<&'a Frame> {
+        frame_at(frames, self.elapsed, self.looping)
+    }
+
+    /// Returns the local ID of the tile currently displayed, or [`None`] if there are no frames.
+    pub fn current_tile(&self, frames: &[Frame]) -> Option<u32> {
+        self.current_frame(frames).map(|frame| frame.tile_id)
+    }
+
+    /// Whether a non-looping animation has reached its end. Looping animations never finish.
+    pub fn is_finished(&self, frames: &[Frame]) -> bool {
+        !self.looping && self.elapsed >= total_duration(frames)
+    }
+}
+
+fn total_duration(frames: &[Frame]) -> u64 {
+    frames.iter().map(|frame| frame.duration as u64).sum()
+}
+
+/// Returns the frame displayed once `elapsed` milliseconds have passed since the start of the
+/// animation.
+pub(crate) fn frame_at(frames: &[Frame], elapsed: u64, looping: bool) -> Option<&Frame> {
+    let total = total_duration(frames);
+    if total == 0 {
+        return frames.first();
+    }
+    let mut time = if looping {
+        elapsed % total
+    } else {
+        elapsed.min(total - 1)
+    };
+    frames.iter().find(|frame| {
+        let duration = frame.duration as u64;
+        if time < duration {
+            true
+        } else {
+            time -= duration;
+            false
+        }
+    })
+}
+
+pub(crate) fn parse_animation(parser: &mut impl XmlParser) -> Result<Vec<Frame>> {
     let mut animation = Vec::new();
     parse_tag!(parser, "animation", {
         "frame" => |attrs| {
-            animation.push(Frame::new(attrs)?);
+            animation.push(Frame::new(attrs, parser.event_position())?);
             Ok(())
         },
     });
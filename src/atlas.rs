@@ -0,0 +1,120 @@
+//! Packing of the tile images of a tileset into a single image.
+
+use std::collections::HashMap;
+
+use ::image::{imageops, RgbaImage};
+
+use crate::{Image, ImageRect, ResourceReader, Result, TileId, Tileset};
+
+/// A single image holding the images of all the tiles of a tileset, along with where each of them
+/// ended up; See [`Tileset::pack_atlas`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct TileAtlas {
+    /// The packed image.
+    pub image: RgbaImage,
+    rects: HashMap<TileId, ImageRect>,
+}
+
+impl TileAtlas {
+    /// Returns the region of the [atlas image](Self::image) holding the tile with the given ID, in
+    /// pixels, or [`None`] if the tile had no image.
+    #[inline]
+    pub fn tile_rect(&self, id: TileId) -> Option<ImageRect> {
+        self.rects.get(&id).copied()
+    }
+
+    /// Returns the region of the [atlas image](Self::image) holding the tile with the given ID as
+    /// texture coordinates ranging from 0 to 1, in `[left, top, right, bottom]` order. Returns
+    /// [`None`] if the tile had no image.
+    pub fn tile_uv(&self, id: TileId) -> Option<[f32; 4]> {
+        let rect = self.tile_rect(id)?;
+        let (width, height) = (self.image.width() as f32, self.image.height() as f32);
+        Some([
+            rect.x as f32 / width,
+            rect.y as f32 / height,
+            (rect.x + rect.width) as f32 / width,
+            (rect.y + rect.height) as f32 / height,
+        ])
+    }
+
+    /// Iterates through the IDs of the packed tiles along with their regions of the
+    /// [atlas image](Self::image), in no particular order.
+    #[inline]
+    pub fn tile_rects(&self) -> impl ExactSizeIterator<Item = (TileId, ImageRect)> + '_ {
+        self.rects.iter().map(|(id, rect)| (*id, *rect))
+    }
+}
+
+/// Packs the images of the tiles of the given tileset; See [`Tileset::pack_atlas`].
+pub(crate) fn pack_atlas(tileset: &Tileset, reader: &mut impl ResourceReader) -> Result<TileAtlas> {
+    // Tiles of the same tileset often share their image, which only needs to be decoded once.
+    let mut images: Vec<(&Image, RgbaImage)> = Vec::new();
+    let mut tiles = Vec::new();
+    for (id, tile) in tileset.tiles() {
+        let (image, rect) = match tile.image_source() {
+            Some(source) => source,
+            None => continue,
+        };
+        let index = match images.iter().position(|(loaded, _)| *loaded == image) {
+            Some(index) => index,
+            None => {
+                images.push((image, image.load_pixels(reader)?));
+                images.len() - 1
+            }
+        };
+        // Regions are clipped to the image they are in, in case they don't fit.
+        let pixels = &images[index].1;
+        let x = (rect.x.max(0) as u32).min(pixels.width());
+        let y = (rect.y.max(0) as u32).min(pixels.height());
+        let width = (rect.width.max(0) as u32).min(pixels.width() - x);
+        let height = (rect.height.max(0) as u32).min(pixels.height() - y);
+        if width > 0 && height > 0 {
+            tiles.push((id, index, x, y, width, height));
+        }
+    }
+
+    // Tiles are placed on shelves from tallest to shortest, in an image about as wide as it is
+    // tall.
+    tiles.sort_by_key(|&(id, _, _, _, _, height)| (std::cmp::Reverse(height), id));
+    let area: u64 = tiles
+        .iter()
+        .map(|&(_, _, _, _, width, height)| width as u64 * height as u64)
+        .sum();
+    let atlas_width = tiles
+        .iter()
+        .map(|&(_, _, _, _, width, _)| width)
+        .max()
+        .unwrap_or(0)
+        .max((area as f64).sqrt().ceil() as u32);
+
+    let mut placed = Vec::with_capacity(tiles.len());
+    let (mut shelf_x, mut shelf_y, mut shelf_height) = (0, 0, 0);
+    for (id, index, x, y, width, height) in tiles {
+        if shelf_x + width > atlas_width {
+            shelf_x = 0;
+            shelf_y += shelf_height;
+            shelf_height = 0;
+        }
+        placed.push((id, index, x, y, width, height, shelf_x, shelf_y));
+        shelf_x += width;
+        shelf_height = shelf_height.max(height);
+    }
+
+    let mut image = RgbaImage::new(atlas_width, shelf_y + shelf_height);
+    let mut rects = HashMap::with_capacity(placed.len());
+    for (id, index, x, y, width, height, atlas_x, atlas_y) in placed {
+        let tile = imageops::crop_imm(&images[index].1, x, y, width, height);
+        imageops::replace(&mut image, &*tile, atlas_x as i64, atlas_y as i64);
+        rects.insert(
+            id,
+            ImageRect {
+                x: atlas_x as i32,
+                y: atlas_y as i32,
+                width: width as i32,
+                height: height as i32,
+            },
+        );
+    }
+
+    Ok(TileAtlas { image, rects })
+}
@@ -0,0 +1,269 @@
+//! Command line tool for inspecting Tiled maps, installed with the `cli` feature.
+
+use std::{fmt::Write, process::ExitCode};
+
+use serde_json::{json, Value};
+use tiled::{Layer, LayerTile, LayerType, Loader, Map, ObjectLayer, TileLayer};
+
+const USAGE: &str = "\
+Usage: tiled-inspect <MAP> [COMMAND]
+
+Commands:
+  tree                      Print the tilesets and layer tree of the map (default)
+  validate                  Check the map for problems, failing if any are found
+  tilesets                  List the tilesets of the map and how many of their tiles are used
+  layer <PATH> [csv|json]   Print the tiles or objects of the layer at the given path, such as
+                            `group/tiles`, as CSV (default) or JSON";
+
+const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
+const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
+const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
+
+#[derive(Clone, Copy)]
+enum Format {
+    Csv,
+    Json,
+}
+
+enum Command {
+    Tree,
+    Validate,
+    Tilesets,
+    Layer { path: String, format: Format },
+}
+
+fn main() -> ExitCode {
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.iter().any(|arg| arg == "-h" || arg == "--help") {
+        println!("{}", USAGE);
+        return ExitCode::SUCCESS;
+    }
+    let (map_path, command) = match parse_args(&args) {
+        Some(parsed) => parsed,
+        None => {
+            eprintln!("{}", USAGE);
+            return ExitCode::from(2);
+        }
+    };
+
+    let map = match Loader::new().load_tmx_map(map_path) {
+        Ok(map) => map,
+        Err(err) => {
+            eprintln!("error: {}", err);
+            return ExitCode::FAILURE;
+        }
+    };
+    let result = match command {
+        Command::Tree => {
+            print!("{}", map.dump_tree());
+            Ok(())
+        }
+        Command::Validate => validate(&map),
+        Command::Tilesets => {
+            print!("{}", tilesets(&map));
+            Ok(())
+        }
+        Command::Layer { path, format } => {
+            layer(&map, &path, format).map(|text| print!("{}", text))
+        }
+    };
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(message) => {
+            eprintln!("error: {}", message);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn parse_args(args: &[String]) -> Option<(&str, Command)> {
+    let args: Vec<&str> = args.iter().map(String::as_str).collect();
+    let command = match args.get(1..)? {
+        [] | ["tree"] => Command::Tree,
+        ["validate"] => Command::Validate,
+        ["tilesets"] => Command::Tilesets,
+        ["layer", path] | ["layer", path, "csv"] => Command::Layer {
+            path: path.to_string(),
+            format: Format::Csv,
+        },
+        ["layer", path, "json"] => Command::Layer {
+            path: path.to_string(),
+            format: Format::Json,
+        },
+        _ => return None,
+    };
+    Some((args[0], command))
+}
+
+fn validate(map: &Map) -> Result<(), String> {
+    for warning in map.warnings() {
+        println!("warning: {}", warning);
+    }
+    let diagnostics = map.validate(None);
+    for diagnostic in &diagnostics {
+        println!("warning: {}", diagnostic);
+    }
+    match diagnostics.len() {
+        0 => {
+            println!("no problems found");
+            Ok(())
+        }
+        1 => Err("found 1 problem".to_owned()),
+        count => Err(format!("found {} problems", count)),
+    }
+}
+
+fn tilesets(map: &Map) -> String {
+    let used = map.used_tiles();
+    let mut text = String::new();
+    for (index, tileset) in map.tilesets().iter().enumerate() {
+        let _ = write!(
+            text,
+            "[{}] {:?}: {} of {} tiles used, first gid {}",
+            index,
+            tileset.name,
+            used[index].len(),
+            tileset.tilecount,
+            map.tileset_first_gids()[index].0
+        );
+        if let Some(path) = map.tileset_path(index) {
+            let _ = write!(text, ", file {}", path.display());
+        }
+        text.push('\n');
+    }
+    text
+}
+
+fn layer(map: &Map, path: &str, format: Format) -> Result<String, String> {
+    let layer = map
+        .layer_at_path(path)
+        .ok_or_else(|| format!("there is no layer at {:?}", path))?;
+    match layer.layer_type() {
+        LayerType::Tiles(tiles) => Ok(tile_layer(layer, tiles, format)),
+        LayerType::Objects(objects) => Ok(object_layer(layer, objects, format)),
+        LayerType::Image(_) | LayerType::Group(_) => {
+            Err(format!("{:?} is not a tile or object layer", layer.name))
+        }
+    }
+}
+
+fn tile_layer(layer: Layer, tiles: TileLayer, format: Format) -> String {
+    // Infinite layers are printed from the top left corner of the area their tiles cover.
+    let ((x, y), (width, height)) = match tiles {
+        TileLayer::Finite(finite) => ((0, 0), (finite.width() as i32, finite.height() as i32)),
+        TileLayer::Infinite(infinite) => {
+            let positions: Vec<(i32, i32)> =
+                infinite.tiles().map(|(position, _)| position).collect();
+            let min_x = positions.iter().map(|&(x, _)| x).min().unwrap_or(0);
+            let min_y = positions.iter().map(|&(_, y)| y).min().unwrap_or(0);
+            let max_x = positions.iter().map(|&(x, _)| x + 1).max().unwrap_or(0);
+            let max_y = positions.iter().map(|&(_, y)| y + 1).max().unwrap_or(0);
+            ((min_x, min_y), (max_x - min_x, max_y - min_y))
+        }
+    };
+    let rows: Vec<Vec<u32>> = (y..y + height)
+        .map(|tile_y| {
+            (x..x + width)
+                .map(|tile_x| tiles.get_tile(tile_x, tile_y).map_or(0, gid_bits))
+                .collect()
+        })
+        .collect();
+
+    match format {
+        Format::Csv => {
+            let mut text = String::new();
+            for row in rows {
+                let row: Vec<String> = row.iter().map(u32::to_string).collect();
+                text += &row.join(",");
+                text.push('\n');
+            }
+            text
+        }
+        Format::Json => {
+            let data: Vec<u32> = rows.into_iter().flatten().collect();
+            let value = json!({
+                "id": layer.id(),
+                "name": layer.name,
+                "x": x,
+                "y": y,
+                "width": width,
+                "height": height,
+                "data": data,
+            });
+            to_json(&value)
+        }
+    }
+}
+
+fn object_layer(layer: Layer, objects: ObjectLayer, format: Format) -> String {
+    match format {
+        Format::Csv => {
+            let mut text = "id,name,class,x,y,rotation,visible\n".to_owned();
+            for object in objects.objects() {
+                let _ = writeln!(
+                    text,
+                    "{},{},{},{},{},{},{}",
+                    object.id().0,
+                    csv_field(&object.name),
+                    csv_field(&object.user_type),
+                    object.x,
+                    object.y,
+                    object.rotation,
+                    object.visible
+                );
+            }
+            text
+        }
+        Format::Json => {
+            let objects: Vec<Value> = objects
+                .objects()
+                .map(|object| {
+                    json!({
+                        "id": object.id(),
+                        "name": object.name,
+                        "class": object.user_type,
+                        "x": object.x,
+                        "y": object.y,
+                        "rotation": object.rotation,
+                        "visible": object.visible,
+                    })
+                })
+                .collect();
+            let value = json!({
+                "id": layer.id(),
+                "name": layer.name,
+                "objects": objects,
+            });
+            to_json(&value)
+        }
+    }
+}
+
+/// Returns the GID of a tile along with its flipping bits, as Tiled stores it.
+fn gid_bits(tile: LayerTile) -> u32 {
+    let mut bits = tile.gid().0;
+    if tile.flip_h {
+        bits |= FLIPPED_HORIZONTALLY_FLAG;
+    }
+    if tile.flip_v {
+        bits |= FLIPPED_VERTICALLY_FLAG;
+    }
+    if tile.flip_d {
+        bits |= FLIPPED_DIAGONALLY_FLAG;
+    }
+    bits
+}
+
+/// Quotes a CSV field if it contains characters that would otherwise break the line apart.
+fn csv_field(text: &str) -> String {
+    if text.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", text.replace('"', "\"\""))
+    } else {
+        text.to_owned()
+    }
+}
+
+fn to_json(value: &Value) -> String {
+    // Serializing a `Value` can't fail.
+    serde_json::to_string_pretty(value).unwrap() + "\n"
+}
@@ -1,10 +1,19 @@
 use std::{
+    cell::RefCell,
     collections::HashMap,
+    mem::size_of,
     path::{Path, PathBuf},
-    sync::Arc,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard,
+    },
+    time::SystemTime,
 };
 
-use crate::{Template, Tileset};
+use crate::{
+    ChunkData, ImageSource, Layer, LayerType, LoaderOptions, Map, ObjectData, ObjectShape, Point,
+    Project, Properties, PropertyValue, ResourceReader, Template, TileData, TileLayer, Tileset,
+};
 
 /// A reference type that is used to refer to a resource. For the owned variant, see [`ResourcePathBuf`].
 pub type ResourcePath = Path;
@@ -21,7 +30,8 @@ pub trait ResourceCache {
     /// Obtains a tileset from the cache, if it exists.
     ///
     /// # Example
-    /// ```
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
     /// use std::fs::File;
     /// use tiled::{Tileset, Loader, ResourceCache};
     /// # use tiled::Result;
@@ -47,6 +57,52 @@ pub trait ResourceCache {
     fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>>;
     /// Insert a new template into the cache.
     fn insert_template(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Template>);
+    /// Removes a tileset from the cache, so that it is parsed again the next time it is needed.
+    ///
+    /// Used by [`Loader::reload_if_changed`](crate::Loader::reload_if_changed) to get rid of
+    /// outdated tilesets. The default implementation does nothing, in which case changed tilesets
+    /// won't be reloaded.
+    fn remove_tileset(&mut self, path: impl AsRef<ResourcePath>) {
+        let _ = path;
+    }
+    /// Removes a template from the cache, so that it is parsed again the next time it is needed.
+    ///
+    /// Used by [`Loader::reload_if_changed`](crate::Loader::reload_if_changed) to get rid of
+    /// outdated templates. The default implementation does nothing, in which case changed
+    /// templates won't be reloaded.
+    fn remove_template(&mut self, path: impl AsRef<ResourcePath>) {
+        let _ = path;
+    }
+    /// Obtains a map from the cache, if it exists.
+    ///
+    /// Used by [`Loader::load_tmx_map`](crate::Loader::load_tmx_map) to avoid parsing the same
+    /// map more than once. The default implementation returns [`None`], in which case maps are
+    /// always parsed.
+    fn get_map(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Map>> {
+        let _ = path;
+        None
+    }
+    /// Insert a new map into the cache.
+    ///
+    /// The default implementation does nothing.
+    fn insert_map(&mut self, path: impl AsRef<ResourcePath>, map: Arc<Map>) {
+        let _ = (path, map);
+    }
+    /// Removes a map from the cache, so that it is parsed again the next time it is loaded.
+    ///
+    /// The default implementation does nothing.
+    fn remove_map(&mut self, path: impl AsRef<ResourcePath>) {
+        let _ = path;
+    }
+    /// Removes every map from the cache, which is done by loaders that
+    /// [cache maps](crate::Loader::set_cache_maps) when their settings change.
+    ///
+    /// The default implementation does nothing.
+    fn clear_maps(&mut self) {}
+    /// Removes every resource from the cache.
+    ///
+    /// The default implementation does nothing.
+    fn clear(&mut self) {}
 }
 
 /// A cache that identifies resources by their path, storing them in a [`HashMap`].
@@ -56,6 +112,8 @@ pub struct DefaultResourceCache {
     pub tilesets: HashMap<ResourcePathBuf, Arc<Tileset>>,
     /// The templates cached until now.
     pub templates: HashMap<ResourcePathBuf, Arc<Template>>,
+    /// The maps cached until now.
+    pub maps: HashMap<ResourcePathBuf, Arc<Map>>,
 }
 
 impl DefaultResourceCache {
@@ -64,6 +122,7 @@ impl DefaultResourceCache {
         Self {
             tilesets: HashMap::new(),
             templates: HashMap::new(),
+            maps: HashMap::new(),
         }
     }
 }
@@ -84,4 +143,651 @@ impl ResourceCache for DefaultResourceCache {
     fn insert_template(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Template>) {
         self.templates.insert(path.as_ref().to_path_buf(), tileset);
     }
+
+    fn remove_tileset(&mut self, path: impl AsRef<ResourcePath>) {
+        self.tilesets.remove(path.as_ref());
+    }
+
+    fn remove_template(&mut self, path: impl AsRef<ResourcePath>) {
+        self.templates.remove(path.as_ref());
+    }
+
+    fn get_map(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Map>> {
+        self.maps.get(path.as_ref()).map(Clone::clone)
+    }
+
+    fn insert_map(&mut self, path: impl AsRef<ResourcePath>, map: Arc<Map>) {
+        self.maps.insert(path.as_ref().to_path_buf(), map);
+    }
+
+    fn remove_map(&mut self, path: impl AsRef<ResourcePath>) {
+        self.maps.remove(path.as_ref());
+    }
+
+    fn clear_maps(&mut self) {
+        self.maps.clear();
+    }
+
+    fn clear(&mut self) {
+        self.tilesets.clear();
+        self.templates.clear();
+        self.maps.clear();
+    }
+}
+
+/// A resource held by a [`LruResourceCache`].
+#[derive(Debug, Clone)]
+enum CachedResource {
+    Map(Arc<Map>),
+    Tileset(Arc<Tileset>),
+    Template(Arc<Template>),
+}
+
+impl CachedResource {
+    fn kind(&self) -> SourceKind {
+        match self {
+            CachedResource::Map(_) => SourceKind::Map,
+            CachedResource::Tileset(_) => SourceKind::Tileset,
+            CachedResource::Template(_) => SourceKind::Template,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LruEntry {
+    resource: CachedResource,
+    size: usize,
+    /// The value of the cache's clock when the entry was last inserted or requested.
+    last_used: AtomicU64,
+}
+
+/// A cache that holds a bounded amount of resources, evicting the least recently used ones once
+/// it holds more entries or bytes than its budget allows. Useful for long-running applications,
+/// such as editors, which load many different maps over time.
+///
+/// The amount of bytes used by a resource is an estimate of the memory it takes up, which
+/// doesn't account for the tilesets that maps and templates refer to. Resources that are larger
+/// than the whole budget are not kept.
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{FilesystemResourceReader, Loader, LruResourceCache, ResourceCache};
+///
+/// let cache = LruResourceCache::new(2, usize::MAX);
+/// let mut loader = Loader::with_cache_and_reader(cache, FilesystemResourceReader::new());
+/// loader.set_cache_maps(true);
+///
+/// // Both the map and its tileset are cached.
+/// loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+/// assert_eq!(loader.cache().len(), 2);
+/// assert!(loader.cache().used_bytes() > 0);
+///
+/// // Loading a map using another tileset evicts the previous ones.
+/// loader.load_tmx_map("assets/tiled_csv_wangsets.tmx")?;
+/// assert_eq!(loader.cache().len(), 2);
+/// assert!(loader.cache().get_tileset("assets/tilesheet.tsx").is_none());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug)]
+pub struct LruResourceCache {
+    entries: HashMap<(SourceKind, ResourcePathBuf), LruEntry>,
+    max_entries: usize,
+    max_bytes: usize,
+    used_bytes: usize,
+    clock: AtomicU64,
+}
+
+impl LruResourceCache {
+    /// Creates an empty cache that holds at most `max_entries` resources, using at most
+    /// `max_bytes` bytes. Use [`usize::MAX`] to leave either of them unbounded.
+    pub fn new(max_entries: usize, max_bytes: usize) -> Self {
+        Self {
+            entries: HashMap::new(),
+            max_entries,
+            max_bytes,
+            used_bytes: 0,
+            clock: AtomicU64::new(0),
+        }
+    }
+
+    /// Returns the maximum amount of resources this cache holds.
+    pub fn max_entries(&self) -> usize {
+        self.max_entries
+    }
+
+    /// Sets the maximum amount of resources this cache holds, evicting resources right away if
+    /// it holds more.
+    pub fn set_max_entries(&mut self, max_entries: usize) {
+        self.max_entries = max_entries;
+        self.evict();
+    }
+
+    /// Returns the maximum amount of bytes this cache uses.
+    pub fn max_bytes(&self) -> usize {
+        self.max_bytes
+    }
+
+    /// Sets the maximum amount of bytes this cache uses, evicting resources right away if it uses
+    /// more.
+    pub fn set_max_bytes(&mut self, max_bytes: usize) {
+        self.max_bytes = max_bytes;
+        self.evict();
+    }
+
+    /// Returns the amount of resources in the cache.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Returns whether the cache holds no resources.
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Returns the estimated amount of bytes used by the resources in the cache.
+    pub fn used_bytes(&self) -> usize {
+        self.used_bytes
+    }
+
+    fn get(&self, kind: SourceKind, path: &ResourcePath) -> Option<&CachedResource> {
+        let entry = self.entries.get(&(kind, path.to_owned()))?;
+        let now = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+        entry.last_used.store(now, Ordering::Relaxed);
+        Some(&entry.resource)
+    }
+
+    fn insert(&mut self, path: &ResourcePath, resource: CachedResource, size: usize) {
+        let now = self.clock.fetch_add(1, Ordering::Relaxed) + 1;
+        let key = (resource.kind(), path.to_owned());
+        let entry = LruEntry {
+            resource,
+            size,
+            last_used: AtomicU64::new(now),
+        };
+        if let Some(old) = self.entries.insert(key, entry) {
+            self.used_bytes -= old.size;
+        }
+        self.used_bytes += size;
+        self.evict();
+    }
+
+    fn remove(&mut self, kind: SourceKind, path: &ResourcePath) {
+        if let Some(old) = self.entries.remove(&(kind, path.to_owned())) {
+            self.used_bytes -= old.size;
+        }
+    }
+
+    /// Removes the least recently used resources until the cache is within its budget.
+    fn evict(&mut self) {
+        while self.len() > self.max_entries || self.used_bytes > self.max_bytes {
+            let oldest = self
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used.load(Ordering::Relaxed))
+                .map(|(key, _)| key.clone());
+            match oldest {
+                Some((kind, path)) => self.remove(kind, &path),
+                None => return,
+            }
+        }
+    }
+}
+
+impl ResourceCache for LruResourceCache {
+    fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
+        match self.get(SourceKind::Tileset, path.as_ref())? {
+            CachedResource::Tileset(tileset) => Some(tileset.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
+        let size = tileset_size(&tileset);
+        self.insert(path.as_ref(), CachedResource::Tileset(tileset), size);
+    }
+
+    fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
+        match self.get(SourceKind::Template, path.as_ref())? {
+            CachedResource::Template(template) => Some(template.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert_template(&mut self, path: impl AsRef<ResourcePath>, template: Arc<Template>) {
+        let size = size_of::<Template>() + object_size(&template.object);
+        self.insert(path.as_ref(), CachedResource::Template(template), size);
+    }
+
+    fn remove_tileset(&mut self, path: impl AsRef<ResourcePath>) {
+        self.remove(SourceKind::Tileset, path.as_ref());
+    }
+
+    fn remove_template(&mut self, path: impl AsRef<ResourcePath>) {
+        self.remove(SourceKind::Template, path.as_ref());
+    }
+
+    fn get_map(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Map>> {
+        match self.get(SourceKind::Map, path.as_ref())? {
+            CachedResource::Map(map) => Some(map.clone()),
+            _ => None,
+        }
+    }
+
+    fn insert_map(&mut self, path: impl AsRef<ResourcePath>, map: Arc<Map>) {
+        let size = map_size(&map);
+        self.insert(path.as_ref(), CachedResource::Map(map), size);
+    }
+
+    fn remove_map(&mut self, path: impl AsRef<ResourcePath>) {
+        self.remove(SourceKind::Map, path.as_ref());
+    }
+
+    fn clear_maps(&mut self) {
+        let mut freed = 0;
+        self.entries.retain(|(kind, _), entry| {
+            let keep = *kind != SourceKind::Map;
+            if !keep {
+                freed += entry.size;
+            }
+            keep
+        });
+        self.used_bytes -= freed;
+    }
+
+    fn clear(&mut self) {
+        self.entries.clear();
+        self.used_bytes = 0;
+    }
+}
+
+/// A cache that can be shared between several [`Loader`](crate::Loader)s, even across threads,
+/// so that they all reuse the same tilesets, templates and maps. It wraps another cache,
+/// [`DefaultResourceCache`] by default, behind an [`Arc`] and a [`RwLock`].
+///
+/// Cloning it returns a handle to the same cache.
+///
+/// Loaders sharing a cache should use the same settings and project. Tilesets and templates are
+/// reused by every loader regardless of the settings they were loaded with, while
+/// [cached maps](crate::Loader::set_cache_maps) are only reused with the same settings, and
+/// changing the settings of a loader that caches maps removes all maps from the cache, including
+/// the ones cached by other loaders.
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// use std::sync::Arc;
+/// use std::thread;
+///
+/// use tiled::{FilesystemResourceReader, Loader, ResourceCache, SharedResourceCache};
+///
+/// let cache = SharedResourceCache::new();
+/// let workers: Vec<_> = ["assets/tiled_base64_external.tmx", "assets/tiled_hexagonal.tmx"]
+///     .into_iter()
+///     .map(|path| {
+///         let cache = cache.clone();
+///         thread::spawn(move || {
+///             let mut loader =
+///                 Loader::with_cache_and_reader(cache, FilesystemResourceReader::new());
+///             loader.load_tmx_map(path).unwrap()
+///         })
+///     })
+///     .collect();
+/// let maps: Vec<_> = workers.into_iter().map(|worker| worker.join().unwrap()).collect();
+///
+/// // Both maps use the same tileset, which is now in the shared cache.
+/// let tileset = cache.get_tileset("assets/tilesheet.tsx").unwrap();
+/// assert!(maps.iter().all(|map| map.tilesets()[0].name == tileset.name));
+/// ```
+#[derive(Debug)]
+pub struct SharedResourceCache<C: ResourceCache = DefaultResourceCache> {
+    cache: Arc<RwLock<C>>,
+}
+
+impl SharedResourceCache {
+    /// Creates an empty shared [`DefaultResourceCache`].
+    pub fn new() -> Self {
+        Self::from_cache(DefaultResourceCache::new())
+    }
+}
+
+impl Default for SharedResourceCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<C: ResourceCache> SharedResourceCache<C> {
+    /// Wraps the given cache so that it can be shared.
+    pub fn from_cache(cache: C) -> Self {
+        Self {
+            cache: Arc::new(RwLock::new(cache)),
+        }
+    }
+
+    /// Locks the wrapped cache for reading, blocking the current thread until it can.
+    pub fn read(&self) -> RwLockReadGuard<'_, C> {
+        // The caches in this crate are left in a valid state if a thread panics while using them.
+        self.cache.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Locks the wrapped cache for writing, blocking the current thread until it can.
+    pub fn write(&self) -> RwLockWriteGuard<'_, C> {
+        self.cache.write().unwrap_or_else(PoisonError::into_inner)
+    }
+}
+
+impl<C: ResourceCache> Clone for SharedResourceCache<C> {
+    fn clone(&self) -> Self {
+        Self {
+            cache: self.cache.clone(),
+        }
+    }
+}
+
+impl<C: ResourceCache> ResourceCache for SharedResourceCache<C> {
+    fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
+        self.read().get_tileset(path)
+    }
+
+    fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
+        self.write().insert_tileset(path, tileset)
+    }
+
+    fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
+        self.read().get_template(path)
+    }
+
+    fn insert_template(&mut self, path: impl AsRef<ResourcePath>, template: Arc<Template>) {
+        self.write().insert_template(path, template)
+    }
+
+    fn remove_tileset(&mut self, path: impl AsRef<ResourcePath>) {
+        self.write().remove_tileset(path)
+    }
+
+    fn remove_template(&mut self, path: impl AsRef<ResourcePath>) {
+        self.write().remove_template(path)
+    }
+
+    fn get_map(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Map>> {
+        self.read().get_map(path)
+    }
+
+    fn insert_map(&mut self, path: impl AsRef<ResourcePath>, map: Arc<Map>) {
+        self.write().insert_map(path, map)
+    }
+
+    fn remove_map(&mut self, path: impl AsRef<ResourcePath>) {
+        self.write().remove_map(path)
+    }
+
+    fn clear_maps(&mut self) {
+        self.write().clear_maps()
+    }
+
+    fn clear(&mut self) {
+        self.write().clear()
+    }
+}
+
+/// Estimates the memory used by a map, in bytes.
+fn map_size(map: &Map) -> usize {
+    size_of::<Map>()
+        + std::mem::size_of_val(map.tilesets())
+        + properties_size(&map.properties)
+        + map.layers().map(layer_size).sum::<usize>()
+}
+
+fn layer_size(layer: Layer) -> usize {
+    let contents = match layer.layer_type() {
+        LayerType::Tiles(TileLayer::Finite(layer)) => {
+            layer.width() as usize * layer.height() as usize * size_of::<u32>()
+        }
+        LayerType::Tiles(TileLayer::Infinite(layer)) => {
+            layer.chunks().len()
+                * (ChunkData::WIDTH * ChunkData::HEIGHT) as usize
+                * size_of::<u32>()
+        }
+        LayerType::Objects(layer) => layer.object_data().iter().map(object_size).sum(),
+        LayerType::Image(layer) => layer
+            .image
+            .as_ref()
+            .map_or(0, |image| image_size(&image.source)),
+        LayerType::Group(layer) => layer.layers().map(layer_size).sum(),
+    };
+    size_of::<crate::LayerData>() + layer.name.len() + properties_size(&layer.properties) + contents
+}
+
+/// Estimates the memory used by a tileset, in bytes.
+fn tileset_size(tileset: &Tileset) -> usize {
+    size_of::<Tileset>()
+        + tileset.name.len()
+        + tileset.user_type.as_ref().map_or(0, String::len)
+        + tileset
+            .image
+            .as_ref()
+            .map_or(0, |image| image_size(&image.source))
+        + properties_size(&tileset.properties)
+        + tileset.wang_sets.len() * size_of::<crate::WangSet>()
+        + tileset
+            .tiles()
+            .map(|(_, tile)| tile_size(&tile))
+            .sum::<usize>()
+}
+
+fn tile_size(tile: &TileData) -> usize {
+    size_of::<(u32, TileData)>()
+        + tile
+            .image
+            .as_ref()
+            .map_or(0, |image| image_size(&image.source))
+        + properties_size(&tile.properties)
+        + tile.collision.as_ref().map_or(0, |collision| {
+            collision.object_data().iter().map(object_size).sum()
+        })
+        + tile
+            .animation
+            .as_ref()
+            .map_or(0, |frames| frames.len() * size_of::<crate::Frame>())
+        + tile.user_type.as_ref().map_or(0, String::len)
+}
+
+fn image_size(source: &ImageSource) -> usize {
+    match source {
+        ImageSource::Path(path) => path.as_os_str().len(),
+        ImageSource::Embedded { format, data } => format.len() + data.len(),
+    }
+}
+
+fn object_size(object: &ObjectData) -> usize {
+    let shape = match &object.shape {
+        ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+            points.len() * size_of::<Point>()
+        }
+        ObjectShape::Text(text) => text.text.len() + text.font_family.len(),
+        _ => 0,
+    };
+    size_of::<ObjectData>()
+        + object.name.len()
+        + object.user_type.len()
+        + shape
+        + properties_size(&object.properties)
+}
+
+fn properties_size(properties: &Properties) -> usize {
+    properties
+        .iter()
+        .map(|(name, value)| {
+            let value = match value {
+                PropertyValue::StringValue(value) => value.len(),
+                PropertyValue::FileValue { relative, resolved } => {
+                    relative.len() + resolved.as_os_str().len()
+                }
+                PropertyValue::ClassValue {
+                    property_type,
+                    properties,
+                } => property_type.len() + properties_size(properties),
+                PropertyValue::EnumValue {
+                    property_type,
+                    values,
+                    ..
+                } => property_type.len() + values.iter().map(String::len).sum::<usize>(),
+                _ => 0,
+            };
+            size_of::<(String, PropertyValue)>() + name.len() + value
+        })
+        .sum()
+}
+
+/// What a file that a map was loaded from contains.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub(crate) enum SourceKind {
+    Map,
+    Tileset,
+    Template,
+}
+
+/// A file that a map was loaded from, along with when it was last modified at the time, which is
+/// used for detecting changes.
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct SourceFile {
+    pub kind: SourceKind,
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+}
+
+/// The files a map was loaded from, the map file itself being first. Since they don't affect the
+/// contents of the map, they are ignored when comparing maps.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MapSources(pub Vec<SourceFile>);
+
+impl PartialEq for MapSources {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+impl SourceFile {
+    pub(crate) fn new(kind: SourceKind, path: PathBuf, reader: &mut impl ResourceReader) -> Self {
+        let modified = reader.modified(&path);
+        Self {
+            kind,
+            path,
+            modified,
+        }
+    }
+
+    /// Returns whether the file has been modified since it was loaded. Files whose modification
+    /// time isn't known are never considered modified.
+    pub(crate) fn has_changed(&self, reader: &mut impl ResourceReader) -> bool {
+        match reader.modified(&self.path) {
+            Some(modified) => self.modified != Some(modified),
+            None => false,
+        }
+    }
+}
+
+/// A cache that wraps another one, keeping track of every tileset and template requested from it
+/// so that the files a map depends on are known.
+pub(crate) struct RecordingCache<'a, C: ResourceCache> {
+    cache: &'a mut C,
+    requested: RefCell<Vec<(SourceKind, PathBuf)>>,
+}
+
+impl<'a, C: ResourceCache> RecordingCache<'a, C> {
+    pub(crate) fn new(cache: &'a mut C) -> Self {
+        Self {
+            cache,
+            requested: RefCell::new(Vec::new()),
+        }
+    }
+
+    /// Returns the paths requested, each only once and in the order they were first requested.
+    pub(crate) fn into_requested(self) -> Vec<(SourceKind, PathBuf)> {
+        self.requested.into_inner()
+    }
+
+    fn record(&self, kind: SourceKind, path: &Path) {
+        let mut requested = self.requested.borrow_mut();
+        if !requested.iter().any(|(k, p)| *k == kind && p == path) {
+            requested.push((kind, path.to_owned()));
+        }
+    }
+}
+
+impl<C: ResourceCache> ResourceCache for RecordingCache<'_, C> {
+    fn get_tileset(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Tileset>> {
+        self.record(SourceKind::Tileset, path.as_ref());
+        self.cache.get_tileset(path)
+    }
+
+    fn insert_tileset(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Tileset>) {
+        self.record(SourceKind::Tileset, path.as_ref());
+        self.cache.insert_tileset(path, tileset)
+    }
+
+    fn get_template(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Template>> {
+        self.record(SourceKind::Template, path.as_ref());
+        self.cache.get_template(path)
+    }
+
+    fn insert_template(&mut self, path: impl AsRef<ResourcePath>, tileset: Arc<Template>) {
+        self.record(SourceKind::Template, path.as_ref());
+        self.cache.insert_template(path, tileset)
+    }
+
+    fn remove_tileset(&mut self, path: impl AsRef<ResourcePath>) {
+        self.cache.remove_tileset(path)
+    }
+
+    fn remove_template(&mut self, path: impl AsRef<ResourcePath>) {
+        self.cache.remove_template(path)
+    }
+
+    fn get_map(&self, path: impl AsRef<ResourcePath>) -> Option<Arc<Map>> {
+        self.cache.get_map(path)
+    }
+
+    fn insert_map(&mut self, path: impl AsRef<ResourcePath>, map: Arc<Map>) {
+        self.cache.insert_map(path, map)
+    }
+
+    fn remove_map(&mut self, path: impl AsRef<ResourcePath>) {
+        self.cache.remove_map(path)
+    }
+
+    fn clear_maps(&mut self) {
+        self.cache.clear_maps()
+    }
+
+    fn clear(&mut self) {
+        self.cache.clear()
+    }
+}
+
+/// The settings a map was loaded with, if it was stored in the cache, which it is only reused
+/// with. Since they don't affect the contents of the map, they are ignored when comparing maps.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct MapSettings(Option<Arc<(LoaderOptions, Option<Project>)>>);
+
+impl MapSettings {
+    pub(crate) fn new(options: &LoaderOptions, project: Option<&Project>) -> Self {
+        Self(Some(Arc::new((options.clone(), project.cloned()))))
+    }
+
+    /// Returns whether these are the given settings.
+    pub(crate) fn matches(&self, options: &LoaderOptions, project: Option<&Project>) -> bool {
+        self.0.as_deref().is_some_and(|(own_options, own_project)| {
+            own_options == options && own_project.as_ref() == project
+        })
+    }
+}
+
+impl PartialEq for MapSettings {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
 }
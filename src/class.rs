@@ -0,0 +1,93 @@
+use crate::{
+    Layer, LayerData, Map, Object, ObjectData, Tile, TileData, Tileset, WangColor, WangSet,
+};
+
+/// Elements that can be given a class by the user in Tiled, which is arbitrary and used to tell
+/// apart different kinds of elements, such as enemies and items.
+///
+/// Tiled 1.9 renamed the `type` attribute to `class` and added it to more elements; Both are
+/// read, so elements saved by any version have their class available through this trait.
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{HasUserClass, Loader};
+///
+/// let map = Loader::new().load_tmx_map("assets/tiled_object_groups.tmx")?;
+/// for layer in map.layers() {
+///     if let Some(objects) = layer.as_object_layer() {
+///         for object in objects.objects() {
+///             println!("{}: {:?}", object.name, object.user_class());
+///         }
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub trait HasUserClass {
+    /// Returns the class of this element, or [`None`] if it doesn't have one.
+    fn user_class(&self) -> Option<&str>;
+}
+
+impl HasUserClass for Map {
+    fn user_class(&self) -> Option<&str> {
+        self.user_type.as_deref()
+    }
+}
+
+impl HasUserClass for LayerData {
+    fn user_class(&self) -> Option<&str> {
+        self.user_type.as_deref()
+    }
+}
+
+impl HasUserClass for Layer<'_> {
+    fn user_class(&self) -> Option<&str> {
+        (**self).user_class()
+    }
+}
+
+impl HasUserClass for Tileset {
+    fn user_class(&self) -> Option<&str> {
+        self.user_type.as_deref()
+    }
+}
+
+impl HasUserClass for TileData {
+    fn user_class(&self) -> Option<&str> {
+        self.user_type.as_deref()
+    }
+}
+
+impl HasUserClass for Tile<'_> {
+    fn user_class(&self) -> Option<&str> {
+        (**self).user_class()
+    }
+}
+
+impl HasUserClass for ObjectData {
+    /// Objects store an empty [`ObjectData::user_type`] when they have no class, for which this
+    /// returns [`None`].
+    fn user_class(&self) -> Option<&str> {
+        Some(self.user_type.as_str()).filter(|user_type| !user_type.is_empty())
+    }
+}
+
+impl HasUserClass for Object<'_> {
+    fn user_class(&self) -> Option<&str> {
+        (**self).user_class()
+    }
+}
+
+impl HasUserClass for WangSet {
+    fn user_class(&self) -> Option<&str> {
+        self.user_type.as_deref()
+    }
+}
+
+impl HasUserClass for WangColor {
+    fn user_class(&self) -> Option<&str> {
+        self.user_type.as_deref()
+    }
+}
@@ -0,0 +1,309 @@
+//! Flattening of the layers of a map into a grid of solid tiles.
+
+use std::convert::TryFrom;
+
+use crate::{LayerTile, LayerType, Map, Object, ObjectShape, Orientation, TileLayer};
+
+/// A rectangular area of a map holding a value for each tile in it, such as the one returned by
+/// [`Map::build_collision_grid`].
+///
+/// Cells are addressed by the same tile positions as the map's layers; The grid starts at
+/// [`Self::origin`], which is only different from `(0, 0)` for infinite maps.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grid<T> {
+    origin: (i32, i32),
+    width: u32,
+    height: u32,
+    cells: Vec<T>,
+}
+
+impl<T> Grid<T> {
+    /// Returns the tile position of the top left cell of the grid.
+    #[inline]
+    pub fn origin(&self) -> (i32, i32) {
+        self.origin
+    }
+
+    /// Returns the width of the grid, in tiles.
+    #[inline]
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    /// Returns the height of the grid, in tiles.
+    #[inline]
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// Returns the value of the cell at the given tile position, or [`None`] if it is outside of
+    /// the grid.
+    pub fn get(&self, x: i32, y: i32) -> Option<&T> {
+        self.index(x, y).map(|index| &self.cells[index])
+    }
+
+    /// Returns the values of all cells, row by row, starting from the one at [`Self::origin`].
+    #[inline]
+    pub fn cells(&self) -> &[T] {
+        &self.cells
+    }
+
+    fn index(&self, x: i32, y: i32) -> Option<usize> {
+        let x = u32::try_from(x as i64 - self.origin.0 as i64).ok()?;
+        let y = u32::try_from(y as i64 - self.origin.1 as i64).ok()?;
+        if x < self.width && y < self.height {
+            Some(y as usize * self.width as usize + x as usize)
+        } else {
+            None
+        }
+    }
+}
+
+pub(crate) fn build_collision_grid(
+    map: &Map,
+    tile_predicate: impl Fn(LayerTile) -> bool,
+    object_predicate: impl Fn(Object) -> bool,
+) -> Grid<bool> {
+    let extent = grid_extent(map);
+    let mut solid = Vec::new();
+    for layer in map.flattened_layers() {
+        let (offset_x, offset_y) = (layer.offset_x, layer.offset_y);
+        match layer.layer.layer_type() {
+            LayerType::Tiles(tiles) => {
+                let tiles: Box<dyn Iterator<Item = ((i32, i32), LayerTile)>> = match tiles {
+                    TileLayer::Finite(tiles) => Box::new(tiles.tiles()),
+                    TileLayer::Infinite(tiles) => Box::new(tiles.tiles()),
+                };
+                solid.extend(
+                    tiles
+                        .filter(|(_, tile)| tile_predicate(*tile))
+                        .map(|((x, y), _)| offset_tile(map, x, y, offset_x, offset_y)),
+                );
+            }
+            LayerType::Objects(objects) => {
+                for object in objects.objects() {
+                    let has_area = matches!(
+                        object.shape,
+                        ObjectShape::Rect { .. }
+                            | ObjectShape::Ellipse { .. }
+                            | ObjectShape::Polygon { .. }
+                    );
+                    if has_area && object_predicate(object) {
+                        if let Some(extent) = extent {
+                            rasterize_object(map, object, offset_x, offset_y, extent, &mut solid);
+                        }
+                    }
+                }
+            }
+            LayerType::Image(_) | LayerType::Group(_) => {}
+        }
+    }
+
+    let size = if map.infinite() {
+        None
+    } else {
+        Some((map.width, map.height))
+    };
+    Grid::from_solid_cells(solid, size)
+}
+
+impl Grid<bool> {
+    /// Creates a grid where the given cells are `true`, which starts at `(0, 0)` and has the
+    /// given size if any, or covers exactly the given cells otherwise. Cells outside of the grid
+    /// are left out.
+    pub(crate) fn from_solid_cells(solid: Vec<(i32, i32)>, size: Option<(u32, u32)>) -> Self {
+        let (origin, (width, height)) = match size {
+            Some(size) => ((0, 0), size),
+            None => {
+                let min_x = solid.iter().map(|&(x, _)| x).min();
+                let min_y = solid.iter().map(|&(_, y)| y).min();
+                let max_x = solid.iter().map(|&(x, _)| x).max();
+                let max_y = solid.iter().map(|&(_, y)| y).max();
+                match (min_x, min_y, max_x, max_y) {
+                    (Some(min_x), Some(min_y), Some(max_x), Some(max_y)) => (
+                        (min_x, min_y),
+                        (
+                            (max_x as i64 - min_x as i64 + 1) as u32,
+                            (max_y as i64 - min_y as i64 + 1) as u32,
+                        ),
+                    ),
+                    _ => ((0, 0), (0, 0)),
+                }
+            }
+        };
+        let mut grid = Grid {
+            origin,
+            width,
+            height,
+            cells: vec![false; width as usize * height as usize],
+        };
+        for (x, y) in solid {
+            if let Some(index) = grid.index(x, y) {
+                grid.cells[index] = true;
+            }
+        }
+        grid
+    }
+}
+
+/// Returns the first and last cells objects can be rasterized into: The whole map for finite
+/// maps, and the area covered by the tile layers for infinite ones.
+fn grid_extent(map: &Map) -> Option<((i32, i32), (i32, i32))> {
+    if !map.infinite() {
+        return if map.width > 0 && map.height > 0 {
+            Some((
+                (0, 0),
+                (
+                    saturating_i32(map.width - 1),
+                    saturating_i32(map.height - 1),
+                ),
+            ))
+        } else {
+            None
+        };
+    }
+    map.flattened_layers()
+        .filter_map(|layer| match layer.layer.layer_type() {
+            LayerType::Tiles(TileLayer::Infinite(tiles)) => {
+                let ((min_x, min_y), (max_x, max_y)) = tiles.bounds()?;
+                let (offset_x, offset_y) = (layer.offset_x, layer.offset_y);
+                let corners = [
+                    (min_x, min_y),
+                    (max_x, min_y),
+                    (min_x, max_y),
+                    (max_x, max_y),
+                ];
+                let cells: Vec<_> = IntoIterator::into_iter(corners)
+                    .map(|(x, y)| offset_tile(map, x, y, offset_x, offset_y))
+                    .collect();
+                Some((
+                    (
+                        cells.iter().map(|&(x, _)| x).min()?,
+                        cells.iter().map(|&(_, y)| y).min()?,
+                    ),
+                    (
+                        cells.iter().map(|&(x, _)| x).max()?,
+                        cells.iter().map(|&(_, y)| y).max()?,
+                    ),
+                ))
+            }
+            _ => None,
+        })
+        .reduce(|(min_a, max_a), (min_b, max_b)| {
+            (
+                (min_a.0.min(min_b.0), min_a.1.min(min_b.1)),
+                (max_a.0.max(max_b.0), max_a.1.max(max_b.1)),
+            )
+        })
+}
+
+fn saturating_i32(value: u32) -> i32 {
+    i32::try_from(value).unwrap_or(i32::MAX)
+}
+
+/// Returns the cell that the center of the given tile ends up in when its layer is offset by the
+/// given amount of pixels.
+fn offset_tile(map: &Map, x: i32, y: i32, offset_x: f32, offset_y: f32) -> (i32, i32) {
+    if offset_x == 0.0 && offset_y == 0.0 {
+        return (x, y);
+    }
+    let (center_x, center_y) = tile_center(map, x, y);
+    map.pixel_to_tile(center_x + offset_x, center_y + offset_y)
+}
+
+fn tile_center(map: &Map, x: i32, y: i32) -> (f32, f32) {
+    let (pixel_x, pixel_y) = map.tile_to_pixel(x, y);
+    (
+        pixel_x + map.tile_width as f32 / 2.0,
+        pixel_y + map.tile_height as f32 / 2.0,
+    )
+}
+
+/// Adds the cells within the given extent whose centers are inside the given object, in a layer
+/// offset by the given amount of pixels.
+fn rasterize_object(
+    map: &Map,
+    object: Object,
+    offset_x: f32,
+    offset_y: f32,
+    (extent_min, extent_max): ((i32, i32), (i32, i32)),
+    solid: &mut Vec<(i32, i32)>,
+) {
+    // Map points are brought back to the shape's own coordinates with the inverse of the
+    // object's transform.
+    let [a, b, c, d, e, f] = object.transform(0.0, 0.0).matrix();
+    let determinant = a * d - b * c;
+    if determinant == 0.0 {
+        return;
+    }
+
+    let bounds = object.placed_bounds();
+    let corners = [
+        (bounds.x, bounds.y),
+        (bounds.x + bounds.width, bounds.y),
+        (bounds.x, bounds.y + bounds.height),
+        (bounds.x + bounds.width, bounds.y + bounds.height),
+    ];
+    let cells: Vec<(i32, i32)> = IntoIterator::into_iter(corners)
+        .map(|(x, y)| {
+            let (x, y) = object_to_pixel(map, x, y);
+            map.pixel_to_tile(x + offset_x, y + offset_y)
+        })
+        .collect();
+    // Staggered rows and columns can put the cell of a corner next to the one containing it.
+    let min_x = cells.iter().map(|&(x, _)| x).min().unwrap_or(0);
+    let min_y = cells.iter().map(|&(_, y)| y).min().unwrap_or(0);
+    let max_x = cells.iter().map(|&(x, _)| x).max().unwrap_or(0);
+    let max_y = cells.iter().map(|&(_, y)| y).max().unwrap_or(0);
+    let min_x = min_x.saturating_sub(1).max(extent_min.0);
+    let min_y = min_y.saturating_sub(1).max(extent_min.1);
+    let max_x = max_x.saturating_add(1).min(extent_max.0);
+    let max_y = max_y.saturating_add(1).min(extent_max.1);
+
+    for y in min_y..=max_y {
+        for x in min_x..=max_x {
+            let (center_x, center_y) = tile_center(map, x, y);
+            let (px, py) = pixel_to_object(map, center_x - offset_x, center_y - offset_y);
+            let (px, py) = (px - e, py - f);
+            let local_x = (d * px - c * py) / determinant;
+            let local_y = (a * py - b * px) / determinant;
+            if object.shape.contains_point(local_x, local_y) {
+                solid.push((x, y));
+            }
+        }
+    }
+}
+
+/// Converts a position in the coordinates objects are stored in to pixels in the map. Those are
+/// the same, except in isometric maps where object positions are measured in tile heights along
+/// the axes of the tiles.
+fn object_to_pixel(map: &Map, x: f32, y: f32) -> (f32, f32) {
+    match map.orientation {
+        Orientation::Isometric => {
+            let (tile_width, tile_height) = (map.tile_width as f32, map.tile_height as f32);
+            let (tile_x, tile_y) = (x / tile_height, y / tile_height);
+            (
+                (tile_x - tile_y + map.height as f32) * tile_width / 2.0,
+                (tile_x + tile_y) * tile_height / 2.0,
+            )
+        }
+        _ => (x, y),
+    }
+}
+
+/// The inverse of [`object_to_pixel`].
+fn pixel_to_object(map: &Map, x: f32, y: f32) -> (f32, f32) {
+    match map.orientation {
+        Orientation::Isometric => {
+            let (tile_width, tile_height) = (map.tile_width as f32, map.tile_height as f32);
+            let difference = x * 2.0 / tile_width - map.height as f32;
+            let sum = y * 2.0 / tile_height;
+            (
+                (sum + difference) / 2.0 * tile_height,
+                (sum - difference) / 2.0 * tile_height,
+            )
+        }
+        _ => (x, y),
+    }
+}
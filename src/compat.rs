@@ -0,0 +1,96 @@
+//! Support for files saved by old versions of Tiled.
+//!
+//! Up to Tiled 1.1, files were all saved in format version 1.0, which changed over time in ways
+//! the parsers don't expect, such as tilesets without a tile count and image layers positioned
+//! with `x` and `y` attributes. The attributes of such files are normalized here to the ones newer
+//! versions would have saved, before the parsers read them.
+
+use crate::{
+    util::{OwnedAttribute, XmlParser},
+    Error, Result,
+};
+
+/// The first format version saved with a version number of its own, by Tiled 1.2.
+const FIRST_NUMBERED_VERSION: (u32, u32) = (1, 2);
+
+/// Returns whether a file with the given `version` attribute was saved by Tiled 1.1 or earlier.
+/// Files without one are older than the attribute, while versions that can't be read are assumed
+/// to be newer than this crate.
+pub(crate) fn is_legacy_version(version: Option<&str>) -> bool {
+    let version = match version {
+        Some(version) => version,
+        None => return true,
+    };
+    let mut parts = version.trim().split('.').map(str::parse::<u32>);
+    match (parts.next(), parts.next()) {
+        (Some(Ok(major)), None) => (major, 0) < FIRST_NUMBERED_VERSION,
+        (Some(Ok(major)), Some(Ok(minor))) => (major, minor) < FIRST_NUMBERED_VERSION,
+        _ => false,
+    }
+}
+
+/// Checks that an attribute which older versions of Tiled used to leave out is present, unless the
+/// file is in a legacy format.
+pub(crate) fn required_unless_legacy<T>(
+    parser: &impl XmlParser,
+    value: Option<T>,
+    element: &str,
+    attribute: &str,
+) -> Result<Option<T>> {
+    if value.is_none() && !parser.legacy_format() {
+        return Err(Error::MissingAttribute {
+            element: element.to_owned(),
+            attribute: attribute.to_owned(),
+            position: parser.event_position(),
+        });
+    }
+    Ok(value)
+}
+
+/// Normalizes the attributes of a layer of a legacy file, given the name of its element.
+///
+/// Layers used to have `x` and `y` attributes, which were always 0 except for image layers, where
+/// they held the offset that is now stored in `offsetx` and `offsety`. Object groups and image
+/// layers also used to have the `width` and `height` of the map.
+pub(crate) fn normalize_layer_attributes(element: &str, attrs: &mut Vec<OwnedAttribute>) {
+    let has = |attrs: &[OwnedAttribute], name: &str| {
+        attrs.iter().any(|attr| attr.name.local_name == name)
+    };
+    if element == "imagelayer" {
+        for &(legacy, name) in &[("x", "offsetx"), ("y", "offsety")] {
+            if has(attrs, name) {
+                continue;
+            }
+            if let Some(attr) = attrs.iter_mut().find(|attr| attr.name.local_name == legacy) {
+                attr.name.local_name = name.to_owned();
+            }
+        }
+    }
+    let has_size = element == "layer";
+    attrs.retain(|attr| match attr.name.local_name.as_str() {
+        "x" | "y" => false,
+        "width" | "height" => has_size,
+        _ => true,
+    });
+}
+
+/// Returns the number of tiles of a tileset from a legacy file, which used to be left out. Tilesets
+/// with an image have as many tiles as fit in it, counted the same way as their columns, while
+/// image collections have one for each tile element.
+pub(crate) fn legacy_tile_count(
+    image_size: Option<(i32, i32)>,
+    (tile_width, tile_height): (u32, u32),
+    margin: u32,
+    spacing: u32,
+    tile_elements: usize,
+) -> u32 {
+    match image_size {
+        Some((width, height)) => {
+            let fitting = |size: i32, tile_size: u32| {
+                (size.max(0) as u32 + spacing).saturating_sub(margin) / (tile_size + spacing)
+            };
+            fitting(width, tile_width) * fitting(height, tile_height)
+        }
+        None => tile_elements as u32,
+    }
+}
@@ -0,0 +1,95 @@
+//! Decompression of tile layer data with decompressors supplied by the user.
+
+use std::{fmt, io::Read, sync::Arc};
+
+/// Decompresses the data of tile layers compressed with a given compression, for compressions this
+/// crate has no built-in support for or to replace its own decompressors. Set one with
+/// [`Loader::set_decompressor`](crate::Loader::set_decompressor).
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// use std::io::{Cursor, Read};
+///
+/// use tiled::{Decompressor, Loader};
+///
+/// /// Decompresses all of the data at once instead of streaming it, like most decompressors
+/// /// written for WASM do.
+/// struct BufferedZlib;
+///
+/// impl Decompressor for BufferedZlib {
+///     fn decompress<'data>(
+///         &self,
+///         mut data: Box<dyn Read + 'data>,
+///     ) -> std::io::Result<Box<dyn Read + 'data>> {
+///         let mut compressed = Vec::new();
+///         data.read_to_end(&mut compressed)?;
+///         # let decompress = |data: &[u8]| -> std::io::Result<Vec<u8>> {
+///         #     let mut decompressed = Vec::new();
+///         #     flate2::read::ZlibDecoder::new(data).read_to_end(&mut decompressed)?;
+///         #     Ok(decompressed)
+///         # };
+///         let decompressed = decompress(&compressed)?;
+///         Ok(Box::new(Cursor::new(decompressed)))
+///     }
+/// }
+///
+/// let mut loader = Loader::new();
+/// loader.set_decompressor("zlib", BufferedZlib);
+/// let map = loader.load_tmx_map("assets/tiled_base64_zlib.tmx").unwrap();
+/// ```
+pub trait Decompressor: Send + Sync {
+    /// Returns a reader giving the decompressed contents of `data`, which reads the tile data
+    /// after it was decoded from base64.
+    fn decompress<'data>(
+        &self,
+        data: Box<dyn Read + 'data>,
+    ) -> std::io::Result<Box<dyn Read + 'data>>;
+}
+
+impl fmt::Debug for dyn Decompressor {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str("Decompressor")
+    }
+}
+
+/// The decompressors set on a loader, along with the compression each one is for.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Decompressors(Vec<(String, Arc<dyn Decompressor>)>);
+
+impl Decompressors {
+    pub(crate) fn get(&self, compression: &str) -> Option<&Arc<dyn Decompressor>> {
+        self.0
+            .iter()
+            .find(|(name, _)| name == compression)
+            .map(|(_, decompressor)| decompressor)
+    }
+
+    /// Sets the decompressor for the given compression, replacing the previous one if any.
+    pub(crate) fn insert(&mut self, compression: String, decompressor: Arc<dyn Decompressor>) {
+        self.0.retain(|(name, _)| *name != compression);
+        self.0.push((compression, decompressor));
+    }
+}
+
+/// Decompressors are the same if they are the very same objects.
+impl PartialEq for Decompressors {
+    fn eq(&self, other: &Self) -> bool {
+        self.0.len() == other.0.len()
+            && self
+                .0
+                .iter()
+                .zip(&other.0)
+                .all(|((a_name, a), (b_name, b))| a_name == b_name && Arc::ptr_eq(a, b))
+    }
+}
+
+/// Returns the feature of this crate that provides a built-in decompressor for the given
+/// compression, if there is one.
+pub(crate) fn compression_feature(compression: &str) -> Option<&'static str> {
+    match compression {
+        "zlib" | "gzip" => Some("flate2"),
+        "zstd" => Some("zstd"),
+        _ => None,
+    }
+}
@@ -0,0 +1,377 @@
+//! Comparison of maps that reports where they differ.
+
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt,
+    sync::Arc,
+};
+
+use crate::{
+    Layer, LayerTile, LayerTileData, LayerType, Map, Object, ObjectId, Properties, PropertyValue,
+    TileData, TileLayer, Tileset,
+};
+
+/// A difference between two maps found by [`Map::diff`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MapDifference {
+    /// Where the difference is, such as `width`, `tilesets[0].tiles[12].probability` or
+    /// `layers[1].layers[0].objects[id=4].x`. Layers are indexed by their position in their
+    /// parent, objects by their ID and tiles of tile layers by their position in the layer.
+    pub path: String,
+    /// The value found in the first map, formatted with [`Debug`](fmt::Debug), or [`None`] if
+    /// there is nothing at that path in it.
+    pub left: Option<String>,
+    /// The value found in the second map; See [`Self::left`].
+    pub right: Option<String>,
+}
+
+impl fmt::Display for MapDifference {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match (&self.left, &self.right) {
+            (Some(left), Some(right)) => write!(f, "{}: {} != {}", self.path, left, right),
+            (Some(left), None) => write!(f, "{}: only in the first map ({})", self.path, left),
+            (None, Some(right)) => write!(f, "{}: only in the second map ({})", self.path, right),
+            (None, None) => write!(f, "{}: differs", self.path),
+        }
+    }
+}
+
+pub(crate) fn diff_maps(a: &Map, b: &Map) -> Vec<MapDifference> {
+    let mut diff = Differ::default();
+    diff.field("", "version", &a.version(), &b.version());
+    diff.field("", "orientation", &a.orientation, &b.orientation);
+    diff.field("", "width", &a.width, &b.width);
+    diff.field("", "height", &a.height, &b.height);
+    diff.field("", "tile_width", &a.tile_width, &b.tile_width);
+    diff.field("", "tile_height", &a.tile_height, &b.tile_height);
+    diff.field("", "stagger_axis", &a.stagger_axis, &b.stagger_axis);
+    diff.field("", "stagger_index", &a.stagger_index, &b.stagger_index);
+    diff.field(
+        "",
+        "hex_side_length",
+        &a.hex_side_length,
+        &b.hex_side_length,
+    );
+    diff.field("", "render_order", &a.render_order, &b.render_order);
+    diff.field(
+        "",
+        "compression_level",
+        &a.compression_level,
+        &b.compression_level,
+    );
+    diff.field(
+        "",
+        "background_color",
+        &a.background_color,
+        &b.background_color,
+    );
+    diff.field(
+        "",
+        "parallax_origin_x",
+        &a.parallax_origin_x,
+        &b.parallax_origin_x,
+    );
+    diff.field(
+        "",
+        "parallax_origin_y",
+        &a.parallax_origin_y,
+        &b.parallax_origin_y,
+    );
+    diff.field("", "infinite", &a.infinite(), &b.infinite());
+    diff.field("", "user_type", &a.user_type, &b.user_type);
+    diff.properties("", &a.properties, &b.properties);
+
+    let tilesets = a.tilesets().len().max(b.tilesets().len());
+    for index in 0..tilesets {
+        let path = format!("tilesets[{}]", index);
+        match (a.tilesets().get(index), b.tilesets().get(index)) {
+            (Some(left), Some(right)) => {
+                diff.field(
+                    &path,
+                    "first_gid",
+                    &a.tileset_first_gids()[index],
+                    &b.tileset_first_gids()[index],
+                );
+                diff.tileset(&path, left, right);
+            }
+            (left, right) => {
+                let describe = |tileset: &Arc<Tileset>| format!("tileset {:?}", tileset.name);
+                diff.presence(path, left.map(describe), right.map(describe))
+            }
+        }
+    }
+
+    diff.layers(
+        "",
+        &a.layers().collect::<Vec<_>>(),
+        &b.layers().collect::<Vec<_>>(),
+    );
+    diff.differences
+}
+
+#[derive(Default)]
+struct Differ {
+    differences: Vec<MapDifference>,
+}
+
+impl Differ {
+    fn push(&mut self, path: String, left: Option<String>, right: Option<String>) {
+        self.differences.push(MapDifference { path, left, right });
+    }
+
+    /// Records a difference if the given values of the field with the given name differ.
+    fn field<T: PartialEq + fmt::Debug + ?Sized>(&mut self, path: &str, name: &str, a: &T, b: &T) {
+        if a != b {
+            self.push(
+                join(path, name),
+                Some(format!("{:?}", a)),
+                Some(format!("{:?}", b)),
+            );
+        }
+    }
+
+    /// Records something that only exists in one of the maps, given a short description of it.
+    fn presence(&mut self, path: String, a: Option<String>, b: Option<String>) {
+        if a.is_some() != b.is_some() {
+            self.push(path, a, b);
+        }
+    }
+
+    fn properties(&mut self, path: &str, a: &Properties, b: &Properties) {
+        if a == b {
+            return;
+        }
+        let names: BTreeSet<&String> = a.keys().chain(b.keys()).collect();
+        for name in names {
+            let path = format!("{}[{:?}]", join(path, "properties"), name);
+            match (a.get(name), b.get(name)) {
+                (Some(left), Some(right)) if left != right => self.push(
+                    path,
+                    Some(format!("{:?}", left)),
+                    Some(format!("{:?}", right)),
+                ),
+                (left, right) => {
+                    let describe = |value: &PropertyValue| format!("{:?}", value);
+                    self.presence(path, left.map(describe), right.map(describe))
+                }
+            }
+        }
+    }
+
+    fn tileset(&mut self, path: &str, a: &Tileset, b: &Tileset) {
+        if a == b {
+            return;
+        }
+        self.field(path, "name", &a.name, &b.name);
+        self.field(path, "tile_width", &a.tile_width, &b.tile_width);
+        self.field(path, "tile_height", &a.tile_height, &b.tile_height);
+        self.field(path, "spacing", &a.spacing, &b.spacing);
+        self.field(path, "margin", &a.margin, &b.margin);
+        self.field(path, "tilecount", &a.tilecount, &b.tilecount);
+        self.field(path, "columns", &a.columns, &b.columns);
+        self.field(path, "offset_x", &a.offset_x, &b.offset_x);
+        self.field(path, "offset_y", &a.offset_y, &b.offset_y);
+        self.field(path, "image", &a.image, &b.image);
+        self.field(path, "wang_sets", &a.wang_sets, &b.wang_sets);
+        self.field(path, "user_type", &a.user_type, &b.user_type);
+        self.properties(path, &a.properties, &b.properties);
+
+        let ids: BTreeSet<u32> = a.tiles().chain(b.tiles()).map(|(id, _)| id).collect();
+        for id in ids {
+            let path = format!("{}[{}]", join(path, "tiles"), id);
+            match (a.get_tile(id), b.get_tile(id)) {
+                (Some(left), Some(right)) => self.tile(&path, &left, &right),
+                (left, right) => {
+                    let describe = |_| format!("tile {}", id);
+                    self.presence(path, left.map(describe), right.map(describe))
+                }
+            }
+        }
+    }
+
+    fn tile(&mut self, path: &str, a: &TileData, b: &TileData) {
+        if a == b {
+            return;
+        }
+        self.field(path, "image", &a.image, &b.image);
+        self.field(path, "image_rect", &a.image_rect, &b.image_rect);
+        self.field(path, "collision", &a.collision, &b.collision);
+        self.field(path, "animation", &a.animation, &b.animation);
+        self.field(path, "user_type", &a.user_type, &b.user_type);
+        self.field(path, "probability", &a.probability, &b.probability);
+        self.properties(path, &a.properties, &b.properties);
+    }
+
+    fn layers(&mut self, path: &str, a: &[Layer], b: &[Layer]) {
+        for index in 0..a.len().max(b.len()) {
+            let path = format!("{}[{}]", join(path, "layers"), index);
+            match (a.get(index), b.get(index)) {
+                (Some(left), Some(right)) => self.layer(&path, *left, *right),
+                (left, right) => {
+                    let describe = |layer: &Layer| {
+                        format!("{} {:?}", layer_type_name(&layer.layer_type()), layer.name)
+                    };
+                    self.presence(path, left.map(describe), right.map(describe))
+                }
+            }
+        }
+    }
+
+    fn layer(&mut self, path: &str, a: Layer, b: Layer) {
+        self.field(path, "id", &a.id(), &b.id());
+        self.field(path, "name", &a.name, &b.name);
+        self.field(path, "visible", &a.visible, &b.visible);
+        self.field(path, "offset_x", &a.offset_x, &b.offset_x);
+        self.field(path, "offset_y", &a.offset_y, &b.offset_y);
+        self.field(path, "parallax_x", &a.parallax_x, &b.parallax_x);
+        self.field(path, "parallax_y", &a.parallax_y, &b.parallax_y);
+        self.field(path, "opacity", &a.opacity, &b.opacity);
+        self.field(path, "tint_color", &a.tint_color, &b.tint_color);
+        self.field(path, "user_type", &a.user_type, &b.user_type);
+        self.properties(path, &a.properties, &b.properties);
+
+        match (a.layer_type(), b.layer_type()) {
+            (LayerType::Tiles(left), LayerType::Tiles(right)) => {
+                let (left, right) = (tiles_by_position(left), tiles_by_position(right));
+                if left == right {
+                    return;
+                }
+                // Sorted by row first, which is how tiles are usually laid out.
+                let positions: BTreeSet<(i32, i32)> =
+                    left.keys().chain(right.keys()).copied().collect();
+                for (y, x) in positions {
+                    let path = format!("{}[({}, {})]", join(path, "tiles"), x, y);
+                    match (left.get(&(y, x)), right.get(&(y, x))) {
+                        (Some(l), Some(r)) if l != r => {
+                            self.push(path, Some(describe_tile(l)), Some(describe_tile(r)))
+                        }
+                        (l, r) => self.presence(path, l.map(describe_tile), r.map(describe_tile)),
+                    }
+                }
+            }
+            (LayerType::Objects(left), LayerType::Objects(right)) => {
+                self.field(path, "color", &left.colour, &right.colour);
+                self.field(path, "draw_order", &left.draw_order, &right.draw_order);
+                let (left, right) = (
+                    objects_by_key(left.objects()),
+                    objects_by_key(right.objects()),
+                );
+                let keys: BTreeSet<&ObjectKey> = left.keys().chain(right.keys()).collect();
+                for key in keys {
+                    let path = format!("{}[{}]", join(path, "objects"), key);
+                    match (left.get(key), right.get(key)) {
+                        (Some(l), Some(r)) => self.object(&path, *l, *r),
+                        (l, r) => {
+                            let describe = |object: &Object| match object.name.as_str() {
+                                "" => format!("object at ({}, {})", object.x, object.y),
+                                name => {
+                                    format!("object {:?} at ({}, {})", name, object.x, object.y)
+                                }
+                            };
+                            self.presence(path, l.map(describe), r.map(describe))
+                        }
+                    }
+                }
+            }
+            (LayerType::Image(left), LayerType::Image(right)) => {
+                self.field(path, "image", &left.image, &right.image);
+                self.field(path, "repeat_x", &left.repeat_x, &right.repeat_x);
+                self.field(path, "repeat_y", &left.repeat_y, &right.repeat_y);
+            }
+            (LayerType::Group(left), LayerType::Group(right)) => self.layers(
+                path,
+                &left.layers().collect::<Vec<_>>(),
+                &right.layers().collect::<Vec<_>>(),
+            ),
+            (left, right) => self.push(
+                join(path, "layer_type"),
+                Some(layer_type_name(&left).to_owned()),
+                Some(layer_type_name(&right).to_owned()),
+            ),
+        }
+    }
+
+    fn object(&mut self, path: &str, a: Object, b: Object) {
+        self.field(path, "name", &a.name, &b.name);
+        self.field(path, "user_type", &a.user_type, &b.user_type);
+        self.field(path, "x", &a.x, &b.x);
+        self.field(path, "y", &a.y, &b.y);
+        self.field(path, "rotation", &a.rotation, &b.rotation);
+        self.field(path, "visible", &a.visible, &b.visible);
+        self.field(path, "shape", &a.shape, &b.shape);
+        self.field(path, "tile", &a.tile_data(), &b.tile_data());
+        self.properties(path, &a.properties, &b.properties);
+    }
+}
+
+fn join(path: &str, name: &str) -> String {
+    if path.is_empty() {
+        name.to_owned()
+    } else {
+        format!("{}.{}", path, name)
+    }
+}
+
+pub(crate) fn layer_type_name(layer_type: &LayerType) -> &'static str {
+    match layer_type {
+        LayerType::Tiles(_) => "tile layer",
+        LayerType::Objects(_) => "object layer",
+        LayerType::Image(_) => "image layer",
+        LayerType::Group(_) => "group layer",
+    }
+}
+
+fn describe_tile(tile: &LayerTileData) -> String {
+    let mut description = format!("tile {} of tileset {}", tile.id(), tile.tileset_index());
+    let flips: Vec<&str> = [
+        (tile.flip_h, "horizontally"),
+        (tile.flip_v, "vertically"),
+        (tile.flip_d, "diagonally"),
+    ]
+    .iter()
+    .filter(|(flipped, _)| *flipped)
+    .map(|(_, axis)| *axis)
+    .collect();
+    if !flips.is_empty() {
+        description += &format!(", flipped {}", flips.join(" and "));
+    }
+    description
+}
+
+/// Returns the tiles of a layer by their position, with the Y coordinate first.
+fn tiles_by_position(layer: TileLayer) -> BTreeMap<(i32, i32), LayerTileData> {
+    let tiles: Box<dyn Iterator<Item = ((i32, i32), LayerTile)>> = match layer {
+        TileLayer::Finite(tiles) => Box::new(tiles.tiles()),
+        TileLayer::Infinite(tiles) => Box::new(tiles.tiles()),
+    };
+    tiles.map(|((x, y), tile)| ((y, x), *tile)).collect()
+}
+
+/// How objects are matched between maps: By their ID, or by their index for those without one.
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+enum ObjectKey {
+    Id(u32),
+    Index(usize),
+}
+
+impl fmt::Display for ObjectKey {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ObjectKey::Id(id) => write!(f, "id={}", id),
+            ObjectKey::Index(index) => write!(f, "{}", index),
+        }
+    }
+}
+
+fn objects_by_key<'map>(
+    objects: impl Iterator<Item = Object<'map>>,
+) -> BTreeMap<ObjectKey, Object<'map>> {
+    objects
+        .enumerate()
+        .map(|(index, object)| match object.id() {
+            ObjectId(0) => (ObjectKey::Index(index), object),
+            ObjectId(id) => (ObjectKey::Id(id), object),
+        })
+        .collect()
+}
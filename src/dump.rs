@@ -0,0 +1,181 @@
+//! Indented textual summaries of maps, for debugging.
+
+use std::fmt::Write;
+
+use crate::{
+    diff::layer_type_name, write::color_string, ImageSource, Layer, LayerType, Map, Properties,
+    PropertyValue, TileLayer, Tileset,
+};
+
+pub(crate) fn dump_map(map: &Map) -> String {
+    let mut dump = Dump::default();
+    let mut header = format!(
+        "map {}x{} {}, tiles {}x{}",
+        map.width, map.height, map.orientation, map.tile_width, map.tile_height
+    );
+    if map.infinite() {
+        header += ", infinite";
+    }
+    if let Some(class) = &map.user_type {
+        header += &format!(", class {:?}", class);
+    }
+    header += &format!(" (version {})", map.version());
+    dump.line(0, header);
+    dump.properties(1, &map.properties);
+
+    if !map.tilesets().is_empty() {
+        dump.line(1, "tilesets:");
+        for (index, tileset) in map.tilesets().iter().enumerate() {
+            let mut line = format!(
+                "[{}] {:?} (first gid {}): {}",
+                index,
+                tileset.name,
+                map.tileset_first_gids()[index].0,
+                describe_tileset(tileset)
+            );
+            if let Some(path) = map.tileset_path(index) {
+                line += &format!(", file {}", path.display());
+            }
+            dump.line(2, line);
+            dump.properties(3, &tileset.properties);
+        }
+    } else if !map.lazy_tilesets().is_empty() {
+        dump.line(1, "tilesets (not resolved):");
+        for (index, tileset) in map.lazy_tilesets().iter().enumerate() {
+            let line = match tileset.path() {
+                Some(path) => format!("[{}] {}", index, path.display()),
+                None => format!("[{}] embedded", index),
+            };
+            dump.line(2, line);
+        }
+    }
+
+    if map.layers().len() > 0 {
+        dump.line(1, "layers:");
+        dump.layers(2, map.layers());
+    }
+    dump.text
+}
+
+#[derive(Default)]
+struct Dump {
+    text: String,
+}
+
+impl Dump {
+    fn line(&mut self, depth: usize, line: impl AsRef<str>) {
+        // Writing to a string can't fail.
+        let _ = writeln!(
+            self.text,
+            "{:indent$}{}",
+            "",
+            line.as_ref(),
+            indent = depth * 2
+        );
+    }
+
+    fn properties(&mut self, depth: usize, properties: &Properties) {
+        if properties.is_empty() {
+            return;
+        }
+        self.line(depth, "properties:");
+        self.property_values(depth + 1, properties);
+    }
+
+    fn property_values(&mut self, depth: usize, properties: &Properties) {
+        let mut names: Vec<&String> = properties.keys().collect();
+        names.sort();
+        for name in names {
+            let value = &properties[name];
+            self.line(depth, format!("{} = {}", name, describe_value(value)));
+            if let PropertyValue::ClassValue { properties, .. } = value {
+                self.property_values(depth + 1, properties);
+            }
+        }
+    }
+
+    fn layers<'map>(&mut self, depth: usize, layers: impl Iterator<Item = Layer<'map>>) {
+        for (index, layer) in layers.enumerate() {
+            let mut line = format!(
+                "[{}] {} {:?} (id {})",
+                index,
+                layer_type_name(&layer.layer_type()),
+                layer.name,
+                layer.id().0
+            );
+            if let Some(class) = &layer.user_type {
+                line += &format!(", class {:?}", class);
+            }
+            if !layer.visible {
+                line += ", hidden";
+            }
+            if layer.offset_x != 0.0 || layer.offset_y != 0.0 {
+                line += &format!(", offset ({}, {})", layer.offset_x, layer.offset_y);
+            }
+            if layer.opacity != 1.0 {
+                line += &format!(", opacity {}", layer.opacity);
+            }
+            match layer.layer_type() {
+                LayerType::Tiles(TileLayer::Finite(tiles)) => {
+                    let count = tiles.tiles().count();
+                    line += &format!(": {}x{}, {} tiles", tiles.width(), tiles.height(), count);
+                }
+                LayerType::Tiles(TileLayer::Infinite(tiles)) => {
+                    let count = tiles.tiles().count();
+                    line += &format!(": {} chunks, {} tiles", tiles.chunks().len(), count);
+                }
+                LayerType::Objects(objects) => {
+                    line += &format!(": {} objects", objects.objects().len());
+                }
+                LayerType::Image(image) => match &image.image {
+                    Some(image) => line += &format!(": image {}", describe_image(&image.source)),
+                    None => line += ": no image",
+                },
+                LayerType::Group(_) => {}
+            }
+            self.line(depth, line);
+            self.properties(depth + 1, &layer.properties);
+            if let LayerType::Group(group) = layer.layer_type() {
+                self.layers(depth + 1, group.layers());
+            }
+        }
+    }
+}
+
+fn describe_tileset(tileset: &Tileset) -> String {
+    let mut description = format!(
+        "{} tiles of {}x{}",
+        tileset.tilecount, tileset.tile_width, tileset.tile_height
+    );
+    match &tileset.image {
+        Some(image) => description += &format!(", image {}", describe_image(&image.source)),
+        None => description += ", image collection",
+    }
+    description
+}
+
+fn describe_image(source: &ImageSource) -> String {
+    match source {
+        ImageSource::Path(path) => path.display().to_string(),
+        ImageSource::Embedded { format, .. } => format!("embedded {} image", format),
+    }
+}
+
+fn describe_value(value: &PropertyValue) -> String {
+    match value {
+        PropertyValue::BoolValue(value) => value.to_string(),
+        PropertyValue::FloatValue(value) => value.to_string(),
+        PropertyValue::IntValue(value) => value.to_string(),
+        PropertyValue::ColorValue(color) => color_string(color),
+        PropertyValue::StringValue(value) => format!("{:?}", value),
+        PropertyValue::FileValue { relative, .. } => format!("file {:?}", relative),
+        PropertyValue::ObjectValue(0) => "no object".to_owned(),
+        PropertyValue::ObjectValue(id) => format!("object {}", id),
+        PropertyValue::ClassValue { property_type, .. } => property_type.clone(),
+        PropertyValue::EnumValue {
+            property_type,
+            values,
+            ..
+        } => format!("{}::{}", property_type, values.join(" | ")),
+    }
+}
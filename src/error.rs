@@ -1,6 +1,9 @@
 use crate::InvalidTilesetError::InvalidTileDimensions;
 use std::num::ParseIntError;
-use std::{fmt, path::PathBuf};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+};
 
 /// Errors that can occur while decoding csv data.
 #[derive(Clone, Debug, PartialEq, Eq)]
@@ -41,6 +44,63 @@ impl fmt::Display for InvalidTilesetError {
 
 impl std::error::Error for InvalidTilesetError {}
 
+/// A position within an XML file, such as the one of the element an error was found in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct XmlPosition {
+    /// The line number, starting from 1.
+    pub line: u64,
+    /// The column number, starting from 1.
+    pub column: u64,
+}
+
+impl fmt::Display for XmlPosition {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}:{}", self.line, self.column)
+    }
+}
+
+/// A problem that was recovered from while loading a map with
+/// [`ErrorTolerance::Lenient`](crate::ErrorTolerance::Lenient), which would have otherwise made
+/// loading fail, or a conflict between IDs or GIDs of a map. Also see
+/// [`Map::warnings`](crate::Map::warnings).
+#[derive(Clone, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Warning {
+    /// The path of the file the problem was found in.
+    pub path: PathBuf,
+    /// The position of the element that caused the problem.
+    pub position: XmlPosition,
+    /// A description of the problem, which is the message of the error that would have been
+    /// returned otherwise.
+    pub description: String,
+}
+
+impl Warning {
+    /// Creates a warning out of an error, using its location if it has one and the one given
+    /// otherwise.
+    pub(crate) fn new(error: &Error, path: &Path, position: XmlPosition) -> Self {
+        let (path, position) = error.location().unwrap_or((path, position));
+        Self {
+            path: path.to_owned(),
+            position,
+            description: error.inner().to_string(),
+        }
+    }
+}
+
+impl fmt::Display for Warning {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{}:{}: {}",
+            self.path.to_string_lossy(),
+            self.position,
+            self.description
+        )
+    }
+}
+
 /// Errors which occurred when parsing the file
 #[derive(Debug)]
 #[non_exhaustive]
@@ -48,6 +108,27 @@ pub enum Error {
     /// A attribute was missing, had the wrong type of wasn't formated
     /// correctly.
     MalformedAttributes(String),
+    /// A required attribute was not present in an element.
+    MissingAttribute {
+        /// The name of the element the attribute is missing from.
+        element: String,
+        /// The name of the missing attribute.
+        attribute: String,
+        /// The position of the element in its file.
+        position: XmlPosition,
+    },
+    /// The value of an attribute was not valid, for instance because it was not a number when
+    /// one was expected.
+    InvalidAttributeValue {
+        /// The name of the element the attribute belongs to.
+        element: String,
+        /// The name of the attribute.
+        attribute: String,
+        /// The value that could not be parsed.
+        value: String,
+        /// The position of the element in its file.
+        position: XmlPosition,
+    },
     /// An error occurred when decompressing using the
     /// [flate2](https://github.com/alexcrichton/flate2-rs) crate.
     DecompressingError(std::io::Error),
@@ -56,9 +137,15 @@ pub enum Error {
     /// An error occurred when decoding a csv encoded dataset.
     CsvDecodingError(CsvDecodingError),
     /// An error occurred when parsing an XML file, such as a TMX or TSX file.
-    XmlDecodingError(xml::reader::Error),
+    XmlDecodingError(quick_xml::Error),
     /// The XML stream ended before the document was fully parsed.
     PrematureEnd(String),
+    /// An error occurred when writing a file, such as a TSX file.
+    WritingError(std::io::Error),
+    /// An error occurred when decoding an image with the [image](https://github.com/image-rs/image)
+    /// crate.
+    #[cfg(feature = "image-loading")]
+    ImageDecodingError(::image::ImageError),
     /// The path given is invalid because it isn't contained in any folder.
     PathIsNotFile,
     /// An error generated by [`ResourceReader`](crate::ResourceReader) while trying to read a
@@ -85,6 +172,13 @@ pub enum Error {
         /// A description of the error that occurred.
         description: String,
     },
+    /// A property required by [`FromProperties::from_properties`] was missing.
+    ///
+    /// [`FromProperties::from_properties`]: crate::FromProperties::from_properties
+    MissingProperty {
+        /// The name of the missing property.
+        name: String,
+    },
     /// Found an unknown property value type while parsing a [`PropertyValue`].
     ///
     /// [`PropertyValue`]: crate::PropertyValue
@@ -107,6 +201,109 @@ pub enum Error {
     },
     /// There was an invalid tileset in the map parsed.
     InvalidTileset(InvalidTilesetError),
+    /// A Tiled project file could not be parsed.
+    InvalidProjectFile {
+        /// A description of the error that occurred.
+        description: String,
+    },
+    /// A map being built in code was given invalid data, such as a tile referencing a tileset
+    /// that isn't part of the map.
+    InvalidMapData {
+        /// A description of the error that occurred.
+        description: String,
+    },
+    /// Tile data being built in code with [`TileDataBuilder`](crate::TileDataBuilder) was given
+    /// invalid data, such as an image region that doesn't fit in its image.
+    InvalidTileData {
+        /// A description of the error that occurred.
+        description: String,
+    },
+    /// Several layers of a map have the same ID.
+    DuplicateLayerId {
+        /// The ID used by more than one layer.
+        id: u32,
+    },
+    /// Several objects of a map have the same ID.
+    DuplicateObjectId {
+        /// The ID used by more than one object.
+        id: u32,
+    },
+    /// The GID ranges of two tilesets of a map overlap, so that some GIDs could refer to tiles of
+    /// either of them.
+    OverlappingTilesets {
+        /// The first GID of the tileset whose range overlaps with an earlier one.
+        first_gid: u32,
+        /// The first GID of the earlier tileset.
+        other_first_gid: u32,
+    },
+    /// A file went over one of the [`ParseLimits`](crate::ParseLimits) it was loaded with.
+    LimitExceeded {
+        /// A description of the limit that was exceeded.
+        description: String,
+    },
+    /// An element contained something it can't, such as a child element inside of an object's
+    /// `<text>`.
+    UnexpectedContent {
+        /// The name of the element the content was found in.
+        element: String,
+        /// A description of what was found.
+        found: String,
+    },
+    /// Tile layer data is compressed with a compression that there is no decompressor for, either
+    /// because the feature of this crate providing one is disabled or because it's unknown to
+    /// this crate. A decompressor can be supplied with
+    /// [`Loader::set_decompressor`](crate::Loader::set_decompressor).
+    UnsupportedCompression {
+        /// The `compression` attribute of the tile layer data.
+        compression: String,
+        /// The feature of this crate that provides a decompressor for the compression, if any.
+        feature: Option<&'static str>,
+    },
+    /// An error that occurred while parsing a file, along with the location of the element that
+    /// caused it.
+    ///
+    /// Every error that happens while parsing the contents of a file is wrapped in this variant;
+    /// Use [`Error::inner`] to get the actual error.
+    Located {
+        /// The path of the file the error occurred in.
+        path: PathBuf,
+        /// The position of the element that caused the error.
+        position: XmlPosition,
+        /// The error that occurred.
+        error: Box<Error>,
+    },
+}
+
+impl Error {
+    /// Returns the error without its location; That is, the error wrapped by an
+    /// [`Error::Located`], or the error itself for any other variant.
+    pub fn inner(&self) -> &Error {
+        match self {
+            Error::Located { error, .. } => error,
+            error => error,
+        }
+    }
+
+    /// Returns the path of the file and the position of the element that caused the error, if
+    /// known.
+    pub fn location(&self) -> Option<(&Path, XmlPosition)> {
+        match self {
+            Error::Located { path, position, .. } => Some((path, *position)),
+            _ => None,
+        }
+    }
+
+    /// Wraps the error in an [`Error::Located`], unless it already is one.
+    pub(crate) fn located(self, path: &Path, position: XmlPosition) -> Self {
+        match self {
+            Error::Located { .. } => self,
+            error => Error::Located {
+                path: path.to_owned(),
+                position,
+                error: Box::new(error),
+            },
+        }
+    }
 }
 
 /// A result with an error variant of [`crate::Error`].
@@ -116,11 +313,20 @@ impl fmt::Display for Error {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> std::result::Result<(), fmt::Error> {
         match self {
             Error::MalformedAttributes(s) => write!(fmt, "{}", s),
+            Error::MissingAttribute { element, attribute, .. } =>
+                write!(fmt, "Missing attribute '{}' in element '{}'", attribute, element),
+            Error::InvalidAttributeValue { element, attribute, value, .. } =>
+                write!(fmt, "Invalid value '{}' for attribute '{}' in element '{}'", value, attribute, element),
+            Error::Located { path, position, error } =>
+                write!(fmt, "{}:{}: {}", path.to_string_lossy(), position, error),
             Error::DecompressingError(e) => write!(fmt, "{}", e),
             Error::Base64DecodingError(e) => write!(fmt, "{}", e),
             Error::CsvDecodingError(e) => write!(fmt, "{}", e),
             Error::XmlDecodingError(e) => write!(fmt, "{}", e),
             Error::PrematureEnd(e) => write!(fmt, "{}", e),
+            Error::WritingError(e) => write!(fmt, "{}", e),
+            #[cfg(feature = "image-loading")]
+            Error::ImageDecodingError(e) => write!(fmt, "{}", e),
             Error::PathIsNotFile => {
                 write!(
                     fmt,
@@ -150,6 +356,7 @@ impl fmt::Display for Error {
                 ),
             Error::InvalidPropertyValue{description} =>
                 write!(fmt, "Invalid property value: {}", description),
+            Error::MissingProperty { name } => write!(fmt, "Missing property \"{}\"", name),
             Error::UnknownPropertyType { type_name } =>
                 write!(fmt, "Unknown property value type '{}'", type_name),
             Error::TemplateHasNoObject => write!(fmt, "A template was found with no object element"),
@@ -158,6 +365,32 @@ impl fmt::Display for Error {
             Error::InvalidObjectData{description} =>
                 write!(fmt, "Invalid object data: {}", description),
             Error::InvalidTileset(e) => write!(fmt, "{}", e),
+            Error::InvalidProjectFile { description } =>
+                write!(fmt, "Invalid project file: {}", description),
+            Error::InvalidMapData { description } =>
+                write!(fmt, "Invalid map data: {}", description),
+            Error::InvalidTileData { description } =>
+                write!(fmt, "Invalid tile data: {}", description),
+            Error::DuplicateLayerId { id } => write!(fmt, "Duplicate layer ID {}", id),
+            Error::DuplicateObjectId { id } => write!(fmt, "Duplicate object ID {}", id),
+            Error::OverlappingTilesets { first_gid, other_first_gid } =>
+                write!(
+                    fmt,
+                    "The GIDs of the tileset starting at {} overlap with the ones of the tileset starting at {}",
+                    first_gid, other_first_gid
+                ),
+            Error::LimitExceeded { description } =>
+                write!(fmt, "Parsing limit exceeded: {}", description),
+            Error::UnexpectedContent { element, found } =>
+                write!(fmt, "Unexpected {} inside <{}>", found, element),
+            Error::UnsupportedCompression { compression, feature: Some(feature) } =>
+                write!(
+                    fmt,
+                    "Tile data compressed with {} requires the `{}` feature or a custom decompressor",
+                    compression, feature
+                ),
+            Error::UnsupportedCompression { compression, feature: None } =>
+                write!(fmt, "Unsupported tile data compression '{}'", compression),
         }
     }
 }
@@ -168,7 +401,11 @@ impl std::error::Error for Error {
             Error::DecompressingError(e) => Some(e as &dyn std::error::Error),
             Error::Base64DecodingError(e) => Some(e as &dyn std::error::Error),
             Error::XmlDecodingError(e) => Some(e as &dyn std::error::Error),
+            Error::WritingError(e) => Some(e as &dyn std::error::Error),
+            #[cfg(feature = "image-loading")]
+            Error::ImageDecodingError(e) => Some(e as &dyn std::error::Error),
             Error::ResourceLoadingError { err, .. } => Some(err.as_ref()),
+            Error::Located { error, .. } => Some(error.as_ref()),
             _ => None,
         }
     }
@@ -0,0 +1,140 @@
+//! Preservation of the parts of files that aren't read into any other field.
+
+use std::collections::HashMap;
+
+use quick_xml::escape::escape;
+
+use crate::{
+    util::{OwnedAttribute, XmlEvent, XmlParser},
+    Error, Result,
+};
+
+/// The attributes and child elements of an element that this crate doesn't read, such as ones
+/// added by newer versions of Tiled.
+///
+/// These are only collected when [`LoaderOptions::preserve_unknown_xml`] is enabled. The ones of
+/// tilesets and their objects are written back out by [`Tileset::to_tsx_writer`], so that saving
+/// a loaded tileset doesn't lose them.
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{Loader, LoaderOptionsBuilder};
+///
+/// let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1"
+///     tilewidth="32" tileheight="32" futurefeature="on">
+///  <future><setting value="1"/></future>
+/// </map>"#;
+///
+/// let options = LoaderOptionsBuilder::new().preserve_unknown_xml(true).build();
+/// let mut loader = Loader::new().with_options(options);
+/// let map = loader.load_tmx_map_from(xml.as_bytes(), "assets/future.tmx")?;
+/// assert_eq!(map.extra.attributes["futurefeature"], "on");
+/// assert_eq!(
+///     map.extra.elements,
+///     [r#"<future><setting value="1"></setting></future>"#]
+/// );
+/// # Ok(())
+/// # }
+/// ```
+///
+/// [`LoaderOptions::preserve_unknown_xml`]: crate::LoaderOptions::preserve_unknown_xml
+/// [`Tileset::to_tsx_writer`]: crate::Tileset::to_tsx_writer
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ExtraXml {
+    /// The unknown attributes of the element, by name.
+    pub attributes: HashMap<String, String>,
+    /// The unknown child elements of the element, as XML fragments in the order they were found
+    /// in.
+    pub elements: Vec<String>,
+}
+
+impl ExtraXml {
+    /// Returns whether there are no unknown attributes or elements.
+    pub fn is_empty(&self) -> bool {
+        self.attributes.is_empty() && self.elements.is_empty()
+    }
+
+    /// Collects the attributes of an element whose names aren't in `known`, if the parser
+    /// preserves unknown XML.
+    pub(crate) fn from_attributes(
+        parser: &impl XmlParser,
+        attrs: &[OwnedAttribute],
+        known: &[&str],
+    ) -> Self {
+        let mut extra = Self::default();
+        if parser.options().preserve_unknown_xml() {
+            extra.attributes = attrs
+                .iter()
+                .filter(|attr| !known.contains(&attr.name.local_name.as_str()))
+                .map(|attr| (attr.name.local_name.clone(), attr.value.clone()))
+                .collect();
+        }
+        extra
+    }
+
+    /// Reads the unknown element that was just opened, along with everything inside of it, and
+    /// stores it as an XML fragment if the parser preserves unknown XML. Otherwise, the element
+    /// is left to be skipped by the caller.
+    pub(crate) fn read_element(
+        &mut self,
+        parser: &mut impl XmlParser,
+        name: String,
+        attrs: Vec<OwnedAttribute>,
+    ) -> Result<()> {
+        if !parser.options().preserve_unknown_xml() {
+            return Ok(());
+        }
+        let mut xml = String::new();
+        push_start_tag(&mut xml, &name, &attrs);
+        let mut depth = 1;
+        while depth > 0 {
+            let event = parser.next().ok_or_else(|| {
+                Error::PrematureEnd(format!("Document ended inside of <{}>", name))
+            })?;
+            match event.map_err(Error::XmlDecodingError)? {
+                XmlEvent::StartElement { name, attributes } => {
+                    depth += 1;
+                    push_start_tag(&mut xml, &name.local_name, &attributes);
+                }
+                XmlEvent::EndElement { name } => {
+                    depth -= 1;
+                    xml.push_str("</");
+                    xml.push_str(&name.local_name);
+                    xml.push('>');
+                }
+                XmlEvent::Characters(text) | XmlEvent::Whitespace(text) => {
+                    xml.push_str(&escape(text.as_str()))
+                }
+                XmlEvent::Comment(comment) => {
+                    xml.push_str("<!--");
+                    xml.push_str(&comment);
+                    xml.push_str("-->");
+                }
+                XmlEvent::EndDocument => {
+                    return Err(Error::PrematureEnd(format!(
+                        "Document ended inside of <{}>",
+                        name
+                    )))
+                }
+            }
+        }
+        self.elements.push(xml);
+        Ok(())
+    }
+}
+
+fn push_start_tag(xml: &mut String, name: &str, attrs: &[OwnedAttribute]) {
+    xml.push('<');
+    xml.push_str(name);
+    for attr in attrs {
+        xml.push(' ');
+        xml.push_str(&attr.name.local_name);
+        xml.push_str("=\"");
+        xml.push_str(&escape(attr.value.as_str()));
+        xml.push('"');
+    }
+    xml.push('>');
+}
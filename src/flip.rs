@@ -0,0 +1,130 @@
+//! Flags describing how tiles are flipped, along with helpers for applying them correctly.
+
+bitflags::bitflags! {
+    /// How a tile placed in a tile layer or by a tile object is flipped, as returned by
+    /// [`LayerTileData::flip`](crate::LayerTileData::flip) and
+    /// [`ObjectTileData::flip`](crate::ObjectTileData::flip).
+    ///
+    /// Like in Tiled, the diagonal flip is applied first, followed by the horizontal and vertical
+    /// ones. Rather than applying these by hand, which is easy to get wrong when flipping
+    /// diagonally, use [`Self::apply_to_uv`] or [`Self::to_rotation_and_scale`].
+    #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Default)]
+    pub struct TileFlip: u8 {
+        /// The tile is flipped horizontally, mirroring it over its vertical axis.
+        const HORIZONTAL = 0b001;
+        /// The tile is flipped vertically, mirroring it over its horizontal axis.
+        const VERTICAL = 0b010;
+        /// The tile is flipped diagonally, mirroring it over the line from its top-left to its
+        /// bottom-right corner, which swaps its X and Y axes.
+        const DIAGONAL = 0b100;
+    }
+}
+
+impl TileFlip {
+    /// Creates the flags for the given flips, horizontal first.
+    pub fn new(flip_h: bool, flip_v: bool, flip_d: bool) -> Self {
+        let mut flip = Self::empty();
+        flip.set(Self::HORIZONTAL, flip_h);
+        flip.set(Self::VERTICAL, flip_v);
+        flip.set(Self::DIAGONAL, flip_d);
+        flip
+    }
+
+    /// Rearranges the texture coordinates of the corners of a tile's quad so that the tile is
+    /// drawn flipped.
+    ///
+    /// Both `corners` and the value returned are in top-left, top-right, bottom-right and
+    /// bottom-left order: Given the coordinates of the unflipped tile's corners within its image,
+    /// this returns the coordinates to use at each corner of the quad it is drawn on.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::TileFlip;
+    ///
+    /// let corners = [(0.0, 0.0), (1.0, 0.0), (1.0, 1.0), (0.0, 1.0)];
+    /// // A diagonal and a horizontal flip rotate the tile clockwise by 90 degrees, so the
+    /// // top-left corner of the quad shows what was the bottom-left corner of the tile.
+    /// let flip = TileFlip::DIAGONAL | TileFlip::HORIZONTAL;
+    /// assert_eq!(
+    ///     flip.apply_to_uv(corners),
+    ///     [(0.0, 1.0), (0.0, 0.0), (1.0, 0.0), (1.0, 1.0)]
+    /// );
+    /// ```
+    pub fn apply_to_uv<T: Copy>(self, corners: [T; 4]) -> [T; 4] {
+        const CORNERS: [(u8, u8); 4] = [(0, 0), (1, 0), (1, 1), (0, 1)];
+        CORNERS.map(|(x, y)| {
+            // Flipping is its own inverse, so the corner of the tile that ends up at a corner of
+            // the quad is found by undoing the flips in reverse order.
+            let y = if self.contains(Self::VERTICAL) {
+                1 - y
+            } else {
+                y
+            };
+            let x = if self.contains(Self::HORIZONTAL) {
+                1 - x
+            } else {
+                x
+            };
+            let (x, y) = if self.contains(Self::DIAGONAL) {
+                (y, x)
+            } else {
+                (x, y)
+            };
+            let index = CORNERS.iter().position(|&corner| corner == (x, y));
+            // All four corners are listed.
+            corners[index.unwrap()]
+        })
+    }
+
+    /// Returns a clockwise rotation in degrees and a scale that flip a tile when applied around
+    /// its center, scaling first, for engines that draw sprites with a rotation and a scale
+    /// instead of texture coordinates.
+    ///
+    /// The rotation is 0, 90, 180 or 270 degrees, clockwise with the Y axis pointing down like in
+    /// Tiled, and the scale is `(-1.0, 1.0)` for flips that mirror the tile and `(1.0, 1.0)`
+    /// otherwise. Tiles that aren't square take up their size with width and height swapped when
+    /// rotated by 90 or 270 degrees.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::TileFlip;
+    ///
+    /// assert_eq!(TileFlip::empty().to_rotation_and_scale(), (0.0, (1.0, 1.0)));
+    /// let flip = TileFlip::HORIZONTAL | TileFlip::VERTICAL;
+    /// assert_eq!(flip.to_rotation_and_scale(), (180.0, (1.0, 1.0)));
+    /// let flip = TileFlip::DIAGONAL | TileFlip::HORIZONTAL;
+    /// assert_eq!(flip.to_rotation_and_scale(), (90.0, (1.0, 1.0)));
+    /// assert_eq!(TileFlip::DIAGONAL.to_rotation_and_scale(), (270.0, (-1.0, 1.0)));
+    /// ```
+    pub fn to_rotation_and_scale(self) -> (f32, (f32, f32)) {
+        // Where the X axis of the tile ends up, as `(x, y)` with the Y axis pointing down.
+        let mut x_axis = if self.contains(Self::DIAGONAL) {
+            (0, 1)
+        } else {
+            (1, 0)
+        };
+        if self.contains(Self::HORIZONTAL) {
+            x_axis.0 = -x_axis.0;
+        }
+        if self.contains(Self::VERTICAL) {
+            x_axis.1 = -x_axis.1;
+        }
+
+        // Mirrored tiles are flipped horizontally before rotating them, which leaves the direction
+        // of their X axis reversed.
+        let mirrored = self.bits().count_ones() % 2 == 1;
+        let rotated_x_axis = if mirrored {
+            (-x_axis.0, -x_axis.1)
+        } else {
+            x_axis
+        };
+        let rotation = match rotated_x_axis {
+            (1, 0) => 0.0,
+            (0, 1) => 90.0,
+            (-1, 0) => 180.0,
+            _ => 270.0,
+        };
+        let scale_x = if mirrored { -1.0 } else { 1.0 };
+        (rotation, (scale_x, 1.0))
+    }
+}
@@ -1,27 +1,77 @@
 use std::path::{Path, PathBuf};
 
-use xml::attribute::OwnedAttribute;
+#[cfg(feature = "image-loading")]
+use std::io::{Cursor, Read};
 
+#[cfg(feature = "image-loading")]
+use crate::ResourceReader;
 use crate::{
+    compat,
     error::{Error, Result},
     properties::Color,
     util::*,
 };
 
-/// A reference to an image stored somewhere within the filesystem.
+/// Where the data of an [`Image`] can be found.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub struct Image {
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub enum ImageSource {
     /// The **uncanonicalized** filepath of the image, starting from the path given to load the file
-    /// this image is in. See the example for more details.
+    /// this image is in. See the example in [`Image::source`] for more details.
+    Path(PathBuf),
+    /// Image data embedded within the file itself.
     ///
-    /// ## Note
-    /// The crate does not currently support embedded images (Even though Tiled
-    /// does not allow creating maps with embedded image data, the TMX format does; [source])
+    /// Even though Tiled does not allow creating maps with embedded image data, the TMX format
+    /// does; [source]
     ///
     /// [source]: https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#image
+    Embedded {
+        /// The format of the image data, such as `png` or `gif`.
+        format: String,
+        /// The raw, already base64-decoded contents of the image.
+        data: Vec<u8>,
+    },
+}
+
+impl ImageSource {
+    /// Returns the path of the image, if it is not embedded.
+    pub fn as_path(&self) -> Option<&Path> {
+        match self {
+            ImageSource::Path(path) => Some(path),
+            ImageSource::Embedded { .. } => None,
+        }
+    }
+}
+
+/// A rectangular region of an [`Image`], in pixels.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct ImageRect {
+    /// The X coordinate of the left side of the region.
+    pub x: i32,
+    /// The Y coordinate of the top side of the region.
+    pub y: i32,
+    /// The width of the region.
+    pub width: i32,
+    /// The height of the region.
+    pub height: i32,
+}
+
+/// A reference to an image, stored either somewhere within the filesystem or embedded in the file
+/// that uses it.
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct Image {
+    /// Where the image can be found. Images stored in the filesystem have an
+    /// **uncanonicalized** filepath, starting from the path given to load the file this image is
+    /// in. See the example for more details.
     ///
     /// ## Example
-    /// ```
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
     /// use std::path::Path;
     /// use std::fs::File;
     /// use tiled::*;
@@ -42,7 +92,7 @@ pub struct Image {
     /// // Image layer has an image with the source attribute set to "../tilesheet.png"
     /// // Given the information we gave to the `parse_file` function, the image source should be
     /// // "assets/folder/../tilesheet.png". The filepath is not canonicalized.
-    /// let image_source = &image_layer.image.as_ref().unwrap().source;
+    /// let image_source = image_layer.image.as_ref().unwrap().source.as_path().unwrap();
     ///
     /// assert_eq!(
     ///     image_source,
@@ -57,38 +107,193 @@ pub struct Image {
     /// ```
     /// Check the assets/tiled_relative_paths.tmx file at the crate root to see the structure of the
     /// file this example is referring to.
-    // TODO: Embedded images
-    pub source: PathBuf,
-    /// The width in pixels of the image.
+    pub source: ImageSource,
+    /// The width in pixels of the image. 0 when the file doesn't store it, which Tiled 1.1 and
+    /// earlier as well as some other tools do for images of image layers. With the
+    /// `image-loading` feature, `Image::load_size` gets the size in that case.
     pub width: i32,
-    /// The height in pixels of the image.
+    /// The height in pixels of the image, which is 0 in the same cases as the width.
     pub height: i32,
     /// A color that should be interpreted as transparent (0 alpha), if any.
     pub transparent_colour: Option<Color>,
 }
 
 impl Image {
+    /// Parses an image, whose size is required if `size_required` is set unless the file is in a
+    /// legacy format.
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         path_relative_to: impl AsRef<Path>,
+        size_required: bool,
     ) -> Result<Image> {
-        let (c, (s, w, h)) = get_attrs!(
-            for v in attrs {
+        let ((c, s, f), (w, h)) = get_attrs!(
+            for v in attrs of ("image", parser.event_position()) {
                 Some("trans") => trans ?= v.parse(),
-                "source" => source = v,
-                "width" => width ?= v.parse::<i32>(),
-                "height" => height ?= v.parse::<i32>(),
+                Some("source") => source = v,
+                Some("format") => format = v,
+                Some("width") => width ?= v.parse::<i32>(),
+                Some("height") => height ?= v.parse::<i32>(),
             }
-            (trans, (source, width, height))
+            ((trans, source, format), (width, height))
         );
 
-        parse_tag!(parser, "image", {});
+        // Image layers of legacy files didn't store the size of their image, which is left as 0.
+        let (w, h) = if size_required {
+            (
+                compat::required_unless_legacy(parser, w, "image", "width")?,
+                compat::required_unless_legacy(parser, h, "image", "height")?,
+            )
+        } else {
+            (w, h)
+        };
+        let (w, h) = (w.unwrap_or(0), h.unwrap_or(0));
+
+        let mut data = None;
+        parse_tag!(parser, "image", {
+            "data" => |attrs: Vec<OwnedAttribute>| {
+                let encoding = get_attrs!(
+                    for v in attrs of ("data", parser.event_position()) {
+                        Some("encoding") => encoding = v,
+                    }
+                    encoding
+                );
+                if encoding.as_deref() != Some("base64") {
+                    return Err(Error::InvalidEncodingFormat { encoding, compression: None });
+                }
+                data = Some(parse_base64(parser)?);
+                Ok(())
+            },
+        });
+
+        let source = match (s, data) {
+            (Some(s), _) => ImageSource::Path(path_relative_to.as_ref().join(s)),
+            (None, Some(data)) => ImageSource::Embedded {
+                format: f.unwrap_or_default(),
+                data,
+            },
+            (None, None) => {
+                return Err(Error::MalformedAttributes(
+                    "image must have either a source or embedded data".to_owned(),
+                ))
+            }
+        };
+
         Ok(Image {
-            source: path_relative_to.as_ref().join(s),
+            source,
             width: w,
             height: h,
             transparent_colour: c,
         })
     }
+
+    /// Reads and decodes the image through the given reader, returning its pixels. Pixels of the
+    /// [transparent colour](Self::transparent_colour), if any, are made fully transparent.
+    ///
+    /// PNG, GIF, JPEG and BMP images are supported. Requires the `image-loading` feature.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{FilesystemResourceReader, Loader};
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let image = tileset.image.as_ref().unwrap();
+    /// let pixels = image.load_pixels(&mut FilesystemResourceReader::new())?;
+    ///
+    /// assert_eq!(pixels.width() as i32, image.width);
+    /// assert_eq!(pixels.height() as i32, image.height);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "image-loading")]
+    pub fn load_pixels(&self, reader: &mut impl ResourceReader) -> Result<::image::RgbaImage> {
+        let mut pixels = self
+            .image_reader(reader)?
+            .decode()
+            .map_err(Error::ImageDecodingError)?
+            .into_rgba8();
+
+        if let Some(Color {
+            red, green, blue, ..
+        }) = self.transparent_colour
+        {
+            for pixel in pixels.pixels_mut() {
+                if pixel.0[..3] == [red, green, blue] {
+                    pixel.0[3] = 0;
+                }
+            }
+        }
+        Ok(pixels)
+    }
+
+    /// Returns the width and height in pixels of the image. If the file didn't store them, they
+    /// are read from the header of the image through the given reader, without decoding it.
+    ///
+    /// Requires the `image-loading` feature.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{FilesystemResourceReader, Loader};
+    ///
+    /// // Image layers written by some tools don't store the size of their image.
+    /// let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1"
+    ///     tilewidth="32" tileheight="32">
+    ///  <imagelayer id="1" name="background">
+    ///   <image source="tilesheet.png"/>
+    ///  </imagelayer>
+    /// </map>"#;
+    /// let map = Loader::new().load_tmx_map_from(xml.as_bytes(), "assets/background.tmx")?;
+    /// let layer = map.get_layer(0).unwrap().as_image_layer().unwrap();
+    /// let image = layer.image.as_ref().unwrap();
+    ///
+    /// assert_eq!((image.width, image.height), (0, 0));
+    /// assert_eq!(image.load_size(&mut FilesystemResourceReader::new())?, (448, 192));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "image-loading")]
+    pub fn load_size(&self, reader: &mut impl ResourceReader) -> Result<(i32, i32)> {
+        if self.width != 0 || self.height != 0 {
+            return Ok((self.width, self.height));
+        }
+        let (width, height) = self
+            .image_reader(reader)?
+            .into_dimensions()
+            .map_err(Error::ImageDecodingError)?;
+        Ok((width as i32, height as i32))
+    }
+
+    /// Reads the data of the image through the given reader, ready to be decoded.
+    #[cfg(feature = "image-loading")]
+    fn image_reader(
+        &self,
+        reader: &mut impl ResourceReader,
+    ) -> Result<::image::ImageReader<Cursor<Vec<u8>>>> {
+        let (data, format) = match &self.source {
+            ImageSource::Path(path) => {
+                let loading_error = |err| Error::ResourceLoadingError {
+                    path: path.clone(),
+                    err,
+                };
+                let mut data = Vec::new();
+                reader
+                    .read_from(path)
+                    .map_err(|err| loading_error(Box::new(err)))?
+                    .read_to_end(&mut data)
+                    .map_err(|err| loading_error(Box::new(err)))?;
+                (data, None)
+            }
+            ImageSource::Embedded { format, data } => {
+                (data.clone(), ::image::ImageFormat::from_extension(format))
+            }
+        };
+        match format {
+            Some(format) => Ok(::image::ImageReader::with_format(Cursor::new(data), format)),
+            None => ::image::ImageReader::new(Cursor::new(data))
+                .with_guessed_format()
+                .map_err(|err| Error::ImageDecodingError(::image::ImageError::IoError(err))),
+        }
+    }
 }
@@ -1,31 +1,32 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
     error::Result,
-    layers::{LayerData, LayerTag},
+    layers::{LayerData, LayerId, LayerResize, LayerTag},
     properties::{parse_properties, Properties},
     util::*,
-    Error, Layer, MapTilesetGid, ResourceCache, ResourceReader, Tileset,
+    Error, ExtraXml, Gid, Layer, Map, MapTilesetGid, ObjectData, ResourceCache, ResourceReader,
+    Tileset,
 };
 
 /// The raw data of a [`GroupLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct GroupLayerData {
     layers: Vec<LayerData>,
 }
 
 impl GroupLayerData {
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         infinite: bool,
-        map_path: &Path,
         tilesets: &[MapTilesetGid],
         for_tileset: Option<Arc<Tileset>>,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
-    ) -> Result<(Self, Properties)> {
+        context: &mut LoadContext<'_, impl ResourceReader, impl ResourceCache>,
+    ) -> Result<(Self, Properties, ExtraXml)> {
         let mut properties = HashMap::new();
         let mut layers = Vec::new();
+        let mut extra = ExtraXml::default();
         parse_tag!(parser, "group", {
             "layer" => |attrs| {
                 layers.push(LayerData::new(
@@ -33,10 +34,9 @@ impl GroupLayerData {
                     attrs,
                     LayerTag::Tiles,
                     infinite,
-                    map_path,
                     tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
+                    for_tileset.as_ref().cloned(),
+                    context,
                 )?);
                 Ok(())
             },
@@ -46,10 +46,9 @@ impl GroupLayerData {
                     attrs,
                     LayerTag::Image,
                     infinite,
-                    map_path,
                     tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
+                    for_tileset.as_ref().cloned(),
+                    context,
                 )?);
                 Ok(())
             },
@@ -59,10 +58,9 @@ impl GroupLayerData {
                     attrs,
                     LayerTag::Objects,
                     infinite,
-                    map_path,
                     tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
+                    for_tileset.as_ref().cloned(),
+                    context,
                 )?);
                 Ok(())
             },
@@ -72,19 +70,96 @@ impl GroupLayerData {
                     attrs,
                     LayerTag::Group,
                     infinite,
-                    map_path,
                     tilesets,
-                    for_tileset.as_ref().cloned(),reader,
-                    cache
+                    for_tileset.as_ref().cloned(),
+                    context,
                 )?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, context.project)?;
                 Ok(())
             },
-        });
-        Ok((Self { layers }, properties))
+        } else |name, attrs| extra.read_element(parser, name, attrs));
+        Ok((Self { layers }, properties, extra))
+    }
+
+    /// Checks that the layers in this group can be part of the given map; See
+    /// [`LayerData::check_for_map`].
+    pub(crate) fn check_for_map(&self, map: &Map) -> Result<()> {
+        self.layers
+            .iter()
+            .try_for_each(|layer| layer.check_for_map(map))
+    }
+
+    /// Adds the finite tile layers in this group, including nested ones, to `layers`.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn collect_finite_tile_layers<'a>(
+        &'a mut self,
+        layers: &mut Vec<&'a mut crate::FiniteTileLayerData>,
+    ) {
+        for layer in &mut self.layers {
+            layer.collect_finite_tile_layers(layers);
+        }
+    }
+
+    /// Makes the layers in this group, including nested ones, refer to the tilesets at the new
+    /// indices given by `new_indices`, by old index.
+    pub(crate) fn remap_tilesets(&mut self, new_indices: &[Option<usize>]) {
+        for layer in &mut self.layers {
+            layer.remap_tilesets(new_indices);
+        }
+    }
+
+    /// Removes the layers in this group that don't match `keep`, keeping the groups with nested
+    /// layers that do, and returns whether any layers are left.
+    pub(crate) fn retain_layers(&mut self, keep: &impl Fn(&LayerData) -> bool) -> bool {
+        self.layers.retain_mut(|layer| layer.retain_layers(keep));
+        !self.layers.is_empty()
+    }
+
+    /// Moves the contents of the layers in this group, including nested ones, for resizing their
+    /// map.
+    pub(crate) fn resize(&mut self, resize: &LayerResize) {
+        for layer in &mut self.layers {
+            layer.resize(resize);
+        }
+    }
+
+    /// Makes the tiles of the layers in this group, including nested ones, refer to the map
+    /// tilesets with the given first GIDs, given the first GIDs of the ones they were parsed with.
+    pub(crate) fn resolve_tilesets(&mut self, parsed_with: &[Gid], first_gids: &[Gid]) {
+        for layer in &mut self.layers {
+            layer.resolve_tilesets(parsed_with, first_gids);
+        }
+    }
+
+    /// Adds the objects of the layers in this group to `objects`, including the ones of nested
+    /// layers.
+    pub(crate) fn collect_objects_mut<'a>(&'a mut self, objects: &mut Vec<&'a mut ObjectData>) {
+        for layer in &mut self.layers {
+            layer.collect_objects_mut(objects);
+        }
+    }
+
+    /// Adds the IDs of the layers in this group and of their objects, including nested ones, to
+    /// `layer_ids` and `object_ids`.
+    pub(crate) fn collect_ids(&self, layer_ids: &mut Vec<u32>, object_ids: &mut Vec<u32>) {
+        for layer in &self.layers {
+            layer.collect_ids(layer_ids, object_ids);
+        }
+    }
+
+    /// Returns the layer with the given ID in this group, including nested ones, if any.
+    pub(crate) fn find_layer_mut(&mut self, id: LayerId) -> Option<&mut LayerData> {
+        self.layers
+            .iter_mut()
+            .find_map(|layer| layer.find_layer_mut(id))
+    }
+
+    /// Returns the child layer at the given index, if it exists.
+    pub(crate) fn get_layer_data_mut(&mut self, index: usize) -> Option<&mut LayerData> {
+        self.layers.get_mut(index)
     }
 }
 
@@ -100,7 +175,8 @@ map_wrapper!(
 impl<'map> GroupLayer<'map> {
     /// Returns an iterator over the layers present in this group in display order.
     /// ## Example
-    /// ```
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
     /// use tiled::Layer;
     /// # use tiled::Loader;
     ///
@@ -135,4 +211,10 @@ impl<'map> GroupLayer<'map> {
             .get(index)
             .map(|data| Layer::new(self.map, data))
     }
+
+    /// Returns the first layer in this group with the given name, if any. Layers nested in other
+    /// groups inside this one are not searched.
+    pub fn get_layer_by_name(&self, name: &str) -> Option<Layer<'map>> {
+        self.layers().find(|layer| layer.name == name)
+    }
 }
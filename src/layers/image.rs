@@ -2,38 +2,61 @@ use std::{collections::HashMap, path::Path};
 
 use crate::{
     parse_properties,
-    util::{map_wrapper, parse_tag, XmlEventResult},
-    Error, Image, Properties, Result,
+    util::{get_attrs, map_wrapper, parse_tag, OwnedAttribute, XmlParser},
+    Error, ExtraXml, Image, Project, Properties, Result,
 };
 
 /// The raw data of an [`ImageLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ImageLayerData {
     /// The single image this layer contains, if it exists.
     pub image: Option<Image>,
+    /// Whether the image is repeated along the X axis.
+    pub repeat_x: bool,
+    /// Whether the image is repeated along the Y axis.
+    pub repeat_y: bool,
 }
 
 impl ImageLayerData {
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
+        attrs: Vec<OwnedAttribute>,
         map_path: &Path,
-    ) -> Result<(Self, Properties)> {
+        project: Option<&Project>,
+    ) -> Result<(Self, Properties, ExtraXml)> {
+        let (repeat_x, repeat_y) = get_attrs!(
+            for v in attrs of ("imagelayer", parser.event_position()) {
+                Some("repeatx") => repeat_x = v == "1",
+                Some("repeaty") => repeat_y = v == "1",
+            }
+            (repeat_x, repeat_y)
+        );
         let mut image: Option<Image> = None;
         let mut properties = HashMap::new();
+        let mut extra = ExtraXml::default();
 
         let path_relative_to = map_path.parent().ok_or(Error::PathIsNotFile)?;
 
         parse_tag!(parser, "imagelayer", {
             "image" => |attrs| {
-                image = Some(Image::new(parser, attrs, path_relative_to)?);
+                image = Some(Image::new(parser, attrs, path_relative_to, false)?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project)?;
                 Ok(())
             },
-        });
-        Ok((ImageLayerData { image }, properties))
+        } else |name, attrs| extra.read_element(parser, name, attrs));
+        Ok((
+            ImageLayerData {
+                image,
+                repeat_x: repeat_x.unwrap_or(false),
+                repeat_y: repeat_y.unwrap_or(false),
+            },
+            properties,
+            extra,
+        ))
     }
 }
 
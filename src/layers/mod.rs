@@ -1,10 +1,8 @@
-use std::{path::Path, sync::Arc};
-
-use xml::attribute::OwnedAttribute;
+use std::{collections::HashSet, sync::Arc};
 
 use crate::{
-    error::Result, properties::Properties, util::*, Color, Map, MapTilesetGid, ResourceCache,
-    ResourceReader, Tileset,
+    compat, error::Result, map::MapTilesets, properties::Properties, util::*, Color, ExtraXml, Gid,
+    Map, MapTilesetGid, ObjectData, Rect, ResourceCache, ResourceReader, Tileset,
 };
 
 mod image;
@@ -15,8 +13,13 @@ mod tile;
 pub use tile::*;
 mod group;
 pub use group::*;
+mod query;
+pub use query::*;
+mod shared;
+pub use shared::*;
 
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum LayerDataType {
     Tiles(TileLayerData),
     Objects(ObjectLayerData),
@@ -32,8 +35,106 @@ pub(crate) enum LayerTag {
     Group,
 }
 
+impl LayerTag {
+    /// The name of the XML element layers of this kind are stored in.
+    fn name(self) -> &'static str {
+        match self {
+            LayerTag::Tiles => "layer",
+            LayerTag::Objects => "objectgroup",
+            LayerTag::Image => "imagelayer",
+            LayerTag::Group => "group",
+        }
+    }
+
+    /// The attributes of layers of this kind that are read into [`LayerData`] or its contents.
+    fn attribute_names(self) -> &'static [&'static str] {
+        match self {
+            LayerTag::Tiles => &[
+                "id",
+                "name",
+                "type",
+                "class",
+                "opacity",
+                "visible",
+                "tintcolor",
+                "offsetx",
+                "offsety",
+                "parallaxx",
+                "parallaxy",
+                "width",
+                "height",
+            ],
+            LayerTag::Objects => &[
+                "id",
+                "name",
+                "type",
+                "class",
+                "opacity",
+                "visible",
+                "tintcolor",
+                "offsetx",
+                "offsety",
+                "parallaxx",
+                "parallaxy",
+                "color",
+                "draworder",
+            ],
+            LayerTag::Image => &[
+                "id",
+                "name",
+                "type",
+                "class",
+                "opacity",
+                "visible",
+                "tintcolor",
+                "offsetx",
+                "offsety",
+                "parallaxx",
+                "parallaxy",
+                "repeatx",
+                "repeaty",
+            ],
+            LayerTag::Group => &[
+                "id",
+                "name",
+                "type",
+                "class",
+                "opacity",
+                "visible",
+                "tintcolor",
+                "offsetx",
+                "offsety",
+                "parallaxx",
+                "parallaxy",
+            ],
+        }
+    }
+}
+
+/// How the contents of the layers of a map are moved when it is resized; See
+/// [`Map::resize`](crate::Map::resize).
+pub(crate) struct LayerResize {
+    /// The new width of the map, in tiles.
+    pub(crate) width: u32,
+    /// The new height of the map, in tiles.
+    pub(crate) height: u32,
+    /// How many tiles the tiles of tile layers are moved by.
+    pub(crate) tile_offset: (i64, i64),
+    /// How many pixels objects and image layers are moved by, in the coordinates of objects.
+    pub(crate) pixel_offset: (f32, f32),
+    /// The addresses of the objects that end up outside of the map, which are removed.
+    pub(crate) removed_objects: HashSet<usize>,
+}
+
+/// The ID of a layer, which is unique within its map and, unlike its index, stays the same as the
+/// map is changed; See [`Map::layer_by_id`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayerId(pub u32);
+
 /// The raw data of a [`Layer`]. Does not include a reference to its parent [`Map`](crate::Map).
 #[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct LayerData {
     /// The layer's name, set arbitrarily by the user.
     pub name: String,
@@ -56,6 +157,10 @@ pub struct LayerData {
     pub properties: Properties,
     /// The layer's type, which is arbitrarily setby the user.
     pub user_type: Option<String>,
+    /// The attributes and child elements of the layer element that aren't read into any other
+    /// field, if the map was loaded with
+    /// [`LoaderOptions::preserve_unknown_xml`](crate::LoaderOptions::preserve_unknown_xml).
+    pub extra: ExtraXml,
     layer_type: LayerDataType,
 }
 
@@ -63,21 +168,28 @@ impl LayerData {
     /// Get the layer's id. Unique within the parent map. Valid only if greater than 0. Defaults to
     /// 0 if the layer was loaded from a file that didn't have the attribute present.
     #[inline]
-    pub fn id(&self) -> u32 {
-        self.id
+    pub fn id(&self) -> LayerId {
+        LayerId(self.id)
+    }
+
+    #[inline]
+    pub(crate) fn set_id(&mut self, id: u32) {
+        self.id = id;
     }
 
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
-        attrs: Vec<OwnedAttribute>,
+        parser: &mut impl XmlParser,
+        mut attrs: Vec<OwnedAttribute>,
         tag: LayerTag,
         infinite: bool,
-        map_path: &Path,
         tilesets: &[MapTilesetGid],
         for_tileset: Option<Arc<Tileset>>,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        context: &mut LoadContext<'_, impl ResourceReader, impl ResourceCache>,
     ) -> Result<Self> {
+        parser.count_layer()?;
+        if parser.legacy_format() {
+            compat::normalize_layer_attributes(tag.name(), &mut attrs);
+        }
         let (
             opacity,
             tint_color,
@@ -91,7 +203,7 @@ impl LayerData {
             user_type,
             user_class,
         ) = get_attrs!(
-            for v in attrs {
+            for v in attrs of (tag.name(), parser.event_position()) {
                 Some("opacity") => opacity ?= v.parse(),
                 Some("tintcolor") => tint_color ?= v.parse(),
                 Some("visible") => visible ?= v.parse().map(|x:i32| x == 1),
@@ -106,41 +218,41 @@ impl LayerData {
             }
             (opacity, tint_color, visible, offset_x, offset_y, parallax_x, parallax_y, name, id, user_type, user_class)
         );
+        let attributes =
+            ExtraXml::from_attributes(parser, &attrs, tag.attribute_names()).attributes;
 
-        let (ty, properties) = match tag {
+        let map_path = parser.path().to_owned();
+        let (ty, properties, mut extra) = match tag {
             LayerTag::Tiles => {
-                let (ty, properties) = TileLayerData::new(parser, attrs, infinite, tilesets)?;
-                (LayerDataType::Tiles(ty), properties)
+                let (ty, properties, extra) =
+                    TileLayerData::new(parser, attrs, infinite, tilesets, context.project)?;
+                (LayerDataType::Tiles(ty), properties, extra)
             }
             LayerTag::Objects => {
-                let (ty, properties) = ObjectLayerData::new(
+                let (ty, properties, extra) = ObjectLayerData::new(
                     parser,
                     attrs,
                     Some(tilesets),
                     for_tileset,
                     map_path.parent().ok_or(crate::Error::PathIsNotFile)?,
-                    reader,
-                    cache,
+                    context,
                 )?;
-                (LayerDataType::Objects(ty), properties)
+                (LayerDataType::Objects(ty), properties, extra)
             }
             LayerTag::Image => {
-                let (ty, properties) = ImageLayerData::new(parser, map_path)?;
-                (LayerDataType::Image(ty), properties)
+                let (ty, properties, extra) =
+                    ImageLayerData::new(parser, attrs, &map_path, context.project)?;
+                (LayerDataType::Image(ty), properties, extra)
             }
             LayerTag::Group => {
-                let (ty, properties) = GroupLayerData::new(
-                    parser,
-                    infinite,
-                    map_path,
-                    tilesets,
-                    for_tileset,
-                    reader,
-                    cache,
-                )?;
-                (LayerDataType::Group(ty), properties)
+                parser.enter_nested()?;
+                let group = GroupLayerData::new(parser, infinite, tilesets, for_tileset, context);
+                parser.leave_nested();
+                let (ty, properties, extra) = group?;
+                (LayerDataType::Group(ty), properties, extra)
             }
         };
+        extra.attributes = attributes;
 
         Ok(Self {
             visible: visible.unwrap_or(true),
@@ -154,11 +266,152 @@ impl LayerData {
             id: id.unwrap_or(0),
             user_type: user_type.or(user_class),
             properties,
+            extra,
             layer_type: ty,
         })
     }
 }
 
+impl LayerData {
+    /// Creates a layer with the given contents, with all other attributes set to their defaults.
+    pub(crate) fn with_type(
+        id: u32,
+        name: String,
+        properties: Properties,
+        layer_type: LayerDataType,
+    ) -> Self {
+        Self {
+            name,
+            id,
+            visible: true,
+            offset_x: 0.0,
+            offset_y: 0.0,
+            parallax_x: 1.0,
+            parallax_y: 1.0,
+            opacity: 1.0,
+            tint_color: None,
+            properties,
+            user_type: None,
+            extra: ExtraXml::default(),
+            layer_type,
+        }
+    }
+
+    /// Checks that this layer can be part of the given map: Tile layers must match its infinite
+    /// setting and, if finite, its size, and tiles and tile objects must only reference its
+    /// tilesets.
+    pub(crate) fn check_for_map(&self, map: &Map) -> Result<()> {
+        match &self.layer_type {
+            LayerDataType::Tiles(data) => data.check_for_map(map),
+            LayerDataType::Objects(data) => data.check_for_map(map),
+            LayerDataType::Image(_) => Ok(()),
+            LayerDataType::Group(data) => data.check_for_map(map),
+        }
+    }
+
+    /// Adds this layer to `layers` if it is a finite tile layer, or its finite tile layers if it
+    /// is a group.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn collect_finite_tile_layers<'a>(
+        &'a mut self,
+        layers: &mut Vec<&'a mut FiniteTileLayerData>,
+    ) {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(TileLayerData::Finite(data)) => layers.push(data),
+            LayerDataType::Group(data) => data.collect_finite_tile_layers(layers),
+            _ => {}
+        }
+    }
+
+    /// Makes the tiles and tile objects of this layer or of its nested layers refer to the
+    /// tilesets at the new indices given by `new_indices`, by old index. Tiles from tilesets
+    /// without a new index are removed.
+    pub(crate) fn remap_tilesets(&mut self, new_indices: &[Option<usize>]) {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(data) => data.remap_tilesets(new_indices),
+            LayerDataType::Objects(data) => data.remap_tilesets(new_indices),
+            LayerDataType::Image(_) => {}
+            LayerDataType::Group(data) => data.remap_tilesets(new_indices),
+        }
+    }
+
+    /// Removes the layers nested in this one that don't match `keep`, unless this layer matches
+    /// it itself, and returns whether any of this layer is left.
+    pub(crate) fn retain_layers(&mut self, keep: &impl Fn(&LayerData) -> bool) -> bool {
+        if keep(self) {
+            return true;
+        }
+        match &mut self.layer_type {
+            LayerDataType::Group(data) => data.retain_layers(keep),
+            _ => false,
+        }
+    }
+
+    /// Moves the contents of this layer, or of its nested layers, for resizing its map. Infinite
+    /// tile layers become finite.
+    pub(crate) fn resize(&mut self, resize: &LayerResize) {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(data) => {
+                data.resize(resize.width, resize.height, resize.tile_offset)
+            }
+            LayerDataType::Objects(data) => {
+                data.resize(resize.pixel_offset, &resize.removed_objects)
+            }
+            LayerDataType::Image(_) => {
+                self.offset_x += resize.pixel_offset.0;
+                self.offset_y += resize.pixel_offset.1;
+            }
+            LayerDataType::Group(data) => data.resize(resize),
+        }
+    }
+
+    /// Makes the tiles of this layer, or of its objects or nested layers, refer to the map
+    /// tilesets with the given first GIDs, given the first GIDs of the ones it was parsed with.
+    /// This is needed when tilesets are declared after the layer in its map file.
+    pub(crate) fn resolve_tilesets(&mut self, parsed_with: &[Gid], first_gids: &[Gid]) {
+        match &mut self.layer_type {
+            LayerDataType::Tiles(data) => data.resolve_tilesets(first_gids),
+            LayerDataType::Objects(data) => data.resolve_tilesets(parsed_with, first_gids),
+            LayerDataType::Image(_) => {}
+            LayerDataType::Group(data) => data.resolve_tilesets(parsed_with, first_gids),
+        }
+    }
+
+    /// Returns this layer or the nested layer with the given ID, if any.
+    pub(crate) fn find_layer_mut(&mut self, id: LayerId) -> Option<&mut LayerData> {
+        if self.id == id.0 {
+            return Some(self);
+        }
+        match &mut self.layer_type {
+            LayerDataType::Group(data) => data.find_layer_mut(id),
+            _ => None,
+        }
+    }
+
+    /// Adds the objects of this layer to `objects`, including the ones of nested layers if it is a
+    /// group.
+    pub(crate) fn collect_objects_mut<'a>(&'a mut self, objects: &mut Vec<&'a mut ObjectData>) {
+        match &mut self.layer_type {
+            LayerDataType::Objects(data) => objects.extend(data.object_data_mut()),
+            LayerDataType::Group(data) => data.collect_objects_mut(objects),
+            _ => {}
+        }
+    }
+
+    /// Adds the ID of this layer to `layer_ids` along with the ones of its objects to
+    /// `object_ids`, including the ones of nested layers if it is a group.
+    pub(crate) fn collect_ids(&self, layer_ids: &mut Vec<u32>, object_ids: &mut Vec<u32>) {
+        layer_ids.push(self.id);
+        match &self.layer_type {
+            LayerDataType::Objects(data) => {
+                object_ids.extend(data.object_data().iter().map(|object| object.id().0))
+            }
+            LayerDataType::Group(data) => data.collect_ids(layer_ids, object_ids),
+            _ => {}
+        }
+    }
+}
+
 map_wrapper!(
     #[doc = "A generic map layer, accessed via [`Map::layers()`]."]
     Layer => LayerData
@@ -238,6 +491,425 @@ impl<'map> Layer<'map> {
             _ => None,
         }
     }
+
+    /// Returns the group layer this layer is in, or [`None`] if it is one of the top-level layers
+    /// of its map.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+    /// let layer = map.layer_at_path("group-2/group-3/tile-3").unwrap();
+    /// let parent = layer.parent().unwrap();
+    /// assert_eq!(parent.name, "group-3");
+    /// assert_eq!(parent.parent().unwrap().name, "group-2");
+    /// assert!(parent.parent().unwrap().parent().is_none());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn parent(&self) -> Option<Layer<'map>> {
+        self.map.parent_of_layer(self.data)
+    }
+
+    /// Returns the tint color this layer effectively has after multiplying it with the ones of its
+    /// parent group layers, like Tiled does when rendering it. Returns [`None`] if neither the
+    /// layer nor any of its parent groups are tinted.
+    ///
+    /// When going through all of the layers of a map, [`Map::flattened_layers`] computes this
+    /// along the way.
+    pub fn effective_tint_color(&self) -> Option<Color> {
+        let mut tint = self.tint_color;
+        let mut layer = *self;
+        while let Some(parent) = layer.parent() {
+            tint = combine_tints(parent.tint_color, tint);
+            layer = parent;
+        }
+        tint
+    }
+
+    /// Returns the area covered by the contents of this layer in pixels, moved by the offsets of
+    /// the layer and of its parent group layers, or [`None`] if the layer has no contents. This can
+    /// be used to skip drawing layers outside of the view or to keep a camera within them.
+    ///
+    /// - Finite tile layers cover the bounding boxes of all of their tiles, like
+    ///   [`Map::bounds`] does for the map.
+    /// - Infinite tile layers cover the bounding boxes of the tiles of their chunks that aren't
+    ///   empty; See [`InfiniteTileLayerData::bounds`].
+    /// - Image layers cover a single copy of their image, even if it is repeated.
+    /// - Object layers cover the bounds of their objects, placed like [`Object::transform`]
+    ///   does.
+    /// - Group layers cover the areas of the layers in them.
+    ///
+    /// Parallax factors aren't taken into account, since they depend on the view.
+    ///
+    /// ## Note
+    /// Tile images larger than the map's tiles extend above the bounding boxes of their tiles. In
+    /// isometric maps, object positions are in a projected space that is not the one the map is
+    /// drawn in, so the area of object layers is in that space as well.
+    ///
+    /// [`Object::transform`]: crate::Object::transform
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Rect;
+    ///
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")?;
+    /// // The chunks of this layer go from tile (-16, 0) to (31, 47), and tiles are 32x32 pixels.
+    /// let bounds = map.get_layer(0).unwrap().pixel_bounds().unwrap();
+    /// assert_eq!(
+    ///     bounds,
+    ///     Rect {
+    ///         x: -512.0,
+    ///         y: 0.0,
+    ///         width: 1536.0,
+    ///         height: 1536.0,
+    ///     }
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn pixel_bounds(&self) -> Option<Rect> {
+        let (mut offset_x, mut offset_y) = (self.offset_x, self.offset_y);
+        let mut layer = *self;
+        while let Some(parent) = layer.parent() {
+            offset_x += parent.offset_x;
+            offset_y += parent.offset_y;
+            layer = parent;
+        }
+        self.content_bounds(offset_x, offset_y)
+    }
+
+    /// Returns an owned copy of this layer's data along with the tilesets of its map, which can be
+    /// stored without borrowing the map, such as in components of an ECS.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, OwnedLayer};
+    ///
+    /// let owned: OwnedLayer = {
+    ///     let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    ///     map.get_layer(0).unwrap().to_owned_data()
+    /// };
+    /// let tile = owned.get_tile(0, 0).unwrap();
+    /// assert_eq!(tile.tileset.name, "tilesheet");
+    /// assert_eq!(owned.name, "Tile Layer 1");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_owned_data(&self) -> OwnedLayer {
+        let tilesets = (0..self.map.tileset_first_gids().len())
+            .map(|index| self.map.tileset(index).cloned())
+            .collect();
+        OwnedLayer::new(self.data.clone(), tilesets)
+    }
+
+    /// Returns the area covered by the contents of this layer in pixels, moved by the given
+    /// offsets.
+    fn content_bounds(&self, offset_x: f32, offset_y: f32) -> Option<Rect> {
+        let bounds = match self.layer_type() {
+            LayerType::Tiles(TileLayer::Finite(layer)) => {
+                let (width, height) = (layer.width() as i32, layer.height() as i32);
+                if width <= 0 || height <= 0 {
+                    return None;
+                }
+                self.map.tile_range_bounds((0, 0), (width - 1, height - 1))
+            }
+            LayerType::Tiles(TileLayer::Infinite(layer)) => {
+                let (min, max) = layer.bounds()?;
+                self.map.tile_range_bounds(min, max)
+            }
+            LayerType::Image(layer) => {
+                let image = layer.image.as_ref()?;
+                Rect {
+                    x: 0.0,
+                    y: 0.0,
+                    width: image.width as f32,
+                    height: image.height as f32,
+                }
+            }
+            LayerType::Objects(layer) => {
+                Rect::enclosing_rects(layer.objects().map(|object| object.placed_bounds()))?
+            }
+            LayerType::Group(group) => {
+                return Rect::enclosing_rects(group.layers().filter_map(|layer| {
+                    layer.content_bounds(offset_x + layer.offset_x, offset_y + layer.offset_y)
+                }));
+            }
+        };
+        Some(Rect {
+            x: bounds.x + offset_x,
+            y: bounds.y + offset_y,
+            ..bounds
+        })
+    }
+}
+
+/// A map layer that owns its data and keeps the tilesets of its map alive, so that it doesn't
+/// need to borrow the map; See [`Layer::to_owned_data`].
+///
+/// Dereferences into the layer's [`LayerData`], and gives out tiles as [`OwnedLayerTile`]s.
+#[derive(Clone, PartialEq, Debug)]
+pub struct OwnedLayer {
+    data: LayerData,
+    /// The tilesets of the map this layer is from, by index, if they were loaded.
+    tilesets: Vec<Option<Arc<Tileset>>>,
+}
+
+impl OwnedLayer {
+    #[inline]
+    pub(crate) fn new(data: LayerData, tilesets: Vec<Option<Arc<Tileset>>>) -> Self {
+        Self { data, tilesets }
+    }
+
+    /// Returns the tileset at the given index in the map this layer is from, if the map had one
+    /// there and it was loaded; See [`Map::tileset`].
+    #[inline]
+    pub fn tileset(&self, index: usize) -> Option<&Arc<Tileset>> {
+        self.tilesets.get(index)?.as_ref()
+    }
+
+    /// Creates the owned tile for the given tile data, if its tileset was loaded.
+    fn owned_tile(&self, tile: LayerTileData) -> Option<OwnedLayerTile> {
+        let tileset = self.tileset(tile.tileset_index())?;
+        Some(OwnedLayerTile::new(tile, tileset.clone()))
+    }
+
+    /// Consumes this layer, returning its data.
+    #[inline]
+    pub fn into_data(self) -> LayerData {
+        self.data
+    }
+
+    /// Obtains the tile present at the position given, if this is a tile layer.
+    ///
+    /// If the layer isn't a tile layer, the position given is invalid, the position is empty or
+    /// the tile's tileset wasn't loaded, this function will return [`None`].
+    pub fn get_tile(&self, x: i32, y: i32) -> Option<OwnedLayerTile> {
+        match &self.data.layer_type {
+            LayerDataType::Tiles(data) => self.owned_tile(data.get_tile_data(x, y)?),
+            _ => None,
+        }
+    }
+
+    /// Returns an iterator over the positions and tiles of all of the tiles present in this layer,
+    /// which is empty if it isn't a tile layer. Tiles whose tileset wasn't loaded are skipped.
+    pub fn tiles(&self) -> impl Iterator<Item = ((i32, i32), OwnedLayerTile)> + '_ {
+        let tiles = match &self.data.layer_type {
+            LayerDataType::Tiles(data) => Some(data.tile_data()),
+            _ => None,
+        };
+        tiles
+            .into_iter()
+            .flatten()
+            .filter_map(move |(pos, tile)| Some((pos, self.owned_tile(tile)?)))
+    }
+}
+
+impl std::ops::Deref for OwnedLayer {
+    type Target = LayerData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+/// A mutable reference to a map layer, accessed via [`Map::get_layer_mut()`].
+///
+/// Its settings can be changed through the fields of [`LayerData`], while the tiles and objects
+/// of the layer are changed through [`LayerMut::as_finite_tile_layer_mut`],
+/// [`LayerMut::as_infinite_tile_layer_mut`] and [`LayerMut::as_object_layer_mut`], which check
+/// them against the tilesets of the map.
+#[derive(Debug)]
+pub struct LayerMut<'map> {
+    tilesets: MapTilesets<'map>,
+    next_object_id: &'map mut u32,
+    data: &'map mut LayerData,
+}
+
+impl<'map> LayerMut<'map> {
+    #[inline]
+    pub(crate) fn new(
+        tilesets: MapTilesets<'map>,
+        next_object_id: &'map mut u32,
+        data: &'map mut LayerData,
+    ) -> Self {
+        Self {
+            tilesets,
+            next_object_id,
+            data,
+        }
+    }
+
+    /// Returns this layer as a finite tile layer whose tiles can be changed, only if it is one.
+    pub fn as_finite_tile_layer_mut(self) -> Option<FiniteTileLayerDataMut<'map>> {
+        let Self { tilesets, data, .. } = self;
+        match &mut data.layer_type {
+            LayerDataType::Tiles(TileLayerData::Finite(data)) => {
+                Some(FiniteTileLayerDataMut::new(tilesets, data))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns this layer as an infinite tile layer whose tiles can be changed, only if it is one.
+    pub fn as_infinite_tile_layer_mut(self) -> Option<InfiniteTileLayerDataMut<'map>> {
+        let Self { tilesets, data, .. } = self;
+        match &mut data.layer_type {
+            LayerDataType::Tiles(TileLayerData::Infinite(data)) => {
+                Some(InfiniteTileLayerDataMut::new(tilesets, data))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns this layer as an object layer whose objects can be changed, only if it is one.
+    pub fn as_object_layer_mut(self) -> Option<ObjectLayerDataMut<'map>> {
+        let Self {
+            tilesets,
+            next_object_id,
+            data,
+        } = self;
+        match &mut data.layer_type {
+            LayerDataType::Objects(data) => {
+                Some(ObjectLayerDataMut::new(tilesets, next_object_id, data))
+            }
+            _ => None,
+        }
+    }
+
+    /// Returns the child layer with the given index if this is a group layer and the child
+    /// exists.
+    pub fn get_layer_mut(&mut self, index: usize) -> Option<LayerMut<'_>> {
+        let tilesets = self.tilesets;
+        let next_object_id = &mut *self.next_object_id;
+        match &mut self.data.layer_type {
+            LayerDataType::Group(data) => data
+                .get_layer_data_mut(index)
+                .map(move |data| LayerMut::new(tilesets, next_object_id, data)),
+            _ => None,
+        }
+    }
+}
+
+impl<'map> std::ops::Deref for LayerMut<'map> {
+    type Target = LayerData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+impl<'map> std::ops::DerefMut for LayerMut<'map> {
+    #[inline]
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        self.data
+    }
+}
+
+/// A non-group layer along with the values it effectively ends up with after taking all of its
+/// parent group layers into account, accessed via
+/// [`Map::flattened_layers()`](crate::Map::flattened_layers).
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct FlattenedLayer<'map> {
+    /// The layer itself.
+    pub layer: Layer<'map>,
+    /// The layer's x offset (in pixels), added to the ones of its parent groups.
+    pub offset_x: f32,
+    /// The layer's y offset (in pixels), added to the ones of its parent groups.
+    pub offset_y: f32,
+    /// The layer's opacity, multiplied by the ones of its parent groups.
+    pub opacity: f32,
+    /// The layer's x parallax factor, multiplied by the ones of its parent groups.
+    pub parallax_x: f32,
+    /// The layer's y parallax factor, multiplied by the ones of its parent groups.
+    pub parallax_y: f32,
+    /// The layer's tint color, multiplied by the ones of its parent groups. [`None`] if neither
+    /// the layer nor any of its parent groups are tinted.
+    pub tint_color: Option<Color>,
+    /// Whether the layer and all of its parent groups are visible.
+    pub visible: bool,
+}
+
+impl<'map> FlattenedLayer<'map> {
+    pub(crate) fn new(layer: Layer<'map>, parent: Option<&FlattenedLayer<'map>>) -> Self {
+        let flattened = Self {
+            layer,
+            offset_x: layer.offset_x,
+            offset_y: layer.offset_y,
+            opacity: layer.opacity,
+            parallax_x: layer.parallax_x,
+            parallax_y: layer.parallax_y,
+            tint_color: layer.tint_color,
+            visible: layer.visible,
+        };
+        match parent {
+            Some(parent) => Self {
+                offset_x: parent.offset_x + flattened.offset_x,
+                offset_y: parent.offset_y + flattened.offset_y,
+                opacity: parent.opacity * flattened.opacity,
+                parallax_x: parent.parallax_x * flattened.parallax_x,
+                parallax_y: parent.parallax_y * flattened.parallax_y,
+                tint_color: combine_tints(parent.tint_color, flattened.tint_color),
+                visible: parent.visible && flattened.visible,
+                ..flattened
+            },
+            None => flattened,
+        }
+    }
+
+    /// Returns the offset in pixels to draw this layer at when the center of the view is at the
+    /// given position in the map, which is its [offset](Self::offset_x) shifted by its
+    /// [parallax factor](Self::parallax_x) relative to the map's
+    /// [parallax origin](crate::Map::parallax_origin_x).
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+    /// let (view_width, view_height) = (640.0, 480.0);
+    /// let (camera_x, camera_y) = (100.0, 50.0);
+    /// for layer in map.flattened_layers() {
+    ///     let (x, y) = layer.view_offset(camera_x + view_width / 2.0, camera_y + view_height / 2.0);
+    ///     println!("{} at ({}, {})", layer.layer.name, x, y);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn view_offset(&self, view_center_x: f32, view_center_y: f32) -> (f32, f32) {
+        let map = self.layer.map();
+        (
+            self.offset_x + (view_center_x - map.parallax_origin_x) * (1.0 - self.parallax_x),
+            self.offset_y + (view_center_y - map.parallax_origin_y) * (1.0 - self.parallax_y),
+        )
+    }
+}
+
+/// Combines the tint color of a group with the one of a layer inside of it, either of which may be
+/// missing.
+fn combine_tints(parent: Option<Color>, child: Option<Color>) -> Option<Color> {
+    match (parent, child) {
+        (Some(a), Some(b)) => Some(multiply_colors(a, b)),
+        (a, b) => a.or(b),
+    }
+}
+
+/// Multiplies each of the channels of two colors, like Tiled does when combining tint colors.
+fn multiply_colors(a: Color, b: Color) -> Color {
+    let multiply = |a: u8, b: u8| ((a as u32 * b as u32 + 127) / 255) as u8;
+    Color {
+        alpha: multiply(a.alpha, b.alpha),
+        red: multiply(a.red, b.red),
+        green: multiply(a.green, b.green),
+        blue: multiply(a.blue, b.blue),
+    }
 }
 
 /// Represents some kind of map layer.
@@ -1,54 +1,112 @@
-use std::{collections::HashMap, path::Path, sync::Arc};
-
-use xml::attribute::OwnedAttribute;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::Path,
+    str::FromStr,
+    sync::Arc,
+};
 
 use crate::{
+    layers::{LayerData, LayerDataType},
+    map::MapTilesets,
     parse_properties,
-    util::{get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Color, Error, MapTilesetGid, Object, ObjectData, Properties, ResourceCache, ResourceReader,
-    Result, Tileset,
+    util::{get_attrs, map_wrapper, parse_tag, LoadContext, OwnedAttribute, XmlParser},
+    Color, Error, ExtraXml, Gid, Map, MapTilesetGid, Object, ObjectData, ObjectDataBuilder,
+    ObjectId, Properties, ResourceCache, ResourceReader, Result, Tileset, TilesetLocation,
 };
 
 /// Raw data referring to a map object layer or tile collision data.
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ObjectLayerData {
     objects: Vec<ObjectData>,
     /// The color used in the editor to display objects in this layer.
     pub colour: Option<Color>,
+    /// The order in which the objects in this layer are drawn; See
+    /// [`ObjectLayer::objects_in_draw_order`].
+    pub draw_order: DrawOrder,
+}
+
+/// The order in which the objects of an object layer are drawn.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub enum DrawOrder {
+    /// Objects are drawn from top to bottom, sorted by their Y coordinate.
+    #[default]
+    TopDown,
+    /// Objects are drawn in the order they are stored in, which can be changed manually in Tiled.
+    Index,
+}
+
+#[derive(Debug)]
+/// An error arising from trying to parse a [`DrawOrder`] that is not valid.
+pub struct DrawOrderError {
+    /// The invalid string found.
+    pub str_found: String,
+}
+
+impl std::fmt::Display for DrawOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "failed to parse draw order, valid options are `topdown` and `index` \
+        but got `{}` instead",
+            self.str_found
+        ))
+    }
+}
+
+impl FromStr for DrawOrder {
+    type Err = DrawOrderError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "topdown" => Ok(DrawOrder::TopDown),
+            "index" => Ok(DrawOrder::Index),
+            _ => Err(DrawOrderError {
+                str_found: s.to_owned(),
+            }),
+        }
+    }
 }
 
 impl ObjectLayerData {
     /// If it is known that there are no objects with tile images in it (i.e. collision data)
     /// then we can pass in [`None`] as the tilesets
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         tilesets: Option<&[MapTilesetGid]>,
         for_tileset: Option<Arc<Tileset>>,
         // path_relative_to is a directory to which all other files are relative to
         path_relative_to: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
-    ) -> Result<(ObjectLayerData, Properties)> {
-        let c = get_attrs!(
-            for v in attrs {
+        context: &mut LoadContext<'_, impl ResourceReader, impl ResourceCache>,
+    ) -> Result<(ObjectLayerData, Properties, ExtraXml)> {
+        let (c, draw_order) = get_attrs!(
+            for v in attrs of ("objectgroup", parser.event_position()) {
                 Some("color") => color ?= v.parse(),
+                Some("draworder") => draw_order ?= v.parse::<DrawOrder>(),
             }
-            color
+            (color, draw_order)
         );
         let mut objects = Vec::new();
         let mut properties = HashMap::new();
+        let mut extra = ExtraXml::default();
         parse_tag!(parser, "objectgroup", {
             "object" => |attrs| {
-                objects.push(ObjectData::new(parser, attrs, tilesets, for_tileset.as_ref().cloned(), path_relative_to, reader, cache)?);
+                objects.push(ObjectData::new(parser, attrs, tilesets, for_tileset.as_ref().cloned(), path_relative_to, context)?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, context.project)?;
                 Ok(())
             },
-        });
-        Ok((ObjectLayerData { objects, colour: c }, properties))
+        } else |name, attrs| extra.read_element(parser, name, attrs));
+        let data = ObjectLayerData {
+            objects,
+            colour: c,
+            draw_order: draw_order.unwrap_or_default(),
+        };
+        Ok((data, properties, extra))
     }
 
     /// Returns the data belonging to the objects contained within the layer, in the order they were
@@ -57,6 +115,266 @@ impl ObjectLayerData {
     pub fn object_data(&self) -> &[ObjectData] {
         self.objects.as_ref()
     }
+
+    /// Returns the data of the objects contained within the layer, for changing them in place.
+    #[inline]
+    pub(crate) fn object_data_mut(&mut self) -> &mut [ObjectData] {
+        &mut self.objects
+    }
+
+    /// Creates the data of a layer with the given objects, such as the collision shapes of a tile,
+    /// drawn in the order they are given in.
+    pub(crate) fn from_objects(objects: Vec<ObjectData>) -> Self {
+        Self {
+            objects,
+            colour: None,
+            draw_order: DrawOrder::Index,
+        }
+    }
+
+    /// Moves the objects in this layer by the given amount of pixels for resizing their map,
+    /// removing the ones with the given addresses.
+    pub(crate) fn resize(&mut self, (dx, dy): (f32, f32), removed: &HashSet<usize>) {
+        let removed: Vec<bool> = self
+            .objects
+            .iter()
+            .map(|object| removed.contains(&(object as *const ObjectData as usize)))
+            .collect();
+        let mut removed = removed.into_iter();
+        self.objects.retain(|_| !removed.next().unwrap_or(false));
+        for object in &mut self.objects {
+            object.translate(dx, dy);
+        }
+    }
+
+    /// Makes the tile objects in this layer refer to the tilesets at the new indices given by
+    /// `new_indices`, by old index.
+    pub(crate) fn remap_tilesets(&mut self, new_indices: &[Option<usize>]) {
+        for object in &mut self.objects {
+            object.remap_tilesets(new_indices);
+        }
+    }
+
+    /// Makes the tile objects in this layer refer to the map tilesets with the given first GIDs,
+    /// given the first GIDs of the ones they were parsed with.
+    pub(crate) fn resolve_tilesets(&mut self, parsed_with: &[Gid], first_gids: &[Gid]) {
+        for object in &mut self.objects {
+            object.resolve_tilesets(parsed_with, first_gids);
+        }
+    }
+
+    /// Checks that the tile objects in this layer only reference tilesets of the given map.
+    pub(crate) fn check_for_map(&self, map: &Map) -> Result<()> {
+        let tileset_count = map.tilesets().len();
+        let invalid_index = self
+            .objects
+            .iter()
+            .filter_map(|object| object.tile_data())
+            .find_map(|tile| match tile.tileset_location() {
+                TilesetLocation::Map(index) if *index >= tileset_count => Some(*index),
+                _ => None,
+            });
+        match invalid_index {
+            Some(index) => Err(Error::InvalidMapData {
+                description: format!(
+                    "object references tileset index {}, but the map only has {} tilesets",
+                    index, tileset_count
+                ),
+            }),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A mutable reference to the data of an object layer, accessed via
+/// [`LayerMut::as_object_layer_mut()`](crate::LayerMut::as_object_layer_mut).
+///
+/// Objects added through it are given IDs from the map's
+/// [next object ID](crate::Map::next_object_id), so they stay unique within the map.
+#[derive(Debug)]
+pub struct ObjectLayerDataMut<'map> {
+    tilesets: MapTilesets<'map>,
+    next_object_id: &'map mut u32,
+    data: &'map mut ObjectLayerData,
+}
+
+impl<'map> ObjectLayerDataMut<'map> {
+    #[inline]
+    pub(crate) fn new(
+        tilesets: MapTilesets<'map>,
+        next_object_id: &'map mut u32,
+        data: &'map mut ObjectLayerData,
+    ) -> Self {
+        Self {
+            tilesets,
+            next_object_id,
+            data,
+        }
+    }
+
+    /// Adds an object on top of the ones in this layer, returning the ID it was given.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the object is a tile object whose tile isn't part
+    /// of the map's tilesets or if the map has no object IDs left, and with [`Error::InvalidObjectData`] if the object itself is
+    /// invalid; See [`ObjectDataBuilder::build`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, ObjectDataBuilder, ObjectId, ObjectShape};
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let next_id = map.next_object_id();
+    /// let mut layer = map
+    ///     .get_layer_mut(1)
+    ///     .and_then(|layer| layer.as_object_layer_mut())
+    ///     .unwrap();
+    /// let spawn = ObjectDataBuilder::new(ObjectShape::Point(0.0, 0.0))
+    ///     .name("spawn")
+    ///     .position(64.0, 32.0);
+    /// let id = layer.push_object(spawn)?;
+    /// assert_eq!(id, ObjectId(next_id));
+    /// assert_eq!(map.object_by_id(id).unwrap().name, "spawn");
+    /// assert_eq!(map.next_object_id(), next_id + 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn push_object(&mut self, object: ObjectDataBuilder) -> Result<ObjectId> {
+        if let Some(tile) = object.tile_data() {
+            let exists = match tile.tileset_location() {
+                // Tilesets that haven't been loaded yet can't be checked for the tile.
+                TilesetLocation::Map(index) => match self.tilesets.get(*index) {
+                    Some(tileset) => tileset.get_tile(tile.id()).is_some(),
+                    None => *index < self.tilesets.len(),
+                },
+                TilesetLocation::Template(_) => true,
+            };
+            if !exists {
+                return Err(Error::InvalidMapData {
+                    description: format!(
+                        "object references tile {} of a tileset the map doesn't have it in",
+                        tile.id()
+                    ),
+                });
+            }
+        }
+        let id = *self.next_object_id;
+        let next_id = crate::map::next_id(id, "object")?;
+        self.data.objects.push(object.build(id)?);
+        *self.next_object_id = next_id;
+        Ok(ObjectId(id))
+    }
+
+    /// Removes the object with the given ID from this layer and returns it, if it is in it.
+    pub fn remove_object(&mut self, id: ObjectId) -> Option<ObjectData> {
+        let index = self
+            .data
+            .objects
+            .iter()
+            .position(|object| object.id() == id)?;
+        Some(self.data.objects.remove(index))
+    }
+
+    /// Returns the object with the given ID in this layer, if it is in it. Its position, rotation,
+    /// properties and other attributes can then be changed through its fields.
+    pub fn get_object_mut(&mut self, id: ObjectId) -> Option<&mut ObjectData> {
+        self.data
+            .objects
+            .iter_mut()
+            .find(|object| object.id() == id)
+    }
+}
+
+impl<'map> std::ops::Deref for ObjectLayerDataMut<'map> {
+    type Target = ObjectLayerData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
+}
+
+/// Builds an object layer in code, without parsing any files.
+///
+/// The built layer can then be added to a map with [`MapBuilder::layer`](crate::MapBuilder::layer).
+/// Attributes not covered by the builder, such as opacity or offsets, can be set directly in the
+/// resulting [`LayerData`].
+///
+/// ## Example
+/// ```
+/// use tiled::{ObjectData, ObjectLayerBuilder, ObjectShape};
+///
+/// let mut spawn = ObjectData::from_shape(1, ObjectShape::Point(0.0, 0.0));
+/// spawn.name = "spawn".to_owned();
+/// spawn.x = 64.0;
+/// spawn.y = 32.0;
+///
+/// let layer = ObjectLayerBuilder::new()
+///     .id(2)
+///     .name("Objects")
+///     .object(spawn)
+///     .build();
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct ObjectLayerBuilder {
+    id: u32,
+    name: String,
+    properties: Properties,
+    data: ObjectLayerData,
+}
+
+impl ObjectLayerBuilder {
+    /// Creates a builder for an empty object layer.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the layer's ID, which should be unique within its map.
+    pub fn id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Sets the layer's name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the layer's custom properties.
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    /// Sets the color used in the editor to display objects in this layer.
+    pub fn colour(mut self, colour: Color) -> Self {
+        self.data.colour = Some(colour);
+        self
+    }
+
+    /// Sets the order in which the objects in this layer are drawn.
+    pub fn draw_order(mut self, draw_order: DrawOrder) -> Self {
+        self.data.draw_order = draw_order;
+        self
+    }
+
+    /// Adds an object to the end of the layer.
+    pub fn object(mut self, object: ObjectData) -> Self {
+        self.data.objects.push(object);
+        self
+    }
+
+    /// Builds the layer.
+    pub fn build(self) -> LayerData {
+        LayerData::with_type(
+            self.id,
+            self.name,
+            self.properties,
+            LayerDataType::Objects(self.data),
+        )
+    }
 }
 
 map_wrapper!(
@@ -72,11 +390,20 @@ impl<'map> ObjectLayer<'map> {
             .map(|data| Object::new(self.map, data))
     }
 
+    /// Returns the first object in this layer with the given ID, if any.
+    ///
+    /// Objects are searched one by one; To look up objects in the whole map, use
+    /// [`Map::object_by_id`](crate::Map::object_by_id), which uses an index instead.
+    pub fn object_by_id(&self, id: ObjectId) -> Option<Object<'map>> {
+        self.objects().find(|object| object.id() == id)
+    }
+
     /// Returns an iterator over the objects present in this layer, in the order they were declared
     /// in in the TMX file.
     ///
     /// ## Example
-    /// ```
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
     /// # use tiled::Loader;
     /// use tiled::Object;
     ///
@@ -106,4 +433,29 @@ impl<'map> ObjectLayer<'map> {
             .iter()
             .map(move |object| Object::new(map, object))
     }
+
+    /// Returns the objects in this layer in the order they should be drawn in, following the
+    /// layer's [`draw_order`](ObjectLayerData::draw_order): Sorted by their Y coordinate for
+    /// [`DrawOrder::TopDown`], keeping the stored order between objects at the same height, or in
+    /// the order they are stored in for [`DrawOrder::Index`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_draw_order.tmx")?;
+    /// let layer = map.get_layer(0).unwrap().as_object_layer().unwrap();
+    /// for object in layer.objects_in_draw_order() {
+    ///     println!("Drawing object {} at y = {}", object.id().0, object.y);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn objects_in_draw_order(&self) -> impl ExactSizeIterator<Item = Object<'map>> + 'map {
+        let mut objects: Vec<Object<'map>> = self.objects().collect();
+        if self.data.draw_order == DrawOrder::TopDown {
+            objects.sort_by(|a, b| a.y.total_cmp(&b.y));
+        }
+        objects.into_iter()
+    }
 }
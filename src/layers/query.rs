@@ -0,0 +1,196 @@
+use crate::{GroupLayer, HasUserClass, ImageLayer, Layer, Map, ObjectLayer, TileLayer};
+
+/// A kind of layer that [`LayerQuery::of_type`] can look for.
+pub trait FromLayer<'map>: Sized {
+    /// Returns the given layer as this kind of layer, or [`None`] if it is of another kind.
+    fn from_layer(layer: Layer<'map>) -> Option<Self>;
+}
+
+/// Every layer is a [`Layer`].
+impl<'map> FromLayer<'map> for Layer<'map> {
+    fn from_layer(layer: Layer<'map>) -> Option<Self> {
+        Some(layer)
+    }
+}
+
+impl<'map> FromLayer<'map> for TileLayer<'map> {
+    fn from_layer(layer: Layer<'map>) -> Option<Self> {
+        layer.as_tile_layer()
+    }
+}
+
+impl<'map> FromLayer<'map> for ObjectLayer<'map> {
+    fn from_layer(layer: Layer<'map>) -> Option<Self> {
+        layer.as_object_layer()
+    }
+}
+
+impl<'map> FromLayer<'map> for ImageLayer<'map> {
+    fn from_layer(layer: Layer<'map>) -> Option<Self> {
+        layer.as_image_layer()
+    }
+}
+
+impl<'map> FromLayer<'map> for GroupLayer<'map> {
+    fn from_layer(layer: Layer<'map>) -> Option<Self> {
+        layer.as_group_layer()
+    }
+}
+
+/// A search for the layers of a map that match some conditions, created with
+/// [`Map::query_layers`].
+///
+/// Only the top-level layers of the map are searched, unless [`Self::flattened`] is used. The
+/// matching layers are gone through by iterating over the query.
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::TileLayer;
+///
+/// let map = tiled::Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+/// let names: Vec<String> = map
+///     .query_layers()
+///     .flattened()
+///     .of_type::<TileLayer>()
+///     .name_matches("tile-*")
+///     .into_iter()
+///     .map(|layer| layer.name.clone())
+///     .collect();
+/// assert_eq!(names, ["tile-1", "tile-2", "tile-3"]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+#[must_use]
+pub struct LayerQuery<'map> {
+    map: &'map Map,
+    flattened: bool,
+    kind: Option<fn(Layer<'map>) -> bool>,
+    class: Option<String>,
+    name_pattern: Option<String>,
+}
+
+impl<'map> LayerQuery<'map> {
+    pub(crate) fn new(map: &'map Map) -> Self {
+        Self {
+            map,
+            flattened: false,
+            kind: None,
+            class: None,
+            name_pattern: None,
+        }
+    }
+
+    /// Searches the layers nested in group layers as well, going through the layer tree
+    /// depth-first with each group coming right before its contents, like
+    /// [`Map::flattened_layers`] but including the group layers themselves.
+    pub fn flattened(mut self) -> Self {
+        self.flattened = true;
+        self
+    }
+
+    /// Only matches layers of the given kind, such as [`ObjectLayer`]s; See [`FromLayer`].
+    pub fn of_type<T: FromLayer<'map>>(mut self) -> Self {
+        self.kind = Some(|layer| T::from_layer(layer).is_some());
+        self
+    }
+
+    /// Only matches layers of the given class; See [`HasUserClass`].
+    pub fn with_class(mut self, class: impl Into<String>) -> Self {
+        self.class = Some(class.into());
+        self
+    }
+
+    /// Only matches layers whose whole name matches the given pattern, in which `*` stands for
+    /// any number of characters and `?` for exactly one character.
+    pub fn name_matches(mut self, pattern: impl Into<String>) -> Self {
+        self.name_pattern = Some(pattern.into());
+        self
+    }
+
+    /// Returns the first matching layer, if any.
+    pub fn first(self) -> Option<Layer<'map>> {
+        self.into_iter().next()
+    }
+
+    fn matches(&self, layer: Layer<'map>) -> bool {
+        if let Some(kind) = self.kind {
+            if !kind(layer) {
+                return false;
+            }
+        }
+        if let Some(class) = &self.class {
+            if layer.user_class() != Some(class.as_str()) {
+                return false;
+            }
+        }
+        match &self.name_pattern {
+            Some(pattern) => glob_matches(
+                &pattern.chars().collect::<Vec<_>>(),
+                &layer.name.chars().collect::<Vec<_>>(),
+            ),
+            None => true,
+        }
+    }
+}
+
+impl<'map> IntoIterator for LayerQuery<'map> {
+    type Item = Layer<'map>;
+    type IntoIter = std::vec::IntoIter<Layer<'map>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        let mut layers = Vec::new();
+        // Layers are popped off the end of each level of the tree, so the levels are reversed.
+        let reversed = |layers: &mut dyn Iterator<Item = Layer<'map>>| {
+            let mut layers: Vec<Layer<'map>> = layers.collect();
+            layers.reverse();
+            layers
+        };
+        let mut stack = vec![reversed(&mut self.map.layers())];
+        while let Some(layer) = stack.last_mut().and_then(Vec::pop) {
+            if self.matches(layer) {
+                layers.push(layer);
+            }
+            if self.flattened {
+                if let Some(group) = layer.as_group_layer() {
+                    stack.push(reversed(&mut group.layers()));
+                }
+            }
+            while matches!(stack.last(), Some(layers) if layers.is_empty()) {
+                stack.pop();
+            }
+        }
+        layers.into_iter()
+    }
+}
+
+/// Returns whether the given text matches the given pattern, in which `*` matches any number of
+/// characters and `?` matches a single one.
+fn glob_matches(pattern: &[char], text: &[char]) -> bool {
+    // Where to resume after the last `*`, if the characters after it stop matching.
+    let mut backtrack = None;
+    let (mut p, mut t) = (0, 0);
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, star_t)) => {
+                    backtrack = Some((star, star_t + 1));
+                    p = star + 1;
+                    t = star_t + 1;
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
@@ -0,0 +1,142 @@
+use std::sync::Arc;
+
+use crate::{LayerData, LayerTile, LayerTileData, Map, Tile, Tileset};
+
+use super::Layer;
+
+/// A map layer that holds onto its map through an [`Arc`] instead of borrowing it, so that it can
+/// be cloned cheaply and stored or sent to other threads without any lifetimes; Accessed via
+/// [`Map::layers_arc`].
+///
+/// Dereferences into the layer's [`LayerData`]. Use [`ArcLayer::layer`] to get the regular
+/// [`Layer`] wrapper and everything it provides.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ArcLayer {
+    map: Arc<Map>,
+    /// The index of the layer within the top-level layers of the map, followed by its index
+    /// within each of the group layers it's nested in.
+    path: Arc<[usize]>,
+}
+
+impl ArcLayer {
+    #[inline]
+    pub(crate) fn new(map: Arc<Map>, path: Arc<[usize]>) -> Self {
+        Self { map, path }
+    }
+
+    /// Get the map this layer is from.
+    #[inline]
+    pub fn map(&self) -> &Arc<Map> {
+        &self.map
+    }
+
+    /// Returns the regular [`Layer`] wrapper of this layer, which borrows the map from this one.
+    pub fn layer(&self) -> Layer<'_> {
+        // The map can't change while it is shared, so the path is always valid.
+        let (first, rest) = self
+            .path
+            .split_first()
+            .expect("layer paths are never empty");
+        rest.iter().fold(
+            self.map.get_layer(*first).expect("layer paths are valid"),
+            |layer, index| {
+                layer
+                    .as_group_layer()
+                    .and_then(|group| group.get_layer(*index))
+                    .expect("layer paths are valid")
+            },
+        )
+    }
+
+    /// Returns an iterator over the layers of this group layer, or an empty one if this isn't a
+    /// group layer.
+    pub fn layers(&self) -> impl ExactSizeIterator<Item = ArcLayer> + '_ {
+        let count = self
+            .layer()
+            .as_group_layer()
+            .map_or(0, |group| group.layers().len());
+        (0..count).map(move |index| {
+            let path: Vec<usize> = self.path.iter().copied().chain(Some(index)).collect();
+            ArcLayer::new(self.map.clone(), path.into())
+        })
+    }
+
+    /// Obtains the tile present at the position given, if this is a tile layer.
+    ///
+    /// If the layer isn't a tile layer, the position given is invalid or the position is empty,
+    /// this function will return [`None`].
+    pub fn get_tile(&self, x: i32, y: i32) -> Option<ArcLayerTile> {
+        let tile = self.layer().as_tile_layer()?.get_tile(x, y)?;
+        Some(ArcLayerTile::new(self.map.clone(), tile.data))
+    }
+}
+
+impl std::ops::Deref for ArcLayer {
+    type Target = LayerData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.layer().data
+    }
+}
+
+/// An instance of a [`Tile`] present in a tile layer, which holds onto its map through an [`Arc`]
+/// instead of borrowing it; See [`ArcLayer::get_tile`].
+///
+/// Dereferences into the tile's [`LayerTileData`]. Use [`ArcLayerTile::layer_tile`] to get the
+/// regular [`LayerTile`] wrapper and everything it provides.
+#[derive(Clone, PartialEq, Debug)]
+pub struct ArcLayerTile {
+    map: Arc<Map>,
+    data: LayerTileData,
+}
+
+impl ArcLayerTile {
+    #[inline]
+    pub(crate) fn new(map: Arc<Map>, data: LayerTileData) -> Self {
+        Self { map, data }
+    }
+
+    /// Get the map this tile is from.
+    #[inline]
+    pub fn map(&self) -> &Arc<Map> {
+        &self.map
+    }
+
+    /// Returns the regular [`LayerTile`] wrapper of this tile, which borrows the map from this one.
+    #[inline]
+    pub fn layer_tile(&self) -> LayerTile<'_> {
+        LayerTile::new(&self.map, self.data)
+    }
+
+    /// Get a reference to the layer tile's referenced tile, if it exists.
+    #[inline]
+    pub fn get_tile(&self) -> Option<Tile<'_>> {
+        self.layer_tile().get_tile()
+    }
+
+    /// Get a reference to the layer tile's referenced tileset.
+    ///
+    /// ## Panics
+    /// Panics if the tileset hasn't been loaded yet; See [`LayerTile::get_tileset`].
+    #[inline]
+    pub fn get_tileset(&self) -> &Tileset {
+        self.layer_tile().get_tileset()
+    }
+
+    /// Get a reference to the layer tile's referenced tileset, if it has been loaded; See
+    /// [`LayerTile::try_get_tileset`].
+    #[inline]
+    pub fn try_get_tileset(&self) -> Option<&Tileset> {
+        self.layer_tile().try_get_tileset()
+    }
+}
+
+impl std::ops::Deref for ArcLayerTile {
+    type Target = LayerTileData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
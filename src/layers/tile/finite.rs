@@ -1,19 +1,177 @@
-use xml::attribute::OwnedAttribute;
-
 use crate::{
-    util::{get_attrs, map_wrapper, XmlEventResult},
-    LayerTile, LayerTileData, MapTilesetGid, Result,
+    map::MapTilesets,
+    util::{get_attrs, map_wrapper, OwnedAttribute, XmlParser},
+    Error, Gid, LayerTile, LayerTileData, MapTilesetGid, Result,
 };
 
-use super::util::parse_data_line;
+use super::{
+    util::{
+        check_tile, compact_first_gids, encode_tile, remap_tile, renumbered_first_gids, GidSink,
+        TileDataSource,
+    },
+    InfiniteTileLayerData,
+};
 
 /// The raw data of a [`FiniteTileLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
-#[derive(PartialEq, Clone, Default)]
+#[derive(Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct FiniteTileLayerData {
     width: u32,
     height: u32,
-    /// The tiles are arranged in rows.
-    tiles: Vec<Option<LayerTileData>>,
+    /// The [`Gid`] of each tile along with its flipping bits. Tiles are only turned into
+    /// [`LayerTileData`] when accessed, which keeps large layers compact.
+    gids: TileStorage,
+    /// The first [`Gid`] of each tileset the gids refer to, ordered by tileset index.
+    first_gids: Vec<Gid>,
+    /// The contents of the layer's `data` element while they haven't been decoded yet, which
+    /// only happens while the map is being parsed, along with the sparse threshold the map is
+    /// loaded with.
+    #[cfg(feature = "rayon")]
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pending: Option<(TileDataSource, Option<f32>)>,
+}
+
+/// How the gids of a [`FiniteTileLayerData`] are stored. A gid of `0` marks an empty tile.
+#[derive(Clone, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+enum TileStorage {
+    /// The gid of every tile in the layer, arranged in rows.
+    Dense(Vec<u32>),
+    /// The index and gid of every non-empty tile in the layer, sorted by index. Takes less memory
+    /// than [`TileStorage::Dense`] when more than half of the layer is empty.
+    Sparse(Vec<(u32, u32)>),
+}
+
+impl Default for TileStorage {
+    fn default() -> Self {
+        TileStorage::Dense(Vec::new())
+    }
+}
+
+impl TileStorage {
+    fn get(&self, index: usize) -> u32 {
+        match self {
+            TileStorage::Dense(gids) => gids.get(index).copied().unwrap_or(0),
+            TileStorage::Sparse(gids) => gids
+                .binary_search_by_key(&index, |(i, _)| *i as usize)
+                .map_or(0, |position| gids[position].1),
+        }
+    }
+
+    /// Replaces the gid at the given index, which must be within the layer.
+    fn set(&mut self, index: usize, bits: u32) {
+        match self {
+            TileStorage::Dense(gids) => gids[index] = bits,
+            TileStorage::Sparse(gids) => {
+                match gids.binary_search_by_key(&index, |(i, _)| *i as usize) {
+                    Ok(position) if bits == 0 => {
+                        gids.remove(position);
+                    }
+                    Ok(position) => gids[position].1 = bits,
+                    Err(position) if bits != 0 => gids.insert(position, (index as u32, bits)),
+                    Err(_) => {}
+                }
+            }
+        }
+    }
+
+    /// Returns an iterator over the indices and gids of the non-empty tiles, in index order.
+    fn iter(&self) -> Box<dyn Iterator<Item = (usize, u32)> + '_> {
+        match self {
+            TileStorage::Dense(gids) => Box::new(
+                gids.iter()
+                    .copied()
+                    .enumerate()
+                    .filter(|(_, bits)| *bits != 0),
+            ),
+            TileStorage::Sparse(gids) => {
+                Box::new(gids.iter().map(|(index, bits)| (*index as usize, *bits)))
+            }
+        }
+    }
+}
+
+/// Collects the gids of a layer's tiles into the [`TileStorage`] they end up in as they are
+/// decoded. With a sparse threshold, tiles are stored sparsely until there are too many non-empty
+/// ones for the layer to be empty enough, so mostly empty layers never need a dense buffer.
+struct TileStorageBuilder {
+    storage: TileStorage,
+    /// The number of tiles added so far.
+    count: usize,
+    /// The number of tiles in the layer.
+    len: usize,
+    /// The most non-empty tiles the layer can have to be stored sparsely.
+    max_sparse: usize,
+}
+
+impl TileStorageBuilder {
+    fn new(len: usize, sparse_threshold: Option<f32>) -> Self {
+        let max_sparse = sparse_threshold.and_then(|threshold| {
+            let empty_enough =
+                |non_empty: usize| (len - non_empty) as f32 / len as f32 >= threshold;
+            if len == 0 || len > u32::MAX as usize || !empty_enough(0) {
+                return None;
+            }
+            // Start from an estimate and correct it for rounding, so that the result matches
+            // comparing the fraction of empty tiles with the threshold.
+            let min_empty = (f64::from(threshold) * len as f64).ceil().max(0.0) as usize;
+            let mut max = len - min_empty.min(len);
+            while max < len && empty_enough(max + 1) {
+                max += 1;
+            }
+            while max > 0 && !empty_enough(max) {
+                max -= 1;
+            }
+            Some(max)
+        });
+        Self {
+            storage: match max_sparse {
+                Some(_) => TileStorage::Sparse(Vec::new()),
+                None => TileStorage::Dense(Vec::new()),
+            },
+            count: 0,
+            len,
+            max_sparse: max_sparse.unwrap_or(0),
+        }
+    }
+
+    /// Decodes the gids of a layer with `len` tiles, storing them sparsely if at least the
+    /// fraction of them given by `sparse_threshold` is empty.
+    fn decode(
+        source: TileDataSource,
+        len: usize,
+        sparse_threshold: Option<f32>,
+    ) -> Result<TileStorage> {
+        let mut builder = Self::new(len, sparse_threshold);
+        source.decode_exact(len, &mut builder)?;
+        Ok(builder.storage)
+    }
+}
+
+impl GidSink for TileStorageBuilder {
+    fn push(&mut self, bits: u32) {
+        match &mut self.storage {
+            TileStorage::Dense(gids) => gids.push(bits),
+            TileStorage::Sparse(_) if bits == 0 => {}
+            TileStorage::Sparse(gids) if gids.len() < self.max_sparse && self.count < self.len => {
+                gids.push((self.count as u32, bits));
+            }
+            TileStorage::Sparse(gids) => {
+                // The layer has too many tiles to be stored sparsely.
+                let mut dense = vec![0; self.count];
+                for (index, bits) in gids.drain(..) {
+                    dense[index as usize] = bits;
+                }
+                dense.push(bits);
+                self.storage = TileStorage::Dense(dense);
+            }
+        }
+        self.count += 1;
+    }
+
+    fn count(&self) -> usize {
+        self.count
+    }
 }
 
 impl std::fmt::Debug for FiniteTileLayerData {
@@ -25,6 +183,17 @@ impl std::fmt::Debug for FiniteTileLayerData {
     }
 }
 
+impl PartialEq for FiniteTileLayerData {
+    fn eq(&self, other: &Self) -> bool {
+        // The same tiles may be stored differently, so they need to be compared one by one unless
+        // both layers number their tilesets and store their gids identically.
+        self.width == other.width
+            && self.height == other.height
+            && ((self.first_gids == other.first_gids && self.gids == other.gids)
+                || self.tile_data().eq(other.tile_data()))
+    }
+}
+
 impl FiniteTileLayerData {
     /// Get the tile layer's width in tiles.
     #[inline]
@@ -39,40 +208,298 @@ impl FiniteTileLayerData {
     }
 
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         width: u32,
         height: u32,
         tilesets: &[MapTilesetGid],
     ) -> Result<Self> {
         let (e, c) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("data", parser.event_position()) {
                 Some("encoding") => encoding = v,
                 Some("compression") => compression = v,
             }
             (encoding, compression)
         );
 
-        let tiles = parse_data_line(e, c, parser, tilesets)?;
+        let source = TileDataSource::read(e, c, parser)?;
+        let sparse_threshold = parser.options().sparse_tile_layer_threshold();
+        // With `rayon`, layers are decoded in parallel once the whole map has been read.
+        #[cfg(feature = "rayon")]
+        let (gids, pending) = (TileStorage::default(), Some((source, sparse_threshold)));
+        #[cfg(not(feature = "rayon"))]
+        let gids =
+            TileStorageBuilder::decode(source, width as usize * height as usize, sparse_threshold)?;
 
         Ok(Self {
             width,
             height,
-            tiles,
+            gids,
+            first_gids: tilesets.iter().map(|tileset| tileset.first_gid).collect(),
+            #[cfg(feature = "rayon")]
+            pending,
         })
     }
 
+    /// Creates a layer of the given size with no tiles in it.
+    pub(crate) fn empty(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            gids: TileStorage::Dense(vec![0; width as usize * height as usize]),
+            first_gids: Vec::new(),
+            #[cfg(feature = "rayon")]
+            pending: None,
+        }
+    }
+
+    /// Decodes the contents of the layer's `data` element if that hasn't been done yet.
+    #[cfg(feature = "rayon")]
+    pub(crate) fn decode_pending(&mut self) -> Result<()> {
+        if let Some((source, sparse_threshold)) = self.pending.take() {
+            let len = self.width as usize * self.height as usize;
+            self.gids = TileStorageBuilder::decode(source, len, sparse_threshold)?;
+        }
+        Ok(())
+    }
+
+    /// Replaces the first [`Gid`] of each tileset the gids refer to, which doesn't change the
+    /// gids themselves.
+    pub(crate) fn set_first_gids(&mut self, first_gids: Vec<Gid>) {
+        self.first_gids = first_gids;
+    }
+
+    /// Replaces the gid and flipping bits of the tile at the given position, which must be within
+    /// the layer and numbered after the layer's first gids.
+    pub(super) fn set_bits(&mut self, x: i32, y: i32, bits: u32) {
+        let index = x as usize + y as usize * self.width as usize;
+        self.gids.set(index, bits);
+    }
+
+    fn index_of(&self, x: i32, y: i32) -> Option<usize> {
+        if x < self.width as i32 && y < self.height as i32 && x >= 0 && y >= 0 {
+            Some(x as usize + y as usize * self.width as usize)
+        } else {
+            None
+        }
+    }
+
+    fn tile_at_index(&self, index: usize) -> Option<LayerTileData> {
+        LayerTileData::from_bits_with_first_gids(self.gids.get(index), &self.first_gids)
+    }
+
+    /// Returns whether the tiles of this layer are stored sparsely, which is the case for layers
+    /// loaded while [`Loader::set_sparse_tile_layer_threshold`] was set and that are empty
+    /// enough.
+    ///
+    /// Sparse layers take less memory, but [`FiniteTileLayerData::get_tile_data`] takes
+    /// logarithmic rather than constant time in them.
+    ///
+    /// [`Loader::set_sparse_tile_layer_threshold`]: crate::Loader::set_sparse_tile_layer_threshold
+    pub fn is_sparse(&self) -> bool {
+        matches!(self.gids, TileStorage::Sparse(_))
+    }
+
+    /// Replaces the tile at the given position.
+    ///
+    /// Layers created in code have no tilesets to number their tiles after, so gid ranges are
+    /// assigned to tilesets as tiles from them are added, and grown when they turn out too small.
+    pub(crate) fn set_tile_data(
+        &mut self,
+        x: i32,
+        y: i32,
+        tile: Option<LayerTileData>,
+    ) -> Result<()> {
+        let index = self.index_of(x, y).ok_or_else(|| Error::InvalidMapData {
+            description: format!("tile position ({}, {}) is out of the layer's bounds", x, y),
+        })?;
+        let bits = match tile {
+            Some(tile) => match encode_tile(&self.first_gids, tile) {
+                Some(bits) => bits,
+                None => {
+                    self.renumber_tilesets(tile)?;
+                    encode_tile(&self.first_gids, tile).unwrap()
+                }
+            },
+            None => 0,
+        };
+        self.gids.set(index, bits);
+        Ok(())
+    }
+
+    /// Reassigns gid ranges to tilesets so that all the current tiles plus `new_tile` fit, and
+    /// renumbers the current tiles accordingly.
+    fn renumber_tilesets(&mut self, new_tile: LayerTileData) -> Result<()> {
+        let tiles: Vec<(usize, LayerTileData)> = self
+            .gids
+            .iter()
+            .filter_map(|(index, bits)| {
+                LayerTileData::from_bits_with_first_gids(bits, &self.first_gids)
+                    .map(|tile| (index, tile))
+            })
+            .collect();
+        self.first_gids = renumbered_first_gids(
+            &self.first_gids,
+            tiles.iter().map(|(_, tile)| *tile),
+            new_tile,
+        )?;
+        for (index, tile) in tiles {
+            let bits = tile.to_bits(self.first_gids[tile.tileset_index()]);
+            self.gids.set(index, bits);
+        }
+        Ok(())
+    }
+
     /// Obtains the tile data present at the position given.
     ///
     /// If the position given is invalid or the position is empty, this function will return [`None`].
     ///
     /// If you want to get a [`Tile`](`crate::Tile`) instead, use [`FiniteTileLayer::get_tile()`].
-    pub fn get_tile_data(&self, x: i32, y: i32) -> Option<&LayerTileData> {
-        if x < self.width as i32 && y < self.height as i32 && x >= 0 && y >= 0 {
-            self.tiles[x as usize + y as usize * self.width as usize].as_ref()
-        } else {
-            None
+    pub fn get_tile_data(&self, x: i32, y: i32) -> Option<LayerTileData> {
+        self.tile_at_index(self.index_of(x, y)?)
+    }
+
+    /// Returns an iterator over the tile data of all the non-empty tiles in this layer, along with
+    /// their positions. Tiles are visited row by row, from the top-left corner of the layer.
+    ///
+    /// If you want to get [`LayerTile`]s instead, use [`FiniteTileLayer::tiles()`].
+    pub fn tile_data(&self) -> impl Iterator<Item = ((i32, i32), LayerTileData)> + '_ {
+        let width = self.width as usize;
+        self.gids.iter().filter_map(move |(index, bits)| {
+            LayerTileData::from_bits_with_first_gids(bits, &self.first_gids)
+                .map(|tile| (((index % width) as i32, (index / width) as i32), tile))
+        })
+    }
+
+    /// Changes the size of this layer, moving its tiles by the given amount and dropping the ones
+    /// that end up outside of it.
+    pub(crate) fn resize(&mut self, width: u32, height: u32, (dx, dy): (i64, i64)) {
+        let mut data = Self {
+            width,
+            height,
+            gids: if self.is_sparse() {
+                TileStorage::Sparse(Vec::new())
+            } else {
+                TileStorage::Dense(vec![0; width as usize * height as usize])
+            },
+            first_gids: self.first_gids.clone(),
+            #[cfg(feature = "rayon")]
+            pending: None,
+        };
+        let old_width = self.width as usize;
+        for (index, bits) in self.gids.iter() {
+            let x = (index % old_width) as i64 + dx;
+            let y = (index / old_width) as i64 + dy;
+            if (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                data.set_bits(x as i32, y as i32, bits);
+            }
+        }
+        *self = data;
+    }
+
+    /// Makes the tiles of this layer refer to the tilesets at the new indices given by
+    /// `new_indices`, by old index. Tiles from tilesets without a new index are removed.
+    pub(crate) fn remap_tilesets(&mut self, new_indices: &[Option<usize>]) {
+        let tiles: Vec<(usize, Option<LayerTileData>)> = self
+            .gids
+            .iter()
+            .map(|(index, bits)| {
+                let tile = LayerTileData::from_bits_with_first_gids(bits, &self.first_gids);
+                (index, tile.and_then(|tile| remap_tile(tile, new_indices)))
+            })
+            .collect();
+        self.first_gids = compact_first_gids(tiles.iter().filter_map(|(_, tile)| *tile));
+        for (index, tile) in tiles {
+            let bits = tile.and_then(|tile| encode_tile(&self.first_gids, tile));
+            self.gids.set(index, bits.unwrap_or(0));
+        }
+    }
+
+    /// Returns an infinite layer with the same tiles as this one, at the same positions, for
+    /// converting finite maps into infinite ones.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerType, Loader, TileLayer};
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let layer = match map.get_layer(0).unwrap().layer_type() {
+    ///     LayerType::Tiles(TileLayer::Finite(layer)) => layer,
+    ///     _ => panic!("Layer #0 is not a finite tile layer"),
+    /// };
+    /// let infinite = layer.to_infinite();
+    /// assert_eq!(infinite.get_tile_data(3, 5), layer.get_tile_data(3, 5));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_infinite(&self) -> InfiniteTileLayerData {
+        let mut data = InfiniteTileLayerData::empty();
+        data.set_first_gids(self.first_gids.clone());
+        let width = self.width as usize;
+        for (index, bits) in self.gids.iter() {
+            data.set_bits((index % width) as i32, (index / width) as i32, bits);
+        }
+        data
+    }
+}
+
+/// A mutable reference to the data of a finite tile layer, accessed via
+/// [`LayerMut::as_finite_tile_layer_mut()`](crate::LayerMut::as_finite_tile_layer_mut).
+///
+/// Unlike [`FiniteTileLayerData`], it knows the tilesets of the layer's map, so tiles are checked
+/// against them before being placed.
+#[derive(Debug)]
+pub struct FiniteTileLayerDataMut<'map> {
+    tilesets: MapTilesets<'map>,
+    data: &'map mut FiniteTileLayerData,
+}
+
+impl<'map> FiniteTileLayerDataMut<'map> {
+    #[inline]
+    pub(crate) fn new(tilesets: MapTilesets<'map>, data: &'map mut FiniteTileLayerData) -> Self {
+        Self { tilesets, data }
+    }
+
+    /// Replaces the tile at the given position, or clears it if `tile` is [`None`].
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the position is out of the layer's bounds or the
+    /// tile isn't part of the map's tilesets. Tiles of tilesets that haven't been loaded yet are
+    /// only checked against the map's amount of tilesets; See [`Map::tileset`](crate::Map::tileset).
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerTileData, Loader};
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let mut layer = map
+    ///     .get_layer_mut(0)
+    ///     .and_then(|layer| layer.as_finite_tile_layer_mut())
+    ///     .unwrap();
+    /// layer.set_tile(0, 0, Some(LayerTileData::new(0, 12)))?;
+    /// layer.set_tile(1, 0, None)?;
+    /// assert_eq!(layer.get_tile_data(0, 0).unwrap().id(), 12);
+    /// assert!(layer.set_tile(0, 0, Some(LayerTileData::new(1, 0))).is_err());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: Option<LayerTileData>) -> Result<()> {
+        if let Some(tile) = tile {
+            check_tile(self.tilesets, tile)?;
         }
+        self.data.set_tile_data(x, y, tile)
+    }
+}
+
+impl<'map> std::ops::Deref for FiniteTileLayerDataMut<'map> {
+    type Target = FiniteTileLayerData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.data
     }
 }
 
@@ -90,4 +517,33 @@ impl<'map> FiniteTileLayer<'map> {
             .get_tile_data(x, y)
             .map(|data| LayerTile::new(self.map(), data))
     }
+
+    /// Returns an iterator over all the non-empty tiles in this layer, along with their positions.
+    /// Tiles are visited row by row, from the top-left corner of the layer.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::{Loader, TileLayer};
+    /// #
+    /// # fn main() {
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_base64_zlib.tmx")
+    /// #     .unwrap();
+    /// # let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+    /// #     TileLayer::Finite(layer) => layer,
+    /// #     _ => panic!("Layer #0 is not finite"),
+    /// # };
+    /// #
+    /// for ((x, y), tile) in layer.tiles() {
+    ///     println!("Tile {} at ({}, {})", tile.id(), x, y);
+    /// }
+    /// # }
+    /// ```
+    pub fn tiles(&self) -> impl Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map {
+        let map: &'map crate::Map = self.map;
+        let data: &'map FiniteTileLayerData = self.data;
+        data.tile_data()
+            .map(move |(pos, tile)| (pos, LayerTile::new(map, tile)))
+    }
 }
@@ -1,18 +1,74 @@
-use std::collections::HashMap;
-
-use xml::attribute::OwnedAttribute;
+use std::{collections::HashMap, convert::TryFrom, path::Path, sync::Arc};
 
 use crate::{
-    util::{floor_div, get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Error, LayerTile, LayerTileData, MapTilesetGid, Result,
+    map::MapTilesets,
+    util::{floor_div, get_attrs, map_wrapper, parse_tag, OwnedAttribute, XmlParser},
+    Error, Gid, LayerTile, LayerTileData, Map, MapTilesetGid, Result, XmlPosition,
 };
 
-use super::util::parse_data_line;
+#[cfg(feature = "rayon")]
+use super::util::decode_all;
+use super::{
+    util::{
+        check_tile, compact_first_gids, encode_tile, remap_tile, renumbered_first_gids,
+        TileDataSource,
+    },
+    FiniteTileLayerData,
+};
 
 /// The raw data of a [`InfiniteTileLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
-#[derive(PartialEq, Clone)]
+#[derive(Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct InfiniteTileLayerData {
+    #[cfg_attr(feature = "serde", serde(with = "serde_chunks"))]
     chunks: HashMap<(i32, i32), ChunkData>,
+    /// The first [`Gid`] of each tileset the gids of the chunks refer to, ordered by tileset
+    /// index. Shared with every chunk.
+    first_gids: Arc<[Gid]>,
+}
+
+/// Chunks are (de)serialized as a sequence of position and chunk pairs, since many formats
+/// (such as JSON) don't support non-string map keys.
+#[cfg(feature = "serde")]
+mod serde_chunks {
+    use std::collections::HashMap;
+
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    use super::ChunkData;
+
+    pub(super) fn serialize<S: Serializer>(
+        chunks: &HashMap<(i32, i32), ChunkData>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(chunks.iter())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<HashMap<(i32, i32), ChunkData>, D::Error> {
+        Vec::<((i32, i32), ChunkData)>::deserialize(deserializer)
+            .map(|chunks| chunks.into_iter().collect())
+    }
+}
+
+impl PartialEq for InfiniteTileLayerData {
+    fn eq(&self, other: &Self) -> bool {
+        // The same tiles may be stored differently, so chunks are compared by their tiles, and
+        // empty chunks are the same as missing ones.
+        let is_empty = |chunk: &ChunkData| chunk.tile_data_from((0, 0)).next().is_none();
+        self.chunks
+            .iter()
+            .all(|(pos, chunk)| match other.chunks.get(pos) {
+                Some(other_chunk) => chunk == other_chunk,
+                None => is_empty(chunk),
+            })
+            && other
+                .chunks
+                .iter()
+                .filter(|(pos, _)| !self.chunks.contains_key(pos))
+                .all(|(_, chunk)| is_empty(chunk))
+    }
 }
 
 impl std::fmt::Debug for InfiniteTileLayerData {
@@ -23,42 +79,184 @@ impl std::fmt::Debug for InfiniteTileLayerData {
 
 impl InfiniteTileLayerData {
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         tilesets: &[MapTilesetGid],
     ) -> Result<Self> {
         let (e, c) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("data", parser.event_position()) {
                 Some("encoding") => encoding = v,
                 Some("compression") => compression = v,
             }
             (encoding, compression)
         );
 
+        let first_gids: Arc<[Gid]> = tilesets.iter().map(|tileset| tileset.first_gid).collect();
         let mut chunks = HashMap::<(i32, i32), ChunkData>::new();
+        // With `rayon`, chunks are only decoded once all of them have been read, so that it can be
+        // done in parallel. Otherwise each one is decoded as soon as it is read.
+        #[cfg(feature = "rayon")]
+        let mut pending = Vec::new();
         parse_tag!(parser, "data", {
             "chunk" => |attrs| {
-                let chunk = InternalChunk::new(parser, attrs, e.clone(), c.clone(), tilesets)?;
-                for x in chunk.x..chunk.x + chunk.width as i32 {
-                    for y in chunk.y..chunk.y + chunk.height as i32 {
-                        let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
-                        let relative_pos = (x - chunk_pos.0 * ChunkData::WIDTH as i32, y - chunk_pos.1 * ChunkData::HEIGHT as i32);
-                        let chunk_index = (relative_pos.0 + relative_pos.1 * ChunkData::WIDTH as i32) as usize;
-                        let internal_pos = (x - chunk.x, y - chunk.y);
-                        let internal_index = (internal_pos.0 + internal_pos.1 * chunk.width as i32) as usize;
-
-                        if internal_index >= chunk.tiles.len() {
-                            return Err(Error::InvalidTileFound);
-                        }
-
-                        chunks.entry(chunk_pos).or_insert_with(ChunkData::new).tiles[chunk_index] = chunk.tiles[internal_index];
-                    }
-                }
+                let (chunk, source) = InternalChunk::new(parser, attrs, e.clone(), c.clone())?;
+                #[cfg(feature = "rayon")]
+                pending.push((chunk, source));
+                #[cfg(not(feature = "rayon"))]
+                chunk.place(source.decode()?, &mut chunks, &first_gids, parser)?;
                 Ok(())
             }
         });
 
-        Ok(Self { chunks })
+        #[cfg(feature = "rayon")]
+        {
+            let (internal_chunks, sources): (Vec<_>, Vec<_>) = pending.into_iter().unzip();
+            for (chunk, gids) in internal_chunks.into_iter().zip(decode_all(sources)?) {
+                chunk.place(gids, &mut chunks, &first_gids, parser)?;
+            }
+        }
+
+        Ok(Self { chunks, first_gids })
+    }
+
+    /// Creates a layer with no chunks in it.
+    pub(crate) fn empty() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            first_gids: Arc::new([]),
+        }
+    }
+
+    /// Replaces the first [`Gid`] of each tileset the gids refer to, which doesn't change the
+    /// gids themselves.
+    pub(crate) fn set_first_gids(&mut self, first_gids: Vec<Gid>) {
+        self.first_gids = first_gids.into();
+        for chunk in self.chunks.values_mut() {
+            chunk.first_gids = self.first_gids.clone();
+        }
+    }
+
+    /// Replaces the tile at the given position, creating the chunk that contains it if needed.
+    ///
+    /// As with [`FiniteTileLayerData`](super::FiniteTileLayerData), gid ranges are assigned to
+    /// tilesets as tiles from them are added.
+    pub(crate) fn set_tile_data(
+        &mut self,
+        x: i32,
+        y: i32,
+        tile: Option<LayerTileData>,
+    ) -> Result<()> {
+        let bits = match tile {
+            Some(tile) => match encode_tile(&self.first_gids, tile) {
+                Some(bits) => bits,
+                None => {
+                    self.renumber_tilesets(tile)?;
+                    encode_tile(&self.first_gids, tile).unwrap()
+                }
+            },
+            None => 0,
+        };
+        self.set_bits(x, y, bits);
+        Ok(())
+    }
+
+    /// Replaces the gid and flipping bits of the tile at the given position, which must be
+    /// numbered after the layer's first gids, creating the chunk that contains it if needed.
+    pub(super) fn set_bits(&mut self, x: i32, y: i32, bits: u32) {
+        let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
+        let relative_pos = (
+            x - chunk_pos.0 * ChunkData::WIDTH as i32,
+            y - chunk_pos.1 * ChunkData::HEIGHT as i32,
+        );
+        let chunk_index = (relative_pos.0 + relative_pos.1 * ChunkData::WIDTH as i32) as usize;
+        match self.chunks.get_mut(&chunk_pos) {
+            Some(chunk) => chunk.gids[chunk_index] = bits,
+            // Don't create chunks just to leave them empty.
+            None if bits == 0 => (),
+            None => {
+                let mut chunk = ChunkData::new(self.first_gids.clone());
+                chunk.gids[chunk_index] = bits;
+                self.chunks.insert(chunk_pos, chunk);
+            }
+        }
+    }
+
+    /// Removes the chunks that have no tiles left in them.
+    pub(crate) fn remove_empty_chunks(&mut self) {
+        self.chunks
+            .retain(|_, chunk| chunk.gids.iter().any(|bits| *bits != 0));
+    }
+
+    /// Reassigns gid ranges to tilesets so that all the current tiles plus `new_tile` fit, and
+    /// renumbers the current tiles accordingly.
+    fn renumber_tilesets(&mut self, new_tile: LayerTileData) -> Result<()> {
+        let first_gids = renumbered_first_gids(
+            &self.first_gids,
+            self.tile_data().map(|(_, tile)| tile),
+            new_tile,
+        )?;
+        for chunk in self.chunks.values_mut() {
+            for bits in chunk.gids.iter_mut() {
+                *bits = LayerTileData::from_bits_with_first_gids(*bits, &chunk.first_gids)
+                    .map_or(0, |tile| tile.to_bits(first_gids[tile.tileset_index()]));
+            }
+        }
+        self.set_first_gids(first_gids);
+        Ok(())
+    }
+
+    /// Returns an iterator over the tile data of all the non-empty tiles in this layer, along with
+    /// their positions.
+    ///
+    /// This iterator doesn't have any particular order. If you want to get [`LayerTile`]s instead,
+    /// use [`InfiniteTileLayer::tiles()`].
+    pub fn tile_data(&self) -> impl Iterator<Item = ((i32, i32), LayerTileData)> + '_ {
+        self.chunks
+            .iter()
+            .flat_map(|(pos, chunk)| chunk.tile_data_from(ChunkData::chunk_origin(*pos)))
+    }
+
+    /// Returns the smallest and largest tile positions covered by the chunks of this layer that
+    /// have any tiles in them, or [`None`] if the layer is empty. Both positions are inclusive.
+    ///
+    /// Since the bounds are based on chunks, they may include empty tiles around the edges; See
+    /// [`ChunkData::WIDTH`] and [`ChunkData::HEIGHT`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::{Loader, TileLayer};
+    /// #
+    /// # fn main() {
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+    /// #     .unwrap();
+    /// # let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+    /// #     TileLayer::Infinite(layer) => layer,
+    /// #     _ => panic!("Layer #0 is not infinite"),
+    /// # };
+    /// #
+    /// if let Some(((min_x, min_y), (max_x, max_y))) = layer.bounds() {
+    ///     let size = (max_x - min_x + 1, max_y - min_y + 1);
+    ///     println!("The layer takes up {}x{} tiles", size.0, size.1);
+    /// }
+    /// # }
+    /// ```
+    pub fn bounds(&self) -> Option<((i32, i32), (i32, i32))> {
+        let mut occupied = self
+            .chunks
+            .iter()
+            .filter(|(_, chunk)| chunk.tile_data_from((0, 0)).next().is_some())
+            .map(|(pos, _)| *pos);
+        let first = occupied.next()?;
+        let (min, max) = occupied.fold((first, first), |(min, max), (x, y)| {
+            ((min.0.min(x), min.1.min(y)), (max.0.max(x), max.1.max(y)))
+        });
+        let (width, height) = (ChunkData::WIDTH as i32, ChunkData::HEIGHT as i32);
+        Some((
+            (min.0 * width, min.1 * height),
+            (max.0 * width + (width - 1), max.1 * height + (height - 1)),
+        ))
     }
 
     /// Obtains the tile data present at the position given.
@@ -66,20 +264,12 @@ impl InfiniteTileLayerData {
     /// If the position given is invalid or the position is empty, this function will return [`None`].
     ///
     /// If you want to get a [`Tile`](`crate::Tile`) instead, use [`InfiniteTileLayer::get_tile()`].
-    pub fn get_tile_data(&self, x: i32, y: i32) -> Option<&LayerTileData> {
+    pub fn get_tile_data(&self, x: i32, y: i32) -> Option<LayerTileData> {
         let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
-        self.chunks
-            .get(&chunk_pos)
-            .and_then(|chunk| {
-                let relative_pos = (
-                    x - chunk_pos.0 * ChunkData::WIDTH as i32,
-                    y - chunk_pos.1 * ChunkData::HEIGHT as i32,
-                );
-                let chunk_index =
-                    (relative_pos.0 + relative_pos.1 * ChunkData::WIDTH as i32) as usize;
-                chunk.tiles.get(chunk_index).map(Option::as_ref)
-            })
-            .flatten()
+        self.chunks.get(&chunk_pos)?.get_tile_data(
+            x - chunk_pos.0 * ChunkData::WIDTH as i32,
+            y - chunk_pos.1 * ChunkData::HEIGHT as i32,
+        )
     }
 
     /// Returns an iterator over only the data part of the chunks of this tile layer.
@@ -104,15 +294,218 @@ impl InfiniteTileLayerData {
     pub fn get_chunk_data(&self, x: i32, y: i32) -> Option<&ChunkData> {
         self.chunks.get(&(x, y))
     }
+
+    /// Returns a finite layer with the tiles of this one that are within the given rectangle,
+    /// for converting infinite maps into finite ones. The rectangle is given by its smallest and
+    /// largest tile positions, both inclusive, like the ones [`Self::bounds()`] returns; Its
+    /// smallest position becomes the top-left corner of the finite layer.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the largest position is smaller than the smallest
+    /// one or the rectangle is too large for a finite layer.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerType, Loader, TileLayer};
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")?;
+    /// let layer = match map.get_layer(0).unwrap().layer_type() {
+    ///     LayerType::Tiles(TileLayer::Infinite(layer)) => layer,
+    ///     _ => panic!("Layer #0 is not infinite"),
+    /// };
+    /// let ((min_x, min_y), max) = layer.bounds().unwrap();
+    /// let finite = layer.crop_to_finite(((min_x, min_y), max))?;
+    /// assert_eq!(finite.get_tile_data(0, 0), layer.get_tile_data(min_x, min_y));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn crop_to_finite(
+        &self,
+        ((min_x, min_y), (max_x, max_y)): ((i32, i32), (i32, i32)),
+    ) -> Result<FiniteTileLayerData> {
+        let width = u32::try_from(max_x as i64 - min_x as i64 + 1).ok();
+        let height = u32::try_from(max_y as i64 - min_y as i64 + 1).ok();
+        let (width, height) = match (width, height) {
+            (Some(width), Some(height)) if width > 0 && height > 0 => (width, height),
+            _ => {
+                return Err(Error::InvalidMapData {
+                    description: format!(
+                        "cannot crop to the tiles from ({}, {}) to ({}, {})",
+                        min_x, min_y, max_x, max_y
+                    ),
+                })
+            }
+        };
+        Ok(self.cropped((min_x as i64, min_y as i64), width, height))
+    }
+
+    /// Returns a finite layer of the given size with the tiles of this one, where the given
+    /// position becomes the top-left corner.
+    pub(crate) fn cropped(
+        &self,
+        (min_x, min_y): (i64, i64),
+        width: u32,
+        height: u32,
+    ) -> FiniteTileLayerData {
+        let mut data = FiniteTileLayerData::empty(width, height);
+        data.set_first_gids(self.first_gids.to_vec());
+        for (chunk_pos, chunk) in &self.chunks {
+            let (origin_x, origin_y) = ChunkData::chunk_origin(*chunk_pos);
+            for (index, bits) in chunk.gids.iter().enumerate() {
+                let x = origin_x as i64 + (index % ChunkData::WIDTH as usize) as i64 - min_x;
+                let y = origin_y as i64 + (index / ChunkData::WIDTH as usize) as i64 - min_y;
+                if *bits != 0 && (0..width as i64).contains(&x) && (0..height as i64).contains(&y) {
+                    data.set_bits(x as i32, y as i32, *bits);
+                }
+            }
+        }
+        data
+    }
+
+    /// Makes the tiles of this layer refer to the tilesets at the new indices given by
+    /// `new_indices`, by old index. Tiles from tilesets without a new index are removed.
+    pub(crate) fn remap_tilesets(&mut self, new_indices: &[Option<usize>]) {
+        let old_first_gids = self.first_gids.clone();
+        let remapped = |bits: u32| {
+            LayerTileData::from_bits_with_first_gids(bits, &old_first_gids)
+                .and_then(|tile| remap_tile(tile, new_indices))
+        };
+        let first_gids = compact_first_gids(
+            self.chunks
+                .values()
+                .flat_map(|chunk| chunk.gids.iter())
+                .filter_map(|bits| remapped(*bits)),
+        );
+        for chunk in self.chunks.values_mut() {
+            for bits in chunk.gids.iter_mut() {
+                *bits = remapped(*bits)
+                    .and_then(|tile| encode_tile(&first_gids, tile))
+                    .unwrap_or(0);
+            }
+        }
+        self.set_first_gids(first_gids);
+    }
+}
+
+/// A mutable reference to the data of an infinite tile layer, accessed via
+/// [`LayerMut::as_infinite_tile_layer_mut()`](crate::LayerMut::as_infinite_tile_layer_mut).
+///
+/// Like [`FiniteTileLayerDataMut`](super::FiniteTileLayerDataMut), it checks tiles against the
+/// tilesets of the layer's map before placing them.
+#[derive(Debug)]
+pub struct InfiniteTileLayerDataMut<'map> {
+    tilesets: MapTilesets<'map>,
+    data: &'map mut InfiniteTileLayerData,
+}
+
+impl<'map> InfiniteTileLayerDataMut<'map> {
+    #[inline]
+    pub(crate) fn new(tilesets: MapTilesets<'map>, data: &'map mut InfiniteTileLayerData) -> Self {
+        Self { tilesets, data }
+    }
+
+    /// Replaces the tile at the given position, or clears it if `tile` is [`None`]. The chunk
+    /// that contains the position is created if it doesn't exist yet, unless the tile is being
+    /// cleared.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the tile isn't part of the map's tilesets. Tiles of
+    /// tilesets that haven't been loaded yet are only checked against the map's amount of
+    /// tilesets; See [`Map::tileset`](crate::Map::tileset).
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerTileData, Loader};
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")?;
+    /// let mut layer = map
+    ///     .get_layer_mut(0)
+    ///     .and_then(|layer| layer.as_infinite_tile_layer_mut())
+    ///     .unwrap();
+    /// layer.set_tile(-1000, 1000, Some(LayerTileData::new(0, 12)))?;
+    /// assert_eq!(layer.get_tile_data(-1000, 1000).unwrap().id(), 12);
+    /// layer.set_tile(-1000, 1000, None)?;
+    /// layer.remove_empty_chunks();
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_tile(&mut self, x: i32, y: i32, tile: Option<LayerTileData>) -> Result<()> {
+        if let Some(tile) = tile {
+            check_tile(self.tilesets, tile)?;
+        }
+        self.data.set_tile_data(x, y, tile)
+    }
+
+    /// Removes the chunks that have no tiles left in them, such as the ones whose tiles have all
+    /// been cleared with [`Self::set_tile`].
+    pub fn remove_empty_chunks(&mut self) {
+        self.data.remove_empty_chunks();
+    }
+}
+
+impl<'map> std::ops::Deref for InfiniteTileLayerDataMut<'map> {
+    type Target = InfiniteTileLayerData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        self.data
+    }
 }
 
 /// Part of an infinite tile layer's data.
 ///
 /// Has only the tile data contained within and not a reference to the map it is part of.
 /// In 99.99% of cases you'll actually want to use [`Chunk`].
-#[derive(Debug, PartialEq, Clone)]
+#[derive(Debug, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ChunkData {
-    tiles: Box<[Option<LayerTileData>; Self::TILE_COUNT]>,
+    /// The [`Gid`] of each tile along with its flipping bits, arranged in rows. Tiles are only
+    /// turned into [`LayerTileData`] when accessed.
+    #[cfg_attr(feature = "serde", serde(with = "serde_chunk_tiles"))]
+    gids: Box<[u32; Self::TILE_COUNT]>,
+    /// The first [`Gid`] of each tileset the gids refer to, shared with the rest of the layer.
+    first_gids: Arc<[Gid]>,
+}
+
+impl PartialEq for ChunkData {
+    fn eq(&self, other: &Self) -> bool {
+        // Chunks with the same tiles may number their tilesets differently.
+        (self.first_gids == other.first_gids && self.gids == other.gids)
+            || self.tile_data_from((0, 0)).eq(other.tile_data_from((0, 0)))
+    }
+}
+
+/// Serde only supports arrays of up to 32 elements, so chunk tiles are (de)serialized as a
+/// sequence instead.
+#[cfg(feature = "serde")]
+mod serde_chunk_tiles {
+    use std::convert::TryInto;
+
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    use super::ChunkData;
+
+    type Tiles = Box<[u32; ChunkData::TILE_COUNT]>;
+
+    pub(super) fn serialize<S: Serializer>(
+        tiles: &Tiles,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(tiles.iter())
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Tiles, D::Error> {
+        let tiles = Vec::<u32>::deserialize(deserializer)?;
+        let len = tiles.len();
+        tiles
+            .into_boxed_slice()
+            .try_into()
+            .map_err(|_| D::Error::invalid_length(len, &"a chunk's tile count"))
+    }
 }
 
 impl ChunkData {
@@ -126,9 +519,10 @@ impl ChunkData {
     /// as a breaking change.
     pub const TILE_COUNT: usize = Self::WIDTH as usize * Self::HEIGHT as usize;
 
-    pub(crate) fn new() -> Self {
+    pub(crate) fn new(first_gids: Arc<[Gid]>) -> Self {
         Self {
-            tiles: Box::new([None; Self::TILE_COUNT]),
+            gids: Box::new([0; Self::TILE_COUNT]),
+            first_gids,
         }
     }
 
@@ -137,14 +531,39 @@ impl ChunkData {
     /// If the position given is invalid or the position is empty, this function will return [`None`].
     ///
     /// If you want to get a [`LayerTile`](`crate::LayerTile`) instead, use [`Chunk::get_tile()`].
-    pub fn get_tile_data(&self, x: i32, y: i32) -> Option<&LayerTileData> {
+    pub fn get_tile_data(&self, x: i32, y: i32) -> Option<LayerTileData> {
         if x < Self::WIDTH as i32 && y < Self::HEIGHT as i32 && x >= 0 && y >= 0 {
-            self.tiles[x as usize + y as usize * Self::WIDTH as usize].as_ref()
+            let bits = self.gids[x as usize + y as usize * Self::WIDTH as usize];
+            LayerTileData::from_bits_with_first_gids(bits, &self.first_gids)
         } else {
             None
         }
     }
 
+    /// Returns the position of the top-left-most tile of the chunk at the given position.
+    fn chunk_origin((x, y): (i32, i32)) -> (i32, i32) {
+        (x * ChunkData::WIDTH as i32, y * ChunkData::HEIGHT as i32)
+    }
+
+    /// Returns an iterator over the non-empty tiles in this chunk, with their positions offset by
+    /// the given origin.
+    fn tile_data_from(
+        &self,
+        (origin_x, origin_y): (i32, i32),
+    ) -> impl Iterator<Item = ((i32, i32), LayerTileData)> + '_ {
+        self.gids
+            .iter()
+            .enumerate()
+            .filter_map(move |(index, bits)| {
+                let (x, y) = (
+                    (index % Self::WIDTH as usize) as i32,
+                    (index / Self::WIDTH as usize) as i32,
+                );
+                LayerTileData::from_bits_with_first_gids(*bits, &self.first_gids)
+                    .map(|tile| ((origin_x + x, origin_y + y), tile))
+            })
+    }
+
     /// Returns the position of the chunk that contains the given tile position.
     pub fn tile_to_chunk_pos(x: i32, y: i32) -> (i32, i32) {
         (
@@ -154,10 +573,74 @@ impl ChunkData {
     }
 }
 
-map_wrapper!(
-    #[doc = "Part of an [`InfiniteTileLayer`]."]
-    Chunk => ChunkData
-);
+/// Part of an [`InfiniteTileLayer`].
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Chunk<'map> {
+    map: &'map Map,
+    data: &'map ChunkData,
+    /// The position of the chunk, in chunk coordinates.
+    pos: (i32, i32),
+}
+
+impl<'map> Chunk<'map> {
+    #[inline]
+    fn new(map: &'map Map, data: &'map ChunkData, pos: (i32, i32)) -> Self {
+        Self { map, data, pos }
+    }
+
+    /// Get the map this chunk is from.
+    #[inline]
+    pub fn map(&self) -> &'map Map {
+        self.map
+    }
+
+    /// Returns the position of the top-left-most tile of this chunk in the layer, which is what
+    /// the positions given to [`Chunk::get_tile`] are relative to.
+    #[inline]
+    pub fn origin(&self) -> (i32, i32) {
+        ChunkData::chunk_origin(self.pos)
+    }
+
+    /// Returns an iterator over all the non-empty tiles in this chunk, along with their positions
+    /// in the layer (as opposed to relative to the chunk). Tiles are visited row by row.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::{Loader, TileLayer};
+    /// #
+    /// # fn main() {
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+    /// #     .unwrap();
+    /// # let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+    /// #     TileLayer::Infinite(layer) => layer,
+    /// #     _ => panic!("Layer #0 is not infinite"),
+    /// # };
+    /// #
+    /// for (_, chunk) in layer.chunks() {
+    ///     for ((x, y), tile) in chunk.tiles() {
+    ///         println!("Tile {} at ({}, {})", tile.id(), x, y);
+    ///     }
+    /// }
+    /// # }
+    /// ```
+    pub fn tiles(&self) -> impl Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map {
+        let map = self.map;
+        let data: &'map ChunkData = self.data;
+        data.tile_data_from(self.origin())
+            .map(move |(pos, tile)| (pos, LayerTile::new(map, tile)))
+    }
+}
+
+impl<'map> std::ops::Deref for Chunk<'map> {
+    type Target = ChunkData;
+
+    #[inline]
+    fn deref(&self) -> &'map Self::Target {
+        self.data
+    }
+}
 
 impl<'map> Chunk<'map> {
     /// Obtains the tile present at the position given relative to the chunk's top-left-most tile.
@@ -180,19 +663,20 @@ struct InternalChunk {
     y: i32,
     width: u32,
     height: u32,
-    tiles: Vec<Option<LayerTileData>>,
+    /// Where the chunk element is, for reporting missing tiles.
+    position: XmlPosition,
 }
 
 impl InternalChunk {
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         encoding: Option<String>,
         compression: Option<String>,
-        tilesets: &[MapTilesetGid],
-    ) -> Result<Self> {
+    ) -> Result<(Self, TileDataSource)> {
+        let position = parser.event_position();
         let (x, y, width, height) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("chunk", position) {
                 "x" => x ?= v.parse::<i32>(),
                 "y" => y ?= v.parse::<i32>(),
                 "width" => width ?= v.parse::<u32>(),
@@ -201,15 +685,58 @@ impl InternalChunk {
             (x, y, width, height)
         );
 
-        let tiles = parse_data_line(encoding, compression, parser, tilesets)?;
+        let source = TileDataSource::read(encoding, compression, parser)?;
 
-        Ok(InternalChunk {
-            x,
-            y,
-            width,
-            height,
-            tiles,
-        })
+        Ok((
+            InternalChunk {
+                x,
+                y,
+                width,
+                height,
+                position,
+            },
+            source,
+        ))
+    }
+
+    /// Places the decoded gids of the chunk into the chunks of the layer.
+    fn place(
+        self,
+        gids: Vec<u32>,
+        chunks: &mut HashMap<(i32, i32), ChunkData>,
+        first_gids: &Arc<[Gid]>,
+        parser: &mut impl XmlParser,
+    ) -> Result<()> {
+        let invalid = |path: &Path| Error::InvalidTileFound.located(path, self.position);
+        let size = (self.width as usize)
+            .checked_mul(self.height as usize)
+            .ok_or_else(|| invalid(parser.path()))?;
+        if gids.len() < size {
+            // Tiles missing from the data are left empty if the error is recovered from.
+            let error = invalid(parser.path());
+            parser.recover(error)?;
+        }
+        // Only the tiles present in the data are placed, however large the chunk claims to be.
+        for (index, &gid) in gids.iter().enumerate().take(size) {
+            let x = self.x as i64 + (index % self.width as usize) as i64;
+            let y = self.y as i64 + (index / self.width as usize) as i64;
+            let (x, y) = match (i32::try_from(x), i32::try_from(y)) {
+                (Ok(x), Ok(y)) => (x, y),
+                _ => return Err(invalid(parser.path())),
+            };
+            let chunk_pos = ChunkData::tile_to_chunk_pos(x, y);
+            let relative_pos = (
+                x - chunk_pos.0 * ChunkData::WIDTH as i32,
+                y - chunk_pos.1 * ChunkData::HEIGHT as i32,
+            );
+            let chunk_index = (relative_pos.0 + relative_pos.1 * ChunkData::WIDTH as i32) as usize;
+
+            chunks
+                .entry(chunk_pos)
+                .or_insert_with(|| ChunkData::new(first_gids.clone()))
+                .gids[chunk_index] = gid;
+        }
+        Ok(())
     }
 }
 
@@ -228,6 +755,37 @@ impl<'map> InfiniteTileLayer<'map> {
             .map(|data| LayerTile::new(self.map, data))
     }
 
+    /// Returns an iterator over all the non-empty tiles in this layer, along with their
+    /// positions. Also see [`InfiniteTileLayerData::bounds`].
+    ///
+    /// This iterator doesn't have any particular order.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::{Loader, TileLayer};
+    /// #
+    /// # fn main() {
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+    /// #     .unwrap();
+    /// # let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+    /// #     TileLayer::Infinite(layer) => layer,
+    /// #     _ => panic!("Layer #0 is not infinite"),
+    /// # };
+    /// #
+    /// for ((x, y), tile) in layer.tiles() {
+    ///     println!("Tile {} at ({}, {})", tile.id(), x, y);
+    /// }
+    /// # }
+    /// ```
+    pub fn tiles(&self) -> impl Iterator<Item = ((i32, i32), LayerTile<'map>)> + 'map {
+        let map: &'map crate::Map = self.map;
+        let data: &'map InfiniteTileLayerData = self.data;
+        data.tile_data()
+            .map(move |(pos, tile)| (pos, LayerTile::new(map, tile)))
+    }
+
     /// Returns an iterator over different parts of this map called [`Chunk`]s.
     ///
     /// These **may not** correspond with the chunks in the TMX file, as the chunk size is
@@ -239,7 +797,8 @@ impl<'map> InfiniteTileLayer<'map> {
     /// This iterator doesn't have any particular order.
     ///
     /// ## Example
-    /// ```
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
     /// # use tiled::{Loader, LayerType, TileLayer};
     /// use tiled::ChunkData;
     ///
@@ -272,7 +831,7 @@ impl<'map> InfiniteTileLayer<'map> {
         self.data
             .chunks
             .iter()
-            .map(move |(pos, chunk)| (*pos, Chunk::new(map, chunk)))
+            .map(move |(pos, chunk)| (*pos, Chunk::new(map, chunk, *pos)))
     }
 
     /// Obtains a chunk by its position. To obtain the position of the chunk that contains a tile,
@@ -282,6 +841,6 @@ impl<'map> InfiniteTileLayer<'map> {
         let map: &'map crate::Map = self.map;
         self.data
             .get_chunk_data(x, y)
-            .map(move |data| Chunk::new(map, data))
+            .map(move |data| Chunk::new(map, data, (x, y)))
     }
 }
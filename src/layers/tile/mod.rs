@@ -1,11 +1,11 @@
-use std::collections::HashMap;
-
-use xml::attribute::OwnedAttribute;
+use std::{collections::HashMap, sync::Arc};
 
 use crate::{
+    layers::{LayerData, LayerDataType},
     parse_properties,
-    util::{get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Error, Gid, Map, MapTilesetGid, Properties, Result, Tile, TileId, Tileset,
+    util::{get_attrs, parse_tag, OwnedAttribute, XmlParser},
+    Error, ExtraXml, Gid, Map, MapTilesetGid, ObjectData, Project, Properties, Result, Tile,
+    TileFlip, TileId, Tileset,
 };
 
 mod finite;
@@ -16,7 +16,11 @@ pub use finite::*;
 pub use infinite::*;
 
 /// Stores the internal tile gid about a layer tile, along with how it is flipped.
-#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+///
+/// The default value is the unflipped tile with ID 0 in the first tileset of the map.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 pub struct LayerTileData {
     /// The index of the tileset this tile's in, relative to the tile's map. Guaranteed to be a
     /// valid index of the map tileset container, but **isn't guaranteed to actually contain
@@ -50,6 +54,53 @@ impl LayerTileData {
         self.id
     }
 
+    /// Creates a new, unflipped layer tile referencing the tile with local ID `id` in the map
+    /// tileset at `tileset_index`.
+    ///
+    /// Used for building tile layers in code; See [`TileLayerBuilder`]. The tileset index is
+    /// checked when the layer is added to a map through [`MapBuilder`](crate::MapBuilder).
+    #[inline]
+    pub fn new(tileset_index: usize, id: TileId) -> Self {
+        Self {
+            tileset_index,
+            id,
+            flip_h: false,
+            flip_v: false,
+            flip_d: false,
+        }
+    }
+
+    /// Returns this layer tile with its flips set to the ones given, horizontal first.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::LayerTileData;
+    ///
+    /// let tile = LayerTileData::new(0, 5).with_flips(true, false, true);
+    /// assert!(tile.flip_h && !tile.flip_v && tile.flip_d);
+    /// ```
+    #[inline]
+    pub fn with_flips(mut self, flip_h: bool, flip_v: bool, flip_d: bool) -> Self {
+        self.flip_h = flip_h;
+        self.flip_v = flip_v;
+        self.flip_d = flip_d;
+        self
+    }
+
+    /// Returns how this tile is flipped, as flags that help with applying the flips correctly.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{LayerTileData, TileFlip};
+    ///
+    /// let tile = LayerTileData::new(0, 5).with_flips(true, false, true);
+    /// assert_eq!(tile.flip(), TileFlip::HORIZONTAL | TileFlip::DIAGONAL);
+    /// ```
+    #[inline]
+    pub fn flip(&self) -> TileFlip {
+        TileFlip::new(self.flip_h, self.flip_v, self.flip_d)
+    }
+
     const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
     const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
     const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
@@ -57,8 +108,19 @@ impl LayerTileData {
         | Self::FLIPPED_VERTICALLY_FLAG
         | Self::FLIPPED_DIAGONALLY_FLAG;
 
-    /// Creates a new [`LayerTileData`] from a [`Gid`] plus its flipping bits.
-    pub(crate) fn from_bits(bits: u32, tilesets: &[MapTilesetGid]) -> Option<Self> {
+    /// Creates a new [`LayerTileData`] from a [`Gid`] plus its flipping bits, given the first
+    /// [`Gid`] of each tileset in the map, ordered by tileset index.
+    pub(crate) fn from_bits_with_first_gids(bits: u32, first_gids: &[Gid]) -> Option<Self> {
+        Self::from_bits_with(bits, |gid| {
+            let tileset_index = first_gids.iter().rposition(|first_gid| *first_gid <= gid)?;
+            Some((tileset_index, first_gids[tileset_index]))
+        })
+    }
+
+    fn from_bits_with(
+        bits: u32,
+        find_tileset: impl FnOnce(Gid) -> Option<(usize, Gid)>,
+    ) -> Option<Self> {
         let flags = bits & Self::ALL_FLIP_FLAGS;
         let gid = Gid(bits & !Self::ALL_FLIP_FLAGS);
         let flip_d = flags & Self::FLIPPED_DIAGONALLY_FLAG == Self::FLIPPED_DIAGONALLY_FLAG; // Swap x and y axis (anti-diagonally) [flips over y = -x line]
@@ -68,8 +130,8 @@ impl LayerTileData {
         if gid == Gid::EMPTY {
             None
         } else {
-            let (tileset_index, tileset) = crate::util::get_tileset_for_gid(tilesets, gid)?;
-            let id = gid.0 - tileset.first_gid.0;
+            let (tileset_index, first_gid) = find_tileset(gid)?;
+            let id = gid.0 - first_gid.0;
 
             Some(Self {
                 tileset_index,
@@ -80,6 +142,25 @@ impl LayerTileData {
             })
         }
     }
+
+    /// The largest [`Gid`] that can be stored along with the flipping bits.
+    pub(crate) const MAX_GID: u32 = !Self::ALL_FLIP_FLAGS;
+
+    /// Converts this tile back into a [`Gid`] plus its flipping bits, given the first [`Gid`] of
+    /// its tileset. The resulting [`Gid`] must not be larger than [`Self::MAX_GID`].
+    pub(crate) fn to_bits(self, first_gid: Gid) -> u32 {
+        let mut bits = first_gid.0 + self.id;
+        if self.flip_h {
+            bits |= Self::FLIPPED_HORIZONTALLY_FLAG;
+        }
+        if self.flip_v {
+            bits |= Self::FLIPPED_VERTICALLY_FLAG;
+        }
+        if self.flip_d {
+            bits |= Self::FLIPPED_DIAGONALLY_FLAG;
+        }
+        bits
+    }
 }
 
 /// The raw data of a [`TileLayer`]. Does not include a reference to its parent [`Map`](crate::Map).
@@ -88,6 +169,7 @@ impl LayerTileData {
 /// dereference [`TileLayer`] into this structure, and even if we could, it wouldn't make much
 /// sense, since we can already deref from the finite/infinite tile layers themselves.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub(crate) enum TileLayerData {
     Finite(FiniteTileLayerData),
     Infinite(InfiniteTileLayerData),
@@ -95,13 +177,14 @@ pub(crate) enum TileLayerData {
 
 impl TileLayerData {
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         infinite: bool,
         tilesets: &[MapTilesetGid],
-    ) -> Result<(Self, Properties)> {
+        project: Option<&Project>,
+    ) -> Result<(Self, Properties, ExtraXml)> {
         let (width, height) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("layer", parser.event_position()) {
                 "width" => width ?= v.parse::<u32>(),
                 "height" => height ?= v.parse::<u32>(),
             }
@@ -109,6 +192,7 @@ impl TileLayerData {
         );
         let mut result = Self::Finite(Default::default());
         let mut properties = HashMap::new();
+        let mut extra = ExtraXml::default();
         parse_tag!(parser, "layer", {
             "data" => |attrs| {
                 if infinite {
@@ -119,31 +203,402 @@ impl TileLayerData {
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project)?;
                 Ok(())
             },
-        });
+        } else |name, attrs| extra.read_element(parser, name, attrs));
+
+        Ok((result, properties, extra))
+    }
+
+    /// Makes the tiles of this layer refer to the map tilesets with the given first GIDs. Since
+    /// tile layers store raw gids, this doesn't depend on the tilesets they were parsed with.
+    pub(crate) fn resolve_tilesets(&mut self, first_gids: &[Gid]) {
+        match self {
+            Self::Finite(data) => data.set_first_gids(first_gids.to_vec()),
+            Self::Infinite(data) => data.set_first_gids(first_gids.to_vec()),
+        }
+    }
+
+    /// Changes the size of this layer, moving its tiles by the given amount and dropping the ones
+    /// that end up outside of it. Infinite layers become finite.
+    pub(crate) fn resize(&mut self, width: u32, height: u32, (dx, dy): (i64, i64)) {
+        match self {
+            Self::Finite(data) => data.resize(width, height, (dx, dy)),
+            Self::Infinite(data) => *self = Self::Finite(data.cropped((-dx, -dy), width, height)),
+        }
+    }
+
+    /// Makes the tiles of this layer refer to the tilesets at the new indices given by
+    /// `new_indices`, by old index. Tiles from tilesets without a new index are removed.
+    pub(crate) fn remap_tilesets(&mut self, new_indices: &[Option<usize>]) {
+        match self {
+            Self::Finite(data) => data.remap_tilesets(new_indices),
+            Self::Infinite(data) => data.remap_tilesets(new_indices),
+        }
+    }
 
-        Ok((result, properties))
+    /// Obtains the tile data present at the position given, or [`None`] if it's empty or out of
+    /// bounds.
+    pub(crate) fn get_tile_data(&self, x: i32, y: i32) -> Option<LayerTileData> {
+        match self {
+            Self::Finite(data) => data.get_tile_data(x, y),
+            Self::Infinite(data) => data.get_tile_data(x, y),
+        }
+    }
+
+    /// Iterates over the positions and data of all of the tiles present in this layer.
+    pub(crate) fn tile_data(&self) -> Box<dyn Iterator<Item = ((i32, i32), LayerTileData)> + '_> {
+        match self {
+            Self::Finite(data) => Box::new(data.tile_data()),
+            Self::Infinite(data) => Box::new(data.tile_data()),
+        }
+    }
+
+    /// Checks that this layer can be part of the given map; See [`LayerData::check_for_map`].
+    pub(crate) fn check_for_map(&self, map: &Map) -> Result<()> {
+        let tileset_count = map.tilesets().len();
+        let mut tiles: Box<dyn Iterator<Item = LayerTileData>> = match (self, map.infinite()) {
+            (Self::Finite(data), false)
+                if (data.width(), data.height()) != (map.width, map.height) =>
+            {
+                return Err(Error::InvalidMapData {
+                    description: format!(
+                        "tile layer is {}x{} tiles, but the map is {}x{}",
+                        data.width(),
+                        data.height(),
+                        map.width,
+                        map.height
+                    ),
+                })
+            }
+            (Self::Finite(data), false) => Box::new(data.tile_data().map(|(_, tile)| tile)),
+            (Self::Infinite(data), true) => Box::new(data.tile_data().map(|(_, tile)| tile)),
+            (Self::Finite(_), true) => {
+                return Err(Error::InvalidMapData {
+                    description: "infinite maps cannot contain finite tile layers".to_owned(),
+                })
+            }
+            (Self::Infinite(_), false) => {
+                return Err(Error::InvalidMapData {
+                    description: "finite maps cannot contain infinite tile layers".to_owned(),
+                })
+            }
+        };
+        match tiles.find(|tile| tile.tileset_index >= tileset_count) {
+            Some(tile) => Err(Error::InvalidMapData {
+                description: format!(
+                    "tile references tileset index {}, but the map only has {} tilesets",
+                    tile.tileset_index, tileset_count
+                ),
+            }),
+            None => Ok(()),
+        }
     }
 }
 
-map_wrapper!(
-    #[doc = "An instance of a [`Tile`] present in a [`TileLayer`]."]
-    LayerTile => LayerTileData
-);
+/// Builds a tile layer in code, without parsing any files.
+///
+/// The built layer can then be added to a map with [`MapBuilder::layer`](crate::MapBuilder::layer).
+/// Attributes not covered by the builder, such as opacity or offsets, can be set directly in the
+/// resulting [`LayerData`].
+///
+/// ## Example
+/// ```
+/// use tiled::{LayerTileData, TileLayerBuilder};
+///
+/// # fn main() -> tiled::Result<()> {
+/// let mut layer = TileLayerBuilder::new(4, 4)
+///     .id(1)
+///     .name("Ground")
+///     .set_tile(2, 3, Some(LayerTileData::new(0, 5)))?
+///     .build();
+/// layer.opacity = 0.5;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct TileLayerBuilder {
+    id: u32,
+    name: String,
+    properties: Properties,
+    data: TileLayerData,
+}
+
+impl TileLayerBuilder {
+    /// Creates a builder for an empty finite tile layer of the given size, in tiles.
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_data(TileLayerData::Finite(FiniteTileLayerData::empty(
+            width, height,
+        )))
+    }
+
+    /// Creates a builder for an empty infinite tile layer, to be used in infinite maps.
+    pub fn new_infinite() -> Self {
+        Self::with_data(TileLayerData::Infinite(InfiniteTileLayerData::empty()))
+    }
+
+    fn with_data(data: TileLayerData) -> Self {
+        Self {
+            id: 0,
+            name: String::new(),
+            properties: HashMap::new(),
+            data,
+        }
+    }
+
+    /// Sets the layer's ID, which should be unique within its map.
+    pub fn id(mut self, id: u32) -> Self {
+        self.id = id;
+        self
+    }
+
+    /// Sets the layer's name.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.name = name.into();
+        self
+    }
+
+    /// Sets the layer's custom properties.
+    pub fn properties(mut self, properties: Properties) -> Self {
+        self.properties = properties;
+        self
+    }
+
+    /// Places a tile at the given position, or clears it if `tile` is [`None`].
+    ///
+    /// Returns an error if the layer is finite and the position is out of its bounds.
+    pub fn set_tile(mut self, x: i32, y: i32, tile: Option<LayerTileData>) -> Result<Self> {
+        match &mut self.data {
+            TileLayerData::Finite(data) => data.set_tile_data(x, y, tile)?,
+            TileLayerData::Infinite(data) => data.set_tile_data(x, y, tile)?,
+        }
+        Ok(self)
+    }
+
+    /// Builds the layer.
+    pub fn build(self) -> LayerData {
+        LayerData::with_type(
+            self.id,
+            self.name,
+            self.properties,
+            LayerDataType::Tiles(self.data),
+        )
+    }
+}
+
+/// An instance of a [`Tile`] present in a [`TileLayer`].
+///
+/// Unlike most other map wrappers, this one holds its [`LayerTileData`] by value, since tile layers
+/// don't necessarily store it as such.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct LayerTile<'map> {
+    pub(crate) map: &'map Map,
+    pub(crate) data: LayerTileData,
+}
+
+impl<'map> LayerTile<'map> {
+    #[inline]
+    pub(crate) fn new(map: &'map Map, data: LayerTileData) -> Self {
+        Self { map, data }
+    }
+
+    /// Get the map this tile is from.
+    #[inline]
+    pub fn map(&self) -> &'map Map {
+        self.map
+    }
+}
+
+impl std::ops::Deref for LayerTile<'_> {
+    type Target = LayerTileData;
+
+    #[inline]
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
 
 impl<'map> LayerTile<'map> {
-    /// Get a reference to the layer tile's referenced tile, if it exists.
+    /// Get a reference to the layer tile's referenced tile, if it exists and its tileset has
+    /// been loaded.
     #[inline]
     pub fn get_tile(&self) -> Option<Tile<'map>> {
-        self.get_tileset().get_tile(self.data.id)
+        self.try_get_tileset()?.get_tile(self.data.id)
     }
     /// Get a reference to the layer tile's referenced tileset.
+    ///
+    /// ## Panics
+    /// Panics if the map was loaded with [lazy tilesets](crate::Loader::set_lazy_tilesets) and
+    /// the tileset hasn't been loaded yet; Use [`Self::try_get_tileset`] for such maps.
     #[inline]
     pub fn get_tileset(&self) -> &'map Tileset {
-        // SAFETY: `tileset_index` is guaranteed to be valid
-        &self.map.tilesets()[self.data.tileset_index]
+        self.try_get_tileset()
+            .expect("the tile's tileset hasn't been loaded yet")
+    }
+
+    /// Get a reference to the layer tile's referenced tileset, or [`None`] if the map was loaded
+    /// with [lazy tilesets](crate::Loader::set_lazy_tilesets) and the tileset hasn't been loaded
+    /// yet; See [`Map::tileset`].
+    #[inline]
+    pub fn try_get_tileset(&self) -> Option<&'map Tileset> {
+        self.map
+            .tileset(self.data.tileset_index)
+            .map(|tileset| &**tileset)
+    }
+
+    /// Returns the [`Gid`] of this tile in its map, which is what map files store for it along
+    /// with its flipping bits. Also see [`Map::tile_for_gid`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    /// let tile = layer.get_tile(0, 0).unwrap();
+    /// let (_, same_tile) = map.tile_for_gid(tile.gid().0).unwrap();
+    /// assert_eq!(same_tile.id(), tile.id());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn gid(&self) -> Gid {
+        Gid(self.map.tileset_first_gids()[self.data.tileset_index].0 + self.data.id)
+    }
+
+    /// Returns an owned copy of this tile, which holds onto its tileset instead of borrowing its
+    /// map. Useful for keeping tiles around after the map has been dropped or modified.
+    ///
+    /// ## Panics
+    /// Panics if the tile's tileset hasn't been loaded yet, like [`Self::get_tileset`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let owned = {
+    ///     let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    ///     let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    ///     layer.get_tile(0, 0).unwrap().to_owned_tile()
+    /// };
+    /// assert_eq!(owned.tileset.name, "tilesheet");
+    /// assert!(owned.get_tile().is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_owned_tile(&self) -> OwnedLayerTile {
+        let tileset = self
+            .map
+            .tileset(self.data.tileset_index)
+            .expect("the tile's tileset hasn't been loaded yet");
+        OwnedLayerTile::new(self.data, tileset.clone())
+    }
+
+    /// Returns the collision shapes of this tile (see [`TileData::collision`]) positioned in map
+    /// coordinates, for the tile placed at the given tile position of a layer offset by
+    /// `offset_x` and `offset_y` pixels.
+    ///
+    /// The shapes are flipped along with the tile, and moved by the tileset's drawing offset. Tile
+    /// images are aligned to the bottom-left corner of their cell, as described in
+    /// [`Map::tile_to_pixel`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let layer = map.get_layer(0).unwrap();
+    /// let tiles = layer.as_tile_layer().unwrap();
+    /// if let Some(tile) = tiles.get_tile(3, 5) {
+    ///     for shape in tile.collision_shapes(3, 5, layer.offset_x, layer.offset_y) {
+    ///         println!("Collision shape bounds: {:?}", shape.bounding_box());
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TileData::collision`]: crate::TileData::collision
+    pub fn collision_shapes(
+        &self,
+        x: i32,
+        y: i32,
+        offset_x: f32,
+        offset_y: f32,
+    ) -> impl Iterator<Item = ObjectData> + 'map {
+        // Tiles whose tileset isn't loaded have no collision shapes, so the defaults don't matter.
+        let tileset = self.try_get_tileset();
+        let tile = self.get_tile();
+        let size = match (tile.as_ref().and_then(|tile| tile.image.as_ref()), tileset) {
+            (Some(image), _) => (image.width as f32, image.height as f32),
+            (None, Some(tileset)) => (tileset.tile_width as f32, tileset.tile_height as f32),
+            (None, None) => (0.0, 0.0),
+        };
+        let (tileset_x, tileset_y) =
+            tileset.map_or((0, 0), |tileset| (tileset.offset_x, tileset.offset_y));
+        let (cell_x, cell_y) = self.map.tile_to_pixel(x, y);
+        let image_x = cell_x + tileset_x as f32 + offset_x;
+        let image_y = cell_y + self.map.tile_height as f32 - size.1 + tileset_y as f32 + offset_y;
+        let (flip_h, flip_v, flip_d) = (self.data.flip_h, self.data.flip_v, self.data.flip_d);
+
+        tile.and_then(|tile| tile.data.collision.as_ref())
+            .map(|collision| collision.object_data())
+            .unwrap_or_default()
+            .iter()
+            .map(move |object| {
+                let mut object = object.clone();
+                object.flip(flip_h, flip_v, flip_d, size);
+                object.translate(image_x, image_y);
+                object
+            })
+    }
+}
+
+/// An instance of a [`Tile`] present in a tile layer, which owns a reference to its tileset
+/// instead of borrowing the map it's from; See [`LayerTile::to_owned_tile`] and
+/// [`OwnedLayer`](crate::OwnedLayer).
+#[derive(Clone, PartialEq, Debug)]
+pub struct OwnedLayerTile {
+    /// The tileset this tile is in.
+    pub tileset: Arc<Tileset>,
+    /// The local ID of the tile in its tileset.
+    pub id: TileId,
+    /// Whether this tile is flipped on its Y axis (horizontally).
+    pub flip_h: bool,
+    /// Whether this tile is flipped on its X axis (vertically).
+    pub flip_v: bool,
+    /// Whether this tile is flipped diagonally.
+    pub flip_d: bool,
+}
+
+impl OwnedLayerTile {
+    /// Creates an owned tile from the given tile data and the tileset it refers to.
+    pub(crate) fn new(data: LayerTileData, tileset: Arc<Tileset>) -> Self {
+        Self {
+            tileset,
+            id: data.id,
+            flip_h: data.flip_h,
+            flip_v: data.flip_v,
+            flip_d: data.flip_d,
+        }
+    }
+
+    /// Get a reference to the tile this layer tile is referencing, if it exists.
+    #[inline]
+    pub fn get_tile(&self) -> Option<Tile<'_>> {
+        self.tileset.get_tile(self.id)
+    }
+
+    /// Returns how this tile is flipped, as flags that help with applying the flips correctly.
+    #[inline]
+    pub fn flip(&self) -> TileFlip {
+        TileFlip::new(self.flip_h, self.flip_v, self.flip_d)
     }
 }
 
@@ -177,7 +632,8 @@ impl<'map> TileLayer<'map> {
     /// The width of this layer, if finite, or `None` if infinite.
     ///
     /// ## Example
-    /// ```
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
     /// use tiled::LayerType;
     /// use tiled::Loader;
     ///
@@ -211,7 +667,8 @@ impl<'map> TileLayer<'map> {
     /// The height of this layer, if finite, or `None` if infinite.
     ///
     /// ## Example
-    /// ```
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
     /// use tiled::LayerType;
     /// use tiled::Loader;
     ///
@@ -1,90 +1,240 @@
-use std::{convert::TryInto, io::Read};
-
-use base64::Engine;
-use xml::reader::XmlEvent;
-
-use crate::{util::XmlEventResult, CsvDecodingError, Error, LayerTileData, MapTilesetGid, Result};
-
-pub(crate) fn parse_data_line(
-    encoding: Option<String>,
-    compression: Option<String>,
-    parser: &mut impl Iterator<Item = XmlEventResult>,
-    tilesets: &[MapTilesetGid],
-) -> Result<Vec<Option<LayerTileData>>> {
-    match (encoding.as_deref(), compression.as_deref()) {
-        (Some("csv"), None) => decode_csv(parser, tilesets),
-
-        (Some("base64"), None) => parse_base64(parser).map(|v| convert_to_tiles(&v, tilesets)),
-        (Some("base64"), Some("zlib")) => parse_base64(parser)
-            .and_then(|data| process_decoder(Ok(flate2::bufread::ZlibDecoder::new(&data[..]))))
-            .map(|v| convert_to_tiles(&v, tilesets)),
-        (Some("base64"), Some("gzip")) => parse_base64(parser)
-            .and_then(|data| process_decoder(Ok(flate2::bufread::GzDecoder::new(&data[..]))))
-            .map(|v| convert_to_tiles(&v, tilesets)),
-        #[cfg(feature = "zstd")]
-        (Some("base64"), Some("zstd")) => parse_base64(parser)
-            .and_then(|data| process_decoder(zstd::stream::read::Decoder::with_buffer(&data[..])))
-            .map(|v| convert_to_tiles(&v, tilesets)),
-
-        _ => Err(Error::InvalidEncodingFormat {
-            encoding,
-            compression,
-        }),
-    }
-}
-
-fn parse_base64(parser: &mut impl Iterator<Item = XmlEventResult>) -> Result<Vec<u8>> {
-    for next in parser {
-        match next.map_err(Error::XmlDecodingError)? {
-            XmlEvent::Characters(s) => {
-                return base64::engine::GeneralPurpose::new(
-                    &base64::alphabet::STANDARD,
-                    base64::engine::general_purpose::PAD,
-                )
-                .decode(s.trim().as_bytes())
-                .map_err(Error::Base64DecodingError);
+use std::{
+    convert::TryInto,
+    io::{ErrorKind, Read},
+    path::PathBuf,
+    sync::Arc,
+};
+
+use crate::{
+    decompress::compression_feature,
+    map::MapTilesets,
+    util::{get_attrs, XmlEvent, XmlParser},
+    CsvDecodingError, Decompressor, Error, Gid, LayerTileData, Result, XmlPosition,
+};
+
+/// The still encoded contents of a `data` or `chunk` element, which can be decoded separately from
+/// parsing the rest of the file.
+#[derive(Debug, Clone)]
+pub(crate) struct TileDataSource {
+    contents: TileDataContents,
+    /// The most bytes the contents can decode to; See
+    /// [`ParseLimits::max_decompressed_size`](crate::ParseLimits::max_decompressed_size).
+    max_size: Option<usize>,
+    /// Where the element is, for reporting decoding errors.
+    path: PathBuf,
+    position: XmlPosition,
+}
+
+#[derive(Debug, Clone)]
+enum TileDataContents {
+    /// Text in the given format, if the element has any.
+    Encoded(TileDataFormat, Option<String>),
+    /// The GIDs of the `tile` children of an element without an encoding, which is how data is
+    /// stored in the plain XML format. These don't need any decoding.
+    Xml(Vec<u32>),
+}
+
+#[derive(Debug, Clone)]
+enum TileDataFormat {
+    Csv,
+    Base64,
+    #[cfg(feature = "flate2")]
+    Zlib,
+    #[cfg(feature = "flate2")]
+    Gzip,
+    #[cfg(feature = "zstd")]
+    Zstd,
+    /// Base64 with a compression handled by a decompressor set on the loader.
+    Custom(Arc<dyn Decompressor>),
+}
+
+impl TileDataSource {
+    /// Reads the contents of the `data` or `chunk` element being parsed, which has just been
+    /// opened. Fails if the encoding and compression given aren't supported.
+    ///
+    /// Elements without an encoding or compression store their tiles as `tile` children, which
+    /// are read right away.
+    pub(crate) fn read(
+        encoding: Option<String>,
+        compression: Option<String>,
+        parser: &mut impl XmlParser,
+    ) -> Result<Self> {
+        let path = parser.path().to_owned();
+        let position = parser.event_position();
+        // Decompressors set on the loader take precedence over the built-in ones.
+        let custom = match (encoding.as_deref(), compression.as_deref()) {
+            (Some("base64"), Some(compression)) => {
+                parser.options().decompressors().get(compression).cloned()
             }
-            XmlEvent::EndElement { name, .. } if name.local_name == "data" => {
-                return Ok(Vec::new());
+            _ => None,
+        };
+        let format = match (encoding.as_deref(), compression.as_deref(), custom) {
+            (_, _, Some(decompressor)) => TileDataFormat::Custom(decompressor),
+            (None, None, _) => {
+                return Ok(Self {
+                    contents: TileDataContents::Xml(read_xml_gids(parser)?),
+                    max_size: None,
+                    path,
+                    position,
+                })
+            }
+            (Some("csv"), None, _) => TileDataFormat::Csv,
+            (Some("base64"), None, _) => TileDataFormat::Base64,
+            #[cfg(feature = "flate2")]
+            (Some("base64"), Some("zlib"), _) => TileDataFormat::Zlib,
+            #[cfg(feature = "flate2")]
+            (Some("base64"), Some("gzip"), _) => TileDataFormat::Gzip,
+            #[cfg(feature = "zstd")]
+            (Some("base64"), Some("zstd"), _) => TileDataFormat::Zstd,
+            (Some("base64"), Some(compression), _) => {
+                return Err(Error::UnsupportedCompression {
+                    compression: compression.to_owned(),
+                    feature: compression_feature(compression),
+                })
+            }
+            _ => {
+                return Err(Error::InvalidEncodingFormat {
+                    encoding,
+                    compression,
+                })
+            }
+        };
+        Ok(Self {
+            contents: TileDataContents::Encoded(format, read_data_text(parser)?),
+            max_size: parser.options().limits().max_decompressed_size,
+            path,
+            position,
+        })
+    }
+
+    /// Decodes the contents into the [`Gid`](crate::Gid)s of the tiles, along with their flipping
+    /// bits.
+    pub(crate) fn decode(self) -> Result<Vec<u32>> {
+        let mut gids = Vec::new();
+        self.decode_into(&mut gids)?;
+        Ok(gids)
+    }
+
+    /// Decodes the contents into the given sink, one gid at a time.
+    ///
+    /// Base64 data is decoded and decompressed as a stream, straight into the sink, so no
+    /// intermediate buffers are needed.
+    fn decode_into(self, sink: &mut impl GidSink) -> Result<()> {
+        let Self {
+            contents,
+            max_size,
+            path,
+            position,
+        } = self;
+        let (format, text) = match contents {
+            TileDataContents::Xml(gids) => {
+                gids.into_iter().for_each(|bits| sink.push(bits));
+                return Ok(());
+            }
+            TileDataContents::Encoded(_, None) => return Ok(()),
+            TileDataContents::Encoded(format, Some(text)) => (format, text),
+        };
+        match format {
+            TileDataFormat::Csv => decode_csv(&text, sink),
+            TileDataFormat::Base64 => decode_base64(&text, max_size, sink, |data| Ok(data)),
+            #[cfg(feature = "flate2")]
+            TileDataFormat::Zlib => decode_base64(&text, max_size, sink, |data| {
+                Ok(Box::new(flate2::bufread::ZlibDecoder::new(
+                    std::io::BufReader::new(data),
+                )))
+            }),
+            #[cfg(feature = "flate2")]
+            TileDataFormat::Gzip => decode_base64(&text, max_size, sink, |data| {
+                Ok(Box::new(flate2::bufread::GzDecoder::new(
+                    std::io::BufReader::new(data),
+                )))
+            }),
+            #[cfg(feature = "zstd")]
+            TileDataFormat::Zstd => decode_base64(&text, max_size, sink, |data| {
+                Ok(Box::new(zstd::stream::read::Decoder::new(data)?))
+            }),
+            TileDataFormat::Custom(decompressor) => {
+                decode_base64(&text, max_size, sink, |data| decompressor.decompress(data))
             }
-            _ => {}
         }
+        .map_err(|err| err.located(&path, position))
+    }
+
+    /// Decodes the contents into the given sink like [`TileDataSource::decode`], failing with
+    /// [`Error::InvalidTileFound`] unless they hold exactly `count` tiles.
+    pub(crate) fn decode_exact(self, count: usize, sink: &mut impl GidSink) -> Result<()> {
+        let (path, position) = (self.path.clone(), self.position);
+        self.decode_into(sink)?;
+        if sink.count() != count {
+            return Err(Error::InvalidTileFound.located(&path, position));
+        }
+        Ok(())
     }
-    Err(Error::PrematureEnd("Ran out of XML data".to_owned()))
 }
 
-fn process_decoder(decoder: std::io::Result<impl Read>) -> Result<Vec<u8>> {
-    decoder
-        .and_then(|mut decoder| {
-            let mut data = Vec::new();
-            decoder.read_to_end(&mut data)?;
-            Ok(data)
-        })
-        .map_err(Error::DecompressingError)
+/// Receives the gids of tiles, along with their flipping bits, as they are decoded.
+pub(crate) trait GidSink {
+    /// Adds the gid of the next tile.
+    fn push(&mut self, bits: u32);
+
+    /// The number of gids added so far.
+    fn count(&self) -> usize;
 }
 
-fn decode_csv(
-    parser: &mut impl Iterator<Item = XmlEventResult>,
-    tilesets: &[MapTilesetGid],
-) -> Result<Vec<Option<LayerTileData>>> {
+impl GidSink for Vec<u32> {
+    fn push(&mut self, bits: u32) {
+        Vec::push(self, bits);
+    }
+
+    fn count(&self) -> usize {
+        self.len()
+    }
+}
+
+/// Decodes all the given sources in parallel.
+#[cfg(feature = "rayon")]
+pub(crate) fn decode_all(sources: Vec<TileDataSource>) -> Result<Vec<Vec<u32>>> {
+    use rayon::prelude::*;
+    sources
+        .into_par_iter()
+        .map(TileDataSource::decode)
+        .collect()
+}
+
+/// Returns the text contents of the `data` or `chunk` element being parsed, or [`None`] if it
+/// has none.
+fn read_data_text(parser: &mut impl XmlParser) -> Result<Option<String>> {
     for next in parser {
         match next.map_err(Error::XmlDecodingError)? {
-            XmlEvent::Characters(s) => {
-                let mut tiles = Vec::new();
-                for v in s.split(',') {
-                    match v.trim().parse() {
-                        Ok(bits) => tiles.push(LayerTileData::from_bits(bits, tilesets)),
-                        Err(e) => {
-                            return Err(Error::CsvDecodingError(
-                                CsvDecodingError::TileDataParseError(e),
-                            ))
-                        }
+            XmlEvent::Characters(s) => return Ok(Some(s)),
+            XmlEvent::EndElement { name, .. } if is_data_element(&name.local_name) => {
+                return Ok(None)
+            }
+            _ => {}
+        }
+    }
+    Err(Error::PrematureEnd("Ran out of XML data".to_owned()))
+}
+
+/// Reads the GIDs of the `tile` children of the `data` or `chunk` element being parsed, until
+/// it is closed. Children without a `gid` attribute are empty tiles.
+fn read_xml_gids(parser: &mut impl XmlParser) -> Result<Vec<u32>> {
+    let mut gids = Vec::new();
+    while let Some(next) = parser.next() {
+        match next.map_err(Error::XmlDecodingError)? {
+            XmlEvent::StartElement {
+                name, attributes, ..
+            } if name.local_name == "tile" => {
+                let gid = get_attrs!(
+                    for v in attributes of ("tile", parser.event_position()) {
+                        Some("gid") => gid ?= v.parse::<u32>(),
                     }
-                }
-                return Ok(tiles);
+                    gid
+                );
+                gids.push(gid.unwrap_or(0));
             }
-            XmlEvent::EndElement { name, .. } if name.local_name == "data" => {
-                return Ok(Vec::new());
+            XmlEvent::EndElement { name, .. } if is_data_element(&name.local_name) => {
+                return Ok(gids)
             }
             _ => {}
         }
@@ -92,11 +242,224 @@ fn decode_csv(
     Err(Error::PrematureEnd("Ran out of XML data".to_owned()))
 }
 
-fn convert_to_tiles(data: &[u8], tilesets: &[MapTilesetGid]) -> Vec<Option<LayerTileData>> {
-    data.chunks_exact(4)
-        .map(|chunk| {
-            let bits = u32::from_le_bytes(chunk.try_into().unwrap());
-            LayerTileData::from_bits(bits, tilesets)
+fn is_data_element(name: &str) -> bool {
+    name == "data" || name == "chunk"
+}
+
+/// Decodes base64 tile data into `sink`, passing it through the decompressor returned by
+/// `decompress`. Fails if it decodes to more than `max_size` bytes.
+fn decode_base64(
+    text: &str,
+    max_size: Option<usize>,
+    sink: &mut impl GidSink,
+    decompress: impl FnOnce(Box<dyn Read + '_>) -> std::io::Result<Box<dyn Read + '_>>,
+) -> Result<()> {
+    let data = base64::read::DecoderReader::new(
+        text.trim().as_bytes(),
+        &base64::engine::general_purpose::STANDARD,
+    );
+    decompress(Box::new(data))
+        .and_then(|reader| read_gids(reader, max_size, sink))
+        .map_err(|err| {
+            // Base64 errors are reported through I/O errors by the streaming decoder.
+            match err
+                .get_ref()
+                .and_then(|inner| inner.downcast_ref::<base64::DecodeError>())
+            {
+                Some(inner) => Error::Base64DecodingError(inner.clone()),
+                None if err.kind() == ErrorKind::OutOfMemory => Error::LimitExceeded {
+                    description: format!(
+                        "tile data decoding to more than {} bytes",
+                        max_size.unwrap_or(usize::MAX)
+                    ),
+                },
+                None => Error::DecompressingError(err),
+            }
+        })
+}
+
+/// Reads little-endian gids into `sink` until the end of the given reader. Trailing bytes that
+/// don't make up a whole gid are ignored.
+///
+/// Reading more than `max_size` bytes fails with an error of kind [`ErrorKind::OutOfMemory`].
+fn read_gids(
+    mut reader: Box<dyn Read + '_>,
+    max_size: Option<usize>,
+    sink: &mut impl GidSink,
+) -> std::io::Result<()> {
+    let mut buffer = [0; 4096];
+    let mut filled = 0;
+    let mut total = 0;
+    loop {
+        let read = match reader.read(&mut buffer[filled..]) {
+            Ok(0) => return Ok(()),
+            Ok(read) => read,
+            Err(err) if err.kind() == ErrorKind::Interrupted => continue,
+            Err(err) => return Err(err),
+        };
+        filled += read;
+        total += read;
+        if max_size.is_some_and(|max_size| total > max_size) {
+            return Err(ErrorKind::OutOfMemory.into());
+        }
+        let whole = filled - filled % 4;
+        for chunk in buffer[..whole].chunks_exact(4) {
+            sink.push(u32::from_le_bytes(chunk.try_into().unwrap()));
+        }
+        buffer.copy_within(whole..filled, 0);
+        filled -= whole;
+    }
+}
+
+fn decode_csv(text: &str, sink: &mut impl GidSink) -> Result<()> {
+    for v in text.split(',') {
+        let bits = v
+            .trim()
+            .parse()
+            .map_err(|e| Error::CsvDecodingError(CsvDecodingError::TileDataParseError(e)))?;
+        sink.push(bits);
+    }
+    Ok(())
+}
+
+/// Checks that the tile given is part of the tilesets of a map, before placing it in one of the
+/// map's layers. Only the tileset index can be checked for tilesets that haven't been loaded yet.
+pub(super) fn check_tile(tilesets: MapTilesets, tile: LayerTileData) -> Result<()> {
+    if tile.tileset_index() >= tilesets.len() {
+        return Err(Error::InvalidMapData {
+            description: format!(
+                "tile references tileset index {}, but the map only has {} tilesets",
+                tile.tileset_index(),
+                tilesets.len()
+            ),
+        });
+    }
+    let missing = tilesets
+        .get(tile.tileset_index())
+        .is_some_and(|tileset| tileset.get_tile(tile.id()).is_none());
+    if missing {
+        return Err(Error::InvalidMapData {
+            description: format!(
+                "tile {} doesn't exist in the tileset at index {}",
+                tile.id(),
+                tile.tileset_index()
+            ),
+        });
+    }
+    Ok(())
+}
+
+/// Returns the gid and flipping bits of the tile given, if the gid range of its tileset in
+/// `first_gids` fits it.
+pub(super) fn encode_tile(first_gids: &[Gid], tile: LayerTileData) -> Option<u32> {
+    let first_gid = *first_gids.get(tile.tileset_index())?;
+    let end = match first_gids.get(tile.tileset_index() + 1) {
+        Some(next_first_gid) => next_first_gid.0,
+        None => LayerTileData::MAX_GID + 1,
+    };
+    let gid = first_gid.0.checked_add(tile.id())?;
+    (gid < end).then(|| tile.to_bits(first_gid))
+}
+
+/// Returns the tile given with its tileset index replaced by the new one in `new_indices`, by old
+/// index, or [`None`] if its tileset has no new index.
+pub(super) fn remap_tile(
+    mut tile: LayerTileData,
+    new_indices: &[Option<usize>],
+) -> Option<LayerTileData> {
+    tile.tileset_index = new_indices.get(tile.tileset_index).copied().flatten()?;
+    Some(tile)
+}
+
+/// Returns first gids for the tilesets of a layer with just enough room for the given tiles,
+/// which must have fit in the layer's gid ranges before.
+pub(super) fn compact_first_gids(tiles: impl Iterator<Item = LayerTileData>) -> Vec<Gid> {
+    let mut sizes = Vec::<u32>::new();
+    for tile in tiles {
+        if sizes.len() <= tile.tileset_index {
+            sizes.resize(tile.tileset_index + 1, 0);
+        }
+        let size = &mut sizes[tile.tileset_index];
+        *size = (*size).max(tile.id.saturating_add(1));
+    }
+    let mut first_gid = 1u32;
+    sizes
+        .into_iter()
+        .map(|size| {
+            let gid = Gid(first_gid);
+            first_gid = first_gid.saturating_add(size);
+            gid
         })
         .collect()
 }
+
+/// Returns new first gids for the tilesets of a layer so that all of its current `tiles` plus
+/// `new_tile` fit in their tileset's gid range, for when [`encode_tile`] fails to encode
+/// `new_tile`.
+pub(super) fn renumbered_first_gids(
+    first_gids: &[Gid],
+    tiles: impl Iterator<Item = LayerTileData>,
+    new_tile: LayerTileData,
+) -> Result<Vec<Gid>> {
+    // Every tileset needs a first gid, so there can't be more tilesets than gids.
+    if new_tile.tileset_index() >= LayerTileData::MAX_GID as usize {
+        return Err(Error::InvalidMapData {
+            description: format!(
+                "tileset index {} is too large to be stored in the layer",
+                new_tile.tileset_index()
+            ),
+        });
+    }
+    let tileset_count = first_gids.len().max(new_tile.tileset_index() + 1);
+    // The amount of gids each tileset currently has, plus the amount it needs.
+    let mut ranges = vec![(0u64, 0u64); tileset_count];
+    for (index, window) in first_gids.windows(2).enumerate() {
+        ranges[index].0 = (window[1].0 - window[0].0) as u64;
+    }
+    for tile in tiles.chain(std::iter::once(new_tile)) {
+        let needed = &mut ranges[tile.tileset_index()].1;
+        *needed = (*needed).max(tile.id() as u64 + 1);
+    }
+
+    // Grow ranges geometrically so that adding tiles one by one doesn't renumber the layer each
+    // time, unless that wouldn't leave enough gids for every tileset.
+    let grown = |(current, needed): (u64, u64)| {
+        if needed > current {
+            needed.max(current * 2)
+        } else {
+            current
+        }
+    };
+    let max_gid = LayerTileData::MAX_GID as u64;
+    let sizes: Vec<u64> = if ranges.iter().copied().map(grown).sum::<u64>() <= max_gid {
+        ranges.iter().copied().map(grown).collect()
+    } else if ranges
+        .iter()
+        .map(|(current, needed)| *current.max(needed))
+        .sum::<u64>()
+        <= max_gid
+    {
+        ranges
+            .iter()
+            .map(|(current, needed)| *current.max(needed))
+            .collect()
+    } else {
+        return Err(Error::InvalidMapData {
+            description: format!(
+                "tile ID {} of tileset index {} is too large to be stored in the layer",
+                new_tile.id(),
+                new_tile.tileset_index()
+            ),
+        });
+    };
+
+    let mut first_gid = 1;
+    Ok(sizes
+        .into_iter()
+        .map(|size| {
+            let gid = Gid(first_gid as u32);
+            first_gid += size;
+            gid
+        })
+        .collect())
+}
@@ -1,4 +1,9 @@
-#![doc = include_str!("../README.md")]
+// The examples in the README load maps from the filesystem.
+#![cfg_attr(feature = "filesystem", doc = include_str!("../README.md"))]
+#![cfg_attr(
+    not(feature = "filesystem"),
+    doc = "A crate for reading and writing maps and tilesets made with the Tiled map editor."
+)]
 #![warn(missing_docs)]
 #![deny(rustdoc::broken_intra_doc_links)]
 #![deny(unsafe_code)]
@@ -6,31 +11,70 @@
 #![deny(missing_debug_implementations)]
 
 mod animation;
+#[cfg(feature = "image-loading")]
+mod atlas;
 mod cache;
+mod class;
+mod collision;
+mod compat;
+mod decompress;
+mod diff;
+mod dump;
 mod error;
+mod extra;
+mod flip;
 mod image;
 mod layers;
 mod loader;
 mod map;
 mod objects;
+#[cfg(feature = "outlines")]
+mod outline;
 mod parse;
+mod project;
 mod properties;
 mod reader;
+#[cfg(feature = "bevy_reflect")]
+mod reflect;
+#[cfg(feature = "render-helpers")]
+mod render;
+pub mod stream;
 mod template;
 mod tile;
 mod tileset;
 mod util;
+mod validate;
+mod write;
 
 pub use animation::*;
+#[cfg(feature = "image-loading")]
+pub use atlas::*;
 pub use cache::*;
+pub use class::*;
+pub use collision::*;
+pub use decompress::*;
+pub use diff::*;
 pub use error::*;
+pub use extra::*;
+pub use flip::*;
 pub use image::*;
 pub use layers::*;
 pub use loader::*;
 pub use map::*;
 pub use objects::*;
+#[cfg(feature = "outlines")]
+pub use outline::*;
+pub use project::*;
 pub use properties::*;
 pub use reader::*;
+#[cfg(feature = "bevy_reflect")]
+pub use reflect::*;
+#[cfg(feature = "render-helpers")]
+pub use render::*;
 pub use template::*;
 pub use tile::*;
 pub use tileset::*;
+pub use validate::*;
+
+#[cfg(feature = "derive")]
+pub use tiled_derive::FromProperties;
@@ -1,31 +1,295 @@
-use std::path::Path;
+use std::{io::BufRead, path::Path, sync::Arc};
 
 use crate::{
-    DefaultResourceCache, FilesystemResourceReader, Map, ResourceCache, ResourceReader, Result,
+    cache::{MapSettings, MapSources, RecordingCache, SourceFile, SourceKind},
+    decompress::Decompressors,
+    stream::{MapEvent, MapEvents},
+    util::XmlEvents,
+    Decompressor, DefaultResourceCache, Map, Project, ResourceCache, ResourceReader, Result,
     Tileset,
 };
 
-/// A type used for loading [`Map`]s and [`Tileset`]s.
+#[cfg(feature = "filesystem")]
+use crate::FilesystemResourceReader;
+
+/// How a [`Loader`] deals with problems in the maps it loads.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ErrorTolerance {
+    /// Any problem makes loading fail. This is the default.
+    #[default]
+    Strict,
+    /// Problems that only affect a small part of a map are recorded as [`Warning`]s in
+    /// [`Map::warnings`] and the affected part is left out, instead of making loading fail:
+    /// - Properties with an unknown type or an invalid value are skipped.
+    /// - Polylines, polygons and texts that can't be parsed are replaced by rectangles.
+    /// - Tiles missing from infinite layer chunks are left empty.
+    /// - Tilesets with overlapping GID ranges and layers or objects with duplicate IDs are kept as
+    ///   they are, when they are [denied](Loader::set_deny_conflicts).
+    ///
+    /// [`Warning`]: crate::Warning
+    Lenient,
+}
+
+/// Limits on the size and complexity of the files a [`Loader`] parses, for loading files that
+/// can't be trusted, such as user uploads, without letting them take unbounded time and memory.
+/// Going over a limit makes loading fail with [`Error::LimitExceeded`], regardless of the
+/// [`ErrorTolerance`].
 ///
-/// Internally, it holds a [`ResourceCache`] that, as its name implies, caches intermediate loading
-/// artifacts, most notably map tilesets.
+/// No limits are set by default.
 ///
-/// It also contains a [`ResourceReader`] which is the object in charge of providing read handles
-/// to files via a [`ResourcePath`](crate::ResourcePath).
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// use tiled::{Error, Loader, ParseLimits};
+///
+/// let mut loader = Loader::new().with_limits(ParseLimits {
+///     max_layers: Some(2),
+///     ..ParseLimits::default()
+/// });
+/// let error = loader
+///     .load_tmx_map("assets/tiled_group_layers.tmx")
+///     .unwrap_err();
+/// assert!(matches!(error.inner(), Error::LimitExceeded { .. }));
+/// ```
+///
+/// [`Error::LimitExceeded`]: crate::Error::LimitExceeded
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct ParseLimits {
+    /// The most layers a map can have, including the ones inside group layers.
+    pub max_layers: Option<usize>,
+    /// How deep group layers and class property values can be nested, counted together. A group
+    /// layer at the top of a map or a class property of an object has a depth of 1.
+    pub max_nesting_depth: Option<usize>,
+    /// The most bytes the tile data of a single `<data>` or `<chunk>` element can decode to,
+    /// after being decompressed.
+    pub max_decompressed_size: Option<usize>,
+}
+
+/// The settings a [`Loader`] loads files with, which can be built with [`LoaderOptionsBuilder`]
+/// and set all at once with [`Loader::with_options`].
 ///
-/// ## Reasoning
-/// This type is used for loading operations because they require a [`ResourceCache`] for
-/// intermediate artifacts, so using a type for creation can ensure that the cache is reused if
-/// loading more than one object is required.
+/// Each setting can also be changed on its own through the loader, e.g. with
+/// [`Loader::set_lazy_tilesets`].
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{ErrorTolerance, Loader, LoaderOptionsBuilder, ParseLimits};
+///
+/// let options = LoaderOptionsBuilder::new()
+///     .error_tolerance(ErrorTolerance::Lenient)
+///     .limits(ParseLimits {
+///         max_layers: Some(1000),
+///         ..ParseLimits::default()
+///     })
+///     .build();
+/// let mut loader = Loader::new().with_options(options);
+/// assert_eq!(loader.error_tolerance(), ErrorTolerance::Lenient);
+///
+/// let map = loader.load_tmx_map("assets/tiled_base64.tmx")?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct LoaderOptions {
+    lazy_tilesets: bool,
+    cache_maps: bool,
+    sparse_tile_layer_threshold: Option<f32>,
+    error_tolerance: ErrorTolerance,
+    deny_conflicts: bool,
+    limits: ParseLimits,
+    preserve_unknown_xml: bool,
+    decompressors: Decompressors,
+}
+
+impl LoaderOptions {
+    /// Returns whether maps defer loading their external tilesets. See
+    /// [`Loader::set_lazy_tilesets`].
+    #[inline]
+    pub fn lazy_tilesets(&self) -> bool {
+        self.lazy_tilesets
+    }
+
+    /// Returns whether maps are stored in the cache and reused from it. See
+    /// [`Loader::set_cache_maps`].
+    #[inline]
+    pub fn cache_maps(&self) -> bool {
+        self.cache_maps
+    }
+
+    /// Returns the fraction of empty tiles above which finite tile layers are stored sparsely, if
+    /// any. See [`Loader::set_sparse_tile_layer_threshold`].
+    #[inline]
+    pub fn sparse_tile_layer_threshold(&self) -> Option<f32> {
+        self.sparse_tile_layer_threshold
+    }
+
+    /// Returns how problems in maps are dealt with. See [`Loader::set_error_tolerance`].
+    #[inline]
+    pub fn error_tolerance(&self) -> ErrorTolerance {
+        self.error_tolerance
+    }
+
+    /// Returns whether conflicting IDs and GIDs in maps are handled as errors instead of warnings.
+    /// See [`Loader::set_deny_conflicts`].
+    #[inline]
+    pub fn deny_conflicts(&self) -> bool {
+        self.deny_conflicts
+    }
+
+    /// Returns the limits files are parsed with. See [`Loader::set_limits`].
+    #[inline]
+    pub fn limits(&self) -> ParseLimits {
+        self.limits
+    }
+
+    /// Returns whether the attributes and elements that aren't read into any other field are
+    /// kept in the [`ExtraXml`](crate::ExtraXml) of maps, layers, tilesets and objects. This is
+    /// disabled by default.
+    #[inline]
+    pub fn preserve_unknown_xml(&self) -> bool {
+        self.preserve_unknown_xml
+    }
+
+    /// Returns the decompressor set for the given compression, if any. See
+    /// [`Loader::set_decompressor`].
+    pub fn decompressor(&self, compression: &str) -> Option<&dyn Decompressor> {
+        self.decompressors.get(compression).map(AsRef::as_ref)
+    }
+
+    pub(crate) fn decompressors(&self) -> &Decompressors {
+        &self.decompressors
+    }
+}
+
+/// Builds [`LoaderOptions`], starting from the default value of every setting.
 #[derive(Debug, Clone, Default)]
-pub struct Loader<
-    Cache: ResourceCache = DefaultResourceCache,
-    Reader: ResourceReader = FilesystemResourceReader,
-> {
-    cache: Cache,
-    reader: Reader,
+pub struct LoaderOptionsBuilder {
+    options: LoaderOptions,
+}
+
+impl LoaderOptionsBuilder {
+    /// Creates a builder with the default value of every setting.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets whether maps defer loading their external tilesets. See
+    /// [`Loader::set_lazy_tilesets`].
+    pub fn lazy_tilesets(mut self, lazy_tilesets: bool) -> Self {
+        self.options.lazy_tilesets = lazy_tilesets;
+        self
+    }
+
+    /// Sets whether maps are stored in the cache and reused from it. See
+    /// [`Loader::set_cache_maps`].
+    pub fn cache_maps(mut self, cache_maps: bool) -> Self {
+        self.options.cache_maps = cache_maps;
+        self
+    }
+
+    /// Sets the fraction of empty tiles above which finite tile layers are stored sparsely. See
+    /// [`Loader::set_sparse_tile_layer_threshold`].
+    pub fn sparse_tile_layer_threshold(mut self, threshold: Option<f32>) -> Self {
+        self.options.sparse_tile_layer_threshold = threshold;
+        self
+    }
+
+    /// Sets how problems in maps are dealt with. See [`Loader::set_error_tolerance`].
+    pub fn error_tolerance(mut self, error_tolerance: ErrorTolerance) -> Self {
+        self.options.error_tolerance = error_tolerance;
+        self
+    }
+
+    /// Sets whether conflicting IDs and GIDs in maps are handled as errors instead of warnings.
+    /// See [`Loader::set_deny_conflicts`].
+    pub fn deny_conflicts(mut self, deny_conflicts: bool) -> Self {
+        self.options.deny_conflicts = deny_conflicts;
+        self
+    }
+
+    /// Sets the limits files are parsed with. See [`Loader::set_limits`].
+    pub fn limits(mut self, limits: ParseLimits) -> Self {
+        self.options.limits = limits;
+        self
+    }
+
+    /// Sets whether the attributes and elements that aren't read into any other field are kept.
+    /// See [`LoaderOptions::preserve_unknown_xml`].
+    ///
+    /// ## Note
+    /// Tilesets and templates already in the internal loader cache are reused as they are, even
+    /// if they were loaded without this.
+    pub fn preserve_unknown_xml(mut self, preserve_unknown_xml: bool) -> Self {
+        self.options.preserve_unknown_xml = preserve_unknown_xml;
+        self
+    }
+
+    /// Sets the decompressor used for tile data with the given compression. See
+    /// [`Loader::set_decompressor`].
+    pub fn decompressor(
+        mut self,
+        compression: impl Into<String>,
+        decompressor: impl Decompressor + 'static,
+    ) -> Self {
+        self.options
+            .decompressors
+            .insert(compression.into(), Arc::new(decompressor));
+        self
+    }
+
+    /// Returns the options built.
+    pub fn build(self) -> LoaderOptions {
+        self.options
+    }
+}
+
+impl From<LoaderOptions> for LoaderOptionsBuilder {
+    fn from(options: LoaderOptions) -> Self {
+        Self { options }
+    }
+}
+
+/// Declares [`Loader`] with the given defaults for its type parameters. Defaults can only be given
+/// for the reader if [`FilesystemResourceReader`] exists, and the cache's default has to go too
+/// without it since defaults can't come before parameters without one.
+macro_rules! declare_loader {
+    ($($cache_default:ty)?; $($reader_default:ty)?) => {
+        /// A type used for loading [`Map`]s and [`Tileset`]s.
+        ///
+        /// Internally, it holds a [`ResourceCache`] that, as its name implies, caches intermediate
+        /// loading artifacts, most notably map tilesets.
+        ///
+        /// It also contains a [`ResourceReader`] which is the object in charge of providing read
+        /// handles to files via a [`ResourcePath`](crate::ResourcePath).
+        ///
+        /// Without the `filesystem` feature, the reader has to be given with
+        /// [`Loader::with_reader`] or [`Loader::with_cache_and_reader`].
+        ///
+        /// ## Reasoning
+        /// This type is used for loading operations because they require a [`ResourceCache`] for
+        /// intermediate artifacts, so using a type for creation can ensure that the cache is
+        /// reused if loading more than one object is required.
+        #[derive(Debug, Clone, Default)]
+        pub struct Loader<
+            Cache: ResourceCache $(= $cache_default)?,
+            Reader: ResourceReader $(= $reader_default)?,
+        > {
+            cache: Cache,
+            reader: Reader,
+            project: Option<Project>,
+            options: LoaderOptions,
+        }
+    };
 }
 
+#[cfg(feature = "filesystem")]
+declare_loader!(DefaultResourceCache; FilesystemResourceReader);
+#[cfg(not(feature = "filesystem"))]
+declare_loader!(;);
+
+#[cfg(feature = "filesystem")]
 impl Loader {
     /// Creates a new loader, creating a default resource cache and reader
     /// ([`DefaultResourceCache`] & [`FilesystemResourceReader`] respectively) in the process.
@@ -33,6 +297,8 @@ impl Loader {
         Self {
             cache: DefaultResourceCache::new(),
             reader: FilesystemResourceReader::new(),
+            project: None,
+            options: LoaderOptions::default(),
         }
     }
 }
@@ -68,8 +334,8 @@ impl<Reader: ResourceReader> Loader<DefaultResourceCache, Reader> {
     /// let map = loader.load_tmx_map("/my-map.tmx")?;
     ///
     /// assert_eq!(
-    ///     map.tilesets()[0].image.as_ref().unwrap().source,
-    ///     Path::new("/tilesheet.png")
+    ///     map.tilesets()[0].image.as_ref().unwrap().source.as_path(),
+    ///     Some(Path::new("/tilesheet.png"))
     /// );
     ///
     /// # Ok(())
@@ -79,6 +345,8 @@ impl<Reader: ResourceReader> Loader<DefaultResourceCache, Reader> {
         Self {
             cache: DefaultResourceCache::new(),
             reader,
+            project: None,
+            options: LoaderOptions::default(),
         }
     }
 }
@@ -148,25 +416,279 @@ impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
     /// let map = loader.load_tmx_map("/my-map.tmx")?;
     ///
     /// assert_eq!(
-    ///     map.tilesets()[0].image.as_ref().unwrap().source,
-    ///     Path::new("/tilesheet.png")
+    ///     map.tilesets()[0].image.as_ref().unwrap().source.as_path(),
+    ///     Some(Path::new("/tilesheet.png"))
     /// );
     ///
     /// # Ok(())
     /// # }
     /// ```
     pub fn with_cache_and_reader(cache: Cache, reader: Reader) -> Self {
-        Self { cache, reader }
+        Self {
+            cache,
+            reader,
+            project: None,
+            options: LoaderOptions::default(),
+        }
     }
 
     /// Parses a file hopefully containing a Tiled map and tries to parse it. All external files
     /// will be loaded relative to the path given.
     ///
     /// All intermediate objects such as map tilesets will be stored in the [internal loader cache].
+    /// With [map caching](Loader::set_cache_maps), so is the map itself, and a copy of it is
+    /// returned instead of parsing it again if it's already there.
     ///
     /// [internal loader cache]: Loader::cache()
     pub fn load_tmx_map(&mut self, path: impl AsRef<Path>) -> Result<Map> {
-        crate::parse::xml::parse_map(path.as_ref(), &mut self.reader, &mut self.cache)
+        let path = path.as_ref();
+        if self.options.cache_maps {
+            if let Some(cached) = self.cache.get_map(path) {
+                if cached
+                    .settings
+                    .matches(&self.options, self.project.as_ref())
+                {
+                    return Ok(Map::clone(&cached));
+                }
+            }
+        }
+        self.reload_tmx_map(path)
+    }
+
+    /// Parses a map like [`Loader::load_tmx_map`] does, but without looking for it in the
+    /// [internal loader cache] first. With [map caching](Loader::set_cache_maps), the map is
+    /// still stored in it, replacing any previous version of it.
+    ///
+    /// This is useful to force a map to be parsed again, such as after changing the settings of
+    /// the loader. Tilesets and templates are still taken from the cache.
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn reload_tmx_map(&mut self, path: impl AsRef<Path>) -> Result<Map> {
+        let path = path.as_ref();
+        let mut cache = RecordingCache::new(&mut self.cache);
+        let mut map = crate::parse::xml::parse_map(
+            path,
+            &mut self.reader,
+            &mut cache,
+            self.project.as_ref(),
+            &self.options,
+            &mut MapEvents::none(),
+        )?;
+        let requested = cache.into_requested();
+        map.sources = MapSources(
+            std::iter::once((SourceKind::Map, path.to_owned()))
+                .chain(requested)
+                .map(|(kind, path)| SourceFile::new(kind, path, &mut self.reader))
+                .collect(),
+        );
+        // Maps with lazy tilesets depend on which tilesets were in the cache, so they aren't kept.
+        if self.options.cache_maps && map.lazy_tilesets().is_empty() {
+            map.settings = MapSettings::new(&self.options, self.project.as_ref());
+            self.cache.insert_map(path, Arc::new(map.clone()));
+        }
+        Ok(map)
+    }
+
+    /// Parses a map from the given source, such as an in-memory document or one received over the
+    /// network, instead of reading it through the [`ResourceReader`].
+    ///
+    /// `virtual_path` is the path the map is treated as having: The external tilesets, templates
+    /// and images it references are resolved relative to its directory and read through the
+    /// reader as usual. The map isn't stored in the [internal loader cache] and is never reloaded
+    /// by [`Loader::reload_if_changed`], since it doesn't come from a file.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::path::Path;
+    ///
+    /// use tiled::Loader;
+    ///
+    /// let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1"
+    ///     tilewidth="32" tileheight="32">
+    ///  <tileset firstgid="1" source="tilesheet.tsx"/>
+    ///  <layer id="1" name="tiles" width="1" height="1"><data encoding="csv">3</data></layer>
+    /// </map>"#;
+    ///
+    /// let mut loader = Loader::new();
+    /// let map = loader.load_tmx_map_from(xml.as_bytes(), "assets/downloaded.tmx")?;
+    /// assert_eq!(
+    ///     map.tilesets()[0].image.as_ref().unwrap().source.as_path(),
+    ///     Some(Path::new("assets/tilesheet.png"))
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn load_tmx_map_from(
+        &mut self,
+        source: impl BufRead,
+        virtual_path: impl AsRef<Path>,
+    ) -> Result<Map> {
+        let parser = XmlEvents::new(source, virtual_path.as_ref()).with_map_options(&self.options);
+        crate::parse::xml::parse_map_from(
+            parser,
+            &mut self.reader,
+            &mut self.cache,
+            self.project.as_ref(),
+            &mut MapEvents::none(),
+        )
+    }
+
+    /// Parses a map like [`Loader::load_tmx_map`] does, but reports what is read to `handler` as
+    /// it goes; See the [`stream`](crate::stream) module.
+    ///
+    /// The returned map has all of its attributes, properties and tilesets, but none of its
+    /// layers, since those are handed over to the handler in [`MapEvent::LayerParsed`] events.
+    /// The handler can stop parsing early by returning an error, which is then returned by this
+    /// function. The map isn't stored in the [internal loader cache], but its tilesets and
+    /// templates are.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{stream::MapEvent, Loader};
+    ///
+    /// // Count the tiles of the map, without keeping more than one layer in memory at a time.
+    /// let mut tile_count = 0;
+    /// let map = Loader::new().stream_tmx_map("assets/tiled_base64.tmx", |event| {
+    ///     if let MapEvent::LayerParsed(layer) = event {
+    ///         tile_count += layer.tiles().count();
+    ///     }
+    ///     Ok(())
+    /// })?;
+    /// assert!(tile_count > 0);
+    /// assert_eq!(map.layers().len(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn stream_tmx_map(
+        &mut self,
+        path: impl AsRef<Path>,
+        mut handler: impl FnMut(MapEvent<'_>) -> Result<()>,
+    ) -> Result<Map> {
+        crate::parse::xml::parse_map(
+            path.as_ref(),
+            &mut self.reader,
+            &mut self.cache,
+            self.project.as_ref(),
+            &self.options,
+            &mut MapEvents::new(&mut handler),
+        )
+    }
+
+    /// Reloads the parts of a map previously loaded with [`Loader::load_tmx_map`] whose files
+    /// have changed since, returning whether anything was reloaded.
+    ///
+    /// Changes are detected through [`ResourceReader::modified`], so the reader must implement it
+    /// for this to do anything. Changed tilesets and templates are removed from the
+    /// [internal loader cache] and parsed again. If only external tilesets used directly by the map
+    /// changed, they are swapped into it and nothing else is parsed; Otherwise, the map file is
+    /// parsed again, reusing the unchanged tilesets and templates from the cache.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```no_run")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut loader = Loader::new();
+    /// let mut map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+    /// loop {
+    ///     // Check for changes once per frame, for instance.
+    ///     if loader.reload_if_changed(&mut map)? {
+    ///         println!("Map reloaded");
+    ///     }
+    ///     # break;
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// ## Note
+    /// Maps that weren't loaded through [`Loader::load_tmx_map`], such as deserialized or built
+    /// ones, are never reloaded. Since the map is parsed again with the loader's current settings,
+    /// these should match the ones it was originally loaded with.
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn reload_if_changed(&mut self, map: &mut Map) -> Result<bool> {
+        let changed: Vec<&SourceFile> = map
+            .sources
+            .0
+            .iter()
+            .filter(|source| source.has_changed(&mut self.reader))
+            .collect();
+        if changed.is_empty() {
+            return Ok(false);
+        }
+
+        // Templates hold on to the tilesets they use, so they are outdated if any tileset is.
+        let uses_templates = map
+            .sources
+            .0
+            .iter()
+            .any(|source| source.kind == SourceKind::Template);
+        let mut reparse_map = false;
+        let mut replacements = Vec::new();
+        for source in changed {
+            match source.kind {
+                SourceKind::Map => reparse_map = true,
+                SourceKind::Template => {
+                    self.cache.remove_template(&source.path);
+                    reparse_map = true;
+                }
+                SourceKind::Tileset => {
+                    let old = self.cache.get_tileset(&source.path);
+                    self.cache.remove_tileset(&source.path);
+                    match old {
+                        Some(old) if !uses_templates => {
+                            replacements.push((source.path.clone(), old))
+                        }
+                        _ => reparse_map = true,
+                    }
+                }
+            }
+        }
+
+        if !reparse_map {
+            let mut reloaded = Vec::new();
+            for (path, old) in &replacements {
+                let new = self.load_cached_tileset(path)?;
+                reloaded.push((old, new));
+            }
+            // Tilesets only used by other tilesets or that aren't loaded yet can't be swapped in.
+            reparse_map = reloaded
+                .iter()
+                .any(|(old, new)| !map.replace_tileset(old, new));
+        }
+
+        if reparse_map {
+            if uses_templates {
+                for source in &map.sources.0 {
+                    if source.kind == SourceKind::Template {
+                        self.cache.remove_template(&source.path);
+                    }
+                }
+            }
+            let path = map.sources.0[0].path.clone();
+            *map = self.reload_tmx_map(path)?;
+        } else {
+            for source in &mut map.sources.0 {
+                if replacements.iter().any(|(path, _)| *path == source.path) {
+                    source.modified = self.reader.modified(&source.path);
+                }
+            }
+            let path = &map.sources.0[0].path;
+            if self.options.cache_maps && self.cache.get_map(path).is_some() {
+                self.cache.insert_map(path, Arc::new(map.clone()));
+            }
+        }
+        Ok(true)
     }
 
     /// Parses a file hopefully containing a Tiled tileset and tries to parse it. All external files
@@ -179,7 +701,319 @@ impl<Cache: ResourceCache, Reader: ResourceReader> Loader<Cache, Reader> {
     /// This function will **not** cache the tileset inside the internal [`ResourceCache`], since
     /// in this context it is not an intermediate object.
     pub fn load_tsx_tileset(&mut self, path: impl AsRef<Path>) -> Result<Tileset> {
-        crate::parse::xml::parse_tileset(path.as_ref(), &mut self.reader, &mut self.cache)
+        crate::parse::xml::parse_tileset(
+            path.as_ref(),
+            &mut self.reader,
+            &mut self.cache,
+            self.project.as_ref(),
+            &self.options,
+        )
+    }
+
+    /// Parses a file hopefully containing a Tiled project (`.tiled-project` file) and tries to
+    /// parse it.
+    ///
+    /// The project returned is **not** used by the loader until it is passed to
+    /// [`Loader::set_project`]. See [`Project`] for more information.
+    pub fn load_tiled_project(&mut self, path: impl AsRef<Path>) -> Result<Project> {
+        Project::load(path.as_ref(), &mut self.reader)
+    }
+
+    /// Returns a reference to the [`Project`] used when loading files, if any.
+    pub fn project(&self) -> Option<&Project> {
+        self.project.as_ref()
+    }
+
+    /// Sets the [`Project`] used when loading files from now on, which resolves custom property
+    /// types (class member defaults and enum values) against the project's definitions.
+    ///
+    /// ## Note
+    /// Tilesets and templates already present in the [internal loader cache] were loaded with
+    /// the project that was set at the time, and won't be affected by this change.
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn set_project(&mut self, project: Option<Project>) {
+        self.project = project;
+        self.forget_cached_maps();
+    }
+
+    /// Returns whether maps loaded from now on defer loading their external tilesets. See
+    /// [`Loader::set_lazy_tilesets`].
+    pub fn lazy_tilesets(&self) -> bool {
+        self.options.lazy_tilesets
+    }
+
+    /// Sets whether maps loaded from now on should defer loading their external tilesets, which
+    /// is disabled by default.
+    ///
+    /// When enabled, external tilesets that aren't in the [internal loader cache] yet are not
+    /// parsed along with the map. Instead, the map holds [`TilesetRef`](crate::TilesetRef)s
+    /// (accessible through [`Map::lazy_tilesets`]) that can be loaded individually, and
+    /// [`Map::tilesets`] stays empty until [`Map::resolve_tilesets`] is called.
+    ///
+    /// ## Note
+    /// Functions that access a map's tilesets, such as [`LayerTile::get_tileset`], will panic if
+    /// called before its tilesets are resolved.
+    ///
+    /// [internal loader cache]: Loader::cache()
+    /// [`LayerTile::get_tileset`]: crate::LayerTile::get_tileset
+    pub fn set_lazy_tilesets(&mut self, lazy_tilesets: bool) {
+        self.options.lazy_tilesets = lazy_tilesets;
+        self.forget_cached_maps();
+    }
+
+    /// Returns whether maps loaded from now on are stored in the cache and reused from it. See
+    /// [`Loader::set_cache_maps`].
+    pub fn cache_maps(&self) -> bool {
+        self.options.cache_maps
+    }
+
+    /// Sets whether maps loaded from now on are stored in the [internal loader cache] and reused
+    /// from it by [`Loader::load_tmx_map`], which is disabled by default. This is useful when the
+    /// same maps are loaded over and over, such as the ones of a world.
+    ///
+    /// Cached maps are only reused by loaders with the same settings and project they were loaded
+    /// with, and are returned as they are, even if their files changed since. Use
+    /// [`Loader::reload_if_changed`] to pick up changes, which also updates the cached map.
+    /// Changing the settings of the loader removes all maps from the cache, including the ones
+    /// cached by other loaders sharing it through a
+    /// [`SharedResourceCache`](crate::SharedResourceCache).
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, ResourceCache};
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_cache_maps(true);
+    /// let map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+    /// assert!(loader.cache().get_map("assets/tiled_base64_external.tmx").is_some());
+    ///
+    /// // Loading the map again doesn't parse it.
+    /// assert!(map == loader.load_tmx_map("assets/tiled_base64_external.tmx")?);
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn set_cache_maps(&mut self, cache_maps: bool) {
+        self.forget_cached_maps();
+        self.options.cache_maps = cache_maps;
+    }
+
+    /// Returns the fraction of empty tiles above which finite tile layers of maps loaded from now
+    /// on are stored sparsely, if any. See [`Loader::set_sparse_tile_layer_threshold`].
+    pub fn sparse_tile_layer_threshold(&self) -> Option<f32> {
+        self.options.sparse_tile_layer_threshold
+    }
+
+    /// Sets the fraction of empty tiles (between `0.0` and `1.0`) at or above which finite tile
+    /// layers of maps loaded from now on are stored sparsely, or disables sparse storage if
+    /// [`None`], which is the default.
+    ///
+    /// Sparse layers only store their non-empty tiles, which saves memory in mostly empty layers
+    /// at the cost of tile lookups taking logarithmic instead of constant time. Since each stored
+    /// tile takes twice as much memory as in a regular layer, thresholds below `0.5` are rarely
+    /// useful.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, TileLayer};
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_sparse_tile_layer_threshold(Some(0.75));
+    /// let map = loader.load_tmx_map("assets/tiled_base64_zlib.tmx")?;
+    /// if let Some(TileLayer::Finite(layer)) = map.get_layer(0).unwrap().as_tile_layer() {
+    ///     println!("Layer stored sparsely: {}", layer.is_sparse());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn set_sparse_tile_layer_threshold(&mut self, threshold: Option<f32>) {
+        self.options.sparse_tile_layer_threshold = threshold;
+        self.forget_cached_maps();
+    }
+
+    /// Returns how problems in maps loaded from now on are dealt with. See
+    /// [`Loader::set_error_tolerance`].
+    pub fn error_tolerance(&self) -> ErrorTolerance {
+        self.options.error_tolerance
+    }
+
+    /// Sets how problems in maps loaded from now on are dealt with, which is
+    /// [`ErrorTolerance::Strict`] by default.
+    ///
+    /// With [`ErrorTolerance::Lenient`], recoverable problems are collected in
+    /// [`Map::warnings`] instead of making loading fail, which is useful for editors and
+    /// validators that need to work with partially broken maps.
+    ///
+    /// ## Note
+    /// Only problems in the map file itself, including its embedded tilesets, are recovered from.
+    /// External tilesets and templates are stored in the [internal loader cache] and shared between
+    /// maps, so they are always loaded strictly.
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn set_error_tolerance(&mut self, error_tolerance: ErrorTolerance) {
+        self.options.error_tolerance = error_tolerance;
+        self.forget_cached_maps();
+    }
+
+    /// Consumes the loader and returns it with the given [`ErrorTolerance`]. See
+    /// [`Loader::set_error_tolerance`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{ErrorTolerance, Loader};
+    ///
+    /// let mut loader = Loader::new().with_error_tolerance(ErrorTolerance::Lenient);
+    /// let map = loader.load_tmx_map("assets/tiled_base64.tmx")?;
+    /// for warning in map.warnings() {
+    ///     eprintln!("{}", warning);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn with_error_tolerance(mut self, error_tolerance: ErrorTolerance) -> Self {
+        self.set_error_tolerance(error_tolerance);
+        self
+    }
+
+    /// Returns whether conflicting IDs and GIDs in maps loaded from now on are handled as errors
+    /// instead of warnings. See [`Loader::set_deny_conflicts`].
+    pub fn deny_conflicts(&self) -> bool {
+        self.options.deny_conflicts
+    }
+
+    /// Sets whether conflicting IDs and GIDs in maps loaded from now on are handled as errors,
+    /// which is disabled by default.
+    ///
+    /// Layers or objects with the same ID and tilesets whose GID ranges overlap are reported
+    /// through [`Error::DuplicateLayerId`], [`Error::DuplicateObjectId`] and
+    /// [`Error::OverlappingTilesets`]. Tiled itself tolerates them, so by default they are only
+    /// recorded in [`Map::warnings`] and the map is loaded as it is. When enabled, they are handled
+    /// like other recoverable problems instead, which make loading fail unless the
+    /// [error tolerance](Loader::set_error_tolerance) is [`ErrorTolerance::Lenient`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// use tiled::{Error, Loader};
+    ///
+    /// let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1" tilewidth="32" tileheight="32">
+    ///  <objectgroup id="1" name="first"/>
+    ///  <objectgroup id="1" name="second"/>
+    /// </map>"#;
+    /// let mut loader = Loader::new();
+    /// let map = loader.load_tmx_map_from(xml.as_bytes(), "map.tmx").unwrap();
+    /// assert_eq!(map.warnings().len(), 1);
+    ///
+    /// loader.set_deny_conflicts(true);
+    /// let error = loader.load_tmx_map_from(xml.as_bytes(), "map.tmx").unwrap_err();
+    /// assert!(matches!(error.inner(), Error::DuplicateLayerId { id: 1 }));
+    /// ```
+    ///
+    /// [`Error::DuplicateLayerId`]: crate::Error::DuplicateLayerId
+    /// [`Error::DuplicateObjectId`]: crate::Error::DuplicateObjectId
+    /// [`Error::OverlappingTilesets`]: crate::Error::OverlappingTilesets
+    pub fn set_deny_conflicts(&mut self, deny_conflicts: bool) {
+        self.options.deny_conflicts = deny_conflicts;
+        self.forget_cached_maps();
+    }
+
+    /// Returns the limits files loaded from now on are parsed with. See [`Loader::set_limits`].
+    pub fn limits(&self) -> ParseLimits {
+        self.options.limits
+    }
+
+    /// Sets the limits files loaded from now on are parsed with, of which there are none by
+    /// default.
+    ///
+    /// ## Note
+    /// Tilesets and templates already in the [internal loader cache] are reused as they are, even
+    /// if they go over the new limits.
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub fn set_limits(&mut self, limits: ParseLimits) {
+        self.options.limits = limits;
+        self.forget_cached_maps();
+    }
+
+    /// Consumes the loader and returns it with the given [`ParseLimits`]. See
+    /// [`Loader::set_limits`].
+    pub fn with_limits(mut self, limits: ParseLimits) -> Self {
+        self.set_limits(limits);
+        self
+    }
+
+    /// Sets the [`Decompressor`] used from now on for the data of tile layers compressed with the
+    /// given compression, such as `zlib` or `zstd`. It takes precedence over the decompressor built
+    /// into this crate for that compression, if any, so it can be used both to replace those and
+    /// to support compressions that they don't cover.
+    ///
+    /// Tile data with a compression that there is no decompressor for fails to load with
+    /// [`Error::UnsupportedCompression`](crate::Error::UnsupportedCompression). See
+    /// [`Decompressor`] for an example.
+    pub fn set_decompressor(
+        &mut self,
+        compression: impl Into<String>,
+        decompressor: impl Decompressor + 'static,
+    ) {
+        self.options
+            .decompressors
+            .insert(compression.into(), Arc::new(decompressor));
+        self.forget_cached_maps();
+    }
+
+    /// Returns all the settings files loaded from now on are loaded with.
+    pub fn options(&self) -> &LoaderOptions {
+        &self.options
+    }
+
+    /// Sets all the settings files loaded from now on are loaded with at once. See
+    /// [`LoaderOptions`].
+    pub fn set_options(&mut self, options: LoaderOptions) {
+        self.options = options;
+        self.forget_cached_maps();
+    }
+
+    /// Consumes the loader and returns it with the given [`LoaderOptions`]. See
+    /// [`Loader::set_options`].
+    pub fn with_options(mut self, options: LoaderOptions) -> Self {
+        self.set_options(options);
+        self
+    }
+
+    /// Removes all maps from the cache if this loader caches maps, after a change to its
+    /// settings. Those loaded with other settings wouldn't be reused anyway, and other loaders
+    /// sharing the cache are expected to use the same settings.
+    fn forget_cached_maps(&mut self) {
+        if self.options.cache_maps {
+            self.cache.clear_maps();
+        }
+    }
+
+    /// Returns the tileset at the given path from the [internal loader cache], parsing it and
+    /// inserting it into the cache first if it isn't there.
+    ///
+    /// [internal loader cache]: Loader::cache()
+    pub(crate) fn load_cached_tileset(&mut self, path: &Path) -> Result<Arc<Tileset>> {
+        if let Some(tileset) = self.cache.get_tileset(path) {
+            return Ok(tileset);
+        }
+        let tileset = Arc::new(crate::parse::xml::parse_tileset(
+            path,
+            &mut self.reader,
+            &mut self.cache,
+            self.project.as_ref(),
+            &self.options,
+        )?);
+        self.cache.insert_tileset(path, tileset.clone());
+        Ok(tileset)
     }
 
     /// Returns a reference to the loader's internal [`ResourceCache`].
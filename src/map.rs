@@ -1,25 +1,43 @@
 //! Structures related to Tiled maps.
 
-use std::{collections::HashMap, fmt, path::Path, str::FromStr, sync::Arc};
-
-use xml::attribute::OwnedAttribute;
+use std::{
+    borrow::Cow,
+    collections::{HashMap, HashSet},
+    convert::TryFrom,
+    fmt,
+    path::{Path, PathBuf},
+    str::FromStr,
+    sync::{Arc, OnceLock},
+};
 
 use crate::{
+    cache::{MapSettings, MapSources},
+    collision::build_collision_grid,
+    compat,
+    diff::diff_maps,
+    dump::dump_map,
     error::{Error, Result},
-    layers::{LayerData, LayerTag},
-    properties::{parse_properties, Color, Properties},
+    layers::{LayerData, LayerResize, LayerTag},
+    properties::{parse_properties, Color, Properties, PropertyValue},
+    stream::{MapEvent, MapEvents, MapHeader},
     tileset::Tileset,
-    util::{get_attrs, parse_tag, XmlEventResult},
-    EmbeddedParseResultType, Layer, ResourceCache, ResourceReader,
+    util::{get_attrs, parse_tag, LoadContext, OwnedAttribute, XmlParser},
+    validate::validate_map,
+    ArcLayer, Diagnostic, EmbeddedParseResultType, ExtraXml, FlattenedLayer, Grid, Layer, LayerId,
+    LayerMut, LayerQuery, LayerTile, LayerTileData, LayerType, Loader, MapDifference, Object,
+    ObjectData, ObjectId, Project, Rect, ResourceCache, ResourceReader, TemplateObjectReference,
+    Tile, TileId, TileLayer, TileLayerBuilder, TilesetLocation, Warning, XmlPosition,
 };
 
+#[derive(Clone)]
 pub(crate) struct MapTilesetGid {
     pub first_gid: Gid,
-    pub tileset: Arc<Tileset>,
+    pub tileset: TilesetRef,
 }
 
 /// All Tiled map files will be parsed into this. Holds all the layers and tilesets.
 #[derive(PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Map {
     version: String,
     /// The way tiles are laid out in the map.
@@ -52,17 +70,92 @@ pub struct Map {
     pub stagger_axis: StaggerAxis,
     /// The stagger index of Hexagonal/Staggered map.
     pub stagger_index: StaggerIndex,
+    /// The length of the side of a hexagonal tile, in pixels. Only present in Hexagonal maps.
+    pub hex_side_length: Option<u32>,
+    /// The order in which tiles on tile layers are rendered.
+    pub render_order: RenderOrder,
+    /// The level used by Tiled to compress the map's tile layer data, or [`None`] to use the
+    /// compression algorithm's default, which Tiled stores as -1.
+    pub compression_level: Option<i32>,
     /// The tilesets present on this map.
     tilesets: Vec<Arc<Tileset>>,
+    /// The references to this map's tilesets if it was loaded lazily and these haven't been
+    /// resolved yet.
+    lazy_tilesets: Vec<TilesetRef>,
+    /// The first GID of each of this map's tilesets, in the same order as them.
+    tileset_first_gids: Vec<Gid>,
+    /// The path of the external file each of this map's tilesets is stored in, or [`None`] for
+    /// embedded tilesets.
+    tileset_paths: Vec<Option<PathBuf>>,
     /// The layers present in this map.
     layers: Vec<LayerData>,
     /// The custom properties of this map.
     pub properties: Properties,
     /// The background color of this map, if any.
     pub background_color: Option<Color>,
+    /// The X coordinate of the parallax origin in pixels, which is where the center of the view
+    /// has to be for layers with a parallax factor to be drawn at their own offset. 0 by default.
+    pub parallax_origin_x: f32,
+    /// The Y coordinate of the parallax origin in pixels; See [`Self::parallax_origin_x`].
+    pub parallax_origin_y: f32,
     infinite: bool,
     /// The type of the map, which is arbitrary and set by the user.
     pub user_type: Option<String>,
+    /// The attributes and child elements of the map element that aren't read into any other
+    /// field, if the map was loaded with
+    /// [`LoaderOptions::preserve_unknown_xml`](crate::LoaderOptions::preserve_unknown_xml).
+    pub extra: ExtraXml,
+    /// The ID given to the next layer added to the map.
+    next_layer_id: u32,
+    /// The ID given to the next object added to the map.
+    next_object_id: u32,
+    warnings: Vec<Warning>,
+    /// The files this map was loaded from, for detecting changes to them.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) sources: MapSources,
+    /// The settings this map was loaded with, if it was stored in the cache.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) settings: MapSettings,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    object_index: ObjectIndex,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    parent_index: ParentIndex,
+}
+
+/// The location of each object in a map by ID, as the indices of the layers leading to its object
+/// layer followed by its index within it.
+///
+/// It is built when the map is parsed or built, or on first use if the map was deserialized.
+/// Since it is derived from the map's layers, it is ignored when comparing maps.
+#[derive(Clone, Default)]
+struct ObjectIndex(OnceLock<HashMap<u32, (Vec<usize>, usize)>>);
+
+impl PartialEq for ObjectIndex {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
+}
+
+/// The locations of the layers of a map by ID, as the indices of the layers leading to each of
+/// them, and of its objects by ID, as the indices leading to their object layer followed by their
+/// index within it. Since several layers or objects may share an ID, each ID can have several
+/// locations.
+///
+/// It is built on first use. Since it is derived from the map's layers, it is ignored when
+/// comparing maps.
+#[derive(Clone, Default)]
+struct ParentIndex(OnceLock<ParentPaths>);
+
+#[derive(Clone, Default)]
+struct ParentPaths {
+    layers: HashMap<u32, Vec<Vec<usize>>>,
+    objects: HashMap<u32, Vec<(Vec<usize>, usize)>>,
+}
+
+impl PartialEq for ParentIndex {
+    fn eq(&self, _other: &Self) -> bool {
+        true
+    }
 }
 
 impl fmt::Debug for Map {
@@ -76,12 +169,27 @@ impl fmt::Debug for Map {
             .field("tile_height", &self.tile_height)
             .field("stagger_axis", &self.stagger_axis)
             .field("stagger_index", &self.stagger_index)
+            .field("hex_side_length", &self.hex_side_length)
+            .field("render_order", &self.render_order)
+            .field("compression_level", &self.compression_level)
             .field("tilesets", &format!("{} tilesets", self.tilesets.len()))
+            .field(
+                "lazy_tilesets",
+                &format!("{} lazy tilesets", self.lazy_tilesets.len()),
+            )
+            .field("tileset_first_gids", &self.tileset_first_gids)
+            .field("tileset_paths", &self.tileset_paths)
             .field("layers", &format!("{} layers", self.layers.len()))
             .field("properties", &self.properties)
             .field("background_color", &self.background_color)
+            .field("parallax_origin_x", &self.parallax_origin_x)
+            .field("parallax_origin_y", &self.parallax_origin_y)
             .field("infinite", &self.infinite)
             .field("user_type", &self.user_type)
+            .field("extra", &self.extra)
+            .field("next_layer_id", &self.next_layer_id)
+            .field("next_object_id", &self.next_object_id)
+            .field("warnings", &self.warnings)
             .finish()
     }
 }
@@ -99,6 +207,13 @@ impl Map {
     pub fn infinite(&self) -> bool {
         self.infinite
     }
+
+    /// The problems that were recovered from while loading this map. These are only ever errors
+    /// that were recovered from with [`ErrorTolerance::Lenient`](crate::ErrorTolerance::Lenient)
+    /// and conflicting IDs and GIDs, unless [denied](Loader::set_deny_conflicts).
+    pub fn warnings(&self) -> &[Warning] {
+        &self.warnings
+    }
 }
 
 impl Map {
@@ -108,65 +223,1592 @@ impl Map {
         self.tilesets.as_ref()
     }
 
-    /// Get an iterator over top-level layers in the map in ascending order of their layer index.
+    /// Returns the tileset at the given index, if the map has one there and it has been loaded.
+    ///
+    /// Unlike [`Self::tilesets`], this also finds the tilesets of a map loaded with
+    /// [lazy tilesets](Loader::set_lazy_tilesets) before they are
+    /// [resolved](Self::resolve_tilesets), as long as they are loaded already; Embedded tilesets
+    /// and ones that were in the loader's cache always are.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut loader = Loader::new();
+    /// loader.set_lazy_tilesets(true);
+    /// let map = loader.load_tmx_map("assets/tiled_base64_external.tmx")?;
+    /// assert!(map.tilesets().is_empty());
+    /// assert!(map.tileset(0).is_none());
+    ///
+    /// let map = loader.load_tmx_map("assets/tiled_base64.tmx")?;
+    /// assert_eq!(map.tileset(0).unwrap().name, "tilesheet");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tileset(&self, index: usize) -> Option<&Arc<Tileset>> {
+        MapTilesets::new(&self.tilesets, &self.lazy_tilesets).get(index)
+    }
+
+    /// Returns the first [`Gid`] of each of the map's tilesets, in the same order as
+    /// [`Self::tilesets`] (or [`Self::lazy_tilesets`] if they haven't been resolved yet).
+    ///
+    /// Each tileset takes up the range of GIDs from its first one up to the next tileset's first
+    /// one, which is what tile layers store internally. Also see [`Self::tile_for_gid`] and
+    /// [`Self::gid_for`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Gid, Loader};
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")?;
+    /// assert_eq!(map.tileset_first_gids(), [Gid(1), Gid(85)]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn tileset_first_gids(&self) -> &[Gid] {
+        &self.tileset_first_gids
+    }
+
+    /// Returns the tile that a [GID](https://doc.mapeditor.org/en/latest/reference/global-tile-ids/)
+    /// of this map refers to, along with the index of its tileset. Flipping bits in the GID are
+    /// ignored.
+    ///
+    /// Returns [`None`] for empty tiles (GID 0), GIDs that don't correspond to any tile, and if
+    /// the tile's tileset hasn't been loaded yet; See [`Self::tileset`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let (tileset_index, tile) = map.tile_for_gid(5).unwrap();
+    /// assert_eq!(tileset_index, 0);
+    /// println!("Tile from {}: {:?}", tile.tileset().name, tile.properties);
+    /// // The GID of the fifth tile of the first tileset.
+    /// assert_eq!(map.gid_for(0, 4), Some(5));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tile_for_gid(&self, gid: u32) -> Option<(usize, Tile<'_>)> {
+        let tile = LayerTileData::from_bits_with_first_gids(gid, &self.tileset_first_gids)?;
+        let tileset = self.tileset(tile.tileset_index())?;
+        Some((tile.tileset_index(), tileset.get_tile(tile.id())?))
+    }
+
+    /// Returns the [GID](https://doc.mapeditor.org/en/latest/reference/global-tile-ids/) of the
+    /// tile with the given ID in the tileset at the given index, which is the inverse of
+    /// [`Self::tile_for_gid`].
+    ///
+    /// Returns [`None`] if the tile doesn't exist or its tileset hasn't been loaded yet; See
+    /// [`Self::tileset`].
+    pub fn gid_for(&self, tileset_index: usize, id: TileId) -> Option<u32> {
+        let first_gid = self.tileset_first_gids.get(tileset_index)?;
+        // Image collections can have tiles with IDs beyond their tile count.
+        self.tileset(tileset_index)?.get_tile(id)?;
+        first_gid.0.checked_add(id)
+    }
+
+    /// Adds a tileset after the map's other tilesets and returns its index, which tiles from it
+    /// can then be placed with. Its [first GID](Self::tileset_first_gids) follows the ones of the
+    /// other tilesets.
+    ///
+    /// If the map's tilesets haven't been [resolved](Self::resolve_tilesets) yet, the tileset is
+    /// added to its [lazy tilesets](Self::lazy_tilesets).
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::sync::Arc;
+    /// use tiled::{LayerTileData, Loader};
+    ///
+    /// let mut loader = Loader::new();
+    /// let tileset = Arc::new(loader.load_tsx_tileset("assets/tilesheet.tsx")?);
+    /// let mut map = loader.load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let index = map.append_tileset(tileset);
+    /// assert_eq!(index, 1);
+    /// let mut layer = map
+    ///     .get_layer_mut(0)
+    ///     .and_then(|layer| layer.as_finite_tile_layer_mut())
+    ///     .unwrap();
+    /// layer.set_tile(0, 0, Some(LayerTileData::new(index, 3)))?;
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn append_tileset(&mut self, tileset: Arc<Tileset>) -> usize {
+        let index = self.tileset_first_gids.len();
+        let first_gid = match self.tileset_first_gids.last() {
+            Some(last) => Gid(last.0.saturating_add(self.tileset_gid_count(index - 1))),
+            None => Gid(1),
+        };
+        self.tileset_first_gids.push(first_gid);
+        self.tileset_paths.push(None);
+        if self.lazy_tilesets.is_empty() {
+            self.tilesets.push(tileset);
+        } else {
+            self.lazy_tilesets.push(TilesetRef::from(tileset));
+        }
+        index
+    }
+
+    /// Rearranges the map's tilesets to be in the order of the indices in `order`, which are
+    /// their current indices, rewriting the tiles of its layers and tile objects to refer to the
+    /// new indices. Tilesets left out of `order` are removed along with their tiles.
+    ///
+    /// The [first GIDs](Self::tileset_first_gids) of the tilesets are renumbered to follow each
+    /// other in the new order.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if an index is out of range or repeated.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let mut map = tiled::Loader::new().load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")?;
+    /// let names = |map: &tiled::Map| -> Vec<String> {
+    ///     map.tilesets().iter().map(|tileset| tileset.name.clone()).collect()
+    /// };
+    /// let (first, second) = (names(&map)[0].clone(), names(&map)[1].clone());
+    /// // Swap the two tilesets.
+    /// map.remap_tilesets(&[1, 0])?;
+    /// assert_eq!(names(&map), [second.clone(), first]);
+    /// // Remove the first tileset along with its tiles.
+    /// map.remap_tilesets(&[1])?;
+    /// assert_eq!(names(&map), [second]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn remap_tilesets(&mut self, order: &[usize]) -> Result<()> {
+        let mut new_indices = vec![None; self.tileset_first_gids.len()];
+        for (new_index, old_index) in order.iter().enumerate() {
+            match new_indices.get_mut(*old_index) {
+                Some(slot) if slot.is_none() => *slot = Some(new_index),
+                _ => {
+                    return Err(Error::InvalidMapData {
+                        description: format!(
+                            "tileset index {} is out of range or repeated",
+                            old_index
+                        ),
+                    })
+                }
+            }
+        }
+
+        let mut first_gid = Gid(1);
+        let first_gids = order
+            .iter()
+            .map(|index| {
+                let gid = first_gid;
+                first_gid = Gid(first_gid.0.saturating_add(self.tileset_gid_count(*index)));
+                gid
+            })
+            .collect();
+        self.tileset_first_gids = first_gids;
+        reorder(&mut self.tilesets, order);
+        reorder(&mut self.lazy_tilesets, order);
+        reorder(&mut self.tileset_paths, order);
+        for layer in &mut self.layers {
+            layer.remap_tilesets(&new_indices);
+        }
+        Ok(())
+    }
+
+    /// Returns the amount of GIDs the tileset at the given index needs. Tilesets that haven't been
+    /// loaded keep the range they have, or take up the GIDs the map uses if they are last.
+    fn tileset_gid_count(&self, index: usize) -> u32 {
+        match (self.tileset(index), self.tileset_first_gids.get(index + 1)) {
+            (Some(tileset), _) => tileset.gid_count(),
+            (None, Some(next)) => next.0 - self.tileset_first_gids[index].0,
+            (None, None) => self.used_tiles()[index]
+                .iter()
+                .max()
+                .map_or(0, |id| id.saturating_add(1)),
+        }
+    }
+
+    /// Returns the path of the external TSX file the tileset at the given index is stored in, or
+    /// [`None`] if it is embedded in the map or there is no such tileset.
+    #[inline]
+    pub fn tileset_path(&self, index: usize) -> Option<&Path> {
+        self.tileset_paths.get(index)?.as_deref()
+    }
+
+    /// Writes the embedded tileset at the given index to a TSX file at `path` and turns it into a
+    /// reference to that file, as [`Self::tileset_path`] then reports. Image and file paths in the
+    /// tileset are written relative to the new file.
+    ///
+    /// Fails if there is no such tileset, if it already is external or if the map's tilesets
+    /// haven't been [resolved](Self::resolve_tilesets) yet. Only available with the `filesystem`
+    /// feature.
+    ///
+    /// ## Example
+    /// ```no_run
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// map.externalize_tileset(0, "assets/tilesheet_extracted.tsx")?;
+    /// assert!(map.tileset_path(0).is_some());
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "filesystem")]
+    pub fn externalize_tileset(&mut self, index: usize, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let tileset = self
+            .tilesets
+            .get(index)
+            .ok_or_else(|| Error::InvalidMapData {
+                description: format!("the map has no loaded tileset at index {}", index),
+            })?;
+        if self.tileset_paths[index].is_some() {
+            return Err(Error::InvalidMapData {
+                description: format!("the tileset at index {} is already external", index),
+            });
+        }
+        let dir = path.parent().ok_or(Error::PathIsNotFile)?;
+        let file = std::fs::File::create(path).map_err(Error::WritingError)?;
+        let mut writer = std::io::BufWriter::new(file);
+        crate::write::write_tsx(tileset, &mut writer, Some(dir))?;
+        // Dropping the writer would silently ignore errors writing what is still buffered.
+        std::io::Write::flush(&mut writer).map_err(Error::WritingError)?;
+        self.tileset_paths[index] = Some(path.to_owned());
+        Ok(())
+    }
+
+    /// Get a reference to the map's unresolved tileset references.
+    ///
+    /// This is only non-empty if the map was loaded with
+    /// [lazy tilesets](Loader::set_lazy_tilesets) and [`Self::resolve_tilesets`] hasn't been
+    /// called yet; In that case, it holds one entry per tileset, in the same order as they will
+    /// appear in [`Self::tilesets`] once resolved.
+    #[inline]
+    pub fn lazy_tilesets(&self) -> &[TilesetRef] {
+        self.lazy_tilesets.as_ref()
+    }
+
+    /// Loads every tileset of a map loaded with [lazy tilesets](Loader::set_lazy_tilesets) that
+    /// hasn't been loaded yet and moves them into [`Self::tilesets`]. Does nothing if the map's
+    /// tilesets were already resolved.
+    ///
+    /// Tilesets are loaded through the loader's cache, so the loader should be the same one (or
+    /// use the same resources) as the one the map was loaded with.
+    pub fn resolve_tilesets<Cache: ResourceCache, Reader: ResourceReader>(
+        &mut self,
+        loader: &mut Loader<Cache, Reader>,
+    ) -> Result<()> {
+        for tileset in &self.lazy_tilesets {
+            tileset.load(loader)?;
+        }
+        let lazy_tilesets = std::mem::take(&mut self.lazy_tilesets);
+        self.tilesets = lazy_tilesets
+            .into_iter()
+            .filter_map(|tileset| tileset.tileset.into_inner())
+            .collect();
+        Ok(())
+    }
+
+    /// Get an iterator over top-level layers in the map in ascending order of their layer index.
+    ///
+    /// Note: "top-level" means that if a map has layers of `LayerDataType::Group` type, you
+    /// need to recursively enumerate those group layers.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::Loader;
+    /// #
+    /// # fn main() {
+    /// # struct Renderer;
+    /// # impl Renderer {
+    /// #     fn render(&self, _: tiled::TileLayer) {}
+    /// # }
+    /// # let my_renderer = Renderer;
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_group_layers.tmx")
+    /// #     .unwrap();
+    /// #
+    /// let tile_layers = map.layers().filter_map(|layer| match layer.layer_type() {
+    ///     tiled::LayerType::Tiles(layer) => Some(layer),
+    ///     _ => None,
+    /// });
+    ///
+    /// for layer in tile_layers {
+    ///     my_renderer.render(layer);
+    /// }
+    /// # }
+    /// ```
+    #[inline]
+    pub fn layers(&self) -> impl ExactSizeIterator<Item = Layer> {
+        self.layers.iter().map(move |layer| Layer::new(self, layer))
+    }
+
+    /// Get an iterator over all non-group layers in the map, walking the layer tree depth-first
+    /// in ascending order of layer index.
+    ///
+    /// Each layer is yielded along with the offset, opacity, parallax factor, tint color and
+    /// visibility it effectively has after applying the ones of its parent group layers.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::Loader;
+    /// #
+    /// # fn main() {
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_group_layers.tmx")
+    /// #     .unwrap();
+    /// #
+    /// for flattened in map.flattened_layers().filter(|layer| layer.visible) {
+    ///     println!(
+    ///         "{} at ({}, {})",
+    ///         flattened.layer.name, flattened.offset_x, flattened.offset_y
+    ///     );
+    /// }
+    /// # }
+    /// ```
+    pub fn flattened_layers(&self) -> impl Iterator<Item = FlattenedLayer<'_>> {
+        let mut stack = vec![(self.layers().collect::<Vec<_>>().into_iter(), None)];
+        std::iter::from_fn(move || loop {
+            let (layers, parent) = stack.last_mut()?;
+            match layers.next() {
+                Some(layer) => {
+                    let flattened = FlattenedLayer::new(layer, parent.as_ref());
+                    match layer.as_group_layer() {
+                        Some(group) => stack.push((
+                            group.layers().collect::<Vec<_>>().into_iter(),
+                            Some(flattened),
+                        )),
+                        None => return Some(flattened),
+                    }
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        })
+    }
+
+    /// Returns the top-level layer that has the specified index, if it exists.
+    pub fn get_layer(&self, index: usize) -> Option<Layer> {
+        self.layers.get(index).map(|data| Layer::new(self, data))
+    }
+
+    /// Get an iterator over all the top-level layers of a shared map, in ascending order of their
+    /// layer index. Unlike the ones from [`Self::layers`], these layers keep the map alive by
+    /// holding onto the [`Arc`] instead of borrowing it, so they can be freely stored and sent to
+    /// other threads.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::sync::Arc;
+    /// use tiled::{ArcLayerTile, Loader};
+    ///
+    /// let map = Arc::new(Loader::new().load_tmx_map("assets/tiled_base64.tmx")?);
+    /// let layer = map.layers_arc().next().unwrap();
+    /// let handle = std::thread::spawn(move || -> Option<ArcLayerTile> { layer.get_tile(0, 0) });
+    /// let tile = handle.join().unwrap().unwrap();
+    /// assert_eq!(tile.get_tileset().name, "tilesheet");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn layers_arc(self: &Arc<Self>) -> impl ExactSizeIterator<Item = ArcLayer> + '_ {
+        (0..self.layers.len()).map(move |index| ArcLayer::new(self.clone(), Arc::new([index])))
+    }
+
+    /// Returns a mutable reference to the top-level layer that has the specified index, if it
+    /// exists. Use [`LayerMut::get_layer_mut`] to get to the layers within groups.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let mut layer = map.get_layer_mut(0).unwrap();
+    /// layer.visible = false;
+    /// assert!(!map.get_layer(0).unwrap().visible);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn get_layer_mut(&mut self, index: usize) -> Option<LayerMut<'_>> {
+        // The layer's objects may be changed, so they need to be indexed again.
+        self.object_index = ObjectIndex::default();
+        self.parent_index = ParentIndex::default();
+        let tilesets = MapTilesets::new(&self.tilesets, &self.lazy_tilesets);
+        let next_object_id = &mut self.next_object_id;
+        self.layers
+            .get_mut(index)
+            .map(move |data| LayerMut::new(tilesets, next_object_id, data))
+    }
+
+    /// Returns the layer with the given ID, including the ones in groups, for changing it; See
+    /// [`Map::get_layer_mut`]. Unlike indices, IDs stay the same as layers are added to the map.
+    pub fn layer_by_id_mut(&mut self, id: LayerId) -> Option<LayerMut<'_>> {
+        self.object_index = ObjectIndex::default();
+        self.parent_index = ParentIndex::default();
+        let tilesets = MapTilesets::new(&self.tilesets, &self.lazy_tilesets);
+        let next_object_id = &mut self.next_object_id;
+        self.layers
+            .iter_mut()
+            .find_map(|layer| layer.find_layer_mut(id))
+            .map(move |data| LayerMut::new(tilesets, next_object_id, data))
+    }
+
+    /// Adds a layer on top of the map's top-level layers, giving it the map's
+    /// [next layer ID](Self::next_layer_id) and returning it. Layers are created with
+    /// [`TileLayerBuilder`](crate::TileLayerBuilder) and
+    /// [`ObjectLayerBuilder`](crate::ObjectLayerBuilder), or can be cloned from other maps.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the layer doesn't match the map's infinite setting
+    /// or size, references a tileset the map doesn't have or if the map has no layer IDs left.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerId, Loader, ObjectLayerBuilder};
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let next_id = map.next_layer_id();
+    /// let id = map.push_layer(ObjectLayerBuilder::new().name("Spawns").build())?;
+    /// assert_eq!(id, LayerId(next_id));
+    /// assert_eq!(map.layer_by_id(id).unwrap().name, "Spawns");
+    /// assert_eq!(map.next_layer_id(), next_id + 1);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn push_layer(&mut self, mut layer: LayerData) -> Result<LayerId> {
+        layer.check_for_map(self)?;
+        let id = self.next_layer_id;
+        let next_id = next_id(id, "layer")?;
+        layer.set_id(id);
+        self.layers.push(layer);
+        self.next_layer_id = next_id;
+        // The layer's objects need to be indexed, and new ones shouldn't reuse their IDs.
+        self.object_index = ObjectIndex::default();
+        self.parent_index = ParentIndex::default();
+        self.next_object_id = self
+            .next_object_id
+            .max(self.max_object_id().saturating_add(1));
+        Ok(LayerId(id))
+    }
+
+    /// Returns the object with the given ID from any of the map's object layers, including the
+    /// ones in groups, if it exists. If several objects share the ID, the first one is returned.
+    ///
+    /// This can be used to follow the references stored in
+    /// [`PropertyValue::ObjectValue`] properties.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::{Loader, ObjectId, PropertyValue};
+    /// #
+    /// # fn main() {
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_object_property.tmx")
+    /// #     .unwrap();
+    /// # let object = map.object_by_id(ObjectId(2)).unwrap();
+    /// if let Some(id) = object.properties.get("object property").and_then(PropertyValue::as_object_id) {
+    ///     let target = map.object_by_id(id).unwrap();
+    ///     println!("Object {} points at {:?}", object.id().0, target.name);
+    /// }
+    /// # }
+    /// ```
+    pub fn object_by_id(&self, id: ObjectId) -> Option<Object<'_>> {
+        let (layer_indices, object_index) = self.object_index().get(&id.0)?;
+        self.layer_at_indices(layer_indices)?
+            .as_object_layer()?
+            .get_object(*object_index)
+    }
+
+    /// Returns the layer with the given ID, including the ones in groups, if any. Unlike indices,
+    /// IDs stay the same as layers are added to the map.
+    pub fn layer_by_id(&self, id: LayerId) -> Option<Layer<'_>> {
+        fn find<'map>(
+            mut layers: impl Iterator<Item = Layer<'map>>,
+            id: LayerId,
+        ) -> Option<Layer<'map>> {
+            layers.find_map(|layer| {
+                if layer.id() == id {
+                    return Some(layer);
+                }
+                find(layer.as_group_layer()?.layers(), id)
+            })
+        }
+
+        find(self.layers(), id)
+    }
+
+    /// Returns the layer reached by following the given indices from the top-level layers through
+    /// group layers.
+    fn layer_at_indices(&self, indices: &[usize]) -> Option<Layer<'_>> {
+        let (first, rest) = indices.split_first()?;
+        let mut layer = self.get_layer(*first)?;
+        for index in rest {
+            layer = layer.as_group_layer()?.get_layer(*index)?;
+        }
+        Some(layer)
+    }
+
+    /// Returns the group layer containing the given layer data, if it is part of this map and
+    /// nested in a group.
+    pub(crate) fn parent_of_layer(&self, data: &LayerData) -> Option<Layer<'_>> {
+        let paths = self.parent_index().layers.get(&data.id().0)?;
+        // Layers sharing an ID are told apart by which of them the data is.
+        let path = paths.iter().find(|path| {
+            self.layer_at_indices(path)
+                .is_some_and(|layer| std::ptr::eq(layer.data, data))
+        })?;
+        self.layer_at_indices(&path[..path.len() - 1])
+    }
+
+    /// Returns the object layer containing the given object data, if it is part of this map.
+    pub(crate) fn parent_of_object(&self, data: &ObjectData) -> Option<Layer<'_>> {
+        let locations = self.parent_index().objects.get(&data.id().0)?;
+        // Objects sharing an ID are told apart by which of them the data is.
+        let (path, _) = locations.iter().find(|(path, index)| {
+            self.layer_at_indices(path)
+                .and_then(|layer| layer.as_object_layer()?.get_object(*index))
+                .is_some_and(|object| std::ptr::eq(object.data, data))
+        })?;
+        self.layer_at_indices(path)
+    }
+
+    fn parent_index(&self) -> &ParentPaths {
+        fn index_layers<'map>(
+            layers: impl Iterator<Item = Layer<'map>>,
+            path: &mut Vec<usize>,
+            index: &mut ParentPaths,
+        ) {
+            for (layer_index, layer) in layers.enumerate() {
+                path.push(layer_index);
+                index
+                    .layers
+                    .entry(layer.id().0)
+                    .or_default()
+                    .push(path.clone());
+                match layer.layer_type() {
+                    LayerType::Objects(objects) => {
+                        for (object_index, object) in objects.objects().enumerate() {
+                            index
+                                .objects
+                                .entry(object.id().0)
+                                .or_default()
+                                .push((path.clone(), object_index));
+                        }
+                    }
+                    LayerType::Group(group) => index_layers(group.layers(), path, index),
+                    _ => {}
+                }
+                path.pop();
+            }
+        }
+
+        self.parent_index.0.get_or_init(|| {
+            let mut index = ParentPaths::default();
+            index_layers(self.layers(), &mut Vec::new(), &mut index);
+            index
+        })
+    }
+
+    /// Returns the object properties that the objects of this map, including the ones in groups,
+    /// inherited from their templates and which refer to other objects, in the order the objects
+    /// were declared in. See [`ObjectData::template_object_properties`].
+    ///
+    /// The IDs these hold were assigned in the template file and generally don't refer to the
+    /// right objects of this map, if to any at all.
+    pub fn template_object_references(&self) -> Vec<TemplateObjectReference> {
+        fn collect<'map>(
+            layers: impl Iterator<Item = Layer<'map>>,
+            references: &mut Vec<TemplateObjectReference>,
+        ) {
+            for layer in layers {
+                match layer.layer_type() {
+                    LayerType::Objects(objects) => {
+                        for object in objects.objects() {
+                            references.extend(template_references(&object));
+                        }
+                    }
+                    LayerType::Group(group) => collect(group.layers(), references),
+                    _ => {}
+                }
+            }
+        }
+
+        let mut references = Vec::new();
+        collect(self.layers(), &mut references);
+        references
+    }
+
+    /// Remaps the object properties that the objects of this map inherited from their templates,
+    /// as listed by [`Map::template_object_references`], to objects of this map.
+    ///
+    /// `resolve` is called with each reference and returns the ID of the map object it should
+    /// point to instead. References for which it returns [`None`] or the ID of an object that
+    /// isn't in the map are left untouched and returned, in the order they were found.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let mut map = tiled::Loader::new().load_tmx_map("assets/tiled_object_template.tmx")?;
+    /// // Keep the references that happen to point to objects of the map.
+    /// let unresolved = map.resolve_template_object_references(|reference| Some(reference.target));
+    /// for reference in unresolved {
+    ///     println!(
+    ///         "Object {:?} has a dangling reference in {:?}",
+    ///         reference.object, reference.property
+    ///     );
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resolve_template_object_references(
+        &mut self,
+        mut resolve: impl FnMut(&TemplateObjectReference) -> Option<ObjectId>,
+    ) -> Vec<TemplateObjectReference> {
+        let object_ids: HashSet<u32> = self.object_index().keys().copied().collect();
+        let mut objects = Vec::new();
+        for layer in &mut self.layers {
+            layer.collect_objects_mut(&mut objects);
+        }
+
+        let mut unresolved = Vec::new();
+        for object in objects {
+            for reference in template_references(object).collect::<Vec<_>>() {
+                match resolve(&reference) {
+                    Some(target) if object_ids.contains(&target.0) => {
+                        object.resolve_template_object_property(&reference.property, target)
+                    }
+                    _ => unresolved.push(reference),
+                }
+            }
+        }
+        unresolved
+    }
+
+    /// Returns the ID that will be given to the next object added to this map through
+    /// [`ObjectLayerDataMut::push_object`](crate::ObjectLayerDataMut::push_object), which is
+    /// always greater than the IDs of the objects it already has. Tiled stores this as the
+    /// `nextobjectid` of the map.
+    #[inline]
+    pub fn next_object_id(&self) -> u32 {
+        self.next_object_id
+    }
+
+    /// Returns the ID that will be given to the next layer added to this map, which is always
+    /// greater than the IDs of the layers it already has, including the ones in groups. Tiled
+    /// stores this as the `nextlayerid` of the map.
+    #[inline]
+    pub fn next_layer_id(&self) -> u32 {
+        self.next_layer_id
+    }
+
+    /// Returns the highest ID of the layers in this map, including the ones in groups, or 0 if it
+    /// has none.
+    fn max_layer_id(&self) -> u32 {
+        fn max_id<'map>(layers: impl Iterator<Item = Layer<'map>>) -> u32 {
+            layers
+                .map(|layer| match layer.layer_type() {
+                    LayerType::Group(group) => layer.id().0.max(max_id(group.layers())),
+                    _ => layer.id().0,
+                })
+                .max()
+                .unwrap_or(0)
+        }
+
+        max_id(self.layers())
+    }
+
+    /// Returns the highest ID of the objects in this map, or 0 if it has none.
+    fn max_object_id(&self) -> u32 {
+        self.object_index().keys().copied().max().unwrap_or(0)
+    }
+
+    fn object_index(&self) -> &HashMap<u32, (Vec<usize>, usize)> {
+        fn index_layers<'map>(
+            layers: impl Iterator<Item = Layer<'map>>,
+            path: &mut Vec<usize>,
+            index: &mut HashMap<u32, (Vec<usize>, usize)>,
+        ) {
+            for (layer_index, layer) in layers.enumerate() {
+                path.push(layer_index);
+                match layer.layer_type() {
+                    LayerType::Objects(objects) => {
+                        for (object_index, object) in objects.objects().enumerate() {
+                            index
+                                .entry(object.id().0)
+                                .or_insert_with(|| (path.clone(), object_index));
+                        }
+                    }
+                    LayerType::Group(group) => index_layers(group.layers(), path, index),
+                    _ => {}
+                }
+                path.pop();
+            }
+        }
+
+        self.object_index.0.get_or_init(|| {
+            let mut index = HashMap::new();
+            index_layers(self.layers(), &mut Vec::new(), &mut index);
+            index
+        })
+    }
+
+    /// Returns the first top-level layer with the given name, if any.
+    ///
+    /// To look up layers nested in groups, use [`Map::layer_at_path`].
+    pub fn get_layer_by_name(&self, name: &str) -> Option<Layer<'_>> {
+        self.layers().find(|layer| layer.name == name)
+    }
+
+    /// Returns the layer at the given path, made of the names of the group layers that contain it
+    /// followed by its own name, separated by slashes. If several layers on the way have the same
+    /// name, the first one is used.
+    ///
+    /// Since slashes are always treated as separators, layers whose names contain them can't be
+    /// looked up this way.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # use tiled::{LayerId, Loader};
+    /// #
+    /// # fn main() {
+    /// # let map = Loader::new()
+    /// #     .load_tmx_map("assets/tiled_group_layers.tmx")
+    /// #     .unwrap();
+    /// #
+    /// let layer = map.layer_at_path("group-2/group-3/tile-3").unwrap();
+    /// assert_eq!(layer.id(), LayerId(9));
+    /// # }
+    /// ```
+    pub fn layer_at_path(&self, path: &str) -> Option<Layer<'_>> {
+        let mut names = path.split('/');
+        let mut layer = self.get_layer_by_name(names.next()?)?;
+        for name in names {
+            layer = layer.as_group_layer()?.get_layer_by_name(name)?;
+        }
+        Some(layer)
+    }
+
+    /// Starts a search for the layers of this map with a given kind, class or name, which can be
+    /// nested in group layers; See [`LayerQuery`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::ObjectLayer;
+    ///
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_groups.tmx")?;
+    /// for layer in map.query_layers().flattened().of_type::<ObjectLayer>() {
+    ///     println!("{} has {} objects", layer.name, layer.as_object_layer().unwrap().objects().len());
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn query_layers(&self) -> LayerQuery<'_> {
+        LayerQuery::new(self)
+    }
+}
+
+impl Map {
+    /// Returns the size of the map's tiles in pixels, as [`Self::tile_width`] and
+    /// [`Self::tile_height`].
+    #[inline]
+    pub fn tile_size(&self) -> (u32, u32) {
+        (self.tile_width, self.tile_height)
+    }
+
+    /// Returns the width of the map in pixels, taking into account the map's orientation, stagger
+    /// settings and hex side length; See [`Self::bounds`].
+    pub fn pixel_width(&self) -> u32 {
+        self.pixel_size().0
+    }
+
+    /// Returns the height of the map in pixels, taking into account the map's orientation, stagger
+    /// settings and hex side length; See [`Self::bounds`].
+    pub fn pixel_height(&self) -> u32 {
+        self.pixel_size().1
+    }
+
+    /// Returns the area covered by the bounding boxes of the map's tiles, in pixels, which is
+    /// computed the same way Tiled does. It starts at the origin, and is
+    /// [`Self::pixel_width`] by [`Self::pixel_height`] pixels.
+    ///
+    /// For infinite maps, this is the area of the [`Self::width`] by [`Self::height`] tiles
+    /// starting at the origin rather than the area actually used by their layers.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{MapBuilder, Orientation, Rect};
+    ///
+    /// let map = MapBuilder::new(Orientation::Isometric, 4, 3, 64, 32)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(
+    ///     map.bounds(),
+    ///     Rect {
+    ///         x: 0.0,
+    ///         y: 0.0,
+    ///         width: 224.0,
+    ///         height: 112.0,
+    ///     }
+    /// );
+    /// ```
+    pub fn bounds(&self) -> Rect {
+        let (width, height) = self.pixel_size();
+        Rect {
+            x: 0.0,
+            y: 0.0,
+            width: width as f32,
+            height: height as f32,
+        }
+    }
+
+    /// Returns the smallest rectangle containing the bounding boxes of the tiles between the
+    /// given ones, inclusive, in pixels.
+    pub(crate) fn tile_range_bounds(&self, min: (i32, i32), max: (i32, i32)) -> Rect {
+        // The outermost tiles are at the corners of the range, or next to them in maps where
+        // every other row or column is shifted.
+        let near = |min: i32, max: i32| {
+            [
+                min,
+                min.saturating_add(1).min(max),
+                max.saturating_sub(1).max(min),
+                max,
+            ]
+        };
+        let (tile_width, tile_height) = (self.tile_width as f32, self.tile_height as f32);
+        Rect::enclosing(IntoIterator::into_iter(near(min.0, max.0)).flat_map(|x| {
+            IntoIterator::into_iter(near(min.1, max.1)).flat_map(move |y| {
+                let (px, py) = self.tile_to_pixel(x, y);
+                [(px, py), (px + tile_width, py + tile_height)]
+            })
+        }))
+    }
+
+    fn pixel_size(&self) -> (u32, u32) {
+        match self.orientation {
+            Orientation::Orthogonal => (
+                self.width.saturating_mul(self.tile_width),
+                self.height.saturating_mul(self.tile_height),
+            ),
+            Orientation::Isometric => {
+                let side = self.width as i128 + self.height as i128;
+                (
+                    saturating_u32(side * self.tile_width as i128 / 2),
+                    saturating_u32(side * self.tile_height as i128 / 2),
+                )
+            }
+            Orientation::Staggered | Orientation::Hexagonal => {
+                StaggerParams::new(self).map_size(self.width, self.height)
+            }
+        }
+    }
+
+    /// Converts a tile position into the pixel position of the top-left corner of the tile's
+    /// bounding box, taking into account the map's orientation, stagger settings and hex side
+    /// length.
+    ///
+    /// The bounding box of a tile is [`Self::tile_width`] by [`Self::tile_height`] pixels. Tile
+    /// images larger than that are aligned to the bottom-left corner of it. Note that objects in
+    /// isometric maps use a different coordinate system and can't be converted with this method.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{MapBuilder, Orientation};
+    ///
+    /// let map = MapBuilder::new(Orientation::Isometric, 4, 4, 64, 32)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(map.tile_to_pixel(0, 0), (96.0, 0.0));
+    /// assert_eq!(map.tile_to_pixel(0, 3), (0.0, 48.0));
+    /// ```
+    pub fn tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        let tile_width = self.tile_width as f32;
+        let tile_height = self.tile_height as f32;
+        match self.orientation {
+            Orientation::Orthogonal => (x as f32 * tile_width, y as f32 * tile_height),
+            Orientation::Isometric => {
+                let (x, y) = (x as i64, y as i64);
+                (
+                    (x - y + self.height as i64 - 1) as f32 * tile_width / 2.0,
+                    (x + y) as f32 * tile_height / 2.0,
+                )
+            }
+            Orientation::Staggered | Orientation::Hexagonal => {
+                StaggerParams::new(self).tile_to_pixel(x, y)
+            }
+        }
+    }
+
+    /// Converts a pixel position into the position of the tile that contains it; The inverse of
+    /// [`Self::tile_to_pixel`].
+    ///
+    /// The position returned may be outside of the map's bounds.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{MapBuilder, Orientation};
+    ///
+    /// let map = MapBuilder::new(Orientation::Isometric, 4, 4, 64, 32)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(map.pixel_to_tile(128.0, 16.0), (0, 0));
+    /// assert_eq!(map.pixel_to_tile(32.0, 64.0), (0, 3));
+    /// ```
+    pub fn pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        let tile_width = self.tile_width as f32;
+        let tile_height = self.tile_height as f32;
+        match self.orientation {
+            Orientation::Orthogonal => (
+                (x / tile_width).floor() as i32,
+                (y / tile_height).floor() as i32,
+            ),
+            Orientation::Isometric => {
+                // Make the position relative to the top corner of the tile at (0, 0).
+                let x = x - self.height as f32 * tile_width / 2.0;
+                let y = y / tile_height;
+                (
+                    (y + x / tile_width).floor() as i32,
+                    (y - x / tile_width).floor() as i32,
+                )
+            }
+            Orientation::Staggered | Orientation::Hexagonal => {
+                StaggerParams::new(self).pixel_to_tile(x, y)
+            }
+        }
+    }
+
+    /// Returns the positions of the tiles that share an edge with the tile at the given position,
+    /// taking into account the map's orientation and stagger settings: 4 for orthogonal,
+    /// isometric and staggered maps, and 6 for hexagonal ones.
+    ///
+    /// The positions returned may be outside of the map's bounds.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{MapBuilder, Orientation};
+    ///
+    /// // Odd rows are shifted right by default.
+    /// let map = MapBuilder::new(Orientation::Hexagonal, 8, 8, 32, 32)
+    ///     .build()
+    ///     .unwrap();
+    /// let mut neighbors = map.neighbors(2, 1);
+    /// neighbors.sort();
+    /// assert_eq!(neighbors, [(1, 1), (2, 0), (2, 2), (3, 0), (3, 1), (3, 2)]);
+    /// ```
+    pub fn neighbors(&self, x: i32, y: i32) -> Vec<(i32, i32)> {
+        const SQUARE: [(i64, i64); 4] = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+        const AXIAL: [(i64, i64); 6] = [(1, 0), (1, -1), (0, -1), (-1, 0), (-1, 1), (0, 1)];
+        let fit = |(x, y): (i64, i64)| Some((i32::try_from(x).ok()?, i32::try_from(y).ok()?));
+        match self.orientation {
+            Orientation::Orthogonal | Orientation::Isometric => SQUARE
+                .iter()
+                .filter_map(|(dx, dy)| fit((x as i64 + dx, y as i64 + dy)))
+                .collect(),
+            Orientation::Staggered | Orientation::Hexagonal => {
+                let stagger = StaggerParams::new(self);
+                let (q, r) = stagger.tile_to_axial(x, y);
+                AXIAL
+                    .iter()
+                    // Staggered tiles only touch the ones next to them along the stagger axis
+                    // at their corners.
+                    .filter(|&&(dq, dr)| {
+                        stagger.hexagonal || if stagger.stagger_x { dq != 0 } else { dr != 0 }
+                    })
+                    .filter_map(|(dq, dr)| fit(stagger.axial_to_tile(q + dq, r + dr)))
+                    .collect()
+            }
+        }
+    }
+
+    /// Returns the number of steps it takes to go from one tile to another by moving between
+    /// [neighbors](Self::neighbors), taking into account the map's orientation and stagger
+    /// settings.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{MapBuilder, Orientation};
+    ///
+    /// let map = MapBuilder::new(Orientation::Hexagonal, 8, 8, 32, 32)
+    ///     .build()
+    ///     .unwrap();
+    /// assert_eq!(map.tile_distance((2, 1), (3, 2)), 1);
+    /// assert_eq!(map.tile_distance((0, 0), (3, 4)), 5);
+    /// ```
+    pub fn tile_distance(&self, a: (i32, i32), b: (i32, i32)) -> u64 {
+        match self.orientation {
+            Orientation::Orthogonal | Orientation::Isometric => {
+                (a.0 as i64 - b.0 as i64).unsigned_abs() + (a.1 as i64 - b.1 as i64).unsigned_abs()
+            }
+            Orientation::Staggered | Orientation::Hexagonal => {
+                let stagger = StaggerParams::new(self);
+                let (a, b) = (
+                    stagger.tile_to_axial(a.0, a.1),
+                    stagger.tile_to_axial(b.0, b.1),
+                );
+                let (dq, dr) = (a.0 - b.0, a.1 - b.1);
+                if stagger.hexagonal {
+                    (dq.unsigned_abs() + dr.unsigned_abs() + (dq + dr).unsigned_abs()) / 2
+                } else if stagger.stagger_x {
+                    // Staggered maps are square grids turned by 45 degrees, whose axes go along
+                    // the steps of (1, 0) and (1, -1) in axial coordinates here, and (0, 1) and
+                    // (1, -1) when staggered along the Y axis.
+                    (dq + dr).unsigned_abs() + dr.unsigned_abs()
+                } else {
+                    (dq + dr).unsigned_abs() + dq.unsigned_abs()
+                }
+            }
+        }
+    }
+}
+
+impl Map {
+    /// Changes the size of this finite map to the given amount of tiles, keeping its contents
+    /// in place relative to the given anchor, like Tiled's "Resize Map" does.
+    ///
+    /// The tiles of tile layers, the objects and the image layers are all moved by the same
+    /// amount, and tiles and objects that end up outside of the map are removed. Objects are kept
+    /// if their bounds touch the map.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the map is infinite or the new size is empty.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, ResizeAnchor};
+    ///
+    /// let mut map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let tile_id = |map: &tiled::Map, x, y| {
+    ///     let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    ///     layer.get_tile(x, y).map(|tile| tile.id())
+    /// };
+    /// let corner = tile_id(&map, 99, 99);
+    /// // Make room for two more rows and columns on the top and on the left.
+    /// map.resize(102, 102, ResizeAnchor::BottomRight)?;
+    /// assert_eq!((map.width, map.height), (102, 102));
+    /// assert_eq!(tile_id(&map, 101, 101), corner);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn resize(&mut self, width: u32, height: u32, anchor: ResizeAnchor) -> Result<()> {
+        self.check_resizable()?;
+        let (anchor_x, anchor_y) = anchor.halves();
+        let offset = |new: u32, old: u32, anchor: i64| (new as i64 - old as i64) * anchor / 2;
+        let tile_offset = (
+            offset(width, self.width, anchor_x),
+            offset(height, self.height, anchor_y),
+        );
+        self.resize_with_offset(width, height, tile_offset)
+    }
+
+    /// Crops this finite map to the given rectangle of tiles, which is given by its smallest and
+    /// largest tile positions, both inclusive; See [`Self::resize`].
+    ///
+    /// The rectangle can extend past the map, in which case the map grows. This can be used to
+    /// split large maps into smaller ones by cropping clones of them.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the map is infinite or the largest position is
+    /// smaller than the smallest one.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let mut map = tiled::Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let tile_id = |map: &tiled::Map, x, y| {
+    ///     let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    ///     layer.get_tile(x, y).map(|tile| tile.id())
+    /// };
+    /// let tile = tile_id(&map, 10, 20);
+    /// map.crop(((10, 20), (41, 51)))?;
+    /// assert_eq!((map.width, map.height), (32, 32));
+    /// assert_eq!(tile_id(&map, 0, 0), tile);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn crop(
+        &mut self,
+        ((min_x, min_y), (max_x, max_y)): ((i32, i32), (i32, i32)),
+    ) -> Result<()> {
+        self.check_resizable()?;
+        let (width, height) = region_size(((min_x, min_y), (max_x, max_y)))?;
+        self.resize_with_offset(width, height, (-(min_x as i64), -(min_y as i64)))
+    }
+
+    /// Returns a new finite map with the contents of the given rectangle of tiles of this one and
+    /// the layers that match the given filter, for splitting large maps into smaller ones. The
+    /// rectangle is given by its smallest and largest tile positions, both inclusive.
+    ///
+    /// The contents of the rectangle are moved to the new map as [`Self::crop`] does, which also
+    /// turns the layers of infinite maps into finite ones. Only the tilesets the new map uses are
+    /// kept, as [`Self::remap_tilesets`] does.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if the largest position is smaller than the smallest
+    /// one.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{LayerFilter, Loader};
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")?;
+    /// let cell = map.extract(((0, 0), (15, 15)), &LayerFilter::All)?;
+    /// assert!(!cell.infinite());
+    /// assert_eq!((cell.width, cell.height), (16, 16));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn extract(
+        &self,
+        ((min_x, min_y), (max_x, max_y)): ((i32, i32), (i32, i32)),
+        layers: &LayerFilter,
+    ) -> Result<Map> {
+        let (width, height) = region_size(((min_x, min_y), (max_x, max_y)))?;
+        let mut map = self.clone();
+        map.layers
+            .retain_mut(|layer| layer.retain_layers(&|layer| layers.matches(layer)));
+        map.resize_with_offset(width, height, (-(min_x as i64), -(min_y as i64)))?;
+        let kept = map.used_tilesets();
+        map.remap_tilesets(&kept)?;
+        Ok(map)
+    }
+
+    /// Checks that this map can be resized, which infinite maps can't.
+    fn check_resizable(&self) -> Result<()> {
+        if self.infinite {
+            return Err(Error::InvalidMapData {
+                description: "infinite maps cannot be resized".to_owned(),
+            });
+        }
+        Ok(())
+    }
+
+    /// Combines the top-level tile layers with the given indices into a new finite tile layer
+    /// with the given name, where the tiles of upper layers replace the ones below them. Hidden
+    /// layers are left out, and the offsets and other settings of the layers aren't applied.
+    ///
+    /// The new layer isn't part of the map; It can be added with [`Self::push_layer`], for
+    /// example after removing the layers that were merged.
+    ///
+    /// Fails with [`Error::InvalidMapData`] if an index doesn't refer to a finite tile layer.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let mut map = tiled::Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+    /// let merged = map.merge_tile_layers(&[0], "merged")?;
+    /// let id = map.push_layer(merged)?;
+    /// let layer = map.layer_by_id(id).unwrap();
+    /// assert_eq!(layer.name, "merged");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn merge_tile_layers(
+        &self,
+        indices: &[usize],
+        name: impl Into<String>,
+    ) -> Result<LayerData> {
+        let mut indices = indices.to_vec();
+        indices.sort_unstable();
+        indices.dedup();
+        let mut merged = TileLayerBuilder::new(self.width, self.height).name(name);
+        for index in indices {
+            let layer = self.get_layer(index);
+            match layer.map(|layer| (layer.visible, layer.as_tile_layer())) {
+                Some((true, Some(TileLayer::Finite(tiles)))) => {
+                    for ((x, y), tile) in tiles.tile_data() {
+                        merged = merged.set_tile(x, y, Some(tile))?;
+                    }
+                }
+                Some((false, Some(TileLayer::Finite(_)))) => {}
+                _ => {
+                    return Err(Error::InvalidMapData {
+                        description: format!("layer {} is not a finite tile layer", index),
+                    })
+                }
+            }
+        }
+        Ok(merged.build())
+    }
+
+    /// Changes the size of this map, moving its contents by the given amount of tiles. Infinite
+    /// maps become finite.
+    fn resize_with_offset(
+        &mut self,
+        width: u32,
+        height: u32,
+        tile_offset: (i64, i64),
+    ) -> Result<()> {
+        if width == 0 || height == 0 {
+            return Err(Error::InvalidMapData {
+                description: format!("cannot resize the map to {}x{} tiles", width, height),
+            });
+        }
+
+        // Objects are moved along with the tile their position is in, which in isometric maps
+        // is measured in tile heights along both axes.
+        let pixel_offset = match self.orientation {
+            Orientation::Isometric => (
+                tile_offset.0 as f32 * self.tile_height as f32,
+                tile_offset.1 as f32 * self.tile_height as f32,
+            ),
+            _ => {
+                let fit = |offset: i64| -offset.clamp(i32::MIN as i64 + 1, i32::MAX as i64) as i32;
+                let (origin_x, origin_y) = self.tile_to_pixel(0, 0);
+                let (x, y) = self.tile_to_pixel(fit(tile_offset.0), fit(tile_offset.1));
+                (origin_x - x, origin_y - y)
+            }
+        };
+
+        let objects: Vec<(usize, Rect)> = self
+            .flattened_layers()
+            .filter_map(|layer| layer.layer.as_object_layer())
+            .flat_map(|layer| layer.objects())
+            .map(|object| {
+                (
+                    &*object as *const ObjectData as usize,
+                    object.placed_bounds(),
+                )
+            })
+            .collect();
+        self.width = width;
+        self.height = height;
+        let bounds = match self.orientation {
+            Orientation::Isometric => Rect {
+                x: 0.0,
+                y: 0.0,
+                width: width as f32 * self.tile_height as f32,
+                height: height as f32 * self.tile_height as f32,
+            },
+            _ => self.bounds(),
+        };
+        let removed_objects = objects
+            .into_iter()
+            .filter(|(_, object)| {
+                let (x, y) = (object.x + pixel_offset.0, object.y + pixel_offset.1);
+                x > bounds.x + bounds.width
+                    || y > bounds.y + bounds.height
+                    || x + object.width < bounds.x
+                    || y + object.height < bounds.y
+            })
+            .map(|(address, _)| address)
+            .collect();
+
+        let resize = LayerResize {
+            width,
+            height,
+            tile_offset,
+            pixel_offset,
+            removed_objects,
+        };
+        for layer in &mut self.layers {
+            layer.resize(&resize);
+        }
+        self.infinite = false;
+        self.object_index = ObjectIndex::default();
+        self.parent_index = ParentIndex::default();
+        Ok(())
+    }
+}
+
+impl Map {
+    /// Returns the IDs of the tiles this map uses from each of its tilesets, in the same order as
+    /// [`Self::tileset_first_gids`]. This can be used to trim texture atlases down to the tiles that
+    /// are actually needed.
+    ///
+    /// A tile is considered used if it is placed in any tile layer, including the ones in groups,
+    /// or by any tile object. If the map's tilesets have been [resolved](Self::resolve_tilesets),
+    /// the frames of the animations of used tiles are included as well. Tiles used by objects whose
+    /// tileset comes from a template aren't part of the map's tilesets, so they are left out.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let used = map.used_tiles();
+    /// println!("The map uses {} tiles from its first tileset", used[0].len());
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn used_tiles(&self) -> Vec<HashSet<TileId>> {
+        let mut used = vec![HashSet::new(); self.tileset_first_gids.len()];
+        let mut add = |tileset_index: usize, id: TileId| {
+            if let Some(tiles) = used.get_mut(tileset_index) {
+                tiles.insert(id);
+            }
+        };
+        for flattened in self.flattened_layers() {
+            match flattened.layer.layer_type() {
+                LayerType::Tiles(TileLayer::Finite(layer)) => {
+                    for (_, tile) in layer.tile_data() {
+                        add(tile.tileset_index(), tile.id());
+                    }
+                }
+                LayerType::Tiles(TileLayer::Infinite(layer)) => {
+                    for (_, tile) in layer.tile_data() {
+                        add(tile.tileset_index(), tile.id());
+                    }
+                }
+                LayerType::Objects(layer) => {
+                    for object in layer.objects() {
+                        if let Some(tile) = object.tile_data() {
+                            if let TilesetLocation::Map(tileset_index) = tile.tileset_location() {
+                                add(*tileset_index, tile.id());
+                            }
+                        }
+                    }
+                }
+                LayerType::Image(_) | LayerType::Group(_) => {}
+            }
+        }
+
+        for (tiles, tileset) in used.iter_mut().zip(&self.tilesets) {
+            let mut frames = Vec::new();
+            for tile in tiles.iter().filter_map(|id| tileset.get_tile(*id)) {
+                if let Some(animation) = &tile.animation {
+                    frames.extend(animation.iter().map(|frame| frame.tile_id));
+                }
+            }
+            tiles.extend(frames);
+        }
+        used
+    }
+
+    /// Returns the indices of the tilesets that have at least one tile used by this map, as
+    /// described in [`Self::used_tiles`], in ascending order. Tilesets whose indices are missing
+    /// are referenced by the map without being needed.
+    pub fn used_tilesets(&self) -> Vec<usize> {
+        self.used_tiles()
+            .iter()
+            .enumerate()
+            .filter(|(_, tiles)| !tiles.is_empty())
+            .map(|(index, _)| index)
+            .collect()
+    }
+}
+
+impl Map {
+    /// Returns which tiles of the map are solid, judging by the tiles placed on them: A cell of
+    /// the grid is `true` if any tile layer has a tile there for which `predicate` returns `true`.
+    ///
+    /// All tile layers are merged, including hidden ones and the ones in groups. Layers offset by
+    /// their own offset and the ones of their parent groups have their tiles moved to the cells
+    /// their centers end up in. The grid covers the whole map for finite maps, and the area of
+    /// the solid tiles for infinite maps; See [`Grid::origin`].
+    ///
+    /// To also take objects into account, use [`Self::build_collision_grid_with_objects`].
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// // Consider every tile that has collision shapes to be solid.
+    /// let grid = map.build_collision_grid(|tile| {
+    ///     matches!(tile.get_tile(), Some(tile) if tile.collision.is_some())
+    /// });
+    /// assert_eq!((grid.width(), grid.height()), (map.width, map.height));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build_collision_grid(&self, predicate: impl Fn(LayerTile) -> bool) -> Grid<bool> {
+        build_collision_grid(self, predicate, |_| false)
+    }
+
+    /// Like [`Self::build_collision_grid`], but also marks the cells whose centers are inside
+    /// the rectangles, ellipses, polygons and tile objects for which `object_predicate` returns
+    /// `true`, taking their rotation and the offsets of their layers into account.
+    ///
+    /// In infinite maps, objects only mark cells within the area covered by the tile layers.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_groups.tmx")?;
+    /// let grid = map.build_collision_grid_with_objects(
+    ///     |_| false,
+    ///     |object| object.user_type == "wall",
+    /// );
+    /// assert!(grid.cells().iter().all(|solid| !solid));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn build_collision_grid_with_objects(
+        &self,
+        tile_predicate: impl Fn(LayerTile) -> bool,
+        object_predicate: impl Fn(Object) -> bool,
+    ) -> Grid<bool> {
+        build_collision_grid(self, tile_predicate, object_predicate)
+    }
+}
+
+impl Map {
+    /// Checks this map for problems that don't prevent it from loading but likely point at
+    /// mistakes, such as tiles that don't exist in their tileset or layers sharing an ID, returning
+    /// a [`Diagnostic`] for each one found. This can be used to lint maps as part of an asset
+    /// pipeline.
     ///
-    /// Note: "top-level" means that if a map has layers of `LayerDataType::Group` type, you
-    /// need to recursively enumerate those group layers.
+    /// Class properties are checked against the given [`Project`], if any. Checks involving
+    /// tilesets are skipped if they haven't been [resolved](Self::resolve_tilesets) yet.
     ///
     /// ## Example
-    /// ```
-    /// # use tiled::Loader;
-    /// #
-    /// # fn main() {
-    /// # struct Renderer;
-    /// # impl Renderer {
-    /// #     fn render(&self, _: tiled::TileLayer) {}
-    /// # }
-    /// # let my_renderer = Renderer;
-    /// # let map = Loader::new()
-    /// #     .load_tmx_map("assets/tiled_group_layers.tmx")
-    /// #     .unwrap();
-    /// #
-    /// let tile_layers = map.layers().filter_map(|layer| match layer.layer_type() {
-    ///     tiled::LayerType::Tiles(layer) => Some(layer),
-    ///     _ => None,
-    /// });
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
     ///
-    /// for layer in tile_layers {
-    ///     my_renderer.render(layer);
+    /// let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// for diagnostic in map.validate(None) {
+    ///     eprintln!("warning: {}", diagnostic);
     /// }
+    /// # Ok(())
     /// # }
     /// ```
-    #[inline]
-    pub fn layers(&self) -> impl ExactSizeIterator<Item = Layer> {
-        self.layers.iter().map(move |layer| Layer::new(self, layer))
+    pub fn validate(&self, project: Option<&Project>) -> Vec<Diagnostic> {
+        validate_map(self, project)
     }
 
-    /// Returns the top-level layer that has the specified index, if it exists.
-    pub fn get_layer(&self, index: usize) -> Option<Layer> {
-        self.layers.get(index).map(|data| Layer::new(self, data))
+    /// Compares this map with another one, returning a [`MapDifference`] for each value that
+    /// differs between them, such as a layer's offset, an object's position, a tile of a tile
+    /// layer or a property. This can be used to check the output of map processing in tests.
+    ///
+    /// Layers are matched by their position in the layer tree, objects by their ID and tilesets
+    /// by their index. Differences in the file format [version](Self::version) are reported too,
+    /// while [warnings](Self::warnings) and unknown XML are ignored.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let mut loader = Loader::new();
+    /// let map = loader.load_tmx_map("assets/tiled_base64.tmx")?;
+    /// let mut changed = map.clone();
+    /// changed.width += 1;
+    ///
+    /// assert!(map.diff(&map).is_empty());
+    /// let differences = map.diff(&changed);
+    /// assert_eq!(differences.len(), 1);
+    /// assert_eq!(differences[0].to_string(), "width: 100 != 101");
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn diff(&self, other: &Map) -> Vec<MapDifference> {
+        diff_maps(self, other)
+    }
+
+    /// Returns an indented textual summary of this map, listing its tilesets, its layer tree with
+    /// the number of tiles or objects in each layer, and all properties. This is meant for
+    /// debugging and inspecting maps rather than being parsed; Its format may change.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let map = Loader::new().load_tmx_map("assets/tiled_group_layers.tmx")?;
+    /// println!("{}", map.dump_tree());
+    /// assert!(map.dump_tree().starts_with("map 8x8 orthogonal, tiles 32x32"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn dump_tree(&self) -> String {
+        dump_map(self)
     }
 }
 
 impl Map {
+    /// Replaces every use of the given tileset by this map's layers with another one, returning
+    /// whether it was found. Tilesets used by objects created from templates aren't replaced.
+    pub(crate) fn replace_tileset(&mut self, old: &Arc<Tileset>, new: &Arc<Tileset>) -> bool {
+        let mut found = false;
+        for tileset in &mut self.tilesets {
+            if Arc::ptr_eq(tileset, old) {
+                *tileset = new.clone();
+                found = true;
+            }
+        }
+        for tileset in &mut self.lazy_tilesets {
+            if tileset
+                .get()
+                .is_some_and(|tileset| Arc::ptr_eq(tileset, old))
+            {
+                *tileset = TilesetRef {
+                    path: tileset.path.clone(),
+                    tileset: OnceLock::from(new.clone()),
+                };
+                found = true;
+            }
+        }
+        found
+    }
+
+    /// Returns the finite tile layers of this map, including the ones in groups.
+    #[cfg(feature = "rayon")]
+    fn finite_tile_layers_mut(&mut self) -> Vec<&mut crate::FiniteTileLayerData> {
+        let mut layers = Vec::new();
+        for layer in &mut self.layers {
+            layer.collect_finite_tile_layers(&mut layers);
+        }
+        layers
+    }
+
     pub(crate) fn parse_xml(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         map_path: &Path,
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
+        events: &mut MapEvents<'_>,
     ) -> Result<Map> {
+        // Streamed layers are handed over along with their tilesets, so these must be loaded.
+        let lazy_tilesets = parser.options().lazy_tilesets() && !events.is_streaming();
         let (
             (c, infinite, user_type, user_class, stagger_axis, stagger_index),
+            (hex_side_length, render_order, next_layer_id, next_object_id, compression_level),
             (v, o, w, h, tw, th),
+            (parallax_origin_x, parallax_origin_y),
         ) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("map", parser.event_position()) {
                 Some("backgroundcolor") => colour ?= v.parse(),
                 Some("infinite") => infinite = v == "1",
                 Some("type") => user_type ?= v.parse(),
                 Some("class") => user_class ?= v.parse(),
                 Some("staggeraxis") => stagger_axis ?= v.parse::<StaggerAxis>(),
                 Some("staggerindex") => stagger_index ?= v.parse::<StaggerIndex>(),
+                Some("hexsidelength") => hex_side_length ?= v.parse::<u32>(),
+                Some("renderorder") => render_order ?= v.parse::<RenderOrder>(),
+                Some("compressionlevel") => compression_level ?= v.parse::<i32>(),
+                Some("nextlayerid") => next_layer_id ?= v.parse::<u32>(),
+                Some("nextobjectid") => next_object_id ?= v.parse::<u32>(),
+                Some("parallaxoriginx") => parallax_origin_x ?= v.parse::<f32>(),
+                Some("parallaxoriginy") => parallax_origin_y ?= v.parse::<f32>(),
                 "version" => version = v,
                 "orientation" => orientation ?= v.parse::<Orientation>(),
                 "width" => width ?= v.parse::<u32>(),
@@ -174,108 +1816,170 @@ impl Map {
                 "tilewidth" => tile_width ?= v.parse::<u32>(),
                 "tileheight" => tile_height ?= v.parse::<u32>(),
             }
-            ((colour, infinite, user_type, user_class, stagger_axis, stagger_index), (version, orientation, width, height, tile_width, tile_height))
+            ((colour, infinite, user_type, user_class, stagger_axis, stagger_index), (hex_side_length, render_order, next_layer_id, next_object_id, compression_level), (version, orientation, width, height, tile_width, tile_height), (parallax_origin_x, parallax_origin_y))
         );
+        let mut extra = ExtraXml::from_attributes(parser, &attrs, MAP_ATTRIBUTES);
+        parser.set_legacy_format(compat::is_legacy_version(Some(&v)));
 
         let infinite = infinite.unwrap_or(false);
         let user_type = user_type.or(user_class);
         let stagger_axis = stagger_axis.unwrap_or_default();
         let stagger_index = stagger_index.unwrap_or_default();
+        let render_order = render_order.unwrap_or_default();
+        let compression_level = compression_level.filter(|level| *level != -1);
+        events.emit(MapEvent::MapStart(&MapHeader {
+            version: v.clone(),
+            orientation: o,
+            width: w,
+            height: h,
+            tile_width: tw,
+            tile_height: th,
+            infinite,
+        }))?;
 
-        // We can only parse sequentally, but tilesets are guaranteed to appear before layers.
-        // So we can pass in tileset data to layer construction without worrying about unfinished
-        // data usage.
+        // We can only parse sequentially, and tilesets may appear after the layers using them. So
+        // layers are parsed with the tilesets declared so far, and their tiles are looked up in the
+        // complete list of tilesets once the whole map has been parsed.
         let mut layers = Vec::new();
         let mut properties = HashMap::new();
         let mut tilesets = Vec::new();
 
+        // Elements from files without IDs all get 0, so it is left out when looking for
+        // duplicates.
+        let mut layer_ids = HashSet::new();
+        let mut object_ids = HashSet::new();
+        // The first GIDs of the tilesets each layer was parsed with, which miss the tilesets
+        // declared after it.
+        let mut layer_first_gids: Vec<Vec<Gid>> = Vec::new();
+
         parse_tag!(parser, "map", {
             "tileset" => |attrs: Vec<OwnedAttribute>| {
-                let res = Tileset::parse_xml_in_map(parser, &attrs, map_path,  reader, cache)?;
+                let position = parser.event_position();
+                let res = Tileset::parse_xml_in_map(parser, &attrs, map_path,  reader, cache, project)?;
+                let first_gid = res.first_gid;
                 match res.result_type {
                     EmbeddedParseResultType::ExternalReference { tileset_path } => {
                         let tileset = if let Some(ts) = cache.get_tileset(&tileset_path) {
-                            ts
+                            TilesetRef::external(tileset_path, Some(ts))
+                        } else if lazy_tilesets {
+                            TilesetRef::external(tileset_path, None)
                         } else {
-                            let tileset = Arc::new(crate::parse::xml::parse_tileset(&tileset_path,  reader, cache)?);
+                            let tileset = Arc::new(crate::parse::xml::parse_tileset(&tileset_path, reader, cache, project, parser.options())?);
                             cache.insert_tileset(tileset_path.clone(), tileset.clone());
-                            tileset
+                            TilesetRef::external(tileset_path, Some(tileset))
                         };
 
                         tilesets.push(MapTilesetGid{first_gid: res.first_gid, tileset});
                     }
                     EmbeddedParseResultType::Embedded { tileset } => {
-                        tilesets.push(MapTilesetGid{first_gid: res.first_gid, tileset: Arc::new(tileset)});
+                        tilesets.push(MapTilesetGid{first_gid: res.first_gid, tileset: Arc::<Tileset>::from(tileset).into()});
                     },
                 };
+                let (added, previous) = tilesets.split_last().unwrap();
+                if let Some(other) = previous.iter().find(|other| gid_ranges_overlap(added, other)) {
+                    let error = Error::OverlappingTilesets {
+                        first_gid: first_gid.0,
+                        other_first_gid: other.first_gid.0,
+                    };
+                    report_conflict(parser, error.located(parser.path(), position))?;
+                }
+                if let Some(tileset) = added.tileset.get() {
+                    events.emit(MapEvent::TilesetLoaded { index: previous.len(), first_gid, tileset })?;
+                }
                 Ok(())
             },
             "layer" => |attrs| {
-                layers.push(LayerData::new(
+                let position = parser.event_position();
+                let layer_tilesets = tilesets_for_layer(&tilesets);
+                let layer = LayerData::new(
                     parser,
                     attrs,
                     LayerTag::Tiles,
                     infinite,
-                    map_path,
-                    &tilesets,
+                    &layer_tilesets,
                     None,
-                    reader,
-                    cache
-                )?);
-                Ok(())
+                    &mut LoadContext::new(reader, cache, project),
+                )?;
+                check_unique_ids(parser, &layer, position, &mut layer_ids, &mut object_ids)?;
+                add_parsed_layer(layer, &layer_tilesets, &tilesets, &mut layers, &mut layer_first_gids, events)
             },
             "imagelayer" => |attrs| {
-                layers.push(LayerData::new(
+                let position = parser.event_position();
+                let layer_tilesets = tilesets_for_layer(&tilesets);
+                let layer = LayerData::new(
                     parser,
                     attrs,
                     LayerTag::Image,
                     infinite,
-                    map_path,
-                    &tilesets,
+                    &layer_tilesets,
                     None,
-                    reader,
-                    cache
-                )?);
-                Ok(())
+                    &mut LoadContext::new(reader, cache, project),
+                )?;
+                check_unique_ids(parser, &layer, position, &mut layer_ids, &mut object_ids)?;
+                add_parsed_layer(layer, &layer_tilesets, &tilesets, &mut layers, &mut layer_first_gids, events)
             },
             "objectgroup" => |attrs| {
-                layers.push(LayerData::new(
+                let position = parser.event_position();
+                let layer_tilesets = tilesets_for_layer(&tilesets);
+                let layer = LayerData::new(
                     parser,
                     attrs,
                     LayerTag::Objects,
                     infinite,
-                    map_path,
-                    &tilesets,
+                    &layer_tilesets,
                     None,
-                    reader,
-                    cache
-                )?);
-                Ok(())
+                    &mut LoadContext::new(reader, cache, project),
+                )?;
+                check_unique_ids(parser, &layer, position, &mut layer_ids, &mut object_ids)?;
+                add_parsed_layer(layer, &layer_tilesets, &tilesets, &mut layers, &mut layer_first_gids, events)
             },
             "group" => |attrs| {
-                layers.push(LayerData::new(
+                let position = parser.event_position();
+                let layer_tilesets = tilesets_for_layer(&tilesets);
+                let layer = LayerData::new(
                     parser,
                     attrs,
                     LayerTag::Group,
                     infinite,
-                    map_path,
-                    &tilesets,
+                    &layer_tilesets,
                     None,
-                    reader,
-                    cache
-                )?);
-                Ok(())
+                    &mut LoadContext::new(reader, cache, project),
+                )?;
+                check_unique_ids(parser, &layer, position, &mut layer_ids, &mut object_ids)?;
+                add_parsed_layer(layer, &layer_tilesets, &tilesets, &mut layers, &mut layer_first_gids, events)
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project)?;
                 Ok(())
             },
-        });
+        } else |name, attrs| extra.read_element(parser, name, attrs));
 
-        // We do not need first GIDs any more
-        let tilesets = tilesets.into_iter().map(|ts| ts.tileset).collect();
+        let tileset_first_gids: Vec<Gid> = tilesets.iter().map(|ts| ts.first_gid).collect();
+        for (layer, parsed_with) in layers.iter_mut().zip(&layer_first_gids) {
+            if *parsed_with != tileset_first_gids {
+                layer.resolve_tilesets(parsed_with, &tileset_first_gids);
+            }
+        }
+        let tileset_paths = tilesets
+            .iter()
+            .map(|ts| ts.tileset.path().map(Path::to_owned))
+            .collect();
+        let (tilesets, lazy_tilesets) = if tilesets.iter().any(|ts| ts.tileset.get().is_none()) {
+            (
+                Vec::new(),
+                tilesets.into_iter().map(|ts| ts.tileset).collect(),
+            )
+        } else {
+            (
+                tilesets
+                    .into_iter()
+                    .filter_map(|ts| ts.tileset.tileset.into_inner())
+                    .collect(),
+                Vec::new(),
+            )
+        };
 
-        Ok(Map {
+        let mut map = Map {
             version: v,
             orientation: o,
             width: w,
@@ -284,19 +1988,540 @@ impl Map {
             tile_height: th,
             stagger_axis,
             stagger_index,
+            hex_side_length,
+            render_order,
+            compression_level,
             tilesets,
+            lazy_tilesets,
+            tileset_first_gids,
+            tileset_paths,
             layers,
             properties,
             background_color: c,
+            parallax_origin_x: parallax_origin_x.unwrap_or(0.0),
+            parallax_origin_y: parallax_origin_y.unwrap_or(0.0),
             infinite,
             user_type,
+            extra,
+            next_layer_id: 0,
+            next_object_id: 0,
+            warnings: parser.take_warnings(),
+            sources: MapSources::default(),
+            settings: MapSettings::default(),
+            object_index: ObjectIndex::default(),
+            parent_index: ParentIndex::default(),
+        };
+
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            map.finite_tile_layers_mut()
+                .into_par_iter()
+                .try_for_each(|layer| layer.decode_pending())?;
+        }
+        // Old maps don't store the next IDs, and they shouldn't clash with existing ones, including
+        // the ones of streamed layers that aren't in the map anymore.
+        // IDs are kept as they are, even the largest possible ones, which leave no room for more.
+        let max_layer_id = map
+            .max_layer_id()
+            .max(layer_ids.into_iter().max().unwrap_or(0));
+        let max_object_id = map
+            .max_object_id()
+            .max(object_ids.into_iter().max().unwrap_or(0));
+        map.next_layer_id = next_layer_id
+            .unwrap_or(0)
+            .max(max_layer_id.saturating_add(1));
+        map.next_object_id = next_object_id
+            .unwrap_or(0)
+            .max(max_object_id.saturating_add(1));
+
+        Ok(map)
+    }
+}
+
+/// Keeps a top-level layer that has just been parsed along with the first GIDs of the tilesets it
+/// was parsed with, or hands it over if the map is being streamed.
+///
+/// Streamed layers are handed over right away, so they are made to refer to the tilesets declared
+/// before them in the map instead of the ones they were parsed with.
+fn add_parsed_layer(
+    mut layer: LayerData,
+    layer_tilesets: &[MapTilesetGid],
+    tilesets: &[MapTilesetGid],
+    layers: &mut Vec<LayerData>,
+    layer_first_gids: &mut Vec<Vec<Gid>>,
+    events: &mut MapEvents<'_>,
+) -> Result<()> {
+    let parsed_with: Vec<Gid> = layer_tilesets.iter().map(|ts| ts.first_gid).collect();
+    if !events.is_streaming() {
+        layers.push(layer);
+        layer_first_gids.push(parsed_with);
+        return Ok(());
+    }
+    let first_gids: Vec<Gid> = tilesets.iter().map(|ts| ts.first_gid).collect();
+    if parsed_with != first_gids {
+        layer.resolve_tilesets(&parsed_with, &first_gids);
+    }
+    let tilesets = tilesets
+        .iter()
+        .filter_map(|ts| ts.tileset.get().cloned())
+        .collect();
+    events.layer_parsed(layer, tilesets)
+}
+
+/// Returns the tilesets to parse a layer against, given the ones declared before it in its map.
+///
+/// GIDs that come before the first of these tilesets can't be resolved, which would be the case
+/// for all of them if the tilesets were declared after the layer. A placeholder tileset starting
+/// at GID 1 is put first in that case, so that the GIDs can be recovered once all of the map's
+/// tilesets are known; See [`LayerData::resolve_tilesets`].
+fn tilesets_for_layer(tilesets: &[MapTilesetGid]) -> Cow<'_, [MapTilesetGid]> {
+    let first_gid = Gid(1);
+    if matches!(tilesets.first(), Some(ts) if ts.first_gid <= first_gid) {
+        return Cow::Borrowed(tilesets);
+    }
+    let placeholder = MapTilesetGid {
+        first_gid,
+        tileset: TilesetRef::external(PathBuf::new(), None),
+    };
+    Cow::Owned(
+        std::iter::once(placeholder)
+            .chain(tilesets.iter().cloned())
+            .collect(),
+    )
+}
+
+/// Returns the template object references of the given object; See
+/// [`Map::template_object_references`].
+fn template_references(object: &ObjectData) -> impl Iterator<Item = TemplateObjectReference> + '_ {
+    object
+        .template_object_properties()
+        .iter()
+        .filter_map(move |property| match object.properties.get(property) {
+            Some(PropertyValue::ObjectValue(target)) => Some(TemplateObjectReference {
+                object: object.id(),
+                property: property.clone(),
+                target: ObjectId(*target),
+            }),
+            _ => None,
         })
+}
+
+/// The attributes of the map element that are read by [`Map::parse_xml`], along with the ones
+/// describing the file rather than the map.
+const MAP_ATTRIBUTES: &[&str] = &[
+    "version",
+    "tiledversion",
+    "backgroundcolor",
+    "infinite",
+    "type",
+    "class",
+    "staggeraxis",
+    "staggerindex",
+    "hexsidelength",
+    "renderorder",
+    "compressionlevel",
+    "nextlayerid",
+    "nextobjectid",
+    "parallaxoriginx",
+    "parallaxoriginy",
+    "orientation",
+    "width",
+    "height",
+    "tilewidth",
+    "tileheight",
+];
+
+/// Returns the size in tiles of the rectangle between the given smallest and largest tile
+/// positions, both inclusive.
+fn region_size(((min_x, min_y), (max_x, max_y)): ((i32, i32), (i32, i32))) -> Result<(u32, u32)> {
+    let width = u32::try_from(max_x as i64 - min_x as i64 + 1).ok();
+    let height = u32::try_from(max_y as i64 - min_y as i64 + 1).ok();
+    match (width, height) {
+        (Some(width), Some(height)) if width > 0 && height > 0 => Ok((width, height)),
+        _ => Err(Error::InvalidMapData {
+            description: format!(
+                "invalid region from ({}, {}) to ({}, {})",
+                min_x, min_y, max_x, max_y
+            ),
+        }),
+    }
+}
+
+/// Rearranges the items given to be in the order of the indices in `order`, leaving out the ones
+/// not in it.
+fn reorder<T: Clone>(items: &mut Vec<T>, order: &[usize]) {
+    *items = order
+        .iter()
+        .filter_map(|index| items.get(*index).cloned())
+        .collect();
+}
+
+/// Returns the ID to use after the given one for a new layer or object, failing if there are no
+/// IDs left.
+pub(crate) fn next_id(id: u32, kind: &str) -> Result<u32> {
+    id.checked_add(1).ok_or_else(|| Error::InvalidMapData {
+        description: format!("the map has run out of {} IDs", kind),
+    })
+}
+
+/// Returns whether the GID ranges of two tilesets of a map overlap. Tilesets that haven't been
+/// loaded yet are only known to use their first GID.
+fn gid_ranges_overlap(a: &MapTilesetGid, b: &MapTilesetGid) -> bool {
+    let range = |tileset: &MapTilesetGid| {
+        let gid_count = tileset.tileset.get().map_or(1, |loaded| loaded.gid_count());
+        (
+            tileset.first_gid.0 as u64,
+            tileset.first_gid.0 as u64 + gid_count as u64,
+        )
+    };
+    let ((a_start, a_end), (b_start, b_end)) = (range(a), range(b));
+    a_start < b_end && b_start < a_end
+}
+
+/// Records the IDs of a layer that was just parsed, along with the ones of its objects and nested
+/// layers, and handles the ones already used by earlier layers and objects as recoverable errors
+/// located at the layer's element.
+fn check_unique_ids(
+    parser: &mut impl XmlParser,
+    layer: &LayerData,
+    position: XmlPosition,
+    layer_ids: &mut HashSet<u32>,
+    object_ids: &mut HashSet<u32>,
+) -> Result<()> {
+    let (mut new_layer_ids, mut new_object_ids) = (Vec::new(), Vec::new());
+    layer.collect_ids(&mut new_layer_ids, &mut new_object_ids);
+    let duplicate_layer_ids = new_layer_ids
+        .into_iter()
+        .filter(|id| *id != 0 && !layer_ids.insert(*id))
+        .map(|id| Error::DuplicateLayerId { id });
+    let duplicate_object_ids = new_object_ids
+        .into_iter()
+        .filter(|id| *id != 0 && !object_ids.insert(*id))
+        .map(|id| Error::DuplicateObjectId { id });
+    for error in duplicate_layer_ids
+        .chain(duplicate_object_ids)
+        .collect::<Vec<_>>()
+    {
+        report_conflict(parser, error.located(parser.path(), position))?;
+    }
+    Ok(())
+}
+
+/// Handles conflicting IDs or GIDs, which are only warned about unless
+/// [denied](Loader::set_deny_conflicts).
+fn report_conflict(parser: &mut impl XmlParser, error: Error) -> Result<()> {
+    if parser.options().deny_conflicts() {
+        parser.recover(error)
+    } else {
+        parser.warn(error);
+        Ok(())
+    }
+}
+
+/// A reference to one of the tilesets of a map loaded with
+/// [lazy tilesets](Loader::set_lazy_tilesets), which might not have been loaded yet.
+///
+/// External tilesets are loaded on first access through [`TilesetRef::load`], which goes through
+/// the loader's [`ResourceCache`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilesetRef {
+    path: Option<PathBuf>,
+    #[cfg_attr(feature = "serde", serde(with = "serde_tileset_cell"))]
+    tileset: OnceLock<Arc<Tileset>>,
+}
+
+#[cfg(feature = "serde")]
+mod serde_tileset_cell {
+    use std::sync::{Arc, OnceLock};
+
+    use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+    use crate::Tileset;
+
+    pub(super) fn serialize<S: Serializer>(
+        tileset: &OnceLock<Arc<Tileset>>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        tileset.get().serialize(serializer)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<OnceLock<Arc<Tileset>>, D::Error> {
+        Option::<Arc<Tileset>>::deserialize(deserializer)
+            .map(|tileset| tileset.map(OnceLock::from).unwrap_or_default())
+    }
+}
+
+impl TilesetRef {
+    pub(crate) fn external(path: PathBuf, tileset: Option<Arc<Tileset>>) -> Self {
+        Self {
+            path: Some(path),
+            tileset: tileset.map(OnceLock::from).unwrap_or_default(),
+        }
+    }
+
+    /// The path to the external tileset file this reference points to, or [`None`] if the
+    /// tileset was embedded in the map.
+    #[inline]
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    /// Returns the referenced tileset if it has already been loaded.
+    #[inline]
+    pub fn get(&self) -> Option<&Arc<Tileset>> {
+        self.tileset.get()
+    }
+
+    /// Returns the referenced tileset, loading it first if it hasn't been loaded yet.
+    ///
+    /// If the loader's cache already holds the tileset, that instance is used; Otherwise, the
+    /// tileset is parsed and inserted into the cache. Fails if the reference has no tileset and no
+    /// path to load it from, which can only happen to deserialized references.
+    pub fn load<Cache: ResourceCache, Reader: ResourceReader>(
+        &self,
+        loader: &mut Loader<Cache, Reader>,
+    ) -> Result<&Arc<Tileset>> {
+        if let Some(tileset) = self.tileset.get() {
+            return Ok(tileset);
+        }
+        // References to embedded tilesets are always loaded, except when deserialized without
+        // their tileset.
+        let path = self.path.as_deref().ok_or_else(|| Error::InvalidMapData {
+            description: "tileset reference has neither a path nor a tileset".to_owned(),
+        })?;
+        let tileset = loader.load_cached_tileset(path)?;
+        Ok(self.tileset.get_or_init(|| tileset))
+    }
+}
+
+/// The tilesets of a map, whether they have been [resolved](Map::resolve_tilesets) or not. Used
+/// to check tiles placed in the map's layers while the rest of it is borrowed.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct MapTilesets<'map> {
+    tilesets: &'map [Arc<Tileset>],
+    lazy_tilesets: &'map [TilesetRef],
+}
+
+impl<'map> MapTilesets<'map> {
+    #[inline]
+    pub(crate) fn new(tilesets: &'map [Arc<Tileset>], lazy_tilesets: &'map [TilesetRef]) -> Self {
+        Self {
+            tilesets,
+            lazy_tilesets,
+        }
+    }
+
+    /// Returns the amount of tilesets the map has, loaded or not.
+    pub(crate) fn len(&self) -> usize {
+        if self.lazy_tilesets.is_empty() {
+            self.tilesets.len()
+        } else {
+            self.lazy_tilesets.len()
+        }
+    }
+
+    /// Returns the tileset at the given index, if the map has one there and it has been loaded;
+    /// See [`Map::tileset`].
+    pub(crate) fn get(&self, index: usize) -> Option<&'map Arc<Tileset>> {
+        if self.lazy_tilesets.is_empty() {
+            self.tilesets.get(index)
+        } else {
+            self.lazy_tilesets.get(index)?.get()
+        }
+    }
+}
+
+impl From<Arc<Tileset>> for TilesetRef {
+    fn from(tileset: Arc<Tileset>) -> Self {
+        Self {
+            path: None,
+            tileset: OnceLock::from(tileset),
+        }
+    }
+}
+
+/// Builds a [`Map`] in code, without parsing any files; Useful for procedural generation or tests.
+///
+/// Layers are created with [`TileLayerBuilder`](crate::TileLayerBuilder) and
+/// [`ObjectLayerBuilder`](crate::ObjectLayerBuilder), or can be cloned from other maps.
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// use tiled::{LayerTileData, Loader, MapBuilder, Orientation, TileLayerBuilder};
+///
+/// # fn main() -> tiled::Result<()> {
+/// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+///
+/// let mut ground = TileLayerBuilder::new(8, 8).id(1).name("Ground");
+/// for x in 0..8 {
+///     ground = ground.set_tile(x, 7, Some(LayerTileData::new(0, 3)))?;
+/// }
+///
+/// let map = MapBuilder::new(Orientation::Orthogonal, 8, 8, 32, 32)
+///     .tileset(tileset.into())
+///     .layer(ground.build())
+///     .build()?;
+///
+/// let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+/// assert_eq!(layer.get_tile(4, 7).unwrap().id(), 3);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct MapBuilder {
+    map: Map,
+}
+
+impl MapBuilder {
+    /// The TMX format version given to built maps.
+    const VERSION: &'static str = "1.10";
+
+    /// Creates a builder for an empty finite map with the given orientation, size (in tiles) and
+    /// tile size (in pixels).
+    pub fn new(
+        orientation: Orientation,
+        width: u32,
+        height: u32,
+        tile_width: u32,
+        tile_height: u32,
+    ) -> Self {
+        Self {
+            map: Map {
+                version: Self::VERSION.to_owned(),
+                orientation,
+                width,
+                height,
+                tile_width,
+                tile_height,
+                stagger_axis: StaggerAxis::default(),
+                stagger_index: StaggerIndex::default(),
+                hex_side_length: None,
+                render_order: RenderOrder::default(),
+                compression_level: None,
+                tilesets: Vec::new(),
+                lazy_tilesets: Vec::new(),
+                tileset_first_gids: Vec::new(),
+                tileset_paths: Vec::new(),
+                layers: Vec::new(),
+                properties: HashMap::new(),
+                background_color: None,
+                parallax_origin_x: 0.0,
+                parallax_origin_y: 0.0,
+                infinite: false,
+                user_type: None,
+                extra: ExtraXml::default(),
+                next_layer_id: 1,
+                next_object_id: 1,
+                warnings: Vec::new(),
+                sources: MapSources::default(),
+                settings: MapSettings::default(),
+                object_index: ObjectIndex::default(),
+                parent_index: ParentIndex::default(),
+            },
+        }
+    }
+
+    /// Sets whether the map is infinite. Infinite maps can only contain infinite tile layers, and
+    /// finite maps only finite ones.
+    pub fn infinite(mut self, infinite: bool) -> Self {
+        self.map.infinite = infinite;
+        self
+    }
+
+    /// Sets the stagger axis, for staggered and hexagonal maps.
+    pub fn stagger_axis(mut self, stagger_axis: StaggerAxis) -> Self {
+        self.map.stagger_axis = stagger_axis;
+        self
+    }
+
+    /// Sets the stagger index, for staggered and hexagonal maps.
+    pub fn stagger_index(mut self, stagger_index: StaggerIndex) -> Self {
+        self.map.stagger_index = stagger_index;
+        self
+    }
+
+    /// Sets the length of the side of a hexagonal tile, in pixels, for hexagonal maps.
+    pub fn hex_side_length(mut self, hex_side_length: u32) -> Self {
+        self.map.hex_side_length = Some(hex_side_length);
+        self
+    }
+
+    /// Sets the order in which tiles on tile layers are rendered.
+    pub fn render_order(mut self, render_order: RenderOrder) -> Self {
+        self.map.render_order = render_order;
+        self
+    }
+
+    /// Sets the level used to compress the map's tile layer data.
+    pub fn compression_level(mut self, compression_level: i32) -> Self {
+        self.map.compression_level = Some(compression_level);
+        self
+    }
+
+    /// Sets the map's background color.
+    pub fn background_color(mut self, color: Color) -> Self {
+        self.map.background_color = Some(color);
+        self
+    }
+
+    /// Sets the position of the parallax origin in pixels; See [`Map::parallax_origin_x`].
+    pub fn parallax_origin(mut self, x: f32, y: f32) -> Self {
+        self.map.parallax_origin_x = x;
+        self.map.parallax_origin_y = y;
+        self
+    }
+
+    /// Sets the map's type, which is arbitrary and set by the user.
+    pub fn user_type(mut self, user_type: impl Into<String>) -> Self {
+        self.map.user_type = Some(user_type.into());
+        self
+    }
+
+    /// Sets a custom property of the map, replacing any previous value it had.
+    pub fn property(mut self, name: impl Into<String>, value: PropertyValue) -> Self {
+        self.map.properties.insert(name.into(), value);
+        self
+    }
+
+    /// Adds a tileset to the map. Tilesets are indexed in the order they are added, which is what
+    /// [`LayerTileData::new`](crate::LayerTileData::new) refers to.
+    pub fn tileset(mut self, tileset: Arc<Tileset>) -> Self {
+        self.map.append_tileset(tileset);
+        self
+    }
+
+    /// Adds a layer on top of the ones already added.
+    pub fn layer(mut self, layer: LayerData) -> Self {
+        self.map.layers.push(layer);
+        self
+    }
+
+    /// Builds the map.
+    ///
+    /// Returns [`Error::InvalidMapData`] if a tile layer doesn't match the map's infinite setting,
+    /// if a finite tile layer isn't the same size as the map, or if any tile references a tileset
+    /// index not added to the map.
+    pub fn build(self) -> Result<Map> {
+        let mut map = self.map;
+        for layer in &map.layers {
+            layer.check_for_map(&map)?;
+        }
+        map.next_layer_id = map.max_layer_id().saturating_add(1);
+        map.next_object_id = map.max_object_id().saturating_add(1);
+        Ok(map)
     }
 }
 
-// Specifies whether the odd or even rows/columns are shifted half a tile
-// right/down. Only applies to Staggered and Hexagonal map orientations.
+/// Specifies whether the odd or even rows/columns are shifted half a tile
+/// right/down. Only applies to Staggered and Hexagonal map orientations.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub enum StaggerIndex {
     Even,
@@ -334,9 +2559,11 @@ impl FromStr for StaggerIndex {
     }
 }
 
-// Specifies which axis is staggered. Only applies to Staggered and Hexagonal
-// map orientations.
+/// Specifies which axis is staggered. Only applies to Staggered and Hexagonal
+/// map orientations.
 #[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub enum StaggerAxis {
     X,
@@ -374,8 +2601,56 @@ impl FromStr for StaggerAxis {
     }
 }
 
+/// The order in which the tiles of a map's tile layers are rendered. Tiled itself only uses this
+/// for orthogonal maps.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+#[allow(missing_docs)]
+pub enum RenderOrder {
+    #[default]
+    RightDown,
+    RightUp,
+    LeftDown,
+    LeftUp,
+}
+
+#[derive(Debug)]
+/// An error arising from trying to parse a [`RenderOrder`] that is not valid.
+pub struct RenderOrderError {
+    /// The invalid string found.
+    pub str_found: String,
+}
+
+impl std::fmt::Display for RenderOrderError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_fmt(format_args!(
+            "failed to parse render order, valid options are `right-down`, `right-up`, \
+        `left-down` and `left-up` but got `{}` instead",
+            self.str_found
+        ))
+    }
+}
+
+impl FromStr for RenderOrder {
+    type Err = RenderOrderError;
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        match s {
+            "right-down" => Ok(RenderOrder::RightDown),
+            "right-up" => Ok(RenderOrder::RightUp),
+            "left-down" => Ok(RenderOrder::LeftDown),
+            "left-up" => Ok(RenderOrder::LeftUp),
+            _ => Err(RenderOrderError {
+                str_found: s.to_owned(),
+            }),
+        }
+    }
+}
+
 /// Represents the way tiles are laid out in a map.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub enum Orientation {
     Orthogonal,
@@ -427,6 +2702,233 @@ impl fmt::Display for Orientation {
     }
 }
 
+/// The point of a map that stays in place when it is resized with [`Map::resize`], as in Tiled's
+/// "Resize Map" dialog. Contents are added or removed on the opposite sides.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Default)]
+#[allow(missing_docs)]
+pub enum ResizeAnchor {
+    #[default]
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ResizeAnchor {
+    /// Returns how far along each axis of the map the anchor is, in halves.
+    fn halves(self) -> (i64, i64) {
+        match self {
+            ResizeAnchor::TopLeft => (0, 0),
+            ResizeAnchor::Top => (1, 0),
+            ResizeAnchor::TopRight => (2, 0),
+            ResizeAnchor::Left => (0, 1),
+            ResizeAnchor::Center => (1, 1),
+            ResizeAnchor::Right => (2, 1),
+            ResizeAnchor::BottomLeft => (0, 2),
+            ResizeAnchor::Bottom => (1, 2),
+            ResizeAnchor::BottomRight => (2, 2),
+        }
+    }
+}
+
+/// Which layers of a map [`Map::extract`] keeps.
+///
+/// Group layers that match are kept along with all of their nested layers, while other groups are
+/// only kept with the nested layers that match, if any.
+#[derive(Debug, PartialEq, Eq, Clone, Default)]
+pub enum LayerFilter {
+    /// All of the map's layers.
+    #[default]
+    All,
+    /// The layers with the given IDs.
+    Ids(Vec<LayerId>),
+    /// The layers with the given names.
+    Names(Vec<String>),
+}
+
+impl LayerFilter {
+    fn matches(&self, layer: &LayerData) -> bool {
+        match self {
+            LayerFilter::All => true,
+            LayerFilter::Ids(ids) => ids.contains(&layer.id()),
+            LayerFilter::Names(names) => names.contains(&layer.name),
+        }
+    }
+}
+
+/// Measurements used to lay out staggered and hexagonal maps, the same way as Tiled does.
+/// Staggered maps are handled as hexagonal maps with a side length of 0.
+pub(crate) struct StaggerParams {
+    hexagonal: bool,
+    stagger_x: bool,
+    stagger_even: bool,
+    tile_width: i64,
+    tile_height: i64,
+    side_length_x: i64,
+    side_length_y: i64,
+    column_width: i64,
+    row_height: i64,
+}
+
+impl StaggerParams {
+    pub(crate) fn new(map: &Map) -> Self {
+        let hexagonal = map.orientation == Orientation::Hexagonal;
+        let stagger_x = map.stagger_axis == StaggerAxis::X;
+        // Tiled only works with even tile sizes for these orientations. Measurements are kept
+        // in 64 bits so that no combination of sizes can overflow.
+        let tile_width = map.tile_width as i64 & !1;
+        let tile_height = map.tile_height as i64 & !1;
+        let side_length = if hexagonal {
+            map.hex_side_length.unwrap_or(0) as i64
+        } else {
+            0
+        };
+        let (side_length_x, side_length_y) = if stagger_x {
+            (side_length, 0)
+        } else {
+            (0, side_length)
+        };
+        let side_offset_x = (tile_width - side_length_x) / 2;
+        let side_offset_y = (tile_height - side_length_y) / 2;
+
+        Self {
+            hexagonal,
+            stagger_x,
+            stagger_even: map.stagger_index == StaggerIndex::Even,
+            tile_width,
+            tile_height,
+            side_length_x,
+            side_length_y,
+            column_width: side_offset_x + side_length_x,
+            row_height: side_offset_y + side_length_y,
+        }
+    }
+
+    /// The size of a map with the given amount of tiles, in pixels.
+    fn map_size(&self, width: u32, height: u32) -> (u32, u32) {
+        let (width, height) = (width as i128, height as i128);
+        let (column_width, row_height) = (self.column_width as i128, self.row_height as i128);
+        let (pixel_width, pixel_height) = if self.stagger_x {
+            let side_offset_x = column_width - self.side_length_x as i128;
+            let mut pixel_height = height * (self.tile_height + self.side_length_y) as i128;
+            if width > 1 {
+                pixel_height += row_height;
+            }
+            (width * column_width + side_offset_x, pixel_height)
+        } else {
+            let side_offset_y = row_height - self.side_length_y as i128;
+            let mut pixel_width = width * (self.tile_width + self.side_length_x) as i128;
+            if height > 1 {
+                pixel_width += column_width;
+            }
+            (pixel_width, height * row_height + side_offset_y)
+        };
+        (saturating_u32(pixel_width), saturating_u32(pixel_height))
+    }
+
+    /// Converts a tile position into axial coordinates, in which the neighbors of every tile of a
+    /// hexagonal map are at the same offsets.
+    fn tile_to_axial(&self, x: i32, y: i32) -> (i64, i64) {
+        let (x, y) = (x as i64, y as i64);
+        let even = self.stagger_even as i64;
+        if self.stagger_x {
+            (x, y - (x + even).div_euclid(2))
+        } else {
+            (x - (y + even).div_euclid(2), y)
+        }
+    }
+
+    /// Converts axial coordinates back into a tile position; The inverse of
+    /// [`Self::tile_to_axial`].
+    fn axial_to_tile(&self, q: i64, r: i64) -> (i64, i64) {
+        let even = self.stagger_even as i64;
+        if self.stagger_x {
+            (q, r + (q + even).div_euclid(2))
+        } else {
+            (q + (r + even).div_euclid(2), r)
+        }
+    }
+
+    /// Whether the given row or column along the stagger axis is shifted.
+    pub(crate) fn is_staggered(&self, index: i32) -> bool {
+        (index & 1 == 1) != self.stagger_even
+    }
+
+    fn tile_to_pixel(&self, x: i32, y: i32) -> (f32, f32) {
+        let (wide_x, wide_y) = (x as i128, y as i128);
+        let (pixel_x, pixel_y) = if self.stagger_x {
+            let shift = if self.is_staggered(x) {
+                self.row_height
+            } else {
+                0
+            };
+            (
+                wide_x * self.column_width as i128,
+                wide_y * (self.tile_height + self.side_length_y) as i128 + shift as i128,
+            )
+        } else {
+            let shift = if self.is_staggered(y) {
+                self.column_width
+            } else {
+                0
+            };
+            (
+                wide_x * (self.tile_width + self.side_length_x) as i128 + shift as i128,
+                wide_y * self.row_height as i128,
+            )
+        };
+        (pixel_x as f32, pixel_y as f32)
+    }
+
+    fn pixel_to_tile(&self, x: f32, y: f32) -> (i32, i32) {
+        // Find the tile roughly containing the position; The actual one is either that tile or
+        // one of its neighbours.
+        let (column, row) = if self.stagger_x {
+            (
+                (x / self.column_width as f32).floor() as i32,
+                (y / (self.tile_height + self.side_length_y) as f32).floor() as i32,
+            )
+        } else {
+            (
+                (x / (self.tile_width + self.side_length_x) as f32).floor() as i32,
+                (y / self.row_height as f32).floor() as i32,
+            )
+        };
+
+        let half_width = self.tile_width as f32 / 2.0;
+        let half_height = self.tile_height as f32 / 2.0;
+        let distance = |(tile_x, tile_y): (i32, i32)| {
+            let (left, top) = self.tile_to_pixel(tile_x, tile_y);
+            let dx = (x - left - half_width).abs();
+            let dy = (y - top - half_height).abs();
+            if self.hexagonal {
+                dx * dx + dy * dy
+            } else {
+                // Staggered tiles are diamonds, whose interior is the area within a (scaled)
+                // Manhattan distance of 1 from their center.
+                dx / half_width + dy / half_height
+            }
+        };
+
+        (column.saturating_sub(1)..=column.saturating_add(1))
+            .flat_map(|tile_x| {
+                (row.saturating_sub(1)..=row.saturating_add(1)).map(move |tile_y| (tile_x, tile_y))
+            })
+            .min_by(|a, b| distance(*a).total_cmp(&distance(*b)))
+            .unwrap_or((column, row))
+    }
+}
+
+/// Converts a pixel measurement to a `u32`, clamping it to the range of values representable.
+fn saturating_u32(value: i128) -> u32 {
+    value.clamp(0, u32::MAX as i128) as u32
+}
+
 /// A Tiled global tile ID.
 ///
 /// These are used to identify tiles in a map. Since the map may have more than one tileset, an
@@ -437,11 +2939,12 @@ impl fmt::Display for Orientation {
 /// a starting GID of 1.
 ///
 /// See also: <https://doc.mapeditor.org/en/latest/reference/global-tile-ids/>
-#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
-pub(crate) struct Gid(pub u32);
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct Gid(pub u32);
 
 impl Gid {
     /// The GID representing an empty tile in the map.
-    #[allow(dead_code)]
     pub const EMPTY: Gid = Gid(0);
 }
@@ -1,19 +1,19 @@
 use std::{collections::HashMap, path::Path, sync::Arc};
 
-use xml::attribute::OwnedAttribute;
-
 use crate::{
-    error::{Error, Result},
-    properties::{parse_properties, Properties},
+    error::{Error, Result, XmlPosition},
+    properties::{merge_properties, parse_properties, Properties},
     template::Template,
-    util::{get_attrs, map_wrapper, parse_tag, XmlEventResult},
-    Color, Gid, MapTilesetGid, ResourceCache, ResourceReader, Tile, TileId, Tileset,
+    util::{get_attrs, map_wrapper, parse_tag, LoadContext, OwnedAttribute, XmlEvent, XmlParser},
+    Color, ExtraXml, Gid, HasUserClass, Layer, MapTilesetGid, ObjectAlignment, Orientation,
+    Project, PropertyValue, ResourceCache, ResourceReader, Tile, TileFlip, TileId, Tileset,
 };
 
 /// The location of the tileset this tile is in
 ///
 /// Tilesets can be contained within either a map or a template.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum TilesetLocation {
     /// Index into the Map's tileset list, guaranteed to be a valid index of the map tileset container.
     Map(usize),
@@ -23,6 +23,7 @@ pub enum TilesetLocation {
 
 /// Stores the internal tile gid about a layer tile, along with how it is flipped.
 #[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ObjectTileData {
     /// A valid TilesetLocation that points to a tileset that **may or may not contain** this tile.
     tileset_location: TilesetLocation,
@@ -50,6 +51,12 @@ impl ObjectTileData {
         &self.tileset_location
     }
 
+    /// Returns how this tile is flipped, as flags that help with applying the flips correctly.
+    #[inline]
+    pub fn flip(&self) -> TileFlip {
+        TileFlip::new(self.flip_h, self.flip_v, self.flip_d)
+    }
+
     const FLIPPED_HORIZONTALLY_FLAG: u32 = 0x80000000;
     const FLIPPED_VERTICALLY_FLAG: u32 = 0x40000000;
     const FLIPPED_DIAGONALLY_FLAG: u32 = 0x20000000;
@@ -98,18 +105,33 @@ map_wrapper!(
 );
 
 impl<'map> ObjectTile<'map> {
-    /// Get a reference to the object tile's referenced tile, if it exists.
+    /// Get a reference to the object tile's referenced tile, if it exists and its tileset has
+    /// been loaded.
     #[inline]
     pub fn get_tile(&self) -> Option<Tile<'map>> {
-        self.get_tileset().get_tile(self.data.id)
+        self.try_get_tileset()?.get_tile(self.data.id)
     }
     /// Get a reference to the object tile's referenced tileset.
+    ///
+    /// ## Panics
+    /// Panics if it's one of the map's tilesets and the map was loaded with
+    /// [lazy tilesets](crate::Loader::set_lazy_tilesets) without it being loaded yet; Use
+    /// [`Self::try_get_tileset`] for such maps.
     #[inline]
     pub fn get_tileset(&self) -> &'map Tileset {
+        self.try_get_tileset()
+            .expect("the tile's tileset hasn't been loaded yet")
+    }
+
+    /// Get a reference to the object tile's referenced tileset, or [`None`] if it's one of the
+    /// map's tilesets and the map was loaded with
+    /// [lazy tilesets](crate::Loader::set_lazy_tilesets) without it being loaded yet; See
+    /// [`Map::tileset`](crate::Map::tileset).
+    #[inline]
+    pub fn try_get_tileset(&self) -> Option<&'map Tileset> {
         match &self.data.tileset_location {
-            // SAFETY: `tileset_index` is guaranteed to be valid
-            TilesetLocation::Map(n) => &self.map.tilesets()[*n],
-            TilesetLocation::Template(t) => t,
+            TilesetLocation::Map(n) => self.map.tileset(*n).map(|tileset| &**tileset),
+            TilesetLocation::Template(t) => Some(t),
         }
     }
 }
@@ -118,44 +140,130 @@ impl<'map> ObjectTile<'map> {
 ///
 /// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-object).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub enum ObjectShape {
-    Rect {
-        width: f32,
-        height: f32,
-    },
-    Ellipse {
-        width: f32,
-        height: f32,
-    },
-    Polyline {
-        points: Vec<(f32, f32)>,
-    },
-    Polygon {
-        points: Vec<(f32, f32)>,
-    },
+    Rect { width: f32, height: f32 },
+    Ellipse { width: f32, height: f32 },
+    Polyline { points: Vec<Point> },
+    Polygon { points: Vec<Point> },
     Point(f32, f32),
-    Text {
-        font_family: String,
-        pixel_size: usize,
-        wrap: bool,
-        color: Color,
-        bold: bool,
-        italic: bool,
-        underline: bool,
-        strikeout: bool,
-        kerning: bool,
-        halign: HorizontalAlignment,
-        valign: VerticalAlignment,
-        /// The actual text content of this object.
-        text: String,
-        width: f32,
-        height: f32,
-    },
+    Text(TextData),
+}
+
+/// A point of an [`ObjectShape::Polyline`] or [`ObjectShape::Polygon`], in pixels and relative to
+/// the position of its object.
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct Point {
+    /// The X coordinate of the point.
+    pub x: f32,
+    /// The Y coordinate of the point.
+    pub y: f32,
+}
+
+impl Point {
+    /// Creates a point with the given coordinates.
+    #[inline]
+    pub const fn new(x: f32, y: f32) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<(f32, f32)> for Point {
+    #[inline]
+    fn from((x, y): (f32, f32)) -> Self {
+        Self { x, y }
+    }
+}
+
+impl From<Point> for (f32, f32) {
+    #[inline]
+    fn from(point: Point) -> Self {
+        (point.x, point.y)
+    }
+}
+
+/// The direction in which the points of a closed shape go around it; See [`ObjectShape::winding`].
+///
+/// Directions are as seen in Tiled, where the Y axis points down. Physics engines where it points
+/// up see the opposite direction.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub enum Winding {
+    /// The points go around the shape clockwise.
+    Clockwise,
+    /// The points go around the shape counterclockwise.
+    CounterClockwise,
+}
+
+/// The contents and formatting of a text object; Also see [`ObjectShape::Text`].
+///
+/// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#text).
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct TextData {
+    /// The font family used, `sans-serif` by default.
+    pub font_family: String,
+    /// The size of the font in pixels, 16 by default.
+    pub pixel_size: usize,
+    /// Whether word wrapping is enabled.
+    pub wrap: bool,
+    /// The color of the text, opaque black by default.
+    pub color: Color,
+    /// Whether the font is bold.
+    pub bold: bool,
+    /// Whether the font is italic.
+    pub italic: bool,
+    /// Whether a line should be drawn below the text.
+    pub underline: bool,
+    /// Whether a line should be drawn through the text.
+    pub strikeout: bool,
+    /// Whether kerning should be used while rendering the text.
+    pub kerning: bool,
+    /// How the text is aligned horizontally within the object's bounds.
+    pub halign: HorizontalAlignment,
+    /// How the text is aligned vertically within the object's bounds.
+    pub valign: VerticalAlignment,
+    /// The actual text content of this object.
+    ///
+    /// Like in Tiled, the contents are kept as written: Leading and trailing whitespace is
+    /// preserved, and an empty `text` element results in an empty string.
+    pub text: String,
+    /// The width of the object's bounds in pixels.
+    pub width: f32,
+    /// The height of the object's bounds in pixels.
+    pub height: f32,
+}
+
+impl TextData {
+    /// Returns the width at which lines should be wrapped, which is the width of the object if
+    /// [`TextData::wrap`] is enabled, or [`None`] if lines should never be wrapped.
+    pub fn wrap_width(&self) -> Option<f32> {
+        if self.wrap {
+            Some(self.width)
+        } else {
+            None
+        }
+    }
+
+    /// Returns an iterator over the explicit lines of the text, without any line terminators.
+    /// Both `\n` and `\r\n` line endings are recognized.
+    ///
+    /// Lines aren't wrapped, since that depends on the font; Also see [`TextData::wrap_width`].
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.text.lines()
+    }
 }
 
 /// The horizontal alignment of an [`ObjectShape::Text`].
 #[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub enum HorizontalAlignment {
     #[default]
@@ -167,6 +275,8 @@ pub enum HorizontalAlignment {
 
 /// The vertical alignment of an [`ObjectShape::Text`].
 #[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub enum VerticalAlignment {
     #[default]
@@ -175,13 +285,266 @@ pub enum VerticalAlignment {
     Bottom,
 }
 
+/// An axis-aligned rectangle, such as the bounds of an object; Also see
+/// [`ObjectShape::bounding_box`].
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct Rect {
+    /// The X coordinate of the left side of the rectangle, in pixels.
+    pub x: f32,
+    /// The Y coordinate of the top side of the rectangle, in pixels.
+    pub y: f32,
+    /// The width of the rectangle, in pixels.
+    pub width: f32,
+    /// The height of the rectangle, in pixels.
+    pub height: f32,
+}
+
+impl Rect {
+    /// Returns the smallest rectangle containing all the given points, or a rectangle with no size
+    /// at the origin if there are none.
+    pub(crate) fn enclosing(points: impl IntoIterator<Item = (f32, f32)>) -> Self {
+        let mut points = points.into_iter();
+        let (x, y) = match points.next() {
+            Some(first) => first,
+            None => return Self::default(),
+        };
+        let (min_x, min_y, max_x, max_y) = points.fold((x, y, x, y), |bounds, (x, y)| {
+            (
+                bounds.0.min(x),
+                bounds.1.min(y),
+                bounds.2.max(x),
+                bounds.3.max(y),
+            )
+        });
+        Self {
+            x: min_x,
+            y: min_y,
+            width: max_x - min_x,
+            height: max_y - min_y,
+        }
+    }
+
+    /// Returns the smallest rectangle containing all the given rectangles, or [`None`] if there
+    /// are none.
+    pub(crate) fn enclosing_rects(rects: impl IntoIterator<Item = Rect>) -> Option<Self> {
+        let mut rects = rects.into_iter().peekable();
+        rects.peek()?;
+        Some(Self::enclosing(rects.flat_map(|rect| {
+            [
+                (rect.x, rect.y),
+                (rect.x + rect.width, rect.y + rect.height),
+            ]
+        })))
+    }
+
+    /// Returns whether the given point is inside this rectangle or on its edges.
+    pub fn contains_point(&self, x: f32, y: f32) -> bool {
+        x >= self.x && x <= self.x + self.width && y >= self.y && y <= self.y + self.height
+    }
+}
+
+impl ObjectShape {
+    /// Returns the axis-aligned bounds of this shape in pixels, for an object positioned at `x`
+    /// and `y` and rotated clockwise by `rotation` degrees around that position, which is how
+    /// [`ObjectData`] places its shape.
+    ///
+    /// [`ObjectShape::Point`]s already hold their position and have no size, so their bounds are
+    /// an empty rectangle at the point.
+    ///
+    /// ## Note
+    /// Tile objects are drawn above and to the right of their position in orthogonal maps, while
+    /// their shape is a [`ObjectShape::Rect`] which extends below it; Their bounds need to be
+    /// moved up by their height.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{ObjectShape, Rect};
+    ///
+    /// let shape = ObjectShape::Rect {
+    ///     width: 20.0,
+    ///     height: 10.0,
+    /// };
+    /// let bounds = shape.bounding_box(100.0, 50.0, 90.0);
+    /// assert_eq!(bounds.x.round(), 90.0);
+    /// assert_eq!(bounds.y.round(), 50.0);
+    /// assert_eq!(bounds.width.round(), 10.0);
+    /// assert_eq!(bounds.height.round(), 20.0);
+    /// ```
+    pub fn bounding_box(&self, x: f32, y: f32, rotation: f32) -> Rect {
+        let (sin, cos) = rotation.to_radians().sin_cos();
+        let transform = |(px, py): (f32, f32)| (x + px * cos - py * sin, y + px * sin + py * cos);
+        match self {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Text(TextData { width, height, .. }) => Rect::enclosing(
+                [(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)].map(transform),
+            ),
+            ObjectShape::Ellipse { width, height } => {
+                // The bounds of a rotated ellipse are given by the extents of its rotated axes.
+                let (radius_x, radius_y) = (width / 2.0, height / 2.0);
+                let extent_x = (radius_x * cos).hypot(radius_y * sin);
+                let extent_y = (radius_x * sin).hypot(radius_y * cos);
+                let (center_x, center_y) = transform((radius_x, radius_y));
+                Rect {
+                    x: center_x - extent_x,
+                    y: center_y - extent_y,
+                    width: extent_x * 2.0,
+                    height: extent_y * 2.0,
+                }
+            }
+            ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+                Rect::enclosing(points.iter().map(|point| transform((point.x, point.y))))
+            }
+            ObjectShape::Point(x, y) => Rect {
+                x: *x,
+                y: *y,
+                width: 0.0,
+                height: 0.0,
+            },
+        }
+    }
+
+    /// Returns whether the given point, relative to the position of the object and not rotated
+    /// with it, is inside this shape. Points on the edges of rectangles and ellipses count as
+    /// inside them.
+    ///
+    /// Polylines and points have no area, so they never contain any point. To check against
+    /// coordinates in the map instead, use [`ObjectData::contains_point`].
+    pub fn contains_point(&self, px: f32, py: f32) -> bool {
+        match self {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Text(TextData { width, height, .. }) => {
+                px >= 0.0 && px <= *width && py >= 0.0 && py <= *height
+            }
+            ObjectShape::Ellipse { width, height } => {
+                let (radius_x, radius_y) = (width / 2.0, height / 2.0);
+                if radius_x <= 0.0 || radius_y <= 0.0 {
+                    return false;
+                }
+                let dx = (px - radius_x) / radius_x;
+                let dy = (py - radius_y) / radius_y;
+                dx * dx + dy * dy <= 1.0
+            }
+            ObjectShape::Polygon { points } => {
+                // Even-odd rule: Count the edges crossed by a ray cast from the point to the right.
+                let mut inside = false;
+                let mut previous = match points.last() {
+                    Some(last) => *last,
+                    None => return false,
+                };
+                for &point in points {
+                    let Point { x, y } = point;
+                    if (y > py) != (previous.y > py)
+                        && px < (previous.x - x) * (py - y) / (previous.y - y) + x
+                    {
+                        inside = !inside;
+                    }
+                    previous = point;
+                }
+                inside
+            }
+            ObjectShape::Polyline { .. } | ObjectShape::Point(..) => false,
+        }
+    }
+
+    /// Returns the axis-aligned bounds of this shape in pixels, relative to the position of its
+    /// object and without rotating it. Also see [`ObjectShape::bounding_box`].
+    #[inline]
+    pub fn aabb(&self) -> Rect {
+        self.bounding_box(0.0, 0.0, 0.0)
+    }
+
+    /// Returns whether this shape encloses an area: Rectangles, ellipses, texts and polygons
+    /// always do, and polylines do when they have more than two points and end where they start.
+    pub fn is_closed(&self) -> bool {
+        match self {
+            ObjectShape::Rect { .. }
+            | ObjectShape::Ellipse { .. }
+            | ObjectShape::Text(_)
+            | ObjectShape::Polygon { .. } => true,
+            ObjectShape::Polyline { points } => points.len() > 2 && points.first() == points.last(),
+            ObjectShape::Point(..) => false,
+        }
+    }
+
+    /// Returns an iterator over the line segments between the points of a polyline or polygon, as
+    /// pairs of start and end points. Polygons with more than two points also have a segment from
+    /// their last point back to the first one. Other shapes have no segments.
+    ///
+    /// ## Example
+    /// ```
+    /// use tiled::{ObjectShape, Point};
+    ///
+    /// let triangle = ObjectShape::Polygon {
+    ///     points: vec![(0.0, 0.0).into(), (10.0, 0.0).into(), (0.0, 10.0).into()],
+    /// };
+    /// let segments: Vec<(Point, Point)> = triangle.iter_segments().collect();
+    /// assert_eq!(segments.len(), 3);
+    /// assert_eq!(segments[2], (Point::new(0.0, 10.0), Point::new(0.0, 0.0)));
+    /// ```
+    pub fn iter_segments(&self) -> impl Iterator<Item = (Point, Point)> + '_ {
+        let (points, closing): (&[Point], _) = match self {
+            ObjectShape::Polyline { points } => (points, None),
+            ObjectShape::Polygon { points } if points.len() > 2 => {
+                (points, Some((points[points.len() - 1], points[0])))
+            }
+            ObjectShape::Polygon { points } => (points, None),
+            _ => (&[], None),
+        };
+        points
+            .windows(2)
+            .map(|pair| (pair[0], pair[1]))
+            .chain(closing)
+    }
+
+    /// Returns the direction in which the points of a polygon, or of a closed polyline, go around
+    /// it; See [`ObjectShape::is_closed`]. Returns [`None`] for other shapes, and for shapes with
+    /// no area, such as polygons whose points are all on the same line.
+    ///
+    /// Polygons whose edges cross each other take the direction which encloses most of the area.
+    pub fn winding(&self) -> Option<Winding> {
+        let points = match self {
+            ObjectShape::Polygon { points } => points,
+            ObjectShape::Polyline { points } if self.is_closed() => points,
+            _ => return None,
+        };
+        // Twice the signed area of the polygon, which is positive when the points go clockwise
+        // since the Y axis points down.
+        let area: f32 = points
+            .iter()
+            .zip(points.iter().cycle().skip(1))
+            .map(|(a, b)| a.x * b.y - b.x * a.y)
+            .sum();
+        if area > 0.0 {
+            Some(Winding::Clockwise)
+        } else if area < 0.0 {
+            Some(Winding::CounterClockwise)
+        } else {
+            None
+        }
+    }
+}
+
+/// The ID of an object, which is unique within its map and stays the same as the map is changed;
+/// See [`Map::object_by_id`](crate::Map::object_by_id).
+///
+/// Object properties referring to no object hold an ID of 0.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectId(pub u32);
+
 /// Raw data belonging to an object. Used internally and for tile collisions.
 ///
 /// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-object).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct ObjectData {
     id: u32,
     tile: Option<ObjectTileData>,
+    /// The GID of the tile shown by a tile object in a tile's collision group, kept as it was in
+    /// the file since there are no map tilesets to resolve it against.
+    collision_gid: Option<u32>,
     /// The name of the object, which is arbitrary and set by the user.
     pub name: String,
     /// The type of the object, which is arbitrary and set by the user.
@@ -198,6 +561,15 @@ pub struct ObjectData {
     pub shape: ObjectShape,
     /// The object's custom properties as set by the user.
     pub properties: Properties,
+    /// The attributes and child elements of the object element that aren't read into any other
+    /// field, if the object was loaded with
+    /// [`LoaderOptions::preserve_unknown_xml`](crate::LoaderOptions::preserve_unknown_xml).
+    pub extra: ExtraXml,
+    /// The template this object is an instance of, if any.
+    template: Option<Arc<Template>>,
+    /// The names of the object properties inherited from this object's template, whose IDs refer
+    /// to objects of the template rather than of the map.
+    template_object_properties: Vec<String>,
 }
 
 impl ObjectData {
@@ -205,8 +577,8 @@ impl ObjectData {
     ///
     /// On older versions this value is defaulted to 0.
     #[inline]
-    pub fn id(&self) -> u32 {
-        self.id
+    pub fn id(&self) -> ObjectId {
+        ObjectId(self.id)
     }
 
     /// Returns the data of the tile that this object is referencing, if it exists.
@@ -214,23 +586,407 @@ impl ObjectData {
     pub fn tile_data(&self) -> Option<ObjectTileData> {
         self.tile.clone()
     }
+
+    /// Returns the GID of the tile shown by this object, including its flipping bits, if it is a
+    /// tile object in a tile's collision group.
+    #[inline]
+    pub(crate) fn collision_gid(&self) -> Option<u32> {
+        self.collision_gid
+    }
+
+    /// Returns the names of the [`ObjectValue`](crate::PropertyValue::ObjectValue) properties this
+    /// object inherited from its template, in alphabetical order. Unset references (with an ID of
+    /// 0) and properties overridden by the object itself are not included.
+    ///
+    /// The IDs these hold were assigned in the template file and don't refer to objects of the
+    /// map; See [`Map::resolve_template_object_references`](crate::Map::resolve_template_object_references).
+    #[inline]
+    pub fn template_object_properties(&self) -> &[String] {
+        &self.template_object_properties
+    }
+
+    /// Makes the inherited object property with the given name refer to the map object with the
+    /// given ID, after which it is no longer listed as coming from the template.
+    pub(crate) fn resolve_template_object_property(&mut self, property: &str, target: ObjectId) {
+        self.properties
+            .insert(property.to_owned(), PropertyValue::ObjectValue(target.0));
+        self.template_object_properties
+            .retain(|name| name != property);
+    }
+
+    /// Returns the template this object is an instance of, if it was loaded with a `template`
+    /// attribute. Its [`Template::source`] is the path of the template file, and its object holds
+    /// the values this object inherited unless it overrides them.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use std::path::Path;
+    ///
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_template.tmx")?;
+    /// let object = map.object_by_id(tiled::ObjectId(3)).unwrap();
+    /// let template = object.template().unwrap();
+    /// assert_eq!(template.source, Path::new("assets/tiled_object_template.tx"));
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[inline]
+    pub fn template(&self) -> Option<&Arc<Template>> {
+        self.template.as_ref()
+    }
+
+    /// Returns the final properties of this object, which are the ones it sets itself layered over
+    /// the ones of its [template](Self::template), layered over the defaults of its class in the
+    /// given project; See [`merge_properties`]. Class values also get their unset members filled
+    /// in with the defaults of their class.
+    ///
+    /// The [`properties`](Self::properties) of objects already hold the properties they inherit
+    /// from their template and, when loaded with a project, class defaults. Unlike those, the
+    /// members of class values that are only partially overridden are kept here.
+    pub fn resolved_properties(&self, project: &Project) -> Properties {
+        let properties = match &self.template {
+            Some(template) => merge_properties(&template.object.properties, &self.properties),
+            None => self.properties.clone(),
+        };
+        let mut properties = match self.user_class() {
+            Some(class) => project.fill_class_defaults(class, properties),
+            None => properties,
+        };
+        for value in properties.values_mut() {
+            if let PropertyValue::ClassValue {
+                property_type,
+                properties,
+            } = value
+            {
+                *properties =
+                    project.fill_class_defaults(property_type, std::mem::take(properties));
+            }
+        }
+        properties
+    }
+
+    /// Returns the axis-aligned bounds of this object's shape in pixels, taking its position and
+    /// rotation into account. See [`ObjectShape::bounding_box`].
+    pub fn bounding_box(&self) -> Rect {
+        self.shape.bounding_box(self.x, self.y, self.rotation)
+    }
+
+    /// Returns whether the given point, in the same coordinates as the object's position, is
+    /// inside this object's shape, taking its rotation into account. See
+    /// [`ObjectShape::contains_point`].
+    pub fn contains_point(&self, px: f32, py: f32) -> bool {
+        // Undo the object's translation and rotation to get the point in the shape's coordinates.
+        let (sin, cos) = (-self.rotation).to_radians().sin_cos();
+        let (dx, dy) = (px - self.x, py - self.y);
+        self.shape
+            .contains_point(dx * cos - dy * sin, dx * sin + dy * cos)
+    }
+
+    /// Applies the flips of a tile to this object, one of the tile's collision shapes, given the
+    /// size of the tile's image. As in Tiled, the diagonal flip is done first.
+    pub(crate) fn flip(&mut self, flip_h: bool, flip_v: bool, flip_d: bool, size: (f32, f32)) {
+        let (mut width, mut height) = size;
+        if flip_d {
+            self.transpose();
+            std::mem::swap(&mut width, &mut height);
+        }
+        if flip_h {
+            self.mirror(true, width);
+        }
+        if flip_v {
+            self.mirror(false, height);
+        }
+    }
+
+    /// Makes the tile of this object refer to the map tilesets with the given first GIDs, given
+    /// the first GIDs of the ones it was parsed with. The tile is removed if no tileset has its
+    /// GID.
+    pub(crate) fn resolve_tilesets(&mut self, parsed_with: &[Gid], first_gids: &[Gid]) {
+        let tile = match &mut self.tile {
+            Some(tile) => tile,
+            None => return,
+        };
+        if let TilesetLocation::Map(index) = tile.tileset_location {
+            let gid = parsed_with[index].0 + tile.id;
+            match first_gids.iter().rposition(|first_gid| first_gid.0 <= gid) {
+                Some(index) => {
+                    tile.tileset_location = TilesetLocation::Map(index);
+                    tile.id = gid - first_gids[index].0;
+                }
+                None => self.tile = None,
+            }
+        }
+    }
+
+    /// Makes the tile of this object refer to the new index of its map tileset in `new_indices`,
+    /// by old index. The tile is removed if its tileset has no new index.
+    pub(crate) fn remap_tilesets(&mut self, new_indices: &[Option<usize>]) {
+        let tile = match &mut self.tile {
+            Some(tile) => tile,
+            None => return,
+        };
+        if let TilesetLocation::Map(index) = tile.tileset_location {
+            match new_indices.get(index).copied().flatten() {
+                Some(index) => tile.tileset_location = TilesetLocation::Map(index),
+                None => self.tile = None,
+            }
+        }
+    }
+
+    /// Moves this object by the given amount of pixels.
+    pub(crate) fn translate(&mut self, dx: f32, dy: f32) {
+        self.x += dx;
+        self.y += dy;
+        if let ObjectShape::Point(x, y) = &mut self.shape {
+            *x += dx;
+            *y += dy;
+        }
+    }
+
+    /// Swaps the X and Y axes, flipping the object over the line going down-right from the origin.
+    fn transpose(&mut self) {
+        std::mem::swap(&mut self.x, &mut self.y);
+        self.rotation = -self.rotation;
+        match &mut self.shape {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Ellipse { width, height }
+            | ObjectShape::Text(TextData { width, height, .. }) => std::mem::swap(width, height),
+            ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+                for Point { x, y } in points {
+                    std::mem::swap(x, y);
+                }
+            }
+            ObjectShape::Point(x, y) => std::mem::swap(x, y),
+        }
+    }
+
+    /// Mirrors the object horizontally or vertically within an area of the given width or height.
+    fn mirror(&mut self, horizontally: bool, size: f32) {
+        let mirror = |(x, y): (f32, f32)| if horizontally { (-x, y) } else { (x, -y) };
+        let (x, y) = mirror((self.x, self.y));
+        (self.x, self.y) = if horizontally {
+            (x + size, y)
+        } else {
+            (x, y + size)
+        };
+        // Mirroring reverses the direction of the rotation.
+        self.rotation = -self.rotation;
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        match &mut self.shape {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Ellipse { width, height }
+            | ObjectShape::Text(TextData { width, height, .. }) => {
+                // The mirrored shape extends from the origin in the opposite direction, so its
+                // origin moves to the opposite side to keep its size positive.
+                let (dx, dy) = mirror((*width, *height));
+                let (dx, dy) = if horizontally { (dx, 0.0) } else { (0.0, dy) };
+                self.x += dx * cos - dy * sin;
+                self.y += dx * sin + dy * cos;
+            }
+            ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+                for point in points {
+                    *point = mirror((*point).into()).into();
+                }
+            }
+            ObjectShape::Point(x, y) => (*x, *y) = (self.x, self.y),
+        }
+    }
+
+    /// Creates a new visible object with the given ID and shape, positioned at the origin and with
+    /// no name, type or properties.
+    ///
+    /// Used for building object layers in code; See
+    /// [`ObjectLayerBuilder`](crate::ObjectLayerBuilder).
+    pub fn from_shape(id: u32, shape: ObjectShape) -> Self {
+        Self {
+            id,
+            tile: None,
+            collision_gid: None,
+            name: String::new(),
+            user_type: String::new(),
+            x: 0.0,
+            y: 0.0,
+            rotation: 0.0,
+            visible: true,
+            shape,
+            properties: HashMap::new(),
+            extra: ExtraXml::default(),
+            template: None,
+            template_object_properties: Vec::new(),
+        }
+    }
+}
+
+impl Default for ObjectData {
+    /// Returns a visible, empty rectangle object at the origin with an ID of 0.
+    fn default() -> Self {
+        Self::from_shape(
+            0,
+            ObjectShape::Rect {
+                width: 0.0,
+                height: 0.0,
+            },
+        )
+    }
+}
+
+/// Builds an object, either to be added to a loaded map through
+/// [`ObjectLayerDataMut::push_object`](crate::ObjectLayerDataMut::push_object), which gives it
+/// its ID, or on its own through [`ObjectDataBuilder::build`].
+///
+/// Objects start out visible and positioned at the origin, with no name, type or properties.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{ObjectDataBuilder, ObjectId, ObjectShape, Point};
+///
+/// let object = ObjectDataBuilder::new(ObjectShape::Point(0.0, 0.0))
+///     .name("spawn")
+///     .position(32.0, 64.0)
+///     .build(1)?;
+/// assert_eq!((object.id(), object.x, object.y), (ObjectId(1), 32.0, 64.0));
+///
+/// let polygon = ObjectDataBuilder::new(ObjectShape::Polygon {
+///     points: vec![Point::new(0.0, 0.0), Point::new(1.0, 1.0)],
+/// });
+/// assert!(polygon.build(2).is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct ObjectDataBuilder {
+    object: ObjectData,
+}
+
+impl ObjectDataBuilder {
+    /// Creates a builder for an object with the given shape.
+    pub fn new(shape: ObjectShape) -> Self {
+        Self {
+            object: ObjectData::from_shape(0, shape),
+        }
+    }
+
+    /// Sets the name of the object.
+    pub fn name(mut self, name: impl Into<String>) -> Self {
+        self.object.name = name.into();
+        self
+    }
+
+    /// Sets the type of the object, which is arbitrary and set by the user.
+    pub fn user_type(mut self, user_type: impl Into<String>) -> Self {
+        self.object.user_type = user_type.into();
+        self
+    }
+
+    /// Sets the position of the object, in pixels. Also moves [`ObjectShape::Point`]s, which
+    /// hold their position.
+    pub fn position(mut self, x: f32, y: f32) -> Self {
+        self.object.x = x;
+        self.object.y = y;
+        if let ObjectShape::Point(point_x, point_y) = &mut self.object.shape {
+            (*point_x, *point_y) = (x, y);
+        }
+        self
+    }
+
+    /// Sets the clockwise rotation of the object around its position, in degrees.
+    pub fn rotation(mut self, rotation: f32) -> Self {
+        self.object.rotation = rotation;
+        self
+    }
+
+    /// Sets whether the object is shown or hidden.
+    pub fn visible(mut self, visible: bool) -> Self {
+        self.object.visible = visible;
+        self
+    }
+
+    /// Sets a custom property of the object, replacing any previous value it had.
+    pub fn property(mut self, name: impl Into<String>, value: crate::PropertyValue) -> Self {
+        self.object.properties.insert(name.into(), value);
+        self
+    }
+
+    /// Makes this a tile object showing the tile with the given ID from the map's tileset at the
+    /// given index.
+    pub fn tile(mut self, tileset_index: usize, id: TileId) -> Self {
+        self.object.tile = Some(ObjectTileData {
+            tileset_location: TilesetLocation::Map(tileset_index),
+            id,
+            flip_h: false,
+            flip_v: false,
+            flip_d: false,
+        });
+        self
+    }
+
+    /// Returns the data of the tile this object will show, if it is a tile object.
+    pub(crate) fn tile_data(&self) -> Option<&ObjectTileData> {
+        self.object.tile.as_ref()
+    }
+
+    /// Builds the object, giving it the ID given.
+    ///
+    /// Fails if the position, rotation or shape of the object are not finite, if its shape has a
+    /// negative size, or if it is a polyline with less than 2 points or a polygon with less than
+    /// 3.
+    pub fn build(mut self, id: u32) -> Result<ObjectData> {
+        let object = &self.object;
+        let invalid = |description: &str| {
+            Err(Error::InvalidObjectData {
+                description: description.to_owned(),
+            })
+        };
+        if !(object.x.is_finite() && object.y.is_finite() && object.rotation.is_finite()) {
+            return invalid("position and rotation must be finite");
+        }
+        match &object.shape {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Ellipse { width, height }
+            | ObjectShape::Text(TextData { width, height, .. }) => {
+                if !(width.is_finite() && height.is_finite() && *width >= 0.0 && *height >= 0.0) {
+                    return invalid("size must be finite and not negative");
+                }
+            }
+            ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+                let min_points = match object.shape {
+                    ObjectShape::Polygon { .. } => 3,
+                    _ => 2,
+                };
+                if points.len() < min_points {
+                    return invalid("polylines need 2 points and polygons need 3");
+                }
+                if !points.iter().all(|p| p.x.is_finite() && p.y.is_finite()) {
+                    return invalid("points must be finite");
+                }
+            }
+            ObjectShape::Point(x, y) => {
+                if !(x.is_finite() && y.is_finite()) {
+                    return invalid("points must be finite");
+                }
+            }
+        }
+        self.object.id = id;
+        Ok(self.object)
+    }
 }
 
 impl ObjectData {
     /// If it is known that the object has no tile images in it (i.e. collision data)
     /// then we can pass in [`None`] as the tilesets
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         tilesets: Option<&[MapTilesetGid]>,
         for_tileset: Option<Arc<Tileset>>,
         // Base path is a directory to which all other files are relative to
         base_path: &Path,
-        reader: &mut impl ResourceReader,
-        cache: &mut impl ResourceCache,
+        context: &mut LoadContext<'_, impl ResourceReader, impl ResourceCache>,
     ) -> Result<ObjectData> {
         let (id, tile, mut n, mut t, c, mut w, mut h, mut v, mut r, template, x, y) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("object", parser.event_position()) {
                 Some("id") => id ?= v.parse(),
                 Some("gid") => tile ?= v.parse::<u32>(),
                 Some("name") => name ?= v.parse(),
@@ -246,8 +1002,10 @@ impl ObjectData {
             }
             (id, tile, name, user_type, user_class, width, height, visible, rotation, template, x, y)
         );
+        let mut extra = ExtraXml::from_attributes(parser, &attrs, OBJECT_ATTRIBUTES);
         let x = x.unwrap_or(0.);
         let y = y.unwrap_or(0.);
+        let collision_gid = tile.filter(|_| tilesets.is_none() && for_tileset.is_none());
         let mut tile = tile.and_then(|bits| {
             ObjectTileData::from_bits(bits, tilesets?, for_tileset.as_ref().cloned())
         });
@@ -257,12 +1015,20 @@ impl ObjectData {
                 let template_path = base_path.join(Path::new(&template_path));
 
                 // Check the cache to see if this template exists
-                let template = if let Some(templ) = cache.get_template(&template_path) {
+                let template = if let Some(templ) = context.cache.get_template(&template_path) {
                     templ
                 } else {
-                    let template = Template::parse_template(&template_path, reader, cache)?;
+                    let template = Template::parse_template(
+                        &template_path,
+                        context.reader,
+                        context.cache,
+                        context.project,
+                        parser.options(),
+                    )?;
                     // Insert it into the cache
-                    cache.insert_template(&template_path, template.clone());
+                    context
+                        .cache
+                        .insert_template(&template_path, template.clone());
                     template
                 };
 
@@ -278,7 +1044,7 @@ impl ObjectData {
                 match &obj.shape {
                     ObjectShape::Rect { width, height }
                     | ObjectShape::Ellipse { width, height }
-                    | ObjectShape::Text { width, height, .. } => {
+                    | ObjectShape::Text(TextData { width, height, .. }) => {
                         w.get_or_insert(*width);
                         h.get_or_insert(*height);
                     }
@@ -297,6 +1063,7 @@ impl ObjectData {
         let user_type: String = t.or(c).unwrap_or_default();
         let mut shape = None;
         let mut properties = HashMap::new();
+        let mut template_object_properties = Vec::new();
 
         parse_tag!(parser, "object", {
             "ellipse" => |_| {
@@ -307,11 +1074,17 @@ impl ObjectData {
                 Ok(())
             },
             "polyline" => |attrs| {
-                shape = Some(ObjectData::new_polyline(attrs)?);
+                match ObjectData::new_polyline(attrs, parser.event_position()) {
+                    Ok(polyline) => shape = Some(polyline),
+                    Err(err) => parser.recover(err)?,
+                }
                 Ok(())
             },
             "polygon" => |attrs| {
-                shape = Some(ObjectData::new_polygon(attrs)?);
+                match ObjectData::new_polygon(attrs, parser.event_position()) {
+                    Ok(polygon) => shape = Some(polygon),
+                    Err(err) => parser.recover(err)?,
+                }
                 Ok(())
             },
             "point" => |_| {
@@ -319,16 +1092,19 @@ impl ObjectData {
                 Ok(())
             },
             "text" => |attrs| {
-                shape = Some(ObjectData::new_text(attrs, parser, width, height)?);
+                match ObjectData::new_text(attrs, parser, width, height) {
+                    Ok(text) => shape = Some(text),
+                    Err(err) => parser.recover(err)?,
+                }
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, context.project)?;
                 Ok(())
             },
-        });
+        } else |name, attrs| extra.read_element(parser, name, attrs));
 
-        if let Some(templ) = template {
+        if let Some(templ) = &template {
             shape.get_or_insert_with(|| {
                 // Inherit the shape from the template but use the size and
                 // position from the object where relevant
@@ -336,37 +1112,11 @@ impl ObjectData {
                     ObjectShape::Rect { .. } => ObjectShape::Rect { width, height },
                     ObjectShape::Ellipse { .. } => ObjectShape::Ellipse { width, height },
                     ObjectShape::Point(_, _) => ObjectShape::Point(x, y),
-                    ObjectShape::Text {
-                        font_family,
-                        pixel_size,
-                        wrap,
-                        color,
-                        bold,
-                        italic,
-                        underline,
-                        strikeout,
-                        kerning,
-                        halign,
-                        valign,
-                        text,
-                        width: _,
-                        height: _,
-                    } => ObjectShape::Text {
-                        font_family: font_family.clone(),
-                        pixel_size: pixel_size.clone(),
-                        wrap: wrap.clone(),
-                        color: color.clone(),
-                        bold: bold.clone(),
-                        italic: italic.clone(),
-                        underline: underline.clone(),
-                        strikeout: strikeout.clone(),
-                        kerning: kerning.clone(),
-                        halign: halign.clone(),
-                        valign: valign.clone(),
-                        text: text.clone(),
+                    ObjectShape::Text(text) => ObjectShape::Text(TextData {
                         width,
                         height,
-                    },
+                        ..text.clone()
+                    }),
                     shape => shape.clone(),
                 }
             });
@@ -375,9 +1125,13 @@ impl ObjectData {
             // Any that already exist in the object's map don't get copied over
             for (k, v) in &templ.object.properties {
                 if !properties.contains_key(k) {
+                    if matches!(v, PropertyValue::ObjectValue(id) if *id != 0) {
+                        template_object_properties.push(k.clone());
+                    }
                     properties.insert(k.clone(), v.clone());
                 }
             }
+            template_object_properties.sort();
         }
 
         let shape = shape.unwrap_or(ObjectShape::Rect { width, height });
@@ -385,6 +1139,7 @@ impl ObjectData {
         Ok(ObjectData {
             id,
             tile,
+            collision_gid,
             name,
             user_type,
             x,
@@ -393,14 +1148,23 @@ impl ObjectData {
             visible,
             shape,
             properties,
+            extra,
+            template,
+            template_object_properties,
         })
     }
 }
 
+/// The attributes of the object element that are read by [`ObjectData::new`].
+const OBJECT_ATTRIBUTES: &[&str] = &[
+    "id", "gid", "name", "type", "class", "width", "height", "visible", "rotation", "template",
+    "x", "y",
+];
+
 impl ObjectData {
-    fn new_polyline(attrs: Vec<OwnedAttribute>) -> Result<ObjectShape> {
+    fn new_polyline(attrs: Vec<OwnedAttribute>, position: XmlPosition) -> Result<ObjectShape> {
         let points = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("polyline", position) {
                 "points" => points ?= ObjectData::parse_points(v),
             }
             points
@@ -408,9 +1172,9 @@ impl ObjectData {
         Ok(ObjectShape::Polyline { points })
     }
 
-    fn new_polygon(attrs: Vec<OwnedAttribute>) -> Result<ObjectShape> {
+    fn new_polygon(attrs: Vec<OwnedAttribute>, position: XmlPosition) -> Result<ObjectShape> {
         let points = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("polygon", position) {
                 "points" => points ?= ObjectData::parse_points(v),
             }
             points
@@ -420,7 +1184,7 @@ impl ObjectData {
 
     fn new_text(
         attrs: Vec<OwnedAttribute>,
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         width: f32,
         height: f32,
     ) -> Result<ObjectShape> {
@@ -437,7 +1201,7 @@ impl ObjectData {
             halign,
             valign,
         ) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("text", parser.event_position()) {
                 Some("fontfamily") => font_family = v,
                 Some("pixelsize") => pixel_size ?= v.parse(),
                 Some("wrap") => wrap ?= v.parse(),
@@ -447,21 +1211,18 @@ impl ObjectData {
                 Some("underline") => underline ?= v.parse(),
                 Some("strikeout") => strikeout ?= v.parse(),
                 Some("kerning") => kerning ?= v.parse::<i32>(),
-                Some("halign") => halign = match v.as_str() {
-                    "left" => HorizontalAlignment::Left,
-                    "center" => HorizontalAlignment::Center,
-                    "right" => HorizontalAlignment::Right,
-                    "justify" => HorizontalAlignment::Justify,
-                    _ => return Err(Error::MalformedAttributes("`halign` property did not contain a valid value of 'left', 'center', 'right' or 'justify'".to_string()))
+                Some("halign") => halign ?= match v.as_str() {
+                    "left" => Ok(HorizontalAlignment::Left),
+                    "center" => Ok(HorizontalAlignment::Center),
+                    "right" => Ok(HorizontalAlignment::Right),
+                    "justify" => Ok(HorizontalAlignment::Justify),
+                    _ => Err(()),
                 },
-                Some("valign") => valign = match v.as_str() {
-                    "top" => VerticalAlignment::Top,
-                    "center" => VerticalAlignment::Center,
-                    "bottom" => VerticalAlignment::Bottom,
-                    _ => return Err(Error::MalformedAttributes(
-                        "`halign` property did not contain a valid value of 'top', 'center' or 'bottom'"
-                            .to_string(),
-                    )),
+                Some("valign") => valign ?= match v.as_str() {
+                    "top" => Ok(VerticalAlignment::Top),
+                    "center" => Ok(VerticalAlignment::Center),
+                    "bottom" => Ok(VerticalAlignment::Bottom),
+                    _ => Err(()),
                 },
             }
             (
@@ -491,27 +1252,35 @@ impl ObjectData {
         let italic = italic == Some(1);
         let underline = underline == Some(1);
         let strikeout = strikeout == Some(1);
-        let kerning = kerning.map_or(true, |k| k == 1);
+        let kerning = !matches!(kerning, Some(k) if k != 1);
         let halign = halign.unwrap_or_default();
         let valign = valign.unwrap_or_default();
-        let contents = match parser.next().map_or_else(
-            || {
-                Err(Error::PrematureEnd(
-                    "XML stream ended when trying to parse text contents".to_owned(),
-                ))
-            },
-            |r| r.map_err(Error::XmlDecodingError),
-        )? {
-            xml::reader::XmlEvent::Characters(contents) => contents,
-            _ => {
-                return Err(Error::InvalidObjectData {
-                    description: "Text attribute contained anything but characters as content"
-                        .into(),
-                })
+        // Tiled doesn't trim the contents, so whitespace is kept and an empty element is valid.
+        let mut text = String::new();
+        loop {
+            match parser.next().map_or_else(
+                || {
+                    Err(Error::PrematureEnd(
+                        "XML stream ended when trying to parse text contents".to_owned(),
+                    ))
+                },
+                |r| r.map_err(Error::XmlDecodingError),
+            )? {
+                XmlEvent::Characters(contents) | XmlEvent::Whitespace(contents) => {
+                    text.push_str(&contents)
+                }
+                XmlEvent::Comment(_) => {}
+                XmlEvent::EndElement { name } if name.local_name == "text" => break,
+                event => {
+                    return Err(Error::UnexpectedContent {
+                        element: "text".to_owned(),
+                        found: event.describe(),
+                    })
+                }
             }
-        };
+        }
 
-        Ok(ObjectShape::Text {
+        Ok(ObjectShape::Text(TextData {
             font_family,
             pixel_size,
             wrap,
@@ -523,13 +1292,13 @@ impl ObjectData {
             kerning,
             halign,
             valign,
-            text: contents,
+            text,
             width,
             height,
-        })
+        }))
     }
 
-    fn parse_points(s: String) -> Result<Vec<(f32, f32)>> {
+    fn parse_points(s: String) -> Result<Vec<Point>> {
         let pairs = s.split(' ');
         pairs
             .map(|point| point.split(','))
@@ -543,7 +1312,7 @@ impl ObjectData {
                 }
                 let (x, y) = (v[0].parse().ok(), v[1].parse().ok());
                 match (x, y) {
-                    (Some(x), Some(y)) => Ok((x, y)),
+                    (Some(x), Some(y)) => Ok(Point { x, y }),
                     _ => Err(Error::MalformedAttributes(
                         "one of polyline's points does not have i32eger coordinates".to_string(),
                     )),
@@ -567,4 +1336,171 @@ impl<'map> Object<'map> {
             .as_ref()
             .map(|tile| ObjectTile::new(self.map, tile))
     }
+
+    /// Returns the object layer this object is in, which may itself be nested in group layers;
+    /// See [`Layer::parent`].
+    pub fn parent_layer(&self) -> Option<Layer<'map>> {
+        self.map.parent_of_object(self.data)
+    }
+
+    /// Returns the axis-aligned bounds of this object as placed by [`Self::transform`], without
+    /// any layer offsets.
+    pub(crate) fn placed_bounds(&self) -> Rect {
+        let transform = self.transform(0.0, 0.0);
+        let (width, height) = (transform.width, transform.height);
+        let corners = [(0.0, 0.0), (width, 0.0), (0.0, height), (width, height)];
+        let points: Vec<(f32, f32)> = match &self.shape {
+            ObjectShape::Polyline { points } | ObjectShape::Polygon { points } => {
+                points.iter().map(|point| (point.x, point.y)).collect()
+            }
+            _ => corners.to_vec(),
+        };
+        Rect::enclosing(
+            points
+                .into_iter()
+                .map(|(x, y)| transform.transform_point(x, y)),
+        )
+    }
+
+    /// Returns where and how this object is placed in the map, for an object layer with the given
+    /// offsets in pixels. Those of a [`FlattenedLayer`](crate::FlattenedLayer) already include the
+    /// offsets of its parent groups.
+    ///
+    /// The position of tile objects is their alignment point, which depends on the
+    /// [`Tileset::object_alignment`] of their tileset and on the orientation of the map when it
+    /// is [`ObjectAlignment::Unspecified`]. The transform rotates the tile around that point and
+    /// flips it within its bounds, like Tiled does. Other objects are rotated around their
+    /// position, which is the top left corner of rectangles, ellipses and texts.
+    ///
+    /// ## Note
+    /// In isometric maps, object positions are in a projected space that is not the one the
+    /// map is drawn in; They need to be converted to screen coordinates separately.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_object_groups.tmx")?;
+    /// for layer in map.flattened_layers() {
+    ///     if let Some(objects) = layer.layer.as_object_layer() {
+    ///         for object in objects.objects() {
+    ///             let transform = object.transform(layer.offset_x, layer.offset_y);
+    ///             // Where the top left corner of the object's bounds ends up in the map.
+    ///             println!("{:?}", transform.transform_point(0.0, 0.0));
+    ///         }
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn transform(&self, offset_x: f32, offset_y: f32) -> ObjectTransform {
+        let (width, height) = match &self.shape {
+            ObjectShape::Rect { width, height }
+            | ObjectShape::Ellipse { width, height }
+            | ObjectShape::Text(TextData { width, height, .. }) => (*width, *height),
+            _ => (0.0, 0.0),
+        };
+        let mut transform = ObjectTransform {
+            x: self.x + offset_x,
+            y: self.y + offset_y,
+            rotation: self.rotation,
+            width,
+            height,
+            pivot_x: 0.0,
+            pivot_y: 0.0,
+            flip_h: false,
+            flip_v: false,
+        };
+        if let Some(tile) = self.get_tile() {
+            let alignment = tile
+                .try_get_tileset()
+                .map_or(ObjectAlignment::Unspecified, |tileset| {
+                    tileset.object_alignment
+                });
+            let alignment = match alignment {
+                ObjectAlignment::Unspecified if self.map.orientation == Orientation::Isometric => {
+                    ObjectAlignment::Bottom
+                }
+                ObjectAlignment::Unspecified => ObjectAlignment::BottomLeft,
+                alignment => alignment,
+            };
+            let (x, y) = match alignment {
+                ObjectAlignment::TopLeft => (0.0, 0.0),
+                ObjectAlignment::Top => (0.5, 0.0),
+                ObjectAlignment::TopRight => (1.0, 0.0),
+                ObjectAlignment::Left => (0.0, 0.5),
+                ObjectAlignment::Center => (0.5, 0.5),
+                ObjectAlignment::Right => (1.0, 0.5),
+                ObjectAlignment::Unspecified | ObjectAlignment::BottomLeft => (0.0, 1.0),
+                ObjectAlignment::Bottom => (0.5, 1.0),
+                ObjectAlignment::BottomRight => (1.0, 1.0),
+            };
+            transform.pivot_x = x * width;
+            transform.pivot_y = y * height;
+            transform.flip_h = tile.data.flip_h;
+            transform.flip_v = tile.data.flip_v;
+        }
+        transform
+    }
+}
+
+/// The placement of an object in its map; See [`Object::transform`].
+///
+/// Points of the object are given relative to the top left corner of its bounds before it is
+/// flipped and rotated, which for polylines, polygons and points is the position of the object.
+#[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ObjectTransform {
+    /// The X coordinate of the pivot in the map, in pixels.
+    pub x: f32,
+    /// The Y coordinate of the pivot in the map, in pixels.
+    pub y: f32,
+    /// The clockwise rotation of the object around the pivot, in degrees.
+    pub rotation: f32,
+    /// The width of the object's bounds in pixels, or 0 for polylines, polygons and points.
+    pub width: f32,
+    /// The height of the object's bounds in pixels, or 0 for polylines, polygons and points.
+    pub height: f32,
+    /// The X coordinate of the pivot relative to the top left corner of the object's bounds.
+    pub pivot_x: f32,
+    /// The Y coordinate of the pivot relative to the top left corner of the object's bounds.
+    pub pivot_y: f32,
+    /// Whether the object is mirrored horizontally within its bounds.
+    pub flip_h: bool,
+    /// Whether the object is mirrored vertically within its bounds.
+    pub flip_v: bool,
+}
+
+impl ObjectTransform {
+    /// Returns the affine transform from points of the object to the map as a matrix in
+    /// `[a, b, c, d, e, f]` order, which maps `(x, y)` to `(a * x + c * y + e, b * x + d * y + f)`.
+    pub fn matrix(&self) -> [f32; 6] {
+        let (sin, cos) = self.rotation.to_radians().sin_cos();
+        // Flipping mirrors points within the bounds, before they are moved so that the pivot is at
+        // the origin and rotated around it.
+        let (scale_x, offset_x) = if self.flip_h {
+            (-1.0, self.width - self.pivot_x)
+        } else {
+            (1.0, -self.pivot_x)
+        };
+        let (scale_y, offset_y) = if self.flip_v {
+            (-1.0, self.height - self.pivot_y)
+        } else {
+            (1.0, -self.pivot_y)
+        };
+        [
+            cos * scale_x,
+            sin * scale_x,
+            -sin * scale_y,
+            cos * scale_y,
+            self.x + offset_x * cos - offset_y * sin,
+            self.y + offset_x * sin + offset_y * cos,
+        ]
+    }
+
+    /// Returns where the given point of the object ends up in the map.
+    pub fn transform_point(&self, x: f32, y: f32) -> (f32, f32) {
+        let [a, b, c, d, e, f] = self.matrix();
+        (a * x + c * y + e, b * x + d * y + f)
+    }
 }
@@ -0,0 +1,338 @@
+//! Extraction of simplified collision outlines from tile layers, for physics engines and
+//! pathfinding.
+
+use std::collections::{HashMap, HashSet};
+
+use crate::{
+    Grid, Layer, LayerTile, LayerType, Map, ObjectData, ObjectShape, Orientation, Point, TileLayer,
+};
+
+/// The number of sides of the polygons ellipses are approximated with.
+const ELLIPSE_SIDES: usize = 16;
+
+/// A closed outline of a solid area, as returned by [`Grid::outlines`] and
+/// [`Map::collision_outlines`].
+///
+/// The last point connects back to the first one. Outer outlines go around their area clockwise as
+/// seen in Tiled, where the Y axis points down, and holes go counterclockwise; See
+/// [`Winding`](crate::Winding).
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Outline {
+    /// The corners of the outline. Points in the middle of straight edges are left out.
+    pub points: Vec<Point>,
+    /// Whether this is the outline of a hole inside of another outline.
+    pub hole: bool,
+}
+
+/// The collision outlines of a tile layer; See [`Map::collision_outlines`].
+#[derive(Debug, PartialEq, Clone)]
+pub struct LayerOutlines<'map> {
+    /// The tile layer the outlines are from.
+    pub layer: Layer<'map>,
+    /// The outlines, in pixels and including the offsets of the layer and its parent groups.
+    pub outlines: Vec<Outline>,
+}
+
+impl Grid<bool> {
+    /// Returns the outlines of the areas made up of adjacent `true` cells, in tiles: The cell at
+    /// `(x, y)` covers the square from `(x, y)` to `(x + 1, y + 1)`.
+    ///
+    /// Cells that only touch diagonally are kept in separate outlines, and areas with empty cells
+    /// inside get an outline for each hole.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Point;
+    ///
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_csv.tmx")?;
+    /// // Consider the tiles with ID 0 to be solid.
+    /// let grid = map.build_collision_grid(|tile| tile.id() == 0);
+    /// for outline in grid.outlines() {
+    ///     let Point { x, y } = outline.points[0];
+    ///     println!("Outline starting at tile ({}, {})", x, y);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn outlines(&self) -> Vec<Outline> {
+        let (origin_x, origin_y) = self.origin();
+        let solid = |x: i64, y: i64| {
+            x >= 0
+                && y >= 0
+                && x < self.width() as i64
+                && y < self.height() as i64
+                && self.cells()[(y * self.width() as i64 + x) as usize]
+        };
+
+        // The edges between solid and empty cells, directed so that the solid cell is on their
+        // right, which makes outer outlines go clockwise.
+        let mut edges: Vec<((i64, i64), Direction)> = Vec::new();
+        for y in 0..self.height() as i64 {
+            for x in 0..self.width() as i64 {
+                if !solid(x, y) {
+                    continue;
+                }
+                if !solid(x, y - 1) {
+                    edges.push(((x, y), Direction::Right));
+                }
+                if !solid(x + 1, y) {
+                    edges.push(((x + 1, y), Direction::Down));
+                }
+                if !solid(x, y + 1) {
+                    edges.push(((x + 1, y + 1), Direction::Left));
+                }
+                if !solid(x - 1, y) {
+                    edges.push(((x, y + 1), Direction::Up));
+                }
+            }
+        }
+        let mut outgoing: HashMap<(i64, i64), Vec<Direction>> = HashMap::new();
+        for &(start, direction) in &edges {
+            outgoing.entry(start).or_default().push(direction);
+        }
+
+        let mut used = HashSet::new();
+        let mut outlines = Vec::new();
+        for &(start, direction) in &edges {
+            if used.contains(&(start, direction)) {
+                continue;
+            }
+            let mut corners = Vec::new();
+            let initial_direction = direction;
+            let (mut position, mut direction) = (start, direction);
+            loop {
+                used.insert((position, direction));
+                position = direction.step(position);
+                if position == start {
+                    if direction != initial_direction {
+                        corners.insert(0, start);
+                    }
+                    break;
+                }
+                // Turning right first keeps cells that only touch diagonally apart.
+                let next = [direction.right(), direction, direction.left()]
+                    .iter()
+                    .copied()
+                    .find(|next| {
+                        outgoing
+                            .get(&position)
+                            .into_iter()
+                            .flatten()
+                            .any(|d| d == next)
+                            && !used.contains(&(position, *next))
+                    });
+                match next {
+                    Some(next) => {
+                        if next != direction {
+                            corners.push(position);
+                        }
+                        direction = next;
+                    }
+                    None => break,
+                }
+            }
+
+            let points: Vec<Point> = corners
+                .into_iter()
+                .map(|(x, y)| {
+                    Point::new((x + origin_x as i64) as f32, (y + origin_y as i64) as f32)
+                })
+                .collect();
+            let hole = signed_area(&points) < 0.0;
+            outlines.push(Outline { points, hole });
+        }
+        outlines
+    }
+}
+
+impl Map {
+    /// Returns simplified outlines of the collision shapes of the tiles in each tile layer of
+    /// this orthogonal map, in pixels, or [`None`] for maps of other orientations.
+    ///
+    /// Tiles whose collision shapes are a single rectangle covering the whole tile are merged
+    /// with the adjacent ones into outlines with the corners of the area they cover; See
+    /// [`Grid::outlines`]. Other collision shapes get an outline each: Rectangles and polygons
+    /// keep their corners, ellipses are approximated with polygons, and polylines, points and
+    /// texts are left out as they have no area. Layers without any collision shapes are left out.
+    ///
+    /// The tiles' collision shapes are taken from [`TileData::collision`], positioned as
+    /// described in [`LayerTile::collision_shapes`]. Hidden layers are included.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_tile_collision.tmx")?;
+    /// for layer in map.collision_outlines().unwrap() {
+    ///     for outline in layer.outlines {
+    ///         println!("{}: {:?}", layer.layer.name, outline.points);
+    ///     }
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    ///
+    /// [`TileData::collision`]: crate::TileData::collision
+    pub fn collision_outlines(&self) -> Option<Vec<LayerOutlines<'_>>> {
+        if self.orientation != Orientation::Orthogonal {
+            return None;
+        }
+        let (tile_width, tile_height) = (self.tile_width as f32, self.tile_height as f32);
+        let mut layers = Vec::new();
+        for layer in self.flattened_layers() {
+            let tiles: Box<dyn Iterator<Item = ((i32, i32), LayerTile)>> =
+                match layer.layer.layer_type() {
+                    LayerType::Tiles(TileLayer::Finite(tiles)) => Box::new(tiles.tiles()),
+                    LayerType::Tiles(TileLayer::Infinite(tiles)) => Box::new(tiles.tiles()),
+                    _ => continue,
+                };
+            let (offset_x, offset_y) = (layer.offset_x, layer.offset_y);
+
+            let mut solid = Vec::new();
+            let mut outlines = Vec::new();
+            for ((x, y), tile) in tiles {
+                let shapes: Vec<ObjectData> =
+                    tile.collision_shapes(x, y, offset_x, offset_y).collect();
+                let cell = (
+                    x as f32 * tile_width + offset_x,
+                    y as f32 * tile_height + offset_y,
+                );
+                if let [shape] = shapes.as_slice() {
+                    if covers_cell(shape, cell, (tile_width, tile_height)) {
+                        solid.push((x, y));
+                        continue;
+                    }
+                }
+                outlines.extend(shapes.iter().filter_map(shape_outline));
+            }
+            if solid.is_empty() && outlines.is_empty() {
+                continue;
+            }
+
+            let merged = Grid::from_solid_cells(solid, None).outlines();
+            let merged = merged.into_iter().map(|outline| Outline {
+                points: outline
+                    .points
+                    .iter()
+                    .map(|point| {
+                        Point::new(
+                            point.x * tile_width + offset_x,
+                            point.y * tile_height + offset_y,
+                        )
+                    })
+                    .collect(),
+                ..outline
+            });
+            layers.push(LayerOutlines {
+                layer: layer.layer,
+                outlines: merged.chain(outlines).collect(),
+            });
+        }
+        Some(layers)
+    }
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy)]
+enum Direction {
+    Right,
+    Down,
+    Left,
+    Up,
+}
+
+impl Direction {
+    fn step(self, (x, y): (i64, i64)) -> (i64, i64) {
+        match self {
+            Direction::Right => (x + 1, y),
+            Direction::Down => (x, y + 1),
+            Direction::Left => (x - 1, y),
+            Direction::Up => (x, y - 1),
+        }
+    }
+
+    /// The direction after turning right, as seen with the Y axis pointing down.
+    fn right(self) -> Self {
+        match self {
+            Direction::Right => Direction::Down,
+            Direction::Down => Direction::Left,
+            Direction::Left => Direction::Up,
+            Direction::Up => Direction::Right,
+        }
+    }
+
+    fn left(self) -> Self {
+        self.right().right().right()
+    }
+}
+
+/// Twice the signed area of a polygon, which is positive when its points go clockwise since the Y
+/// axis points down.
+fn signed_area(points: &[Point]) -> f32 {
+    points
+        .iter()
+        .zip(points.iter().cycle().skip(1))
+        .map(|(a, b)| a.x * b.y - b.x * a.y)
+        .sum()
+}
+
+/// Whether the shape is an unrotated rectangle covering exactly the cell at the given position.
+fn covers_cell(shape: &ObjectData, (x, y): (f32, f32), (width, height): (f32, f32)) -> bool {
+    const EPSILON: f32 = 0.01;
+    let close = |a: f32, b: f32| (a - b).abs() < EPSILON;
+    match shape.shape {
+        ObjectShape::Rect {
+            width: shape_width,
+            height: shape_height,
+        } => {
+            shape.rotation % 360.0 == 0.0
+                && close(shape.x, x)
+                && close(shape.y, y)
+                && close(shape_width, width)
+                && close(shape_height, height)
+        }
+        _ => false,
+    }
+}
+
+/// Returns the outline of a positioned collision shape, or [`None`] if it has no area.
+fn shape_outline(shape: &ObjectData) -> Option<Outline> {
+    let points: Vec<(f32, f32)> = match &shape.shape {
+        ObjectShape::Rect { width, height } => {
+            vec![(0.0, 0.0), (*width, 0.0), (*width, *height), (0.0, *height)]
+        }
+        ObjectShape::Ellipse { width, height } => {
+            let (radius_x, radius_y) = (width / 2.0, height / 2.0);
+            (0..ELLIPSE_SIDES)
+                .map(|i| {
+                    let angle = i as f32 / ELLIPSE_SIDES as f32 * std::f32::consts::TAU;
+                    (
+                        radius_x + radius_x * angle.cos(),
+                        radius_y + radius_y * angle.sin(),
+                    )
+                })
+                .collect()
+        }
+        ObjectShape::Polygon { points } => points.iter().map(|point| (point.x, point.y)).collect(),
+        ObjectShape::Polyline { .. } | ObjectShape::Point(..) | ObjectShape::Text(_) => {
+            return None
+        }
+    };
+    let (sin, cos) = shape.rotation.to_radians().sin_cos();
+    let mut points: Vec<Point> = points
+        .into_iter()
+        .map(|(x, y)| Point::new(shape.x + x * cos - y * sin, shape.y + x * sin + y * cos))
+        .collect();
+    let area = signed_area(&points);
+    if area == 0.0 {
+        return None;
+    }
+    // Flipped tiles mirror their shapes, which reverses the direction of their points.
+    if area < 0.0 {
+        points.reverse();
+    }
+    Some(Outline {
+        points,
+        hole: false,
+    })
+}
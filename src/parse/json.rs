@@ -0,0 +1,249 @@
+//! A minimal JSON reader, used for the handful of Tiled files that are only available in JSON
+//! form (such as `.tiled-project` files).
+
+use std::{fmt, iter::Peekable, str::Chars};
+
+/// A parsed JSON value.
+#[derive(Debug, PartialEq, Clone)]
+pub(crate) enum JsonValue {
+    Null,
+    Bool(bool),
+    Number(JsonNumber),
+    String(String),
+    Array(Vec<JsonValue>),
+    /// Object members, in the order they were declared.
+    Object(Vec<(String, JsonValue)>),
+}
+
+/// A parsed JSON number. Integers are kept apart from other numbers so that they don't lose
+/// precision.
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub(crate) enum JsonNumber {
+    /// A number without a fraction or exponent that fits in an `i64`.
+    Int(i64),
+    Float(f64),
+}
+
+impl fmt::Display for JsonNumber {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            JsonNumber::Int(n) => n.fmt(f),
+            JsonNumber::Float(n) => n.fmt(f),
+        }
+    }
+}
+
+impl JsonValue {
+    /// Returns the value of the member with the given key, if this is an object that has it.
+    pub fn get(&self, key: &str) -> Option<&JsonValue> {
+        match self {
+            JsonValue::Object(members) => members.iter().find(|(k, _)| k == key).map(|(_, v)| v),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            JsonValue::String(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    /// Returns the value if this is an integer; Numbers with a fraction or exponent aren't
+    /// integers, even if their value is integral.
+    pub fn as_i64(&self) -> Option<i64> {
+        match self {
+            JsonValue::Number(JsonNumber::Int(n)) => Some(*n),
+            _ => None,
+        }
+    }
+
+    pub fn as_bool(&self) -> Option<bool> {
+        match self {
+            JsonValue::Bool(b) => Some(*b),
+            _ => None,
+        }
+    }
+
+    pub fn as_array(&self) -> Option<&[JsonValue]> {
+        match self {
+            JsonValue::Array(values) => Some(values),
+            _ => None,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&[(String, JsonValue)]> {
+        match self {
+            JsonValue::Object(members) => Some(members),
+            _ => None,
+        }
+    }
+}
+
+/// How deep arrays and objects can be nested in a JSON document. Each level of nesting takes up
+/// stack space while parsing, so without a limit a malicious file could overflow the stack.
+const MAX_DEPTH: usize = 128;
+
+/// Parses a complete JSON document. On failure, returns a description of the problem.
+pub(crate) fn parse_json(input: &str) -> Result<JsonValue, String> {
+    let mut chars = input.chars().peekable();
+    let value = parse_value(&mut chars, 0)?;
+    skip_whitespace(&mut chars);
+    match chars.next() {
+        None => Ok(value),
+        Some(c) => Err(format!("unexpected trailing character '{}'", c)),
+    }
+}
+
+fn skip_whitespace(chars: &mut Peekable<Chars>) {
+    while matches!(chars.peek(), Some(c) if c.is_whitespace()) {
+        chars.next();
+    }
+}
+
+fn expect(chars: &mut Peekable<Chars>, expected: char) -> Result<(), String> {
+    match chars.next() {
+        Some(c) if c == expected => Ok(()),
+        Some(c) => Err(format!("expected '{}', found '{}'", expected, c)),
+        None => Err(format!("expected '{}', found end of input", expected)),
+    }
+}
+
+fn expect_literal(chars: &mut Peekable<Chars>, literal: &str) -> Result<(), String> {
+    literal.chars().try_for_each(|c| expect(chars, c))
+}
+
+/// Parses a value nested inside `depth` arrays or objects.
+fn parse_value(chars: &mut Peekable<Chars>, depth: usize) -> Result<JsonValue, String> {
+    skip_whitespace(chars);
+    match chars.peek() {
+        Some('{') | Some('[') if depth >= MAX_DEPTH => Err(format!(
+            "arrays and objects are nested more than {} levels deep",
+            MAX_DEPTH
+        )),
+        Some('{') => parse_object(chars, depth + 1),
+        Some('[') => parse_array(chars, depth + 1),
+        Some('"') => parse_string(chars).map(JsonValue::String),
+        Some('t') => expect_literal(chars, "true").map(|_| JsonValue::Bool(true)),
+        Some('f') => expect_literal(chars, "false").map(|_| JsonValue::Bool(false)),
+        Some('n') => expect_literal(chars, "null").map(|_| JsonValue::Null),
+        Some(c) if *c == '-' || c.is_ascii_digit() => parse_number(chars),
+        Some(c) => Err(format!("unexpected character '{}'", c)),
+        None => Err("unexpected end of input".to_owned()),
+    }
+}
+
+fn parse_object(chars: &mut Peekable<Chars>, depth: usize) -> Result<JsonValue, String> {
+    expect(chars, '{')?;
+    let mut members = Vec::new();
+    skip_whitespace(chars);
+    if chars.peek() == Some(&'}') {
+        chars.next();
+        return Ok(JsonValue::Object(members));
+    }
+    loop {
+        skip_whitespace(chars);
+        let key = parse_string(chars)?;
+        skip_whitespace(chars);
+        expect(chars, ':')?;
+        let value = parse_value(chars, depth)?;
+        members.push((key, value));
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some('}') => return Ok(JsonValue::Object(members)),
+            Some(c) => return Err(format!("expected ',' or '}}', found '{}'", c)),
+            None => return Err("unexpected end of input inside object".to_owned()),
+        }
+    }
+}
+
+fn parse_array(chars: &mut Peekable<Chars>, depth: usize) -> Result<JsonValue, String> {
+    expect(chars, '[')?;
+    let mut values = Vec::new();
+    skip_whitespace(chars);
+    if chars.peek() == Some(&']') {
+        chars.next();
+        return Ok(JsonValue::Array(values));
+    }
+    loop {
+        values.push(parse_value(chars, depth)?);
+        skip_whitespace(chars);
+        match chars.next() {
+            Some(',') => continue,
+            Some(']') => return Ok(JsonValue::Array(values)),
+            Some(c) => return Err(format!("expected ',' or ']', found '{}'", c)),
+            None => return Err("unexpected end of input inside array".to_owned()),
+        }
+    }
+}
+
+fn parse_string(chars: &mut Peekable<Chars>) -> Result<String, String> {
+    expect(chars, '"')?;
+    let mut string = String::new();
+    loop {
+        match chars.next() {
+            Some('"') => return Ok(string),
+            Some('\\') => match chars.next() {
+                Some('"') => string.push('"'),
+                Some('\\') => string.push('\\'),
+                Some('/') => string.push('/'),
+                Some('b') => string.push('\u{8}'),
+                Some('f') => string.push('\u{c}'),
+                Some('n') => string.push('\n'),
+                Some('r') => string.push('\r'),
+                Some('t') => string.push('\t'),
+                Some('u') => string.push(parse_unicode_escape(chars)?),
+                Some(c) => return Err(format!("invalid escape sequence '\\{}'", c)),
+                None => return Err("unexpected end of input inside string".to_owned()),
+            },
+            Some(c) => string.push(c),
+            None => return Err("unexpected end of input inside string".to_owned()),
+        }
+    }
+}
+
+fn parse_hex4(chars: &mut Peekable<Chars>) -> Result<u32, String> {
+    let hex: String = chars.by_ref().take(4).collect();
+    if hex.len() != 4 {
+        return Err("unexpected end of input inside unicode escape".to_owned());
+    }
+    u32::from_str_radix(&hex, 16).map_err(|_| format!("invalid unicode escape '\\u{}'", hex))
+}
+
+fn parse_unicode_escape(chars: &mut Peekable<Chars>) -> Result<char, String> {
+    let first = parse_hex4(chars)?;
+    let code = if (0xD800..0xDC00).contains(&first) {
+        // High surrogate; must be followed by an escaped low surrogate.
+        expect_literal(chars, "\\u")?;
+        let second = parse_hex4(chars)?;
+        if !(0xDC00..0xE000).contains(&second) {
+            return Err("invalid unicode surrogate pair".to_owned());
+        }
+        0x10000 + ((first - 0xD800) << 10) + (second - 0xDC00)
+    } else {
+        first
+    };
+    char::from_u32(code).ok_or_else(|| "invalid unicode code point".to_owned())
+}
+
+fn parse_number(chars: &mut Peekable<Chars>) -> Result<JsonValue, String> {
+    let mut number = String::new();
+    while let Some(c) = chars.peek() {
+        if c.is_ascii_digit() || matches!(c, '-' | '+' | '.' | 'e' | 'E') {
+            number.push(*c);
+            chars.next();
+        } else {
+            break;
+        }
+    }
+    let is_integer = !number.contains(['.', 'e', 'E']);
+    match number.parse() {
+        Ok(n) if is_integer => Ok(JsonValue::Number(JsonNumber::Int(n))),
+        // Integers too large for an `i64` are still valid JSON.
+        _ => number
+            .parse()
+            .map(|n| JsonValue::Number(JsonNumber::Float(n)))
+            .map_err(|_| format!("invalid number '{}'", number)),
+    }
+}
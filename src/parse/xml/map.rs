@@ -1,44 +1,62 @@
-use std::path::Path;
+use std::{io::Read, path::Path};
 
-use xml::{reader::XmlEvent, EventReader};
-
-use crate::{Error, Map, ResourceCache, ResourceReader, Result};
+use crate::{
+    stream::MapEvents,
+    util::{XmlEvent, XmlEvents, XmlParser},
+    Error, LoaderOptions, Map, Project, ResourceCache, ResourceReader, Result,
+};
 
 pub fn parse_map(
     path: &Path,
     reader: &mut impl ResourceReader,
     cache: &mut impl ResourceCache,
+    project: Option<&Project>,
+    options: &LoaderOptions,
+    events: &mut MapEvents<'_>,
 ) -> Result<Map> {
-    let mut parser =
-        EventReader::new(
-            reader
-                .read_from(path)
-                .map_err(|err| Error::ResourceLoadingError {
-                    path: path.to_owned(),
-                    err: Box::new(err),
-                })?,
-        );
-    loop {
-        match parser.next().map_err(Error::XmlDecodingError)? {
+    let source = reader
+        .read_from(path)
+        .map_err(|err| Error::ResourceLoadingError {
+            path: path.to_owned(),
+            err: Box::new(err),
+        })?;
+    let parser = XmlEvents::new(source, path).with_map_options(options);
+    parse_map_from(parser, reader, cache, project, events)
+}
+
+/// Parses a map from the given parser instead of reading it through the reader. The path of the
+/// parser is still used to resolve the paths of the files the map references.
+pub fn parse_map_from(
+    mut parser: XmlEvents<impl Read>,
+    reader: &mut impl ResourceReader,
+    cache: &mut impl ResourceCache,
+    project: Option<&Project>,
+    events: &mut MapEvents<'_>,
+) -> Result<Map> {
+    let path = parser.path().to_owned();
+    let path = path.as_path();
+    while let Some(next) = parser.next() {
+        match next.map_err(|err| parser.locate(Error::XmlDecodingError(err)))? {
             XmlEvent::StartElement {
                 name, attributes, ..
-            } => {
-                if name.local_name == "map" {
-                    return Map::parse_xml(
-                        &mut parser.into_iter(),
-                        attributes,
-                        path,
-                        reader,
-                        cache,
-                    );
-                }
-            }
-            XmlEvent::EndDocument => {
-                return Err(Error::PrematureEnd(
-                    "Document ended before map was parsed".to_string(),
-                ))
+            } if name.local_name == "map" => {
+                let position = parser.event_position();
+                return Map::parse_xml(
+                    &mut parser,
+                    attributes,
+                    path,
+                    reader,
+                    cache,
+                    project,
+                    events,
+                )
+                .map_err(|err| err.located(path, position));
             }
+            XmlEvent::EndDocument => break,
             _ => {}
         }
     }
+    Err(parser.locate(Error::PrematureEnd(
+        "Document ended before map was parsed".to_string(),
+    )))
 }
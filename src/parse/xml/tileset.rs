@@ -1,42 +1,54 @@
 use std::path::Path;
 
-use xml::{reader::XmlEvent, EventReader};
-
-use crate::{Error, ResourceCache, ResourceReader, Result, Tileset};
+use crate::{
+    compat,
+    util::{XmlEvent, XmlEvents, XmlParser},
+    Error, LoaderOptions, Project, ResourceCache, ResourceReader, Result, Tileset,
+};
 
 pub fn parse_tileset(
     path: &Path,
     reader: &mut impl ResourceReader,
     cache: &mut impl ResourceCache,
+    project: Option<&Project>,
+    options: &LoaderOptions,
 ) -> Result<Tileset> {
-    let mut tileset_parser =
-        EventReader::new(
-            reader
-                .read_from(path)
-                .map_err(|err| Error::ResourceLoadingError {
-                    path: path.to_owned(),
-                    err: Box::new(err),
-                })?,
-        );
-    loop {
-        match tileset_parser.next().map_err(Error::XmlDecodingError)? {
+    let mut tileset_parser = XmlEvents::new(
+        reader
+            .read_from(path)
+            .map_err(|err| Error::ResourceLoadingError {
+                path: path.to_owned(),
+                err: Box::new(err),
+            })?,
+        path,
+    )
+    .with_options(options);
+    while let Some(next) = tileset_parser.next() {
+        match next.map_err(|err| tileset_parser.locate(Error::XmlDecodingError(err)))? {
             XmlEvent::StartElement {
                 name, attributes, ..
             } if name.local_name == "tileset" => {
+                let position = tileset_parser.event_position();
+                let version = attributes
+                    .iter()
+                    .find(|attr| attr.name.local_name == "version")
+                    .map(|attr| attr.value.as_str());
+                tileset_parser.set_legacy_format(compat::is_legacy_version(version));
                 return Tileset::parse_external_tileset(
-                    &mut tileset_parser.into_iter(),
+                    &mut tileset_parser,
                     &attributes,
                     path,
                     reader,
                     cache,
-                );
-            }
-            XmlEvent::EndDocument => {
-                return Err(Error::PrematureEnd(
-                    "Tileset Document ended before map was parsed".to_string(),
-                ))
+                    project,
+                )
+                .map_err(|err| err.located(path, position));
             }
+            XmlEvent::EndDocument => break,
             _ => {}
         }
     }
+    Err(tileset_parser.locate(Error::PrematureEnd(
+        "Tileset Document ended before map was parsed".to_string(),
+    )))
 }
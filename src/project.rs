@@ -0,0 +1,463 @@
+//! Structures related to Tiled projects.
+
+use std::{collections::HashMap, convert::TryFrom, io::Read, path::Path};
+
+use crate::{
+    error::{Error, Result},
+    parse::json::{parse_json, JsonValue},
+    properties::{Properties, PropertyValue},
+    ResourceReader,
+};
+
+/// A Tiled project, as saved in `.tiled-project` files.
+///
+/// Projects hold the custom property types (classes and enums) used by the maps, tilesets and
+/// templates in it. Maps and other files only store the class members that were explicitly set,
+/// and enum values in their raw stored form; By loading the project and setting it in the
+/// [`Loader`](crate::Loader), class properties get their unset members filled in with the
+/// defaults defined in the project, and enum properties are resolved to
+/// [`PropertyValue::EnumValue`].
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{Loader, PropertyValue};
+///
+/// let mut loader = Loader::new();
+/// let project = loader.load_tiled_project("assets/tiled_project.tiled-project")?;
+/// loader.set_project(Some(project));
+///
+/// let map = loader.load_tmx_map("assets/tiled_project_properties.tmx")?;
+/// assert!(matches!(
+///     map.properties.get("direction"),
+///     Some(PropertyValue::EnumValue { .. })
+/// ));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, PartialEq, Clone, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Project {
+    /// The custom property types defined in this project.
+    pub property_types: Vec<PropertyType>,
+}
+
+/// A custom property type defined in a [`Project`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum PropertyType {
+    /// A class type; Also see [`ClassPropertyType`].
+    Class(ClassPropertyType),
+    /// An enum type; Also see [`EnumPropertyType`].
+    Enum(EnumPropertyType),
+}
+
+impl PropertyType {
+    /// The unique ID of this type within its project.
+    pub fn id(&self) -> u32 {
+        match self {
+            PropertyType::Class(class) => class.id,
+            PropertyType::Enum(enum_type) => enum_type.id,
+        }
+    }
+
+    /// The name of this type, which is what `propertytype` attributes refer to.
+    pub fn name(&self) -> &str {
+        match self {
+            PropertyType::Class(class) => &class.name,
+            PropertyType::Enum(enum_type) => &enum_type.name,
+        }
+    }
+}
+
+/// A custom class type, which groups a set of members under a name.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ClassPropertyType {
+    /// The unique ID of this type within its project.
+    pub id: u32,
+    /// The name of this class.
+    pub name: String,
+    /// The members of this class along with their default values.
+    ///
+    /// Members of a class type hold a [`PropertyValue::ClassValue`] with only the members whose
+    /// default differs from the nested class' own defaults.
+    pub members: Properties,
+}
+
+/// How the value of an [`EnumPropertyType`] is stored in files.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum EnumStorageType {
+    /// The value is stored as the name of the value (or a comma-separated list of names, for
+    /// flag enums).
+    #[default]
+    String,
+    /// The value is stored as the index of the value (or a bitmask of indices, for flag enums).
+    Int,
+}
+
+/// A custom enum type, which restricts a property to a set of named values.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct EnumPropertyType {
+    /// The unique ID of this type within its project.
+    pub id: u32,
+    /// The name of this enum.
+    pub name: String,
+    /// How the values of this enum are stored in files.
+    pub storage_type: EnumStorageType,
+    /// The possible values of this enum.
+    pub values: Vec<String>,
+    /// Whether more than one value can be set at once, in which case the enum acts as a set of
+    /// flags.
+    pub values_as_flags: bool,
+}
+
+impl EnumPropertyType {
+    /// Converts a raw string or int property value of this enum type to the names of the enum
+    /// values it represents.
+    fn resolve(&self, value: &PropertyValue) -> Result<Vec<String>> {
+        let invalid = || Error::InvalidPropertyValue {
+            description: format!("{:?} is not a valid value of enum '{}'", value, self.name),
+        };
+        let find = |name: &str| {
+            self.values
+                .iter()
+                .find(|v| *v == name)
+                .cloned()
+                .ok_or_else(invalid)
+        };
+
+        match (self.storage_type, value) {
+            (EnumStorageType::String, PropertyValue::StringValue(s)) if self.values_as_flags => s
+                .split(',')
+                .filter(|name| !name.is_empty())
+                .map(find)
+                .collect(),
+            (EnumStorageType::String, PropertyValue::StringValue(s)) => Ok(vec![find(s)?]),
+            (EnumStorageType::Int, PropertyValue::IntValue(bits)) if self.values_as_flags => {
+                // Shifting by 64 or more gives `None`, as there are no bits left.
+                let bits = u64::try_from(*bits).map_err(|_| invalid())?;
+                let flag = |i: usize| bits.checked_shr(i as u32).map_or(0, |bits| bits & 1);
+                if bits.checked_shr(self.values.len() as u32).unwrap_or(0) != 0 {
+                    return Err(invalid());
+                }
+                Ok(self
+                    .values
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, _)| flag(*i) != 0)
+                    .map(|(_, name)| name.clone())
+                    .collect())
+            }
+            (EnumStorageType::Int, PropertyValue::IntValue(index)) => usize::try_from(*index)
+                .ok()
+                .and_then(|index| self.values.get(index))
+                .map(|name| vec![name.clone()])
+                .ok_or_else(invalid),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl Project {
+    /// Obtains a property type by its name.
+    pub fn get_property_type(&self, name: &str) -> Option<&PropertyType> {
+        self.property_types.iter().find(|ty| ty.name() == name)
+    }
+
+    pub(crate) fn load(path: &Path, reader: &mut impl ResourceReader) -> Result<Project> {
+        let mut contents = String::new();
+        reader
+            .read_from(path)
+            .map_err(|err| Error::ResourceLoadingError {
+                path: path.to_owned(),
+                err: Box::new(err),
+            })?
+            .read_to_string(&mut contents)
+            .map_err(|err| Error::ResourceLoadingError {
+                path: path.to_owned(),
+                err: Box::new(err),
+            })?;
+
+        let json = parse_json(&contents)
+            .map_err(|description| Error::InvalidProjectFile { description })?;
+        let types_json = json
+            .get("propertyTypes")
+            .and_then(JsonValue::as_array)
+            .unwrap_or_default();
+
+        let mut project = Project {
+            property_types: types_json
+                .iter()
+                .map(parse_property_type)
+                .collect::<Result<_>>()?,
+        };
+
+        // Member defaults may refer to any other type, so they can only be parsed once every type
+        // is known.
+        let members = types_json
+            .iter()
+            .map(|ty| project.parse_class_members(ty, types_json, path))
+            .collect::<Result<Vec<_>>>()?;
+        for (ty, members) in project.property_types.iter_mut().zip(members) {
+            if let PropertyType::Class(class) = ty {
+                class.members = members;
+            }
+        }
+
+        Ok(project)
+    }
+
+    /// Resolves a raw string or int property that has a `propertytype` attribute. Values whose
+    /// type isn't an enum of this project are returned unchanged.
+    pub(crate) fn resolve_enum(
+        &self,
+        property_type: &str,
+        value: PropertyValue,
+    ) -> Result<PropertyValue> {
+        match self.get_property_type(property_type) {
+            Some(PropertyType::Enum(enum_type)) => Ok(PropertyValue::EnumValue {
+                property_type: enum_type.name.clone(),
+                values: enum_type.resolve(&value)?,
+                int_value: match value {
+                    PropertyValue::IntValue(value) => Some(value),
+                    _ => None,
+                },
+            }),
+            _ => Ok(value),
+        }
+    }
+
+    /// Fills in the members of a class value that haven't been set with the defaults of its
+    /// class, recursing into nested classes.
+    pub(crate) fn fill_class_defaults(
+        &self,
+        property_type: &str,
+        properties: Properties,
+    ) -> Properties {
+        self.fill_class_defaults_inner(property_type, properties, &mut Vec::new())
+    }
+
+    fn fill_class_defaults_inner<'a>(
+        &'a self,
+        property_type: &str,
+        mut properties: Properties,
+        // Tiled doesn't allow classes to contain themselves, but keep track of the chain anyway so
+        // that malformed projects can't cause infinite recursion.
+        visiting: &mut Vec<&'a str>,
+    ) -> Properties {
+        let class = match self.get_property_type(property_type) {
+            Some(PropertyType::Class(class)) if !visiting.contains(&class.name.as_str()) => class,
+            _ => return properties,
+        };
+        visiting.push(&class.name);
+
+        for (name, default) in &class.members {
+            let value = match properties.remove(name) {
+                Some(mut value) => {
+                    merge_defaults(&mut value, default);
+                    value
+                }
+                None => default.clone(),
+            };
+            let value = match value {
+                PropertyValue::ClassValue {
+                    property_type,
+                    properties,
+                } => {
+                    let properties =
+                        self.fill_class_defaults_inner(&property_type, properties, visiting);
+                    PropertyValue::ClassValue {
+                        property_type,
+                        properties,
+                    }
+                }
+                value => value,
+            };
+            properties.insert(name.clone(), value);
+        }
+
+        visiting.pop();
+        properties
+    }
+
+    fn parse_class_members(
+        &self,
+        class_json: &JsonValue,
+        types_json: &[JsonValue],
+        path: &Path,
+    ) -> Result<Properties> {
+        let mut members = HashMap::new();
+        for member in class_json
+            .get("members")
+            .and_then(JsonValue::as_array)
+            .unwrap_or_default()
+        {
+            let name = member
+                .get("name")
+                .and_then(JsonValue::as_str)
+                .ok_or_else(|| invalid_project("class member is missing a name"))?;
+            let value = member.get("value").unwrap_or(&JsonValue::Null);
+            if let Some(value) = self.parse_member_value(member, value, types_json, path)? {
+                members.insert(name.to_owned(), value);
+            }
+        }
+        Ok(members)
+    }
+
+    /// Parses the value of a class member, using the member's definition to determine its type.
+    ///
+    /// Returns `None` for values that have no meaningful default, such as unset colors.
+    fn parse_member_value(
+        &self,
+        member_json: &JsonValue,
+        value: &JsonValue,
+        types_json: &[JsonValue],
+        path: &Path,
+    ) -> Result<Option<PropertyValue>> {
+        let member_type = member_json
+            .get("type")
+            .and_then(JsonValue::as_str)
+            .unwrap_or("string");
+        let property_type = member_json.get("propertyType").and_then(JsonValue::as_str);
+
+        if member_type == "class" {
+            // Nested class values only contain the members that differ from the class defaults,
+            // with their types given by the nested class' own definition.
+            let nested_members = types_json
+                .iter()
+                .find(|ty| {
+                    ty.get("name").and_then(JsonValue::as_str) == property_type
+                        && ty.get("type").and_then(JsonValue::as_str) == Some("class")
+                })
+                .and_then(|ty| ty.get("members"))
+                .and_then(JsonValue::as_array)
+                .unwrap_or_default();
+
+            let mut properties = HashMap::new();
+            for (name, value) in value.as_object().unwrap_or_default() {
+                let nested_member = nested_members
+                    .iter()
+                    .find(|m| m.get("name").and_then(JsonValue::as_str) == Some(name));
+                if let Some(nested_member) = nested_member {
+                    if let Some(value) =
+                        self.parse_member_value(nested_member, value, types_json, path)?
+                    {
+                        properties.insert(name.clone(), value);
+                    }
+                }
+            }
+            return Ok(Some(PropertyValue::ClassValue {
+                property_type: property_type.unwrap_or_default().to_owned(),
+                properties,
+            }));
+        }
+
+        let raw = match value {
+            JsonValue::String(s) => s.clone(),
+            JsonValue::Bool(b) => b.to_string(),
+            JsonValue::Number(n) => n.to_string(),
+            _ => String::new(),
+        };
+        if member_type == "color" && raw.is_empty() {
+            return Ok(None);
+        }
+        let value = PropertyValue::new(member_type.to_owned(), raw, path)?;
+        match property_type {
+            Some(property_type) => self.resolve_enum(property_type, value).map(Some),
+            None => Ok(Some(value)),
+        }
+    }
+}
+
+/// Fills in the members of a class value that were left unset, using the ones in the class member
+/// default `default`. Nested class values are merged recursively.
+fn merge_defaults(value: &mut PropertyValue, default: &PropertyValue) {
+    if let (
+        PropertyValue::ClassValue { properties, .. },
+        PropertyValue::ClassValue {
+            properties: defaults,
+            ..
+        },
+    ) = (value, default)
+    {
+        for (name, default) in defaults {
+            match properties.get_mut(name) {
+                Some(value) => merge_defaults(value, default),
+                None => {
+                    properties.insert(name.clone(), default.clone());
+                }
+            }
+        }
+    }
+}
+
+fn invalid_project(description: impl Into<String>) -> Error {
+    Error::InvalidProjectFile {
+        description: description.into(),
+    }
+}
+
+fn parse_property_type(json: &JsonValue) -> Result<PropertyType> {
+    let name = json
+        .get("name")
+        .and_then(JsonValue::as_str)
+        .ok_or_else(|| invalid_project("property type is missing a name"))?
+        .to_owned();
+    let id = match json.get("id") {
+        None => 0,
+        Some(id) => id
+            .as_i64()
+            .and_then(|id| u32::try_from(id).ok())
+            .ok_or_else(|| {
+                invalid_project(format!(
+                    "property type '{}' has an id that isn't a 32-bit unsigned integer",
+                    name
+                ))
+            })?,
+    };
+
+    match json.get("type").and_then(JsonValue::as_str) {
+        // Members are filled in once every type is known; See `Project::load`.
+        Some("class") => Ok(PropertyType::Class(ClassPropertyType {
+            id,
+            name,
+            members: HashMap::new(),
+        })),
+        Some("enum") => {
+            let storage_type = match json.get("storageType").and_then(JsonValue::as_str) {
+                Some("int") => EnumStorageType::Int,
+                _ => EnumStorageType::String,
+            };
+            let values = json
+                .get("values")
+                .and_then(JsonValue::as_array)
+                .unwrap_or_default()
+                .iter()
+                .map(|v| {
+                    v.as_str().map(str::to_owned).ok_or_else(|| {
+                        invalid_project(format!("enum '{}' has a non-string value", name))
+                    })
+                })
+                .collect::<Result<_>>()?;
+            let values_as_flags = json
+                .get("valuesAsFlags")
+                .and_then(JsonValue::as_bool)
+                .unwrap_or(false);
+            Ok(PropertyType::Enum(EnumPropertyType {
+                id,
+                name,
+                storage_type,
+                values,
+                values_as_flags,
+            }))
+        }
+        other => Err(invalid_project(format!(
+            "property type '{}' has unknown kind {:?}",
+            name, other
+        ))),
+    }
+}
@@ -1,14 +1,20 @@
-use std::{collections::HashMap, str::FromStr};
-
-use xml::{attribute::OwnedAttribute, reader::XmlEvent};
+use std::{
+    collections::HashMap,
+    convert::TryFrom,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
 use crate::{
     error::{Error, Result},
-    util::{get_attrs, parse_tag, XmlEventResult},
+    util::{get_attrs, parse_tag, OwnedAttribute, XmlEvent, XmlParser},
+    ObjectId, Project,
 };
 
 /// Represents a RGBA color with 8-bit depth on each channel.
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub struct Color {
     pub alpha: u8,
@@ -65,20 +71,33 @@ impl FromStr for Color {
 ///
 /// Also read the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tmx-properties).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+// Class values hold properties of their own, which the derived bounds would recurse into.
+#[cfg_attr(feature = "bevy_reflect", reflect(no_field_bounds))]
 pub enum PropertyValue {
     /// A boolean value. Corresponds to the `bool` property type.
     BoolValue(bool),
     /// A floating point value. Corresponds to the `float` property type.
     FloatValue(f32),
     /// A signed integer value. Corresponds to the `int` property type.
-    IntValue(i32),
+    ///
+    /// Tiled itself only writes 32-bit values, but this holds 64 bits so that the larger values
+    /// written by some other tools and exporters can be loaded too.
+    IntValue(i64),
     /// A color value. Corresponds to the `color` property type.
     ColorValue(Color),
     /// A string value. Corresponds to the `string` property type.
     StringValue(String),
     /// A filepath value. Corresponds to the `file` property type.
-    /// Holds the path relative to the map or tileset.
-    FileValue(String),
+    FileValue {
+        /// The path as stored in the file, usually relative to the map, tileset, template or
+        /// project the property was defined in. Empty if unset.
+        relative: String,
+        /// The path resolved against the directory of the file the property was defined in,
+        /// ready to be passed to a [`ResourceReader`](crate::ResourceReader). Empty if unset.
+        resolved: PathBuf,
+    },
     /// An object ID value. Corresponds to the `object` property type.
     /// Holds the id of a referenced object, or 0 if unset.
     ObjectValue(u32),
@@ -90,10 +109,32 @@ pub enum PropertyValue {
         /// A set of properties.
         properties: Properties,
     },
+    /// An enum value. Corresponds to a `string` or `int` property whose `propertytype` is an
+    /// enum defined in the [`Project`](crate::Project) set in the [`Loader`](crate::Loader).
+    /// Without a project, these are loaded as [`PropertyValue::StringValue`] or
+    /// [`PropertyValue::IntValue`] respectively.
+    EnumValue {
+        /// The name of the enum type.
+        property_type: String,
+        /// The names of the enum values that are set. Contains exactly one value unless the enum
+        /// is declared to use its values as flags.
+        values: Vec<String>,
+        /// The value as it was stored in the file for enums stored as ints, which is the index of
+        /// the value or a bitmask of the indices of the values that are set. It is written back
+        /// instead of [`values`](PropertyValue::EnumValue::values) when saving. [`None`] for enums
+        /// stored as strings.
+        int_value: Option<i64>,
+    },
 }
 
 impl PropertyValue {
-    fn new(property_type: String, value: String) -> Result<PropertyValue> {
+    /// Parses a raw property value. File paths are resolved relative to `base_path`, the path of
+    /// the file the property was defined in.
+    pub(crate) fn new(
+        property_type: String,
+        value: String,
+        base_path: &Path,
+    ) -> Result<PropertyValue> {
         // Check the property type against the value.
         match property_type.as_str() {
             "bool" => match value.parse() {
@@ -126,25 +167,397 @@ impl PropertyValue {
                     description: err.to_string(),
                 }),
             },
-            "file" => Ok(PropertyValue::FileValue(value)),
+            "file" => {
+                let resolved = if value.is_empty() {
+                    PathBuf::new()
+                } else {
+                    base_path.parent().ok_or(Error::PathIsNotFile)?.join(&value)
+                };
+                Ok(PropertyValue::FileValue {
+                    relative: value,
+                    resolved,
+                })
+            }
             _ => Err(Error::UnknownPropertyType {
                 type_name: property_type,
             }),
         }
     }
+
+    /// Returns the object this value refers to if it is an [`ObjectValue`](Self::ObjectValue)
+    /// that is set, which can then be looked up with [`Map::object_by_id`](crate::Map::object_by_id).
+    pub fn as_object_id(&self) -> Option<ObjectId> {
+        match self {
+            PropertyValue::ObjectValue(id) if *id != 0 => Some(ObjectId(*id)),
+            _ => None,
+        }
+    }
 }
 
 /// A custom property container.
 pub type Properties = HashMap<String, PropertyValue>;
 
+/// Conversion of property values to the types they hold, used by [`PropertiesExt::get_as`].
+///
+/// Implement this to read properties directly as your own types, such as enums stored as strings.
+///
+/// ## Example
+/// ```
+/// use tiled::{FromProperty, PropertiesExt, PropertyValue};
+///
+/// #[derive(Debug, PartialEq)]
+/// enum Team {
+///     Red,
+///     Blue,
+/// }
+///
+/// impl FromProperty<'_> for Team {
+///     fn from_property(value: &PropertyValue) -> Option<Self> {
+///         match value {
+///             PropertyValue::StringValue(name) if name == "red" => Some(Team::Red),
+///             PropertyValue::StringValue(name) if name == "blue" => Some(Team::Blue),
+///             _ => None,
+///         }
+///     }
+/// }
+///
+/// let mut properties = tiled::Properties::new();
+/// properties.insert("team".to_owned(), PropertyValue::StringValue("blue".to_owned()));
+/// assert_eq!(properties.get_as::<Team>("team"), Some(Team::Blue));
+/// ```
+pub trait FromProperty<'a>: Sized {
+    /// Returns the value held by the given property value, or [`None`] if it doesn't hold one of
+    /// this type.
+    fn from_property(value: &'a PropertyValue) -> Option<Self>;
+}
+
+impl FromProperty<'_> for bool {
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::BoolValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromProperty<'_> for i64 {
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::IntValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+/// Int values are only read as `i32` if they fit in one.
+impl FromProperty<'_> for i32 {
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        i64::from_property(value).and_then(|value| i32::try_from(value).ok())
+    }
+}
+
+impl FromProperty<'_> for f32 {
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::FloatValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl FromProperty<'_> for Color {
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::ColorValue(value) => Some(*value),
+            _ => None,
+        }
+    }
+}
+
+impl<'a> FromProperty<'a> for &'a str {
+    fn from_property(value: &'a PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::StringValue(value) => Some(value),
+            _ => None,
+        }
+    }
+}
+
+impl FromProperty<'_> for String {
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        <&str>::from_property(value).map(str::to_owned)
+    }
+}
+
+/// File values are read as their resolved path; Empty if unset.
+impl<'a> FromProperty<'a> for &'a Path {
+    fn from_property(value: &'a PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::FileValue { resolved, .. } => Some(resolved),
+            _ => None,
+        }
+    }
+}
+
+/// Object values are read as the ID of the object they refer to, if they are set.
+impl FromProperty<'_> for ObjectId {
+    fn from_property(value: &PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::ObjectValue(_) => value.as_object_id(),
+            _ => None,
+        }
+    }
+}
+
+/// Class values are read as their members.
+impl<'a> FromProperty<'a> for &'a Properties {
+    fn from_property(value: &'a PropertyValue) -> Option<Self> {
+        match value {
+            PropertyValue::ClassValue { properties, .. } => Some(properties),
+            _ => None,
+        }
+    }
+}
+
+/// Typed access to the values of [`Properties`], which returns [`None`] when a property is
+/// missing or holds a value of another type.
+///
+/// ## Example
+#[cfg_attr(feature = "filesystem", doc = "```")]
+#[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{Loader, PropertiesExt};
+///
+/// let map = Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+/// let tileset = &map.tilesets()[0];
+/// let tile = tileset.get_tile(1).unwrap();
+/// assert_eq!(tile.properties.get_string("a tile property"), Some("123"));
+/// assert_eq!(tile.properties.get_int("a tile property"), None);
+/// # Ok(())
+/// # }
+/// ```
+pub trait PropertiesExt {
+    /// Returns the value of the given property converted to `T`; See [`FromProperty`].
+    fn get_as<'a, T: FromProperty<'a>>(&'a self, name: &str) -> Option<T>;
+
+    /// Returns the value of the given `bool` property.
+    fn get_bool(&self, name: &str) -> Option<bool> {
+        self.get_as(name)
+    }
+
+    /// Returns the value of the given `int` property.
+    fn get_int(&self, name: &str) -> Option<i64> {
+        self.get_as(name)
+    }
+
+    /// Returns the value of the given `float` property.
+    fn get_float(&self, name: &str) -> Option<f32> {
+        self.get_as(name)
+    }
+
+    /// Returns the value of the given `string` property.
+    fn get_string(&self, name: &str) -> Option<&str> {
+        self.get_as(name)
+    }
+
+    /// Returns the value of the given `color` property.
+    fn get_color(&self, name: &str) -> Option<Color> {
+        self.get_as(name)
+    }
+
+    /// Returns the resolved path of the given `file` property; See
+    /// [`PropertyValue::FileValue`].
+    fn get_file(&self, name: &str) -> Option<&Path> {
+        self.get_as(name)
+    }
+
+    /// Returns the object the given `object` property refers to, if it is set.
+    fn get_object(&self, name: &str) -> Option<ObjectId> {
+        self.get_as(name)
+    }
+
+    /// Returns the members of the given `class` property.
+    fn get_class(&self, name: &str) -> Option<&Properties> {
+        self.get_as(name)
+    }
+}
+
+impl PropertiesExt for Properties {
+    fn get_as<'a, T: FromProperty<'a>>(&'a self, name: &str) -> Option<T> {
+        self.get(name).and_then(T::from_property)
+    }
+}
+
+/// Conversion of a set of [`Properties`] to a type that has a field for each of them, such as the
+/// ones an object of a certain kind is expected to have.
+///
+/// With the `derive` feature enabled, this can be derived for structs with named fields, whose
+/// types implement [`FromProperty`]. Each field is read from the property of the same name, and
+/// [`Option`] fields are [`None`] when their property is missing. Fields can be annotated with
+/// `#[tiled(rename = "name")]` to read them from a property with another name, and with
+/// `#[tiled(default)]` to use their [`Default`] value when their property is missing.
+///
+/// ## Example
+/// ```
+/// # #[cfg(feature = "derive")]
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{FromProperties, PropertyValue};
+///
+/// #[derive(FromProperties)]
+/// struct Door<'a> {
+///     locked: bool,
+///     #[tiled(rename = "key id")]
+///     key_id: i32,
+///     label: Option<&'a str>,
+///     #[tiled(default)]
+///     speed: f32,
+/// }
+///
+/// let mut properties = tiled::Properties::new();
+/// properties.insert("locked".to_owned(), PropertyValue::BoolValue(true));
+/// properties.insert("key id".to_owned(), PropertyValue::IntValue(3));
+///
+/// let door = Door::from_properties(&properties)?;
+/// assert!(door.locked);
+/// assert_eq!(door.key_id, 3);
+/// assert_eq!(door.label, None);
+/// assert_eq!(door.speed, 0.0);
+/// # Ok(())
+/// # }
+/// # #[cfg(not(feature = "derive"))]
+/// # fn main() {}
+/// ```
+pub trait FromProperties<'a>: Sized {
+    /// Reads the value out of the given properties, returning [`Error::MissingProperty`] if a
+    /// required property is missing and [`Error::InvalidPropertyValue`] if one holds a value of
+    /// the wrong type.
+    fn from_properties(properties: &'a Properties) -> Result<Self>;
+}
+
+/// Support functions for the code generated by `#[derive(FromProperties)]`.
+#[doc(hidden)]
+pub mod __private {
+    use super::{FromProperty, Properties, PropertyValue};
+    use crate::{Error, Result};
+
+    pub fn required_property<'a, T: FromProperty<'a>>(
+        properties: &'a Properties,
+        name: &str,
+    ) -> Result<T> {
+        match properties.get(name) {
+            Some(value) => convert(value, name),
+            None => Err(Error::MissingProperty {
+                name: name.to_owned(),
+            }),
+        }
+    }
+
+    pub fn optional_property<'a, T: FromProperty<'a>>(
+        properties: &'a Properties,
+        name: &str,
+    ) -> Result<Option<T>> {
+        properties
+            .get(name)
+            .map(|value| convert(value, name))
+            .transpose()
+    }
+
+    fn convert<'a, T: FromProperty<'a>>(value: &'a PropertyValue, name: &str) -> Result<T> {
+        T::from_property(value).ok_or_else(|| Error::InvalidPropertyValue {
+            description: format!(
+                "property \"{}\" doesn't hold a value of type {}",
+                name,
+                std::any::type_name::<T>()
+            ),
+        })
+    }
+}
+
+/// Returns the properties in `overrides` layered over the ones in `base`, the way Tiled combines
+/// the properties an element inherits with the ones set on the element itself.
+///
+/// Class values of the same type that are in both are merged member by member, recursively, so
+/// that setting a single member of an inherited class value keeps its other members. Any other
+/// value in `overrides` replaces the one in `base`.
+///
+/// ## Example
+/// ```
+/// use std::collections::HashMap;
+/// use tiled::{merge_properties, PropertyValue};
+///
+/// let vector = |members: &[(&str, f32)]| PropertyValue::ClassValue {
+///     property_type: "Vector".to_owned(),
+///     properties: members
+///         .iter()
+///         .map(|(name, value)| (name.to_string(), PropertyValue::FloatValue(*value)))
+///         .collect(),
+/// };
+/// let base = HashMap::from([
+///     ("speed".to_owned(), PropertyValue::IntValue(1)),
+///     ("spawn".to_owned(), vector(&[("x", 1.0), ("y", 2.0)])),
+/// ]);
+/// let overrides = HashMap::from([
+///     ("speed".to_owned(), PropertyValue::IntValue(5)),
+///     ("spawn".to_owned(), vector(&[("y", 8.0)])),
+/// ]);
+///
+/// let merged = merge_properties(&base, &overrides);
+/// assert_eq!(merged["speed"], PropertyValue::IntValue(5));
+/// assert_eq!(merged["spawn"], vector(&[("x", 1.0), ("y", 8.0)]));
+/// ```
+pub fn merge_properties(base: &Properties, overrides: &Properties) -> Properties {
+    let mut merged = base.clone();
+    for (name, value) in overrides {
+        match merged.get_mut(name) {
+            Some(base) => merge_value(base, value),
+            None => {
+                merged.insert(name.clone(), value.clone());
+            }
+        }
+    }
+    merged
+}
+
+/// Layers `value` over `base`, merging class values of the same type member by member.
+fn merge_value(base: &mut PropertyValue, value: &PropertyValue) {
+    match (base, value) {
+        (
+            PropertyValue::ClassValue {
+                property_type: base_type,
+                properties: base_properties,
+            },
+            PropertyValue::ClassValue {
+                property_type,
+                properties,
+            },
+        ) if base_type == property_type => {
+            *base_properties = merge_properties(base_properties, properties);
+        }
+        (base, value) => *base = value.clone(),
+    }
+}
+
 pub(crate) fn parse_properties(
-    parser: &mut impl Iterator<Item = XmlEventResult>,
+    parser: &mut impl XmlParser,
+    project: Option<&Project>,
+) -> Result<Properties> {
+    parse_properties_inner(parser, project, true)
+}
+
+/// Parses a properties element. Class defaults are only filled in when `fill_defaults` is set,
+/// since nested class values must be filled in by their parent class, whose member defaults take
+/// priority over the nested class' own defaults.
+fn parse_properties_inner(
+    parser: &mut impl XmlParser,
+    project: Option<&Project>,
+    fill_defaults: bool,
 ) -> Result<Properties> {
     let mut p = HashMap::new();
     parse_tag!(parser, "properties", {
         "property" => |attrs:Vec<OwnedAttribute>| {
+            let position = parser.event_position();
             let (t, v_attr, k, p_t) = get_attrs!(
-                for attr in attrs {
+                for attr in attrs of ("property", position) {
                     Some("type") => obj_type = attr,
                     Some("value") => value = attr,
                     Some("propertytype") => propertytype = attr,
@@ -158,12 +571,22 @@ pub(crate) fn parse_properties(
                 // element. Only the actually set members are saved. When no members have been set
                 // the properties element is left out entirely.
                 let properties = if has_properties_tag_next(parser) {
-                    parse_properties(parser)?
+                    parser.enter_nested()?;
+                    let properties = parse_properties_inner(parser, project, false);
+                    parser.leave_nested();
+                    properties?
                 } else {
                     HashMap::new()
                 };
+                let property_type = p_t.unwrap_or_default();
+                let properties = match project {
+                    Some(project) if fill_defaults => {
+                        project.fill_class_defaults(&property_type, properties)
+                    }
+                    _ => properties,
+                };
                 p.insert(k, PropertyValue::ClassValue {
-                    property_type: p_t.unwrap_or_default(),
+                    property_type,
                     properties,
                 });
                 return Ok(());
@@ -176,21 +599,38 @@ pub(crate) fn parse_properties(
                     match parser.next() {
                         Some(Ok(XmlEvent::Characters(s))) => Ok(s),
                         Some(Err(err)) => Err(Error::XmlDecodingError(err)),
-                        None => unreachable!(), // EndDocument or error must come first
-                        _ => Err(Error::MalformedAttributes(format!("property '{}' is missing a value", k))),
+                        None => Err(Error::PrematureEnd(
+                            "XML stream ended when trying to parse a property value".to_owned(),
+                        )),
+                        _ => Err(Error::MissingAttribute {
+                            element: "property".to_owned(),
+                            attribute: "value".to_owned(),
+                            position,
+                        }),
                     }?
                 }
             };
 
-            p.insert(k, PropertyValue::new(t, v)?);
-            Ok(())
+            let value = PropertyValue::new(t, v, parser.path()).and_then(|value| {
+                match (project, p_t) {
+                    (Some(project), Some(p_t)) => project.resolve_enum(&p_t, value),
+                    _ => Ok(value),
+                }
+            });
+            match value {
+                Ok(value) => {
+                    p.insert(k, value);
+                    Ok(())
+                }
+                Err(err) => parser.recover(err),
+            }
         },
     });
     Ok(p)
 }
 
 /// Checks if there is a properties tag next in the parser. Will consume any whitespace or comments.
-fn has_properties_tag_next(parser: &mut impl Iterator<Item = XmlEventResult>) -> bool {
+fn has_properties_tag_next(parser: &mut impl XmlParser) -> bool {
     let mut peekable = parser.by_ref().peekable();
     while let Some(Ok(next)) = peekable.peek() {
         match next {
@@ -1,5 +1,6 @@
-use std::io::BufReader;
-use std::{fs::File, io::Read, path::Path};
+#[cfg(feature = "filesystem")]
+use std::{fs::File, io::BufReader};
+use std::{io::Read, path::Path, time::SystemTime};
 
 /// A trait defining types that can load data from a [`ResourcePath`](crate::ResourcePath).
 ///
@@ -27,20 +28,34 @@ use std::{fs::File, io::Read, path::Path};
 /// ```
 pub trait ResourceReader {
     /// The type of the resource that the reader provides. For example, for
-    /// [`FilesystemResourceReader`], this is defined as [`File`].
+    /// `FilesystemResourceReader`, this is defined as a buffered [`std::fs::File`].
     type Resource: Read;
     /// The type that is returned if [`read_from()`](Self::read_from()) fails. For example, for
-    /// [`FilesystemResourceReader`], this is defined as [`std::io::Error`].
+    /// `FilesystemResourceReader`, this is defined as [`std::io::Error`].
     type Error: std::error::Error + Send + Sync + 'static;
 
     /// Try to return a reader object from a path into the resources filesystem.
     fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error>;
+
+    /// Returns when the resource at the given path was last modified, if known.
+    ///
+    /// This is used by [`Loader::reload_if_changed`](crate::Loader::reload_if_changed) to detect
+    /// changed files. The default implementation returns [`None`], in which case resources are
+    /// never considered to have changed.
+    fn modified(&mut self, path: &Path) -> Option<SystemTime> {
+        let _ = path;
+        None
+    }
 }
 
 /// A [`ResourceReader`] that reads from [`File`] handles.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+///
+/// Only available with the `filesystem` feature, which is enabled by default.
+#[cfg(feature = "filesystem")]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub struct FilesystemResourceReader;
 
+#[cfg(feature = "filesystem")]
 impl FilesystemResourceReader {
     /// Creates a new [`FilesystemResourceReader`].
     pub fn new() -> Self {
@@ -48,6 +63,7 @@ impl FilesystemResourceReader {
     }
 }
 
+#[cfg(feature = "filesystem")]
 impl ResourceReader for FilesystemResourceReader {
     type Resource = BufReader<File>;
     type Error = std::io::Error;
@@ -56,6 +72,12 @@ impl ResourceReader for FilesystemResourceReader {
         let file = File::open(path)?;
         Ok(BufReader::new(file))
     }
+
+    fn modified(&mut self, path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path)
+            .and_then(|meta| meta.modified())
+            .ok()
+    }
 }
 
 impl<T, R, E> ResourceReader for T
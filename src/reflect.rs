@@ -0,0 +1,45 @@
+//! Support for Bevy's reflection through the `bevy_reflect` feature.
+
+use bevy_reflect::TypeRegistry;
+
+use crate::{
+    Color, DrawOrder, FillMode, Frame, Gid, HorizontalAlignment, Image, ImageRect, ImageSource,
+    LayerTileData, ObjectAlignment, ObjectShape, Orientation, Point, PropertyValue, Rect,
+    RenderOrder, StaggerAxis, StaggerIndex, TextData, TileRenderSize, Transformations,
+    VerticalAlignment, WangSetType, Winding,
+};
+
+/// Registers the types of this crate that implement [`Reflect`](bevy_reflect::Reflect) in the
+/// given registry, such as the one of a Bevy app, so that they can be found by name and shown in
+/// inspectors.
+///
+/// Those are the plain data types that maps are made of, like [`Color`], [`PropertyValue`] and
+/// [`ObjectShape`]. Types that refer to other parts of a map, like maps and layers themselves,
+/// don't implement [`Reflect`](bevy_reflect::Reflect).
+pub fn register_reflect_types(registry: &mut TypeRegistry) {
+    registry.register::<Color>();
+    registry.register::<DrawOrder>();
+    registry.register::<FillMode>();
+    registry.register::<Frame>();
+    registry.register::<Gid>();
+    registry.register::<HorizontalAlignment>();
+    registry.register::<Image>();
+    registry.register::<ImageRect>();
+    registry.register::<ImageSource>();
+    registry.register::<LayerTileData>();
+    registry.register::<ObjectAlignment>();
+    registry.register::<ObjectShape>();
+    registry.register::<Orientation>();
+    registry.register::<Point>();
+    registry.register::<PropertyValue>();
+    registry.register::<Rect>();
+    registry.register::<RenderOrder>();
+    registry.register::<StaggerAxis>();
+    registry.register::<StaggerIndex>();
+    registry.register::<TextData>();
+    registry.register::<TileRenderSize>();
+    registry.register::<Transformations>();
+    registry.register::<VerticalAlignment>();
+    registry.register::<WangSetType>();
+    registry.register::<Winding>();
+}
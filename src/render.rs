@@ -0,0 +1,162 @@
+//! Draw commands for the tiles of a map, for rendering them in the order Tiled does.
+
+use crate::{
+    map::StaggerParams, Color, FillMode, FlattenedLayer, Image, ImageRect, Layer, LayerTile,
+    LayerType, Map, Orientation, Rect, RenderOrder, StaggerAxis, TileId, TileLayer, TileRenderSize,
+};
+
+/// Everything needed to draw one tile of a tile layer, as returned by [`Map::draw_commands`].
+#[derive(Debug, PartialEq, Clone, Copy)]
+pub struct TileDrawCommand<'map> {
+    /// The tile layer the tile is in.
+    pub layer: Layer<'map>,
+    /// The position of the tile in the layer.
+    pub position: (i32, i32),
+    /// The index of the tile's tileset in [`Map::tilesets`].
+    pub tileset_index: usize,
+    /// The local ID of the tile within its tileset. Animations aren't applied.
+    pub id: TileId,
+    /// The image to draw from, which is the tileset's image or the tile's own one for "image
+    /// collection" tilesets.
+    pub image: &'map Image,
+    /// The region of [`Self::image`] to draw.
+    pub source: ImageRect,
+    /// Where to draw the region, in pixels. It includes the offsets of the tileset, the layer and
+    /// its parent groups, and the scaling of tilesets rendered at the map's tile size.
+    pub destination: Rect,
+    /// Whether the tile is flipped horizontally, which is done after flipping it diagonally.
+    pub flip_h: bool,
+    /// Whether the tile is flipped vertically, which is done after flipping it diagonally.
+    pub flip_v: bool,
+    /// Whether the tile is flipped diagonally, swapping its X and Y axes.
+    pub flip_d: bool,
+    /// The opacity of the layer, multiplied by the ones of its parent groups.
+    pub opacity: f32,
+    /// The tint of the layer combined with the ones of its parent groups, if any.
+    pub tint_color: Option<Color>,
+}
+
+impl Map {
+    /// Returns the commands for drawing the tiles of all of the map's visible tile layers, from
+    /// back to front, which is in order of the layers and then in the order Tiled draws tiles
+    /// within a layer; See [`FlattenedLayer::draw_commands`].
+    ///
+    /// Tiles whose tileset hasn't been [resolved](Self::resolve_tilesets) or that have no image
+    /// are left out. Requires the `render-helpers` feature.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// let map = tiled::Loader::new().load_tmx_map("assets/tiled_base64.tmx")?;
+    /// for command in map.draw_commands() {
+    ///     let (source, destination) = (command.source, command.destination);
+    ///     // Draw `source` from `command.image` at `destination` with your engine of choice.
+    /// #   let _ = (source, destination);
+    /// }
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn draw_commands(&self) -> Vec<TileDrawCommand<'_>> {
+        self.flattened_layers()
+            .filter(|layer| layer.visible)
+            .flat_map(|layer| layer.draw_commands())
+            .collect()
+    }
+}
+
+impl<'map> FlattenedLayer<'map> {
+    /// Returns the commands for drawing the tiles of this layer if it is a tile layer, in the order
+    /// Tiled draws them so that overlapping tiles are layered correctly: Row by row following the
+    /// [render order](Map::render_order) in orthogonal maps, diagonal by diagonal from the top
+    /// corner in isometric maps, and row by row in staggered and hexagonal maps, drawing the
+    /// columns that are shifted down last in each row of maps staggered along the X axis.
+    ///
+    /// Tiles are aligned to the bottom-left corner of their cell. Hidden layers are included.
+    /// Requires the `render-helpers` feature.
+    pub fn draw_commands(&self) -> Vec<TileDrawCommand<'map>> {
+        let tiles: Vec<((i32, i32), LayerTile<'map>)> = match self.layer.layer_type() {
+            LayerType::Tiles(TileLayer::Finite(layer)) => layer.tiles().collect(),
+            LayerType::Tiles(TileLayer::Infinite(layer)) => layer.tiles().collect(),
+            _ => return Vec::new(),
+        };
+        let map = self.layer.map();
+        let mut commands: Vec<TileDrawCommand<'map>> = tiles
+            .into_iter()
+            .filter_map(|(position, tile)| self.draw_command(map, position, tile))
+            .collect();
+        let key = draw_order_key(map);
+        commands.sort_by_key(|command| key(command.position));
+        commands
+    }
+
+    fn draw_command(
+        &self,
+        map: &'map Map,
+        (x, y): (i32, i32),
+        tile: LayerTile<'map>,
+    ) -> Option<TileDrawCommand<'map>> {
+        let tileset = map.tileset(tile.tileset_index())?;
+        let (image, source) = tile.get_tile()?.image_source()?;
+
+        let (source_width, source_height) = (source.width as f32, source.height as f32);
+        let (cell_width, cell_height) = (map.tile_width as f32, map.tile_height as f32);
+        let (width, height) = match (tileset.tile_render_size, tileset.fill_mode) {
+            (TileRenderSize::Tile, _) => (source_width, source_height),
+            (TileRenderSize::Grid, FillMode::Stretch) => (cell_width, cell_height),
+            (TileRenderSize::Grid, FillMode::PreserveAspectFit) => {
+                let scale = (cell_width / source_width).min(cell_height / source_height);
+                (source_width * scale, source_height * scale)
+            }
+        };
+        // Scaled tiles are centered within their cell, while others stick out of it at the top
+        // and on the right.
+        let (align_x, align_y) = match tileset.tile_render_size {
+            TileRenderSize::Tile => (0.0, cell_height - height),
+            TileRenderSize::Grid => ((cell_width - width) / 2.0, (cell_height - height) / 2.0),
+        };
+        let (cell_x, cell_y) = map.tile_to_pixel(x, y);
+
+        Some(TileDrawCommand {
+            layer: self.layer,
+            position: (x, y),
+            tileset_index: tile.tileset_index(),
+            id: tile.id(),
+            image,
+            source,
+            destination: Rect {
+                x: cell_x + align_x + tileset.offset_x as f32 + self.offset_x,
+                y: cell_y + align_y + tileset.offset_y as f32 + self.offset_y,
+                width,
+                height,
+            },
+            flip_h: tile.flip_h,
+            flip_v: tile.flip_v,
+            flip_d: tile.flip_d,
+            opacity: self.opacity,
+            tint_color: self.tint_color,
+        })
+    }
+}
+
+/// Returns a function giving keys that sort the tiles of a layer of the map by their position in
+/// the order Tiled draws them in.
+fn draw_order_key(map: &Map) -> impl Fn((i32, i32)) -> (i64, bool, i64) {
+    let (orientation, render_order) = (map.orientation, map.render_order);
+    let stagger = StaggerParams::new(map);
+    let stagger_x = map.stagger_axis == StaggerAxis::X;
+    move |(x, y)| {
+        let (wide_x, wide_y) = (x as i64, y as i64);
+        match orientation {
+            Orientation::Orthogonal => match render_order {
+                RenderOrder::RightDown => (wide_y, false, wide_x),
+                RenderOrder::RightUp => (-wide_y, false, wide_x),
+                RenderOrder::LeftDown => (wide_y, false, -wide_x),
+                RenderOrder::LeftUp => (-wide_y, false, -wide_x),
+            },
+            Orientation::Isometric => (wide_x + wide_y, false, wide_x),
+            Orientation::Staggered | Orientation::Hexagonal => {
+                (wide_y, stagger_x && stagger.is_staggered(x), wide_x)
+            }
+        }
+    }
+}
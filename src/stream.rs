@@ -0,0 +1,148 @@
+//! Lower-level access to maps while they are being parsed.
+//!
+//! [`Loader::stream_tmx_map`](crate::Loader::stream_tmx_map) parses a map like
+//! [`Loader::load_tmx_map`](crate::Loader::load_tmx_map) does, but reports what it reads as
+//! [`MapEvent`]s along the way. Top-level layers are handed over to the event handler as soon as
+//! they are parsed instead of being kept in the map, so loading screens can show progress while
+//! a map loads, and very large maps can be processed one layer at a time without holding all of
+//! them in memory.
+//!
+//! Since layers are handed over as [`OwnedLayer`]s, which need the tilesets their tiles refer to,
+//! external tilesets are always loaded right away, even with
+//! [lazy tilesets](crate::Loader::set_lazy_tilesets).
+//!
+//! ## Example
+#![cfg_attr(feature = "filesystem", doc = "```")]
+#![cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+//! # fn main() -> tiled::Result<()> {
+//! use tiled::{stream::MapEvent, Loader};
+//!
+//! let mut layer_names = Vec::new();
+//! let map = Loader::new().stream_tmx_map("assets/tiled_group_layers.tmx", |event| {
+//!     match event {
+//!         MapEvent::MapStart(header) => println!("Loading a {}x{} map", header.width, header.height),
+//!         MapEvent::TilesetLoaded { index, .. } => println!("Loaded tileset #{}", index),
+//!         MapEvent::LayerParsed(layer) => layer_names.push(layer.name.clone()),
+//!         _ => {}
+//!     }
+//!     Ok(())
+//! })?;
+//! assert_eq!(layer_names, ["tile-1", "group-1", "group-2"]);
+//! assert_eq!(map.layers().len(), 0);
+//! assert_eq!(map.tilesets().len(), 1);
+//! # Ok(())
+//! # }
+//! ```
+
+use std::sync::Arc;
+
+use crate::{Gid, LayerData, ObjectData, Orientation, OwnedLayer, Result, Tileset};
+
+/// The basic attributes of a map, which are read before any of its contents; See
+/// [`MapEvent::MapStart`].
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct MapHeader {
+    /// The TMX format version this map was saved to.
+    pub version: String,
+    /// The way tiles are laid out in the map.
+    pub orientation: Orientation,
+    /// The width of the map, in tiles.
+    pub width: u32,
+    /// The height of the map, in tiles.
+    pub height: u32,
+    /// The width of a tile, in pixels.
+    pub tile_width: u32,
+    /// The height of a tile, in pixels.
+    pub tile_height: u32,
+    /// Whether the map is infinite.
+    pub infinite: bool,
+}
+
+/// Something that was read while parsing a map with
+/// [`Loader::stream_tmx_map`](crate::Loader::stream_tmx_map), in the order it appears in the map
+/// file.
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum MapEvent<'a> {
+    /// The attributes of the map have been read. Always the first event.
+    MapStart(&'a MapHeader),
+    /// A tileset of the map has been loaded.
+    TilesetLoaded {
+        /// The index the tileset has in the map.
+        index: usize,
+        /// The first [`Gid`] of the tileset in the map.
+        first_gid: Gid,
+        /// The tileset itself.
+        tileset: &'a Arc<Tileset>,
+    },
+    /// An object has been parsed. Objects are reported once the top-level layer they are in has
+    /// been fully parsed, right before the [`MapEvent::LayerParsed`] event for it.
+    ObjectParsed(&'a ObjectData),
+    /// A top-level layer has been parsed, which is handed over instead of being kept in the map.
+    ///
+    /// The layer holds onto the tilesets declared before it in the map file, which its tiles
+    /// refer to. Tiled always writes all of a map's tilesets before its layers.
+    LayerParsed(Box<OwnedLayer>),
+}
+
+/// A function that map events are reported to.
+type MapEventHandler<'h> = &'h mut dyn FnMut(MapEvent<'_>) -> Result<()>;
+
+/// Where the events of a map being parsed go, if anywhere.
+pub(crate) struct MapEvents<'h> {
+    handler: Option<MapEventHandler<'h>>,
+}
+
+impl<'h> MapEvents<'h> {
+    /// Events for a map that is parsed as a whole, which aren't reported anywhere.
+    pub(crate) fn none() -> Self {
+        Self { handler: None }
+    }
+
+    /// Events for a map that is streamed to the given handler.
+    pub(crate) fn new(handler: MapEventHandler<'h>) -> Self {
+        Self {
+            handler: Some(handler),
+        }
+    }
+
+    /// Whether the layers of the map are handed over to a handler instead of kept in the map.
+    pub(crate) fn is_streaming(&self) -> bool {
+        self.handler.is_some()
+    }
+
+    /// Reports an event, if the map is being streamed.
+    pub(crate) fn emit(&mut self, event: MapEvent<'_>) -> Result<()> {
+        match &mut self.handler {
+            Some(handler) => handler(event),
+            None => Ok(()),
+        }
+    }
+
+    /// Hands over a top-level layer that has just been parsed, along with its objects, given the
+    /// tilesets its tiles refer to.
+    pub(crate) fn layer_parsed(
+        &mut self,
+        mut layer: LayerData,
+        tilesets: Vec<Arc<Tileset>>,
+    ) -> Result<()> {
+        #[cfg(feature = "rayon")]
+        {
+            use rayon::prelude::*;
+            let mut finite_layers = Vec::new();
+            layer.collect_finite_tile_layers(&mut finite_layers);
+            finite_layers
+                .into_par_iter()
+                .try_for_each(|data| data.decode_pending())?;
+        }
+        let mut objects = Vec::new();
+        layer.collect_objects_mut(&mut objects);
+        for object in objects {
+            self.emit(MapEvent::ObjectParsed(object))?;
+        }
+        self.emit(MapEvent::LayerParsed(Box::new(OwnedLayer::new(
+            layer,
+            tilesets.into_iter().map(Some).collect(),
+        ))))
+    }
+}
@@ -1,31 +1,49 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
-use xml::EventReader;
-use xml::{attribute::OwnedAttribute, reader::XmlEvent};
-
 use crate::{
-    util::*, EmbeddedParseResultType, Error, MapTilesetGid, ObjectData, ResourceCache,
-    ResourceReader, Result, Tileset,
+    util::*, EmbeddedParseResultType, Error, LoaderOptions, MapTilesetGid, ObjectData, ObjectId,
+    Project, ResourceCache, ResourceReader, Result, Tileset,
 };
 
 /// A template, consisting of an object and a tileset
 ///
 /// Templates define a tileset and object data to use for an object that can be shared between multiple objects and
 /// maps.
-#[derive(Clone, Debug)]
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Template {
+    /// The path of the template file, starting from the path given to load the map or tileset
+    /// that uses it. Templates are shared between the objects that use the same file.
+    pub source: PathBuf,
     /// The tileset this template contains a reference to
     pub tileset: Option<Arc<Tileset>>,
     /// The object data for this template
     pub object: ObjectData,
 }
 
+/// An object property inherited from a template which refers to another object by ID.
+///
+/// Such IDs are assigned in the template file, so they don't mean anything in the maps the template
+/// is used in; See [`Map::resolve_template_object_references`](crate::Map::resolve_template_object_references).
+#[derive(Debug, PartialEq, Eq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TemplateObjectReference {
+    /// The ID of the map object that inherited the property.
+    pub object: ObjectId,
+    /// The name of the property.
+    pub property: String,
+    /// The ID of the referenced object, as stored in the template.
+    pub target: ObjectId,
+}
+
 impl Template {
     pub(crate) fn parse_template(
         path: &Path,
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
+        options: &LoaderOptions,
     ) -> Result<Arc<Template>> {
         // Open the template file
         let file = reader
@@ -35,37 +53,39 @@ impl Template {
                 err: Box::new(err),
             })?;
 
-        let mut template_parser = EventReader::new(file);
-        loop {
-            match template_parser.next().map_err(Error::XmlDecodingError)? {
+        let mut template_parser = XmlEvents::new(file, path).with_options(options);
+        while let Some(next) = template_parser.next() {
+            match next.map_err(|err| template_parser.locate(Error::XmlDecodingError(err)))? {
                 XmlEvent::StartElement {
                     name,
                     attributes: _,
                     ..
                 } if name.local_name == "template" => {
-                    let template = Self::parse_external_template(
-                        &mut template_parser.into_iter(),
+                    let position = template_parser.event_position();
+                    return Self::parse_external_template(
+                        &mut template_parser,
                         path,
                         reader,
                         cache,
-                    )?;
-                    return Ok(template);
-                }
-                XmlEvent::EndDocument => {
-                    return Err(Error::PrematureEnd(
-                        "Template Document ended before template element was parsed".to_string(),
-                    ))
+                        project,
+                    )
+                    .map_err(|err| err.located(path, position));
                 }
+                XmlEvent::EndDocument => break,
                 _ => {}
             }
         }
+        Err(template_parser.locate(Error::PrematureEnd(
+            "Template Document ended before template element was parsed".to_string(),
+        )))
     }
 
     fn parse_external_template(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         template_path: &Path,
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
     ) -> Result<Arc<Template>> {
         let mut object = Option::None;
         let mut tileset = None;
@@ -73,27 +93,27 @@ impl Template {
 
         parse_tag!(parser, "template", {
             "object" => |attrs| {
-                object = Some(ObjectData::new(parser, attrs, Some(&tileset_gid), tileset.clone(), template_path.parent().ok_or(Error::PathIsNotFile)?, reader, cache)?);
+                object = Some(ObjectData::new(parser, attrs, Some(&tileset_gid), tileset.clone(), template_path.parent().ok_or(Error::PathIsNotFile)?, &mut LoadContext::new(reader, cache, project))?);
                 Ok(())
             },
             "tileset" => |attrs: Vec<OwnedAttribute>| {
-                let res = Tileset::parse_xml_in_map(parser, &attrs, template_path, reader, cache)?;
+                let res = Tileset::parse_xml_in_map(parser, &attrs, template_path, reader, cache, project)?;
                 match res.result_type {
                     EmbeddedParseResultType::ExternalReference { tileset_path } => {
                         tileset = Some(if let Some(ts) = cache.get_tileset(&tileset_path) {
                             ts
                         } else {
-                            let tileset = Arc::new(crate::parse::xml::parse_tileset(&tileset_path, reader, cache)?);
+                            let tileset = Arc::new(crate::parse::xml::parse_tileset(&tileset_path, reader, cache, project, parser.options())?);
                             cache.insert_tileset(tileset_path.clone(), tileset.clone());
                             tileset
                         });
                     }
                     EmbeddedParseResultType::Embedded { tileset: embedded_tileset } => {
-                        tileset = Some(Arc::new(embedded_tileset));
+                        tileset = Some(Arc::from(embedded_tileset));
                     },
                 };
                 tileset_gid.push(MapTilesetGid {
-                    tileset: tileset.clone().unwrap(),
+                    tileset: tileset.clone().unwrap().into(),
                     first_gid: res.first_gid,
                 });
                 Ok(())
@@ -102,6 +122,10 @@ impl Template {
 
         let object = object.ok_or(Error::TemplateHasNoObject)?;
 
-        Ok(Arc::new(Template { tileset, object }))
+        Ok(Arc::new(Template {
+            source: template_path.to_owned(),
+            tileset,
+            object,
+        }))
     }
 }
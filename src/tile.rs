@@ -1,25 +1,33 @@
 use std::{collections::HashMap, path::Path};
 
-use xml::attribute::OwnedAttribute;
-
 use crate::{
-    animation::{parse_animation, Frame},
+    animation::{frame_at, parse_animation, Frame},
     error::Error,
-    image::Image,
+    image::{Image, ImageRect},
     layers::ObjectLayerData,
+    objects::ObjectData,
     properties::{parse_properties, Properties},
-    util::{get_attrs, parse_tag, XmlEventResult},
-    ResourceCache, ResourceReader, Result, Tileset,
+    util::{get_attrs, parse_tag, LoadContext, OwnedAttribute, XmlParser},
+    Project, ResourceCache, ResourceReader, Result, Tileset,
 };
 
 /// A tile ID, local to a tileset.
 pub type TileId = u32;
 
 /// Raw data belonging to a tile.
-#[derive(Debug, PartialEq, Clone, Default)]
+///
+/// Also see [`TileDataBuilder`] for creating tile data in code.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct TileData {
     /// The image of the tile. Only set when the tile is part of an "image collection" tileset.
     pub image: Option<Image>,
+    /// The region of [`Self::image`] this tile uses, if it doesn't use all of it. Only set when
+    /// the tile is part of an "image collection" tileset.
+    ///
+    /// To get the region of a tile regardless of the kind of tileset it is in, use
+    /// [`Tile::image_source`].
+    pub image_rect: Option<ImageRect>,
     /// The custom properties of this tile.
     pub properties: Properties,
     /// The collision shapes of this tile.
@@ -32,22 +40,220 @@ pub struct TileData {
     pub probability: f32,
 }
 
+impl Default for TileData {
+    /// Returns the data of a tile with no image, properties, collision or animation, and with a
+    /// probability of 1, which is what tiles not listed in a tileset have.
+    fn default() -> Self {
+        Self {
+            image: None,
+            image_rect: None,
+            properties: HashMap::new(),
+            collision: None,
+            animation: None,
+            user_type: None,
+            probability: 1.0,
+        }
+    }
+}
+
+/// Builds [`TileData`] in code, such as for mocking tiles in tests, checking that it is valid.
+///
+/// ## Example
+/// ```
+/// # fn main() -> tiled::Result<()> {
+/// use tiled::{Frame, ObjectData, ObjectShape, PropertyValue, TileDataBuilder};
+///
+/// let tile = TileDataBuilder::new()
+///     .user_type("wall")
+///     .property("solid", PropertyValue::BoolValue(true))
+///     .collision_object(ObjectData::from_shape(
+///         1,
+///         ObjectShape::Rect {
+///             width: 32.0,
+///             height: 32.0,
+///         },
+///     ))
+///     .animation(vec![
+///         Frame { tile_id: 0, duration: 100 },
+///         Frame { tile_id: 1, duration: 100 },
+///     ])
+///     .build()?;
+/// assert_eq!(tile.collision.unwrap().object_data().len(), 1);
+///
+/// assert!(TileDataBuilder::new().probability(-1.0).build().is_err());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct TileDataBuilder {
+    data: TileData,
+    collision: Vec<ObjectData>,
+}
+
+impl TileDataBuilder {
+    /// Creates a builder for a tile with no image, properties, collision or animation, and with
+    /// a probability of 1.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the image of the tile, for tiles of "image collection" tilesets.
+    pub fn image(mut self, image: Image) -> Self {
+        self.data.image = Some(image);
+        self
+    }
+
+    /// Sets the region of the tile's image that it uses, if it doesn't use all of it.
+    pub fn image_rect(mut self, rect: ImageRect) -> Self {
+        self.data.image_rect = Some(rect);
+        self
+    }
+
+    /// Sets a custom property of the tile, replacing any previous value it had.
+    pub fn property(mut self, name: impl Into<String>, value: crate::PropertyValue) -> Self {
+        self.data.properties.insert(name.into(), value);
+        self
+    }
+
+    /// Adds a collision shape to the tile.
+    pub fn collision_object(mut self, object: ObjectData) -> Self {
+        self.collision.push(object);
+        self
+    }
+
+    /// Sets the animation frames of the tile.
+    pub fn animation(mut self, frames: Vec<Frame>) -> Self {
+        self.data.animation = Some(frames);
+        self
+    }
+
+    /// Sets the type of the tile.
+    pub fn user_type(mut self, user_type: impl Into<String>) -> Self {
+        self.data.user_type = Some(user_type.into());
+        self
+    }
+
+    /// Sets the probability of the tile being chosen when painting with terrains or random mode.
+    pub fn probability(mut self, probability: f32) -> Self {
+        self.data.probability = probability;
+        self
+    }
+
+    /// Builds the tile data.
+    ///
+    /// Fails if the image region is set without an image or doesn't fit in it, if the probability
+    /// is negative or not finite, if the animation has no frames or if a collision shape is a tile
+    /// object, which Tiled doesn't allow.
+    pub fn build(self) -> Result<TileData> {
+        let Self {
+            mut data,
+            collision,
+        } = self;
+        let invalid = |description: &str| {
+            Err(Error::InvalidTileData {
+                description: description.to_owned(),
+            })
+        };
+        if let Some(rect) = data.image_rect {
+            let image = match &data.image {
+                Some(image) => image,
+                None => return invalid("image region set without an image"),
+            };
+            if rect.x < 0
+                || rect.y < 0
+                || rect.width <= 0
+                || rect.height <= 0
+                || rect.x + rect.width > image.width
+                || rect.y + rect.height > image.height
+            {
+                return invalid("image region doesn't fit in the image");
+            }
+        }
+        if !(data.probability.is_finite() && data.probability >= 0.0) {
+            return invalid("probability must be finite and not negative");
+        }
+        if data.animation.as_ref().is_some_and(Vec::is_empty) {
+            return invalid("animation has no frames");
+        }
+        if collision.iter().any(|object| object.tile_data().is_some()) {
+            return invalid("collision shapes can't be tile objects");
+        }
+        if !collision.is_empty() {
+            data.collision = Some(ObjectLayerData::from_objects(collision));
+        }
+        Ok(data)
+    }
+}
+
 /// Points to a tile belonging to a tileset.
 #[derive(Debug)]
 pub struct Tile<'tileset> {
     pub(crate) tileset: &'tileset Tileset,
+    pub(crate) id: TileId,
     pub(crate) data: &'tileset TileData,
 }
 
 impl<'tileset> Tile<'tileset> {
-    pub(crate) fn new(tileset: &'tileset Tileset, data: &'tileset TileData) -> Self {
-        Self { tileset, data }
+    pub(crate) fn new(tileset: &'tileset Tileset, id: TileId, data: &'tileset TileData) -> Self {
+        Self { tileset, id, data }
     }
 
     /// Get the tileset this tile is from.
     pub fn tileset(&self) -> &'tileset Tileset {
         self.tileset
     }
+
+    /// Get the local ID of this tile within its tileset.
+    #[inline]
+    pub fn id(&self) -> TileId {
+        self.id
+    }
+
+    /// Returns the image this tile is drawn from along with the region of it that the tile uses,
+    /// so that tiles can be rendered the same way regardless of the kind of tileset they are in.
+    ///
+    /// For regular tilesets, this is the tileset's image and the tile's place in its grid, taking
+    /// the [margin](Tileset::margin) and [spacing](Tileset::spacing) into account. For "image
+    /// collection" tilesets, it is the tile's own image and its [`TileData::image_rect`], or the
+    /// whole image if it doesn't have one.
+    ///
+    /// Returns [`None`] if there is no image for this tile.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{ImageRect, Loader};
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let (image, rect) = tileset.get_tile(15).unwrap().image_source().unwrap();
+    /// assert_eq!(image.source.as_path().unwrap().file_name().unwrap(), "tilesheet.png");
+    /// assert_eq!(
+    ///     rect,
+    ///     ImageRect {
+    ///         x: 32,
+    ///         y: 32,
+    ///         width: 32,
+    ///         height: 32
+    ///     }
+    /// );
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn image_source(&self) -> Option<(&'tileset Image, ImageRect)> {
+        if let Some(image) = &self.data.image {
+            let rect = self.data.image_rect.unwrap_or(ImageRect {
+                x: 0,
+                y: 0,
+                width: image.width,
+                height: image.height,
+            });
+            return Some((image, rect));
+        }
+
+        let image = self.tileset.image.as_ref()?;
+        Some((image, self.tileset.tile_rect(self.id)?))
+    }
 }
 
 impl<'tileset> std::ops::Deref for Tile<'tileset> {
@@ -59,22 +265,42 @@ impl<'tileset> std::ops::Deref for Tile<'tileset> {
     }
 }
 
+impl TileData {
+    /// Returns the local ID of the tile displayed by this tile's animation once `elapsed_ms`
+    /// milliseconds have passed since it started, looping it as needed. Returns [`None`] if the
+    /// tile is not animated.
+    ///
+    /// To control the looping or keep track of the elapsed time, see
+    /// [`AnimationState`](crate::AnimationState).
+    pub fn frame_at(&self, elapsed_ms: u64) -> Option<TileId> {
+        self.animation
+            .as_deref()
+            .and_then(|frames| frame_at(frames, elapsed_ms, true))
+            .map(|frame| frame.tile_id)
+    }
+}
+
 impl TileData {
     pub(crate) fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
         path_relative_to: &Path,
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
     ) -> Result<(TileId, TileData)> {
-        let ((user_type, user_class, probability), id) = get_attrs!(
-            for v in attrs {
+        let ((user_type, user_class, probability), (x, y, width, height), id) = get_attrs!(
+            for v in attrs of ("tile", parser.event_position()) {
                 Some("type") => user_type ?= v.parse(),
                 Some("class") => user_class ?= v.parse(),
                 Some("probability") => probability ?= v.parse(),
+                Some("x") => x ?= v.parse::<i32>(),
+                Some("y") => y ?= v.parse::<i32>(),
+                Some("width") => width ?= v.parse::<i32>(),
+                Some("height") => height ?= v.parse::<i32>(),
                 "id" => id ?= v.parse::<u32>(),
             }
-            ((user_type, user_class, probability), id)
+            ((user_type, user_class, probability), (x, y, width, height), id)
         );
         let user_type = user_type.or(user_class);
         let mut image = Option::None;
@@ -83,17 +309,17 @@ impl TileData {
         let mut animation = None;
         parse_tag!(parser, "tile", {
             "image" => |attrs| {
-                image = Some(Image::new(parser, attrs, path_relative_to)?);
+                image = Some(Image::new(parser, attrs, path_relative_to, true)?);
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project)?;
                 Ok(())
             },
             "objectgroup" => |attrs| {
                 // Tile objects are not allowed within tile object groups, so we can pass None as the
                 // tilesets vector
-                objectgroup = Some(ObjectLayerData::new(parser, attrs, None, None, path_relative_to, reader, cache)?.0);
+                objectgroup = Some(ObjectLayerData::new(parser, attrs, None, None, path_relative_to, &mut LoadContext::new(reader, cache, project))?.0);
                 Ok(())
             },
             "animation" => |_| {
@@ -101,10 +327,22 @@ impl TileData {
                 Ok(())
             },
         });
+        // The region defaults to the whole image, in which case it isn't stored.
+        let image_rect = match (&image, x, y, width, height) {
+            (Some(_), None, None, None, None) => None,
+            (Some(image), x, y, width, height) => Some(ImageRect {
+                x: x.unwrap_or(0),
+                y: y.unwrap_or(0),
+                width: width.unwrap_or(image.width),
+                height: height.unwrap_or(image.height),
+            }),
+            (None, ..) => None,
+        };
         Ok((
             id,
             TileData {
                 image,
+                image_rect,
                 properties,
                 collision: objectgroup,
                 animation,
@@ -1,14 +1,22 @@
 use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::io::Write;
+use std::ops::RangeBounds;
 use std::path::{Path, PathBuf};
 
-use xml::attribute::OwnedAttribute;
-
-use crate::error::{Error, Result};
-use crate::image::Image;
+use crate::compat;
+use crate::error::{Error, Result, XmlPosition};
+use crate::image::{Image, ImageRect};
 use crate::properties::{parse_properties, Properties};
 use crate::tile::TileData;
-use crate::{util::*, Gid, InvalidTilesetError, ResourceCache, ResourceReader, Tile, TileId};
-
+use crate::write::write_tsx;
+use crate::{
+    util::*, ExtraXml, Gid, InvalidTilesetError, Orientation, Project, ResourceCache,
+    ResourceReader, Tile, TileId,
+};
+
+mod tile_storage;
+use tile_storage::TileStorage;
 mod wangset;
 pub use wangset::*;
 
@@ -16,6 +24,7 @@ pub use wangset::*;
 ///
 /// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#tileset).
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct Tileset {
     /// The name of the tileset, set by the user.
     pub name: String,
@@ -46,6 +55,18 @@ pub struct Tileset {
     pub offset_x: i32,
     /// The y-offset to be used when drawing tiles of this tileset.
     pub offset_y: i32,
+    /// The alignment to use for tile objects using tiles from this tileset.
+    pub object_alignment: ObjectAlignment,
+    /// The size to use when rendering tiles from this tileset on a tile layer.
+    pub tile_render_size: TileRenderSize,
+    /// How tiles are scaled when rendered at a size different from their own; Only relevant when
+    /// [`Self::tile_render_size`] is [`TileRenderSize::Grid`].
+    pub fill_mode: FillMode,
+    /// The grid used when drawing tiles of this tileset in tile object mode, if specified. Mostly
+    /// relevant for image collection tilesets meant for isometric maps.
+    pub grid: Option<TilesetGrid>,
+    /// The transformations allowed on tiles of this tileset when painting with terrain brushes.
+    pub transformations: Transformations,
 
     /// A tileset can either:
     /// * have a single spritesheet `image` in `tileset` ("regular" tileset);
@@ -57,7 +78,7 @@ pub struct Tileset {
     pub image: Option<Image>,
 
     /// All the tiles present in this tileset, indexed by their local IDs.
-    tiles: HashMap<TileId, TileData>,
+    tiles: TileStorage,
 
     /// All the wangsets present in this tileset.
     pub wang_sets: Vec<WangSet>,
@@ -67,11 +88,16 @@ pub struct Tileset {
 
     /// The custom tileset type, arbitrarily set by the user.
     pub user_type: Option<String>,
+
+    /// The attributes and child elements of the tileset element that aren't read into any other
+    /// field, if the tileset was loaded with
+    /// [`LoaderOptions::preserve_unknown_xml`](crate::LoaderOptions::preserve_unknown_xml).
+    pub extra: ExtraXml,
 }
 
 pub(crate) enum EmbeddedParseResultType {
     ExternalReference { tileset_path: PathBuf },
-    Embedded { tileset: Tileset },
+    Embedded { tileset: Box<Tileset> },
 }
 
 pub(crate) struct EmbeddedParseResult {
@@ -83,12 +109,17 @@ pub(crate) struct EmbeddedParseResult {
 struct TilesetProperties {
     spacing: Option<u32>,
     margin: Option<u32>,
-    tilecount: u32,
+    /// Only missing from legacy files; See [`compat::legacy_tile_count`].
+    tilecount: Option<u32>,
     columns: Option<u32>,
     name: String,
     user_type: Option<String>,
     tile_width: u32,
     tile_height: u32,
+    object_alignment: ObjectAlignment,
+    tile_render_size: TileRenderSize,
+    fill_mode: FillMode,
+    extra: ExtraXml,
     /// The root all non-absolute paths contained within the tileset are relative to.
     root_path: PathBuf,
 }
@@ -97,29 +128,176 @@ impl Tileset {
     /// Gets the tile with the specified ID from the tileset.
     #[inline]
     pub fn get_tile(&self, id: TileId) -> Option<Tile> {
-        self.tiles.get(&id).map(|data| Tile::new(self, data))
+        self.tiles.get(id).map(|data| Tile::new(self, id, data))
     }
 
-    /// Iterates through the tiles from this tileset.
+    /// Returns how many [GIDs](https://doc.mapeditor.org/en/latest/reference/global-tile-ids/) the
+    /// tileset takes up in a map, which is its [tile count](Self::tilecount) unless it is an image
+    /// collection with tile IDs beyond it, in which case it goes up to its highest tile ID.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// assert_eq!(tileset.gid_count(), tileset.tilecount);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn gid_count(&self) -> u32 {
+        match self.tiles.max_id() {
+            Some(max_id) => self.tilecount.max(max_id.saturating_add(1)),
+            None => self.tilecount,
+        }
+    }
+
+    /// Returns the region of the tileset's [image](Self::image) that the tile with the given ID
+    /// uses, taking the [margin](Self::margin) and [spacing](Self::spacing) into account.
+    ///
+    /// Returns [`None`] for "image collection" tilesets, which have no shared image, and for IDs
+    /// that are not below the [tile count](Self::tilecount), as well as when the region's position
+    /// or size doesn't fit in an [`i32`]. Also see
+    /// [`Tile::image_source`](crate::Tile::image_source), which handles both kinds of tilesets.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let rect = tileset.tile_rect(15).unwrap();
+    /// assert_eq!((rect.x, rect.y), (32, 32));
+    /// assert_eq!(tileset.tile_rect(84), None);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tile_rect(&self, id: TileId) -> Option<ImageRect> {
+        if self.image.is_none() || self.columns == 0 || id >= self.tilecount {
+            return None;
+        }
+        let (column, row) = (id % self.columns, id / self.columns);
+        // Computed in 64 bits, which can't overflow.
+        let offset = |index: u32, size: u32| {
+            let offset = self.margin as u64 + index as u64 * (size as u64 + self.spacing as u64);
+            i32::try_from(offset).ok()
+        };
+        Some(ImageRect {
+            x: offset(column, self.tile_width)?,
+            y: offset(row, self.tile_height)?,
+            width: i32::try_from(self.tile_width).ok()?,
+            height: i32::try_from(self.tile_height).ok()?,
+        })
+    }
+
+    /// Iterates through the tiles from this tileset, in no particular order; See
+    /// [`Self::tiles_sorted`] for iterating through them in a reproducible one.
     #[inline]
     pub fn tiles(&self) -> impl ExactSizeIterator<Item = (TileId, Tile)> {
+        self.tiles_in_range(..)
+    }
+
+    /// Iterates through the tiles from this tileset in increasing order of their IDs.
+    pub fn tiles_sorted(&self) -> impl ExactSizeIterator<Item = (TileId, Tile<'_>)> {
+        self.tiles_in_range(..)
+    }
+
+    /// Iterates through the tiles from this tileset whose IDs are within the given range, in
+    /// increasing order of their IDs.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let ids: Vec<u32> = tileset.tiles_in_range(10..14).map(|(id, _)| id).collect();
+    /// assert_eq!(ids, [10, 11, 12, 13]);
+    /// assert_eq!(tileset.tiles_in_range(84..).len(), 0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn tiles_in_range(
+        &self,
+        range: impl RangeBounds<TileId>,
+    ) -> impl ExactSizeIterator<Item = (TileId, Tile<'_>)> {
         self.tiles
-            .iter()
-            .map(move |(id, data)| (*id, Tile::new(self, data)))
+            .range(range)
+            .map(move |(id, data)| (id, Tile::new(self, id, data)))
+    }
+
+    /// Packs the images of all the tiles of this tileset into a single image, so that "image
+    /// collection" tilesets can be drawn like regular ones. Tiles without an image are left out.
+    ///
+    /// The images are read through the given reader and decoded like
+    /// [`Image::load_pixels`](crate::Image::load_pixels) does. Requires the `image-loading`
+    /// feature.
+    ///
+    /// ## Example
+    /// ```
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{FilesystemResourceReader, Loader};
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet_image_collection.tsx")?;
+    /// let atlas = tileset.pack_atlas(&mut FilesystemResourceReader::new())?;
+    ///
+    /// let rect = atlas.tile_rect(1).unwrap();
+    /// assert_eq!((rect.width, rect.height), (96, 160));
+    /// let [left, top, right, bottom] = atlas.tile_uv(1).unwrap();
+    /// assert!(0.0 <= left && left < right && right <= 1.0);
+    /// assert!(0.0 <= top && top < bottom && bottom <= 1.0);
+    /// # Ok(())
+    /// # }
+    /// ```
+    #[cfg(feature = "image-loading")]
+    pub fn pack_atlas(&self, reader: &mut impl ResourceReader) -> Result<crate::TileAtlas> {
+        crate::atlas::pack_atlas(self, reader)
+    }
+
+    /// Writes this tileset to the given writer as a TSX file, which Tiled and this crate can load
+    /// as an external tileset.
+    ///
+    /// Image and file paths are written as they are stored, which is relative to the path the
+    /// tileset was loaded from; Use [`Map::externalize_tileset`](crate::Map::externalize_tileset)
+    /// to write them relative to the file instead. Enum properties are written as the int they
+    /// were loaded with if they are stored as ints, and by name otherwise.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::Loader;
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet.tsx")?;
+    /// let mut tsx = Vec::new();
+    /// tileset.to_tsx_writer(&mut tsx)?;
+    /// assert!(String::from_utf8(tsx).unwrap().contains("<tileset version=\"1.10\""));
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn to_tsx_writer(&self, writer: impl Write) -> Result<()> {
+        write_tsx(self, writer, None)
     }
 }
 
 impl Tileset {
     pub(crate) fn parse_xml_in_map(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: &[OwnedAttribute],
         path: &Path, // Template or Map file
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
     ) -> Result<EmbeddedParseResult> {
-        Tileset::parse_xml_embedded(parser, attrs, path, reader, cache).or_else(|err| {
-            if matches!(err, Error::MalformedAttributes(_)) {
-                Tileset::parse_xml_reference(attrs, path)
+        Tileset::parse_xml_embedded(parser, attrs, path, reader, cache, project).or_else(|err| {
+            if matches!(
+                err,
+                Error::MissingAttribute { .. } | Error::InvalidAttributeValue { .. }
+            ) {
+                Tileset::parse_xml_reference(attrs, path, parser.event_position())
             } else {
                 Err(err)
             }
@@ -127,32 +305,39 @@ impl Tileset {
     }
 
     fn parse_xml_embedded(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: &[OwnedAttribute],
         path: &Path, // Template or Map file
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
     ) -> Result<EmbeddedParseResult> {
         let (
             (spacing, margin, columns, name, user_type, user_class),
+            (object_alignment, tile_render_size, fill_mode),
             (tilecount, first_gid, tile_width, tile_height),
         ) = get_attrs!(
-           for v in attrs {
+           for v in attrs of ("tileset", parser.event_position()) {
             Some("spacing") => spacing ?= v.parse(),
             Some("margin") => margin ?= v.parse(),
             Some("columns") => columns ?= v.parse(),
             Some("name") => name = v,
             Some("type") => user_type ?= v.parse(),
             Some("class") => user_class ?= v.parse(),
+            Some("objectalignment") => object_alignment ?= ObjectAlignment::parse(&v),
+            Some("tilerendersize") => tile_render_size ?= TileRenderSize::parse(&v),
+            Some("fillmode") => fill_mode ?= FillMode::parse(&v),
 
-            "tilecount" => tilecount ?= v.parse::<u32>(),
+            Some("tilecount") => tilecount ?= v.parse::<u32>(),
             "firstgid" => first_gid ?= v.parse::<u32>().map(Gid),
             "tilewidth" => tile_width ?= v.parse::<u32>(),
             "tileheight" => tile_height ?= v.parse::<u32>(),
            }
-           ((spacing, margin, columns, name, user_type, user_class), (tilecount, first_gid, tile_width, tile_height))
+           ((spacing, margin, columns, name, user_type, user_class), (object_alignment, tile_render_size, fill_mode), (tilecount, first_gid, tile_width, tile_height))
         );
 
+        let tilecount = compat::required_unless_legacy(parser, tilecount, "tileset", "tilecount")?;
+        let extra = ExtraXml::from_attributes(parser, attrs, TILESET_ATTRIBUTES);
         let root_path = path.parent().ok_or(Error::PathIsNotFile)?.to_owned();
 
         Self::finish_parsing_xml(
@@ -167,22 +352,30 @@ impl Tileset {
                 tilecount,
                 tile_height,
                 tile_width,
+                object_alignment: object_alignment.unwrap_or_default(),
+                tile_render_size: tile_render_size.unwrap_or_default(),
+                fill_mode: fill_mode.unwrap_or_default(),
+                extra,
             },
             reader,
             cache,
+            project,
         )
         .map(|tileset| EmbeddedParseResult {
             first_gid,
-            result_type: EmbeddedParseResultType::Embedded { tileset },
+            result_type: EmbeddedParseResultType::Embedded {
+                tileset: Box::new(tileset),
+            },
         })
     }
 
     fn parse_xml_reference(
         attrs: &[OwnedAttribute],
         map_path: &Path,
+        position: XmlPosition,
     ) -> Result<EmbeddedParseResult> {
         let (first_gid, source) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("tileset", position) {
                 "firstgid" => first_gid ?= v.parse::<u32>().map(Gid),
                 "source" => source = v,
             }
@@ -198,31 +391,38 @@ impl Tileset {
     }
 
     pub(crate) fn parse_external_tileset(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: &[OwnedAttribute],
         path: &Path,
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
     ) -> Result<Tileset> {
         let (
             (spacing, margin, columns, name, user_type, user_class),
+            (object_alignment, tile_render_size, fill_mode),
             (tilecount, tile_width, tile_height),
         ) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("tileset", parser.event_position()) {
                 Some("spacing") => spacing ?= v.parse(),
                 Some("margin") => margin ?= v.parse(),
                 Some("columns") => columns ?= v.parse(),
                 Some("name") => name = v,
                 Some("type") => user_type ?= v.parse(),
                 Some("class") => user_class ?= v.parse(),
+                Some("objectalignment") => object_alignment ?= ObjectAlignment::parse(&v),
+                Some("tilerendersize") => tile_render_size ?= TileRenderSize::parse(&v),
+                Some("fillmode") => fill_mode ?= FillMode::parse(&v),
 
-                "tilecount" => tilecount ?= v.parse::<u32>(),
+                Some("tilecount") => tilecount ?= v.parse::<u32>(),
                 "tilewidth" => tile_width ?= v.parse::<u32>(),
                 "tileheight" => tile_height ?= v.parse::<u32>(),
             }
-            ((spacing, margin, columns, name, user_type, user_class), (tilecount, tile_width, tile_height))
+            ((spacing, margin, columns, name, user_type, user_class), (object_alignment, tile_render_size, fill_mode), (tilecount, tile_width, tile_height))
         );
 
+        let tilecount = compat::required_unless_legacy(parser, tilecount, "tileset", "tilecount")?;
+        let extra = ExtraXml::from_attributes(parser, attrs, TILESET_ATTRIBUTES);
         let root_path = path.parent().ok_or(Error::PathIsNotFile)?.to_owned();
 
         Self::finish_parsing_xml(
@@ -237,66 +437,91 @@ impl Tileset {
                 tilecount,
                 tile_height,
                 tile_width,
+                object_alignment: object_alignment.unwrap_or_default(),
+                tile_render_size: tile_render_size.unwrap_or_default(),
+                fill_mode: fill_mode.unwrap_or_default(),
+                extra,
             },
             reader,
             cache,
+            project,
         )
     }
 
     fn finish_parsing_xml(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
-        prop: TilesetProperties,
+        parser: &mut impl XmlParser,
+        mut prop: TilesetProperties,
         reader: &mut impl ResourceReader,
         cache: &mut impl ResourceCache,
+        project: Option<&Project>,
     ) -> Result<Tileset> {
         let mut image = Option::None;
-        let mut tiles = HashMap::with_capacity(prop.tilecount as usize);
+        let mut tiles = HashMap::with_capacity(prop.tilecount.unwrap_or(0) as usize);
         let mut properties = HashMap::new();
         let mut wang_sets = Vec::new();
         let mut offset = (0i32, 0i32);
+        let mut grid = None;
+        let mut transformations = Transformations::default();
+        let mut extra = std::mem::take(&mut prop.extra);
 
         parse_tag!(parser, "tileset", {
             "image" => |attrs| {
-                image = Some(Image::new(parser, attrs, &prop.root_path)?);
+                image = Some(Image::new(parser, attrs, &prop.root_path, true)?);
                 Ok(())
             },
             "tileoffset" => |attrs| {
-                offset = parse_tileoffset(attrs)?;
+                offset = parse_tileoffset(attrs, parser.event_position())?;
+                Ok(())
+            },
+            "grid" => |attrs| {
+                grid = Some(TilesetGrid::new(attrs, parser.event_position())?);
+                Ok(())
+            },
+            "transformations" => |attrs| {
+                transformations = Transformations::new(attrs, parser.event_position());
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project)?;
                 Ok(())
             },
             "tile" => |attrs| {
-                let (id, tile) = TileData::new(parser, attrs, &prop.root_path, reader, cache)?;
+                let (id, tile) = TileData::new(parser, attrs, &prop.root_path, reader, cache, project)?;
                 tiles.insert(id, tile);
                 Ok(())
             },
             "wangset" => |attrs| {
-                let set = WangSet::new(parser, attrs)?;
+                let set = WangSet::new(parser, attrs, project)?;
                 wang_sets.push(set);
                 Ok(())
             },
-        });
+        } else |name, attrs| extra.read_element(parser, name, attrs));
 
         // A tileset is considered an image collection tileset if there is no image attribute (because its tiles do).
         let is_image_collection_tileset = image.is_none();
 
-        if !is_image_collection_tileset {
-            if prop.tile_width == 0 || prop.tile_height == 0 {
-                return Err(Error::InvalidTileset(
-                    InvalidTilesetError::InvalidTileDimensions,
-                ));
-            }
-
-            for tile_id in 0..prop.tilecount {
-                tiles.entry(tile_id).or_default();
-            }
+        if !is_image_collection_tileset && (prop.tile_width == 0 || prop.tile_height == 0) {
+            return Err(Error::InvalidTileset(
+                InvalidTilesetError::InvalidTileDimensions,
+            ));
         }
 
         let margin = prop.margin.unwrap_or(0);
         let spacing = prop.spacing.unwrap_or(0);
+        let tilecount = prop.tilecount.unwrap_or_else(|| {
+            compat::legacy_tile_count(
+                image.as_ref().map(|image| (image.width, image.height)),
+                (prop.tile_width, prop.tile_height),
+                margin,
+                spacing,
+                tiles.len(),
+            )
+        });
+        if !is_image_collection_tileset {
+            for tile_id in 0..tilecount {
+                tiles.entry(tile_id).or_default();
+            }
+        }
         let columns = prop
             .columns
             .map(Ok)
@@ -312,11 +537,17 @@ impl Tileset {
             columns,
             offset_x: offset.0,
             offset_y: offset.1,
-            tilecount: prop.tilecount,
+            object_alignment: prop.object_alignment,
+            tile_render_size: prop.tile_render_size,
+            fill_mode: prop.fill_mode,
+            grid,
+            transformations,
+            tilecount,
             image,
-            tiles,
+            tiles: TileStorage::new(tiles),
             wang_sets,
             properties,
+            extra,
         })
     }
 
@@ -337,13 +568,231 @@ impl Tileset {
     }
 }
 
+/// The attributes of the tileset element that are read by the tileset parsers, along with the ones
+/// describing the file rather than the tileset.
+const TILESET_ATTRIBUTES: &[&str] = &[
+    "version",
+    "tiledversion",
+    "firstgid",
+    "source",
+    "name",
+    "type",
+    "class",
+    "tilewidth",
+    "tileheight",
+    "spacing",
+    "margin",
+    "tilecount",
+    "columns",
+    "objectalignment",
+    "tilerendersize",
+    "fillmode",
+];
+
 /// Parse the optional <tileoffset x=... y=.../> tag.
-fn parse_tileoffset(attrs: Vec<OwnedAttribute>) -> Result<(i32, i32)> {
+fn parse_tileoffset(attrs: Vec<OwnedAttribute>, position: XmlPosition) -> Result<(i32, i32)> {
     Ok(get_attrs!(
-        for v in attrs {
+        for v in attrs of ("tileoffset", position) {
             "x" => offset_x ?= v.parse::<i32>(),
             "y" => offset_y ?= v.parse::<i32>(),
         }
         (offset_x, offset_y)
     ))
 }
+
+/// The grid settings of a tileset, used to snap and draw its tiles in tile object mode.
+///
+/// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#grid).
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TilesetGrid {
+    /// The orientation of the grid; Either [`Orientation::Orthogonal`] or
+    /// [`Orientation::Isometric`].
+    pub orientation: Orientation,
+    /// The width of a grid cell, in pixels.
+    pub width: u32,
+    /// The height of a grid cell, in pixels.
+    pub height: u32,
+}
+
+impl TilesetGrid {
+    fn new(attrs: Vec<OwnedAttribute>, position: XmlPosition) -> Result<Self> {
+        let (orientation, width, height) = get_attrs!(
+            for v in attrs of ("grid", position) {
+                Some("orientation") => orientation ?= v.parse::<Orientation>(),
+                "width" => width ?= v.parse::<u32>(),
+                "height" => height ?= v.parse::<u32>(),
+            }
+            (orientation, width, height)
+        );
+
+        Ok(Self {
+            orientation: orientation.unwrap_or(Orientation::Orthogonal),
+            width,
+            height,
+        })
+    }
+}
+
+/// The ways in which the tiles of a tileset may be transformed by Tiled's terrain brushes and the
+/// random mode of the stamp brush.
+///
+/// Also see the [TMX docs](https://doc.mapeditor.org/en/stable/reference/tmx-map-format/#transformations).
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub struct Transformations {
+    /// Whether tiles can be flipped horizontally.
+    pub hflip: bool,
+    /// Whether tiles can be flipped vertically.
+    pub vflip: bool,
+    /// Whether tiles can be rotated in 90-degree increments.
+    pub rotate: bool,
+    /// Whether untransformed tiles remain preferred, otherwise transformed tiles are used to
+    /// produce more variations.
+    pub prefer_untransformed: bool,
+}
+
+impl Transformations {
+    fn new(attrs: Vec<OwnedAttribute>, position: XmlPosition) -> Self {
+        let (hflip, vflip, rotate, prefer_untransformed) = get_attrs!(
+            for v in attrs of ("transformations", position) {
+                Some("hflip") => hflip = v == "1",
+                Some("vflip") => vflip = v == "1",
+                Some("rotate") => rotate = v == "1",
+                Some("preferuntransformed") => prefer_untransformed = v == "1",
+            }
+            (hflip, vflip, rotate, prefer_untransformed)
+        );
+
+        Self {
+            hflip: hflip.unwrap_or(false),
+            vflip: vflip.unwrap_or(false),
+            rotate: rotate.unwrap_or(false),
+            prefer_untransformed: prefer_untransformed.unwrap_or(false),
+        }
+    }
+}
+
+/// The alignment of tile objects relative to their position.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+#[allow(missing_docs)]
+pub enum ObjectAlignment {
+    /// No explicit alignment: Bottom-left for orthogonal maps, bottom for isometric maps.
+    #[default]
+    Unspecified,
+    TopLeft,
+    Top,
+    TopRight,
+    Left,
+    Center,
+    Right,
+    BottomLeft,
+    Bottom,
+    BottomRight,
+}
+
+impl ObjectAlignment {
+    /// The name used for this value in the `objectalignment` attribute.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            ObjectAlignment::Unspecified => "unspecified",
+            ObjectAlignment::TopLeft => "topleft",
+            ObjectAlignment::Top => "top",
+            ObjectAlignment::TopRight => "topright",
+            ObjectAlignment::Left => "left",
+            ObjectAlignment::Center => "center",
+            ObjectAlignment::Right => "right",
+            ObjectAlignment::BottomLeft => "bottomleft",
+            ObjectAlignment::Bottom => "bottom",
+            ObjectAlignment::BottomRight => "bottomright",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "unspecified" => Ok(ObjectAlignment::Unspecified),
+            "topleft" => Ok(ObjectAlignment::TopLeft),
+            "top" => Ok(ObjectAlignment::Top),
+            "topright" => Ok(ObjectAlignment::TopRight),
+            "left" => Ok(ObjectAlignment::Left),
+            "center" => Ok(ObjectAlignment::Center),
+            "right" => Ok(ObjectAlignment::Right),
+            "bottomleft" => Ok(ObjectAlignment::BottomLeft),
+            "bottom" => Ok(ObjectAlignment::Bottom),
+            "bottomright" => Ok(ObjectAlignment::BottomRight),
+            _ => Err(Error::MalformedAttributes(format!(
+                "`objectalignment` attribute contained an invalid value '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// The size at which the tiles of a tileset are rendered on tile layers.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub enum TileRenderSize {
+    /// Tiles are rendered at their own size.
+    #[default]
+    Tile,
+    /// Tiles are rendered at the map's tile size, scaled according to the tileset's [`FillMode`].
+    Grid,
+}
+
+impl TileRenderSize {
+    /// The name used for this value in the `tilerendersize` attribute.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            TileRenderSize::Tile => "tile",
+            TileRenderSize::Grid => "grid",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "tile" => Ok(TileRenderSize::Tile),
+            "grid" => Ok(TileRenderSize::Grid),
+            _ => Err(Error::MalformedAttributes(format!(
+                "`tilerendersize` attribute contained an invalid value '{}'",
+                s
+            ))),
+        }
+    }
+}
+
+/// How tiles are scaled when rendered at a size different from their own.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
+pub enum FillMode {
+    /// Tiles are stretched to fill the target size.
+    #[default]
+    Stretch,
+    /// Tiles are scaled as much as possible while keeping their aspect ratio, and centered.
+    PreserveAspectFit,
+}
+
+impl FillMode {
+    /// The name used for this value in the `fillmode` attribute.
+    pub(crate) fn name(&self) -> &'static str {
+        match self {
+            FillMode::Stretch => "stretch",
+            FillMode::PreserveAspectFit => "preserve-aspect-fit",
+        }
+    }
+
+    fn parse(s: &str) -> Result<Self> {
+        match s {
+            "stretch" => Ok(FillMode::Stretch),
+            "preserve-aspect-fit" => Ok(FillMode::PreserveAspectFit),
+            _ => Err(Error::MalformedAttributes(format!(
+                "`fillmode` attribute contained an invalid value '{}'",
+                s
+            ))),
+        }
+    }
+}
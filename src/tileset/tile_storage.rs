@@ -0,0 +1,168 @@
+use std::{
+    collections::HashMap,
+    ops::{Bound, RangeBounds},
+};
+
+use crate::{TileData, TileId};
+
+/// The tiles of a tileset, stored so that looking them up by ID is as fast as it can be.
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub(crate) enum TileStorage {
+    /// Tiles whose IDs mostly go from 0 up, like the ones of regular tilesets, stored at the index
+    /// of their ID.
+    Dense {
+        tiles: Vec<Option<TileData>>,
+        /// The number of tiles present.
+        len: usize,
+    },
+    /// Tiles with scattered IDs, which image collections can have.
+    Sparse {
+        tiles: HashMap<TileId, TileData>,
+        /// The IDs of the tiles, sorted.
+        ids: Vec<TileId>,
+    },
+}
+
+impl TileStorage {
+    /// Stores the given tiles densely if at least half of the IDs up to the highest one are used.
+    pub(crate) fn new(tiles: HashMap<TileId, TileData>) -> Self {
+        let len = tiles.len();
+        match tiles.keys().max() {
+            Some(&max) if (max as u64) < len as u64 * 2 => {
+                let mut dense: Vec<Option<TileData>> = (0..=max).map(|_| None).collect();
+                for (id, tile) in tiles {
+                    dense[id as usize] = Some(tile);
+                }
+                TileStorage::Dense { tiles: dense, len }
+            }
+            Some(_) => {
+                let mut ids: Vec<TileId> = tiles.keys().copied().collect();
+                ids.sort_unstable();
+                TileStorage::Sparse { tiles, ids }
+            }
+            None => TileStorage::Dense {
+                tiles: Vec::new(),
+                len: 0,
+            },
+        }
+    }
+
+    /// Returns the highest ID of the tiles, if there are any.
+    pub(crate) fn max_id(&self) -> Option<TileId> {
+        match self {
+            // Dense storage ends at the highest ID.
+            TileStorage::Dense { tiles, .. } => tiles.len().checked_sub(1).map(|id| id as TileId),
+            TileStorage::Sparse { ids, .. } => ids.last().copied(),
+        }
+    }
+
+    #[inline]
+    pub(crate) fn get(&self, id: TileId) -> Option<&TileData> {
+        match self {
+            TileStorage::Dense { tiles, .. } => tiles.get(id as usize)?.as_ref(),
+            TileStorage::Sparse { tiles, .. } => tiles.get(&id),
+        }
+    }
+
+    /// Iterates through the tiles whose IDs are within the given range, in order of their IDs.
+    pub(crate) fn range(&self, range: impl RangeBounds<TileId>) -> TileStorageIter<'_> {
+        match self {
+            TileStorage::Dense { tiles, len } => {
+                let start = match range.start_bound() {
+                    Bound::Included(&id) => id as usize,
+                    Bound::Excluded(&id) => id as usize + 1,
+                    Bound::Unbounded => 0,
+                };
+                let end = match range.end_bound() {
+                    Bound::Included(&id) => id as usize + 1,
+                    Bound::Excluded(&id) => id as usize,
+                    Bound::Unbounded => tiles.len(),
+                }
+                .min(tiles.len());
+                let start = start.min(end);
+                let remaining = if (start, end) == (0, tiles.len()) {
+                    *len
+                } else {
+                    tiles[start..end]
+                        .iter()
+                        .filter(|tile| tile.is_some())
+                        .count()
+                };
+                TileStorageIter::Dense {
+                    tiles,
+                    next: start,
+                    end,
+                    remaining,
+                }
+            }
+            TileStorage::Sparse { tiles, ids } => {
+                let start = match range.start_bound() {
+                    Bound::Included(&id) => ids.partition_point(|&other| other < id),
+                    Bound::Excluded(&id) => ids.partition_point(|&other| other <= id),
+                    Bound::Unbounded => 0,
+                };
+                let end = match range.end_bound() {
+                    Bound::Included(&id) => ids.partition_point(|&other| other <= id),
+                    Bound::Excluded(&id) => ids.partition_point(|&other| other < id),
+                    Bound::Unbounded => ids.len(),
+                };
+                TileStorageIter::Sparse {
+                    tiles,
+                    ids: ids[start..end.max(start)].iter(),
+                }
+            }
+        }
+    }
+}
+
+/// Iterates through tiles of a [`TileStorage`] in order of their IDs; See [`TileStorage::range`].
+pub(crate) enum TileStorageIter<'a> {
+    Dense {
+        tiles: &'a [Option<TileData>],
+        next: usize,
+        end: usize,
+        /// The number of tiles left before `end`.
+        remaining: usize,
+    },
+    Sparse {
+        tiles: &'a HashMap<TileId, TileData>,
+        ids: std::slice::Iter<'a, TileId>,
+    },
+}
+
+impl<'a> Iterator for TileStorageIter<'a> {
+    type Item = (TileId, &'a TileData);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self {
+            TileStorageIter::Dense {
+                tiles,
+                next,
+                end,
+                remaining,
+            } => {
+                while *next < *end {
+                    let id = *next;
+                    *next += 1;
+                    if let Some(tile) = &tiles[id] {
+                        *remaining -= 1;
+                        return Some((id as TileId, tile));
+                    }
+                }
+                None
+            }
+            TileStorageIter::Sparse { tiles, ids } => ids.next().map(|&id| (id, &tiles[&id])),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = match self {
+            TileStorageIter::Dense { remaining, .. } => *remaining,
+            TileStorageIter::Sparse { ids, .. } => ids.len(),
+        };
+        (len, Some(len))
+    }
+}
+
+impl ExactSizeIterator for TileStorageIter<'_> {}
@@ -1,12 +1,10 @@
 use std::collections::HashMap;
 
-use xml::attribute::OwnedAttribute;
-
 use crate::{
     error::Error,
     properties::{parse_properties, Properties},
-    util::{get_attrs, parse_tag, XmlEventResult},
-    Result, TileId,
+    util::{get_attrs, parse_tag, OwnedAttribute, XmlParser},
+    Project, Result, TileId,
 };
 
 mod wang_color;
@@ -15,22 +13,20 @@ mod wang_tile;
 pub use wang_tile::*;
 
 /// Wang set's terrain brush connection type.
-#[derive(Debug, PartialEq, Clone, Copy)]
+#[derive(Debug, PartialEq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "bevy_reflect", derive(bevy_reflect::Reflect))]
 #[allow(missing_docs)]
 pub enum WangSetType {
     Corner,
     Edge,
+    #[default]
     Mixed,
 }
 
-impl Default for WangSetType {
-    fn default() -> Self {
-        WangSetType::Mixed
-    }
-}
-
 /// Raw data belonging to a WangSet.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangSet {
     /// The name of the Wang set.
     pub name: String,
@@ -44,22 +40,62 @@ pub struct WangSet {
     pub wang_tiles: HashMap<TileId, WangTile>,
     /// The custom properties of this Wang set.
     pub properties: Properties,
+    /// The class of this Wang set, which is arbitrary and set by the user.
+    pub user_type: Option<String>,
+}
+
+impl WangSet {
+    /// Returns the [`WangId`] of the tile with the given ID, if it is part of this Wang set.
+    #[inline]
+    pub fn wang_id_of(&self, tile: TileId) -> Option<WangId> {
+        self.wang_tiles.get(&tile).map(|tile| tile.wang_id)
+    }
+
+    /// Returns the IDs of the tiles in this Wang set whose [`WangId`] matches the given pattern,
+    /// in ascending order.
+    ///
+    /// ## Example
+    #[cfg_attr(feature = "filesystem", doc = "```")]
+    #[cfg_attr(not(feature = "filesystem"), doc = "```ignore")]
+    /// # fn main() -> tiled::Result<()> {
+    /// use tiled::{Loader, WangIdPattern};
+    ///
+    /// let tileset = Loader::new().load_tsx_tileset("assets/tilesheet_wangsets.tsx")?;
+    /// let wang_set = &tileset.wang_sets[0];
+    /// // Tiles that have the first color all around.
+    /// let filled = wang_set.find_tiles(WangIdPattern([Some(1); 8]));
+    /// assert_eq!(filled, [16, 17]);
+    /// # Ok(())
+    /// # }
+    /// ```
+    pub fn find_tiles(&self, pattern: WangIdPattern) -> Vec<TileId> {
+        let mut tiles: Vec<TileId> = self
+            .wang_tiles
+            .iter()
+            .filter(|(_, tile)| pattern.matches(tile.wang_id))
+            .map(|(id, _)| *id)
+            .collect();
+        tiles.sort_unstable();
+        tiles
+    }
 }
 
 impl WangSet {
     /// Reads data from XML parser to create a WangSet.
-    pub fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+    pub(crate) fn new(
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
+        project: Option<&Project>,
     ) -> Result<WangSet> {
         // Get common data
-        let (name, wang_set_type, tile) = get_attrs!(
-            for v in attrs {
+        let (user_type, name, wang_set_type, tile) = get_attrs!(
+            for v in attrs of ("wangset", parser.event_position()) {
+                Some("class") => user_type = v,
                 "name" => name ?= v.parse::<String>(),
                 "type" => wang_set_type ?= v.parse::<String>(),
                 "tile" => tile ?= v.parse::<i64>(),
             }
-            (name, wang_set_type, tile)
+            (user_type, name, wang_set_type, tile)
         );
 
         let wang_set_type = match wang_set_type.as_str() {
@@ -75,7 +111,7 @@ impl WangSet {
         let mut properties = HashMap::new();
         parse_tag!(parser, "wangset", {
             "wangcolor" => |attrs| {
-                let color = WangColor::new(parser, attrs)?;
+                let color = WangColor::new(parser, attrs, project)?;
                 wang_colors.push(color);
                 Ok(())
             },
@@ -85,7 +121,7 @@ impl WangSet {
                 Ok(())
             },
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project)?;
                 Ok(())
             },
         });
@@ -97,6 +133,7 @@ impl WangSet {
             wang_colors,
             wang_tiles,
             properties,
+            user_type,
         })
     }
 }
@@ -1,16 +1,15 @@
 use std::collections::HashMap;
 
-use xml::attribute::OwnedAttribute;
-
 use crate::{
     error::Error,
     properties::{parse_properties, Color, Properties},
-    util::{get_attrs, parse_tag, XmlEventResult},
-    Result, TileId,
+    util::{get_attrs, parse_tag, OwnedAttribute, XmlParser},
+    Project, Result, TileId,
 };
 
 /// Stores the data of the Wang color.
 #[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangColor {
     /// The name of this color.
     pub name: String,
@@ -22,23 +21,27 @@ pub struct WangColor {
     pub probability: f32,
     /// The custom properties of this color.
     pub properties: Properties,
+    /// The class of this color, which is arbitrary and set by the user.
+    pub user_type: Option<String>,
 }
 
 impl WangColor {
     /// Reads data from XML parser to create a WangColor.
-    pub fn new(
-        parser: &mut impl Iterator<Item = XmlEventResult>,
+    pub(crate) fn new(
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
+        project: Option<&Project>,
     ) -> Result<WangColor> {
         // Get common data
-        let (name, color, tile, probability) = get_attrs!(
-            for v in attrs {
+        let (user_type, name, color, tile, probability) = get_attrs!(
+            for v in attrs of ("wangcolor", parser.event_position()) {
+                Some("class") => user_type = v,
                 "name" => name ?= v.parse::<String>(),
                 "color" => color ?= v.parse(),
                 "tile" => tile ?= v.parse::<i64>(),
                 "probability" => probability ?= v.parse::<f32>(),
             }
-            (name, color, tile, probability)
+            (user_type, name, color, tile, probability)
         );
 
         let tile = if tile >= 0 { Some(tile as u32) } else { None };
@@ -47,7 +50,7 @@ impl WangColor {
         let mut properties = HashMap::new();
         parse_tag!(parser, "wangcolor", {
             "properties" => |_| {
-                properties = parse_properties(parser)?;
+                properties = parse_properties(parser, project)?;
                 Ok(())
             },
         });
@@ -58,6 +61,7 @@ impl WangColor {
             tile,
             probability,
             properties,
+            user_type,
         })
     }
 }
@@ -1,17 +1,174 @@
 use std::str::FromStr;
 
-use xml::attribute::OwnedAttribute;
-
 use crate::{
     error::Error,
-    util::{get_attrs, XmlEventResult},
+    util::{get_attrs, OwnedAttribute, XmlParser},
     Result, TileId,
 };
 
 /// The Wang ID, stored as an array of 8 u8 values.
-#[derive(Debug, PartialEq, Clone, Copy)]
+///
+/// The values are the [`WangColor`](crate::WangColor) indices (starting from 1, with 0 meaning
+/// unset) of the edges and corners of a tile, clockwise from the top edge: Top, top-right, right,
+/// bottom-right, bottom, bottom-left, left and top-left.
+#[derive(Debug, PartialEq, Eq, Hash, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangId(pub [u8; 8]);
 
+impl WangId {
+    /// Returns the color of an edge, with the edges indexed clockwise from the top one: Top,
+    /// right, bottom and left.
+    ///
+    /// ## Panics
+    /// Panics if `index` is 4 or more.
+    #[inline]
+    pub fn edge(&self, index: usize) -> u8 {
+        assert!(index < 4, "edge index out of range: {}", index);
+        self.0[index * 2]
+    }
+
+    /// Returns the color of a corner, with the corners indexed clockwise from the top-right one:
+    /// Top-right, bottom-right, bottom-left and top-left.
+    ///
+    /// ## Panics
+    /// Panics if `index` is 4 or more.
+    #[inline]
+    pub fn corner(&self, index: usize) -> u8 {
+        assert!(index < 4, "corner index out of range: {}", index);
+        self.0[index * 2 + 1]
+    }
+
+    /// Returns an iterator over the colors of the edges, in the same order as [`WangId::edge`].
+    #[inline]
+    pub fn iter_edges(&self) -> impl ExactSizeIterator<Item = u8> {
+        let id = self.0;
+        (0..4).map(move |i| id[i * 2])
+    }
+
+    /// Returns an iterator over the colors of the corners, in the same order as
+    /// [`WangId::corner`].
+    #[inline]
+    pub fn iter_corners(&self) -> impl ExactSizeIterator<Item = u8> {
+        let id = self.0;
+        (0..4).map(move |i| id[i * 2 + 1])
+    }
+
+    /// Returns the color of the top edge.
+    #[inline]
+    pub fn top(&self) -> u8 {
+        self.0[0]
+    }
+
+    /// Returns the color of the top-right corner.
+    #[inline]
+    pub fn top_right(&self) -> u8 {
+        self.0[1]
+    }
+
+    /// Returns the color of the right edge.
+    #[inline]
+    pub fn right(&self) -> u8 {
+        self.0[2]
+    }
+
+    /// Returns the color of the bottom-right corner.
+    #[inline]
+    pub fn bottom_right(&self) -> u8 {
+        self.0[3]
+    }
+
+    /// Returns the color of the bottom edge.
+    #[inline]
+    pub fn bottom(&self) -> u8 {
+        self.0[4]
+    }
+
+    /// Returns the color of the bottom-left corner.
+    #[inline]
+    pub fn bottom_left(&self) -> u8 {
+        self.0[5]
+    }
+
+    /// Returns the color of the left edge.
+    #[inline]
+    pub fn left(&self) -> u8 {
+        self.0[6]
+    }
+
+    /// Returns the color of the top-left corner.
+    #[inline]
+    pub fn top_left(&self) -> u8 {
+        self.0[7]
+    }
+}
+
+impl From<[u8; 8]> for WangId {
+    fn from(colors: [u8; 8]) -> Self {
+        WangId(colors)
+    }
+}
+
+/// A pattern that [`WangId`]s can be matched against, in which each of the edges and corners can
+/// either require a specific color or match any of them; Also see
+/// [`WangSet::find_tiles`](crate::WangSet::find_tiles).
+///
+/// The values are in the same order as in [`WangId`], with [`None`] acting as a wildcard.
+///
+/// ## Example
+/// ```
+/// use tiled::{WangId, WangIdPattern};
+///
+/// // Tiles whose top edge and top-right corner have the first color.
+/// let pattern = WangIdPattern::ANY.with_edge(0, 1).with_corner(0, 1);
+/// assert!(pattern.matches(WangId([1, 1, 2, 2, 2, 2, 2, 1])));
+/// assert!(!pattern.matches(WangId([1, 2, 2, 2, 2, 2, 2, 1])));
+/// ```
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Default)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct WangIdPattern(pub [Option<u8>; 8]);
+
+impl WangIdPattern {
+    /// A pattern that matches every [`WangId`].
+    pub const ANY: WangIdPattern = WangIdPattern([None; 8]);
+
+    /// Returns this pattern with the edge at the given index (as in [`WangId::edge`]) requiring
+    /// the given color.
+    ///
+    /// ## Panics
+    /// Panics if `index` is 4 or more.
+    pub fn with_edge(mut self, index: usize, color: u8) -> Self {
+        assert!(index < 4, "edge index out of range: {}", index);
+        self.0[index * 2] = Some(color);
+        self
+    }
+
+    /// Returns this pattern with the corner at the given index (as in [`WangId::corner`])
+    /// requiring the given color.
+    ///
+    /// ## Panics
+    /// Panics if `index` is 4 or more.
+    pub fn with_corner(mut self, index: usize, color: u8) -> Self {
+        assert!(index < 4, "corner index out of range: {}", index);
+        self.0[index * 2 + 1] = Some(color);
+        self
+    }
+
+    /// Returns whether the given [`WangId`] has the colors this pattern requires.
+    pub fn matches(&self, wang_id: WangId) -> bool {
+        self.0
+            .iter()
+            .zip(wang_id.0)
+            .all(|(expected, color)| expected.is_none() || *expected == Some(color))
+    }
+}
+
+impl From<WangId> for WangIdPattern {
+    /// Creates a pattern that only matches the given [`WangId`].
+    fn from(wang_id: WangId) -> Self {
+        WangIdPattern(wang_id.0.map(Some))
+    }
+}
+
 impl FromStr for WangId {
     type Err = Error;
 
@@ -37,6 +194,7 @@ impl FromStr for WangId {
 
 /// Stores the Wang ID.
 #[derive(Debug, PartialEq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct WangTile {
     #[allow(missing_docs)]
     pub wang_id: WangId,
@@ -45,12 +203,12 @@ pub struct WangTile {
 impl WangTile {
     /// Reads data from XML parser to create a WangTile.
     pub(crate) fn new(
-        _parser: &mut impl Iterator<Item = XmlEventResult>,
+        parser: &mut impl XmlParser,
         attrs: Vec<OwnedAttribute>,
     ) -> Result<(TileId, WangTile)> {
         // Get common data
         let (tile_id, wang_id) = get_attrs!(
-            for v in attrs {
+            for v in attrs of ("wangtile", parser.event_position()) {
                 "tileid" => tile_id ?= v.parse::<u32>(),
                 "wangid" => wang_id ?= v.parse(),
             }
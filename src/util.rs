@@ -4,7 +4,7 @@
 /// The syntax is:
 /// ```ignore
 /// get_attrs!(
-///     for $attr in $attributes {
+///     for $attr in $attributes of ($element, $position) {
 ///         $($branch),*
 ///     }
 ///     $expression_to_return
@@ -12,6 +12,8 @@
 /// ```
 /// Where `$attributes` is anything that implements `Iterator<Item = OwnedAttribute>`,
 /// and `$attr` is the value of the attribute (a String) going to be used in each branch.
+/// `$element` is the name of the element the attributes belong to and `$position` its
+/// [`XmlPosition`](crate::XmlPosition), both of which are used when reporting errors.
 ///
 /// Each branch indicates a variable to be set once a certain attribute is found.
 /// Its syntax is as follows:
@@ -34,8 +36,9 @@
 /// ```
 ///
 /// Finally, branches can also use `?=` instead of `=`, which will make them accept a `Result<T, E>`
-/// instead. If the expression results in an Err, the error will be handled internally and the
-/// iteration will return early with a `Result<T, crate::Error>`.
+/// instead. If the expression results in an Err, the iteration will return early with an
+/// [`Error::InvalidAttributeValue`](crate::Error::InvalidAttributeValue). Missing required
+/// attributes result in an [`Error::MissingAttribute`](crate::Error::MissingAttribute).
 ///
 /// Here are some examples of valid branches:
 /// ```ignore
@@ -55,7 +58,7 @@
 /// ## Example
 /// ```ignore
 /// let ((c, infinite), (v, o, w, h, tw, th)) = get_attrs!(
-///     for v in attrs {
+///     for v in attrs of ("map", parser.event_position()) {
 ///         Some("backgroundcolor") => colour ?= v.parse(),
 ///         Some("infinite") => infinite = v == "1",
 ///         "version" => version = v,
@@ -70,20 +73,25 @@
 /// ```
 macro_rules! get_attrs {
     (
-        for $attr:ident in $attrs:ident {
+        for $attr:ident in $attrs:ident of ($element:expr, $position:expr) {
             $($branches:tt)*
         }
         $ret_expr:expr
     ) => {
         {
+            // Not used if all branches are optional and infallible.
+            #[allow(unused_variables)]
+            let element: &str = &$element;
+            #[allow(unused_variables)]
+            let position: $crate::XmlPosition = $position;
             $crate::util::let_attr_branches!($($branches)*);
 
             for attr in $attrs.iter() {
                 let $attr = attr.value.clone();
-                $crate::util::process_attr_branches!(attr; $($branches)*);
+                $crate::util::process_attr_branches!(attr, element, position; $($branches)*);
             }
 
-            $crate::util::handle_attr_branches!($($branches)*);
+            $crate::util::handle_attr_branches!(element, position; $($branches)*);
 
             $ret_expr
         }
@@ -107,49 +115,51 @@ macro_rules! let_attr_branches {
 pub(crate) use let_attr_branches;
 
 macro_rules! process_attr_branches {
-    ($attr:ident; ) => {};
+    ($attr:ident, $element:ident, $position:ident; ) => {};
 
-    ($attr:ident; Some($attr_pat_opt:literal) => $opt_var:ident = $opt_expr:expr $(, $($tail:tt)*)?) => {
+    ($attr:ident, $element:ident, $position:ident; Some($attr_pat_opt:literal) => $opt_var:ident = $opt_expr:expr $(, $($tail:tt)*)?) => {
         if(&$attr.name.local_name == $attr_pat_opt) {
             $opt_var = Some($opt_expr);
         }
         else {
-            $crate::util::process_attr_branches!($attr; $($($tail)*)?);
+            $crate::util::process_attr_branches!($attr, $element, $position; $($($tail)*)?);
         }
     };
 
-    ($attr:ident; Some($attr_pat_opt:literal) => $opt_var:ident ?= $opt_expr:expr $(, $($tail:tt)*)?) => {
+    ($attr:ident, $element:ident, $position:ident; Some($attr_pat_opt:literal) => $opt_var:ident ?= $opt_expr:expr $(, $($tail:tt)*)?) => {
         if(&$attr.name.local_name == $attr_pat_opt) {
-            $opt_var = Some($opt_expr.map_err(|_|
-                $crate::Error::MalformedAttributes(
-                    concat!("Error parsing optional attribute '", $attr_pat_opt, "'").to_owned()
-                )
-            )?);
+            $opt_var = Some($opt_expr.map_err(|_| $crate::Error::InvalidAttributeValue {
+                element: $element.to_owned(),
+                attribute: $attr_pat_opt.to_owned(),
+                value: $attr.value.clone(),
+                position: $position,
+            })?);
         }
         else {
-            $crate::util::process_attr_branches!($attr; $($($tail)*)?);
+            $crate::util::process_attr_branches!($attr, $element, $position; $($($tail)*)?);
         }
     };
 
-    ($attr:ident; $attr_pat_opt:literal => $opt_var:ident = $opt_expr:expr $(, $($tail:tt)*)?) => {
+    ($attr:ident, $element:ident, $position:ident; $attr_pat_opt:literal => $opt_var:ident = $opt_expr:expr $(, $($tail:tt)*)?) => {
         if(&$attr.name.local_name == $attr_pat_opt) {
             $opt_var = Some($opt_expr);
         }
         else {
-            $crate::util::process_attr_branches!($attr; $($($tail)*)?);
+            $crate::util::process_attr_branches!($attr, $element, $position; $($($tail)*)?);
         }
     };
 
-    ($attr:ident; $attr_pat_opt:literal => $opt_var:ident ?= $opt_expr:expr $(, $($tail:tt)*)?) => {
+    ($attr:ident, $element:ident, $position:ident; $attr_pat_opt:literal => $opt_var:ident ?= $opt_expr:expr $(, $($tail:tt)*)?) => {
         if(&$attr.name.local_name == $attr_pat_opt) {
-            $opt_var = Some($opt_expr.map_err(|_|
-                $crate::Error::MalformedAttributes(
-                    concat!("Error parsing attribute '", $attr_pat_opt, "'").to_owned()
-                )
-            )?);
+            $opt_var = Some($opt_expr.map_err(|_| $crate::Error::InvalidAttributeValue {
+                element: $element.to_owned(),
+                attribute: $attr_pat_opt.to_owned(),
+                value: $attr.value.clone(),
+                position: $position,
+            })?);
         }
         else {
-            $crate::util::process_attr_branches!($attr; $($($tail)*)?);
+            $crate::util::process_attr_branches!($attr, $element, $position; $($($tail)*)?);
         }
     }
 }
@@ -157,21 +167,21 @@ macro_rules! process_attr_branches {
 pub(crate) use process_attr_branches;
 
 macro_rules! handle_attr_branches {
-    () => {};
+    ($element:ident, $position:ident; ) => {};
 
-    (Some($attr_pat_opt:literal) => $opt_var:ident $(?)?= $opt_expr:expr $(, $($tail:tt)*)?) => {
-        $crate::util::handle_attr_branches!($($($tail)*)?);
+    ($element:ident, $position:ident; Some($attr_pat_opt:literal) => $opt_var:ident $(?)?= $opt_expr:expr $(, $($tail:tt)*)?) => {
+        $crate::util::handle_attr_branches!($element, $position; $($($tail)*)?);
     };
 
-    ($attr_pat_opt:literal => $opt_var:ident $(?)?= $opt_expr:expr $(, $($tail:tt)*)?) => {
+    ($element:ident, $position:ident; $attr_pat_opt:literal => $opt_var:ident $(?)?= $opt_expr:expr $(, $($tail:tt)*)?) => {
         let $opt_var = $opt_var
-            .ok_or_else(||
-                Error::MalformedAttributes(
-                    concat!("Missing attribute: ", $attr_pat_opt).to_owned()
-                )
-            )?;
+            .ok_or_else(|| $crate::Error::MissingAttribute {
+                element: $element.to_owned(),
+                attribute: $attr_pat_opt.to_owned(),
+                position: $position,
+            })?;
 
-        $crate::util::handle_attr_branches!($($($tail)*)?);
+        $crate::util::handle_attr_branches!($element, $position; $($($tail)*)?);
     };
 }
 
@@ -179,22 +189,43 @@ pub(crate) use handle_attr_branches;
 
 /// Goes through the children of the tag and will call the correct function for
 /// that child. Closes the tag.
+///
+/// Errors returned by a child's function are given the location of the child element, unless
+/// they already have one.
+///
+/// An `else` function can be given after the children's functions, which is called with the name
+/// and attributes of the children that don't have one; See
+/// [`ExtraXml::read_element`](crate::ExtraXml::read_element). Elements it doesn't read are
+/// skipped, but their children are still gone through.
 macro_rules! parse_tag {
     ($parser:expr, $close_tag:expr, {$($open_tag:expr => $open_method:expr),* $(,)*}) => {
+        $crate::util::parse_tag!($parser, $close_tag, {$($open_tag => $open_method),*} else |_, _| Ok(()))
+    };
+    ($parser:expr, $close_tag:expr, {$($open_tag:expr => $open_method:expr),* $(,)*} else $other_method:expr) => {
         while let Some(next) = $parser.next() {
             match next.map_err(Error::XmlDecodingError)? {
                 #[allow(unused_variables)]
                 $(
-                    xml::reader::XmlEvent::StartElement {name, attributes, ..}
-                        if name.local_name == $open_tag => $open_method(attributes)?,
+                    $crate::util::XmlEvent::StartElement {name, attributes, ..}
+                        if name.local_name == $open_tag => {
+                            let position = $parser.event_position();
+                            let result: $crate::Result<()> = $open_method(attributes);
+                            result.map_err(|err| err.located($parser.path(), position))?
+                        },
                 )*
 
+                $crate::util::XmlEvent::StartElement {name, attributes, ..} => {
+                    let position = $parser.event_position();
+                    let result: $crate::Result<()> = $other_method(name.local_name, attributes);
+                    result.map_err(|err| err.located($parser.path(), position))?
+                }
+
 
-                xml::reader::XmlEvent::EndElement {name, ..} => if name.local_name == $close_tag {
+                $crate::util::XmlEvent::EndElement {name, ..} => if name.local_name == $close_tag {
                     break;
                 }
 
-                xml::reader::XmlEvent::EndDocument => {
+                $crate::util::XmlEvent::EndDocument => {
                     return Err(Error::PrematureEnd("Document ended before we expected.".to_string()));
                 }
                 _ => {}
@@ -241,9 +272,423 @@ pub(crate) use get_attrs;
 pub(crate) use map_wrapper;
 pub(crate) use parse_tag;
 
-use crate::{Gid, MapTilesetGid};
+use std::{
+    io::{BufRead, BufReader, Read},
+    path::{Path, PathBuf},
+};
+
+use base64::Engine;
+use quick_xml::{
+    events::{BytesStart, Event},
+    Decoder, Reader,
+};
+
+use crate::{
+    Error, ErrorTolerance, Gid, LoaderOptions, MapTilesetGid, Project, ResourceCache,
+    ResourceReader, Result, Warning, XmlPosition,
+};
+
+/// The name of an XML element or attribute, without its namespace prefix.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OwnedName {
+    pub local_name: String,
+}
+
+/// An XML attribute along with its unescaped value.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct OwnedAttribute {
+    pub name: OwnedName,
+    pub value: String,
+}
+
+/// The XML events the parsers work with. Empty elements are reported as a start element
+/// immediately followed by its end, and text made only of whitespace is reported separately from
+/// the rest.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum XmlEvent {
+    StartElement {
+        name: OwnedName,
+        attributes: Vec<OwnedAttribute>,
+    },
+    EndElement {
+        name: OwnedName,
+    },
+    Characters(String),
+    Whitespace(String),
+    Comment(String),
+    EndDocument,
+}
+
+impl XmlEvent {
+    /// Describes the event for error messages, e.g. as "element <object>".
+    pub(crate) fn describe(&self) -> String {
+        match self {
+            XmlEvent::StartElement { name, .. } => format!("element <{}>", name.local_name),
+            XmlEvent::EndElement { name } => format!("end tag </{}>", name.local_name),
+            XmlEvent::Characters(_) => "text".to_owned(),
+            XmlEvent::Whitespace(_) => "whitespace".to_owned(),
+            XmlEvent::Comment(_) => "comment".to_owned(),
+            XmlEvent::EndDocument => "end of document".to_owned(),
+        }
+    }
+}
+
+pub(crate) type XmlEventResult = std::result::Result<XmlEvent, quick_xml::Error>;
+
+/// An iterator over XML events which can also tell which file it is reading and where the last
+/// event it returned started.
+pub(crate) trait XmlParser: Iterator<Item = XmlEventResult> {
+    /// The position at which the last event returned started, e.g. the beginning of the tag of
+    /// the element that was just opened.
+    fn event_position(&self) -> XmlPosition;
+
+    /// The path of the file being parsed.
+    fn path(&self) -> &Path;
+
+    /// Adds the location of the last event returned to an error, unless it already has one.
+    fn locate(&self, error: Error) -> Error {
+        error.located(self.path(), self.event_position())
+    }
+
+    /// Handles an error that parsing can recover from. With [`ErrorTolerance::Lenient`], the
+    /// error is recorded as a [`Warning`] located at the last event returned, and `Ok` is
+    /// returned so that parsing can go on without the affected part. Otherwise, the error is
+    /// returned as is.
+    fn recover(&mut self, error: Error) -> Result<()>;
+
+    /// Records an error as a [`Warning`] located at the last event returned, regardless of the
+    /// [`ErrorTolerance`].
+    fn warn(&mut self, error: Error);
+
+    /// Returns the warnings recorded so far, leaving none behind.
+    fn take_warnings(&mut self) -> Vec<Warning>;
+
+    /// The options the file is parsed with. Only their limits apply to files other than maps,
+    /// which are always parsed strictly.
+    fn options(&self) -> &LoaderOptions;
+
+    /// Counts a layer that is about to be parsed, failing if there are more than
+    /// [`ParseLimits::max_layers`].
+    fn count_layer(&mut self) -> Result<()>;
+
+    /// Goes one level deeper into group layers or class property values, failing if that's
+    /// deeper than [`ParseLimits::max_nesting_depth`]. Must be paired with
+    /// [`XmlParser::leave_nested`] once the nested content is parsed, unless this fails.
+    fn enter_nested(&mut self) -> Result<()>;
+
+    /// Goes back up one level after [`XmlParser::enter_nested`].
+    fn leave_nested(&mut self);
+
+    /// Whether the file was saved by Tiled 1.1 or earlier, as told by the `version` attribute of
+    /// its root element; See [`crate::compat`].
+    fn legacy_format(&self) -> bool;
+
+    /// Sets whether the file is in a legacy format, once its root element is read.
+    fn set_legacy_format(&mut self, legacy: bool);
+}
+
+/// The reader, cache and project that layers and objects are loaded with, which they need for the
+/// templates they refer to and for resolving their properties.
+pub(crate) struct LoadContext<'a, R: ResourceReader, C: ResourceCache> {
+    pub reader: &'a mut R,
+    pub cache: &'a mut C,
+    pub project: Option<&'a Project>,
+}
+
+impl<'a, R: ResourceReader, C: ResourceCache> LoadContext<'a, R, C> {
+    pub fn new(reader: &'a mut R, cache: &'a mut C, project: Option<&'a Project>) -> Self {
+        Self {
+            reader,
+            cache,
+            project,
+        }
+    }
+}
+
+/// A buffered reader which counts the lines and columns of everything consumed from it.
+///
+/// `quick-xml` only reports byte offsets, so this is what allows turning them into line and
+/// column numbers.
+struct LineCountingReader<R: Read> {
+    inner: BufReader<R>,
+    /// The number of bytes consumed so far.
+    offset: u64,
+    /// The current line, starting from 1.
+    line: u64,
+    /// The number of characters consumed since the start of the current line.
+    column: u64,
+}
+
+impl<R: Read> LineCountingReader<R> {
+    fn new(source: R) -> Self {
+        Self {
+            inner: BufReader::new(source),
+            offset: 0,
+            line: 1,
+            column: 0,
+        }
+    }
+
+    /// Returns the position of the given byte offset, which must be on the current line and not
+    /// be more than a single ASCII character behind the bytes consumed.
+    fn position_of(&self, offset: u64) -> XmlPosition {
+        let behind = self.offset.saturating_sub(offset);
+        XmlPosition {
+            line: self.line,
+            column: self.column.saturating_sub(behind) + 1,
+        }
+    }
+}
+
+impl<R: Read> Read for LineCountingReader<R> {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        let available = self.fill_buf()?;
+        let amount = available.len().min(buf.len());
+        buf[..amount].copy_from_slice(&available[..amount]);
+        self.consume(amount);
+        Ok(amount)
+    }
+}
+
+impl<R: Read> BufRead for LineCountingReader<R> {
+    fn fill_buf(&mut self) -> std::io::Result<&[u8]> {
+        self.inner.fill_buf()
+    }
+
+    fn consume(&mut self, amount: usize) {
+        if let Ok(buffer) = self.inner.fill_buf() {
+            for &byte in &buffer[..amount.min(buffer.len())] {
+                if byte == b'\n' {
+                    self.line += 1;
+                    self.column = 0;
+                } else if byte & 0xC0 != 0x80 {
+                    // Only count the first byte of each UTF-8 encoded character.
+                    self.column += 1;
+                }
+            }
+        }
+        self.offset += amount as u64;
+        self.inner.consume(amount);
+    }
+}
+
+/// An iterator over the events of an XML file. Besides converting the events of the underlying
+/// [`quick_xml::Reader`] into [`XmlEvent`]s, it keeps track of the file and position of each
+/// event, which are used for error reporting.
+pub(crate) struct XmlEvents<R: Read> {
+    reader: Reader<LineCountingReader<R>>,
+    buffer: Vec<u8>,
+    path: PathBuf,
+    position: XmlPosition,
+    finished: bool,
+    error_tolerance: ErrorTolerance,
+    warnings: Vec<Warning>,
+    options: LoaderOptions,
+    layer_count: usize,
+    depth: usize,
+    legacy_format: bool,
+}
+
+impl<R: Read> XmlEvents<R> {
+    pub fn new(source: R, path: &Path) -> Self {
+        let mut reader = Reader::from_reader(LineCountingReader::new(source));
+        reader.config_mut().expand_empty_elements = true;
+        Self {
+            reader,
+            buffer: Vec::new(),
+            path: path.to_owned(),
+            position: XmlPosition { line: 1, column: 1 },
+            finished: false,
+            error_tolerance: ErrorTolerance::Strict,
+            warnings: Vec::new(),
+            options: LoaderOptions::default(),
+            layer_count: 0,
+            depth: 0,
+            legacy_format: false,
+        }
+    }
+
+    /// Sets the options a file other than a map is parsed with; See [`XmlParser::options`]. Such
+    /// files are parsed strictly, regardless of the error tolerance of the options.
+    pub fn with_options(mut self, options: &LoaderOptions) -> Self {
+        self.options = options.clone();
+        self
+    }
+
+    /// Sets the options a map file is parsed with, including how recoverable errors are handled;
+    /// See [`XmlParser::recover`].
+    pub fn with_map_options(mut self, options: &LoaderOptions) -> Self {
+        self.error_tolerance = options.error_tolerance();
+        self.with_options(options)
+    }
+
+    /// Reads the next event that the parsers are interested in, skipping declarations,
+    /// processing instructions and doctypes.
+    fn read_event(&mut self) -> XmlEventResult {
+        loop {
+            let offset = self.reader.buffer_position();
+            self.position = self.reader.get_ref().position_of(offset);
+            self.buffer.clear();
+            let decoder = self.reader.decoder();
+            let event = match self.reader.read_event_into(&mut self.buffer)? {
+                Event::Start(start) => XmlEvent::StartElement {
+                    name: element_name(&start, decoder)?,
+                    attributes: element_attributes(&start, decoder)?,
+                },
+                Event::End(end) => XmlEvent::EndElement {
+                    name: OwnedName {
+                        local_name: decoder.decode(end.local_name().as_ref())?.into_owned(),
+                    },
+                },
+                Event::Text(text) => {
+                    let text = text.unescape()?.into_owned();
+                    if text.chars().all(char::is_whitespace) {
+                        XmlEvent::Whitespace(text)
+                    } else {
+                        XmlEvent::Characters(text)
+                    }
+                }
+                Event::CData(data) => XmlEvent::Characters(decoder.decode(&data)?.into_owned()),
+                Event::Comment(comment) => {
+                    XmlEvent::Comment(decoder.decode(&comment)?.into_owned())
+                }
+                Event::Eof => XmlEvent::EndDocument,
+                Event::Empty(_) | Event::Decl(_) | Event::PI(_) | Event::DocType(_) => continue,
+            };
+            return Ok(event);
+        }
+    }
+}
+
+impl<R: Read> Iterator for XmlEvents<R> {
+    type Item = XmlEventResult;
+
+    fn next(&mut self) -> Option<XmlEventResult> {
+        if self.finished {
+            return None;
+        }
+        let event = self.read_event();
+        self.finished = matches!(event, Ok(XmlEvent::EndDocument) | Err(_));
+        Some(event)
+    }
+}
+
+impl<R: Read> XmlParser for XmlEvents<R> {
+    fn event_position(&self) -> XmlPosition {
+        self.position
+    }
+
+    fn path(&self) -> &Path {
+        &self.path
+    }
+
+    fn recover(&mut self, error: Error) -> Result<()> {
+        match self.error_tolerance {
+            ErrorTolerance::Strict => Err(error),
+            ErrorTolerance::Lenient => {
+                self.warn(error);
+                Ok(())
+            }
+        }
+    }
+
+    fn warn(&mut self, error: Error) {
+        let warning = Warning::new(&error, &self.path, self.position);
+        self.warnings.push(warning);
+    }
 
-pub(crate) type XmlEventResult = xml::reader::Result<xml::reader::XmlEvent>;
+    fn take_warnings(&mut self) -> Vec<Warning> {
+        std::mem::take(&mut self.warnings)
+    }
+
+    fn options(&self) -> &LoaderOptions {
+        &self.options
+    }
+
+    fn count_layer(&mut self) -> Result<()> {
+        self.layer_count += 1;
+        match self.options.limits().max_layers {
+            Some(max) if self.layer_count > max => Err(Error::LimitExceeded {
+                description: format!("more than {} layers", max),
+            }),
+            _ => Ok(()),
+        }
+    }
+
+    fn enter_nested(&mut self) -> Result<()> {
+        match self.options.limits().max_nesting_depth {
+            Some(max) if self.depth >= max => Err(Error::LimitExceeded {
+                description: format!("nesting deeper than {} levels", max),
+            }),
+            _ => {
+                self.depth += 1;
+                Ok(())
+            }
+        }
+    }
+
+    fn leave_nested(&mut self) {
+        self.depth -= 1;
+    }
+
+    fn legacy_format(&self) -> bool {
+        self.legacy_format
+    }
+
+    fn set_legacy_format(&mut self, legacy: bool) {
+        self.legacy_format = legacy;
+    }
+}
+
+fn element_name(start: &BytesStart, decoder: Decoder) -> quick_xml::Result<OwnedName> {
+    Ok(OwnedName {
+        local_name: decoder.decode(start.local_name().as_ref())?.into_owned(),
+    })
+}
+
+/// Collects the attributes of an element, leaving out namespace declarations.
+fn element_attributes(
+    start: &BytesStart,
+    decoder: Decoder,
+) -> quick_xml::Result<Vec<OwnedAttribute>> {
+    let mut attributes = Vec::new();
+    for attribute in start.attributes() {
+        let attribute = attribute?;
+        if attribute.key.as_namespace_binding().is_some() {
+            continue;
+        }
+        attributes.push(OwnedAttribute {
+            name: OwnedName {
+                local_name: decoder
+                    .decode(attribute.key.local_name().as_ref())?
+                    .into_owned(),
+            },
+            value: attribute.decode_and_unescape_value(decoder)?.into_owned(),
+        });
+    }
+    Ok(attributes)
+}
+
+/// Decodes the base64 contents of the `data` element being parsed.
+pub(crate) fn parse_base64(parser: &mut impl XmlParser) -> Result<Vec<u8>> {
+    for next in parser {
+        match next.map_err(Error::XmlDecodingError)? {
+            XmlEvent::Characters(s) => {
+                return base64::engine::GeneralPurpose::new(
+                    &base64::alphabet::STANDARD,
+                    base64::engine::general_purpose::PAD,
+                )
+                .decode(s.trim().as_bytes())
+                .map_err(Error::Base64DecodingError);
+            }
+            XmlEvent::EndElement { name, .. } if name.local_name == "data" => {
+                return Ok(Vec::new());
+            }
+            _ => {}
+        }
+    }
+    Err(Error::PrematureEnd("Ran out of XML data".to_owned()))
+}
 
 /// Returns both the tileset and its index
 pub(crate) fn get_tileset_for_gid(
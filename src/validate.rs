@@ -0,0 +1,315 @@
+//! Checks for problems in loaded maps that don't prevent them from loading.
+
+use std::{collections::HashSet, fmt};
+
+use crate::{
+    Image, Layer, LayerId, LayerType, Map, ObjectId, Project, PropertyType, PropertyValue, TileId,
+    TileLayer, Tileset, TilesetLocation,
+};
+
+/// A problem found by [`Map::validate`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Diagnostic {
+    /// What the problem was found in.
+    pub target: DiagnosticTarget,
+    /// What the problem is.
+    pub kind: DiagnosticKind,
+}
+
+/// The part of a map a [`Diagnostic`] refers to.
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiagnosticTarget {
+    /// The map itself.
+    Map,
+    /// The layer with the given ID.
+    Layer {
+        /// The ID of the layer.
+        id: LayerId,
+    },
+    /// The object with the given ID.
+    Object {
+        /// The ID of the object.
+        id: ObjectId,
+    },
+    /// The map's tileset at the given index.
+    Tileset {
+        /// The index of the tileset in [`Map::tilesets`].
+        index: usize,
+    },
+    /// A tile of one of the map's tilesets.
+    Tile {
+        /// The index of the tileset in [`Map::tilesets`].
+        tileset_index: usize,
+        /// The local ID of the tile within the tileset.
+        id: TileId,
+    },
+}
+
+/// The kinds of problems reported by [`Map::validate`].
+#[derive(Debug, PartialEq, Clone)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum DiagnosticKind {
+    /// A tile layer has a tile whose GID falls within the range of a tileset that doesn't contain
+    /// it.
+    TileOutOfRange {
+        /// The position of the tile in the layer.
+        position: (i32, i32),
+        /// The index of the tileset in [`Map::tilesets`].
+        tileset_index: usize,
+        /// The local ID of the tile within the tileset.
+        id: TileId,
+    },
+    /// A tile object refers to a tile that its tileset doesn't contain.
+    MissingObjectTile {
+        /// The index of the tileset in [`Map::tilesets`], or [`None`] if the tileset comes from a
+        /// template.
+        tileset_index: Option<usize>,
+        /// The local ID of the tile within the tileset.
+        id: TileId,
+    },
+    /// Several layers in the map have the same ID.
+    DuplicateLayerId,
+    /// A class property's type isn't defined as a class in the [`Project`] given to
+    /// [`Map::validate`].
+    UnknownClass {
+        /// The name of the property.
+        property: String,
+        /// The name of the property's type.
+        class: String,
+    },
+    /// An image has a width or height that is zero or negative.
+    InvalidImageSize {
+        /// The width of the image.
+        width: i32,
+        /// The height of the image.
+        height: i32,
+    },
+}
+
+impl fmt::Display for DiagnosticTarget {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticTarget::Map => write!(f, "map"),
+            DiagnosticTarget::Layer { id } => write!(f, "layer {}", id.0),
+            DiagnosticTarget::Object { id } => write!(f, "object {}", id.0),
+            DiagnosticTarget::Tileset { index } => write!(f, "tileset {}", index),
+            DiagnosticTarget::Tile { tileset_index, id } => {
+                write!(f, "tile {} of tileset {}", id, tileset_index)
+            }
+        }
+    }
+}
+
+impl fmt::Display for DiagnosticKind {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            DiagnosticKind::TileOutOfRange {
+                position: (x, y),
+                tileset_index,
+                id,
+            } => write!(
+                f,
+                "tile at ({}, {}) refers to tile {} of tileset {}, which doesn't exist",
+                x, y, id, tileset_index
+            ),
+            DiagnosticKind::MissingObjectTile {
+                tileset_index: Some(tileset_index),
+                id,
+            } => write!(
+                f,
+                "refers to tile {} of tileset {}, which doesn't exist",
+                id, tileset_index
+            ),
+            DiagnosticKind::MissingObjectTile {
+                tileset_index: None,
+                id,
+            } => write!(
+                f,
+                "refers to tile {} of its template's tileset, which doesn't exist",
+                id
+            ),
+            DiagnosticKind::DuplicateLayerId => write!(f, "ID is used by several layers"),
+            DiagnosticKind::UnknownClass { property, class } => write!(
+                f,
+                "property \"{}\" has type \"{}\", which is not a class of the project",
+                property, class
+            ),
+            DiagnosticKind::InvalidImageSize { width, height } => {
+                write!(f, "image has an invalid size of {}x{}", width, height)
+            }
+        }
+    }
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{}: {}", self.target, self.kind)
+    }
+}
+
+/// Collects the diagnostics of a map; See [`Map::validate`].
+struct Validator<'a> {
+    project: Option<&'a Project>,
+    layer_ids: HashSet<LayerId>,
+    diagnostics: Vec<Diagnostic>,
+}
+
+impl<'a> Validator<'a> {
+    fn report(&mut self, target: DiagnosticTarget, kind: DiagnosticKind) {
+        self.diagnostics.push(Diagnostic { target, kind });
+    }
+
+    fn check_tilesets(&mut self, tilesets: &[std::sync::Arc<Tileset>]) {
+        for (index, tileset) in tilesets.iter().enumerate() {
+            let target = DiagnosticTarget::Tileset { index };
+            self.check_image(target, tileset.image.as_ref());
+            self.check_properties(target, &tileset.properties);
+            for (id, tile) in tileset.tiles() {
+                let target = DiagnosticTarget::Tile {
+                    tileset_index: index,
+                    id,
+                };
+                self.check_image(target, tile.image.as_ref());
+                self.check_properties(target, &tile.properties);
+            }
+        }
+    }
+
+    fn check_layer(&mut self, layer: Layer) {
+        let target = DiagnosticTarget::Layer { id: layer.id() };
+        // Layers without an ID come from old versions of Tiled, which didn't have them.
+        if layer.id().0 != 0 && !self.layer_ids.insert(layer.id()) {
+            self.report(target, DiagnosticKind::DuplicateLayerId);
+        }
+        self.check_properties(target, &layer.properties);
+
+        let map = layer.map();
+        let check_tile =
+            |validator: &mut Self, position: (i32, i32), tileset_index: usize, id: TileId| {
+                if let Some(tileset) = map.tileset(tileset_index) {
+                    if tileset.get_tile(id).is_none() {
+                        validator.report(
+                            target,
+                            DiagnosticKind::TileOutOfRange {
+                                position,
+                                tileset_index,
+                                id,
+                            },
+                        );
+                    }
+                }
+            };
+        match layer.layer_type() {
+            LayerType::Tiles(TileLayer::Finite(layer)) => {
+                for (position, tile) in layer.tile_data() {
+                    check_tile(self, position, tile.tileset_index(), tile.id());
+                }
+            }
+            LayerType::Tiles(TileLayer::Infinite(layer)) => {
+                for (position, tile) in layer.tile_data() {
+                    check_tile(self, position, tile.tileset_index(), tile.id());
+                }
+            }
+            LayerType::Objects(layer) => {
+                for object in layer.objects() {
+                    let target = DiagnosticTarget::Object { id: object.id() };
+                    self.check_properties(target, &object.properties);
+                    let tile = match object.tile_data() {
+                        Some(tile) => tile,
+                        None => continue,
+                    };
+                    let (tileset, tileset_index) = match tile.tileset_location() {
+                        TilesetLocation::Map(index) => match map.tileset(*index) {
+                            Some(tileset) => (tileset, Some(*index)),
+                            None => continue,
+                        },
+                        TilesetLocation::Template(tileset) => (tileset, None),
+                    };
+                    if tileset.get_tile(tile.id()).is_none() {
+                        self.report(
+                            target,
+                            DiagnosticKind::MissingObjectTile {
+                                tileset_index,
+                                id: tile.id(),
+                            },
+                        );
+                    }
+                }
+            }
+            // Image layers may leave out the size of their image, which is then 0.
+            LayerType::Image(layer) => self.check_image(
+                target,
+                layer
+                    .image
+                    .as_ref()
+                    .filter(|image| image.width != 0 || image.height != 0),
+            ),
+            LayerType::Group(layer) => {
+                for layer in layer.layers() {
+                    self.check_layer(layer);
+                }
+            }
+        }
+    }
+
+    fn check_image(&mut self, target: DiagnosticTarget, image: Option<&Image>) {
+        if let Some(image) = image {
+            if image.width <= 0 || image.height <= 0 {
+                self.report(
+                    target,
+                    DiagnosticKind::InvalidImageSize {
+                        width: image.width,
+                        height: image.height,
+                    },
+                );
+            }
+        }
+    }
+
+    fn check_properties<'p>(
+        &mut self,
+        target: DiagnosticTarget,
+        properties: impl IntoIterator<Item = (&'p String, &'p PropertyValue)>,
+    ) {
+        let project = match self.project {
+            Some(project) => project,
+            None => return,
+        };
+        for (name, value) in properties {
+            if let PropertyValue::ClassValue {
+                property_type,
+                properties,
+            } = value
+            {
+                match project.get_property_type(property_type) {
+                    Some(PropertyType::Class(_)) => {}
+                    _ => self.report(
+                        target,
+                        DiagnosticKind::UnknownClass {
+                            property: name.clone(),
+                            class: property_type.clone(),
+                        },
+                    ),
+                }
+                self.check_properties(target, properties);
+            }
+        }
+    }
+}
+
+/// Checks the given map for problems; See [`Map::validate`].
+pub(crate) fn validate_map(map: &Map, project: Option<&Project>) -> Vec<Diagnostic> {
+    let mut validator = Validator {
+        project,
+        layer_ids: HashSet::new(),
+        diagnostics: Vec::new(),
+    };
+    validator.check_properties(DiagnosticTarget::Map, &map.properties);
+    validator.check_tilesets(map.tilesets());
+    for layer in map.layers() {
+        validator.check_layer(layer);
+    }
+    validator.diagnostics
+}
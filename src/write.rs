@@ -0,0 +1,608 @@
+//! Writing of tilesets to the TSX format.
+
+use std::{
+    io::{self, Write},
+    path::{Component, Path, PathBuf},
+};
+
+use base64::Engine;
+use quick_xml::{
+    events::{BytesDecl, BytesEnd, BytesStart, BytesText, Event},
+    Writer,
+};
+
+use crate::{
+    Color, DrawOrder, Error, ExtraXml, FillMode, HorizontalAlignment, Image, ImageSource,
+    ObjectAlignment, ObjectData, ObjectLayerData, ObjectShape, Point, Properties, PropertyValue,
+    Result, TextData, TileData, TileRenderSize, Tileset, VerticalAlignment, WangSet, WangSetType,
+};
+
+/// The version of the TMX format written.
+const FORMAT_VERSION: &str = "1.10";
+
+type Attributes = Vec<(&'static str, String)>;
+
+/// Writes the given tileset as a TSX file.
+///
+/// Paths to images and files are written relative to `dir`, the directory the file is being
+/// written to, or as they are stored if it isn't known.
+pub(crate) fn write_tsx(tileset: &Tileset, writer: impl Write, dir: Option<&Path>) -> Result<()> {
+    let mut writer = TsxWriter {
+        xml: Writer::new_with_indent(writer, b' ', 1),
+        dir,
+    };
+    writer.tileset(tileset).map_err(Error::WritingError)
+}
+
+struct TsxWriter<'a, W: Write> {
+    xml: Writer<W>,
+    dir: Option<&'a Path>,
+}
+
+impl<'a, W: Write> TsxWriter<'a, W> {
+    fn tileset(&mut self, tileset: &Tileset) -> io::Result<()> {
+        self.xml
+            .write_event(Event::Decl(BytesDecl::new("1.0", Some("UTF-8"), None)))?;
+
+        let mut attrs = vec![
+            ("version", FORMAT_VERSION.to_owned()),
+            ("name", tileset.name.clone()),
+        ];
+        if let Some(user_type) = &tileset.user_type {
+            attrs.push(("class", user_type.clone()));
+        }
+        attrs.push(("tilewidth", tileset.tile_width.to_string()));
+        attrs.push(("tileheight", tileset.tile_height.to_string()));
+        if tileset.spacing != 0 {
+            attrs.push(("spacing", tileset.spacing.to_string()));
+        }
+        if tileset.margin != 0 {
+            attrs.push(("margin", tileset.margin.to_string()));
+        }
+        attrs.push(("tilecount", tileset.tilecount.to_string()));
+        attrs.push(("columns", tileset.columns.to_string()));
+        if tileset.object_alignment != ObjectAlignment::Unspecified {
+            attrs.push((
+                "objectalignment",
+                tileset.object_alignment.name().to_owned(),
+            ));
+        }
+        if tileset.tile_render_size != TileRenderSize::Tile {
+            attrs.push(("tilerendersize", tileset.tile_render_size.name().to_owned()));
+        }
+        if tileset.fill_mode != FillMode::Stretch {
+            attrs.push(("fillmode", tileset.fill_mode.name().to_owned()));
+        }
+        self.xml.write_event(Event::Start(element_with_extra(
+            "tileset",
+            &attrs,
+            &tileset.extra,
+        )))?;
+
+        if (tileset.offset_x, tileset.offset_y) != (0, 0) {
+            self.empty(
+                "tileoffset",
+                vec![
+                    ("x", tileset.offset_x.to_string()),
+                    ("y", tileset.offset_y.to_string()),
+                ],
+            )?;
+        }
+        if let Some(grid) = &tileset.grid {
+            self.empty(
+                "grid",
+                vec![
+                    ("orientation", grid.orientation.to_string()),
+                    ("width", grid.width.to_string()),
+                    ("height", grid.height.to_string()),
+                ],
+            )?;
+        }
+        if tileset.transformations != Default::default() {
+            let transformations = &tileset.transformations;
+            self.empty(
+                "transformations",
+                vec![
+                    ("hflip", flag(transformations.hflip)),
+                    ("vflip", flag(transformations.vflip)),
+                    ("rotate", flag(transformations.rotate)),
+                    (
+                        "preferuntransformed",
+                        flag(transformations.prefer_untransformed),
+                    ),
+                ],
+            )?;
+        }
+        if let Some(image) = &tileset.image {
+            self.image(image)?;
+        }
+        self.properties(&tileset.properties)?;
+
+        let tiles = tileset
+            .tiles_sorted()
+            .filter(|(_, tile)| !is_default_tile(tile));
+        for (id, tile) in tiles {
+            self.tile(id, &tile)?;
+        }
+
+        if !tileset.wang_sets.is_empty() {
+            self.start("wangsets", Vec::new())?;
+            for wang_set in &tileset.wang_sets {
+                self.wang_set(wang_set)?;
+            }
+            self.end("wangsets")?;
+        }
+        self.extra_elements(&tileset.extra)?;
+
+        self.end("tileset")
+    }
+
+    fn tile(&mut self, id: u32, tile: &TileData) -> io::Result<()> {
+        let mut attrs = vec![("id", id.to_string())];
+        if let Some(user_type) = &tile.user_type {
+            attrs.push(("type", user_type.clone()));
+        }
+        if tile.probability != 1.0 {
+            attrs.push(("probability", tile.probability.to_string()));
+        }
+        if let Some(rect) = &tile.image_rect {
+            attrs.push(("x", rect.x.to_string()));
+            attrs.push(("y", rect.y.to_string()));
+            attrs.push(("width", rect.width.to_string()));
+            attrs.push(("height", rect.height.to_string()));
+        }
+        self.start("tile", attrs)?;
+        self.properties(&tile.properties)?;
+        if let Some(image) = &tile.image {
+            self.image(image)?;
+        }
+        if let Some(collision) = &tile.collision {
+            self.collision(collision)?;
+        }
+        if let Some(animation) = &tile.animation {
+            self.start("animation", Vec::new())?;
+            for frame in animation {
+                self.empty(
+                    "frame",
+                    vec![
+                        ("tileid", frame.tile_id.to_string()),
+                        ("duration", frame.duration.to_string()),
+                    ],
+                )?;
+            }
+            self.end("animation")?;
+        }
+        self.end("tile")
+    }
+
+    fn image(&mut self, image: &Image) -> io::Result<()> {
+        let mut attrs = Vec::new();
+        match &image.source {
+            ImageSource::Path(path) => attrs.push(("source", self.path(path))),
+            ImageSource::Embedded { format, .. } => attrs.push(("format", format.clone())),
+        }
+        if let Some(color) = &image.transparent_colour {
+            attrs.push((
+                "trans",
+                format!("{:02x}{:02x}{:02x}", color.red, color.green, color.blue),
+            ));
+        }
+        // Images of image layers may have an unknown size, which is left out like Tiled does.
+        if image.width != 0 || image.height != 0 {
+            attrs.push(("width", image.width.to_string()));
+            attrs.push(("height", image.height.to_string()));
+        }
+        match &image.source {
+            ImageSource::Path(_) => self.empty("image", attrs),
+            ImageSource::Embedded { data, .. } => {
+                self.start("image", attrs)?;
+                self.start("data", vec![("encoding", "base64".to_owned())])?;
+                self.text(&base64::engine::general_purpose::STANDARD.encode(data))?;
+                self.end("data")?;
+                self.end("image")
+            }
+        }
+    }
+
+    fn collision(&mut self, collision: &ObjectLayerData) -> io::Result<()> {
+        let draw_order = match collision.draw_order {
+            DrawOrder::TopDown => "topdown",
+            DrawOrder::Index => "index",
+        };
+        let mut attrs = vec![("draworder", draw_order.to_owned())];
+        if let Some(color) = &collision.colour {
+            attrs.push(("color", color_string(color)));
+        }
+        self.start("objectgroup", attrs)?;
+        for object in collision.object_data() {
+            self.object(object)?;
+        }
+        self.end("objectgroup")
+    }
+
+    fn object(&mut self, object: &ObjectData) -> io::Result<()> {
+        let mut attrs = vec![("id", object.id().0.to_string())];
+        if !object.name.is_empty() {
+            attrs.push(("name", object.name.clone()));
+        }
+        if !object.user_type.is_empty() {
+            attrs.push(("type", object.user_type.clone()));
+        }
+        if let Some(gid) = object.collision_gid() {
+            attrs.push(("gid", gid.to_string()));
+        }
+        attrs.push(("x", object.x.to_string()));
+        attrs.push(("y", object.y.to_string()));
+        match &object.shape {
+            ObjectShape::Rect { width, height } | ObjectShape::Ellipse { width, height } => {
+                attrs.push(("width", width.to_string()));
+                attrs.push(("height", height.to_string()));
+            }
+            ObjectShape::Text(text) => {
+                attrs.push(("width", text.width.to_string()));
+                attrs.push(("height", text.height.to_string()));
+            }
+            _ => {}
+        }
+        if object.rotation != 0.0 {
+            attrs.push(("rotation", object.rotation.to_string()));
+        }
+        if !object.visible {
+            attrs.push(("visible", "0".to_owned()));
+        }
+        let element = element_with_extra("object", &attrs, &object.extra);
+        if object.properties.is_empty()
+            && object.extra.elements.is_empty()
+            && matches!(object.shape, ObjectShape::Rect { .. })
+        {
+            return self.xml.write_event(Event::Empty(element));
+        }
+
+        self.xml.write_event(Event::Start(element))?;
+        self.properties(&object.properties)?;
+        match &object.shape {
+            ObjectShape::Rect { .. } => {}
+            ObjectShape::Ellipse { .. } => self.empty("ellipse", Vec::new())?,
+            ObjectShape::Point(..) => self.empty("point", Vec::new())?,
+            ObjectShape::Polygon { points } => {
+                self.empty("polygon", vec![("points", points_string(points))])?
+            }
+            ObjectShape::Polyline { points } => {
+                self.empty("polyline", vec![("points", points_string(points))])?
+            }
+            ObjectShape::Text(text) => self.text_object(text)?,
+        }
+        self.extra_elements(&object.extra)?;
+        self.end("object")
+    }
+
+    fn text_object(&mut self, text: &TextData) -> io::Result<()> {
+        let mut attrs = Vec::new();
+        if text.font_family != "sans-serif" {
+            attrs.push(("fontfamily", text.font_family.clone()));
+        }
+        if text.pixel_size != 16 {
+            attrs.push(("pixelsize", text.pixel_size.to_string()));
+        }
+        let flags = [
+            ("wrap", text.wrap),
+            ("bold", text.bold),
+            ("italic", text.italic),
+            ("underline", text.underline),
+            ("strikeout", text.strikeout),
+        ];
+        for (name, value) in flags {
+            if value {
+                attrs.push((name, flag(true)));
+            }
+        }
+        if !text.kerning {
+            attrs.push(("kerning", flag(false)));
+        }
+        let black = Color {
+            alpha: 255,
+            red: 0,
+            green: 0,
+            blue: 0,
+        };
+        if text.color != black {
+            attrs.push(("color", color_string(&text.color)));
+        }
+        let halign = match text.halign {
+            HorizontalAlignment::Left => None,
+            HorizontalAlignment::Center => Some("center"),
+            HorizontalAlignment::Right => Some("right"),
+            HorizontalAlignment::Justify => Some("justify"),
+        };
+        if let Some(halign) = halign {
+            attrs.push(("halign", halign.to_owned()));
+        }
+        let valign = match text.valign {
+            VerticalAlignment::Top => None,
+            VerticalAlignment::Center => Some("center"),
+            VerticalAlignment::Bottom => Some("bottom"),
+        };
+        if let Some(valign) = valign {
+            attrs.push(("valign", valign.to_owned()));
+        }
+        self.start("text", attrs)?;
+        self.text(&text.text)?;
+        self.end("text")
+    }
+
+    fn wang_set(&mut self, wang_set: &WangSet) -> io::Result<()> {
+        let wang_set_type = match wang_set.wang_set_type {
+            WangSetType::Corner => "corner",
+            WangSetType::Edge => "edge",
+            WangSetType::Mixed => "mixed",
+        };
+        let mut attrs = vec![("name", wang_set.name.clone())];
+        if let Some(user_type) = &wang_set.user_type {
+            attrs.push(("class", user_type.clone()));
+        }
+        attrs.push(("type", wang_set_type.to_owned()));
+        attrs.push(("tile", tile_id_string(wang_set.tile)));
+        self.start("wangset", attrs)?;
+        self.properties(&wang_set.properties)?;
+        for color in &wang_set.wang_colors {
+            let mut attrs = vec![("name", color.name.clone())];
+            if let Some(user_type) = &color.user_type {
+                attrs.push(("class", user_type.clone()));
+            }
+            attrs.extend([
+                ("color", color_string(&color.color)),
+                ("tile", tile_id_string(color.tile)),
+                ("probability", color.probability.to_string()),
+            ]);
+            if color.properties.is_empty() {
+                self.empty("wangcolor", attrs)?;
+            } else {
+                self.start("wangcolor", attrs)?;
+                self.properties(&color.properties)?;
+                self.end("wangcolor")?;
+            }
+        }
+        let mut wang_tiles: Vec<_> = wang_set.wang_tiles.iter().collect();
+        wang_tiles.sort_by_key(|(id, _)| **id);
+        for (id, wang_tile) in wang_tiles {
+            let wang_id = wang_tile.wang_id.0.map(|color| color.to_string()).join(",");
+            self.empty(
+                "wangtile",
+                vec![("tileid", id.to_string()), ("wangid", wang_id)],
+            )?;
+        }
+        self.end("wangset")
+    }
+
+    fn properties(&mut self, properties: &Properties) -> io::Result<()> {
+        if properties.is_empty() {
+            return Ok(());
+        }
+        self.start("properties", Vec::new())?;
+        let mut properties: Vec<_> = properties.iter().collect();
+        properties.sort_by_key(|(name, _)| *name);
+        for (name, value) in properties {
+            self.property(name, value)?;
+        }
+        self.end("properties")
+    }
+
+    fn property(&mut self, name: &str, value: &PropertyValue) -> io::Result<()> {
+        let mut attrs = vec![("name", name.to_owned())];
+        let value = match value {
+            PropertyValue::BoolValue(value) => {
+                attrs.push(("type", "bool".to_owned()));
+                value.to_string()
+            }
+            PropertyValue::FloatValue(value) => {
+                attrs.push(("type", "float".to_owned()));
+                value.to_string()
+            }
+            PropertyValue::IntValue(value) => {
+                attrs.push(("type", "int".to_owned()));
+                value.to_string()
+            }
+            PropertyValue::ColorValue(color) => {
+                attrs.push(("type", "color".to_owned()));
+                format!(
+                    "#{:02x}{:02x}{:02x}{:02x}",
+                    color.alpha, color.red, color.green, color.blue
+                )
+            }
+            PropertyValue::StringValue(value) => value.clone(),
+            PropertyValue::FileValue { relative, resolved } => {
+                attrs.push(("type", "file".to_owned()));
+                match self.dir {
+                    Some(_) if !relative.is_empty() => self.path(resolved),
+                    _ => relative.clone(),
+                }
+            }
+            PropertyValue::ObjectValue(id) => {
+                attrs.push(("type", "object".to_owned()));
+                id.to_string()
+            }
+            PropertyValue::ClassValue {
+                property_type,
+                properties,
+            } => {
+                attrs.push(("type", "class".to_owned()));
+                attrs.push(("propertytype", property_type.clone()));
+                if properties.is_empty() {
+                    return self.empty("property", attrs);
+                }
+                self.start("property", attrs)?;
+                self.properties(properties)?;
+                return self.end("property");
+            }
+            // Enums stored as ints are written as the value they were loaded with, and the others
+            // by name.
+            PropertyValue::EnumValue {
+                property_type,
+                values,
+                int_value,
+            } => {
+                if int_value.is_some() {
+                    attrs.push(("type", "int".to_owned()));
+                }
+                attrs.push(("propertytype", property_type.clone()));
+                match int_value {
+                    Some(value) => value.to_string(),
+                    None => values.join(","),
+                }
+            }
+        };
+        // Attribute values can't hold line breaks, so multiline strings are stored as text.
+        if value.contains('\n') {
+            self.start("property", attrs)?;
+            self.text(&value)?;
+            self.end("property")
+        } else {
+            attrs.push(("value", value));
+            self.empty("property", attrs)
+        }
+    }
+
+    /// Returns the given path as it should be written.
+    fn path(&self, path: &Path) -> String {
+        match self.dir {
+            Some(dir) => relative_path(dir, path),
+            None => path.to_owned(),
+        }
+        .to_string_lossy()
+        .into_owned()
+    }
+
+    fn start(&mut self, name: &str, attrs: Attributes) -> io::Result<()> {
+        self.xml.write_event(Event::Start(element(name, &attrs)))
+    }
+
+    fn empty(&mut self, name: &str, attrs: Attributes) -> io::Result<()> {
+        self.xml.write_event(Event::Empty(element(name, &attrs)))
+    }
+
+    fn end(&mut self, name: &str) -> io::Result<()> {
+        self.xml.write_event(Event::End(BytesEnd::new(name)))
+    }
+
+    fn text(&mut self, text: &str) -> io::Result<()> {
+        self.xml.write_event(Event::Text(BytesText::new(text)))
+    }
+
+    /// Writes the unknown elements that were preserved when loading, as they were read.
+    fn extra_elements(&mut self, extra: &ExtraXml) -> io::Result<()> {
+        for xml in &extra.elements {
+            self.xml.write_indent()?;
+            self.xml.get_mut().write_all(xml.as_bytes())?;
+        }
+        Ok(())
+    }
+}
+
+fn element<'e>(name: &'e str, attrs: &'e Attributes) -> BytesStart<'e> {
+    BytesStart::new(name).with_attributes(attrs.iter().map(|(name, value)| (*name, value.as_str())))
+}
+
+/// Like [`element`], followed by the unknown attributes that were preserved when loading, sorted
+/// by name. Those that would repeat an attribute that is already written are left out.
+fn element_with_extra<'e>(
+    name: &'e str,
+    attrs: &'e Attributes,
+    extra: &'e ExtraXml,
+) -> BytesStart<'e> {
+    let mut extra_attrs: Vec<_> = extra
+        .attributes
+        .iter()
+        .filter(|(name, _)| attrs.iter().all(|(written, _)| written != name))
+        .collect();
+    extra_attrs.sort();
+    element(name, attrs).with_attributes(
+        extra_attrs
+            .into_iter()
+            .map(|(name, value)| (name.as_str(), value.as_str())),
+    )
+}
+
+/// Returns whether a tile has no data besides its ID, in which case it doesn't need to be written.
+fn is_default_tile(tile: &TileData) -> bool {
+    tile.image.is_none()
+        && tile.image_rect.is_none()
+        && tile.properties.is_empty()
+        && tile.collision.is_none()
+        && tile.animation.is_none()
+        && tile.user_type.is_none()
+        && tile.probability == 1.0
+}
+
+fn flag(value: bool) -> String {
+    if value { "1" } else { "0" }.to_owned()
+}
+
+pub(crate) fn color_string(color: &Color) -> String {
+    if color.alpha == 255 {
+        format!("#{:02x}{:02x}{:02x}", color.red, color.green, color.blue)
+    } else {
+        format!(
+            "#{:02x}{:02x}{:02x}{:02x}",
+            color.alpha, color.red, color.green, color.blue
+        )
+    }
+}
+
+fn tile_id_string(id: Option<u32>) -> String {
+    id.map_or_else(|| "-1".to_owned(), |id| id.to_string())
+}
+
+fn points_string(points: &[Point]) -> String {
+    points
+        .iter()
+        .map(|point| format!("{},{}", point.x, point.y))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Returns the path that leads from the directory `dir` to `target`, or `target` itself if
+/// there is none, such as when they are on different drives.
+///
+/// Relative paths are taken as relative to the working directory, and `..` components are
+/// resolved without accessing the filesystem, so symbolic links aren't followed.
+fn relative_path(dir: &Path, target: &Path) -> PathBuf {
+    let (dir, absolute_target) = match (absolute_path(dir), absolute_path(target)) {
+        (Some(dir), Some(target)) => (dir, target),
+        _ => return target.to_owned(),
+    };
+    let common = dir
+        .components()
+        .zip(absolute_target.components())
+        .take_while(|(a, b)| a == b)
+        .count();
+    if common == 0 {
+        return target.to_owned();
+    }
+    let mut relative = PathBuf::new();
+    for _ in dir.components().skip(common) {
+        relative.push("..");
+    }
+    for component in absolute_target.components().skip(common) {
+        relative.push(component);
+    }
+    relative
+}
+
+fn absolute_path(path: &Path) -> Option<PathBuf> {
+    let path = if path.is_absolute() {
+        path.to_owned()
+    } else {
+        std::env::current_dir().ok()?.join(path)
+    };
+    let mut normalized = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::CurDir => {}
+            Component::ParentDir => {
+                normalized.pop();
+            }
+            component => normalized.push(component),
+        }
+    }
+    Some(normalized)
+}
@@ -0,0 +1,78 @@
+#![cfg(feature = "cli")]
+
+use std::process::{Command, Output};
+
+fn inspect(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_tiled-inspect"))
+        .args(args)
+        .output()
+        .unwrap()
+}
+
+fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+#[test]
+fn test_inspect_tree() {
+    let output = inspect(&["assets/tiled_group_layers.tmx"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("map 8x8 orthogonal, tiles 32x32 (version 1.5)\n"));
+    assert_eq!(
+        stdout(&inspect(&["assets/tiled_group_layers.tmx", "tree"])),
+        stdout(&output)
+    );
+}
+
+#[test]
+fn test_inspect_validate() {
+    let output = inspect(&["assets/tiled_csv.tmx", "validate"]);
+    assert!(output.status.success());
+    assert_eq!(stdout(&output), "no problems found\n");
+}
+
+#[test]
+fn test_inspect_tilesets() {
+    let output = inspect(&["assets/tiled_group_layers.tmx", "tilesets"]);
+    assert!(output.status.success());
+    assert!(stdout(&output).starts_with("[0] \"tilesheet\": 18 of 84 tiles used, first gid 1"));
+}
+
+#[test]
+fn test_inspect_layer() {
+    let output = inspect(&["assets/tiled_group_layers.tmx", "layer", "group-1/tile-2"]);
+    assert!(output.status.success());
+    let csv = stdout(&output);
+    let rows: Vec<&str> = csv.lines().collect();
+    assert_eq!(rows.len(), 8);
+    assert_eq!(rows[3], "0,0,0,6,7,8,0,0");
+
+    let output = inspect(&[
+        "assets/tiled_object_draw_order.tmx",
+        "layer",
+        "index",
+        "json",
+    ]);
+    assert!(output.status.success());
+    let json: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+    assert_eq!(json["name"], "index");
+    assert_eq!(json["objects"].as_array().unwrap().len(), 3);
+    assert_eq!(json["objects"][2]["id"], 6);
+    assert_eq!(json["objects"][2]["y"], 32.0);
+}
+
+#[test]
+fn test_inspect_errors() {
+    let output = inspect(&["assets/tiled_group_layers.tmx", "layer", "missing"]);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "error: there is no layer at \"missing\"\n"
+    );
+
+    let output = inspect(&["assets/tiled_group_layers.tmx", "unknown"]);
+    assert_eq!(output.status.code(), Some(2));
+    assert!(String::from_utf8(output.stderr)
+        .unwrap()
+        .starts_with("Usage: tiled-inspect"));
+}
@@ -1,8 +1,24 @@
-use std::path::PathBuf;
+#![cfg(feature = "filesystem")]
+
+use std::{
+    collections::{HashMap, HashSet},
+    io::Cursor,
+    path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use tiled::{
-    Color, FiniteTileLayer, HorizontalAlignment, LayerType, Loader, Map, ObjectShape,
-    PropertyValue, ResourceCache, TileLayer, TilesetLocation, VerticalAlignment, WangId,
+    stream::MapEvent, AnimationState, ChunkData, Color, Diagnostic, DiagnosticKind,
+    DiagnosticTarget, Error, ErrorTolerance, ExtraXml, FillMode, FiniteTileLayer, Gid,
+    HasUserClass, HorizontalAlignment, ImageRect, ImageSource, LayerFilter, LayerId, LayerTileData,
+    LayerType, Loader, LoaderOptions, LoaderOptionsBuilder, LruResourceCache, Map, MapBuilder,
+    ObjectAlignment, ObjectData, ObjectDataBuilder, ObjectId, ObjectLayerBuilder, ObjectShape,
+    OwnedLayer, ParseLimits, Point, PropertiesExt, PropertyValue, Rect, ResizeAnchor,
+    ResourceCache, ResourceReader, SharedResourceCache, TemplateObjectReference, TextData,
+    TileData, TileDataBuilder, TileFlip, TileLayer, TileLayerBuilder, TileRenderSize, TilesetGrid,
+    TilesetLocation, Transformations, VerticalAlignment, WangId, WangIdPattern, Winding,
+    XmlPosition,
 };
 
 fn as_finite<'map>(data: TileLayer<'map>) -> FiniteTileLayer<'map> {
@@ -57,6 +73,31 @@ fn test_gzip_and_zlib_encoded_and_raw_are_the_same() {
     assert!((0..99).map(|x| layer.get_tile(x, 99)).all(|t| t.is_none()));
 }
 
+#[test]
+fn test_xml_encoded_data() {
+    let mut loader = Loader::new();
+    let map = loader.load_tmx_map("assets/tiled_xml.tmx").unwrap();
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert_eq!(layer.width(), 100);
+    assert_eq!(layer.height(), 100);
+    assert_eq!(layer.get_tile(0, 0).unwrap().id(), 29);
+    assert_eq!(layer.tiles().count(), 70);
+
+    let map = loader
+        .load_tmx_map("assets/tiled_xml_infinite.tmx")
+        .unwrap();
+    let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => layer,
+        TileLayer::Finite(_) => panic!("Not an infinite tile layer"),
+    };
+    assert_eq!(layer.get_tile(-2, 0).unwrap().id(), 4);
+    assert!(layer.get_tile(-1, 0).is_none());
+    let flipped = layer.get_tile(-2, 1).unwrap();
+    assert_eq!(flipped.id(), 5);
+    assert!(flipped.flip_h);
+    assert_eq!(layer.get_tile(-1, 1).unwrap().id(), 0);
+}
+
 #[test]
 fn test_external_tileset() {
     let mut loader = Loader::new();
@@ -80,7 +121,7 @@ fn test_sources() {
     );
     assert_eq!(
         e.tilesets()[0].image.as_ref().unwrap().source,
-        PathBuf::from("assets/tilesheet.png")
+        ImageSource::Path(PathBuf::from("assets/tilesheet.png"))
     );
 }
 
@@ -96,6 +137,69 @@ fn test_just_tileset() {
     );
 }
 
+#[test]
+fn test_infinite_layer_bounds_and_tiles() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => layer,
+        TileLayer::Finite(_) => panic!("Not an infinite tile layer"),
+    };
+    let ((min_x, min_y), (max_x, max_y)) = layer.bounds().unwrap();
+    assert_eq!(((min_x, min_y), (max_x, max_y)), ((-16, 0), (31, 47)));
+
+    let tiles: Vec<_> = layer.tiles().collect();
+    let chunk_tile_count: usize = layer
+        .chunks()
+        .map(|(_, chunk)| {
+            (0..ChunkData::WIDTH as i32)
+                .flat_map(|x| (0..ChunkData::HEIGHT as i32).map(move |y| (x, y)))
+                .filter(|(x, y)| chunk.get_tile(*x, *y).is_some())
+                .count()
+        })
+        .sum();
+    assert_eq!(tiles.len(), chunk_tile_count);
+    for ((x, y), tile) in &tiles {
+        assert!((min_x..=max_x).contains(x) && (min_y..=max_y).contains(y));
+        assert_eq!(layer.get_tile(*x, *y).unwrap().id(), tile.id());
+    }
+    assert!(tiles
+        .iter()
+        .any(|((x, y), tile)| (*x, *y) == (-16, 47) && tile.id() == 17));
+
+    for (pos, chunk) in layer.chunks() {
+        let origin = chunk.origin();
+        assert_eq!(
+            origin,
+            (
+                pos.0 * ChunkData::WIDTH as i32,
+                pos.1 * ChunkData::HEIGHT as i32
+            )
+        );
+        for ((x, y), tile) in chunk.tiles() {
+            assert_eq!(
+                chunk.get_tile(x - origin.0, y - origin.1).unwrap().id(),
+                tile.id()
+            );
+            assert_eq!(layer.get_tile(x, y).unwrap().id(), tile.id());
+        }
+    }
+    let chunk = layer.get_chunk(-1, 2).unwrap();
+    assert_eq!(chunk.origin(), (-16, 32));
+    assert!(chunk.tiles().any(|(pos, _)| pos == (-16, 47)));
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_xml_infinite.tmx")
+        .unwrap();
+    let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => layer,
+        TileLayer::Finite(_) => panic!("Not an infinite tile layer"),
+    };
+    assert_eq!(layer.bounds(), Some(((-16, 0), (-1, 15))));
+    assert_eq!(layer.tiles().count(), 3);
+}
+
 #[test]
 fn test_infinite_map() {
     let r = Loader::new()
@@ -164,12 +268,65 @@ fn test_image_layers() {
             .image
             .as_ref()
             .unwrap_or_else(|| panic!("{}'s image shouldn't be None", second.1.name));
-        assert_eq!(image.source, PathBuf::from("assets/tilesheet.png"));
+        assert_eq!(
+            image.source,
+            ImageSource::Path(PathBuf::from("assets/tilesheet.png"))
+        );
         assert_eq!(image.width, 448);
         assert_eq!(image.height, 192);
     }
 }
 
+#[test]
+fn test_image_layer_without_image_size() {
+    let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1"
+        tilewidth="32" tileheight="32">
+     <imagelayer id="1" name="background">
+      <image source="tilesheet.png"/>
+     </imagelayer>
+    </map>"#;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/background.tmx")
+        .unwrap();
+    let layer = map.get_layer(0).unwrap().as_image_layer().unwrap();
+    let image = layer.image.as_ref().unwrap();
+    assert_eq!((image.width, image.height), (0, 0));
+    assert!(map.validate(None).is_empty());
+
+    // Tileset images still need a size, as the layout of their tiles depends on it.
+    let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1"
+        tilewidth="32" tileheight="32">
+     <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+      <image source="tilesheet.png"/>
+     </tileset>
+    </map>"#;
+    let err = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/tiles.tmx")
+        .unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::MissingAttribute { element, attribute, .. } if element == "image" && attribute == "width"
+    ));
+}
+
+#[test]
+fn test_image_layer_repeat() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_image_layers_repeat.tmx")
+        .unwrap();
+    let repeats: Vec<_> = r
+        .layers()
+        .map(|layer| {
+            let image_layer = layer.as_image_layer().unwrap();
+            (image_layer.repeat_x, image_layer.repeat_y)
+        })
+        .collect();
+    assert_eq!(
+        repeats,
+        [(true, false), (false, true), (true, true), (false, false)]
+    );
+}
+
 #[test]
 fn test_tile_property() {
     let r = Loader::new()
@@ -262,6 +419,33 @@ fn test_flipped() {
     assert!(t4.flip_d);
     assert!(!t4.flip_h);
     assert!(!t4.flip_v);
+    assert_eq!(t1.flip(), TileFlip::all());
+    assert_eq!(t4.flip(), TileFlip::DIAGONAL);
+}
+
+#[test]
+fn test_tile_flip_transforms() {
+    // The corners of a tile, in the order used by `TileFlip::apply_to_uv`, centered on the origin.
+    let corners = [(-1, -1), (1, -1), (1, 1), (-1, 1)];
+    for bits in 0..8 {
+        let flip = TileFlip::from_bits(bits).unwrap();
+        let (rotation, (scale_x, scale_y)) = flip.to_rotation_and_scale();
+        // Draw the corners of the tile scaled and then rotated clockwise, and check that each
+        // one ends up where the texture coordinates put it.
+        let (sin, cos) = (rotation as f64).to_radians().sin_cos();
+        let transformed = corners.map(|(x, y)| {
+            let (x, y) = (x as f64 * scale_x as f64, y as f64 * scale_y as f64);
+            (
+                (x * cos - y * sin).round() as i32,
+                (x * sin + y * cos).round() as i32,
+            )
+        });
+        let uv = flip.apply_to_uv(corners);
+        for (corner, drawn_at) in corners.iter().zip(transformed) {
+            let quad_corner = corners.iter().position(|c| *c == drawn_at).unwrap();
+            assert_eq!(uv[quad_corner], *corner, "{:?}", flip);
+        }
+    }
 }
 
 #[test]
@@ -276,6 +460,12 @@ fn test_ldk_export() {
     }
     assert!(layer.get_tile(0, 0).is_none());
     assert_eq!(layer.get_tile(0, 1).unwrap().id(), 0);
+    assert_eq!(r.compression_level, Some(0));
+
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert_eq!(r.compression_level, None);
 }
 
 #[test]
@@ -303,6 +493,18 @@ fn test_parallax_layers() {
             _ => panic!("unexpected layer"),
         }
     }
+
+    assert_eq!((r.parallax_origin_x, r.parallax_origin_y), (64.0, 32.0));
+    let offsets: Vec<_> = r
+        .flattened_layers()
+        .map(|layer| layer.view_offset(164.0, 132.0))
+        .collect();
+    // Layers are at their own offset when the view is centered on the parallax origin, and move
+    // slower or faster than the view otherwise.
+    assert_eq!(offsets, [(50.0, 25.0), (0.0, 0.0), (-100.0, -100.0)]);
+    assert!(r
+        .flattened_layers()
+        .all(|layer| layer.view_offset(64.0, 32.0) == (0.0, 0.0)));
 }
 
 #[test]
@@ -326,6 +528,27 @@ fn test_object_property() {
     assert_eq!(3, prop_value);
 }
 
+#[test]
+fn test_file_property() {
+    let r = Loader::new()
+        .load_tmx_map("assets/folder/tiled_relative_paths.tmx")
+        .unwrap();
+    assert_eq!(
+        r.properties.get("image"),
+        Some(&PropertyValue::FileValue {
+            relative: "../tilesheet.png".to_owned(),
+            resolved: PathBuf::from("assets/folder/../tilesheet.png"),
+        })
+    );
+    assert_eq!(
+        r.properties.get("unset"),
+        Some(&PropertyValue::FileValue {
+            relative: String::new(),
+            resolved: PathBuf::new(),
+        })
+    );
+}
+
 #[test]
 fn test_class_property() {
     let r = Loader::new()
@@ -353,6 +576,116 @@ fn test_class_property() {
     };
 }
 
+#[test]
+fn test_project_property_types() {
+    let mut loader = Loader::new();
+    let without_project = loader
+        .load_tmx_map("assets/tiled_project_properties.tmx")
+        .unwrap();
+    assert_eq!(
+        without_project.properties.get("direction"),
+        Some(&PropertyValue::StringValue("West".to_owned()))
+    );
+
+    let project = loader
+        .load_tiled_project("assets/tiled_project.tiled-project")
+        .unwrap();
+    assert!(project.get_property_type("Character").is_some());
+    loader.set_project(Some(project));
+    let r = loader
+        .load_tmx_map("assets/tiled_project_properties.tmx")
+        .unwrap();
+
+    assert_eq!(
+        r.properties.get("direction"),
+        Some(&PropertyValue::EnumValue {
+            property_type: "Direction".to_owned(),
+            values: vec!["West".to_owned()],
+            int_value: None,
+        })
+    );
+    assert_eq!(
+        r.properties.get("layers"),
+        Some(&PropertyValue::EnumValue {
+            property_type: "Layers".to_owned(),
+            values: vec!["Ground".to_owned(), "Air".to_owned()],
+            int_value: Some(5),
+        })
+    );
+
+    let properties =
+        if let Some(PropertyValue::ClassValue { properties, .. }) = r.properties.get("character") {
+            properties
+        } else {
+            panic!("Expected class property");
+        };
+    assert_eq!(properties.get("health"), Some(&PropertyValue::IntValue(42)));
+    assert_eq!(
+        properties.get("name"),
+        Some(&PropertyValue::StringValue("Unnamed \"hero\"".to_owned()))
+    );
+    assert_eq!(
+        properties.get("facing"),
+        Some(&PropertyValue::EnumValue {
+            property_type: "Direction".to_owned(),
+            values: vec!["South".to_owned()],
+            int_value: None,
+        })
+    );
+    assert!(properties.get("tint").is_none());
+    let spawn = if let Some(PropertyValue::ClassValue { properties, .. }) = properties.get("spawn")
+    {
+        properties
+    } else {
+        panic!("Expected class property");
+    };
+    assert_eq!(spawn.get("x"), Some(&PropertyValue::FloatValue(8.0)));
+    assert_eq!(spawn.get("y"), Some(&PropertyValue::FloatValue(16.0)));
+}
+
+#[test]
+fn test_project_nesting_limit() {
+    let load = |contents: String| {
+        Loader::with_reader(move |_: &Path| -> std::io::Result<_> {
+            Ok(Cursor::new(contents.clone().into_bytes()))
+        })
+        .load_tiled_project("project.tiled-project")
+    };
+
+    let nested = |depth: usize| "[".repeat(depth) + &"]".repeat(depth);
+    assert!(load(format!("{{\"unused\": {}}}", nested(100))).is_ok());
+    // Deeply nested documents must be rejected instead of overflowing the stack.
+    assert!(matches!(
+        load(nested(100_000)),
+        Err(Error::InvalidProjectFile { .. })
+    ));
+}
+
+#[test]
+fn test_project_property_type_ids() {
+    let load = |id: &str| {
+        let contents = format!(
+            r#"{{"propertyTypes": [{{"id": {}, "name": "Kind", "type": "enum", "values": ["a"]}}]}}"#,
+            id
+        );
+        Loader::with_reader(move |_: &Path| -> std::io::Result<_> {
+            Ok(Cursor::new(contents.clone().into_bytes()))
+        })
+        .load_tiled_project("project.tiled-project")
+    };
+
+    let project = load("4294967295").unwrap();
+    assert_eq!(project.get_property_type("Kind").unwrap().id(), u32::MAX);
+    // Ids must be integers that fit in a `u32` instead of being truncated.
+    for id in ["4294967296", "-1", "1.5", "2e0", "\"3\""] {
+        assert!(
+            matches!(load(id), Err(Error::InvalidProjectFile { .. })),
+            "Expected id {} to be rejected",
+            id
+        );
+    }
+}
+
 #[test]
 fn test_tint_color() {
     let r = Loader::new()
@@ -484,6 +817,19 @@ fn test_object_template_property() {
     );
     assert_eq!(object.get_tile().unwrap().id(), 44);
     assert_eq!(object_nt.get_tile().unwrap().id(), 44);
+
+    // Test the template is kept, and shared between the objects using it
+    let template = object.template().unwrap();
+    assert_eq!(
+        template.source,
+        Path::new("assets/tiled_object_template.tx")
+    );
+    assert_eq!(
+        template.object.properties.get("property"),
+        Some(&PropertyValue::IntValue(1))
+    );
+    assert!(Arc::ptr_eq(template, object_resized.template().unwrap()));
+    assert!(object_nt.template().is_none());
 }
 
 #[test]
@@ -505,6 +851,8 @@ fn test_templates() {
         .as_ref()
         .unwrap()
         .source
+        .as_path()
+        .unwrap()
         .canonicalize()
         .unwrap(),
         PathBuf::from(concat!(env!("CARGO_MANIFEST_DIR"), "/assets/tilesheet.png"))
@@ -525,7 +873,18 @@ fn test_reading_wang_sets() {
     assert_eq!(tileset.wang_sets.len(), 3);
     let wangset_2 = tileset.wang_sets.get(1).unwrap();
     let tile_10 = wangset_2.wang_tiles.get(&10).unwrap();
-    assert_eq!(tile_10.wang_id, WangId([2u8, 2, 0, 2, 0, 2, 2, 2]));
+    assert_eq!(tile_10.wang_id, WangId::from([2, 2, 0, 2, 0, 2, 2, 2]));
+    assert_eq!(tile_10.wang_id.top(), 2);
+    assert_eq!(tile_10.wang_id.right(), 0);
+    assert_eq!(tile_10.wang_id.bottom_left(), 2);
+    assert_eq!(tile_10.wang_id.top_left(), 2);
+    assert_eq!(wangset_2.user_type.as_deref(), Some("Walls"));
+    assert_eq!(wangset_2.wang_colors[0].user_type, None);
+    assert_eq!(
+        wangset_2.wang_colors[1].user_type.as_deref(),
+        Some("Shadow")
+    );
+    assert_eq!(tileset.wang_sets[0].user_type, None);
     let wangset_3 = tileset.wang_sets.get(2).unwrap();
     let color_2 = wangset_3.wang_colors.get(1).unwrap();
     let readed_damage = color_2.properties.get("Damage").unwrap();
@@ -534,51 +893,4704 @@ fn test_reading_wang_sets() {
 }
 
 #[test]
-fn test_text_object() {
+fn test_validate() {
+    // The map has duplicate layer IDs, which are only warned about while loading.
     let mut loader = Loader::new();
-    let map = loader.load_tmx_map("assets/tiled_text_object.tmx").unwrap();
+    let map = loader.load_tmx_map("assets/tiled_validation.tmx").unwrap();
+    assert_eq!(map.warnings().len(), 1);
+    let project = loader
+        .load_tiled_project("assets/tiled_project.tiled-project")
+        .unwrap();
 
-    let group = map.get_layer(0).unwrap().as_object_layer().unwrap();
-    match &group.objects().next().unwrap().shape {
-        ObjectShape::Text {
-            font_family,
-            pixel_size,
-            wrap,
-            color,
-            bold,
-            italic,
-            underline,
-            strikeout,
-            kerning,
-            halign,
-            valign,
-            text,
-            width,
-            height,
-        } => {
-            assert_eq!(font_family.as_str(), "sans-serif");
-            assert_eq!(*pixel_size, 16);
-            assert_eq!(*wrap, false);
-            assert_eq!(
-                *color,
-                Color {
-                    red: 85,
-                    green: 255,
-                    blue: 127,
-                    alpha: 100
-                }
-            );
-            assert_eq!(*bold, true);
-            assert_eq!(*italic, true);
-            assert_eq!(*underline, true);
-            assert_eq!(*strikeout, true);
-            assert_eq!(*kerning, true);
-            assert_eq!(*halign, HorizontalAlignment::Center);
-            assert_eq!(*valign, VerticalAlignment::Bottom);
-            assert_eq!(text.as_str(), "Test");
-            assert_eq!(*width, 87.7188);
-            assert_eq!(*height, 21.7969);
+    let diagnostics = map.validate(Some(&project));
+    let expected = [
+        Diagnostic {
+            target: DiagnosticTarget::Map,
+            kind: DiagnosticKind::UnknownClass {
+                property: "unknown".to_owned(),
+                class: "Unknown".to_owned(),
+            },
+        },
+        Diagnostic {
+            target: DiagnosticTarget::Tileset { index: 0 },
+            kind: DiagnosticKind::InvalidImageSize {
+                width: 0,
+                height: 64,
+            },
+        },
+        Diagnostic {
+            target: DiagnosticTarget::Layer { id: LayerId(1) },
+            kind: DiagnosticKind::TileOutOfRange {
+                position: (1, 0),
+                tileset_index: 0,
+                id: 5,
+            },
+        },
+        Diagnostic {
+            target: DiagnosticTarget::Layer { id: LayerId(1) },
+            kind: DiagnosticKind::DuplicateLayerId,
+        },
+        Diagnostic {
+            target: DiagnosticTarget::Object { id: ObjectId(1) },
+            kind: DiagnosticKind::MissingObjectTile {
+                tileset_index: Some(0),
+                id: 7,
+            },
+        },
+    ];
+    assert_eq!(diagnostics, expected);
+    assert_eq!(
+        diagnostics[2].to_string(),
+        "layer 1: tile at (1, 0) refers to tile 5 of tileset 0, which doesn't exist"
+    );
+
+    // Class properties are only checked against a project.
+    assert_eq!(map.validate(None).len(), expected.len() - 1);
+
+    let map = loader.load_tmx_map("assets/tiled_base64.tmx").unwrap();
+    assert!(map.validate(None).is_empty());
+}
+
+#[test]
+fn test_tileset_tile_rect() {
+    let mut loader = Loader::new();
+    let tileset = loader.load_tsx_tileset("assets/tilesheet.tsx").unwrap();
+    assert_eq!(
+        tileset.tile_rect(0),
+        Some(ImageRect {
+            x: 0,
+            y: 0,
+            width: 32,
+            height: 32
+        })
+    );
+    assert_eq!(
+        tileset.tile_rect(83),
+        Some(ImageRect {
+            x: 13 * 32,
+            y: 5 * 32,
+            width: 32,
+            height: 32
+        })
+    );
+    assert_eq!(tileset.tile_rect(84), None);
+
+    let tileset = loader
+        .load_tsx_tileset("assets/tilesheet_spacing.tsx")
+        .unwrap();
+    assert_eq!(
+        tileset.tile_rect(15),
+        Some(ImageRect {
+            x: 1 + 32,
+            y: 1 + 30,
+            width: 30,
+            height: 28
+        })
+    );
+
+    let tileset = loader
+        .load_tsx_tileset("assets/tilesheet_image_collection.tsx")
+        .unwrap();
+    assert_eq!(tileset.tile_rect(0), None);
+
+    // Regions that can't be represented aren't returned.
+    let mut tileset = loader.load_tsx_tileset("assets/tilesheet.tsx").unwrap();
+    tileset.spacing = u32::MAX;
+    assert_eq!(tileset.tile_rect(0).unwrap().x, 0);
+    assert_eq!(tileset.tile_rect(1), None);
+    tileset.margin = u32::MAX;
+    assert_eq!(tileset.tile_rect(0), None);
+}
+
+#[test]
+fn test_tsx_writing_round_trip() {
+    let mut loader = Loader::new();
+    let mut tilesets = Vec::new();
+    for path in [
+        "assets/tilesheet.tsx",
+        "assets/tilesheet_animation.tsx",
+        "assets/tilesheet_image_collection.tsx",
+        "assets/tilesheet_render_options.tsx",
+        "assets/tilesheet_spacing.tsx",
+        "assets/tilesheet_wangsets.tsx",
+    ] {
+        tilesets.push(loader.load_tsx_tileset(path).unwrap());
+    }
+    let map = loader
+        .load_tmx_map("assets/tiled_tile_collision.tmx")
+        .unwrap();
+    tilesets.push(map.tilesets()[0].as_ref().clone());
+
+    for tileset in tilesets {
+        let mut tsx = Vec::new();
+        tileset.to_tsx_writer(&mut tsx).unwrap();
+        // Paths are written as stored, so a file next to the working directory resolves them the
+        // same way.
+        let reader = move |path: &Path| -> std::io::Result<_> {
+            assert_eq!(path, Path::new("written.tsx"));
+            Ok(Cursor::new(tsx.clone()))
+        };
+        let written = Loader::with_reader(reader)
+            .load_tsx_tileset("written.tsx")
+            .unwrap();
+        assert_eq!(written, tileset);
+    }
+}
+
+#[test]
+fn test_tsx_writing_tile_objects_and_enums() {
+    let tsx = r#"<?xml version="1.0" encoding="UTF-8"?>
+<tileset version="1.10" name="ts" tilewidth="32" tileheight="32" tilecount="0" columns="0">
+ <properties>
+  <property name="direction" propertytype="Direction" value="West"/>
+  <property name="layers" type="int" propertytype="Layers" value="5"/>
+ </properties>
+ <tile id="0">
+  <objectgroup>
+   <object id="1" gid="2147483650" x="0" y="32" width="32" height="32"/>
+  </objectgroup>
+ </tile>
+</tileset>"#;
+    let project = Loader::new()
+        .load_tiled_project("assets/tiled_project.tiled-project")
+        .unwrap();
+    let load = |tsx: Vec<u8>| {
+        let reader = move |_: &Path| -> std::io::Result<_> { Ok(Cursor::new(tsx.clone())) };
+        let mut loader = Loader::with_reader(reader);
+        loader.set_project(Some(project.clone()));
+        loader.load_tsx_tileset("assets/written.tsx").unwrap()
+    };
+    let tileset = load(tsx.as_bytes().to_vec());
+
+    let mut written = Vec::new();
+    tileset.to_tsx_writer(&mut written).unwrap();
+    let text = String::from_utf8(written.clone()).unwrap();
+    // Tile objects in collision groups keep their GID, and enums stored as ints are written as
+    // ints rather than by name.
+    assert!(text.contains(r#"gid="2147483650""#));
+    assert!(text.contains(r#"name="layers" type="int" propertytype="Layers" value="5""#));
+    assert!(text.contains(r#"name="direction" propertytype="Direction" value="West""#));
+    assert_eq!(load(written), tileset);
+}
+
+#[test]
+fn test_externalize_tileset() {
+    let dir = std::env::temp_dir().join("rs-tiled-externalize-tileset");
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join("tilesheet.tsx");
+
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_tile_collision.tmx")
+        .unwrap();
+    assert_eq!(map.tileset_path(0), None);
+    map.externalize_tileset(0, &path).unwrap();
+    assert_eq!(map.tileset_path(0), Some(path.as_path()));
+    assert!(matches!(
+        map.externalize_tileset(0, &path),
+        Err(Error::InvalidMapData { .. })
+    ));
+    assert!(matches!(
+        map.externalize_tileset(1, &path),
+        Err(Error::InvalidMapData { .. })
+    ));
+
+    // The image path is rewritten relative to the new file, but still points to the same image.
+    let mut written = Loader::new().load_tsx_tileset(&path).unwrap();
+    let image = written.image.as_mut().unwrap();
+    let image_path = image.source.as_path().unwrap();
+    assert!(image_path.starts_with(&dir));
+    assert_eq!(
+        image_path.canonicalize().unwrap(),
+        Path::new("assets/tilesheet.png").canonicalize().unwrap()
+    );
+    image.source = ImageSource::Path(PathBuf::from("assets/tilesheet.png"));
+    assert_eq!(&written, map.tilesets()[0].as_ref());
+
+    let external = Loader::new()
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert_eq!(
+        external.tileset_path(0),
+        Some(Path::new("assets/tilesheet.tsx"))
+    );
+}
+
+#[test]
+fn test_tile_image_source() {
+    let mut loader = Loader::new();
+    let tileset = loader
+        .load_tsx_tileset("assets/tilesheet_spacing.tsx")
+        .unwrap();
+    let tile = tileset.get_tile(16).unwrap();
+    assert_eq!(tile.id(), 16);
+    let (image, rect) = tile.image_source().unwrap();
+    assert_eq!(image, tileset.image.as_ref().unwrap());
+    assert_eq!(
+        rect,
+        ImageRect {
+            x: 1 + 2 * 32,
+            y: 1 + 30,
+            width: 30,
+            height: 28
         }
-        _ => panic!(),
+    );
+
+    let tileset = loader
+        .load_tsx_tileset("assets/tilesheet_image_collection.tsx")
+        .unwrap();
+    let whole = tileset.get_tile(0).unwrap();
+    assert_eq!(whole.image_rect, None);
+    assert_eq!(
+        whole.image_source().unwrap().1,
+        ImageRect {
+            x: 0,
+            y: 0,
+            width: 448,
+            height: 192
+        }
+    );
+    let part = tileset.get_tile(1).unwrap();
+    let expected = ImageRect {
+        x: 64,
+        y: 32,
+        width: 96,
+        height: 192,
     };
+    assert_eq!(part.image_rect, Some(expected));
+    assert_eq!(part.image_source().unwrap().1, expected);
+}
+
+#[test]
+fn test_layer_mutation() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+
+    let mut group = map.get_layer_mut(1).unwrap();
+    group.name = "renamed".to_owned();
+    assert!(group.get_layer_mut(1).is_none());
+    let mut layer = group
+        .get_layer_mut(0)
+        .unwrap()
+        .as_finite_tile_layer_mut()
+        .unwrap();
+    assert_eq!(layer.get_tile_data(2, 3), None);
+    let mut tile = LayerTileData::new(0, 83);
+    tile.flip_h = true;
+    layer.set_tile(2, 3, Some(tile)).unwrap();
+    assert_eq!(layer.get_tile_data(2, 3), Some(tile));
+    layer.set_tile(2, 3, None).unwrap();
+    assert_eq!(layer.get_tile_data(2, 3), None);
+    layer
+        .set_tile(4, 4, Some(LayerTileData::new(0, 7)))
+        .unwrap();
+
+    // Tiles must exist in the map's tilesets, and positions must be within the layer.
+    for (position, tile) in [
+        ((0, 0), LayerTileData::new(0, 84)),
+        ((0, 0), LayerTileData::new(1, 0)),
+        ((8, 0), LayerTileData::new(0, 0)),
+        ((0, -1), LayerTileData::new(0, 0)),
+    ] {
+        assert!(matches!(
+            layer.set_tile(position.0, position.1, Some(tile)),
+            Err(Error::InvalidMapData { .. })
+        ));
+    }
+
+    assert!(map
+        .get_layer_mut(1)
+        .unwrap()
+        .as_finite_tile_layer_mut()
+        .is_none());
+    assert!(map.get_layer_mut(4).is_none());
+
+    let group = map.get_layer(1).unwrap();
+    assert_eq!(group.name, "renamed");
+    let layer = group.as_group_layer().unwrap().get_layer(0).unwrap();
+    let layer = as_finite(layer.as_tile_layer().unwrap());
+    assert_eq!(layer.get_tile(4, 4).unwrap().id(), 7);
+    assert_eq!(layer.tiles().count(), 9);
+}
+
+#[test]
+fn test_infinite_layer_mutation() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let mut layer = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_infinite_tile_layer_mut()
+        .unwrap();
+    let chunk_count = layer.chunk_data().len();
+    let occupied_chunk_count = layer
+        .chunk_data()
+        .filter(|(_, chunk)| {
+            (0..ChunkData::TILE_COUNT as i32).any(|i| {
+                chunk
+                    .get_tile_data(i % ChunkData::WIDTH as i32, i / ChunkData::WIDTH as i32)
+                    .is_some()
+            })
+        })
+        .count();
+    assert!(layer.get_chunk_data(10, -10).is_none());
+    let mut tile = LayerTileData::new(0, 5);
+    tile.flip_v = true;
+    layer.set_tile(160, -155, Some(tile)).unwrap();
+    assert_eq!(layer.get_tile_data(160, -155), Some(tile));
+    assert_eq!(layer.chunk_data().len(), chunk_count + 1);
+    assert!(layer.get_chunk_data(10, -10).is_some());
+
+    // Clearing tiles doesn't create chunks, and emptied chunks stay until they are removed.
+    layer.set_tile(-500, -500, None).unwrap();
+    layer.set_tile(160, -155, None).unwrap();
+    assert_eq!(layer.chunk_data().len(), chunk_count + 1);
+    layer.remove_empty_chunks();
+    assert_eq!(layer.chunk_data().len(), occupied_chunk_count);
+    assert_eq!(layer.bounds(), Some(((-16, 0), (31, 47))));
+
+    let tileset_count = map.tilesets().len();
+    let mut layer = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_infinite_tile_layer_mut()
+        .unwrap();
+    assert!(matches!(
+        layer.set_tile(0, 0, Some(LayerTileData::new(tileset_count, 0))),
+        Err(Error::InvalidMapData { .. })
+    ));
+    assert!(map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_finite_tile_layer_mut()
+        .is_none());
+
+    // Tiles in the outermost chunks don't overflow the bounds.
+    let mut layer = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_infinite_tile_layer_mut()
+        .unwrap();
+    layer
+        .set_tile(i32::MAX, i32::MAX, Some(LayerTileData::new(0, 5)))
+        .unwrap();
+    layer
+        .set_tile(i32::MIN, i32::MIN, Some(LayerTileData::new(0, 5)))
+        .unwrap();
+    assert_eq!(
+        layer.bounds(),
+        Some(((i32::MIN, i32::MIN), (i32::MAX, i32::MAX)))
+    );
+    assert!(map.get_layer(0).unwrap().pixel_bounds().is_some());
+}
+
+#[test]
+fn test_tile_layer_conversion() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => layer,
+        TileLayer::Finite(_) => panic!("Not an infinite tile layer"),
+    };
+    let bounds = layer.bounds().unwrap();
+    let finite = layer.crop_to_finite(bounds).unwrap();
+    assert_eq!((finite.width(), finite.height()), (48, 48));
+    assert_eq!(finite.tile_data().count(), layer.tile_data().count());
+    for ((x, y), tile) in finite.tile_data() {
+        assert_eq!(layer.get_tile_data(x - 16, y), Some(tile));
+    }
+
+    // Cropping leaves out the tiles outside of the rectangle.
+    let cropped = layer.crop_to_finite(((-16, 40), (-15, 47))).unwrap();
+    assert_eq!((cropped.width(), cropped.height()), (2, 8));
+    assert_eq!(cropped.get_tile_data(0, 7).unwrap().id(), 17);
+    assert_eq!(
+        cropped.tile_data().count(),
+        layer
+            .tile_data()
+            .filter(|((x, y), _)| (-16..=-15).contains(x) && (40..=47).contains(y))
+            .count()
+    );
+    assert!(matches!(
+        layer.crop_to_finite(((0, 0), (-1, 0))),
+        Err(Error::InvalidMapData { .. })
+    ));
+
+    // Converting back gives the original tiles.
+    let infinite = finite.to_infinite();
+    assert_eq!(infinite.bounds(), Some(((0, 0), (47, 47))));
+    let mut original: Vec<_> = layer
+        .tile_data()
+        .map(|((x, y), tile)| ((x + 16, y), tile))
+        .collect();
+    let mut converted: Vec<_> = infinite.tile_data().collect();
+    original.sort_by_key(|(pos, _)| *pos);
+    converted.sort_by_key(|(pos, _)| *pos);
+    assert_eq!(original, converted);
+}
+
+#[test]
+fn test_object_mutation() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert_eq!(map.next_object_id(), 5);
+    assert!(map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_object_layer_mut()
+        .is_none());
+
+    let mut layer = map.get_layer_mut(1).unwrap().as_object_layer_mut().unwrap();
+    let chest = ObjectDataBuilder::new(ObjectShape::Rect {
+        width: 32.0,
+        height: 32.0,
+    })
+    .name("chest")
+    .user_type("item")
+    .position(10.0, 20.0)
+    .rotation(90.0)
+    .visible(false)
+    .property("gold", PropertyValue::IntValue(12))
+    .tile(0, 5);
+    assert_eq!(layer.push_object(chest).unwrap(), ObjectId(5));
+    assert!(matches!(
+        layer.push_object(ObjectDataBuilder::new(ObjectShape::Point(0.0, 0.0)).tile(0, 84)),
+        Err(Error::InvalidMapData { .. })
+    ));
+
+    let object = layer.get_object_mut(ObjectId(2)).unwrap();
+    object.x = 1.0;
+    object.rotation = 45.0;
+    object
+        .properties
+        .insert("moved".to_owned(), PropertyValue::BoolValue(true));
+    assert_eq!(layer.remove_object(ObjectId(3)).unwrap().id(), ObjectId(3));
+    assert!(layer.remove_object(ObjectId(3)).is_none());
+    assert!(layer.get_object_mut(ObjectId(3)).is_none());
+    assert_eq!(layer.object_data().len(), 4);
+
+    assert_eq!(map.next_object_id(), 6);
+    let chest = map.object_by_id(ObjectId(5)).unwrap();
+    assert_eq!(
+        (chest.name.as_str(), chest.user_type.as_str()),
+        ("chest", "item")
+    );
+    assert_eq!((chest.x, chest.y, chest.rotation), (10.0, 20.0, 90.0));
+    assert!(!chest.visible);
+    assert_eq!(chest.properties["gold"], PropertyValue::IntValue(12));
+    assert_eq!(chest.get_tile().unwrap().id(), 5);
+    let moved = map.object_by_id(ObjectId(2)).unwrap();
+    assert_eq!((moved.x, moved.rotation), (1.0, 45.0));
+    assert_eq!(moved.properties["moved"], PropertyValue::BoolValue(true));
+    assert!(map.object_by_id(ObjectId(3)).is_none());
+
+    let map = MapBuilder::new(tiled::Orientation::Orthogonal, 8, 8, 32, 32)
+        .layer(
+            ObjectLayerBuilder::new()
+                .object(ObjectData::from_shape(7, ObjectShape::Point(0.0, 0.0)))
+                .build(),
+        )
+        .build()
+        .unwrap();
+    assert_eq!(map.next_object_id(), 8);
+}
+
+#[test]
+fn test_data_builders() {
+    let object = ObjectDataBuilder::new(ObjectShape::Polyline {
+        points: vec![Point::new(0.0, 0.0), Point::new(16.0, 8.0)],
+    })
+    .name("path")
+    .position(4.0, 2.0)
+    .build(3)
+    .unwrap();
+    assert_eq!((object.id(), object.name.as_str()), (ObjectId(3), "path"));
+    assert_eq!((object.x, object.y), (4.0, 2.0));
+    for shape in [
+        ObjectShape::Rect {
+            width: -1.0,
+            height: 1.0,
+        },
+        ObjectShape::Ellipse {
+            width: 1.0,
+            height: f32::NAN,
+        },
+        ObjectShape::Polygon {
+            points: vec![Point::new(0.0, 0.0), Point::new(1.0, 0.0)],
+        },
+        ObjectShape::Polyline {
+            points: vec![Point::new(0.0, 0.0)],
+        },
+        ObjectShape::Point(f32::INFINITY, 0.0),
+    ] {
+        assert!(matches!(
+            ObjectDataBuilder::new(shape).build(1),
+            Err(Error::InvalidObjectData { .. })
+        ));
+    }
+    assert!(ObjectDataBuilder::new(ObjectShape::Point(0.0, 0.0))
+        .rotation(f32::NAN)
+        .build(1)
+        .is_err());
+
+    // Points hold their position, so it moves them too.
+    let point = ObjectDataBuilder::new(ObjectShape::Point(0.0, 0.0))
+        .position(32.0, 64.0)
+        .build(1)
+        .unwrap();
+    assert_eq!(point.shape, ObjectShape::Point(32.0, 64.0));
+    assert_eq!(
+        (point.bounding_box().x, point.bounding_box().y),
+        (32.0, 64.0)
+    );
+
+    let default = ObjectData::default();
+    assert_eq!((default.id(), default.visible), (ObjectId(0), true));
+    assert!(default.tile_data().is_none());
+
+    let tile = LayerTileData::new(1, 2).with_flips(false, true, false);
+    assert_eq!((tile.tileset_index(), tile.id()), (1, 2));
+    assert!(!tile.flip_h && tile.flip_v && !tile.flip_d);
+    assert_eq!(LayerTileData::default(), LayerTileData::new(0, 0));
+
+    assert_eq!(TileData::default().probability, 1.0);
+    let image = tiled::Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap()
+        .image
+        .unwrap();
+    let rect = ImageRect {
+        x: 32,
+        y: 0,
+        width: 32,
+        height: 32,
+    };
+    let data = TileDataBuilder::new()
+        .image(image.clone())
+        .image_rect(rect)
+        .probability(0.5)
+        .collision_object(ObjectData::default())
+        .build()
+        .unwrap();
+    assert_eq!(data.image_rect, Some(rect));
+    assert_eq!(data.probability, 0.5);
+    assert_eq!(data.collision.unwrap().object_data().len(), 1);
+    assert!(TileDataBuilder::new().build().unwrap().collision.is_none());
+
+    let invalid = [
+        TileDataBuilder::new().image_rect(rect),
+        TileDataBuilder::new()
+            .image(image.clone())
+            .image_rect(ImageRect {
+                x: image.width - 16,
+                ..rect
+            }),
+        TileDataBuilder::new().probability(f32::INFINITY),
+        TileDataBuilder::new().animation(Vec::new()),
+        TileDataBuilder::new().collision_object(
+            ObjectDataBuilder::new(ObjectShape::Point(0.0, 0.0))
+                .tile(0, 1)
+                .build(1)
+                .unwrap(),
+        ),
+    ];
+    for builder in invalid {
+        assert!(matches!(
+            builder.build(),
+            Err(Error::InvalidTileData { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_next_ids() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+    assert_eq!((map.next_layer_id(), map.next_object_id()), (10, 1));
+
+    let objects = ObjectLayerBuilder::new()
+        .id(2)
+        .object(ObjectData::from_shape(4, ObjectShape::Point(0.0, 0.0)))
+        .build();
+    assert_eq!(map.push_layer(objects).unwrap(), LayerId(10));
+    assert_eq!((map.next_layer_id(), map.next_object_id()), (11, 5));
+    let layer = map.get_layer(3).unwrap();
+    assert_eq!(layer.id(), LayerId(10));
+    assert!(map.object_by_id(ObjectId(4)).is_some());
+
+    assert!(matches!(
+        map.push_layer(TileLayerBuilder::new_infinite().build()),
+        Err(Error::InvalidMapData { .. })
+    ));
+    assert_eq!(map.next_layer_id(), 11);
+
+    // Maps built in code continue after the IDs of their layers and objects.
+    let map = MapBuilder::new(tiled::Orientation::Orthogonal, 8, 8, 32, 32)
+        .layer(TileLayerBuilder::new(8, 8).id(3).build())
+        .build()
+        .unwrap();
+    assert_eq!((map.next_layer_id(), map.next_object_id()), (4, 1));
+}
+
+#[test]
+fn test_map_resize() {
+    let xml = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="4" height="3" tilewidth="32" tileheight="16" infinite="0" nextlayerid="5" nextobjectid="4">
+ <tileset firstgid="1" name="tilesheet" tilewidth="32" tileheight="16" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <layer id="1" name="tiles" width="4" height="3">
+  <data encoding="csv">
+1,2,3,4,
+5,6,7,8,
+9,10,11,12
+</data>
+ </layer>
+ <group id="2" name="group">
+  <objectgroup id="3" name="objects">
+   <object id="1" x="8" y="4" width="16" height="8"/>
+   <object id="2" x="100" y="40" width="20" height="4"/>
+   <object id="3" x="70" y="30"/>
+  </objectgroup>
+  <imagelayer id="4" name="image" offsetx="5" offsety="6">
+   <image source="tilesheet.png" width="448" height="192"/>
+  </imagelayer>
+ </group>
+</map>"##;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/resize.tmx")
+        .unwrap();
+    let tile_ids = |map: &Map| {
+        let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+        assert_eq!((layer.width(), layer.height()), (map.width, map.height));
+        (0..map.height as i32)
+            .map(|y| {
+                (0..map.width as i32)
+                    .map(|x| layer.get_tile(x, y).map(|tile| tile.id()))
+                    .collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+    };
+    let object_positions = |map: &Map| {
+        let mut positions: Vec<_> = map
+            .flattened_layers()
+            .filter_map(|layer| layer.layer.as_object_layer())
+            .flat_map(|layer| layer.objects())
+            .map(|object| (object.id().0, object.x, object.y))
+            .collect();
+        positions.sort_by_key(|(id, _, _)| *id);
+        positions
+    };
+    let image_offset = |map: &Map| {
+        let group = map.get_layer(1).unwrap().as_group_layer().unwrap();
+        let layer = group.get_layer(1).unwrap();
+        (layer.offset_x, layer.offset_y)
+    };
+
+    // Growing from the bottom right moves everything right and down.
+    let mut grown = map.clone();
+    grown.resize(6, 4, ResizeAnchor::BottomRight).unwrap();
+    assert_eq!((grown.width, grown.height), (6, 4));
+    assert_eq!(
+        tile_ids(&grown),
+        [
+            vec![None; 6],
+            [None, None, Some(0), Some(1), Some(2), Some(3)].to_vec(),
+            [None, None, Some(4), Some(5), Some(6), Some(7)].to_vec(),
+            [None, None, Some(8), Some(9), Some(10), Some(11)].to_vec(),
+        ]
+    );
+    assert_eq!(
+        object_positions(&grown),
+        [(1, 72.0, 20.0), (2, 164.0, 56.0), (3, 134.0, 46.0)]
+    );
+    assert_eq!(image_offset(&grown), (69.0, 22.0));
+
+    // Shrinking around the center drops what ends up outside of the map.
+    let mut shrunk = map.clone();
+    shrunk.resize(2, 1, ResizeAnchor::Center).unwrap();
+    assert_eq!(tile_ids(&shrunk), [[Some(5), Some(6)]]);
+    assert_eq!(object_positions(&shrunk), [(3, 38.0, 14.0)]);
+    assert!(shrunk.object_by_id(ObjectId(1)).is_none());
+    assert_eq!(image_offset(&shrunk), (-27.0, -10.0));
+
+    // Cropping moves the top left corner of the rectangle to the origin.
+    let mut cropped = map.clone();
+    cropped.crop(((1, 1), (3, 2))).unwrap();
+    assert_eq!((cropped.width, cropped.height), (3, 2));
+    assert_eq!(
+        tile_ids(&cropped),
+        [[Some(5), Some(6), Some(7)], [Some(9), Some(10), Some(11)]]
+    );
+    assert_eq!(
+        object_positions(&cropped),
+        [(2, 68.0, 24.0), (3, 38.0, 14.0)]
+    );
+
+    let mut grown = cropped.clone();
+    grown.crop(((-1, -1), (3, 2))).unwrap();
+    assert_eq!(tile_ids(&grown)[1][0], None);
+    assert_eq!(tile_ids(&grown)[1][1], Some(5));
+
+    assert!(matches!(
+        cropped.crop(((0, 0), (-1, 0))),
+        Err(Error::InvalidMapData { .. })
+    ));
+    assert!(matches!(
+        cropped.resize(0, 2, ResizeAnchor::TopLeft),
+        Err(Error::InvalidMapData { .. })
+    ));
+    let mut infinite = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    assert!(matches!(
+        infinite.resize(4, 4, ResizeAnchor::TopLeft),
+        Err(Error::InvalidMapData { .. })
+    ));
+}
+
+#[test]
+fn test_merge_tile_layers() {
+    let xml = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="3" height="1" tilewidth="32" tileheight="32" infinite="0" nextlayerid="6" nextobjectid="1">
+ <tileset firstgid="1" name="tilesheet" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <tileset firstgid="85" source="tilesheet.tsx"/>
+ <layer id="1" name="ground" width="3" height="1">
+  <data encoding="csv">1,2,3</data>
+ </layer>
+ <objectgroup id="2" name="objects"/>
+ <layer id="3" name="decoration" width="3" height="1">
+  <data encoding="csv">0,2147483733,0</data>
+ </layer>
+ <layer id="4" name="hidden" visible="0" width="3" height="1">
+  <data encoding="csv">10,10,10</data>
+ </layer>
+ <layer id="5" name="top" width="3" height="1">
+  <data encoding="csv">0,0,7</data>
+ </layer>
+</map>"##;
+    let mut map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/merge.tmx")
+        .unwrap();
+    // The order of the indices doesn't matter.
+    let merged = map.merge_tile_layers(&[4, 3, 0, 2], "merged").unwrap();
+    let id = map.push_layer(merged).unwrap();
+    let layer = map.layer_by_id(id).unwrap();
+    assert_eq!(layer.name, "merged");
+    let layer = as_finite(layer.as_tile_layer().unwrap());
+    assert_eq!((layer.width(), layer.height()), (3, 1));
+    let tiles: Vec<_> = (0..3)
+        .map(|x| {
+            let tile = layer.get_tile_data(x, 0).unwrap();
+            (tile.tileset_index(), tile.id(), tile.flip_h)
+        })
+        .collect();
+    assert_eq!(tiles, [(0, 0, false), (1, 0, true), (0, 6, false)]);
+
+    for indices in [&[0, 1][..], &[0, 9]] {
+        assert!(matches!(
+            map.merge_tile_layers(indices, "merged"),
+            Err(Error::InvalidMapData { .. })
+        ));
+    }
+}
+
+#[test]
+fn test_map_extract() {
+    let xml = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="4" height="2" tilewidth="32" tileheight="32" infinite="0" nextlayerid="5" nextobjectid="3">
+ <tileset firstgid="1" name="first" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <tileset firstgid="85" name="second" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <tileset firstgid="169" name="third" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <layer id="1" name="ground" width="4" height="2">
+  <data encoding="csv">
+1,2,170,171,
+3,4,172,173
+</data>
+ </layer>
+ <group id="2" name="cell">
+  <layer id="3" name="decoration" width="4" height="2">
+   <data encoding="csv">
+0,0,0,90,
+0,0,0,0
+</data>
+  </layer>
+  <objectgroup id="4" name="objects">
+   <object id="1" gid="86" x="0" y="32" width="32" height="32"/>
+   <object id="2" gid="175" x="96" y="64" width="32" height="32"/>
+  </objectgroup>
+ </group>
+</map>"##;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/extract.tmx")
+        .unwrap();
+
+    // Only the tilesets used in the region are kept, with their GIDs renumbered.
+    let cell = map.extract(((2, 0), (3, 1)), &LayerFilter::All).unwrap();
+    assert_eq!((cell.width, cell.height), (2, 2));
+    let names: Vec<_> = cell
+        .tilesets()
+        .iter()
+        .map(|tileset| &tileset.name)
+        .collect();
+    assert_eq!(names, ["second", "third"]);
+    assert_eq!(cell.tileset_first_gids(), [Gid(1), Gid(85)]);
+    let ground = as_finite(cell.get_layer(0).unwrap().as_tile_layer().unwrap());
+    let tile = ground.get_tile(0, 1).unwrap();
+    assert_eq!((tile.tileset_index(), tile.id()), (1, 3));
+    assert_eq!(tile.get_tileset().name, "third");
+    let group = cell.get_layer(1).unwrap().as_group_layer().unwrap();
+    let decoration = as_finite(group.get_layer(0).unwrap().as_tile_layer().unwrap());
+    let tile = decoration.get_tile(1, 0).unwrap();
+    assert_eq!((tile.get_tileset().name.as_str(), tile.id()), ("second", 5));
+    let objects = group.get_layer(1).unwrap().as_object_layer().unwrap();
+    let object = objects.get_object(0).unwrap();
+    assert_eq!((object.id(), object.x, object.y), (ObjectId(2), 32.0, 64.0));
+    let tile = object.get_tile().unwrap();
+    assert_eq!((tile.get_tileset().name.as_str(), tile.id()), ("third", 6));
+    assert!(cell.object_by_id(ObjectId(1)).is_none());
+
+    // Groups that don't match keep the layers in them that do.
+    let filter = LayerFilter::Names(vec!["objects".to_owned()]);
+    let cell = map.extract(((0, 0), (1, 1)), &filter).unwrap();
+    assert_eq!(cell.layers().len(), 1);
+    let group = cell.get_layer(0).unwrap().as_group_layer().unwrap();
+    assert_eq!(group.layers().len(), 1);
+    let objects = group.get_layer(0).unwrap().as_object_layer().unwrap();
+    assert_eq!(objects.objects().len(), 1);
+    assert_eq!(cell.tilesets().len(), 1);
+    assert_eq!(cell.tilesets()[0].name, "second");
+    let tile = objects.get_object(0).unwrap().get_tile().unwrap();
+    assert_eq!(tile.id(), 1);
+    let cell = map
+        .extract(((0, 0), (3, 1)), &LayerFilter::Ids(vec![LayerId(2)]))
+        .unwrap();
+    assert_eq!(cell.layers().len(), 1);
+    assert_eq!(cell.get_layer(0).unwrap().name, "cell");
+    assert!(matches!(
+        map.extract(((0, 0), (-1, 0)), &LayerFilter::All),
+        Err(Error::InvalidMapData { .. })
+    ));
+
+    // Infinite maps are split into finite ones.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let infinite = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => layer,
+        TileLayer::Finite(_) => panic!("Not an infinite tile layer"),
+    };
+    let cell = map
+        .extract(((-16, 32), (-1, 47)), &LayerFilter::All)
+        .unwrap();
+    assert!(!cell.infinite());
+    let layer = as_finite(cell.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert_eq!((layer.width(), layer.height()), (16, 16));
+    assert_eq!(layer.get_tile(0, 15).unwrap().id(), 17);
+    assert_eq!(
+        layer.tiles().count(),
+        infinite
+            .tiles()
+            .filter(|((x, y), _)| (-16..0).contains(x) && (32..48).contains(y))
+            .count()
+    );
+}
+
+#[test]
+fn test_tileset_remapping() {
+    let mut loader = Loader::new();
+    let tileset = Arc::new(loader.load_tsx_tileset("assets/tilesheet.tsx").unwrap());
+    let mut map = loader
+        .load_tmx_map("assets/tiled_sparse_image_collection.tmx")
+        .unwrap();
+    let object_tiles = |map: &Map| -> Vec<_> {
+        map.flattened_layers()
+            .filter_map(|layer| layer.layer.as_object_layer())
+            .flat_map(|layer| layer.objects())
+            .filter_map(|object| {
+                let tile = object.tile_data()?;
+                match tile.tileset_location() {
+                    TilesetLocation::Map(index) => Some((object.id().0, *index, tile.id())),
+                    TilesetLocation::Template(_) => None,
+                }
+            })
+            .collect()
+    };
+    assert_eq!(object_tiles(&map), [(1, 0, 5000)]);
+
+    let index = map.append_tileset(tileset.clone());
+    assert_eq!(index, 2);
+    assert_eq!(map.tileset_first_gids(), [Gid(1), Gid(5002), Gid(5086)]);
+    let mut layer = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_finite_tile_layer_mut()
+        .unwrap();
+    let mut tile = LayerTileData::new(index, 9);
+    tile.flip_d = true;
+    layer.set_tile(0, 0, Some(tile)).unwrap();
+    let tiles = |map: &Map| -> Vec<_> {
+        let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+        (0..3)
+            .map(|x| {
+                layer
+                    .get_tile_data(x, 0)
+                    .map(|tile| (tile.tileset_index(), tile.id(), tile.flip_d))
+            })
+            .collect()
+    };
+    assert_eq!(
+        tiles(&map),
+        [Some((2, 9, true)), Some((0, 0, false)), Some((1, 0, false))]
+    );
+
+    // Reordering the tilesets keeps every tile in place.
+    map.remap_tilesets(&[2, 0, 1]).unwrap();
+    assert!(Arc::ptr_eq(&map.tilesets()[0], &tileset));
+    assert_eq!(map.tilesets()[1].name, "collection");
+    assert_eq!(map.tileset_first_gids(), [Gid(1), Gid(85), Gid(5086)]);
+    assert_eq!(
+        tiles(&map),
+        [Some((0, 9, true)), Some((1, 0, false)), Some((2, 0, false))]
+    );
+    assert_eq!(object_tiles(&map), [(1, 1, 5000)]);
+    assert_eq!(map.tile_for_gid(5085).unwrap().1.id(), 5000);
+
+    // Removing a tileset removes its tiles.
+    map.remap_tilesets(&[1, 2]).unwrap();
+    assert_eq!(map.tilesets().len(), 2);
+    assert_eq!(map.tileset_first_gids(), [Gid(1), Gid(5002)]);
+    assert_eq!(
+        tiles(&map),
+        [None, Some((0, 0, false)), Some((1, 0, false))]
+    );
+    assert_eq!(object_tiles(&map), [(1, 0, 5000)]);
+
+    for order in [&[0, 0][..], &[2]] {
+        assert!(matches!(
+            map.remap_tilesets(order),
+            Err(Error::InvalidMapData { .. })
+        ));
+    }
+
+    // Infinite layers are remapped too.
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let tiles = |map: &Map| -> Vec<_> {
+        let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+            TileLayer::Infinite(layer) => layer,
+            TileLayer::Finite(_) => panic!("Not an infinite tile layer"),
+        };
+        let mut tiles: Vec<_> = layer
+            .tiles()
+            .map(|(pos, tile)| (pos, tile.get_tileset().name.clone(), tile.id()))
+            .collect();
+        tiles.sort();
+        tiles
+    };
+    let before = tiles(&map);
+    map.remap_tilesets(&[1, 0]).unwrap();
+    assert_eq!(tiles(&map), before);
+}
+
+#[test]
+fn test_owned_layer_data() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let layer = map.get_layer(0).unwrap();
+    let tiles = layer.as_tile_layer().unwrap();
+    let expected: Vec<_> = match tiles {
+        TileLayer::Infinite(infinite) => infinite
+            .tiles()
+            .map(|(pos, tile)| (pos, tile.to_owned_tile()))
+            .collect(),
+        TileLayer::Finite(_) => panic!("expected an infinite layer"),
+    };
+    let owned = layer.to_owned_data();
+    drop(map);
+
+    assert!(!expected.is_empty());
+    assert_eq!(owned.tiles().collect::<Vec<_>>(), expected);
+    let ((x, y), tile) = &expected[0];
+    assert_eq!(owned.get_tile(*x, *y).as_ref(), Some(tile));
+    assert!(Arc::ptr_eq(&tile.tileset, owned.tileset(0).unwrap()));
+    assert_eq!(tile.get_tile().map(|t| t.id()), Some(tile.id));
+    assert_eq!(owned.clone().into_data().name, owned.name);
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+    let group = map.layer_at_path("group-2").unwrap().to_owned_data();
+    assert!(group.get_tile(0, 0).is_none());
+    assert_eq!(group.tiles().count(), 0);
+}
+
+#[test]
+fn test_arc_layers() {
+    let map = Arc::new(
+        Loader::new()
+            .load_tmx_map("assets/tiled_group_layers.tmx")
+            .unwrap(),
+    );
+    let layers: Vec<_> = map.layers_arc().collect();
+    assert_eq!(layers.len(), map.layers().len());
+    assert_eq!(layers[0].name, "tile-1");
+    assert!(Arc::ptr_eq(layers[0].map(), &map));
+    assert_eq!(layers[0].layers().len(), 0);
+
+    let group_3 = layers[2].layers().next().unwrap();
+    assert_eq!(group_3.name, "group-3");
+    let tile_3 = group_3.layers().next().unwrap();
+    assert_eq!(
+        tile_3.layer(),
+        map.layer_at_path("group-2/group-3/tile-3").unwrap()
+    );
+    assert_eq!(tile_3.layer().parent().unwrap().name, "group-3");
+
+    let expected = map
+        .layer_at_path("group-2/group-3/tile-3")
+        .unwrap()
+        .as_tile_layer()
+        .unwrap()
+        .get_tile(3, 0)
+        .unwrap();
+    let tile = std::thread::spawn(move || tile_3.get_tile(3, 0).unwrap())
+        .join()
+        .unwrap();
+    assert_eq!(*tile, *expected);
+    assert_eq!(tile.layer_tile().gid(), expected.gid());
+    assert_eq!(tile.get_tileset(), expected.get_tileset());
+    assert_eq!(
+        tile.get_tile().map(|t| t.id()),
+        expected.get_tile().map(|t| t.id())
+    );
+    assert!(group_3.get_tile(0, 0).is_none());
+
+    drop(layers);
+    drop(group_3);
+    assert_eq!(Arc::strong_count(&map), 2);
+    drop(map);
+    assert_eq!(tile.map().layers().len(), 3);
+}
+
+#[test]
+fn test_stream_tmx_map() {
+    fn stream(loader: &mut Loader, path: &str) -> (Map, Vec<String>, Vec<OwnedLayer>) {
+        let (mut events, mut layers) = (Vec::new(), Vec::new());
+        let map = loader
+            .stream_tmx_map(path, |event| {
+                match event {
+                    MapEvent::MapStart(header) => events.push(format!(
+                        "start {} {}x{}",
+                        header.version, header.width, header.height
+                    )),
+                    MapEvent::TilesetLoaded {
+                        index,
+                        first_gid,
+                        tileset,
+                    } => events.push(format!(
+                        "tileset {} {} {}",
+                        index, first_gid.0, tileset.name
+                    )),
+                    MapEvent::ObjectParsed(object) => {
+                        events.push(format!("object {}", object.id().0))
+                    }
+                    MapEvent::LayerParsed(layer) => {
+                        events.push(format!("layer {}", layer.name));
+                        layers.push(*layer);
+                    }
+                    _ => {}
+                }
+                Ok(())
+            })
+            .unwrap();
+        (map, events, layers)
+    }
+
+    // Lazy tilesets don't apply, since streamed layers are handed over along with their tilesets.
+    let mut loader = Loader::new();
+    loader.set_lazy_tilesets(true);
+    let (map, events, layers) = stream(&mut loader, "assets/tiled_base64_external.tmx");
+    let expected = Loader::new()
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert_eq!(events[0], "start 1.4 100x100");
+    assert_eq!(events[1], "tileset 0 1 tilesheet");
+    assert_eq!(events.last().unwrap(), "layer Object group");
+    let object_events: Vec<_> = events.iter().filter(|e| e.starts_with("object")).collect();
+    assert_eq!(object_events.len(), 4);
+    assert_eq!(map.layers().len(), 0);
+    assert!(map.lazy_tilesets().is_empty());
+    assert_eq!(map.tilesets(), expected.tilesets());
+    assert_eq!(map.properties, expected.properties);
+    assert_eq!(map.next_layer_id(), expected.next_layer_id());
+    assert_eq!(map.next_object_id(), expected.next_object_id());
+    let expected_layers: Vec<_> = expected.layers().map(|l| l.to_owned_data()).collect();
+    assert_eq!(layers, expected_layers);
+
+    // Groups are handed over as a whole, and layers declared before the map's tilesets get none.
+    let (_, events, layers) = stream(&mut Loader::new(), "assets/tiled_group_layers.tmx");
+    let names: Vec<_> = layers.iter().map(|layer| layer.name.as_str()).collect();
+    assert_eq!(names, ["tile-1", "group-1", "group-2"]);
+    assert_eq!(events.iter().filter(|e| e.starts_with("layer")).count(), 3);
+    let mut loader = Loader::with_reader(|path: &Path| -> std::io::Result<_> {
+        if path == Path::new("assets/late_tileset.tmx") {
+            let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1"
+                tilewidth="32" tileheight="32">
+             <layer id="1" name="tiles" width="1" height="1"><data encoding="csv">3</data></layer>
+             <tileset firstgid="1" source="tilesheet.tsx"/>
+            </map>"#;
+            Ok(Cursor::new(xml.as_bytes().to_vec()))
+        } else {
+            std::fs::read(path).map(Cursor::new)
+        }
+    });
+    let mut layers = Vec::new();
+    let map = loader
+        .stream_tmx_map("assets/late_tileset.tmx", |event| {
+            if let MapEvent::LayerParsed(layer) = event {
+                layers.push(*layer);
+            }
+            Ok(())
+        })
+        .unwrap();
+    assert!(layers[0].get_tile(0, 0).is_none());
+    assert!(layers[0].tileset(0).is_none());
+    assert_eq!(map.tilesets().len(), 1);
+
+    // Errors from the handler stop parsing.
+    let mut layer_count = 0;
+    let result = Loader::new().stream_tmx_map("assets/tiled_group_layers.tmx", |event| {
+        if let MapEvent::LayerParsed(_) = event {
+            layer_count += 1;
+            return Err(Error::InvalidTileFound);
+        }
+        Ok(())
+    });
+    assert!(result.is_err());
+    assert_eq!(layer_count, 1);
+}
+
+#[test]
+fn test_used_tiles() {
+    let mut loader = Loader::new();
+    let map = loader.load_tmx_map("assets/tiled_used_tiles.tmx").unwrap();
+
+    let used = map.used_tiles();
+    assert_eq!(used.len(), 3);
+    // The animated tile brings its frames along.
+    assert_eq!(used[0], HashSet::from([0, 1, 2, 3, 9]));
+    assert!(used[1].is_empty());
+    assert_eq!(used[2], HashSet::from([4]));
+    assert_eq!(map.used_tilesets(), [0, 2]);
+}
+
+#[test]
+fn test_sparse_image_collection() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_sparse_image_collection.tmx")
+        .unwrap();
+    assert!(map.warnings().is_empty());
+    let tileset = &map.tilesets()[0];
+    assert_eq!(tileset.tilecount, 3);
+    assert_eq!(tileset.gid_count(), 5001);
+    assert_eq!(map.tilesets()[1].gid_count(), 84);
+
+    let tile = tileset.get_tile(5000).unwrap();
+    assert_eq!(tile.probability, 0.25);
+    let frames: Vec<u32> = tile
+        .animation
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|frame| frame.tile_id)
+        .collect();
+    assert_eq!(frames, [5000, 1]);
+    assert_eq!(tile.collision.as_ref().unwrap().object_data().len(), 1);
+    assert!(tileset.get_tile(2).is_none());
+    assert!(tileset.get_tile(5001).is_none());
+
+    assert_eq!(map.gid_for(0, 5000), Some(5001));
+    assert_eq!(map.gid_for(0, 2), None);
+    assert_eq!(map.tile_for_gid(5001).unwrap().0, 0);
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(layer.get_tile(0, 0).unwrap().id(), 5000);
+    assert_eq!(layer.get_tile(2, 0).unwrap().tileset_index(), 1);
+
+    let used = map.used_tiles();
+    assert_eq!(used[0], HashSet::from([0, 1, 5000]));
+    assert_eq!(used[1], HashSet::from([0]));
+    assert!(map.validate(None).is_empty());
+
+    // Tilesets added after it start past its highest tile ID.
+    let built = MapBuilder::new(tiled::Orientation::Orthogonal, 1, 1, 32, 32)
+        .tileset(map.tilesets()[0].clone())
+        .tileset(map.tilesets()[1].clone())
+        .build()
+        .unwrap();
+    assert_eq!(built.tileset_first_gids()[1], Gid(5002));
+}
+
+#[test]
+fn test_wang_set_queries() {
+    let mut loader = Loader::new();
+    let map = loader
+        .load_tmx_map("assets/tiled_csv_wangsets.tmx")
+        .unwrap();
+    let wang_set = &map.tilesets()[0].wang_sets[1];
+
+    let wang_id = wang_set.wang_id_of(10).unwrap();
+    assert_eq!(wang_id, WangId([2, 2, 0, 2, 0, 2, 2, 2]));
+    assert_eq!(wang_id.iter_edges().collect::<Vec<_>>(), [2, 0, 0, 2]);
+    assert_eq!(wang_id.iter_corners().collect::<Vec<_>>(), [2, 2, 2, 2]);
+    assert_eq!(wang_id.edge(3), wang_id.left());
+    assert_eq!(wang_id.corner(1), wang_id.bottom_right());
+    assert_eq!(wang_set.wang_id_of(1000), None);
+
+    assert!(wang_set.find_tiles(wang_id.into()).contains(&10));
+    let all = wang_set.find_tiles(WangIdPattern::ANY);
+    assert_eq!(all.len(), wang_set.wang_tiles.len());
+    assert!(all.windows(2).all(|ids| ids[0] < ids[1]));
+    let top_and_right = wang_set.find_tiles(WangIdPattern::ANY.with_edge(0, 2).with_edge(1, 0));
+    assert!(top_and_right.contains(&10));
+    assert!(top_and_right.iter().all(|id| {
+        let wang_id = wang_set.wang_id_of(*id).unwrap();
+        wang_id.edge(0) == 2 && wang_id.edge(1) == 0
+    }));
+}
+
+#[test]
+fn test_text_object() {
+    let mut loader = Loader::new();
+    let map = loader.load_tmx_map("assets/tiled_text_object.tmx").unwrap();
+
+    let group = map.get_layer(0).unwrap().as_object_layer().unwrap();
+    match &group.objects().next().unwrap().shape {
+        ObjectShape::Text(TextData {
+            font_family,
+            pixel_size,
+            wrap,
+            color,
+            bold,
+            italic,
+            underline,
+            strikeout,
+            kerning,
+            halign,
+            valign,
+            text,
+            width,
+            height,
+        }) => {
+            assert_eq!(font_family.as_str(), "sans-serif");
+            assert_eq!(*pixel_size, 16);
+            assert_eq!(*wrap, false);
+            assert_eq!(
+                *color,
+                Color {
+                    red: 85,
+                    green: 255,
+                    blue: 127,
+                    alpha: 100
+                }
+            );
+            assert_eq!(*bold, true);
+            assert_eq!(*italic, true);
+            assert_eq!(*underline, true);
+            assert_eq!(*strikeout, true);
+            assert_eq!(*kerning, true);
+            assert_eq!(*halign, HorizontalAlignment::Center);
+            assert_eq!(*valign, VerticalAlignment::Bottom);
+            assert_eq!(text.as_str(), "Test");
+            assert_eq!(*width, 87.7188);
+            assert_eq!(*height, 21.7969);
+        }
+        _ => panic!(),
+    };
+
+    let texts: Vec<TextData> = group
+        .objects()
+        .map(|object| match &object.shape {
+            ObjectShape::Text(text) => text.clone(),
+            _ => panic!("expected a text object"),
+        })
+        .collect();
+    assert_eq!(texts[0].wrap_width(), None);
+    assert_eq!(texts[1].wrap_width(), Some(64.0));
+    assert_eq!(texts[1].text, " Multiple\nlines ");
+    assert_eq!(
+        texts[1].lines().collect::<Vec<_>>(),
+        [" Multiple", "lines "]
+    );
+    assert_eq!(texts[2].text, "");
+}
+
+#[test]
+fn test_map_builder() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+
+    let tiles = TileLayerBuilder::new(4, 3)
+        .id(1)
+        .name("tiles")
+        .set_tile(1, 2, Some(LayerTileData::new(0, 5)))
+        .unwrap();
+    assert!(tiles
+        .clone()
+        .set_tile(4, 0, Some(LayerTileData::new(0, 5)))
+        .is_err());
+
+    let mut object = ObjectData::from_shape(3, ObjectShape::Point(0.0, 0.0));
+    object.x = 10.0;
+    let objects = ObjectLayerBuilder::new()
+        .id(2)
+        .name("objects")
+        .object(object)
+        .build();
+
+    let map = MapBuilder::new(tiled::Orientation::Orthogonal, 4, 3, 32, 32)
+        .tileset(tileset.into())
+        .property("answer", PropertyValue::IntValue(42))
+        .layer(tiles.build())
+        .layer(objects)
+        .build()
+        .unwrap();
+
+    assert_eq!(map.layers().len(), 2);
+    assert_eq!(
+        map.properties.get("answer"),
+        Some(&PropertyValue::IntValue(42))
+    );
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert_eq!((layer.width(), layer.height()), (4, 3));
+    assert_eq!(layer.get_tile(1, 2).unwrap().id(), 5);
+    assert_eq!(
+        layer.get_tile(1, 2).unwrap().get_tileset().name,
+        "tilesheet"
+    );
+    assert!(layer.get_tile(0, 0).is_none());
+    let object_layer = map.get_layer(1).unwrap().as_object_layer().unwrap();
+    assert_eq!(object_layer.get_object(0).unwrap().id(), ObjectId(3));
+    assert_eq!(object_layer.get_object(0).unwrap().x, 10.0);
+
+    // Finite tile layers must be the same size as the map.
+    for (width, height) in [(3, 3), (4, 4)] {
+        assert!(matches!(
+            MapBuilder::new(tiled::Orientation::Orthogonal, width, height, 32, 32)
+                .layer(TileLayerBuilder::new(4, 3).build())
+                .build(),
+            Err(Error::InvalidMapData { .. })
+        ));
+    }
+    assert!(matches!(
+        map.clone().push_layer(TileLayerBuilder::new(3, 4).build()),
+        Err(Error::InvalidMapData { .. })
+    ));
+
+    // Tiles must reference tilesets in the map.
+    let tiles = TileLayerBuilder::new(1, 1)
+        .set_tile(0, 0, Some(LayerTileData::new(1, 0)))
+        .unwrap();
+    assert!(
+        MapBuilder::new(tiled::Orientation::Orthogonal, 1, 1, 32, 32)
+            .layer(tiles.build())
+            .build()
+            .is_err()
+    );
+    // Tileset indices too large to be numbered are rejected right away.
+    assert!(TileLayerBuilder::new(1, 1)
+        .set_tile(0, 0, Some(LayerTileData::new(usize::MAX, 0)))
+        .is_err());
+    assert!(TileLayerBuilder::new_infinite()
+        .set_tile(0, 0, Some(LayerTileData::new(usize::MAX, 0)))
+        .is_err());
+
+    // Tile layers must match the map's infinite setting.
+    let tiles = TileLayerBuilder::new_infinite()
+        .set_tile(-20, 40, None)
+        .unwrap();
+    assert!(
+        MapBuilder::new(tiled::Orientation::Orthogonal, 1, 1, 32, 32)
+            .layer(tiles.clone().build())
+            .build()
+            .is_err()
+    );
+    let map = MapBuilder::new(tiled::Orientation::Orthogonal, 1, 1, 32, 32)
+        .infinite(true)
+        .layer(tiles.build())
+        .build()
+        .unwrap();
+    assert!(map.infinite());
+}
+
+#[test]
+fn test_hexagonal_map() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_hexagonal.tmx")
+        .unwrap();
+    assert_eq!(r.orientation, tiled::Orientation::Hexagonal);
+    assert_eq!(r.render_order, tiled::RenderOrder::LeftUp);
+    assert_eq!(r.stagger_axis, tiled::StaggerAxis::X);
+    assert_eq!(r.stagger_index, tiled::StaggerIndex::Even);
+    assert_eq!(r.hex_side_length, Some(14));
+
+    let r = Loader::new().load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    assert_eq!(r.render_order, tiled::RenderOrder::RightDown);
+    assert_eq!(r.hex_side_length, None);
+}
+
+#[test]
+fn test_coordinate_conversion() {
+    let hexagonal = Loader::new()
+        .load_tmx_map("assets/tiled_hexagonal.tmx")
+        .unwrap();
+    assert_eq!(hexagonal.tile_to_pixel(0, 0), (0.0, 14.0));
+    assert_eq!(hexagonal.tile_to_pixel(1, 0), (23.0, 0.0));
+    assert_eq!(hexagonal.tile_to_pixel(2, 1), (46.0, 42.0));
+
+    let maps = [
+        hexagonal,
+        MapBuilder::new(tiled::Orientation::Orthogonal, 4, 4, 32, 16)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Isometric, 4, 4, 64, 32)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Staggered, 4, 4, 64, 32)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Hexagonal, 4, 4, 32, 32)
+            .hex_side_length(16)
+            .build()
+            .unwrap(),
+    ];
+    for map in &maps {
+        for x in -3..6 {
+            for y in -3..6 {
+                let (px, py) = map.tile_to_pixel(x, y);
+                let center = (
+                    px + map.tile_width as f32 / 2.0,
+                    py + map.tile_height as f32 / 2.0,
+                );
+                assert_eq!(
+                    map.pixel_to_tile(center.0, center.1),
+                    (x, y),
+                    "{:?} map",
+                    map.orientation
+                );
+            }
+        }
+    }
+
+    // Extreme sizes don't overflow.
+    let map = MapBuilder::new(tiled::Orientation::Hexagonal, 4, 4, 32, 32)
+        .hex_side_length(u32::MAX)
+        .build()
+        .unwrap();
+    assert_eq!(map.pixel_width(), 4 * 32 + 16);
+    assert_eq!(map.pixel_height(), u32::MAX);
+    map.tile_to_pixel(i32::MAX, i32::MIN);
+    map.pixel_to_tile(1e30, -1e30);
+    let map = MapBuilder::new(tiled::Orientation::Isometric, 4, 4, 64, 32)
+        .build()
+        .unwrap();
+    map.tile_to_pixel(i32::MAX, -1);
+    map.tile_to_pixel(i32::MIN, i32::MIN);
+}
+
+#[test]
+fn test_map_neighbors() {
+    use tiled::{Orientation, StaggerAxis, StaggerIndex};
+
+    let map = |orientation, axis, index| {
+        MapBuilder::new(orientation, 8, 8, 32, 32)
+            .stagger_axis(axis)
+            .stagger_index(index)
+            .hex_side_length(16)
+            .build()
+            .unwrap()
+    };
+    let sorted_neighbors = |map: &Map, x, y| {
+        let mut neighbors = map.neighbors(x, y);
+        neighbors.sort();
+        neighbors
+    };
+
+    let staggered = map(Orientation::Staggered, StaggerAxis::Y, StaggerIndex::Odd);
+    assert_eq!(
+        sorted_neighbors(&staggered, 2, 2),
+        [(1, 1), (1, 3), (2, 1), (2, 3)]
+    );
+    assert_eq!(
+        sorted_neighbors(&staggered, 2, 1),
+        [(2, 0), (2, 2), (3, 0), (3, 2)]
+    );
+    let staggered = map(Orientation::Staggered, StaggerAxis::X, StaggerIndex::Even);
+    assert_eq!(
+        sorted_neighbors(&staggered, 2, 2),
+        [(1, 2), (1, 3), (3, 2), (3, 3)]
+    );
+    let hexagonal = map(Orientation::Hexagonal, StaggerAxis::X, StaggerIndex::Odd);
+    assert_eq!(
+        sorted_neighbors(&hexagonal, 1, 1),
+        [(0, 1), (0, 2), (1, 0), (1, 2), (2, 1), (2, 2)]
+    );
+    let orthogonal = map(Orientation::Orthogonal, StaggerAxis::Y, StaggerIndex::Odd);
+    assert_eq!(
+        sorted_neighbors(&orthogonal, 0, 0),
+        [(-1, 0), (0, -1), (0, 1), (1, 0)]
+    );
+    assert_eq!(orthogonal.neighbors(i32::MAX, 0).len(), 3);
+
+    // Distances match the number of steps between neighbors, which go both ways.
+    let maps = [
+        orthogonal,
+        map(Orientation::Isometric, StaggerAxis::Y, StaggerIndex::Odd),
+        map(Orientation::Staggered, StaggerAxis::Y, StaggerIndex::Odd),
+        map(Orientation::Staggered, StaggerAxis::Y, StaggerIndex::Even),
+        map(Orientation::Staggered, StaggerAxis::X, StaggerIndex::Odd),
+        staggered,
+        map(Orientation::Hexagonal, StaggerAxis::Y, StaggerIndex::Odd),
+        map(Orientation::Hexagonal, StaggerAxis::Y, StaggerIndex::Even),
+        hexagonal,
+        map(Orientation::Hexagonal, StaggerAxis::X, StaggerIndex::Even),
+    ];
+    for map in &maps {
+        for start in [(0, 0), (1, 0), (0, 1), (-3, 5)] {
+            let mut steps = HashMap::from([(start, 0)]);
+            let mut queue = std::collections::VecDeque::from([start]);
+            while let Some(tile) = queue.pop_front() {
+                let distance = steps[&tile];
+                assert_eq!(map.tile_distance(start, tile), distance);
+                for neighbor in map.neighbors(tile.0, tile.1) {
+                    assert!(map.neighbors(neighbor.0, neighbor.1).contains(&tile));
+                    if distance < 6 && !steps.contains_key(&neighbor) {
+                        steps.insert(neighbor, distance + 1);
+                        queue.push_back(neighbor);
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[test]
+fn test_map_pixel_size() {
+    let hexagonal = Loader::new()
+        .load_tmx_map("assets/tiled_hexagonal.tmx")
+        .unwrap();
+    assert_eq!(hexagonal.tile_size(), (32, 28));
+    assert_eq!(
+        (hexagonal.pixel_width(), hexagonal.pixel_height()),
+        (101, 126)
+    );
+
+    let maps = [
+        hexagonal,
+        MapBuilder::new(tiled::Orientation::Orthogonal, 5, 3, 32, 16)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Isometric, 5, 3, 64, 32)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Staggered, 5, 3, 64, 32)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Staggered, 5, 3, 64, 32)
+            .stagger_axis(tiled::StaggerAxis::X)
+            .stagger_index(tiled::StaggerIndex::Even)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Hexagonal, 5, 3, 32, 32)
+            .hex_side_length(16)
+            .build()
+            .unwrap(),
+        MapBuilder::new(tiled::Orientation::Hexagonal, 1, 1, 32, 32)
+            .hex_side_length(16)
+            .build()
+            .unwrap(),
+    ];
+    let expected = [
+        (101, 126),
+        (160, 48),
+        (256, 128),
+        (352, 64),
+        (192, 112),
+        (176, 80),
+        (32, 32),
+    ];
+    for (map, expected) in maps.iter().zip(expected) {
+        assert_eq!(
+            (map.pixel_width(), map.pixel_height()),
+            expected,
+            "{:?} map",
+            map.orientation
+        );
+
+        // The bounds are the smallest rectangle containing the bounding boxes of all tiles.
+        let (mut right, mut bottom) = (0.0f32, 0.0f32);
+        for x in 0..map.width as i32 {
+            for y in 0..map.height as i32 {
+                let (px, py) = map.tile_to_pixel(x, y);
+                assert!(px >= 0.0 && py >= 0.0);
+                right = right.max(px + map.tile_width as f32);
+                bottom = bottom.max(py + map.tile_height as f32);
+            }
+        }
+        let bounds = map.bounds();
+        assert_eq!((bounds.x, bounds.y), (0.0, 0.0));
+        assert_eq!((bounds.width, bounds.height), (right, bottom));
+    }
+}
+
+#[test]
+fn test_finite_tiles_iterator() {
+    let r = Loader::new().load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let layer = as_finite(r.get_layer(0).unwrap().as_tile_layer().unwrap());
+
+    let mut count = 0;
+    for ((x, y), tile) in layer.tiles() {
+        assert_eq!(layer.get_tile(x, y).unwrap().id(), tile.id());
+        count += 1;
+    }
+    let expected = (0..layer.height() as i32)
+        .flat_map(|y| (0..layer.width() as i32).map(move |x| (x, y)))
+        .filter(|&(x, y)| layer.get_tile(x, y).is_some())
+        .count();
+    assert!(count > 0);
+    assert_eq!(count, expected);
+    assert_eq!(layer.tile_data().count(), expected);
+
+    let ((x, y), _) = layer.tile_data().next().unwrap();
+    assert!((0..layer.width() as i32).contains(&x));
+    assert!((0..layer.height() as i32).contains(&y));
+}
+
+#[test]
+fn test_finite_layer_tile_storage() {
+    // Tiles from tilesets whose ranges of IDs aren't known up front must all be kept intact.
+    let mut builder = TileLayerBuilder::new(8, 8);
+    let mut expected = Vec::new();
+    for i in 0..64 {
+        let mut tile = LayerTileData::new(i % 3, i as u32 * 1000);
+        tile.flip_h = i % 2 == 0;
+        tile.flip_d = i % 5 == 0;
+        builder = builder
+            .set_tile(i as i32 % 8, i as i32 / 8, Some(tile))
+            .unwrap();
+        expected.push(tile);
+    }
+    builder = builder.set_tile(7, 7, None).unwrap();
+    expected.pop();
+    let tileset = Arc::new(
+        Loader::new()
+            .load_tsx_tileset("assets/tilesheet.tsx")
+            .unwrap(),
+    );
+    let map = MapBuilder::new(tiled::Orientation::Orthogonal, 8, 8, 32, 32)
+        .tileset(tileset.clone())
+        .tileset(tileset.clone())
+        .tileset(tileset)
+        .layer(builder.build())
+        .build()
+        .unwrap();
+    let data = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert_eq!(data.get_tile_data(7, 7), None);
+    let tiles: Vec<_> = data.tile_data().map(|(_, tile)| tile).collect();
+    assert_eq!(tiles, expected);
+}
+
+#[test]
+fn test_sparse_tile_layers() {
+    let dense = Loader::new().load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let dense = as_finite(dense.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert!(!dense.is_sparse());
+
+    let mut loader = Loader::new();
+    loader.set_sparse_tile_layer_threshold(Some(0.0));
+    let map = loader.load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let sparse = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert!(sparse.is_sparse());
+    assert_eq!(*sparse, *dense);
+    for y in 0..dense.height() as i32 {
+        for x in 0..dense.width() as i32 {
+            assert_eq!(sparse.get_tile_data(x, y), dense.get_tile_data(x, y));
+        }
+    }
+
+    // Layers that aren't empty enough are left as they are.
+    loader.set_sparse_tile_layer_threshold(Some(1.0));
+    let map = loader.load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert!(!layer.is_sparse());
+
+    // The storage is picked while decoding, right at the threshold.
+    let len = (dense.width() * dense.height()) as f32;
+    let empty = len - dense.tile_data().count() as f32;
+    for (threshold, expect_sparse) in [(empty / len, true), ((empty + 1.0) / len, false)] {
+        loader.set_sparse_tile_layer_threshold(Some(threshold));
+        let map = loader.load_tmx_map("assets/tiled_csv.tmx").unwrap();
+        let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+        assert_eq!(layer.is_sparse(), expect_sparse);
+        assert_eq!(*layer, *dense);
+    }
+
+    // Streamed layers are stored sparsely too.
+    loader.set_sparse_tile_layer_threshold(Some(0.0));
+    let mut streamed = None;
+    loader
+        .stream_tmx_map("assets/tiled_csv.tmx", |event| {
+            if let MapEvent::LayerParsed(layer) = event {
+                streamed.get_or_insert(layer);
+            }
+            Ok(())
+        })
+        .unwrap();
+    let streamed = streamed.unwrap();
+    let map = MapBuilder::new(tiled::Orientation::Orthogonal, 100, 100, 32, 32)
+        .tileset(streamed.tileset(0).unwrap().clone())
+        .layer(streamed.into_data())
+        .build()
+        .unwrap();
+    let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+    assert!(layer.is_sparse());
+    assert_eq!(*layer, *dense);
+}
+
+#[test]
+fn test_layer_lookup_by_name() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+
+    assert_eq!(map.get_layer_by_name("tile-1").unwrap().id(), LayerId(1));
+    assert!(map.get_layer_by_name("tile-2").is_none());
+    let group = map.get_layer_by_name("group-1").unwrap();
+    let group = group.as_group_layer().unwrap();
+    assert_eq!(group.get_layer_by_name("tile-2").unwrap().id(), LayerId(5));
+
+    assert_eq!(map.layer_at_path("tile-1").unwrap().id(), LayerId(1));
+    assert_eq!(
+        map.layer_at_path("group-1/tile-2").unwrap().id(),
+        LayerId(5)
+    );
+    assert_eq!(
+        map.layer_at_path("group-2/group-3").unwrap().id(),
+        LayerId(8)
+    );
+    assert_eq!(
+        map.layer_at_path("group-2/group-3/tile-3").unwrap().id(),
+        LayerId(9)
+    );
+    assert!(map.layer_at_path("group-2/tile-3").is_none());
+    assert!(map.layer_at_path("tile-1/tile-2").is_none());
+    assert!(map.layer_at_path("").is_none());
+}
+
+#[test]
+fn test_object_lookup_by_id() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_property.tmx")
+        .unwrap();
+    let object = map.object_by_id(ObjectId(2)).unwrap();
+    assert_eq!(object.id(), ObjectId(2));
+    let target = match object.properties.get("object property") {
+        Some(PropertyValue::ObjectValue(id)) => map.object_by_id(ObjectId(*id)).unwrap(),
+        _ => panic!("Object property not found"),
+    };
+    assert_eq!(target.id(), ObjectId(3));
+    assert!(map.object_by_id(ObjectId(0)).is_none());
+
+    let layer = map.get_layer(1).unwrap().as_object_layer().unwrap();
+    assert_eq!(layer.object_by_id(ObjectId(3)).unwrap().id(), ObjectId(3));
+    assert!(layer.object_by_id(ObjectId(42)).is_none());
+
+    // Objects in group layers are indexed too.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_group_transforms.tmx")
+        .unwrap();
+    assert_eq!(map.object_by_id(ObjectId(1)).unwrap().name, "nested");
+}
+
+#[test]
+fn test_id_handles() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_group_transforms.tmx")
+        .unwrap();
+    assert_eq!(map.layer_by_id(LayerId(3)).unwrap().name, "inner");
+    assert_eq!(map.layer_by_id(LayerId(5)).unwrap().name, "top-image");
+    assert!(map.layer_by_id(LayerId(6)).is_none());
+    assert_eq!(map.object_by_id(ObjectId(1)).unwrap().name, "nested");
+
+    // IDs keep referring to the same layers and objects as the map is changed.
+    let id = map.push_layer(ObjectLayerBuilder::new().build()).unwrap();
+    map.layer_by_id_mut(LayerId(4)).unwrap().name = "renamed".to_owned();
+    let layer = map.layer_by_id_mut(LayerId(4)).unwrap();
+    let mut objects = layer.as_object_layer_mut().unwrap();
+    objects.get_object_mut(ObjectId(1)).unwrap().x = 16.0;
+    assert_eq!(map.object_by_id(ObjectId(1)).unwrap().x, 16.0);
+    assert_eq!(
+        map.layer_at_path("outer/inner/renamed").unwrap().id(),
+        LayerId(4)
+    );
+    assert_eq!(map.layer_by_id(id).unwrap().id(), id);
+
+    assert_eq!(
+        PropertyValue::ObjectValue(3).as_object_id(),
+        Some(ObjectId(3))
+    );
+    assert_eq!(PropertyValue::ObjectValue(0).as_object_id(), None);
+    assert_eq!(PropertyValue::IntValue(3).as_object_id(), None);
+}
+
+#[test]
+fn test_embedded_image() {
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_embedded_image.tmx")
+        .unwrap();
+    let layer = r.get_layer(0).unwrap().as_image_layer().unwrap();
+    let image = layer.image.as_ref().unwrap();
+    assert_eq!((image.width, image.height), (1, 1));
+    assert_eq!(image.source.as_path(), None);
+    if let ImageSource::Embedded { format, data } = &image.source {
+        assert_eq!(format, "png");
+        assert_eq!(&data[1..4], b"PNG");
+    } else {
+        panic!("Expected an embedded image");
+    }
+}
+
+#[cfg(feature = "image-loading")]
+#[test]
+fn test_image_loading() {
+    let mut reader = tiled::FilesystemResourceReader::new();
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+    let mut image = tileset.image.clone().unwrap();
+    let pixels = image.load_pixels(&mut reader).unwrap();
+    assert_eq!(
+        (pixels.width() as i32, pixels.height() as i32),
+        (image.width, image.height)
+    );
+    assert_eq!(pixels.get_pixel(0, 0).0[3], 255);
+
+    // Key out the colour of the top left pixel.
+    let [red, green, blue, _] = pixels.get_pixel(0, 0).0;
+    image.transparent_colour = Some(Color {
+        alpha: 255,
+        red,
+        green,
+        blue,
+    });
+    let keyed = image.load_pixels(&mut reader).unwrap();
+    assert_eq!(keyed.get_pixel(0, 0).0, [red, green, blue, 0]);
+    assert_eq!(
+        keyed.pixels().filter(|pixel| pixel.0[3] == 0).count(),
+        pixels
+            .pixels()
+            .filter(|pixel| pixel.0[3] == 0 || pixel.0[..3] == [red, green, blue])
+            .count()
+    );
+
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_embedded_image.tmx")
+        .unwrap();
+    let layer = map.get_layer(0).unwrap().as_image_layer().unwrap();
+    let pixels = layer
+        .image
+        .as_ref()
+        .unwrap()
+        .load_pixels(&mut reader)
+        .unwrap();
+    assert_eq!(pixels.dimensions(), (1, 1));
+}
+
+#[cfg(feature = "image-loading")]
+#[test]
+fn test_atlas_packing() {
+    let mut reader = tiled::FilesystemResourceReader::new();
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet_image_collection.tsx")
+        .unwrap();
+    let atlas = tileset.pack_atlas(&mut reader).unwrap();
+    assert_eq!(atlas.tile_rects().len(), 2);
+
+    let sheet = tileset.get_tile(0).unwrap().image.clone().unwrap();
+    let sheet = sheet.load_pixels(&mut reader).unwrap();
+    for (id, rect) in atlas.tile_rects() {
+        let (_, source) = tileset.get_tile(id).unwrap().image_source().unwrap();
+        // Regions that don't fit in their image are clipped.
+        assert_eq!(
+            (rect.width, rect.height),
+            (
+                source.width.min(sheet.width() as i32 - source.x),
+                source.height.min(sheet.height() as i32 - source.y)
+            )
+        );
+        assert!(rect.x + rect.width <= atlas.image.width() as i32);
+        assert!(rect.y + rect.height <= atlas.image.height() as i32);
+        for (x, y) in [(0, 0), (rect.width - 1, rect.height - 1), (10, 20)] {
+            assert_eq!(
+                atlas
+                    .image
+                    .get_pixel((rect.x + x) as u32, (rect.y + y) as u32),
+                sheet.get_pixel((source.x + x) as u32, (source.y + y) as u32)
+            );
+        }
+    }
+    let (first, second) = (atlas.tile_rect(0).unwrap(), atlas.tile_rect(1).unwrap());
+    assert!(
+        first.x + first.width <= second.x
+            || second.x + second.width <= first.x
+            || first.y + first.height <= second.y
+            || second.y + second.height <= first.y
+    );
+    assert_eq!(atlas.tile_rect(2), None);
+    assert_eq!(atlas.tile_uv(2), None);
+
+    // Regular tilesets can be packed too.
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+    let atlas = tileset.pack_atlas(&mut reader).unwrap();
+    assert_eq!(atlas.tile_rects().len(), tileset.tilecount as usize);
+}
+
+#[test]
+fn test_tileset_render_options() {
+    let mut loader = Loader::new();
+    let tileset = loader
+        .load_tsx_tileset("assets/tilesheet_render_options.tsx")
+        .unwrap();
+    assert_eq!(tileset.object_alignment, ObjectAlignment::TopRight);
+    assert_eq!(tileset.tile_render_size, TileRenderSize::Grid);
+    assert_eq!(tileset.fill_mode, FillMode::PreserveAspectFit);
+    assert_eq!(
+        tileset.grid,
+        Some(TilesetGrid {
+            orientation: tiled::Orientation::Isometric,
+            width: 64,
+            height: 32
+        })
+    );
+    assert_eq!(
+        tileset.transformations,
+        Transformations {
+            hflip: true,
+            vflip: false,
+            rotate: true,
+            prefer_untransformed: true
+        }
+    );
+
+    let tileset = loader.load_tsx_tileset("assets/tilesheet.tsx").unwrap();
+    assert_eq!(tileset.object_alignment, ObjectAlignment::Unspecified);
+    assert_eq!(tileset.tile_render_size, TileRenderSize::Tile);
+    assert_eq!(tileset.fill_mode, FillMode::Stretch);
+    assert_eq!(tileset.grid, None);
+    assert_eq!(tileset.transformations, Transformations::default());
+}
+
+#[test]
+fn test_tile_animation() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet_animation.tsx")
+        .unwrap();
+    let tile = tileset.get_tile(0).unwrap();
+    assert_eq!(tile.frame_at(0), Some(1));
+    assert_eq!(tile.frame_at(99), Some(1));
+    assert_eq!(tile.frame_at(100), Some(2));
+    assert_eq!(tile.frame_at(350), Some(3));
+    assert_eq!(tile.frame_at(600), Some(1));
+    assert_eq!(tileset.get_tile(1).unwrap().frame_at(100), None);
+
+    let frames = tile.animation.as_ref().unwrap();
+    let mut state = AnimationState::new();
+    state.looping = false;
+    state.advance(250);
+    assert_eq!(state.current_tile(frames), Some(2));
+    assert!(!state.is_finished(frames));
+    state.advance(10_000);
+    assert_eq!(state.current_tile(frames), Some(3));
+    assert!(state.is_finished(frames));
+    state.reset();
+    assert_eq!(state.current_frame(frames), frames.first());
+}
+
+#[test]
+fn test_lazy_tilesets() {
+    let eager = Loader::new()
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+
+    let mut loader = Loader::new();
+    loader.set_lazy_tilesets(true);
+    let mut map = loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert!(map.tilesets().is_empty());
+    assert_eq!(map.lazy_tilesets().len(), 1);
+    let tileset_ref = &map.lazy_tilesets()[0];
+    assert_eq!(
+        tileset_ref.path(),
+        Some(PathBuf::from("assets/tilesheet.tsx").as_path())
+    );
+    assert!(tileset_ref.get().is_none());
+    assert!(loader.cache().get_tileset("assets/tilesheet.tsx").is_none());
+
+    tileset_ref.load(&mut loader).unwrap();
+    assert!(tileset_ref.get().is_some());
+    assert!(loader.cache().get_tileset("assets/tilesheet.tsx").is_some());
+
+    map.resolve_tilesets(&mut loader).unwrap();
+    assert!(map.lazy_tilesets().is_empty());
+    assert_eq!(map, eager);
+
+    // Tilesets already present in the cache are used right away.
+    let map = loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert!(map.lazy_tilesets().is_empty());
+    assert_eq!(map.tilesets().len(), 1);
+
+    // Tiles can be looked at before the tilesets are resolved, finding the embedded tilesets.
+    let mut loader = Loader::new();
+    loader.set_lazy_tilesets(true);
+    let mut map = loader
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    assert!(map.tilesets().is_empty());
+    assert_eq!(map.tileset(0).unwrap().name, "tilesheet");
+    assert!(map.tileset(1).is_none());
+    let (mut loaded, mut unloaded) = (0, 0);
+    for layer in map.layers() {
+        let owned = layer.to_owned_data();
+        let tiles = match layer.as_tile_layer() {
+            Some(TileLayer::Infinite(tiles)) => tiles,
+            _ => continue,
+        };
+        for ((x, y), tile) in tiles.tiles() {
+            if tile.tileset_index() == 0 {
+                loaded += 1;
+                assert_eq!(tile.get_tileset().name, "tilesheet");
+                assert_eq!(tile.try_get_tileset().unwrap().name, "tilesheet");
+                assert!(tile.get_tile().is_some());
+                assert_eq!(owned.get_tile(x, y), Some(tile.to_owned_tile()));
+            } else {
+                unloaded += 1;
+                assert!(tile.try_get_tileset().is_none());
+                assert!(tile.get_tile().is_none());
+                assert!(owned.get_tile(x, y).is_none());
+                assert_eq!(tile.collision_shapes(x, y, 0.0, 0.0).count(), 0);
+            }
+        }
+    }
+    assert!(loaded > 0 && unloaded > 0);
+
+    // Tiles can be placed before the tilesets are resolved, checking the ones that are loaded.
+    let tileset_count = map.lazy_tilesets().len();
+    let mut layer = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_infinite_tile_layer_mut()
+        .unwrap();
+    layer
+        .set_tile(0, 0, Some(LayerTileData::new(0, 5)))
+        .unwrap();
+    layer
+        .set_tile(1, 0, Some(LayerTileData::new(1, 5)))
+        .unwrap();
+    assert!(layer
+        .set_tile(2, 0, Some(LayerTileData::new(0, 10_000)))
+        .is_err());
+    assert!(layer
+        .set_tile(2, 0, Some(LayerTileData::new(tileset_count, 0)))
+        .is_err());
+    assert_eq!(layer.get_tile_data(1, 0), Some(LayerTileData::new(1, 5)));
+}
+
+#[test]
+fn test_flattened_layers() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_group_transforms.tmx")
+        .unwrap();
+    let layers: Vec<_> = map.flattened_layers().collect();
+    let names: Vec<_> = layers.iter().map(|l| l.layer.name.as_str()).collect();
+    assert_eq!(names, ["outer-tiles", "inner-objects", "top-image"]);
+
+    let tiles = &layers[0];
+    assert_eq!((tiles.offset_x, tiles.offset_y), (11.0, 22.0));
+    assert_eq!(tiles.opacity, 0.5);
+    assert_eq!((tiles.parallax_x, tiles.parallax_y), (0.5, 1.0));
+    assert_eq!(tiles.tint_color, "#ff8080".parse().ok());
+    assert!(tiles.visible);
+
+    let objects = &layers[1];
+    assert_eq!((objects.offset_x, objects.offset_y), (10.0, 20.0));
+    assert_eq!(objects.opacity, 0.25);
+    assert_eq!((objects.parallax_x, objects.parallax_y), (0.5, 2.0));
+    assert_eq!(objects.tint_color, "#80ff8080".parse().ok());
+    assert!(!objects.visible);
+
+    let image = &layers[2];
+    assert_eq!((image.offset_x, image.offset_y), (3.0, 0.0));
+    assert_eq!(image.opacity, 0.75);
+    assert_eq!(image.tint_color, None);
+    assert!(image.visible);
+
+    // Layers compute the same tints on their own, looking up their parent groups.
+    for flattened in &layers {
+        assert_eq!(flattened.layer.effective_tint_color(), flattened.tint_color);
+    }
+    let inner = map.get_layer(0).unwrap().as_group_layer().unwrap();
+    let inner = inner.get_layer(1).unwrap();
+    assert_eq!(inner.effective_tint_color(), "#80ff8080".parse().ok());
+}
+
+#[test]
+fn test_parent_links() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_group_transforms.tmx")
+        .unwrap();
+    let object = map.object_by_id(ObjectId(1)).unwrap();
+    let layer = object.parent_layer().unwrap();
+    assert_eq!(layer.name, "inner-objects");
+    let names: Vec<_> = std::iter::successors(layer.parent(), |layer| layer.parent())
+        .map(|layer| layer.name.clone())
+        .collect();
+    assert_eq!(names, ["inner", "outer"]);
+    assert!(map.get_layer(1).unwrap().parent().is_none());
+
+    // Clones and changed maps find the parents of their own layers.
+    let mut cloned = map.clone();
+    let layer = cloned.layer_at_path("outer/outer-tiles").unwrap();
+    assert_eq!(layer.parent().unwrap().name, "outer");
+    cloned.get_layer_mut(0).unwrap().name = "renamed".to_owned();
+    let object = cloned.object_by_id(ObjectId(1)).unwrap();
+    let outer = object.parent_layer().unwrap().parent().unwrap().parent();
+    assert_eq!(outer.unwrap().name, "renamed");
+
+    // Layers and objects sharing an ID, like the ones of maps from older Tiled versions, still
+    // find their own parents.
+    let map = Loader::new()
+        .load_tmx_map_from(
+            r#"<map version="1.0" orientation="orthogonal" width="2" height="2" tilewidth="32" tileheight="32">
+ <group name="a"><objectgroup name="a-objects"><object x="0" y="0"/></objectgroup></group>
+ <group name="b"><objectgroup name="b-objects"><object x="0" y="0"/></objectgroup></group>
+</map>"#
+                .as_bytes(),
+            "assets/parents.tmx",
+        )
+        .unwrap();
+    for name in ["a", "b"] {
+        let layer = map
+            .layer_at_path(&format!("{0}/{0}-objects", name))
+            .unwrap();
+        assert_eq!(layer.parent().unwrap().name, name);
+        let object = layer.as_object_layer().unwrap().get_object(0).unwrap();
+        assert_eq!(object.parent_layer().unwrap().name, layer.name);
+    }
+}
+
+#[test]
+fn test_attribute_errors() {
+    let load = |map: &'static str| {
+        Loader::with_reader(move |_: &Path| -> std::io::Result<_> {
+            Ok(std::io::Cursor::new(map.as_bytes()))
+        })
+        .load_tmx_map("map.tmx")
+    };
+
+    let err = load(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <map version=\"1.10\" orientation=\"orthogonal\" width=\"2\" height=\"2\" tilewidth=\"32\" tileheight=\"32\">\n \
+         <layer id=\"1\" name=\"Tiles\" height=\"2\"/>\n\
+         </map>",
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.location(),
+        Some((Path::new("map.tmx"), XmlPosition { line: 3, column: 2 }))
+    );
+    match err.inner() {
+        Error::MissingAttribute {
+            element,
+            attribute,
+            position,
+        } => {
+            assert_eq!(element, "layer");
+            assert_eq!(attribute, "width");
+            assert_eq!(*position, XmlPosition { line: 3, column: 2 });
+        }
+        err => panic!("Expected a missing attribute error, got {:?}", err),
+    }
+
+    let err = load(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+         <map version=\"1.10\" orientation=\"orthogonal\" width=\"two\" height=\"2\" tilewidth=\"32\" tileheight=\"32\"/>",
+    )
+    .unwrap_err();
+    assert_eq!(
+        err.to_string(),
+        "map.tmx:2:1: Invalid value 'two' for attribute 'width' in element 'map'"
+    );
+    match err.inner() {
+        Error::InvalidAttributeValue {
+            element,
+            attribute,
+            value,
+            position,
+        } => {
+            assert_eq!(element, "map");
+            assert_eq!(attribute, "width");
+            assert_eq!(value, "two");
+            assert_eq!(*position, XmlPosition { line: 2, column: 1 });
+        }
+        err => panic!("Expected an invalid attribute value error, got {:?}", err),
+    }
+}
+
+#[test]
+fn test_finite_layer_data_size() {
+    let load = |width: u32, height: u32, data: &str| {
+        let map = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <map version=\"1.10\" orientation=\"orthogonal\" width=\"2\" height=\"2\" tilewidth=\"32\" tileheight=\"32\">\n \
+             <layer id=\"1\" name=\"Tiles\" width=\"{}\" height=\"{}\">\n  \
+             <data encoding=\"csv\">{}</data>\n \
+             </layer>\n\
+             </map>",
+            width, height, data
+        );
+        Loader::with_reader(move |_: &Path| -> std::io::Result<_> {
+            Ok(Cursor::new(map.clone().into_bytes()))
+        })
+        .load_tmx_map("map.tmx")
+    };
+
+    assert!(load(2, 2, "0,0,0,0").is_ok());
+    for (width, height, data) in [(0, 2, "0,0"), (2, 2, "0,0,0"), (2, 2, "0,0,0,0,0")] {
+        let err = load(width, height, data).unwrap_err();
+        assert!(
+            matches!(err.inner(), Error::InvalidTileFound),
+            "Expected an invalid tile error, got {:?}",
+            err
+        );
+    }
+}
+
+#[test]
+fn test_infinite_chunk_data_size() {
+    let xml = |width: u32, height: u32| {
+        format!(
+            r#"<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="32" tileheight="32" infinite="1">
+ <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="tilesheet.png" width="64" height="64"/>
+ </tileset>
+ <layer id="1" name="Tiles" width="2" height="2">
+  <data encoding="csv"><chunk x="-4" y="0" width="{}" height="{}">1,2,3</chunk></data>
+ </layer>
+</map>"#,
+            width, height
+        )
+    };
+
+    // Chunks claiming to be larger than their data are rejected, or only get the tiles present in
+    // the data when the error is recovered from.
+    for (width, height) in [(2, 2), (65536, 65536), (u32::MAX, u32::MAX)] {
+        let err = Loader::new()
+            .load_tmx_map_from(xml(width, height).as_bytes(), "assets/chunks.tmx")
+            .unwrap_err();
+        assert!(
+            matches!(err.inner(), Error::InvalidTileFound),
+            "Expected an invalid tile error, got {:?}",
+            err
+        );
+
+        let map = Loader::new()
+            .with_error_tolerance(ErrorTolerance::Lenient)
+            .load_tmx_map_from(xml(width, height).as_bytes(), "assets/chunks.tmx")
+            .unwrap();
+        assert_eq!(map.warnings().len(), 1);
+        let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+            TileLayer::Infinite(layer) => layer,
+            TileLayer::Finite(_) => panic!("expected an infinite layer"),
+        };
+        assert_eq!(layer.tiles().count(), 3);
+        assert_eq!(layer.bounds(), Some(((-16, 0), (-1, 15))));
+    }
+}
+
+#[test]
+fn test_tile_data_decoding() {
+    // CI runs this both with and without the `rayon` feature, which decodes the layers in
+    // parallel once the whole map has been read instead of while parsing it.
+    let gid = |x: i32, y: i32| (x.rem_euclid(7) + y.rem_euclid(5) * 3) as u32 % 12;
+    let positions: Vec<_> = (-2..2)
+        .flat_map(|y| (-2..2).map(move |x| (x * 16, y * 16)))
+        .collect();
+    let chunk = |(x, y): (i32, i32), csv: bool| {
+        let gids = (0..16 * 16).map(|index| gid(x + index % 16, y + index / 16));
+        let data: Vec<_> = if csv {
+            gids.map(|gid| gid.to_string()).collect()
+        } else {
+            gids.map(|gid| match gid {
+                0 => "<tile/>".to_owned(),
+                gid => format!("<tile gid=\"{}\"/>", gid),
+            })
+            .collect()
+        };
+        format!(
+            r#"<chunk x="{}" y="{}" width="16" height="16">{}</chunk>"#,
+            x,
+            y,
+            data.join(if csv { "," } else { "" })
+        )
+    };
+    let csv: String = positions.iter().map(|&pos| chunk(pos, true)).collect();
+    let xml: String = positions.iter().map(|&pos| chunk(pos, false)).collect();
+    let map = Loader::new()
+        .load_tmx_map_from(
+            format!(
+                r#"<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="32" tileheight="32" infinite="1">
+ <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="16" columns="4">
+  <image source="tilesheet.png" width="128" height="128"/>
+ </tileset>
+ <layer id="1" name="CSV" width="2" height="2"><data encoding="csv">{}</data></layer>
+ <layer id="2" name="XML" width="2" height="2"><data>{}</data></layer>
+</map>"#,
+                csv, xml
+            )
+            .as_bytes(),
+            "assets/chunks.tmx",
+        )
+        .unwrap();
+    for layer in map.layers() {
+        let layer = match layer.as_tile_layer().unwrap() {
+            TileLayer::Infinite(layer) => layer,
+            TileLayer::Finite(_) => panic!("expected an infinite layer"),
+        };
+        for y in -32..32 {
+            for x in -32..32 {
+                let id = layer.get_tile(x, y).map(|tile| tile.id() + 1);
+                assert_eq!(id.unwrap_or(0), gid(x, y), "tile at {:?}", (x, y));
+            }
+        }
+    }
+
+    // Every encoding of the same finite map decodes to the same tiles.
+    let mut loader = Loader::new();
+    let maps: Vec<_> = [
+        "assets/tiled_base64.tmx",
+        "assets/tiled_base64_zlib.tmx",
+        "assets/tiled_base64_gzip.tmx",
+        "assets/tiled_base64_zstandard.tmx",
+        "assets/tiled_csv.tmx",
+    ]
+    .iter()
+    .map(|path| loader.load_tmx_map(path).unwrap())
+    .collect();
+    let tiles = |map: &Map| {
+        let layer = as_finite(map.get_layer(0).unwrap().as_tile_layer().unwrap());
+        (0..100)
+            .flat_map(|y| (0..100).map(move |x| (x, y)))
+            .map(|(x, y)| layer.get_tile(x, y).map(|tile| tile.id()))
+            .collect::<Vec<_>>()
+    };
+    for map in &maps[1..] {
+        assert_eq!(tiles(map), tiles(&maps[0]));
+    }
+}
+
+#[test]
+fn test_error_locations() {
+    let mut loader = Loader::with_reader(|path: &Path| -> std::io::Result<_> {
+        let contents: &'static str = if path == Path::new("map.tmx") {
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <map version=\"1.10\" orientation=\"orthogonal\" width=\"2\" height=\"2\" tilewidth=\"32\" tileheight=\"32\">\n \
+             <tileset firstgid=\"1\" source=\"tileset.tsx\"/>\n\
+             </map>"
+        } else {
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <tileset name=\"tileset\" tilewidth=\"32\" tileheight=\"32\" tilecount=\"1\" columns=\"1\">\n \
+             <tile id=\"0\">\n  \
+             <animation>\n   \
+             <frame tileid=\"0\"/>\n  \
+             </animation>\n \
+             </tile>\n\
+             </tileset>"
+        };
+        Ok(std::io::Cursor::new(contents.as_bytes()))
+    });
+
+    // Errors in a tileset are reported in the tileset's file, not the map's.
+    let err = loader.load_tmx_map("map.tmx").unwrap_err();
+    assert_eq!(
+        err.location(),
+        Some((Path::new("tileset.tsx"), XmlPosition { line: 5, column: 4 }))
+    );
+    assert!(matches!(
+        err.inner(),
+        Error::MissingAttribute { attribute, .. } if attribute == "duration"
+    ));
+
+    // Errors that aren't about attributes also get a location.
+    let err = Loader::with_reader(|_: &Path| -> std::io::Result<_> {
+        Ok(std::io::Cursor::new(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n\
+             <map version=\"1.10\" orientation=\"orthogonal\" width=\"1\" height=\"1\" tilewidth=\"32\" tileheight=\"32\">\n \
+             <layer id=\"1\" name=\"Tiles\" width=\"1\" height=\"1\">\n  \
+             <data encoding=\"csv\">x</data>\n \
+             </layer>\n\
+             </map>"
+                .as_bytes(),
+        ))
+    })
+    .load_tmx_map("map.tmx")
+    .unwrap_err();
+    assert_eq!(
+        err.location(),
+        Some((Path::new("map.tmx"), XmlPosition { line: 4, column: 3 }))
+    );
+    assert!(matches!(err.inner(), Error::CsvDecodingError(_)));
+}
+
+#[test]
+fn test_gid_lookup() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+
+    assert_eq!(map.tileset_first_gids(), [Gid(1), Gid(85)]);
+    assert!(map.tile_for_gid(0).is_none());
+    let (tileset_index, tile) = map.tile_for_gid(84).unwrap();
+    assert_eq!(tileset_index, 0);
+    assert!(std::ptr::eq(tile.tileset(), &*map.tilesets()[0]));
+    // Flipping bits are ignored.
+    let (tileset_index, tile) = map.tile_for_gid(85 | 0x80000000).unwrap();
+    assert_eq!(tileset_index, 1);
+    assert!(std::ptr::eq(tile.tileset(), &*map.tilesets()[1]));
+    assert!(map.tile_for_gid(85 + 84).is_none());
+
+    assert_eq!(map.gid_for(0, 0), Some(1));
+    assert_eq!(map.gid_for(1, 3), Some(88));
+    assert_eq!(map.gid_for(1, 84), None);
+    assert_eq!(map.gid_for(2, 0), None);
+
+    let tileset = Arc::new(
+        Loader::new()
+            .load_tsx_tileset("assets/tilesheet.tsx")
+            .unwrap(),
+    );
+    let built = MapBuilder::new(tiled::Orientation::Orthogonal, 1, 1, 32, 32)
+        .tileset(tileset.clone())
+        .tileset(tileset)
+        .build()
+        .unwrap();
+    assert_eq!(built.tileset_first_gids(), [Gid(1), Gid(85)]);
+    assert_eq!(built.gid_for(1, 0), Some(85));
+    assert_eq!(built.tile_for_gid(85).unwrap().0, 1);
+}
+
+#[test]
+fn test_object_geometry() {
+    let triangle = ObjectShape::Polygon {
+        points: vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(0.0, 10.0),
+        ],
+    };
+    assert!(triangle.contains_point(2.0, 2.0));
+    assert!(!triangle.contains_point(8.0, 8.0));
+    assert!(!triangle.contains_point(-1.0, 1.0));
+    assert_eq!(
+        triangle.bounding_box(5.0, 5.0, 0.0),
+        Rect {
+            x: 5.0,
+            y: 5.0,
+            width: 10.0,
+            height: 10.0
+        }
+    );
+
+    let ellipse = ObjectShape::Ellipse {
+        width: 20.0,
+        height: 10.0,
+    };
+    assert!(ellipse.contains_point(10.0, 5.0));
+    assert!(ellipse.contains_point(19.0, 5.0));
+    assert!(!ellipse.contains_point(1.0, 1.0));
+    let bounds = ellipse.bounding_box(0.0, 0.0, 90.0);
+    assert!((bounds.x + 10.0).abs() < 1e-4);
+    assert!((bounds.width - 10.0).abs() < 1e-4);
+    assert!((bounds.height - 20.0).abs() < 1e-4);
+
+    let polyline = ObjectShape::Polyline {
+        points: vec![
+            Point::new(0.0, 0.0),
+            Point::new(10.0, 0.0),
+            Point::new(0.0, 10.0),
+        ],
+    };
+    assert!(!polyline.contains_point(2.0, 2.0));
+    assert!(!polyline.is_closed());
+    assert_eq!(polyline.winding(), None);
+    assert_eq!(polyline.iter_segments().count(), 2);
+    assert_eq!(
+        triangle.aabb(),
+        Rect {
+            x: 0.0,
+            y: 0.0,
+            width: 10.0,
+            height: 10.0
+        }
+    );
+    assert!(triangle.is_closed());
+    assert_eq!(
+        triangle.iter_segments().collect::<Vec<_>>(),
+        [
+            (Point::new(0.0, 0.0), Point::new(10.0, 0.0)),
+            (Point::new(10.0, 0.0), Point::new(0.0, 10.0)),
+            (Point::new(0.0, 10.0), Point::new(0.0, 0.0)),
+        ]
+    );
+    // The Y axis points down, so going right and then down is clockwise.
+    assert_eq!(triangle.winding(), Some(Winding::Clockwise));
+    let loop_points = vec![
+        Point::new(0.0, 0.0),
+        Point::new(0.0, 10.0),
+        Point::new(10.0, 0.0),
+        Point::new(0.0, 0.0),
+    ];
+    let closed = ObjectShape::Polyline {
+        points: loop_points,
+    };
+    assert!(closed.is_closed());
+    assert_eq!(closed.winding(), Some(Winding::CounterClockwise));
+    let flat = ObjectShape::Polygon {
+        points: vec![(0.0, 0.0).into(), (5.0, 0.0).into(), (10.0, 0.0).into()],
+    };
+    assert_eq!(flat.winding(), None);
+    assert_eq!(ellipse.iter_segments().count(), 0);
+    assert_eq!(<(f32, f32)>::from(Point::from((1.0, 2.0))), (1.0, 2.0));
+    assert_eq!(
+        ObjectShape::Point(3.0, 4.0).bounding_box(3.0, 4.0, 45.0).x,
+        3.0
+    );
+
+    // A rectangle rotated a quarter turn clockwise around its top left corner.
+    let mut object = ObjectData::from_shape(
+        1,
+        ObjectShape::Rect {
+            width: 20.0,
+            height: 10.0,
+        },
+    );
+    object.x = 100.0;
+    object.y = 100.0;
+    object.rotation = 90.0;
+    assert!(object.contains_point(95.0, 115.0));
+    assert!(!object.contains_point(105.0, 105.0));
+    assert!(object.bounding_box().contains_point(91.0, 119.0));
+}
+
+#[test]
+fn test_tile_collision_shapes() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_tile_collision.tmx")
+        .unwrap();
+    let layer = map.get_layer(0).unwrap();
+    let tiles = layer.as_tile_layer().unwrap();
+    let bounds = |x: i32| {
+        let shapes: Vec<ObjectData> = tiles
+            .get_tile(x, 0)
+            .unwrap()
+            .collision_shapes(x, 0, layer.offset_x, layer.offset_y)
+            .collect();
+        assert_eq!(shapes.len(), 1);
+        shapes[0].bounding_box()
+    };
+
+    assert_eq!(
+        bounds(0),
+        Rect {
+            x: 104.0,
+            y: 58.0,
+            width: 10.0,
+            height: 6.0
+        }
+    );
+    // Flipped horizontally.
+    assert_eq!(
+        bounds(1),
+        Rect {
+            x: 150.0,
+            y: 58.0,
+            width: 10.0,
+            height: 6.0
+        }
+    );
+    // Flipped diagonally.
+    assert_eq!(
+        bounds(2),
+        Rect {
+            x: 172.0,
+            y: 54.0,
+            width: 6.0,
+            height: 10.0
+        }
+    );
+}
+
+#[test]
+fn test_lenient_loading() {
+    let path = "assets/tiled_partially_broken.tmx";
+    assert!(Loader::new().load_tmx_map(path).is_err());
+
+    let map = Loader::new()
+        .with_error_tolerance(ErrorTolerance::Lenient)
+        .load_tmx_map(path)
+        .unwrap();
+    let warnings: Vec<(u64, &str)> = map
+        .warnings()
+        .iter()
+        .map(|warning| {
+            assert_eq!(warning.path, Path::new(path));
+            (warning.position.line, warning.description.as_str())
+        })
+        .collect();
+    assert_eq!(warnings.len(), 4);
+    assert_eq!(warnings[0], (5, "Unknown property value type 'vector'"));
+    assert_eq!(warnings[1].0, 6);
+    assert_eq!(warnings[2], (10, "Invalid tile found in map being parsed"));
+    assert_eq!(warnings[3].0, 17);
+
+    assert_eq!(map.properties.len(), 1);
+    assert_eq!(map.properties["valid"], PropertyValue::IntValue(1));
+
+    let objects = map.get_layer(1).unwrap().as_object_layer().unwrap();
+    assert_eq!(
+        objects.get_object(0).unwrap().shape,
+        ObjectShape::Rect {
+            width: 32.0,
+            height: 16.0
+        }
+    );
+    assert!(matches!(
+        objects.get_object(1).unwrap().shape,
+        ObjectShape::Polyline { .. }
+    ));
+}
+
+#[test]
+fn test_reload_if_changed() {
+    /// Keeps files in memory along with a version number, which is used as their modification
+    /// time.
+    struct MemoryReader(HashMap<PathBuf, (String, u64)>);
+
+    impl MemoryReader {
+        fn write(&mut self, path: &str, contents: &str) {
+            let file = self.0.entry(PathBuf::from(path)).or_default();
+            *file = (contents.to_owned(), file.1 + 1);
+        }
+    }
+
+    impl ResourceReader for MemoryReader {
+        type Resource = Cursor<Vec<u8>>;
+        type Error = std::io::Error;
+
+        fn read_from(&mut self, path: &Path) -> std::result::Result<Self::Resource, Self::Error> {
+            self.0
+                .get(path)
+                .map(|(contents, _)| Cursor::new(contents.clone().into_bytes()))
+                .ok_or_else(|| std::io::Error::from(std::io::ErrorKind::NotFound))
+        }
+
+        fn modified(&mut self, path: &Path) -> Option<SystemTime> {
+            self.0
+                .get(path)
+                .map(|(_, version)| SystemTime::UNIX_EPOCH + Duration::from_secs(*version))
+        }
+    }
+
+    let map_xml = |tile_width: u32| {
+        format!(
+            r#"<map version="1.10" orientation="orthogonal" width="2" height="1" tilewidth="{}" tileheight="32">
+ <tileset firstgid="1" source="tileset.tsx"/>
+ <layer id="1" name="tiles" width="2" height="1"><data encoding="csv">1,2</data></layer>
+</map>"#,
+            tile_width
+        )
+    };
+    let tileset_xml = |name: &str| {
+        format!(
+            r#"<tileset name="{}" tilewidth="32" tileheight="32" tilecount="2" columns="2">
+ <image source="tiles.png" width="64" height="32"/>
+</tileset>"#,
+            name
+        )
+    };
+
+    let mut reader = MemoryReader(HashMap::new());
+    reader.write("map.tmx", &map_xml(32));
+    reader.write("tileset.tsx", &tileset_xml("first"));
+    let mut loader = Loader::with_reader(reader);
+    let mut map = loader.load_tmx_map("map.tmx").unwrap();
+    assert_eq!(map.tilesets()[0].name, "first");
+    assert!(!loader.reload_if_changed(&mut map).unwrap());
+
+    // Only the tileset changed, so it is swapped into the map.
+    loader
+        .reader_mut()
+        .write("tileset.tsx", &tileset_xml("second"));
+    assert!(loader.reload_if_changed(&mut map).unwrap());
+    assert_eq!(map.tilesets()[0].name, "second");
+    assert!(Arc::ptr_eq(
+        &map.tilesets()[0],
+        &loader.cache().get_tileset("tileset.tsx").unwrap()
+    ));
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(layer.get_tile(1, 0).unwrap().get_tileset().name, "second");
+    assert!(!loader.reload_if_changed(&mut map).unwrap());
+
+    // The map itself changed, so it is parsed again.
+    loader.reader_mut().write("map.tmx", &map_xml(16));
+    assert!(loader.reload_if_changed(&mut map).unwrap());
+    assert_eq!(map.tile_width, 16);
+    assert_eq!(map.tilesets()[0].name, "second");
+    assert!(!loader.reload_if_changed(&mut map).unwrap());
+
+    // Cached maps are returned as they are, and reloading them updates the cache.
+    loader.set_cache_maps(true);
+    loader.load_tmx_map("map.tmx").unwrap();
+    loader.reader_mut().write("map.tmx", &map_xml(8));
+    let mut map = loader.load_tmx_map("map.tmx").unwrap();
+    assert_eq!(map.tile_width, 16);
+    assert!(loader.reload_if_changed(&mut map).unwrap());
+    assert_eq!(map.tile_width, 8);
+    assert_eq!(loader.cache().get_map("map.tmx").unwrap().tile_width, 8);
+    loader
+        .reader_mut()
+        .write("tileset.tsx", &tileset_xml("third"));
+    assert!(loader.reload_if_changed(&mut map).unwrap());
+    assert_eq!(map.tilesets()[0].name, "third");
+    assert_eq!(
+        loader.cache().get_map("map.tmx").unwrap().tilesets()[0].name,
+        "third"
+    );
+}
+
+#[test]
+fn test_map_caching() {
+    let path = "assets/tiled_base64_external.tmx";
+    // Maps aren't cached by default.
+    let mut loader = Loader::new();
+    loader.load_tmx_map(path).unwrap();
+    assert!(loader.cache().get_map(path).is_none());
+
+    loader.set_cache_maps(true);
+    let map = loader.load_tmx_map(path).unwrap();
+    assert!(*loader.cache().get_map(path).unwrap() == map);
+
+    // Cached maps are used instead of parsing the file again, unless a reload is forced.
+    let mut modified = map.clone();
+    modified.tile_width = 1;
+    loader.cache_mut().insert_map(path, Arc::new(modified));
+    assert_eq!(loader.load_tmx_map(path).unwrap().tile_width, 1);
+    assert_eq!(
+        loader.reload_tmx_map(path).unwrap().tile_width,
+        map.tile_width
+    );
+    assert_eq!(
+        loader.cache().get_map(path).unwrap().tile_width,
+        map.tile_width
+    );
+
+    // Maps loaded with other settings are no longer used.
+    loader.set_error_tolerance(ErrorTolerance::Lenient);
+    assert!(loader.cache().get_map(path).is_none());
+    // Including the ones put in the cache by others, such as loaders sharing it.
+    let mut modified = map.clone();
+    modified.tile_width = 1;
+    loader.cache_mut().insert_map(path, Arc::new(modified));
+    assert_eq!(
+        loader.load_tmx_map(path).unwrap().tile_width,
+        map.tile_width
+    );
+
+    // Maps with lazy tilesets aren't cached.
+    let mut loader = Loader::new();
+    loader.set_cache_maps(true);
+    loader.set_lazy_tilesets(true);
+    let map = loader.load_tmx_map(path).unwrap();
+    assert_eq!(map.lazy_tilesets().len(), 1);
+    assert!(loader.cache().get_map(path).is_none());
+}
+
+#[test]
+fn test_lru_resource_cache() {
+    let mut loader = Loader::new();
+    let tileset = Arc::new(loader.load_tsx_tileset("assets/tilesheet.tsx").unwrap());
+    let wangsets = Arc::new(
+        loader
+            .load_tsx_tileset("assets/tilesheet_wangsets.tsx")
+            .unwrap(),
+    );
+
+    let mut cache = LruResourceCache::new(2, usize::MAX);
+    assert!(cache.is_empty());
+    cache.insert_tileset("a.tsx", tileset.clone());
+    cache.insert_tileset("b.tsx", tileset.clone());
+    // Requesting a resource makes it the most recently used one.
+    assert!(cache.get_tileset("a.tsx").is_some());
+    cache.insert_tileset("c.tsx", wangsets.clone());
+    assert_eq!(cache.len(), 2);
+    assert!(cache.get_tileset("b.tsx").is_none());
+    assert!(cache.get_tileset("a.tsx").is_some());
+    assert!(cache.get_tileset("c.tsx").is_some());
+
+    // Replacing a resource doesn't count it twice.
+    let used = cache.used_bytes();
+    cache.insert_tileset("c.tsx", wangsets.clone());
+    assert_eq!(cache.used_bytes(), used);
+
+    cache.remove_tileset("a.tsx");
+    assert_eq!(cache.len(), 1);
+    assert!(cache.used_bytes() < used);
+
+    // Shrinking the byte budget evicts right away, and resources larger than it aren't kept.
+    cache.set_max_bytes(1);
+    assert!(cache.is_empty());
+    assert_eq!(cache.used_bytes(), 0);
+    cache.insert_tileset("a.tsx", tileset.clone());
+    assert!(cache.get_tileset("a.tsx").is_none());
+
+    cache.set_max_bytes(usize::MAX);
+    cache.insert_tileset("a.tsx", tileset);
+    cache.clear();
+    assert!(cache.is_empty());
+    assert_eq!(cache.used_bytes(), 0);
+
+    // Resources loaded through a loader are bounded too.
+    let mut loader = Loader::with_cache_and_reader(
+        LruResourceCache::new(usize::MAX, usize::MAX),
+        tiled::FilesystemResourceReader::new(),
+    );
+    loader.set_cache_maps(true);
+    loader
+        .load_tmx_map("assets/tiled_object_template.tmx")
+        .unwrap();
+    // The map, its tileset, its template and the template's tileset.
+    assert_eq!(loader.cache().len(), 4);
+    let used = loader.cache().used_bytes();
+    loader.cache_mut().clear_maps();
+    assert_eq!(loader.cache().len(), 3);
+    assert!(loader.cache().used_bytes() < used);
+    loader
+        .load_tmx_map("assets/tiled_object_template.tmx")
+        .unwrap();
+    loader.cache_mut().set_max_entries(1);
+    assert_eq!(loader.cache().len(), 1);
+
+    let mut loader = Loader::new();
+    loader
+        .load_tmx_map("assets/tiled_object_template.tmx")
+        .unwrap();
+    assert!(loader.cache().get_tileset("assets/tilesheet.tsx").is_some());
+    loader.cache_mut().clear();
+    assert!(loader.cache().get_tileset("assets/tilesheet.tsx").is_none());
+    assert!(loader.cache().templates.is_empty());
+}
+
+#[test]
+fn test_shared_resource_cache() {
+    let cache = SharedResourceCache::new();
+    let first = {
+        let cache = cache.clone();
+        std::thread::spawn(move || {
+            let mut loader =
+                Loader::with_cache_and_reader(cache, tiled::FilesystemResourceReader::new());
+            loader.set_cache_maps(true);
+            loader
+                .load_tmx_map("assets/tiled_base64_external.tmx")
+                .unwrap()
+        })
+        .join()
+        .unwrap()
+    };
+    assert!(cache.read().maps.len() == 1 && cache.read().tilesets.len() == 1);
+
+    // Another loader reuses the tileset loaded by the first one.
+    let mut loader =
+        Loader::with_cache_and_reader(cache.clone(), tiled::FilesystemResourceReader::new());
+    let second = loader.load_tmx_map("assets/tiled_hexagonal.tmx").unwrap();
+    assert!(Arc::ptr_eq(&first.tilesets()[0], &second.tilesets()[0]));
+
+    // Changing the settings of a loader that caches maps removes the ones cached by others too.
+    loader.set_cache_maps(true);
+    loader.load_tmx_map("assets/tiled_hexagonal.tmx").unwrap();
+    assert_eq!(cache.read().maps.len(), 2);
+    loader.set_lazy_tilesets(true);
+    assert!(cache.read().maps.is_empty());
+
+    loader.cache_mut().clear();
+    assert!(cache.get_tileset("assets/tilesheet.tsx").is_none());
+
+    // Other caches can be shared too.
+    let mut cache = SharedResourceCache::from_cache(LruResourceCache::new(1, usize::MAX));
+    let tileset = Arc::new(first.tilesets()[0].as_ref().clone());
+    std::thread::scope(|scope| {
+        let mut cache = cache.clone();
+        scope.spawn(move || cache.insert_tileset("a.tsx", tileset));
+    });
+    assert!(cache.get_tileset("a.tsx").is_some());
+    cache.insert_tileset("b.tsx", first.tilesets()[0].clone());
+    assert_eq!(cache.read().len(), 1);
+}
+
+#[test]
+fn test_load_map_from_source() {
+    let path = "assets/tiled_base64_external.tmx";
+    let bytes = std::fs::read(path).unwrap();
+    let mut loader = Loader::new();
+    let mut map = loader
+        .load_tmx_map_from(bytes.as_slice(), "assets/in_memory.tmx")
+        .unwrap();
+    let expected = Loader::new().load_tmx_map(path).unwrap();
+    assert_eq!(map.tilesets(), expected.tilesets());
+    assert_eq!(map.layers().len(), expected.layers().len());
+    assert!(map.layers().zip(expected.layers()).all(|(a, b)| *a == *b));
+    assert!(loader.cache().get_map("assets/in_memory.tmx").is_none());
+    assert!(!loader.reload_if_changed(&mut map).unwrap());
+
+    // External files are resolved relative to the virtual path.
+    let err = loader
+        .load_tmx_map_from(bytes.as_slice(), "elsewhere/map.tmx")
+        .unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::ResourceLoadingError { path, .. } if path == Path::new("elsewhere/tilesheet.tsx")
+    ));
+}
+
+#[test]
+fn test_user_classes() {
+    let xml = r##"<map version="1.10" orientation="orthogonal" width="1" height="1" tilewidth="32" tileheight="32" class="level">
+ <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="1" columns="1" class="terrain">
+  <image source="tilesheet.png" width="32" height="32"/>
+  <tile id="0" type="grass"/>
+  <wangsets>
+   <wangset name="ground" class="ground-set" type="corner" tile="-1">
+    <wangcolor name="dirt" class="dirt-color" color="#ff0000" tile="-1" probability="1"/>
+   </wangset>
+  </wangsets>
+ </tileset>
+ <layer id="1" name="tiles" width="1" height="1" class="floor"><data encoding="csv">1</data></layer>
+ <objectgroup id="2" name="objects">
+  <object id="1" type="enemy" x="0" y="0"/>
+  <object id="2" class="item" x="0" y="0"/>
+  <object id="3" x="0" y="0"/>
+ </objectgroup>
+</map>"##;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/classes.tmx")
+        .unwrap();
+    assert_eq!(map.user_class(), Some("level"));
+
+    let tileset = &map.tilesets()[0];
+    assert_eq!(tileset.user_class(), Some("terrain"));
+    assert_eq!(tileset.get_tile(0).unwrap().user_class(), Some("grass"));
+    assert_eq!(tileset.wang_sets[0].user_class(), Some("ground-set"));
+    assert_eq!(
+        tileset.wang_sets[0].wang_set_type,
+        tiled::WangSetType::Corner
+    );
+    assert_eq!(
+        tileset.wang_sets[0].wang_colors[0].user_class(),
+        Some("dirt-color")
+    );
+
+    let layer = map.get_layer(0).unwrap();
+    assert_eq!(layer.user_class(), Some("floor"));
+    let objects = map.get_layer(1).unwrap();
+    assert_eq!(objects.user_class(), None);
+    let classes: Vec<_> = objects
+        .as_object_layer()
+        .unwrap()
+        .objects()
+        .map(|object| object.user_class().map(str::to_owned))
+        .collect();
+    assert_eq!(
+        classes,
+        [Some("enemy".to_owned()), Some("item".to_owned()), None]
+    );
+
+    // Wang set and color classes are written to TSX files.
+    let mut tsx = Vec::new();
+    tileset.to_tsx_writer(&mut tsx).unwrap();
+    let tsx = String::from_utf8(tsx).unwrap();
+    assert!(tsx.contains(r#"class="ground-set""#) && tsx.contains(r#"class="dirt-color""#));
+}
+
+#[test]
+fn test_id_and_gid_conflicts() {
+    let xml = r##"<map version="1.10" orientation="orthogonal" width="1" height="1" tilewidth="32" tileheight="32">
+ <tileset firstgid="1" name="first" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="tilesheet.png" width="64" height="64"/>
+ </tileset>
+ <tileset firstgid="3" name="second" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="tilesheet.png" width="64" height="64"/>
+ </tileset>
+ <layer id="1" name="tiles" width="1" height="1"><data encoding="csv">3</data></layer>
+ <group id="2" name="group">
+  <objectgroup id="1" name="objects">
+   <object id="1" x="0" y="0"/>
+   <object id="1" x="32" y="0"/>
+  </objectgroup>
+ </group>
+</map>"##;
+    let path = "assets/conflicts.tmx";
+
+    let mut loader = Loader::new();
+    loader.set_deny_conflicts(true);
+    let error = loader.load_tmx_map_from(xml.as_bytes(), path).unwrap_err();
+    assert!(matches!(
+        error.inner(),
+        Error::OverlappingTilesets {
+            first_gid: 3,
+            other_first_gid: 1
+        }
+    ));
+    assert_eq!(
+        error.location().unwrap().1,
+        XmlPosition { line: 5, column: 2 }
+    );
+
+    // Conflicts are only warned about unless denied, or when recovered from.
+    let mut lenient = Loader::new().with_error_tolerance(ErrorTolerance::Lenient);
+    lenient.set_deny_conflicts(true);
+    for mut loader in [Loader::new(), lenient] {
+        let map = loader.load_tmx_map_from(xml.as_bytes(), path).unwrap();
+        let warnings: Vec<_> = map
+            .warnings()
+            .iter()
+            .map(|warning| (warning.position.line, warning.description.as_str()))
+            .collect();
+        assert_eq!(
+            warnings,
+            [
+                (
+                    5,
+                    "The GIDs of the tileset starting at 3 overlap with the ones of the tileset starting at 1"
+                ),
+                (9, "Duplicate layer ID 1"),
+                (9, "Duplicate object ID 1"),
+            ]
+        );
+        // Nothing is left out of the map.
+        assert_eq!(map.tilesets().len(), 2);
+        assert_eq!(map.layers().len(), 2);
+    }
+
+    // Elements without IDs are not considered duplicates.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert!(map.warnings().is_empty());
+}
+
+#[test]
+fn test_tilesets_after_layers() {
+    let tilesets = r##"<tileset firstgid="1" name="first" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="tilesheet.png" width="64" height="64"/>
+ </tileset>
+ <tileset firstgid="5" name="second" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="tilesheet.png" width="64" height="64"/>
+ </tileset>"##;
+    let finite_layers = r##"<layer id="1" name="tiles" width="3" height="1"><data encoding="csv">2,7,2147483654</data></layer>
+ <group id="2" name="group">
+  <objectgroup id="3" name="objects"><object id="1" gid="8" x="0" y="0"/></objectgroup>
+ </group>"##;
+    let infinite_layers = r##"<layer id="1" name="tiles" width="3" height="1">
+  <data encoding="csv"><chunk x="0" y="0" width="3" height="1">2,7,2147483654</chunk></data>
+ </layer>"##;
+    let load = |infinite: u8, contents: String| {
+        let xml = format!(
+            r#"<map version="1.10" orientation="orthogonal" width="3" height="1" tilewidth="32" tileheight="32" infinite="{}">{}</map>"#,
+            infinite, contents
+        );
+        Loader::new()
+            .load_tmx_map_from(xml.as_bytes(), "assets/late_tilesets.tmx")
+            .unwrap()
+    };
+    let tiles = |map: &Map| -> Vec<_> {
+        let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+        (0..3)
+            .map(|x| {
+                layer
+                    .get_tile(x, 0)
+                    .map(|tile| (tile.tileset_index(), tile.id(), tile.flip_h))
+            })
+            .collect()
+    };
+
+    for (infinite, layers) in [(0, finite_layers), (1, infinite_layers)] {
+        let before = load(infinite, format!("{}{}", tilesets, layers));
+        let after = load(infinite, format!("{}{}", layers, tilesets));
+        let expected = [Some((0, 1, false)), Some((1, 2, false)), Some((1, 1, true))];
+        assert_eq!(tiles(&before), expected);
+        assert_eq!(tiles(&after), expected);
+        assert_eq!(before, after);
+    }
+
+    // Tilesets declared in between layers only affect the layers before them.
+    let map = load(
+        0,
+        format!(
+            "{}{}{}",
+            &tilesets[..tilesets.find(" <tileset").unwrap()],
+            finite_layers,
+            &tilesets[tilesets.find(" <tileset").unwrap()..]
+        ),
+    );
+    let object = map.get_layer(1).unwrap().as_group_layer().unwrap();
+    let object = object.get_layer(0).unwrap().as_object_layer().unwrap();
+    let tile = object.get_object(0).unwrap().get_tile().unwrap();
+    assert_eq!(tile.tileset_location(), &tiled::TilesetLocation::Map(1));
+    assert_eq!(tile.id(), 3);
+    assert_eq!(
+        tiles(&map),
+        [Some((0, 1, false)), Some((1, 2, false)), Some((1, 1, true))]
+    );
+}
+
+#[test]
+fn test_infinite_layer_equality() {
+    let load = |second_first_gid: u32| {
+        let xml = format!(
+            r##"<map version="1.10" orientation="orthogonal" width="5" height="1" tilewidth="32" tileheight="32" infinite="1">
+ <tileset firstgid="1" name="first" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="tilesheet.png" width="64" height="64"/>
+ </tileset>
+ <tileset firstgid="{}" name="second" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <layer id="1" name="tiles" width="5" height="1">
+  <data encoding="csv"><chunk x="-2" y="0" width="2" height="1">2,{}</chunk></data>
+ </layer>
+</map>"##,
+            second_first_gid,
+            second_first_gid + 3
+        );
+        Loader::new()
+            .load_tmx_map_from(xml.as_bytes(), "assets/equality.tmx")
+            .unwrap()
+    };
+    let infinite_data = |map: &Map| match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => (*layer).clone(),
+        TileLayer::Finite(_) => panic!("expected an infinite layer"),
+    };
+
+    // Layers with the same tiles are equal however their tilesets are numbered.
+    let mut map = load(5);
+    assert_eq!(infinite_data(&map), infinite_data(&load(100)));
+
+    // Empty chunks are the same as missing ones.
+    let original = infinite_data(&map);
+    let mut layer = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_infinite_tile_layer_mut()
+        .unwrap();
+    layer
+        .set_tile(100, 100, Some(LayerTileData::new(0, 1)))
+        .unwrap();
+    layer.set_tile(100, 100, None).unwrap();
+    assert_eq!(infinite_data(&map), original);
+    assert_eq!(original, infinite_data(&map));
+
+    let mut layer = map
+        .get_layer_mut(0)
+        .unwrap()
+        .as_infinite_tile_layer_mut()
+        .unwrap();
+    layer
+        .set_tile(-1, 0, Some(LayerTileData::new(1, 2)))
+        .unwrap();
+    assert_ne!(infinite_data(&map), original);
+}
+
+#[test]
+fn test_layer_tile_gids() {
+    let gids = [0, 1, 7, 0x80000000 | 5, 0x40000000 | 12];
+    for infinite in [false, true] {
+        let data = if infinite {
+            r#"<data encoding="csv"><chunk x="-2" y="0" width="5" height="1">0,1,7,2147483653,1073741836</chunk></data>"#
+        } else {
+            r#"<data encoding="csv">0,1,7,2147483653,1073741836</data>"#
+        };
+        let xml = format!(
+            r##"<map version="1.10" orientation="orthogonal" width="5" height="1" tilewidth="32" tileheight="32" infinite="{}">
+ <tileset firstgid="1" name="first" tilewidth="32" tileheight="32" tilecount="4" columns="2">
+  <image source="tilesheet.png" width="64" height="64"/>
+ </tileset>
+ <tileset firstgid="5" name="second" tilewidth="32" tileheight="32" tilecount="84" columns="14">
+  <image source="tilesheet.png" width="448" height="192"/>
+ </tileset>
+ <layer id="1" name="tiles" width="5" height="1">{}</layer>
+</map>"##,
+            infinite as u8, data
+        );
+        let map = Loader::new()
+            .load_tmx_map_from(xml.as_bytes(), "assets/gids.tmx")
+            .unwrap();
+        let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+        let x_offset = if infinite { -2 } else { 0 };
+        for (x, bits) in gids.iter().enumerate() {
+            let tile = layer.get_tile(x as i32 + x_offset, 0);
+            assert_eq!(
+                tile.map(|tile| tile.gid()),
+                Some(Gid(bits & 0x1fffffff)).filter(|gid| gid.0 != 0)
+            );
+        }
+        let tile = layer.get_tile(3 + x_offset, 0).unwrap();
+        assert!(tile.flip_h && !tile.flip_v);
+        assert_eq!((tile.tileset_index(), tile.id()), (1, 0));
+    }
+
+    // Infinite layers built in code number their tiles on their own, but tiles still report the
+    // GIDs of the map they end up in.
+    let tileset = Arc::new(
+        Loader::new()
+            .load_tsx_tileset("assets/tilesheet.tsx")
+            .unwrap(),
+    );
+    let mut tiles = TileLayerBuilder::new_infinite();
+    let placed = [((-20, 3), (1, 40)), ((5, 5), (0, 2)), ((100, -7), (1, 80))];
+    for ((x, y), (tileset_index, id)) in placed {
+        tiles = tiles
+            .set_tile(x, y, Some(LayerTileData::new(tileset_index, id)))
+            .unwrap();
+    }
+    let map = MapBuilder::new(tiled::Orientation::Orthogonal, 1, 1, 32, 32)
+        .infinite(true)
+        .tileset(tileset.clone())
+        .tileset(tileset)
+        .layer(tiles.build())
+        .build()
+        .unwrap();
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    for ((x, y), (tileset_index, id)) in placed {
+        let tile = layer.get_tile(x, y).unwrap();
+        assert_eq!((tile.tileset_index(), tile.id()), (tileset_index, id));
+        assert_eq!(
+            tile.gid(),
+            Gid(map.tileset_first_gids()[tileset_index].0 + id)
+        );
+    }
+}
+
+#[test]
+fn test_template_object_references() {
+    let xml = r##"<map version="1.10" orientation="orthogonal" width="1" height="1" tilewidth="32" tileheight="32">
+ <objectgroup id="1" name="objects">
+  <object id="1" template="tiled_object_reference_template.tx" x="0" y="0"/>
+  <object id="2" template="tiled_object_reference_template.tx" x="0" y="0">
+   <properties>
+    <property name="door" type="object" value="3"/>
+   </properties>
+  </object>
+  <object id="3" name="door" x="0" y="0"/>
+  <object id="4" name="light" x="0" y="0"/>
+ </objectgroup>
+</map>"##;
+    let mut map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/references.tmx")
+        .unwrap();
+
+    let object = map.object_by_id(ObjectId(1)).unwrap();
+    assert_eq!(object.template_object_properties(), ["door", "light"]);
+    // Properties set on the object itself refer to objects of the map already.
+    let object = map.object_by_id(ObjectId(2)).unwrap();
+    assert_eq!(object.template_object_properties(), ["light"]);
+    assert_eq!(object.properties["door"], PropertyValue::ObjectValue(3));
+
+    let reference = |object: u32, property: &str, target: u32| TemplateObjectReference {
+        object: ObjectId(object),
+        property: property.to_owned(),
+        target: ObjectId(target),
+    };
+    assert_eq!(
+        map.template_object_references(),
+        [
+            reference(1, "door", 7),
+            reference(1, "light", 9),
+            reference(2, "light", 9)
+        ]
+    );
+
+    let unresolved = map.resolve_template_object_references(|reference| match reference.target.0 {
+        7 => Some(ObjectId(3)),
+        // Objects that aren't in the map can't be referenced.
+        9 if reference.object == ObjectId(2) => Some(ObjectId(10)),
+        9 => Some(ObjectId(4)),
+        _ => None,
+    });
+    assert_eq!(unresolved, [reference(2, "light", 9)]);
+    assert_eq!(map.template_object_references(), unresolved);
+
+    let object = map.object_by_id(ObjectId(1)).unwrap();
+    assert!(object.template_object_properties().is_empty());
+    assert_eq!(object.properties["door"], PropertyValue::ObjectValue(3));
+    assert_eq!(object.properties["light"], PropertyValue::ObjectValue(4));
+    assert_eq!(
+        map.object_by_id(ObjectId(2)).unwrap().properties["light"],
+        PropertyValue::ObjectValue(9)
+    );
+}
+
+#[test]
+fn test_object_transforms() {
+    let xml = r##"<map version="1.10" orientation="orthogonal" width="1" height="1" tilewidth="32" tileheight="32">
+ <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="1" columns="1">
+  <image source="tilesheet.png" width="32" height="32"/>
+ </tileset>
+ <objectgroup id="1" name="objects" offsetx="10" offsety="5">
+  <object id="1" gid="1" x="100" y="200" width="32" height="32"/>
+  <object id="2" gid="2147483649" x="100" y="200" width="32" height="32" rotation="90"/>
+  <object id="3" x="10" y="20" width="8" height="8"/>
+ </objectgroup>
+</map>"##;
+    let approx = |(x, y): (f32, f32), expected: (f32, f32)| {
+        assert!(
+            (x - expected.0).abs() < 1e-3 && (y - expected.1).abs() < 1e-3,
+            "{:?} != {:?}",
+            (x, y),
+            expected
+        );
+    };
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/transforms.tmx")
+        .unwrap();
+    let layer = map.flattened_layers().next().unwrap();
+    let transform = |id: u32| {
+        map.object_by_id(ObjectId(id))
+            .unwrap()
+            .transform(layer.offset_x, layer.offset_y)
+    };
+
+    // Tile objects are aligned to their bottom left corner in orthogonal maps by default.
+    let tile = transform(1);
+    assert_eq!((tile.pivot_x, tile.pivot_y), (0.0, 32.0));
+    approx(tile.transform_point(0.0, 0.0), (110.0, 173.0));
+    approx(tile.transform_point(0.0, 32.0), (110.0, 205.0));
+
+    // Flipping happens within the bounds, before rotating around the pivot.
+    let flipped = transform(2);
+    assert!(flipped.flip_h && !flipped.flip_v);
+    approx(flipped.transform_point(0.0, 0.0), (142.0, 237.0));
+    approx(flipped.transform_point(32.0, 32.0), (110.0, 205.0));
+
+    let rect = transform(3);
+    assert_eq!((rect.pivot_x, rect.pivot_y), (0.0, 0.0));
+    approx(rect.transform_point(5.0, 5.0), (25.0, 30.0));
+
+    // Isometric maps align tile objects to their bottom center instead.
+    let xml = xml.replace("orthogonal", "isometric");
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/transforms.tmx")
+        .unwrap();
+    let tile = map.object_by_id(ObjectId(1)).unwrap().transform(0.0, 0.0);
+    assert_eq!((tile.pivot_x, tile.pivot_y), (16.0, 32.0));
+    approx(tile.transform_point(0.0, 0.0), (84.0, 168.0));
+}
+
+#[test]
+fn test_malformed_objects() {
+    fn load(objects: &str) -> Result<Map, Error> {
+        let xml = format!(
+            r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="2" height="2" tilewidth="32" tileheight="32" infinite="0" nextlayerid="2" nextobjectid="2">
+ <objectgroup id="1" name="objects">
+  {}
+ </objectgroup>
+</map>"##,
+            objects
+        );
+        Loader::new().load_tmx_map_from(xml.as_bytes(), "assets/malformed.tmx")
+    }
+
+    let error = load(r#"<object id="1"><text>a<b/>c</text></object>"#).unwrap_err();
+    assert!(matches!(
+        error.inner(),
+        Error::UnexpectedContent { element, found } if element == "text" && found == "element <b>"
+    ));
+    assert_eq!(
+        error.location().unwrap().1,
+        XmlPosition {
+            line: 4,
+            column: 18
+        }
+    );
+
+    // None of these are valid, and none of them should make the loader panic.
+    let corpus = [
+        r#"<object id="1"><text>a<text>b</text>c</text></object>"#,
+        r#"<object id="1"><text><object id="2"/></text></object>"#,
+        r#"<object id="1"><text>a</object>"#,
+        r#"<object id="1"><text>"#,
+        r#"<object id="1"><text pixelsize="-1">a</text></object>"#,
+        r##"<object id="1"><text color="#zz">a</text></object>"##,
+        r#"<object id="1"><polygon points="0,0 1"/></object>"#,
+        r#"<object id="1"><polyline points="0,0 a,b"/></object>"#,
+        r#"<object id="1"><polygon/></object>"#,
+        r#"<object id="1"><properties><property name="a"><b/></property></properties></object>"#,
+        r#"<object id="1"><properties><property name="a">"#,
+        r#"<object id="1"><properties><property name="a" type="int" value="x"/></properties></object>"#,
+        r#"<object id="x"/>"#,
+        r#"<object id="1" x="nan" y="1e39" rotation=""/>"#,
+        r#"<object id="1" template="missing.tx"/>"#,
+        r#"<object id="1"></text></object>"#,
+        r#"<object"#,
+    ];
+    for objects in corpus {
+        assert!(load(objects).is_err(), "{} was loaded", objects);
+    }
+
+    // Content Tiled wouldn't write but that can be read anyway.
+    let map = load(r#"<object id="1"><text>a<!--b--><![CDATA[<c>]]></text></object>"#).unwrap();
+    let object = map.object_by_id(ObjectId(1)).unwrap();
+    assert!(matches!(&object.shape, ObjectShape::Text(text) if text.text == "a<c>"));
+
+    // The largest possible IDs are kept, which leaves no IDs for new objects.
+    let mut map = load(r#"<object id="4294967295"/>"#).unwrap();
+    assert_eq!(map.next_object_id(), u32::MAX);
+    let mut objects = map.get_layer_mut(0).unwrap().as_object_layer_mut().unwrap();
+    assert!(matches!(
+        objects.push_object(ObjectDataBuilder::new(ObjectShape::Point(0.0, 0.0))),
+        Err(Error::InvalidMapData { .. })
+    ));
+
+    // Cutting a valid document short anywhere fails without panicking.
+    let xml = r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" renderorder="right-down" width="2" height="2" tilewidth="32" tileheight="32" infinite="0">
+ <objectgroup id="1" name="objects">
+  <object id="1" x="4" y="8"><properties><property name="a">multi
+line</property></properties><text wrap="1">Hello</text></object>
+  <object id="2"><polygon points="0,0 8,0 8,8"/></object>
+ </objectgroup>
+</map>"##;
+    assert!(Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/truncated.tmx")
+        .is_ok());
+    for end in 0..xml.len() - 1 {
+        let truncated = &xml.as_bytes()[..end];
+        assert!(
+            Loader::new()
+                .load_tmx_map_from(truncated, "assets/truncated.tmx")
+                .is_err(),
+            "{} was loaded",
+            &xml[..end]
+        );
+    }
+}
+
+#[test]
+fn test_parse_limits() {
+    let load =
+        |limits: ParseLimits, path: &str| Loader::new().with_limits(limits).load_tmx_map(path);
+    let exceeded = |result: Result<Map, Error>| match result {
+        Err(error) => matches!(error.inner(), Error::LimitExceeded { .. }),
+        Ok(_) => false,
+    };
+
+    let layers = |max_layers| ParseLimits {
+        max_layers: Some(max_layers),
+        ..ParseLimits::default()
+    };
+    assert!(load(layers(6), "assets/tiled_group_layers.tmx").is_ok());
+    assert!(exceeded(load(layers(5), "assets/tiled_group_layers.tmx")));
+
+    let depth = |max_nesting_depth| ParseLimits {
+        max_nesting_depth: Some(max_nesting_depth),
+        ..ParseLimits::default()
+    };
+    assert!(load(depth(2), "assets/tiled_group_layers.tmx").is_ok());
+    assert!(exceeded(load(depth(1), "assets/tiled_group_layers.tmx")));
+    assert!(load(depth(1), "assets/tiled_class_property.tmx").is_ok());
+    assert!(exceeded(load(depth(0), "assets/tiled_class_property.tmx")));
+
+    // Deeply nested groups are rejected before they can overflow the stack.
+    let xml = format!(
+        r##"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="2" height="2" tilewidth="32" tileheight="32" infinite="0">
+{}{}
+</map>"##,
+        r#"<group id="1">"#.repeat(100_000),
+        "</group>".repeat(100_000)
+    );
+    let error = Loader::new()
+        .with_limits(depth(64))
+        .load_tmx_map_from(xml.as_bytes(), "assets/nested.tmx")
+        .unwrap_err();
+    assert!(matches!(error.inner(), Error::LimitExceeded { .. }));
+    assert_eq!(error.location().unwrap().1.line, 3);
+
+    // The layer is 100x100 tiles, 4 bytes each.
+    let size = |max_decompressed_size| ParseLimits {
+        max_decompressed_size: Some(max_decompressed_size),
+        ..ParseLimits::default()
+    };
+    assert!(load(size(40_000), "assets/tiled_base64_zlib.tmx").is_ok());
+    let error = load(size(39_999), "assets/tiled_base64_zlib.tmx").unwrap_err();
+    assert!(matches!(error.inner(), Error::LimitExceeded { .. }));
+    assert_eq!(error.location().unwrap().1.line, 24);
+}
+
+#[test]
+fn test_loader_options() {
+    let limits = ParseLimits {
+        max_layers: Some(10),
+        ..ParseLimits::default()
+    };
+    let options = LoaderOptionsBuilder::new()
+        .lazy_tilesets(true)
+        .sparse_tile_layer_threshold(Some(0.5))
+        .error_tolerance(ErrorTolerance::Lenient)
+        .limits(limits)
+        .build();
+    assert!(options.lazy_tilesets());
+    assert_eq!(options.sparse_tile_layer_threshold(), Some(0.5));
+    assert_eq!(options.error_tolerance(), ErrorTolerance::Lenient);
+    assert_eq!(options.limits(), limits);
+    assert_eq!(
+        LoaderOptions::default(),
+        LoaderOptionsBuilder::new().build()
+    );
+
+    let mut loader = Loader::new().with_options(options.clone());
+    assert_eq!(*loader.options(), options);
+    assert!(loader.lazy_tilesets());
+    assert_eq!(loader.error_tolerance(), ErrorTolerance::Lenient);
+    let map = loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert_eq!(map.lazy_tilesets().len(), 1);
+
+    // Changing a single setting changes the options, leaving the others as they were.
+    loader.set_lazy_tilesets(false);
+    let expected = LoaderOptionsBuilder::from(options)
+        .lazy_tilesets(false)
+        .build();
+    assert_eq!(*loader.options(), expected);
+    let map = loader
+        .load_tmx_map("assets/tiled_base64_external.tmx")
+        .unwrap();
+    assert!(map.lazy_tilesets().is_empty());
+}
+
+#[test]
+fn test_preserve_unknown_xml() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="1" height="1" tilewidth="32" tileheight="32" nextlayerid="5" nextobjectid="2" futuremap="1">
+ <tileset firstgid="1" name="ts" tilewidth="32" tileheight="32" tilecount="0" columns="0" futuretileset="a">
+  <futureelement/>
+  <tile id="0">
+   <objectgroup>
+    <object id="1" x="0" y="0" futurecollision="1"><futurepoint/></object>
+   </objectgroup>
+  </tile>
+ </tileset>
+ <layer id="1" name="tiles" width="1" height="1" locked="1">
+  <data encoding="csv">0</data>
+ </layer>
+ <objectgroup id="2" name="objects">
+  <object id="1" x="1" y="2" futureobject="b &amp; c">
+   <futureshape size="3">some &lt;text&gt;</futureshape>
+  </object>
+  <future/>
+ </objectgroup>
+ <group id="3" name="group" futuregroup="1">
+  <imagelayer id="4" name="image" futureimage="x"/>
+ </group>
+ <futurelist><item/><item/></futurelist>
+</map>"#;
+
+    // Unknown XML is dropped by default.
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/future.tmx")
+        .unwrap();
+    assert!(map.extra.is_empty());
+    assert!(map.tilesets()[0].extra.is_empty());
+    assert!(map.layers().all(|layer| layer.extra.is_empty()));
+
+    let options = LoaderOptionsBuilder::new()
+        .preserve_unknown_xml(true)
+        .build();
+    let map = Loader::new()
+        .with_options(options.clone())
+        .load_tmx_map_from(xml.as_bytes(), "assets/future.tmx")
+        .unwrap();
+    assert_eq!(
+        map.extra,
+        ExtraXml {
+            attributes: HashMap::from([("futuremap".to_owned(), "1".to_owned())]),
+            elements: vec!["<futurelist><item></item><item></item></futurelist>".to_owned()],
+        }
+    );
+
+    let tileset = &map.tilesets()[0];
+    assert_eq!(tileset.extra.attributes["futuretileset"], "a");
+    assert_eq!(tileset.extra.elements, ["<futureelement></futureelement>"]);
+
+    let tiles = map.get_layer(0).unwrap();
+    assert_eq!(tiles.extra.attributes["locked"], "1");
+    assert!(tiles.extra.elements.is_empty());
+
+    let objects = map.get_layer(1).unwrap();
+    assert!(objects.extra.attributes.is_empty());
+    assert_eq!(objects.extra.elements, ["<future></future>"]);
+    let object = objects.as_object_layer().unwrap().get_object(0).unwrap();
+    assert_eq!(object.extra.attributes["futureobject"], "b & c");
+    assert_eq!(
+        object.extra.elements,
+        [r#"<futureshape size="3">some &lt;text&gt;</futureshape>"#]
+    );
+
+    let group = map.get_layer(2).unwrap();
+    assert_eq!(group.extra.attributes["futuregroup"], "1");
+    let image = group.as_group_layer().unwrap().get_layer(0).unwrap();
+    assert_eq!(image.extra.attributes["futureimage"], "x");
+
+    // Unknown XML of tilesets and their objects is written back out.
+    let collision = tileset.get_tile(0).unwrap().collision.clone().unwrap();
+    let object = &collision.object_data()[0];
+    assert_eq!(object.extra.attributes["futurecollision"], "1");
+    assert_eq!(object.extra.elements, ["<futurepoint></futurepoint>"]);
+    let mut tileset = tileset.as_ref().clone();
+    let mut tsx = Vec::new();
+    tileset.to_tsx_writer(&mut tsx).unwrap();
+    let reader = move |_: &Path| -> std::io::Result<_> { Ok(Cursor::new(tsx.clone())) };
+    let written = Loader::with_reader(reader)
+        .with_options(options)
+        .load_tsx_tileset("written.tsx")
+        .unwrap();
+    assert_eq!(written, tileset);
+    tileset.extra = ExtraXml::default();
+    assert_ne!(written, tileset);
+}
+
+#[test]
+fn test_layer_pixel_bounds() {
+    let xml = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="4" height="3" tilewidth="16" tileheight="16" nextlayerid="7" nextobjectid="4">
+ <layer id="1" name="tiles" width="4" height="3" offsetx="10">
+  <data encoding="csv">0,0,0,0,0,0,0,0,0,0,0,0</data>
+ </layer>
+ <imagelayer id="2" name="image" offsetx="5" offsety="6">
+  <image source="image.png" width="20" height="30"/>
+ </imagelayer>
+ <imagelayer id="3" name="no image"/>
+ <group id="4" name="group" offsetx="100" offsety="200">
+  <objectgroup id="5" name="objects" offsetx="1" offsety="2">
+   <object id="1" x="10" y="20" width="30" height="40"/>
+   <object id="2" x="0" y="0"><polygon points="0,0 -5,3 4,-6"/></object>
+   <object id="3" x="50" y="50" width="10" height="20" rotation="90"/>
+  </objectgroup>
+  <objectgroup id="6" name="empty"/>
+ </group>
+</map>"#;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/bounds.tmx")
+        .unwrap();
+    let rect = |x: f32, y: f32, width: f32, height: f32| Rect {
+        x,
+        y,
+        width,
+        height,
+    };
+    let bounds = |path: &str| map.layer_at_path(path).unwrap().pixel_bounds();
+
+    assert_eq!(bounds("tiles"), Some(rect(10.0, 0.0, 64.0, 48.0)));
+    assert_eq!(bounds("image"), Some(rect(5.0, 6.0, 20.0, 30.0)));
+    assert_eq!(bounds("no image"), None);
+    // Object layers include the offsets of their parent groups.
+    let objects = rect(96.0, 196.0, 55.0, 66.0);
+    assert_eq!(bounds("group/objects").map(round_rect), Some(objects));
+    assert_eq!(bounds("group/empty"), None);
+    assert_eq!(bounds("group").map(round_rect), Some(objects));
+
+    // Tile layers in other orientations cover the same area as their map when they have its size.
+    for orientation in [
+        tiled::Orientation::Isometric,
+        tiled::Orientation::Staggered,
+        tiled::Orientation::Hexagonal,
+    ] {
+        let map = MapBuilder::new(orientation, 5, 3, 32, 16)
+            .hex_side_length(8)
+            .layer(TileLayerBuilder::new(5, 3).build())
+            .build()
+            .unwrap();
+        assert_eq!(map.get_layer(0).unwrap().pixel_bounds(), Some(map.bounds()));
+    }
+}
+
+fn round_rect(rect: Rect) -> Rect {
+    Rect {
+        x: rect.x.round(),
+        y: rect.y.round(),
+        width: rect.width.round(),
+        height: rect.height.round(),
+    }
+}
+
+#[test]
+fn test_resolved_properties() {
+    let template = r#"<?xml version="1.0" encoding="UTF-8"?>
+<template>
+ <object type="Character" width="16" height="16">
+  <properties>
+   <property name="health" type="int" value="50"/>
+   <property name="spawn" type="class" propertytype="Vector">
+    <properties>
+     <property name="x" type="float" value="5"/>
+    </properties>
+   </property>
+  </properties>
+ </object>
+</template>"#;
+    let map = r#"<?xml version="1.0" encoding="UTF-8"?>
+<map version="1.10" orientation="orthogonal" width="1" height="1" tilewidth="16" tileheight="16" nextlayerid="2" nextobjectid="3">
+ <objectgroup id="1" name="objects">
+  <object id="1" template="character.tx" x="0" y="0">
+   <properties>
+    <property name="extra" type="bool" value="true"/>
+    <property name="spawn" type="class" propertytype="Vector">
+     <properties>
+      <property name="y" type="float" value="7"/>
+     </properties>
+    </property>
+   </properties>
+  </object>
+  <object id="2" x="0" y="0">
+   <properties>
+    <property name="offset" type="class" propertytype="Vector"/>
+   </properties>
+  </object>
+ </objectgroup>
+</map>"#;
+    let reader = move |path: &Path| -> std::io::Result<_> {
+        match path.to_str().unwrap() {
+            "memory/map.tmx" => Ok(Cursor::new(map.as_bytes())),
+            "memory/character.tx" => Ok(Cursor::new(template.as_bytes())),
+            _ => Err(std::io::ErrorKind::NotFound.into()),
+        }
+    };
+    let mut loader = Loader::with_reader(reader);
+    let map = loader.load_tmx_map("memory/map.tmx").unwrap();
+    let project = Loader::new()
+        .load_tiled_project("assets/tiled_project.tiled-project")
+        .unwrap();
+    let vector = |x: f32, y: f32| PropertyValue::ClassValue {
+        property_type: "Vector".to_owned(),
+        properties: HashMap::from([
+            ("x".to_owned(), PropertyValue::FloatValue(x)),
+            ("y".to_owned(), PropertyValue::FloatValue(y)),
+        ]),
+    };
+
+    // Template properties, class defaults and the object's own properties are all combined.
+    let object = map.object_by_id(ObjectId(1)).unwrap();
+    let properties = object.resolved_properties(&project);
+    assert_eq!(properties["health"], PropertyValue::IntValue(50));
+    assert_eq!(
+        properties["name"],
+        PropertyValue::StringValue("Unnamed \"hero\"".to_owned())
+    );
+    assert_eq!(properties["spawn"], vector(5.0, 7.0));
+    assert_eq!(properties["extra"], PropertyValue::BoolValue(true));
+    assert!(properties.contains_key("facing"));
+    // The object's own properties only hold the overridden class members.
+    assert!(matches!(
+        &object.properties["spawn"],
+        PropertyValue::ClassValue { properties, .. } if properties.len() == 1
+    ));
+
+    // Class values of objects without a class get the defaults of their own class.
+    let object = map.object_by_id(ObjectId(2)).unwrap();
+    let properties = object.resolved_properties(&project);
+    assert_eq!(properties.len(), 1);
+    assert_eq!(properties["offset"], vector(0.0, 0.0));
+    assert_eq!(
+        object.resolved_properties(&tiled::Project::default()),
+        object.properties
+    );
+}
+
+#[test]
+fn test_typed_property_access() {
+    let color = Color {
+        alpha: 255,
+        red: 1,
+        green: 2,
+        blue: 3,
+    };
+    let members = HashMap::from([("x".to_owned(), PropertyValue::FloatValue(2.0))]);
+    let properties: tiled::Properties = HashMap::from([
+        ("bool".to_owned(), PropertyValue::BoolValue(true)),
+        ("int".to_owned(), PropertyValue::IntValue(-3)),
+        ("float".to_owned(), PropertyValue::FloatValue(1.5)),
+        (
+            "string".to_owned(),
+            PropertyValue::StringValue("text".to_owned()),
+        ),
+        ("color".to_owned(), PropertyValue::ColorValue(color)),
+        (
+            "file".to_owned(),
+            PropertyValue::FileValue {
+                relative: "a.png".to_owned(),
+                resolved: PathBuf::from("assets/a.png"),
+            },
+        ),
+        ("object".to_owned(), PropertyValue::ObjectValue(4)),
+        ("unset object".to_owned(), PropertyValue::ObjectValue(0)),
+        (
+            "class".to_owned(),
+            PropertyValue::ClassValue {
+                property_type: "Vector".to_owned(),
+                properties: members.clone(),
+            },
+        ),
+    ]);
+
+    assert_eq!(properties.get_bool("bool"), Some(true));
+    assert_eq!(properties.get_int("int"), Some(-3));
+    assert_eq!(properties.get_float("float"), Some(1.5));
+    assert_eq!(properties.get_string("string"), Some("text"));
+    assert_eq!(properties.get_color("color"), Some(color));
+    assert_eq!(properties.get_file("file"), Some(Path::new("assets/a.png")));
+    assert_eq!(properties.get_object("object"), Some(ObjectId(4)));
+    assert_eq!(properties.get_object("unset object"), None);
+    assert_eq!(properties.get_class("class"), Some(&members));
+    assert_eq!(
+        properties.get_class("class").unwrap().get_float("x"),
+        Some(2.0)
+    );
+    assert_eq!(
+        properties.get_as::<String>("string"),
+        Some("text".to_owned())
+    );
+
+    // Missing properties and values of other types give nothing.
+    assert_eq!(properties.get_int("missing"), None);
+    assert_eq!(properties.get_int("float"), None);
+    assert_eq!(properties.get_float("int"), None);
+    assert_eq!(properties.get_string("file"), None);
+    assert_eq!(properties.get_bool("string"), None);
+}
+
+#[cfg(feature = "bevy_reflect")]
+#[test]
+fn test_reflect() {
+    use bevy_reflect::{GetField, PartialReflect, ReflectRef, TypeRegistry};
+
+    let mut registry = TypeRegistry::new();
+    tiled::register_reflect_types(&mut registry);
+    assert!(registry.get_with_short_type_path("PropertyValue").is_some());
+
+    let map = Loader::new().load_tmx_map("assets/tiled_csv.tmx").unwrap();
+    let color = map.background_color.unwrap();
+    assert_eq!(color.get_field::<u8>("red"), Some(&255));
+    assert_eq!(color.get_field::<u8>("green"), Some(&0));
+
+    let value = &map.tilesets()[0].properties["tileset property"];
+    match value.reflect_ref() {
+        ReflectRef::Enum(value) => {
+            assert_eq!(value.variant_name(), "StringValue");
+            let field = value.field_at(0).unwrap();
+            assert_eq!(field.try_downcast_ref::<String>().unwrap(), "tsp");
+        }
+        _ => panic!("property values should be reflected as enums"),
+    }
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde_round_trip() {
+    let mut loader = Loader::new();
+    for path in [
+        "assets/tiled_base64_zlib_infinite.tmx",
+        "assets/tiled_csv_wangsets.tmx",
+        "assets/tiled_group_layers.tmx",
+        "assets/tiled_object_template.tmx",
+        "assets/tiled_tile_collision.tmx",
+    ] {
+        let map = loader.load_tmx_map(path).unwrap();
+        let json = serde_json::to_string(&map).unwrap();
+        let deserialized: Map = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, map, "{}", path);
+    }
+
+    for path in [
+        "assets/tilesheet_animation.tsx",
+        "assets/tilesheet_image_collection.tsx",
+        "assets/tilesheet_wangsets.tsx",
+    ] {
+        let tileset = loader.load_tsx_tileset(path).unwrap();
+        let json = serde_json::to_string(&tileset).unwrap();
+        let deserialized: tiled::Tileset = serde_json::from_str(&json).unwrap();
+        assert_eq!(deserialized, tileset, "{}", path);
+    }
+
+    // Deserialized tileset references might have nothing to load the tileset from.
+    let tileset: tiled::TilesetRef =
+        serde_json::from_str(r#"{"path": null, "tileset": null}"#).unwrap();
+    assert!(matches!(
+        tileset.load(&mut loader),
+        Err(Error::InvalidMapData { .. })
+    ));
+}
+
+#[cfg(feature = "derive")]
+#[test]
+fn test_derive_from_properties() {
+    use tiled::FromProperties;
+
+    #[derive(Debug, PartialEq, FromProperties)]
+    struct Door<'a> {
+        locked: bool,
+        #[tiled(rename = "key id")]
+        key_id: i32,
+        label: Option<&'a str>,
+        #[tiled(default)]
+        speed: f32,
+    }
+
+    #[derive(Debug, PartialEq, FromProperties)]
+    struct Reference {
+        #[tiled(rename = "object property")]
+        target: Option<ObjectId>,
+    }
+
+    let r = Loader::new()
+        .load_tmx_map("assets/tiled_object_property.tmx")
+        .unwrap();
+    let group = r.get_layer(1).unwrap().as_object_layer().unwrap();
+    let reference = Reference::from_properties(&group.objects().next().unwrap().properties);
+    assert_eq!(
+        reference.unwrap(),
+        Reference {
+            target: Some(ObjectId(3))
+        }
+    );
+
+    let mut properties: tiled::Properties = HashMap::from([
+        ("locked".to_owned(), PropertyValue::BoolValue(true)),
+        ("key id".to_owned(), PropertyValue::IntValue(7)),
+    ]);
+    assert_eq!(
+        Door::from_properties(&properties).unwrap(),
+        Door {
+            locked: true,
+            key_id: 7,
+            label: None,
+            speed: 0.0,
+        }
+    );
+
+    properties.insert(
+        "label".to_owned(),
+        PropertyValue::StringValue("Cellar".to_owned()),
+    );
+    properties.insert("speed".to_owned(), PropertyValue::FloatValue(2.5));
+    let door = Door::from_properties(&properties).unwrap();
+    assert_eq!(door.label, Some("Cellar"));
+    assert_eq!(door.speed, 2.5);
+
+    properties.insert("speed".to_owned(), PropertyValue::IntValue(2));
+    assert!(matches!(
+        Door::from_properties(&properties),
+        Err(Error::InvalidPropertyValue { .. })
+    ));
+
+    properties.remove("locked");
+    assert!(matches!(
+        Door::from_properties(&properties),
+        Err(Error::MissingProperty { name }) if name == "locked"
+    ));
+}
+
+#[test]
+fn test_64_bit_int_properties() {
+    let xml = r#"<map version="1.10" orientation="orthogonal" width="1" height="1"
+    tilewidth="32" tileheight="32">
+ <properties>
+  <property name="big" type="int" value="5000000000"/>
+  <property name="negative" type="int" value="-3000000000"/>
+  <property name="small" type="int" value="12"/>
+  <property name="object" type="object" value="4294967295"/>
+ </properties>
+</map>"#;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/big_ints.tmx")
+        .unwrap();
+    let properties = &map.properties;
+    assert_eq!(properties["big"], PropertyValue::IntValue(5_000_000_000));
+    assert_eq!(properties.get_int("negative"), Some(-3_000_000_000));
+    assert_eq!(properties.get_object("object"), Some(ObjectId(u32::MAX)));
+
+    // Values only convert to `i32` if they fit in one.
+    assert_eq!(properties.get_as::<i32>("small"), Some(12));
+    assert_eq!(properties.get_as::<i32>("big"), None);
+    assert_eq!(properties.get_as::<i64>("big"), Some(5_000_000_000));
+
+    // Values that don't fit in 64 bits are still rejected.
+    let too_big = xml.replace("5000000000", "10000000000000000000");
+    let result = Loader::new().load_tmx_map_from(too_big.as_bytes(), "assets/big_ints.tmx");
+    assert!(matches!(
+        result.unwrap_err().inner(),
+        Error::InvalidPropertyValue { .. }
+    ));
+}
+
+#[test]
+fn test_collision_grid() {
+    let xml = r#"<map version="1.10" orientation="orthogonal" width="4" height="3"
+    tilewidth="32" tileheight="32" infinite="0">
+ <tileset firstgid="1" name="walls" tilewidth="32" tileheight="32" tilecount="2" columns="2">
+  <image source="tilesheet.png" width="64" height="32"/>
+  <tile id="1" type="wall"/>
+ </tileset>
+ <layer id="1" name="floor" width="4" height="3">
+  <data encoding="csv">1,2,1,1,1,1,1,1,1,1,1,1</data>
+ </layer>
+ <group id="2" name="moved" offsetx="32">
+  <layer id="3" name="walls" width="4" height="3" offsety="32" visible="0">
+   <data encoding="csv">0,0,0,0,2,0,0,0,0,0,0,2</data>
+  </layer>
+ </group>
+ <objectgroup id="4" name="objects">
+  <object id="1" type="wall" x="64" y="64" width="64" height="32"/>
+  <object id="2" type="wall" x="0" y="64">
+   <polygon points="0,0 40,0 0,40"/>
+  </object>
+  <object id="3" x="0" y="0" width="128" height="96"/>
+ </objectgroup>
+</map>"#;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/collision.tmx")
+        .unwrap();
+    let is_wall = |tile: tiled::LayerTile| matches!(tile.get_tile(), Some(tile) if tile.user_type.as_deref() == Some("wall"));
+    let solid = |grid: &tiled::Grid<bool>| {
+        let mut cells = Vec::new();
+        for y in 0..3 {
+            for x in 0..4 {
+                if grid.get(x, y) == Some(&true) {
+                    cells.push((x, y));
+                }
+            }
+        }
+        cells
+    };
+
+    // The wall at (0, 1) of the hidden layer is moved a tile down and right by the offsets, and
+    // the one at (3, 2) is moved out of the map.
+    let grid = map.build_collision_grid(is_wall);
+    assert_eq!(grid.origin(), (0, 0));
+    assert_eq!((grid.width(), grid.height()), (4, 3));
+    assert_eq!(grid.cells().len(), 12);
+    assert_eq!(solid(&grid), [(1, 0), (1, 2)]);
+    assert_eq!(grid.get(4, 0), None);
+
+    // The polygon only covers the center of the tile at (0, 2).
+    let grid = map.build_collision_grid_with_objects(is_wall, |object| object.user_type == "wall");
+    assert_eq!(solid(&grid), [(1, 0), (0, 2), (1, 2), (2, 2), (3, 2)]);
+
+    // Objects in isometric maps are measured in tile heights along the axes of the tiles.
+    let xml = r#"<map version="1.10" orientation="isometric" width="3" height="3"
+    tilewidth="64" tileheight="32">
+ <objectgroup id="1" name="objects">
+  <object id="1" x="32" y="0" width="32" height="64"/>
+ </objectgroup>
+</map>"#;
+    let isometric = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/collision.tmx")
+        .unwrap();
+    let grid = isometric.build_collision_grid_with_objects(|_| false, |_| true);
+    let solid: Vec<_> = (0..3)
+        .flat_map(|y| (0..3).map(move |x| (x, y)))
+        .filter(|&(x, y)| grid.get(x, y) == Some(&true))
+        .collect();
+    assert_eq!(solid, [(1, 0), (1, 1)]);
+
+    // Infinite maps get a grid covering the solid tiles.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64_zlib_infinite.tmx")
+        .unwrap();
+    let grid = map.build_collision_grid(|_| true);
+    let layer = match map.get_layer(0).unwrap().as_tile_layer().unwrap() {
+        TileLayer::Infinite(layer) => layer,
+        _ => panic!("expected an infinite layer"),
+    };
+    let ((min_x, min_y), (max_x, max_y)) = layer.bounds().unwrap();
+    assert!(grid.origin().0 <= min_x && grid.origin().1 <= min_y);
+    for ((x, y), _) in layer.tiles() {
+        assert_eq!(grid.get(x, y), Some(&true));
+    }
+    assert!(max_x < grid.origin().0 + grid.width() as i32);
+    assert!(max_y < grid.origin().1 + grid.height() as i32);
+
+    // Objects far outside of the map or covering a huge area only mark the cells in it.
+    for infinite in [false, true] {
+        let xml = format!(
+            r#"<map version="1.10" orientation="orthogonal" width="4" height="3"
+    tilewidth="32" tileheight="32" infinite="{}">
+ <tileset firstgid="1" name="walls" tilewidth="32" tileheight="32" tilecount="1" columns="1">
+  <image source="tilesheet.png" width="32" height="32"/>
+ </tileset>
+ <layer id="1" name="floor" width="4" height="3">
+  <data encoding="csv">{}</data>
+ </layer>
+ <objectgroup id="2" name="objects">
+  <object id="1" x="-1e30" y="-1e30" width="32" height="32"/>
+  <object id="2" x="-1e30" y="-1e30" width="2e30" height="2e30"/>
+ </objectgroup>
+</map>"#,
+            infinite as u8,
+            if infinite {
+                r#"<chunk x="0" y="0" width="1" height="1">1</chunk>"#
+            } else {
+                "0,0,0,0,0,0,0,0,0,0,0,0"
+            },
+        );
+        let map = Loader::new()
+            .load_tmx_map_from(xml.as_bytes(), "assets/collision.tmx")
+            .unwrap();
+        let grid = map.build_collision_grid_with_objects(|_| false, |_| true);
+        let expected = if infinite { 16 * 16 } else { 4 * 3 };
+        assert_eq!(grid.cells().len(), expected);
+        assert!(grid.cells().iter().all(|&solid| solid));
+    }
+}
+
+#[cfg(feature = "outlines")]
+#[test]
+fn test_collision_outlines() {
+    use tiled::Outline;
+
+    let points = |points: &[(f32, f32)]| -> Vec<Point> {
+        points.iter().map(|&(x, y)| Point::new(x, y)).collect()
+    };
+
+    // Each tile of this layer has a small rectangle as its collision shape, flipped along with the
+    // tile and moved by the offset of the layer.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_tile_collision.tmx")
+        .unwrap();
+    let layers = map.collision_outlines().unwrap();
+    assert_eq!(layers.len(), 1);
+    assert_eq!(layers[0].layer.name, "Tile Layer 1");
+    assert_eq!(
+        layers[0].outlines,
+        [
+            Outline {
+                points: points(&[(104.0, 58.0), (114.0, 58.0), (114.0, 64.0), (104.0, 64.0)]),
+                hole: false,
+            },
+            Outline {
+                points: points(&[(150.0, 58.0), (160.0, 58.0), (160.0, 64.0), (150.0, 64.0)]),
+                hole: false,
+            },
+            Outline {
+                points: points(&[(172.0, 54.0), (178.0, 54.0), (178.0, 64.0), (172.0, 64.0)]),
+                hole: false,
+            },
+        ]
+    );
+
+    // Tiles covered by their collision shape are merged, keeping holes and tiles that only touch
+    // diagonally apart.
+    let xml = r#"<map version="1.10" orientation="orthogonal" width="5" height="4"
+    tilewidth="16" tileheight="16">
+ <tileset firstgid="1" name="walls" tilewidth="16" tileheight="16" tilecount="2" columns="2">
+  <image source="tilesheet.png" width="32" height="16"/>
+  <tile id="0">
+   <objectgroup draworder="index" id="2">
+    <object id="1" x="0" y="0" width="16" height="16"/>
+   </objectgroup>
+  </tile>
+ </tileset>
+ <layer id="1" name="walls" width="5" height="4">
+  <data encoding="csv">1,1,1,2,0,1,2,1,2,0,1,1,1,2,0,0,0,0,1,0</data>
+ </layer>
+ <layer id="2" name="empty" width="5" height="4">
+  <data encoding="csv">0,0,0,0,0,0,2,0,0,0,0,0,0,0,0,0,0,0,0,0</data>
+ </layer>
+</map>"#;
+    let map = Loader::new()
+        .load_tmx_map_from(xml.as_bytes(), "assets/outlines.tmx")
+        .unwrap();
+    let layers = map.collision_outlines().unwrap();
+    assert_eq!(layers.len(), 1);
+    assert_eq!(
+        layers[0].outlines,
+        [
+            Outline {
+                points: points(&[(0.0, 0.0), (48.0, 0.0), (48.0, 48.0), (0.0, 48.0)]),
+                hole: false,
+            },
+            Outline {
+                points: points(&[(32.0, 16.0), (16.0, 16.0), (16.0, 32.0), (32.0, 32.0)]),
+                hole: true,
+            },
+            Outline {
+                points: points(&[(48.0, 48.0), (64.0, 48.0), (64.0, 64.0), (48.0, 64.0)]),
+                hole: false,
+            },
+        ]
+    );
+
+    // The same outlines in tiles, straight from a grid.
+    let grid = map.build_collision_grid(|tile| tile.id() == 0);
+    let outlines = grid.outlines();
+    assert_eq!(outlines.len(), 3);
+    assert_eq!(
+        outlines[0].points,
+        points(&[(0.0, 0.0), (3.0, 0.0), (3.0, 3.0), (0.0, 3.0)])
+    );
+    assert!(outlines[1].hole);
+
+    let isometric = MapBuilder::new(tiled::Orientation::Isometric, 2, 2, 64, 32)
+        .build()
+        .unwrap();
+    assert_eq!(isometric.collision_outlines(), None);
+}
+
+#[test]
+fn test_query_layers() {
+    let mut map = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+    let names = |query: tiled::LayerQuery| -> Vec<String> {
+        query.into_iter().map(|layer| layer.name.clone()).collect()
+    };
+
+    assert_eq!(names(map.query_layers()), ["tile-1", "group-1", "group-2"]);
+    assert_eq!(
+        names(map.query_layers().flattened()),
+        ["tile-1", "group-1", "tile-2", "group-2", "group-3", "tile-3"]
+    );
+    assert_eq!(
+        names(
+            map.query_layers()
+                .flattened()
+                .of_type::<tiled::GroupLayer>()
+        ),
+        ["group-1", "group-2", "group-3"]
+    );
+    assert_eq!(names(map.query_layers().of_type::<TileLayer>()), ["tile-1"]);
+    assert!(map
+        .query_layers()
+        .flattened()
+        .of_type::<tiled::ImageLayer>()
+        .first()
+        .is_none());
+
+    // Patterns have to match the whole name.
+    assert_eq!(
+        names(map.query_layers().flattened().name_matches("*-3")),
+        ["group-3", "tile-3"]
+    );
+    assert_eq!(
+        names(map.query_layers().flattened().name_matches("tile-?")),
+        ["tile-1", "tile-2", "tile-3"]
+    );
+    assert_eq!(
+        names(map.query_layers().flattened().name_matches("t*e*2")),
+        ["tile-2"]
+    );
+    assert!(names(map.query_layers().flattened().name_matches("tile")).is_empty());
+
+    map.get_layer_mut(0).unwrap().user_type = Some("triggers".to_owned());
+    assert_eq!(
+        names(
+            map.query_layers()
+                .flattened()
+                .of_type::<TileLayer>()
+                .with_class("triggers")
+        ),
+        ["tile-1"]
+    );
+    assert_eq!(
+        map.query_layers()
+            .with_class("walls")
+            .first()
+            .map(|l| l.id()),
+        None
+    );
+}
+
+#[test]
+fn test_object_draw_order() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_object_draw_order.tmx")
+        .unwrap();
+    let ids = |layer: tiled::ObjectLayer| -> Vec<u32> {
+        layer.objects_in_draw_order().map(|o| o.id().0).collect()
+    };
+
+    let top_down = map.get_layer(0).unwrap().as_object_layer().unwrap();
+    assert_eq!(top_down.draw_order, tiled::DrawOrder::TopDown);
+    // Objects at the same height keep the order they are stored in.
+    assert_eq!(ids(top_down), [2, 1, 3]);
+
+    let index = map.get_layer(1).unwrap().as_object_layer().unwrap();
+    assert_eq!(index.draw_order, tiled::DrawOrder::Index);
+    assert_eq!(ids(index), [4, 5, 6]);
+
+    // Tile collision groups are read with the order written in the tileset.
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_tile_collision.tmx")
+        .unwrap();
+    let tile = map.tilesets()[0].get_tile(0).unwrap();
+    assert_eq!(
+        tile.collision.as_ref().unwrap().draw_order,
+        tiled::DrawOrder::Index
+    );
+}
+
+#[test]
+fn test_legacy_formats() {
+    // Saved by Tiled 0.9, without IDs nor tile counts and with image layers positioned by `x`/`y`.
+    let options = LoaderOptionsBuilder::new()
+        .preserve_unknown_xml(true)
+        .build();
+    let old = Loader::new()
+        .with_options(options)
+        .load_tmx_map("assets/tiled_legacy_0_9.tmx")
+        .unwrap();
+    // Saved by Tiled 1.0, which still lacks layer IDs.
+    let newer = Loader::new()
+        .load_tmx_map("assets/tiled_legacy_1_0.tmx")
+        .unwrap();
+
+    for map in [&old, &newer] {
+        assert_eq!(map.tilesets()[0].tilecount, 84);
+        assert_eq!(map.tilesets()[0].columns, 14);
+        let tiles = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+        assert_eq!(tiles.get_tile(1, 1).unwrap().id(), 15);
+        let objects = map.get_layer(1).unwrap().as_object_layer().unwrap();
+        assert_eq!(objects.objects().len(), 2);
+        assert_eq!(objects.get_object(0).unwrap().name, "spawn");
+        let image = map.get_layer(2).unwrap();
+        assert_eq!((image.offset_x, image.offset_y), (16.0, 8.0));
+    }
+    // The legacy attributes are normalized rather than kept as unknown ones.
+    for layer in old.layers() {
+        assert!(layer.extra.attributes.is_empty(), "{:?}", layer.name);
+    }
+    let image = old.get_layer(2).unwrap().as_image_layer().unwrap();
+    assert_eq!(image.image.as_ref().unwrap().width, 0);
+
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet_legacy.tsx")
+        .unwrap();
+    assert_eq!(tileset.tilecount, 84);
+    assert_eq!(tileset.tiles().len(), 84);
+
+    // Newer files still need the attributes older ones could leave out.
+    let map = "<map version=\"1.10\" orientation=\"orthogonal\" width=\"1\" height=\"1\" \
+               tilewidth=\"32\" tileheight=\"32\"><tileset firstgid=\"1\" name=\"t\" \
+               tilewidth=\"32\" tileheight=\"32\"><image source=\"tilesheet.png\" width=\"448\" \
+               height=\"192\"/></tileset></map>";
+    // The tileset is taken for an external one as it lacks the tile count of embedded ones.
+    let err = Loader::new()
+        .load_tmx_map_from(map.as_bytes(), "assets/newer.tmx")
+        .unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        tiled::Error::MissingAttribute { attribute, .. } if attribute == "source"
+    ));
+}
+
+#[test]
+fn test_tiles_sorted() {
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet_image_collection.tsx")
+        .unwrap();
+    let ids: Vec<u32> = tileset.tiles_sorted().map(|(id, _)| id).collect();
+    let mut expected: Vec<u32> = tileset.tiles().map(|(id, _)| id).collect();
+    expected.sort_unstable();
+    assert_eq!(ids, expected);
+    assert_eq!(tileset.tiles_sorted().len(), tileset.tiles().len());
+
+    let tileset = Loader::new()
+        .load_tsx_tileset("assets/tilesheet.tsx")
+        .unwrap();
+    let ids = |tiles: &mut dyn Iterator<Item = (u32, tiled::Tile)>| -> Vec<u32> {
+        tiles.map(|(id, _)| id).collect()
+    };
+    assert_eq!(ids(&mut tileset.tiles_in_range(3..=5)), [3, 4, 5]);
+    assert_eq!(ids(&mut tileset.tiles_in_range(80..)), [80, 81, 82, 83]);
+    assert_eq!(ids(&mut tileset.tiles_in_range(..2)), [0, 1]);
+    assert_eq!(tileset.tiles_in_range(5..5).len(), 0);
+    #[allow(clippy::reversed_empty_ranges)]
+    let reversed = tileset.tiles_in_range(6..3).len();
+    assert_eq!(reversed, 0);
+    assert_eq!(
+        tileset.tiles_in_range(10..14).next().unwrap().1.tileset(),
+        &tileset
+    );
+
+    // Image collections can have scattered IDs.
+    let map = "<map version=\"1.10\" orientation=\"orthogonal\" width=\"1\" height=\"1\" \
+               tilewidth=\"32\" tileheight=\"32\"><tileset firstgid=\"1\" name=\"t\" \
+               tilewidth=\"32\" tileheight=\"32\" tilecount=\"3\" columns=\"0\">\
+               <tile id=\"40\"><image source=\"a.png\" width=\"32\" height=\"32\"/></tile>\
+               <tile id=\"7\"><image source=\"b.png\" width=\"32\" height=\"32\"/></tile>\
+               <tile id=\"1000\"><image source=\"c.png\" width=\"32\" height=\"32\"/></tile>\
+               </tileset></map>";
+    let map = Loader::new()
+        .load_tmx_map_from(map.as_bytes(), "assets/scattered.tmx")
+        .unwrap();
+    let tileset = &map.tilesets()[0];
+    assert_eq!(ids(&mut tileset.tiles_sorted()), [7, 40, 1000]);
+    assert_eq!(ids(&mut tileset.tiles_in_range(8..=1000)), [40, 1000]);
+    assert_eq!(tileset.tiles_in_range(..=40).len(), 2);
+    assert!(tileset.get_tile(40).is_some());
+    assert!(tileset.get_tile(41).is_none());
+}
+
+#[test]
+fn test_map_diff() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_base64.tmx")
+        .unwrap();
+    assert_eq!(map.diff(&map), []);
+
+    let mut changed = map.clone();
+    changed.properties.insert(
+        "new".to_owned(),
+        PropertyValue::StringValue("value".to_owned()),
+    );
+    {
+        let mut layer = changed.get_layer_mut(0).unwrap();
+        layer.offset_x = 4.0;
+        let mut tiles = layer.as_finite_tile_layer_mut().unwrap();
+        tiles.set_tile(0, 0, None).unwrap();
+    }
+    {
+        let layer = changed.get_layer_mut(1).unwrap();
+        let mut objects = layer.as_object_layer_mut().unwrap();
+        objects.get_object_mut(ObjectId(3)).unwrap().x = 40.0;
+        objects.remove_object(ObjectId(2));
+    }
+
+    let differences: Vec<String> = map.diff(&changed).iter().map(ToString::to_string).collect();
+    assert_eq!(
+        differences,
+        [
+            "properties[\"new\"]: only in the second map (StringValue(\"value\"))",
+            "layers[0].offset_x: 0.0 != 4.0",
+            "layers[0].tiles[(0, 0)]: only in the first map (tile 34 of tileset 0)",
+            "layers[1].objects[id=2]: only in the first map (object at (329, 217))",
+            "layers[1].objects[id=3].x: 314.0 != 40.0",
+        ]
+    );
+    let difference = &map.diff(&changed)[1];
+    assert_eq!(difference.path, "layers[0].offset_x");
+    assert_eq!(difference.left.as_deref(), Some("0.0"));
+
+    // Layers of different kinds are reported as such rather than field by field.
+    let other = Loader::new()
+        .load_tmx_map("assets/tiled_image_layers.tmx")
+        .unwrap();
+    let difference = map
+        .diff(&other)
+        .into_iter()
+        .find(|difference| difference.path.ends_with("layer_type"))
+        .unwrap();
+    assert_eq!(
+        difference.to_string(),
+        "layers[0].layer_type: tile layer != image layer"
+    );
+}
+
+#[test]
+fn test_map_dump_tree() {
+    let map = Loader::new()
+        .load_tmx_map("assets/tiled_group_layers.tmx")
+        .unwrap();
+    let tileset_line = format!(
+        "    [0] \"tilesheet\" (first gid 1): 84 tiles of 32x32, image {}, file {}",
+        Path::new("assets").join("tilesheet.png").display(),
+        Path::new("assets").join("tilesheet.tsx").display()
+    );
+    let expected = [
+        "map 8x8 orthogonal, tiles 32x32 (version 1.5)",
+        "  tilesets:",
+        &tileset_line,
+        "      properties:",
+        "        tileset property = \"tsp\"",
+        "  layers:",
+        "    [0] tile layer \"tile-1\" (id 1): 8x8, 9 tiles",
+        "      properties:",
+        "        key = \"value1\"",
+        "    [1] group layer \"group-1\" (id 3)",
+        "      properties:",
+        "        key = #12345678",
+        "      [0] tile layer \"tile-2\" (id 5): 8x8, 9 tiles",
+        "        properties:",
+        "          key = \"value2\"",
+        "    [2] group layer \"group-2\" (id 6)",
+        "      properties:",
+        "        key = \"value5\"",
+        "      [0] group layer \"group-3\" (id 8)",
+        "        properties:",
+        "          key = \"value6\"",
+        "        [0] tile layer \"tile-3\" (id 9): 8x8, 9 tiles",
+        "          properties:",
+        "            key = \"value3\"",
+    ];
+    assert_eq!(map.dump_tree().lines().collect::<Vec<_>>(), expected);
+}
+
+#[test]
+fn test_custom_decompressor() {
+    use std::{
+        io::Read,
+        sync::atomic::{AtomicUsize, Ordering},
+    };
+    use tiled::Decompressor;
+
+    /// Stores the data as it is, counting how many times it was used.
+    struct Identity(Arc<AtomicUsize>);
+
+    impl Decompressor for Identity {
+        fn decompress<'data>(
+            &self,
+            data: Box<dyn Read + 'data>,
+        ) -> std::io::Result<Box<dyn Read + 'data>> {
+            self.0.fetch_add(1, Ordering::Relaxed);
+            Ok(data)
+        }
+    }
+
+    // The gids 1 to 4, as little-endian bytes encoded in base64.
+    let xml = r#"<map version="1.10" orientation="orthogonal" width="2" height="2"
+        tilewidth="32" tileheight="32">
+     <tileset firstgid="1" source="tilesheet.tsx"/>
+     <layer id="1" name="tiles" width="2" height="2">
+      <data encoding="base64" compression="identity">AQAAAAIAAAADAAAABAAAAA==</data>
+     </layer>
+    </map>"#;
+
+    let mut loader = Loader::new();
+    let err = loader
+        .load_tmx_map_from(xml.as_bytes(), "assets/identity.tmx")
+        .unwrap_err();
+    assert!(matches!(
+        err.inner(),
+        Error::UnsupportedCompression { compression, feature: None } if compression == "identity"
+    ));
+
+    let uses = Arc::new(AtomicUsize::new(0));
+    loader.set_decompressor("identity", Identity(uses.clone()));
+    assert!(loader.options().decompressor("identity").is_some());
+    let map = loader
+        .load_tmx_map_from(xml.as_bytes(), "assets/identity.tmx")
+        .unwrap();
+    let layer = map.get_layer(0).unwrap().as_tile_layer().unwrap();
+    assert_eq!(layer.get_tile(0, 0).unwrap().id(), 0);
+    assert_eq!(layer.get_tile(1, 1).unwrap().id(), 3);
+    assert_eq!(uses.load(Ordering::Relaxed), 1);
+
+    // Decompressors set on the loader replace the built-in ones, so the still compressed data is
+    // read as gids here, which don't add up to the size of the layer.
+    loader.set_decompressor("zlib", Identity(uses.clone()));
+    let err = loader
+        .load_tmx_map("assets/tiled_base64_zlib.tmx")
+        .unwrap_err();
+    assert!(matches!(err.inner(), Error::InvalidTileFound));
+    assert_eq!(uses.load(Ordering::Relaxed), 2);
+}
+
+#[cfg(feature = "render-helpers")]
+#[test]
+fn test_draw_commands() {
+    let load = |attributes: &str| {
+        let xml = format!(
+            r#"<map version="1.10" {} width="2" height="2" tilewidth="32" tileheight="16">
+             <tileset firstgid="1" name="tiles" tilewidth="32" tileheight="32" tilecount="84"
+                 columns="14">
+              <image source="tilesheet.png" width="448" height="192"/>
+             </tileset>
+             <layer id="1" name="tiles" width="2" height="2" offsetx="5">
+              <data encoding="csv">1,2,3,2147483652</data>
+             </layer>
+            </map>"#,
+            attributes
+        );
+        Loader::new()
+            .load_tmx_map_from(xml.as_bytes(), "assets/render.tmx")
+            .unwrap()
+    };
+    let positions = |map: &Map| {
+        map.draw_commands()
+            .iter()
+            .map(|command| command.position)
+            .collect::<Vec<_>>()
+    };
+
+    let map = load(r#"orientation="orthogonal""#);
+    assert_eq!(positions(&map), [(0, 0), (1, 0), (0, 1), (1, 1)]);
+    let commands = map.draw_commands();
+    assert_eq!(commands[1].id, 1);
+    assert_eq!(
+        commands[1].source,
+        ImageRect {
+            x: 32,
+            y: 0,
+            width: 32,
+            height: 32
+        }
+    );
+    // Tiles taller than their cell stick out of it at the top.
+    assert_eq!(
+        commands[1].destination,
+        Rect {
+            x: 37.0,
+            y: -16.0,
+            width: 32.0,
+            height: 32.0
+        }
+    );
+    assert!(commands[3].flip_h && !commands[2].flip_h);
+    assert_eq!(commands[3].opacity, 1.0);
+
+    let map = load(r#"orientation="orthogonal" renderorder="left-up""#);
+    assert_eq!(positions(&map), [(1, 1), (0, 1), (1, 0), (0, 0)]);
+    let map = load(r#"orientation="isometric""#);
+    assert_eq!(positions(&map), [(0, 0), (0, 1), (1, 0), (1, 1)]);
+    let map = load(r#"orientation="staggered" staggeraxis="x" staggerindex="even""#);
+    assert_eq!(positions(&map), [(1, 0), (0, 0), (1, 1), (0, 1)]);
+    let map = load(r#"orientation="hexagonal" hexsidelength="8" staggeraxis="y""#);
+    assert_eq!(positions(&map), [(0, 0), (1, 0), (0, 1), (1, 1)]);
 }
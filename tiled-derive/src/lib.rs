@@ -0,0 +1,141 @@
+//! Derive macros for the [tiled](https://docs.rs/tiled) crate.
+//!
+//! Use these through the `derive` feature of `tiled` rather than depending on this crate directly;
+//! See `tiled::FromProperties` for documentation.
+
+use proc_macro::TokenStream;
+use proc_macro2::Span;
+use quote::quote;
+use syn::{
+    parse_macro_input, Data, DeriveInput, Error, Fields, GenericArgument, GenericParam, Lifetime,
+    LifetimeParam, LitStr, PathArguments, Type,
+};
+
+/// Implements `tiled::FromProperties` for a struct with named fields.
+///
+/// Each field is read from the property of the same name. Supported field attributes:
+/// - `#[tiled(rename = "name")]` reads the field from the property with the given name instead.
+/// - `#[tiled(default)]` uses the [`Default`] value of the field when the property is missing.
+///
+/// Fields of type [`Option`] are [`None`] when their property is missing.
+#[proc_macro_derive(FromProperties, attributes(tiled))]
+pub fn derive_from_properties(input: TokenStream) -> TokenStream {
+    let input = parse_macro_input!(input as DeriveInput);
+    expand(input)
+        .unwrap_or_else(Error::into_compile_error)
+        .into()
+}
+
+struct FieldOptions {
+    name: Option<LitStr>,
+    default: bool,
+}
+
+fn expand(input: DeriveInput) -> syn::Result<proc_macro2::TokenStream> {
+    let fields = match &input.data {
+        Data::Struct(data) => match &data.fields {
+            Fields::Named(fields) => fields.named.iter().collect(),
+            Fields::Unit => Vec::new(),
+            Fields::Unnamed(_) => return Err(Error::new_spanned(
+                &input.ident,
+                "FromProperties can't be derived for tuple structs, as their fields have no names",
+            )),
+        },
+        _ => {
+            return Err(Error::new_spanned(
+                &input.ident,
+                "FromProperties can only be derived for structs",
+            ))
+        }
+    };
+
+    let mut initializers = Vec::new();
+    for field in fields {
+        let ident = field.ident.as_ref().unwrap();
+        let options = field_options(field)?;
+        let name = options
+            .name
+            .unwrap_or_else(|| LitStr::new(&ident.to_string(), ident.span()));
+        let value = if is_option(&field.ty) {
+            quote!(::tiled::__private::optional_property(properties, #name)?)
+        } else if options.default {
+            quote!(::tiled::__private::optional_property(properties, #name)?.unwrap_or_default())
+        } else {
+            quote!(::tiled::__private::required_property(properties, #name)?)
+        };
+        initializers.push(quote!(#ident: #value));
+    }
+
+    // Borrowed fields such as `&str` live as long as the properties they're read from, so the
+    // struct's own lifetime is used for them if it has one.
+    let mut generics = input.generics.clone();
+    let lifetime = match input.generics.lifetimes().next() {
+        Some(param) => param.lifetime.clone(),
+        None => {
+            let lifetime = Lifetime::new("'__tiled", Span::call_site());
+            generics.params.insert(
+                0,
+                GenericParam::Lifetime(LifetimeParam::new(lifetime.clone())),
+            );
+            lifetime
+        }
+    };
+    let (impl_generics, _, _) = generics.split_for_impl();
+    let (_, ty_generics, where_clause) = input.generics.split_for_impl();
+    let ident = &input.ident;
+
+    Ok(quote! {
+        impl #impl_generics ::tiled::FromProperties<#lifetime> for #ident #ty_generics #where_clause {
+            fn from_properties(
+                properties: &#lifetime ::tiled::Properties,
+            ) -> ::tiled::Result<Self> {
+                ::core::result::Result::Ok(Self {
+                    #(#initializers,)*
+                })
+            }
+        }
+    })
+}
+
+fn field_options(field: &syn::Field) -> syn::Result<FieldOptions> {
+    let mut options = FieldOptions {
+        name: None,
+        default: false,
+    };
+    for attr in field
+        .attrs
+        .iter()
+        .filter(|attr| attr.path().is_ident("tiled"))
+    {
+        attr.parse_nested_meta(|meta| {
+            if meta.path.is_ident("rename") {
+                options.name = Some(meta.value()?.parse()?);
+                Ok(())
+            } else if meta.path.is_ident("default") {
+                options.default = true;
+                Ok(())
+            } else {
+                Err(meta.error("unknown tiled attribute; expected `rename` or `default`"))
+            }
+        })?;
+    }
+    Ok(options)
+}
+
+/// Whether the type is spelled as an [`Option`], which is all a derive macro can tell.
+fn is_option(ty: &Type) -> bool {
+    let path = match ty {
+        Type::Path(ty) if ty.qself.is_none() => &ty.path,
+        _ => return false,
+    };
+    let segment = match path.segments.last() {
+        Some(segment) => segment,
+        None => return false,
+    };
+    segment.ident == "Option"
+        && matches!(
+            &segment.arguments,
+            PathArguments::AngleBracketed(args)
+                if args.args.len() == 1 && matches!(args.args[0], GenericArgument::Type(_))
+        )
+}